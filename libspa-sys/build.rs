@@ -27,6 +27,15 @@ fn main() {
         .allowlist_type("spa_.*")
         .allowlist_var("SPA_.*")
         .prepend_enum_name(false)
+        // Most SPA enums are ids carried as `uint32_t` in pods and structs, such as the types,
+        // params, object keys or data types, so they stay plain constants. `spa_direction` is
+        // used as a type by the C API, make it a newtype which, unlike a Rust enum, accepts
+        // values added by a newer SPA.
+        .newtype_enum("spa_direction")
+        .derive_copy(true)
+        .derive_debug(true)
+        // Implement `Debug` manually for the types that can't derive it, such as those with unions.
+        .impl_debug(true)
         .derive_eq(true)
         // Create callable wrapper functions around SPAs `static inline` functions so they
         // can be called via FFI
@@ -48,8 +57,11 @@ fn main() {
     bindings
         .write_to_file(out_path.join("bindings.rs"))
         .expect("Couldn't write bindings!");
-    const FILES: &[&str] = &["src/type-info.c"];
-    let cc_files = &[PathBuf::from(FILES[0]), out_path.join("static_fns.c")];
+    const FILES: &[&str] = &["src/type-info.c", "src/log.c"];
+    let cc_files = FILES
+        .iter()
+        .map(PathBuf::from)
+        .chain([out_path.join("static_fns.c")]);
 
     for file in FILES {
         println!("cargo:rerun-if-changed={file}");
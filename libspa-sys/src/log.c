@@ -0,0 +1,76 @@
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdio.h>
+
+#include <spa/support/log.h>
+
+// A `spa_log` whose messages are formatted here and passed to the callbacks of a sink,
+// as Rust can neither define the C-variadic methods of `struct spa_log_methods` nor use their
+// `va_list`. The sink is the `data` of the interface of the log.
+struct libspa_rs_log_sink {
+	bool (*enabled)(void *data, enum spa_log_level level, const char *topic);
+	void (*message)(void *data, enum spa_log_level level, const char *topic,
+			const char *file, int line, const char *func, const char *message);
+	void *data;
+};
+
+// Longer messages are truncated, the message is formatted on the stack so that
+// logging from realtime threads does not allocate.
+#define LIBSPA_RS_LOG_MESSAGE_SIZE 1024
+
+static void sink_log_message(struct libspa_rs_log_sink *sink, enum spa_log_level level,
+		const char *topic, const char *file, int line, const char *func,
+		const char *fmt, va_list args)
+{
+	char message[LIBSPA_RS_LOG_MESSAGE_SIZE];
+
+	if (!sink->enabled(sink->data, level, topic))
+		return;
+
+	vsnprintf(message, sizeof(message), fmt, args);
+	sink->message(sink->data, level, topic, file, line, func, message);
+}
+
+static void sink_logv(void *object, enum spa_log_level level, const char *file, int line,
+		const char *func, const char *fmt, va_list args)
+{
+	sink_log_message(object, level, NULL, file, line, func, fmt, args);
+}
+
+static void sink_log(void *object, enum spa_log_level level, const char *file, int line,
+		const char *func, const char *fmt, ...)
+{
+	va_list args;
+
+	va_start(args, fmt);
+	sink_log_message(object, level, NULL, file, line, func, fmt, args);
+	va_end(args);
+}
+
+#if SPA_VERSION_LOG_METHODS >= 1
+static void sink_logtv(void *object, enum spa_log_level level, const struct spa_log_topic *topic,
+		const char *file, int line, const char *func, const char *fmt, va_list args)
+{
+	sink_log_message(object, level, topic ? topic->topic : NULL, file, line, func, fmt, args);
+}
+
+static void sink_logt(void *object, enum spa_log_level level, const struct spa_log_topic *topic,
+		const char *file, int line, const char *func, const char *fmt, ...)
+{
+	va_list args;
+
+	va_start(args, fmt);
+	sink_log_message(object, level, topic ? topic->topic : NULL, file, line, func, fmt, args);
+	va_end(args);
+}
+#endif
+
+const struct spa_log_methods libspa_rs_log_sink_methods = {
+	.version = SPA_VERSION_LOG_METHODS,
+	.log = sink_log,
+	.logv = sink_logv,
+#if SPA_VERSION_LOG_METHODS >= 1
+	.logt = sink_logt,
+	.logtv = sink_logtv,
+#endif
+};
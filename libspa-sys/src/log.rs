@@ -0,0 +1,36 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::os::raw::{c_char, c_int, c_void};
+
+use super::*;
+
+/// The callbacks of a `spa_log` using [`libspa_rs_log_sink_methods`], defined in `log.c`.
+///
+/// The message is formatted before calling `message`, and truncated to 1023 bytes.
+/// `topic` is null for messages without a topic.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct libspa_rs_log_sink {
+    pub enabled: Option<
+        unsafe extern "C" fn(data: *mut c_void, level: spa_log_level, topic: *const c_char) -> bool,
+    >,
+    pub message: Option<
+        unsafe extern "C" fn(
+            data: *mut c_void,
+            level: spa_log_level,
+            topic: *const c_char,
+            file: *const c_char,
+            line: c_int,
+            func: *const c_char,
+            message: *const c_char,
+        ),
+    >,
+    pub data: *mut c_void,
+}
+
+extern "C" {
+    /// The methods of a `spa_log` whose `data` is a [`libspa_rs_log_sink`].
+    pub static libspa_rs_log_sink_methods: spa_log_methods;
+}
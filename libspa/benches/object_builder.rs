@@ -0,0 +1,71 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Compare the ways of building an audio format pod with [`ObjectBuilder`].
+//!
+//! Run with `cargo bench -p libspa`.
+
+use std::io::Cursor;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use libspa::{
+    param::{
+        audio::AudioFormat,
+        format::{FormatProperties, MediaSubtype, MediaType},
+        ParamType,
+    },
+    pod::ObjectBuilder,
+    utils::SpaTypes,
+};
+
+fn format(builder: ObjectBuilder, rate: i32) -> ObjectBuilder {
+    builder
+        .prop(FormatProperties::MediaType, MediaType::Audio)
+        .prop(FormatProperties::MediaSubtype, MediaSubtype::Raw)
+        .prop(FormatProperties::AudioFormat, AudioFormat::F32LE)
+        .prop(FormatProperties::AudioRate, rate)
+        .prop(FormatProperties::AudioChannels, 2)
+}
+
+fn new_builder() -> ObjectBuilder {
+    ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::Format)
+}
+
+fn build_format_pod(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build_format_pod");
+
+    // a new builder and a new pod for each build
+    group.bench_function("build", |b| {
+        b.iter(|| format(new_builder(), black_box(48000)).build())
+    });
+
+    // one builder reset between the builds, reusing its memory
+    group.bench_function("build_ref", |b| {
+        let mut builder = new_builder();
+        b.iter(|| {
+            builder.reset();
+            builder = format(
+                std::mem::replace(&mut builder, new_builder()),
+                black_box(48000),
+            );
+            builder.build_ref().size()
+        })
+    });
+
+    // a new builder for each build, serialized into a reused buffer
+    group.bench_function("build_into", |b| {
+        let mut buffer = Vec::new();
+        b.iter(|| {
+            buffer.clear();
+            format(new_builder(), black_box(48000))
+                .build_into(Cursor::new(&mut buffer))
+                .unwrap()
+                .1
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, build_format_pod);
+criterion_main!(benches);
@@ -0,0 +1,775 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Buffer metadata.
+
+use std::{
+    fmt::Debug,
+    mem::{align_of, size_of},
+};
+
+use crate::{
+    param::video::VideoFormat,
+    utils::{Point, Rectangle},
+};
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct MetaType(spa_sys::spa_meta_type);
+
+#[allow(non_upper_case_globals)]
+impl MetaType {
+    pub const Invalid: Self = Self(spa_sys::SPA_META_Invalid);
+    /// A [`MetaHeader`].
+    pub const Header: Self = Self(spa_sys::SPA_META_Header);
+    /// The cropping of a video frame, as a [`MetaRegion`].
+    pub const VideoCrop: Self = Self(spa_sys::SPA_META_VideoCrop);
+    /// The damaged regions of a video frame, as an array of [`MetaRegion`].
+    pub const VideoDamage: Self = Self(spa_sys::SPA_META_VideoDamage);
+    /// A [`MetaBitmap`].
+    pub const Bitmap: Self = Self(spa_sys::SPA_META_Bitmap);
+    /// A [`MetaCursor`], optionally followed by a [`MetaBitmap`].
+    pub const Cursor: Self = Self(spa_sys::SPA_META_Cursor);
+    /// A sequence of timed control pods.
+    pub const Control: Self = Self(spa_sys::SPA_META_Control);
+    /// A [`MetaBusy`].
+    pub const Busy: Self = Self(spa_sys::SPA_META_Busy);
+    /// A [`MetaSyncTimeline`], for buffers with [`DataType::SyncObj`](super::DataType::SyncObj) datas.
+    ///
+    /// Added in PipeWire 1.2, its value is hardcoded so that it is available with older headers.
+    pub const SyncTimeline: Self = Self(9);
+
+    pub fn from_raw(raw: spa_sys::spa_meta_type) -> Self {
+        Self(raw)
+    }
+
+    pub fn as_raw(&self) -> spa_sys::spa_meta_type {
+        self.0
+    }
+}
+
+impl Debug for MetaType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = format!(
+            "MetaType::{}",
+            match *self {
+                Self::Invalid => "Invalid",
+                Self::Header => "Header",
+                Self::VideoCrop => "VideoCrop",
+                Self::VideoDamage => "VideoDamage",
+                Self::Bitmap => "Bitmap",
+                Self::Cursor => "Cursor",
+                Self::Control => "Control",
+                Self::Busy => "Busy",
+                Self::SyncTimeline => "SyncTimeline",
+                _ => "Unknown",
+            }
+        );
+        f.write_str(&name)
+    }
+}
+
+mod private {
+    pub trait MetaDataSeal {}
+
+    impl MetaDataSeal for super::MetaHeader {}
+    impl MetaDataSeal for super::MetaRegion {}
+    impl MetaDataSeal for super::MetaBitmap {}
+    impl MetaDataSeal for super::MetaCursor {}
+    impl MetaDataSeal for super::MetaBusy {}
+    impl MetaDataSeal for super::MetaSyncTimeline {}
+}
+
+/// A type stored in the data of a [`Meta`], see [`Meta::get`].
+///
+/// This trait is sealed and cannot be implemented outside of this crate.
+pub trait MetaData: private::MetaDataSeal {
+    /// Whether the data of metadata of type `type_` starts with a `Self`.
+    fn has_type(type_: MetaType) -> bool;
+}
+
+/// Metadata attached to a buffer.
+///
+/// The typed content of the metadata is accessed with [`get`](Self::get) and [`get_mut`](Self::get_mut),
+/// which check the type and size of the metadata.
+#[repr(transparent)]
+pub struct Meta(spa_sys::spa_meta);
+
+impl Meta {
+    pub fn as_raw(&self) -> &spa_sys::spa_meta {
+        &self.0
+    }
+
+    pub fn type_(&self) -> MetaType {
+        MetaType::from_raw(self.0.type_)
+    }
+
+    /// Size of the data of the metadata, in bytes.
+    pub fn size(&self) -> u32 {
+        self.0.size
+    }
+
+    pub fn data(&self) -> Option<&[u8]> {
+        if self.0.data.is_null() {
+            None
+        } else {
+            unsafe {
+                Some(std::slice::from_raw_parts(
+                    self.0.data as *const u8,
+                    usize::try_from(self.0.size).unwrap(),
+                ))
+            }
+        }
+    }
+
+    pub fn data_mut(&mut self) -> Option<&mut [u8]> {
+        if self.0.data.is_null() {
+            None
+        } else {
+            unsafe {
+                Some(std::slice::from_raw_parts_mut(
+                    self.0.data as *mut u8,
+                    usize::try_from(self.0.size).unwrap(),
+                ))
+            }
+        }
+    }
+
+    /// Pointer to a `T` at `offset` in the data, if it is within the size of the metadata and aligned.
+    fn ptr_at<T>(&self, offset: usize) -> Option<*mut T> {
+        let end = offset.checked_add(size_of::<T>())?;
+        if self.0.data.is_null() || end > usize::try_from(self.0.size).ok()? {
+            return None;
+        }
+
+        let ptr = unsafe { self.0.data.cast::<u8>().add(offset) };
+        if ptr as usize % align_of::<T>() != 0 {
+            return None;
+        }
+        Some(ptr.cast())
+    }
+
+    fn typed_ptr<M: MetaData>(&self) -> Option<*mut M> {
+        if !M::has_type(self.type_()) {
+            return None;
+        }
+        self.ptr_at(0)
+    }
+
+    /// Get the data as a `M`.
+    ///
+    /// Returns `None` if the metadata is of a type not containing a `M`, or is too small.
+    pub fn get<M: MetaData>(&self) -> Option<&M> {
+        self.typed_ptr().map(|ptr| unsafe { &*ptr })
+    }
+
+    /// Get the data as a mutable `M`, to be filled by producers.
+    ///
+    /// Returns `None` if the metadata is of a type not containing a `M`, or is too small.
+    pub fn get_mut<M: MetaData>(&mut self) -> Option<&mut M> {
+        self.typed_ptr().map(|ptr| unsafe { &mut *ptr })
+    }
+
+    /// Get all the regions of [`MetaType::VideoCrop`] or [`MetaType::VideoDamage`] metadata.
+    ///
+    /// Damaged regions end at the first region with an invalid size, see [`MetaRegion::is_valid`].
+    pub fn regions(&self) -> &[MetaRegion] {
+        match self.typed_ptr::<MetaRegion>() {
+            Some(ptr) => unsafe {
+                std::slice::from_raw_parts(ptr, self.0.size as usize / size_of::<MetaRegion>())
+            },
+            None => &[],
+        }
+    }
+
+    /// Offset of the bitmap of cursor metadata, if it has one.
+    fn cursor_bitmap_offset(&self) -> Option<usize> {
+        let offset = usize::try_from(self.get::<MetaCursor>()?.bitmap_offset()).ok()?;
+        // 0 means there is no bitmap, and the bitmap cannot overlap the cursor
+        if offset < size_of::<MetaCursor>() {
+            return None;
+        }
+        Some(offset)
+    }
+
+    /// Get the bitmap following a [`MetaCursor`], if [`MetaCursor::bitmap_offset`] is set.
+    pub fn cursor_bitmap(&self) -> Option<&MetaBitmap> {
+        let ptr = self.ptr_at(self.cursor_bitmap_offset()?)?;
+        Some(unsafe { &*ptr })
+    }
+
+    /// Get the bitmap following a [`MetaCursor`] to be filled by producers,
+    /// once [`MetaCursor::set_bitmap_offset`] was called.
+    pub fn cursor_bitmap_mut(&mut self) -> Option<&mut MetaBitmap> {
+        let ptr = self.ptr_at(self.cursor_bitmap_offset()?)?;
+        Some(unsafe { &mut *ptr })
+    }
+
+    /// Location of the pixels of the cursor bitmap, within the size of the metadata.
+    fn cursor_pixels(&self) -> Option<(*mut u8, usize)> {
+        let bitmap_offset = self.cursor_bitmap_offset()?;
+        let bitmap = self.cursor_bitmap()?;
+
+        // the pixels cannot overlap the bitmap
+        let offset = usize::try_from(bitmap.offset()).ok()?;
+        if offset < size_of::<MetaBitmap>() {
+            return None;
+        }
+        let stride = usize::try_from(bitmap.stride()).ok()?;
+        let len = stride.checked_mul(usize::try_from(bitmap.size().height).ok()?)?;
+
+        let start = bitmap_offset.checked_add(offset)?;
+        if start.checked_add(len)? > usize::try_from(self.0.size).ok()? {
+            return None;
+        }
+        Some((unsafe { self.0.data.cast::<u8>().add(start) }, len))
+    }
+
+    /// Get the pixels of the cursor bitmap, `stride * height` bytes at [`MetaBitmap::offset`].
+    ///
+    /// Returns `None` if there is no bitmap or if its pixels do not fit in the metadata.
+    pub fn cursor_bitmap_pixels(&self) -> Option<&[u8]> {
+        let (ptr, len) = self.cursor_pixels()?;
+        Some(unsafe { std::slice::from_raw_parts(ptr, len) })
+    }
+
+    /// Get the pixels of the cursor bitmap to be filled by producers, once the bitmap is set.
+    pub fn cursor_bitmap_pixels_mut(&mut self) -> Option<&mut [u8]> {
+        let (ptr, len) = self.cursor_pixels()?;
+        Some(unsafe { std::slice::from_raw_parts_mut(ptr, len) })
+    }
+}
+
+impl Debug for Meta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Meta")
+            .field("type", &self.type_())
+            .field("size", &self.size())
+            .finish()
+    }
+}
+
+bitflags::bitflags! {
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct MetaHeaderFlags: u32 {
+        /// Data is not continuous with previous buffer
+        const DISCONT = spa_sys::SPA_META_HEADER_FLAG_DISCONT;
+        /// Data might be corrupted
+        const CORRUPTED = spa_sys::SPA_META_HEADER_FLAG_CORRUPTED;
+        /// Media specific marker
+        const MARKER = spa_sys::SPA_META_HEADER_FLAG_MARKER;
+        /// Data contains a codec specific header
+        const HEADER = spa_sys::SPA_META_HEADER_FLAG_HEADER;
+        /// Data contains media neutral data
+        const GAP = spa_sys::SPA_META_HEADER_FLAG_GAP;
+        /// Cannot be decoded independently
+        const DELTA_UNIT = spa_sys::SPA_META_HEADER_FLAG_DELTA_UNIT;
+    }
+}
+
+/// Describes the essential buffer header metadata, such as flags and timestamps.
+#[repr(transparent)]
+pub struct MetaHeader(spa_sys::spa_meta_header);
+
+impl MetaHeader {
+    pub fn as_raw(&self) -> &spa_sys::spa_meta_header {
+        &self.0
+    }
+
+    pub fn flags(&self) -> MetaHeaderFlags {
+        MetaHeaderFlags::from_bits_retain(self.0.flags)
+    }
+
+    pub fn set_flags(&mut self, flags: MetaHeaderFlags) {
+        self.0.flags = flags.bits();
+    }
+
+    /// Offset in the current cycle.
+    pub fn offset(&self) -> u32 {
+        self.0.offset
+    }
+
+    pub fn set_offset(&mut self, offset: u32) {
+        self.0.offset = offset;
+    }
+
+    /// Presentation timestamp in nanoseconds.
+    pub fn pts(&self) -> i64 {
+        self.0.pts
+    }
+
+    pub fn set_pts(&mut self, pts: i64) {
+        self.0.pts = pts;
+    }
+
+    /// Decoding timestamp as a difference with the pts.
+    pub fn dts_offset(&self) -> i64 {
+        self.0.dts_offset
+    }
+
+    pub fn set_dts_offset(&mut self, dts_offset: i64) {
+        self.0.dts_offset = dts_offset;
+    }
+
+    /// Sequence number, increments with a media specific frequency.
+    pub fn seq(&self) -> u64 {
+        self.0.seq
+    }
+
+    pub fn set_seq(&mut self, seq: u64) {
+        self.0.seq = seq;
+    }
+}
+
+impl MetaData for MetaHeader {
+    fn has_type(type_: MetaType) -> bool {
+        type_ == MetaType::Header
+    }
+}
+
+impl Debug for MetaHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetaHeader")
+            .field("flags", &self.flags())
+            .field("offset", &self.offset())
+            .field("pts", &self.pts())
+            .field("dts_offset", &self.dts_offset())
+            .field("seq", &self.seq())
+            .finish()
+    }
+}
+
+/// A region of a video frame, of [`MetaType::VideoCrop`] or [`MetaType::VideoDamage`] metadata.
+#[repr(transparent)]
+pub struct MetaRegion(spa_sys::spa_meta_region);
+
+impl MetaRegion {
+    pub fn as_raw(&self) -> &spa_sys::spa_meta_region {
+        &self.0
+    }
+
+    pub fn position(&self) -> Point {
+        Point::from_raw(self.0.region.position)
+    }
+
+    pub fn set_position(&mut self, position: Point) {
+        self.0.region.position = position.as_raw();
+    }
+
+    pub fn size(&self) -> Rectangle {
+        Rectangle::from_raw(self.0.region.size)
+    }
+
+    pub fn set_size(&mut self, size: Rectangle) {
+        self.0.region.size = size.as_raw();
+    }
+
+    /// Whether the region has a non-empty size.
+    pub fn is_valid(&self) -> bool {
+        self.0.region.size.width != 0 && self.0.region.size.height != 0
+    }
+}
+
+impl MetaData for MetaRegion {
+    fn has_type(type_: MetaType) -> bool {
+        type_ == MetaType::VideoCrop || type_ == MetaType::VideoDamage
+    }
+}
+
+impl Debug for MetaRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetaRegion")
+            .field("position", &self.position())
+            .field("size", &self.size())
+            .finish()
+    }
+}
+
+/// A bitmap, such as the image of a cursor.
+///
+/// The pixels are stored at [`offset`](Self::offset) bytes from the start of the bitmap,
+/// use [`Meta::cursor_bitmap_pixels`] to access those of a cursor.
+#[repr(transparent)]
+pub struct MetaBitmap(spa_sys::spa_meta_bitmap);
+
+impl MetaBitmap {
+    pub fn as_raw(&self) -> &spa_sys::spa_meta_bitmap {
+        &self.0
+    }
+
+    pub fn format(&self) -> VideoFormat {
+        VideoFormat::from_raw(self.0.format)
+    }
+
+    pub fn set_format(&mut self, format: VideoFormat) {
+        self.0.format = format.as_raw();
+    }
+
+    pub fn size(&self) -> Rectangle {
+        Rectangle::from_raw(self.0.size)
+    }
+
+    pub fn set_size(&mut self, size: Rectangle) {
+        self.0.size = size.as_raw();
+    }
+
+    pub fn stride(&self) -> i32 {
+        self.0.stride
+    }
+
+    pub fn set_stride(&mut self, stride: i32) {
+        self.0.stride = stride;
+    }
+
+    /// Offset of the pixels from the start of the bitmap.
+    pub fn offset(&self) -> u32 {
+        self.0.offset
+    }
+
+    pub fn set_offset(&mut self, offset: u32) {
+        self.0.offset = offset;
+    }
+}
+
+impl MetaData for MetaBitmap {
+    fn has_type(type_: MetaType) -> bool {
+        type_ == MetaType::Bitmap
+    }
+}
+
+impl Debug for MetaBitmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetaBitmap")
+            .field("format", &self.format())
+            .field("size", &self.size())
+            .field("stride", &self.stride())
+            .field("offset", &self.offset())
+            .finish()
+    }
+}
+
+/// Cursor metadata, optionally followed by a [`MetaBitmap`] with its image,
+/// see [`Meta::cursor_bitmap`].
+#[repr(transparent)]
+pub struct MetaCursor(spa_sys::spa_meta_cursor);
+
+impl MetaCursor {
+    pub fn as_raw(&self) -> &spa_sys::spa_meta_cursor {
+        &self.0
+    }
+
+    /// Cursor id, 0 if the cursor is invalid.
+    pub fn id(&self) -> u32 {
+        self.0.id
+    }
+
+    pub fn set_id(&mut self, id: u32) {
+        self.0.id = id;
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.0.flags
+    }
+
+    pub fn set_flags(&mut self, flags: u32) {
+        self.0.flags = flags;
+    }
+
+    pub fn position(&self) -> Point {
+        Point::from_raw(self.0.position)
+    }
+
+    pub fn set_position(&mut self, position: Point) {
+        self.0.position = position.as_raw();
+    }
+
+    /// Position of the hotspot relative to the position of the cursor.
+    pub fn hotspot(&self) -> Point {
+        Point::from_raw(self.0.hotspot)
+    }
+
+    pub fn set_hotspot(&mut self, hotspot: Point) {
+        self.0.hotspot = hotspot.as_raw();
+    }
+
+    /// Offset of the bitmap from the start of the cursor, 0 if there is no bitmap.
+    pub fn bitmap_offset(&self) -> u32 {
+        self.0.bitmap_offset
+    }
+
+    pub fn set_bitmap_offset(&mut self, bitmap_offset: u32) {
+        self.0.bitmap_offset = bitmap_offset;
+    }
+}
+
+impl MetaData for MetaCursor {
+    fn has_type(type_: MetaType) -> bool {
+        type_ == MetaType::Cursor
+    }
+}
+
+impl Debug for MetaCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetaCursor")
+            .field("id", &self.id())
+            .field("flags", &self.flags())
+            .field("position", &self.position())
+            .field("hotspot", &self.hotspot())
+            .field("bitmap_offset", &self.bitmap_offset())
+            .finish()
+    }
+}
+
+/// Metadata telling whether a buffer is in use by the other side.
+#[repr(transparent)]
+pub struct MetaBusy(spa_sys::spa_meta_busy);
+
+impl MetaBusy {
+    pub fn as_raw(&self) -> &spa_sys::spa_meta_busy {
+        &self.0
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.0.flags
+    }
+
+    pub fn set_flags(&mut self, flags: u32) {
+        self.0.flags = flags;
+    }
+
+    /// Number of users busy with the buffer.
+    pub fn count(&self) -> u32 {
+        self.0.count
+    }
+
+    pub fn set_count(&mut self, count: u32) {
+        self.0.count = count;
+    }
+}
+
+impl MetaData for MetaBusy {
+    fn has_type(type_: MetaType) -> bool {
+        type_ == MetaType::Busy
+    }
+}
+
+impl Debug for MetaBusy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetaBusy")
+            .field("flags", &self.flags())
+            .field("count", &self.count())
+            .finish()
+    }
+}
+
+/// Metadata with the timeline points of the syncobjs of a buffer, for explicit sync.
+///
+/// The consumer of the buffer waits for the acquire point of the acquire syncobj to be signaled
+/// before reading the buffer, and signals the release point of the release syncobj once it is
+/// done with it. The syncobjs are the two [`DataType::SyncObj`](super::DataType::SyncObj) datas
+/// of the buffer.
+///
+/// This mirrors `struct spa_meta_sync_timeline` of PipeWire 1.2, so that it is available with older headers.
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetaSyncTimeline {
+    flags: u32,
+    padding: u32,
+    acquire_point: u64,
+    release_point: u64,
+}
+
+impl MetaSyncTimeline {
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    pub fn set_flags(&mut self, flags: u32) {
+        self.flags = flags;
+    }
+
+    /// The point of the acquire syncobj to wait for before reading the buffer.
+    pub fn acquire_point(&self) -> u64 {
+        self.acquire_point
+    }
+
+    pub fn set_acquire_point(&mut self, point: u64) {
+        self.acquire_point = point;
+    }
+
+    /// The point of the release syncobj to signal once the buffer is not used anymore.
+    pub fn release_point(&self) -> u64 {
+        self.release_point
+    }
+
+    pub fn set_release_point(&mut self, point: u64) {
+        self.release_point = point;
+    }
+}
+
+impl MetaData for MetaSyncTimeline {
+    fn has_type(type_: MetaType) -> bool {
+        type_ == MetaType::SyncTimeline
+    }
+}
+
+impl Debug for MetaSyncTimeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetaSyncTimeline")
+            .field("flags", &self.flags())
+            .field("acquire_point", &self.acquire_point())
+            .field("release_point", &self.release_point())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Metadata of `type_` pointing to `storage`, limited to `size` bytes.
+    fn meta(type_: MetaType, storage: &mut [u64], size: usize) -> Meta {
+        assert!(size <= storage.len() * 8);
+        Meta(spa_sys::spa_meta {
+            type_: type_.as_raw(),
+            size: size as u32,
+            data: storage.as_mut_ptr().cast(),
+        })
+    }
+
+    #[test]
+    fn header() {
+        let mut storage = [0u64; 8];
+        let mut meta = meta(MetaType::Header, &mut storage, size_of::<MetaHeader>());
+
+        let header = meta.get_mut::<MetaHeader>().unwrap();
+        header.set_flags(MetaHeaderFlags::DISCONT);
+        header.set_pts(42);
+        header.set_seq(7);
+
+        let header = meta.get::<MetaHeader>().unwrap();
+        assert_eq!(header.flags(), MetaHeaderFlags::DISCONT);
+        assert_eq!(header.pts(), 42);
+        assert_eq!(header.seq(), 7);
+
+        // wrong type
+        assert!(meta.get::<MetaBusy>().is_none());
+    }
+
+    #[test]
+    fn too_small() {
+        let mut storage = [0u64; 8];
+        let meta = meta(MetaType::Header, &mut storage, size_of::<MetaHeader>() - 1);
+        assert!(meta.get::<MetaHeader>().is_none());
+
+        let null = Meta(spa_sys::spa_meta {
+            type_: MetaType::Header.as_raw(),
+            size: size_of::<MetaHeader>() as u32,
+            data: std::ptr::null_mut(),
+        });
+        assert!(null.get::<MetaHeader>().is_none());
+        assert!(null.data().is_none());
+    }
+
+    #[test]
+    fn regions() {
+        let mut storage = [0u64; 8];
+        let mut meta = meta(
+            MetaType::VideoDamage,
+            &mut storage,
+            2 * size_of::<MetaRegion>(),
+        );
+
+        let region = meta.get_mut::<MetaRegion>().unwrap();
+        region.set_position(Point { x: 10, y: 20 });
+        region.set_size(Rectangle {
+            width: 640,
+            height: 480,
+        });
+
+        let regions = meta.regions();
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0].position(), Point { x: 10, y: 20 });
+        assert!(regions[0].is_valid());
+        assert!(!regions[1].is_valid());
+    }
+
+    #[test]
+    fn cursor_bitmap() {
+        const CURSOR: usize = size_of::<MetaCursor>();
+        const BITMAP: usize = size_of::<MetaBitmap>();
+        let mut storage = [0u64; 32];
+        let size = CURSOR + BITMAP + 4 * 4 * 2;
+        let mut meta = meta(MetaType::Cursor, &mut storage, size);
+
+        // no bitmap
+        assert!(meta.get::<MetaCursor>().is_some());
+        assert!(meta.cursor_bitmap().is_none());
+        assert!(meta.cursor_bitmap_pixels().is_none());
+
+        let cursor = meta.get_mut::<MetaCursor>().unwrap();
+        cursor.set_id(1);
+        cursor.set_bitmap_offset(CURSOR as u32);
+        let bitmap = meta.cursor_bitmap_mut().unwrap();
+        bitmap.set_format(VideoFormat::RGBA);
+        bitmap.set_size(Rectangle {
+            width: 4,
+            height: 2,
+        });
+        bitmap.set_stride(16);
+        bitmap.set_offset(BITMAP as u32);
+        meta.cursor_bitmap_pixels_mut().unwrap().fill(0xff);
+
+        assert_eq!(meta.cursor_bitmap().unwrap().format(), VideoFormat::RGBA);
+        let pixels = meta.cursor_bitmap_pixels().unwrap();
+        assert_eq!(pixels.len(), 32);
+        assert!(pixels.iter().all(|p| *p == 0xff));
+
+        // pixels past the end of the metadata
+        meta.cursor_bitmap_mut().unwrap().set_stride(17);
+        assert!(meta.cursor_bitmap_pixels().is_none());
+        meta.cursor_bitmap_mut().unwrap().set_stride(-16);
+        assert!(meta.cursor_bitmap_pixels().is_none());
+
+        // pixels overlapping the bitmap
+        let bitmap = meta.cursor_bitmap_mut().unwrap();
+        bitmap.set_stride(16);
+        bitmap.set_offset(4);
+        assert!(meta.cursor_bitmap_pixels().is_none());
+
+        // bitmap overlapping the cursor or past the end of the metadata
+        meta.get_mut::<MetaCursor>().unwrap().set_bitmap_offset(8);
+        assert!(meta.cursor_bitmap().is_none());
+        meta.get_mut::<MetaCursor>()
+            .unwrap()
+            .set_bitmap_offset(size as u32);
+        assert!(meta.cursor_bitmap().is_none());
+    }
+    #[test]
+    fn sync_timeline() {
+        assert_eq!(size_of::<MetaSyncTimeline>(), 24);
+        let mut storage = [0u64; 8];
+        let mut written = meta(
+            MetaType::SyncTimeline,
+            &mut storage,
+            size_of::<MetaSyncTimeline>(),
+        );
+
+        let timeline = written.get_mut::<MetaSyncTimeline>().unwrap();
+        timeline.set_acquire_point(3);
+        timeline.set_release_point(4);
+        assert_eq!(storage[1..3], [3, 4]);
+
+        let read = meta(
+            MetaType::SyncTimeline,
+            &mut storage,
+            size_of::<MetaSyncTimeline>(),
+        );
+        let timeline = read.get::<MetaSyncTimeline>().unwrap();
+        assert_eq!((timeline.acquire_point(), timeline.release_point()), (3, 4));
+        assert!(read.get::<MetaBusy>().is_none());
+        assert_eq!(
+            format!("{:?}", MetaType::SyncTimeline),
+            "MetaType::SyncTimeline"
+        );
+    }
+}
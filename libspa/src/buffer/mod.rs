@@ -3,6 +3,14 @@
 
 use std::{convert::TryFrom, fmt::Debug};
 
+use nix::errno::Errno;
+
+use crate::param::DataTypes;
+use crate::pod::{Pod, PodSequence};
+
+mod meta;
+pub use meta::*;
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub struct DataType(spa_sys::spa_data_type);
 
@@ -17,6 +25,10 @@ impl DataType {
     pub const DmaBuf: Self = Self(spa_sys::SPA_DATA_DmaBuf);
     /// Memory is identified with an id
     pub const MemId: Self = Self(spa_sys::SPA_DATA_MemId);
+    /// A syncobj, usually requires a spa_meta_sync_timeline metadata with timeline points.
+    ///
+    /// Added in PipeWire 1.2, its value is hardcoded so that it is available with older headers.
+    pub const SyncObj: Self = Self(5);
 
     pub fn from_raw(raw: spa_sys::spa_data_type) -> Self {
         Self(raw)
@@ -29,18 +41,16 @@ impl DataType {
 
 impl std::fmt::Debug for DataType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let name = format!(
-            "DataType::{}",
-            match *self {
-                Self::Invalid => "Invalid",
-                Self::MemPtr => "MemPtr",
-                Self::MemFd => "MemFd",
-                Self::DmaBuf => "DmaBuf",
-                Self::MemId => "MemId",
-                _ => "Unknown",
-            }
-        );
-        f.write_str(&name)
+        let name = match *self {
+            Self::Invalid => "Invalid",
+            Self::MemPtr => "MemPtr",
+            Self::MemFd => "MemFd",
+            Self::DmaBuf => "DmaBuf",
+            Self::MemId => "MemId",
+            Self::SyncObj => "SyncObj",
+            _ => return write!(f, "DataType::Unknown({})", self.0),
+        };
+        write!(f, "DataType::{name}")
     }
 }
 
@@ -69,11 +79,66 @@ impl Data {
         DataType::from_raw(self.0.type_)
     }
 
+    /// Set the type of the memory of the data.
+    ///
+    /// When the buffers are allocated by the application, the type is initially a mask
+    /// of the allowed types, see [`allowed_types`](Self::allowed_types).
+    pub fn set_type(&mut self, type_: DataType) {
+        self.0.type_ = type_.as_raw();
+    }
+
+    /// The types allowed for data of buffers allocated by the application, before its type is set.
+    pub fn allowed_types(&self) -> DataTypes {
+        DataTypes::from_bits_retain(self.0.type_)
+    }
+
     pub fn flags(&self) -> DataFlags {
         DataFlags::from_bits_retain(self.0.flags)
     }
 
-    // FIXME: Add bindings for the fd field, but how to detect when it is not set / invalid?
+    pub fn set_flags(&mut self, flags: DataFlags) {
+        self.0.flags = flags.bits();
+    }
+
+    /// The raw fd field: the fd of the memory for [`DataType::MemFd`] and [`DataType::DmaBuf`],
+    /// the id of the memory block for [`DataType::MemId`], or `-1` if unset.
+    pub fn fd(&self) -> i64 {
+        self.0.fd
+    }
+
+    /// Set the raw fd field, see [`fd`](Self::fd).
+    ///
+    /// The fd is not owned by the data and has to be kept open while the buffer exists.
+    pub fn set_fd(&mut self, fd: i64) {
+        self.0.fd = fd;
+    }
+
+    /// Offset of the data in the memory of the fd, to be used when mapping it.
+    pub fn mapoffset(&self) -> u32 {
+        self.0.mapoffset
+    }
+
+    pub fn set_mapoffset(&mut self, mapoffset: u32) {
+        self.0.mapoffset = mapoffset;
+    }
+
+    /// Size of the memory of the data, in bytes.
+    pub fn maxsize(&self) -> u32 {
+        self.0.maxsize
+    }
+
+    pub fn set_maxsize(&mut self, maxsize: u32) {
+        self.0.maxsize = maxsize;
+    }
+
+    /// Set the pointer to the memory of the data, as returned by [`data`](Self::data).
+    ///
+    /// # Safety
+    /// `data` must be null or point to at least [`maxsize`](Self::maxsize) bytes of memory
+    /// which stays valid while the buffer exists.
+    pub unsafe fn set_data(&mut self, data: *mut std::os::raw::c_void) {
+        self.0.data = data;
+    }
 
     pub fn data(&mut self) -> Option<&mut [u8]> {
         // FIXME: For safety, perhaps only return a non-mut slice when DataFlags::WRITABLE is not set?
@@ -97,12 +162,66 @@ impl Data {
         }
     }
 
-    pub fn chunk_mut(&mut self) -> &mut Chunk {
+    /// Get the chunk of the data to be filled by producers before queueing the buffer.
+    pub fn chunk_mut(&mut self) -> ChunkRef<'_> {
         assert_ne!(self.0.chunk, std::ptr::null_mut());
-        unsafe {
+        let chunk = unsafe {
             let chunk: *mut spa_sys::spa_chunk = self.0.chunk;
             &mut *(chunk as *mut Chunk)
+        };
+        ChunkRef {
+            chunk,
+            maxsize: self.0.maxsize,
+        }
+    }
+
+    /// Get the control sequence in the valid region of the data, as described by its chunk.
+    ///
+    /// This is how the buffers of streams with a `application/control` format, such as MIDI,
+    /// carry their events.
+    ///
+    /// Returns `None` if there is no memory, or if the region does not hold a complete,
+    /// properly aligned sequence pod.
+    pub fn sequence(&self) -> Option<&PodSequence> {
+        if self.0.data.is_null() || self.0.chunk.is_null() || self.0.maxsize == 0 {
+            return None;
         }
+
+        let chunk = self.chunk();
+        let offset = usize::try_from(chunk.offset() % self.0.maxsize).unwrap();
+        let size = usize::try_from(chunk.size()).unwrap();
+        let maxsize = usize::try_from(self.0.maxsize).unwrap();
+        let size = size.min(maxsize - offset);
+
+        // Safety: the memory holds at least maxsize bytes, and the region is inside of it.
+        let bytes =
+            unsafe { std::slice::from_raw_parts(self.0.data.cast::<u8>().add(offset), size) };
+        if bytes
+            .as_ptr()
+            .align_offset(std::mem::align_of::<spa_sys::spa_pod>())
+            != 0
+        {
+            return None;
+        }
+
+        Pod::from_bytes(bytes)?.as_sequence().ok()
+    }
+
+    /// Copy `sequence` to the start of the memory, and set the chunk to cover it.
+    ///
+    /// Fails with `ENOSPC` if the sequence does not fit in the memory, and with `EINVAL`
+    /// if there is no memory.
+    pub fn write_sequence(&mut self, sequence: &PodSequence) -> Result<(), Errno> {
+        let bytes = sequence.as_pod().as_bytes();
+        let data = self.data().ok_or(Errno::EINVAL)?;
+        let dest = data.get_mut(..bytes.len()).ok_or(Errno::ENOSPC)?;
+        dest.copy_from_slice(bytes);
+
+        let mut chunk = self.chunk_mut();
+        chunk.set_offset(0);
+        chunk.set_size(u32::try_from(bytes.len()).unwrap());
+        chunk.set_stride(1);
+        Ok(())
     }
 }
 
@@ -111,7 +230,7 @@ impl Debug for Data {
         f.debug_struct("Data")
             .field("type", &self.type_())
             .field("flags", &self.flags())
-            // FIXME: Add fd
+            .field("fd", &self.fd())
             .field("data", &self.0.data) // Only print the pointer here, as we don't want to print a (potentially very big) slice.
             .field("chunk", &self.chunk())
             .finish()
@@ -123,6 +242,9 @@ bitflags::bitflags! {
     pub struct ChunkFlags: i32 {
         /// Chunk data is corrupted in some way
         const CORRUPTED = 1<<0;
+        /// Chunk data is empty with media specific neutral data such as silence or black.
+        /// This could be used to optimize processing.
+        const EMPTY = 1<<1;
     }
 }
 
@@ -138,26 +260,14 @@ impl Chunk {
         self.0.size
     }
 
-    pub fn size_mut(&mut self) -> &mut u32 {
-        &mut self.0.size
-    }
-
     pub fn offset(&self) -> u32 {
         self.0.offset
     }
 
-    pub fn offset_mut(&mut self) -> &mut u32 {
-        &mut self.0.offset
-    }
-
     pub fn stride(&self) -> i32 {
         self.0.stride
     }
 
-    pub fn stride_mut(&mut self) -> &mut i32 {
-        &mut self.0.stride
-    }
-
     pub fn flags(&self) -> ChunkFlags {
         ChunkFlags::from_bits_retain(self.0.flags)
     }
@@ -173,3 +283,182 @@ impl Debug for Chunk {
             .finish()
     }
 }
+
+/// A mutable reference to the [`Chunk`] of a [`Data`], see [`Data::chunk_mut`].
+///
+/// The fields of the chunk can be read through [`Deref`](std::ops::Deref).
+pub struct ChunkRef<'a> {
+    chunk: &'a mut Chunk,
+    maxsize: u32,
+}
+
+impl ChunkRef<'_> {
+    /// Set the offset of the valid data, taken modulo the `maxsize` of the data.
+    pub fn set_offset(&mut self, offset: u32) {
+        self.chunk.0.offset = offset;
+    }
+
+    /// Set the size of the valid data.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `size` is larger than the [`maxsize`](Data::maxsize) of the data.
+    pub fn set_size(&mut self, size: u32) {
+        debug_assert!(
+            size <= self.maxsize,
+            "chunk size {size} is larger than the data maxsize {}",
+            self.maxsize
+        );
+        self.chunk.0.size = size;
+    }
+
+    /// Set the stride of the valid data.
+    pub fn set_stride(&mut self, stride: i32) {
+        self.chunk.0.stride = stride;
+    }
+
+    pub fn set_flags(&mut self, flags: ChunkFlags) {
+        self.chunk.0.flags = flags.bits();
+    }
+}
+
+impl std::ops::Deref for ChunkRef<'_> {
+    type Target = Chunk;
+
+    fn deref(&self) -> &Self::Target {
+        self.chunk
+    }
+}
+
+impl Debug for ChunkRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.chunk.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_type_debug() {
+        assert_eq!(format!("{:?}", DataType::DmaBuf), "DataType::DmaBuf");
+        assert_eq!(format!("{:?}", DataType::SyncObj), "DataType::SyncObj");
+        assert_eq!(
+            format!("{:?}", DataType::from_raw(42)),
+            "DataType::Unknown(42)"
+        );
+    }
+
+    fn data(chunk: &mut spa_sys::spa_chunk, maxsize: u32) -> Data {
+        Data(spa_sys::spa_data {
+            type_: DataType::MemPtr.as_raw(),
+            flags: DataFlags::READWRITE.bits(),
+            fd: -1,
+            mapoffset: 0,
+            maxsize,
+            data: std::ptr::null_mut(),
+            chunk,
+        })
+    }
+
+    #[test]
+    fn chunk_setters() {
+        let mut chunk: spa_sys::spa_chunk = unsafe { std::mem::zeroed() };
+        let mut data = data(&mut chunk, 1024);
+
+        let mut chunk_ref = data.chunk_mut();
+        chunk_ref.set_offset(16);
+        chunk_ref.set_size(1024);
+        chunk_ref.set_stride(4);
+        chunk_ref.set_flags(ChunkFlags::CORRUPTED | ChunkFlags::EMPTY);
+        assert_eq!(chunk_ref.size(), 1024);
+
+        let chunk = data.chunk();
+        assert_eq!(chunk.offset(), 16);
+        assert_eq!(chunk.size(), 1024);
+        assert_eq!(chunk.stride(), 4);
+        assert_eq!(chunk.flags(), ChunkFlags::CORRUPTED | ChunkFlags::EMPTY);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "larger than the data maxsize")]
+    fn chunk_size_larger_than_maxsize() {
+        let mut chunk: spa_sys::spa_chunk = unsafe { std::mem::zeroed() };
+        let mut data = data(&mut chunk, 1024);
+
+        data.chunk_mut().set_size(1025);
+    }
+
+    #[test]
+    fn data_setters() {
+        let mut chunk: spa_sys::spa_chunk = unsafe { std::mem::zeroed() };
+        let mut data = data(&mut chunk, 0);
+        data.set_type(DataType::from_raw(
+            (DataTypes::MEM_FD | DataTypes::DMA_BUF).bits(),
+        ));
+        assert!(data.allowed_types().allows(DataType::MemFd));
+        assert!(!data.allowed_types().allows(DataType::MemPtr));
+
+        let mut mem = [0u8; 64];
+        data.set_type(DataType::MemFd);
+        data.set_flags(DataFlags::READABLE);
+        data.set_fd(7);
+        data.set_mapoffset(16);
+        data.set_maxsize(48);
+        unsafe { data.set_data(mem[16..].as_mut_ptr().cast()) };
+
+        assert_eq!(data.type_(), DataType::MemFd);
+        assert_eq!(data.flags(), DataFlags::READABLE);
+        assert_eq!(data.fd(), 7);
+        assert_eq!(data.mapoffset(), 16);
+        assert_eq!(data.maxsize(), 48);
+        assert_eq!(data.data().map(|d| d.len()), Some(48));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn sequence_roundtrip() {
+        use crate::pod::{ControlType, SequenceBuilder};
+
+        let mut chunk: spa_sys::spa_chunk = unsafe { std::mem::zeroed() };
+        let mut mem = [0u64; 8];
+        let mut data = data(&mut chunk, 64);
+        assert!(data.sequence().is_none());
+        unsafe { data.set_data(mem.as_mut_ptr().cast()) };
+
+        let sequence = SequenceBuilder::new()
+            .bytes(16, ControlType::Midi, &[0x90, 60, 100])
+            .build();
+        data.write_sequence(sequence.as_sequence().unwrap())
+            .unwrap();
+        assert_eq!(data.chunk().size(), 40);
+
+        let controls: Vec<_> = data
+            .sequence()
+            .unwrap()
+            .controls()
+            .map(|control| (control.offset(), control.type_(), control.bytes()))
+            .collect();
+        assert_eq!(
+            controls,
+            [(16, ControlType::Midi, Some(&[0x90, 60, 100][..]))]
+        );
+
+        // a chunk which is not a sequence, or is truncated
+        data.chunk_mut().set_size(20);
+        assert!(data.sequence().is_none());
+        data.chunk_mut().set_offset(8);
+        data.chunk_mut().set_size(40);
+        assert!(data.sequence().is_none());
+
+        let too_big = SequenceBuilder::new()
+            .bytes(0, ControlType::Midi, &[0; 64])
+            .build();
+        assert_eq!(
+            data.write_sequence(too_big.as_sequence().unwrap()),
+            Err(Errno::ENOSPC)
+        );
+    }
+}
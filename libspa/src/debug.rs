@@ -0,0 +1,223 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Names of SPA types and ids, looked up in the type info tables of SPA like `spa/debug/types.h` in C.
+//!
+//! Type names are fully qualified, such as `Spa:Pod:Object:Param:Format`, while the names of
+//! ids and property keys are the last component of their qualified name, such as `EnumFormat`
+//! or `mediaType`.
+//! All functions return `None` for values missing from the tables of the SPA version in use.
+//!
+//! ```
+//! use libspa::{debug, param::ParamType, utils::SpaTypes};
+//!
+//! assert_eq!(debug::param_type_name(ParamType::EnumFormat), Some("EnumFormat"));
+//! assert_eq!(
+//!     debug::type_name(SpaTypes::ObjectParamFormat.as_raw()),
+//!     Some("Spa:Pod:Object:Param:Format")
+//! );
+//! assert_eq!(
+//!     debug::object_prop_name(SpaTypes::ObjectParamFormat, libspa::sys::SPA_FORMAT_mediaType),
+//!     Some("mediaType")
+//! );
+//! ```
+
+use std::ffi::{c_char, CStr};
+
+use crate::{
+    param::{
+        audio::{AudioChannel, AudioFormat},
+        format::{MediaSubtype, MediaType},
+        video::VideoFormat,
+        ParamType,
+    },
+    utils::SpaTypes,
+};
+
+pub(crate) type TypeTable = *const spa_sys::spa_type_info;
+
+/// Convert a name from the static type tables.
+fn static_str(name: *const c_char) -> Option<&'static str> {
+    if name.is_null() {
+        return None;
+    }
+    // Safety: the names of the type tables are static, nul terminated strings.
+    unsafe { CStr::from_ptr(name) }.to_str().ok()
+}
+
+/// Find the entry of `value` in `table`, ignoring null tables.
+fn find(table: TypeTable, value: u32) -> Option<&'static spa_sys::spa_type_info> {
+    // spa_debug_type_find() would look into the root table instead
+    if table.is_null() {
+        return None;
+    }
+    unsafe { spa_sys::spa_debug_type_find(table, value).as_ref() }
+}
+
+/// The short name of `value` in `table`.
+fn short_name(table: TypeTable, value: u32) -> Option<&'static str> {
+    if table.is_null() {
+        return None;
+    }
+    static_str(unsafe { spa_sys::spa_debug_type_find_short_name(table, value) })
+}
+
+/// The table naming the values of the entry `value` of `table`, such as the property keys of an
+/// object type or the ids of a property key, or null if they have no names.
+#[cfg(feature = "serde")]
+pub(crate) fn values_table(table: TypeTable, value: u32) -> TypeTable {
+    find(table, value).map_or(std::ptr::null(), |info| info.values)
+}
+
+/// The value of the entry of `table` whose name matches `name`, looking into the same entries
+/// as [`find`].
+#[cfg(feature = "serde")]
+fn find_by_name(table: TypeTable, name: &dyn Fn(&str) -> bool) -> Option<u32> {
+    let mut entry = table;
+    while let Some(info) = unsafe { entry.as_ref() } {
+        if info.name.is_null() {
+            break;
+        }
+        // SPA_ID_INVALID entries group other entries
+        if info.type_ == u32::MAX {
+            if let Some(value) = find_by_name(info.values, name) {
+                return Some(value);
+            }
+        } else if static_str(info.name).is_some_and(name) {
+            return Some(info.type_);
+        }
+        entry = unsafe { entry.add(1) };
+    }
+    None
+}
+
+/// The type whose qualified name is `name`, the reverse of [`type_name`].
+#[cfg(feature = "serde")]
+pub(crate) fn type_by_name(name: &str) -> Option<u32> {
+    find_by_name(unsafe { spa_sys::spa_types }, &|qualified: &str| {
+        qualified == name
+    })
+}
+
+/// The short name of `value` in `table`, if it names this value only.
+///
+/// Short names are not unique, e.g. the `format` keys of audio and video formats, those are
+/// `None` so that the names found with [`value_by_short_name`] are the same values.
+#[cfg(feature = "serde")]
+pub(crate) fn unique_short_name(table: TypeTable, value: u32) -> Option<&'static str> {
+    short_name(table, value).filter(|name| value_by_short_name(table, name) == Some(value))
+}
+
+/// The first value of `table` whose short name is `name`.
+#[cfg(feature = "serde")]
+pub(crate) fn value_by_short_name(table: TypeTable, name: &str) -> Option<u32> {
+    find_by_name(table, &|qualified: &str| {
+        qualified.rsplit(':').next() == Some(name)
+    })
+}
+
+/// The qualified name of a type, such as `Spa:Pod:Object:Param:Format` for
+/// [`SpaTypes::ObjectParamFormat`], or `Spa:Int` for `SPA_TYPE_Int`.
+pub fn type_name(type_: u32) -> Option<&'static str> {
+    static_str(find(unsafe { spa_sys::spa_types }, type_)?.name)
+}
+
+/// The name of a param type, such as `EnumFormat`.
+pub fn param_type_name(param: ParamType) -> Option<&'static str> {
+    short_name(unsafe { spa_sys::spa_type_param }, param.as_raw())
+}
+
+/// The name of the property `key` of objects of type `object_type`, such as `mediaType` for
+/// [`FormatProperties::MediaType`](crate::param::format::FormatProperties::MediaType) in
+/// [`SpaTypes::ObjectParamFormat`] objects.
+pub fn object_prop_name(object_type: SpaTypes, key: u32) -> Option<&'static str> {
+    // the table of an object type lists its property keys
+    let keys = find(unsafe { spa_sys::spa_types }, object_type.as_raw())?.values;
+    short_name(keys, key)
+}
+
+/// The name of a media type, such as `audio`.
+pub fn media_type_name(media_type: MediaType) -> Option<&'static str> {
+    short_name(unsafe { spa_sys::spa_type_media_type }, media_type.as_raw())
+}
+
+/// The name of a media subtype, such as `raw`.
+pub fn media_subtype_name(media_subtype: MediaSubtype) -> Option<&'static str> {
+    short_name(
+        unsafe { spa_sys::spa_type_media_subtype },
+        media_subtype.as_raw(),
+    )
+}
+
+/// The name of an audio format, such as `F32LE`.
+pub fn audio_format_name(format: AudioFormat) -> Option<&'static str> {
+    short_name(unsafe { spa_sys::spa_type_audio_format }, format.as_raw())
+}
+
+/// The name of an audio channel, such as `FL`.
+pub fn audio_channel_name(channel: AudioChannel) -> Option<&'static str> {
+    short_name(unsafe { spa_sys::spa_type_audio_channel }, channel.as_raw())
+}
+
+/// The name of a video format, such as `BGRx`.
+pub fn video_format_name(format: VideoFormat) -> Option<&'static str> {
+    short_name(unsafe { spa_sys::spa_type_video_format }, format.as_raw())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn types() {
+        assert_eq!(type_name(spa_sys::SPA_TYPE_Int), Some("Spa:Int"));
+        assert_eq!(
+            type_name(spa_sys::SPA_TYPE_OBJECT_Format),
+            Some("Spa:Pod:Object:Param:Format")
+        );
+        assert_eq!(type_name(u32::MAX), None);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn params() {
+        assert_eq!(param_type_name(ParamType::EnumFormat), Some("EnumFormat"));
+        assert_eq!(param_type_name(ParamType::Props), Some("Props"));
+        assert_eq!(param_type_name(ParamType::from_raw(u32::MAX)), None);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn object_props() {
+        assert_eq!(
+            object_prop_name(SpaTypes::ObjectParamFormat, spa_sys::SPA_FORMAT_mediaType),
+            Some("mediaType")
+        );
+        assert_eq!(
+            object_prop_name(
+                SpaTypes::ObjectParamFormat,
+                spa_sys::SPA_FORMAT_AUDIO_format
+            ),
+            Some("format")
+        );
+        assert_eq!(
+            object_prop_name(SpaTypes::ObjectParamProps, spa_sys::SPA_PROP_volume),
+            Some("volume")
+        );
+        assert_eq!(object_prop_name(SpaTypes::ObjectParamProps, u32::MAX), None);
+        // not an object type, without keys
+        assert_eq!(object_prop_name(SpaTypes::Int, 1), None);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn values() {
+        assert_eq!(media_type_name(MediaType::Audio), Some("audio"));
+        assert_eq!(media_subtype_name(MediaSubtype::Dsp), Some("dsp"));
+        assert_eq!(audio_format_name(AudioFormat::F32LE), Some("F32LE"));
+        assert_eq!(audio_channel_name(AudioChannel::FL), Some("FL"));
+        assert_eq!(video_format_name(VideoFormat::BGRx), Some("BGRx"));
+        assert_eq!(video_format_name(VideoFormat(u32::MAX)), None);
+    }
+}
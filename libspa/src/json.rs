@@ -0,0 +1,636 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Parser and serializer for the relaxed JSON dialect of SPA.
+//!
+//! PipeWire config files, module arguments and many metadata values use the syntax
+//! understood by the `spa_json_*` functions, which extends JSON with:
+//! - bare words, which are strings unless they are `null`, `true`, `false` or a number,
+//! - `=` or `:` between keys and values, both optional,
+//! - optional `,` between items,
+//! - `#` comments up to the end of the line.
+//!
+//! ```
+//! use libspa::json::JsonValue;
+//!
+//! let args: JsonValue = "{ node.name = my-sink audio.position = [ FL FR ] }".parse().unwrap();
+//! assert_eq!(args.get("node.name").and_then(JsonValue::as_str), Some("my-sink"));
+//! assert_eq!(args.to_string(), r#"{"node.name":"my-sink","audio.position":["FL","FR"]}"#);
+//! ```
+
+use std::{fmt, str::FromStr};
+
+/// A value of SPA JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    /// The members of an object, in their order in the source.
+    ///
+    /// Keys are not deduplicated, as config files may repeat them.
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Parse a single value, such as the args of a module or a metadata value.
+    pub fn parse(json: &str) -> Result<Self, ParseJsonError> {
+        let mut parser = Parser::new(json);
+        let value = parser.value()?;
+        parser.end()?;
+        Ok(value)
+    }
+
+    /// Parse the members of an object whose braces may be omitted, like the content of
+    /// `pipewire.conf` and the other config files.
+    pub fn parse_object_relaxed(json: &str) -> Result<Vec<(String, JsonValue)>, ParseJsonError> {
+        let mut parser = Parser::new(json);
+        parser.skip();
+        let members = if parser.peek() == Some(b'{') {
+            parser.pos += 1;
+            parser.members(Some(b'}'))?
+        } else {
+            parser.members(None)?
+        };
+        parser.end()?;
+        Ok(members)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Get the value of a number, integer or not.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Int(i) => Some(*i as f64),
+            Self::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            Self::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, JsonValue)]> {
+        match self {
+            Self::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    /// Get the last member of an object with the given key, which is the one
+    /// taking effect when a key is repeated.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.as_object()?
+            .iter()
+            .rev()
+            .find_map(|(k, v)| (k == key).then_some(v))
+    }
+}
+
+impl FromStr for JsonValue {
+    type Err = ParseJsonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i64> for JsonValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<f64> for JsonValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+/// Serialize the value as compact, standard JSON, which SPA JSON parsers accept too.
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => f.write_str("null"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Int(i) => write!(f, "{i}"),
+            Self::Float(v) if v.is_finite() => write!(f, "{v:?}"),
+            Self::Float(_) => f.write_str("null"),
+            Self::String(s) => write_string(f, s),
+            Self::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_str("]")
+            }
+            Self::Object(members) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            '\u{8}' => f.write_str("\\b")?,
+            '\u{c}' => f.write_str("\\f")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+/// Error returned when parsing invalid SPA JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseJsonError {
+    offset: usize,
+    reason: &'static str,
+}
+
+impl ParseJsonError {
+    /// The byte offset in the input at which the error was found.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl std::error::Error for ParseJsonError {}
+
+impl fmt::Display for ParseJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.reason, self.offset)
+    }
+}
+
+/// Nesting limit, so malicious input cannot overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    fn error(&self, reason: &'static str) -> ParseJsonError {
+        ParseJsonError {
+            offset: self.pos,
+            reason,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    /// Skip whitespace, separators and comments, which may appear between any two tokens.
+    fn skip(&mut self) {
+        while let Some(c) = self.peek() {
+            match c {
+                b' ' | b'\t' | b'\r' | b'\n' | b'\0' | b':' | b',' | b'=' => self.pos += 1,
+                b'#' => {
+                    let input = self.input;
+                    let rest = &input[self.pos..];
+                    self.pos += rest.find('\n').unwrap_or(rest.len());
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn end(&mut self) -> Result<(), ParseJsonError> {
+        self.skip();
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error("unexpected trailing value")),
+        }
+    }
+
+    fn value(&mut self) -> Result<JsonValue, ParseJsonError> {
+        self.skip();
+        match self.peek() {
+            None => Err(self.error("expected a value")),
+            Some(b'{') | Some(b'[') if self.depth >= MAX_DEPTH => {
+                Err(self.error("nesting too deep"))
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                self.depth += 1;
+                let members = self.members(Some(b'}'))?;
+                self.depth -= 1;
+                Ok(JsonValue::Object(members))
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                self.depth += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip();
+                    match self.peek() {
+                        Some(b']') => break,
+                        None => return Err(self.error("unterminated array")),
+                        _ => items.push(self.value()?),
+                    }
+                }
+                self.pos += 1;
+                self.depth -= 1;
+                Ok(JsonValue::Array(items))
+            }
+            Some(b'"') => self.string().map(JsonValue::String),
+            Some(b'}') | Some(b']') => Err(self.error("unexpected closing bracket")),
+            Some(_) => Ok(bare_value(self.bare())),
+        }
+    }
+
+    /// Parse object members up to `close`, or up to the end of the input if `None`.
+    fn members(&mut self, close: Option<u8>) -> Result<Vec<(String, JsonValue)>, ParseJsonError> {
+        let mut members = Vec::new();
+        loop {
+            self.skip();
+            let key = match self.peek() {
+                c if c == close => break,
+                None => return Err(self.error("unterminated object")),
+                Some(b'"') => self.string()?,
+                Some(b'{') | Some(b'[') | Some(b'}') | Some(b']') => {
+                    return Err(self.error("expected a key"))
+                }
+                Some(_) => self.bare().to_owned(),
+            };
+            self.skip();
+            if self.peek() == close {
+                return Err(self.error("expected a value"));
+            }
+            members.push((key, self.value()?));
+        }
+        if close.is_some() {
+            self.pos += 1;
+        }
+        Ok(members)
+    }
+
+    /// Read a bare word, which ends at whitespace, separators, brackets and quotes.
+    fn bare(&mut self) -> &'a str {
+        let input = self.input;
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            match c {
+                b' ' | b'\t' | b'\r' | b'\n' | b'\0' | b':' | b',' | b'=' | b'{' | b'}' | b'['
+                | b']' | b'"' => break,
+                _ => self.pos += 1,
+            }
+        }
+        &input[start..self.pos]
+    }
+
+    fn string(&mut self) -> Result<String, ParseJsonError> {
+        // skip the opening quote
+        self.pos += 1;
+        let input = self.input;
+        let mut string = String::new();
+        loop {
+            let rest = &input[self.pos..];
+            let Some(i) = rest.find(['"', '\\']) else {
+                return Err(self.error("unterminated string"));
+            };
+            string.push_str(&rest[..i]);
+            self.pos += i + 1;
+            if rest.as_bytes()[i] == b'"' {
+                return Ok(string);
+            }
+
+            let Some(escape) = self.peek() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += 1;
+            match escape {
+                b'"' => string.push('"'),
+                b'\\' => string.push('\\'),
+                b'/' => string.push('/'),
+                b'b' => string.push('\u{8}'),
+                b'f' => string.push('\u{c}'),
+                b'n' => string.push('\n'),
+                b'r' => string.push('\r'),
+                b't' => string.push('\t'),
+                b'u' => {
+                    let high = self.hex4()?;
+                    let c = if (0xd800..0xdc00).contains(&high)
+                        && self.input[self.pos..].starts_with("\\u")
+                    {
+                        self.pos += 2;
+                        let low = self.hex4()?;
+                        char::decode_utf16([high, low]).next().and_then(Result::ok)
+                    } else {
+                        char::from_u32(high.into())
+                    };
+                    string.push(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                // unknown escapes keep the escaped character, like spa_json_parse_stringn()
+                _ => {
+                    self.pos -= 1;
+                    let c = self.input[self.pos..].chars().next().unwrap();
+                    string.push(c);
+                    self.pos += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u16, ParseJsonError> {
+        let hex = self
+            .input
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        let value =
+            u16::from_str_radix(hex, 16).map_err(|_| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        Ok(value)
+    }
+}
+
+/// Interpret a bare word, which is a string unless it is a keyword or a number.
+fn bare_value(word: &str) -> JsonValue {
+    match word {
+        "null" => return JsonValue::Null,
+        "true" => return JsonValue::Bool(true),
+        "false" => return JsonValue::Bool(false),
+        _ => {}
+    }
+
+    // only accept what looks like a number, so words like `inf` or `nan` stay strings
+    if word.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '-' | '+' | '.')) {
+        if let Ok(i) = word.parse() {
+            return JsonValue::Int(i);
+        }
+        if let Ok(f) = word.parse::<f64>() {
+            if f.is_finite() {
+                return JsonValue::Float(f);
+            }
+        }
+    }
+
+    JsonValue::String(word.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object<const N: usize>(members: [(&str, JsonValue); N]) -> JsonValue {
+        JsonValue::Object(
+            members
+                .into_iter()
+                .map(|(k, v)| (k.to_owned(), v))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn pipewire_conf() {
+        let conf = r#"
+# Daemon config file for PipeWire #
+context.properties = {
+    ## Configure properties in the system.
+    #library.name.system                   = support/libspa-support
+    link.max-buffers                       = 16
+    core.daemon                            = true
+    core.name                              = pipewire-0
+    default.clock.rate                     = 48000
+    default.clock.allowed-rates            = [ 44100 48000 ]
+    default.clock.quantum-limit            = 8192
+    settings.check-quantum                 = false
+}
+
+context.modules = [
+    { name = libpipewire-module-rt
+        args = {
+            nice.level   = -11
+            #rt.prio      = 88
+            rt.time.soft = -1
+        }
+        flags = [ ifexists nofail ]
+    }
+    { name = libpipewire-module-protocol-native }
+    { name = libpipewire-module-loopback
+        args = {
+            audio.position = [ FL FR ]
+            capture.props = {
+                node.description = "Loopback \"capture\""
+            }
+        }
+        condition = [ { module.x11.bell = !false } ]
+    }
+]
+"#;
+        let members = JsonValue::parse_object_relaxed(conf).unwrap();
+        assert_eq!(members.len(), 2);
+        let (key, properties) = &members[0];
+        assert_eq!(key, "context.properties");
+        assert_eq!(
+            *properties,
+            object([
+                ("link.max-buffers", JsonValue::Int(16)),
+                ("core.daemon", JsonValue::Bool(true)),
+                ("core.name", "pipewire-0".into()),
+                ("default.clock.rate", JsonValue::Int(48000)),
+                (
+                    "default.clock.allowed-rates",
+                    JsonValue::Array(vec![JsonValue::Int(44100), JsonValue::Int(48000)])
+                ),
+                ("default.clock.quantum-limit", JsonValue::Int(8192)),
+                ("settings.check-quantum", JsonValue::Bool(false)),
+            ])
+        );
+
+        let modules = members[1].1.as_array().unwrap();
+        assert_eq!(modules.len(), 3);
+        assert_eq!(
+            modules[0],
+            object([
+                ("name", "libpipewire-module-rt".into()),
+                (
+                    "args",
+                    object([
+                        ("nice.level", JsonValue::Int(-11)),
+                        ("rt.time.soft", JsonValue::Int(-1)),
+                    ])
+                ),
+                (
+                    "flags",
+                    JsonValue::Array(vec!["ifexists".into(), "nofail".into()])
+                ),
+            ])
+        );
+        let args = modules[2].get("args").unwrap();
+        assert_eq!(
+            args.get("capture.props")
+                .and_then(|props| props.get("node.description"))
+                .and_then(JsonValue::as_str),
+            Some("Loopback \"capture\"")
+        );
+        assert_eq!(
+            modules[2].get("condition").unwrap().as_array().unwrap()[0].get("module.x11.bell"),
+            Some(&"!false".into())
+        );
+
+        // the braces of the top level object are optional
+        let braced = format!("{{ {conf} }}");
+        assert_eq!(JsonValue::parse_object_relaxed(&braced), Ok(members));
+    }
+
+    #[test]
+    fn metadata() {
+        // values of the default metadata, as set by wireplumber
+        let value: JsonValue = r#"{"name":"alsa_output.pci-0000_00_1f.3.analog-stereo"}"#
+            .parse()
+            .unwrap();
+        assert_eq!(
+            value.get("name").and_then(JsonValue::as_str),
+            Some("alsa_output.pci-0000_00_1f.3.analog-stereo")
+        );
+
+        // values of the route settings, as stored by wireplumber
+        let value: JsonValue = r#"{ "volume": 0.5, "mute": false, "channelVolumes": [ 0.4, 0.6 ], "channelMap": [ "FL", "FR" ], "latencyOffsetNsec": 0 }"#.parse().unwrap();
+        assert_eq!(value.get("volume").and_then(JsonValue::as_f64), Some(0.5));
+        assert_eq!(value.get("mute").and_then(JsonValue::as_bool), Some(false));
+        assert_eq!(
+            value.get("latencyOffsetNsec").and_then(JsonValue::as_i64),
+            Some(0)
+        );
+        assert_eq!(
+            value.to_string(),
+            r#"{"volume":0.5,"mute":false,"channelVolumes":[0.4,0.6],"channelMap":["FL","FR"],"latencyOffsetNsec":0}"#
+        );
+
+        // repeated keys are kept, the last one wins
+        let value: JsonValue = "{ a = 1 a = 2 }".parse().unwrap();
+        assert_eq!(value.get("a"), Some(&JsonValue::Int(2)));
+        assert_eq!(value.as_object().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn scalars() {
+        assert_eq!("null".parse(), Ok(JsonValue::Null));
+        assert_eq!(" true ".parse(), Ok(JsonValue::Bool(true)));
+        assert_eq!("-7".parse(), Ok(JsonValue::Int(-7)));
+        assert_eq!("1e3".parse(), Ok(JsonValue::Float(1000.0)));
+        assert_eq!("inf".parse(), Ok(JsonValue::from("inf")));
+        assert_eq!("1.2.3".parse(), Ok(JsonValue::from("1.2.3")));
+        assert_eq!(
+            r#""a\tbé😀\q""#.parse(),
+            Ok(JsonValue::from("a\tb\u{e9}\u{1f600}q"))
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        let value = object([
+            ("node.name", "a \"quoted\"\nname".into()),
+            ("rate", JsonValue::Int(48000)),
+            ("gain", JsonValue::Float(1.0)),
+            (
+                "nested",
+                JsonValue::Array(vec![JsonValue::Null, object([("k", true.into())])]),
+            ),
+            ("control\u{1}", JsonValue::Array(vec![])),
+        ]);
+        let json = value.to_string();
+        assert_eq!(
+            json,
+            r#"{"node.name":"a \"quoted\"\nname","rate":48000,"gain":1.0,"nested":[null,{"k":true}],"control\u0001":[]}"#
+        );
+        assert_eq!(json.parse(), Ok(value));
+    }
+
+    #[test]
+    fn invalid() {
+        for json in [
+            "",
+            "{",
+            "[ 1 2",
+            "\"abc",
+            "{ a }",
+            "{ [ 1 ] = 2 }",
+            "]",
+            "1 2",
+            r#""\u12""#,
+        ] {
+            assert!(JsonValue::parse(json).is_err(), "{json:?} was parsed");
+        }
+        assert_eq!(JsonValue::parse("{ a }").unwrap_err().offset(), 4);
+
+        let deep = "[".repeat(1000);
+        assert!(JsonValue::parse(&deep).is_err());
+    }
+}
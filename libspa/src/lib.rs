@@ -7,6 +7,8 @@
 //! [libspa]: https://docs.pipewire.org/page_spa.html
 
 pub mod buffer;
+pub mod debug;
+pub mod json;
 pub mod param;
 pub mod pod;
 pub mod support;
@@ -0,0 +1,342 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The position of an audio channel, such as [`AudioChannel::FL`] for front left.
+///
+/// Channels are printed and parsed using the short names of PipeWire, such as `"FL"` or `"AUX3"`,
+/// which are also used in the `audio.position` property, see [`AudioChannel::parse_position`].
+#[repr(transparent)]
+#[derive(PartialEq, Eq, Clone, Copy, Hash)]
+pub struct AudioChannel(pub spa_sys::spa_audio_channel);
+
+impl AudioChannel {
+    /// unspecified
+    pub const UNKNOWN: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_UNKNOWN);
+    /// N/A, silent
+    pub const NA: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_NA);
+    /// mono stream
+    pub const MONO: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_MONO);
+    /// front left
+    pub const FL: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_FL);
+    /// front right
+    pub const FR: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_FR);
+    /// front center
+    pub const FC: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_FC);
+    /// low frequency effects
+    pub const LFE: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_LFE);
+    /// side left
+    pub const SL: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_SL);
+    /// side right
+    pub const SR: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_SR);
+    /// front left center
+    pub const FLC: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_FLC);
+    /// front right center
+    pub const FRC: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_FRC);
+    /// rear center
+    pub const RC: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_RC);
+    /// rear left
+    pub const RL: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_RL);
+    /// rear right
+    pub const RR: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_RR);
+    /// top center
+    pub const TC: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_TC);
+    /// top front left
+    pub const TFL: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_TFL);
+    /// top front center
+    pub const TFC: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_TFC);
+    /// top front right
+    pub const TFR: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_TFR);
+    /// top rear left
+    pub const TRL: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_TRL);
+    /// top rear center
+    pub const TRC: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_TRC);
+    /// top rear right
+    pub const TRR: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_TRR);
+    /// rear left center
+    pub const RLC: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_RLC);
+    /// rear right center
+    pub const RRC: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_RRC);
+    /// front left wide
+    pub const FLW: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_FLW);
+    /// front right wide
+    pub const FRW: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_FRW);
+    /// LFE 2
+    pub const LFE2: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_LFE2);
+    /// front left high
+    pub const FLH: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_FLH);
+    /// front center high
+    pub const FCH: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_FCH);
+    /// front right high
+    pub const FRH: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_FRH);
+    /// top front left center
+    pub const TFLC: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_TFLC);
+    /// top front right center
+    pub const TFRC: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_TFRC);
+    /// top side left
+    pub const TSL: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_TSL);
+    /// top side right
+    pub const TSR: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_TSR);
+    /// left LFE
+    pub const LLFE: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_LLFE);
+    /// right LFE
+    pub const RLFE: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_RLFE);
+    /// bottom center
+    pub const BC: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_BC);
+    /// bottom left center
+    pub const BLC: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_BLC);
+    /// bottom right center
+    pub const BRC: Self = Self(spa_sys::SPA_AUDIO_CHANNEL_BRC);
+
+    /// The channels with a short name, in the order of their raw value.
+    const NAMED: [(Self, &'static str); 38] = [
+        (Self::UNKNOWN, "UNK"),
+        (Self::NA, "NA"),
+        (Self::MONO, "MONO"),
+        (Self::FL, "FL"),
+        (Self::FR, "FR"),
+        (Self::FC, "FC"),
+        (Self::LFE, "LFE"),
+        (Self::SL, "SL"),
+        (Self::SR, "SR"),
+        (Self::FLC, "FLC"),
+        (Self::FRC, "FRC"),
+        (Self::RC, "RC"),
+        (Self::RL, "RL"),
+        (Self::RR, "RR"),
+        (Self::TC, "TC"),
+        (Self::TFL, "TFL"),
+        (Self::TFC, "TFC"),
+        (Self::TFR, "TFR"),
+        (Self::TRL, "TRL"),
+        (Self::TRC, "TRC"),
+        (Self::TRR, "TRR"),
+        (Self::RLC, "RLC"),
+        (Self::RRC, "RRC"),
+        (Self::FLW, "FLW"),
+        (Self::FRW, "FRW"),
+        (Self::LFE2, "LFE2"),
+        (Self::FLH, "FLH"),
+        (Self::FCH, "FCH"),
+        (Self::FRH, "FRH"),
+        (Self::TFLC, "TFLC"),
+        (Self::TFRC, "TFRC"),
+        (Self::TSL, "TSL"),
+        (Self::TSR, "TSR"),
+        (Self::LLFE, "LLFE"),
+        (Self::RLFE, "RLFE"),
+        (Self::BC, "BC"),
+        (Self::BLC, "BLC"),
+        (Self::BRC, "BRC"),
+    ];
+
+    /// The `n`th auxiliary channel, printed as `AUX<n>`.
+    ///
+    /// Returns `None` if `n` is past the last auxiliary channel.
+    pub const fn aux(n: u32) -> Option<Self> {
+        match spa_sys::SPA_AUDIO_CHANNEL_START_Aux.checked_add(n) {
+            Some(raw) if raw <= spa_sys::SPA_AUDIO_CHANNEL_LAST_Aux => Some(Self(raw)),
+            _ => None,
+        }
+    }
+
+    /// The index of the auxiliary channel, if this is one.
+    pub fn aux_index(&self) -> Option<u32> {
+        (spa_sys::SPA_AUDIO_CHANNEL_START_Aux..=spa_sys::SPA_AUDIO_CHANNEL_LAST_Aux)
+            .contains(&self.0)
+            .then(|| self.0 - spa_sys::SPA_AUDIO_CHANNEL_START_Aux)
+    }
+
+    /// Obtain an [`AudioChannel`] from a raw `spa_audio_channel` variant.
+    pub fn from_raw(raw: spa_sys::spa_audio_channel) -> Self {
+        Self(raw)
+    }
+
+    /// Get the raw [`spa_sys::spa_audio_channel`] representing this `AudioChannel`.
+    pub fn as_raw(&self) -> spa_sys::spa_audio_channel {
+        self.0
+    }
+
+    /// The usual positions of `channels` channels, as used by PipeWire when no position is given.
+    ///
+    /// Mono, stereo, 2.1, quad, 5.0, 5.1, 6.1 and 7.1 are used up to 8 channels,
+    /// more channels are auxiliary channels.
+    /// Returns `None` if there are more channels than auxiliary channels.
+    pub fn default_layout(channels: u32) -> Option<Vec<Self>> {
+        use AudioChannel as C;
+
+        let layout = match channels {
+            1 => vec![C::MONO],
+            2 => vec![C::FL, C::FR],
+            3 => vec![C::FL, C::FR, C::LFE],
+            4 => vec![C::FL, C::FR, C::RL, C::RR],
+            5 => vec![C::FL, C::FR, C::FC, C::RL, C::RR],
+            6 => vec![C::FL, C::FR, C::FC, C::LFE, C::RL, C::RR],
+            7 => vec![C::FL, C::FR, C::FC, C::LFE, C::SL, C::SR, C::RC],
+            8 => vec![C::FL, C::FR, C::FC, C::LFE, C::SL, C::SR, C::RL, C::RR],
+            n => {
+                // check the last channel first, not to allocate for a bogus count
+                C::aux(n.saturating_sub(1))?;
+                (0..n).map(C::aux).collect::<Option<_>>()?
+            }
+        };
+        Some(layout)
+    }
+
+    /// Parse channel positions in the form of the `audio.position` property, such as `"[ FL FR ]"`.
+    ///
+    /// The brackets are optional, and the names may be separated by spaces or commas as in `"FL,FR"`.
+    pub fn parse_position(position: &str) -> Result<Vec<Self>, ParseAudioChannelError> {
+        let position = position.trim();
+        let position = position
+            .strip_prefix('[')
+            .and_then(|p| p.strip_suffix(']'))
+            .unwrap_or(position);
+
+        position
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|name| !name.is_empty())
+            .map(str::parse)
+            .collect()
+    }
+
+    /// Format channel positions in the form of the `audio.position` property, such as `"[ FL FR ]"`.
+    pub fn format_position(position: &[Self]) -> String {
+        let mut s = String::from("[ ");
+        for channel in position {
+            s.push_str(&channel.to_string());
+            s.push(' ');
+        }
+        s.push(']');
+        s
+    }
+}
+
+impl fmt::Display for AudioChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((_, name)) = Self::NAMED.iter().find(|(channel, _)| channel == self) {
+            f.write_str(name)
+        } else if let Some(n) = self.aux_index() {
+            write!(f, "AUX{n}")
+        } else {
+            write!(f, "UNK{}", self.0)
+        }
+    }
+}
+
+impl fmt::Debug for AudioChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AudioChannel::{self}")
+    }
+}
+
+/// Error returned when parsing an [`AudioChannel`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseAudioChannelError(pub String);
+
+impl fmt::Display for ParseAudioChannelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid audio channel {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseAudioChannelError {}
+
+/// Parse the short name of a channel, such as `"FL"` or `"AUX3"`.
+impl FromStr for AudioChannel {
+    type Err = ParseAudioChannelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((channel, _)) = Self::NAMED.iter().find(|(_, name)| *name == s) {
+            return Ok(*channel);
+        }
+
+        s.strip_prefix("AUX")
+            .and_then(|n| n.parse().ok())
+            .and_then(Self::aux)
+            .ok_or_else(|| ParseAudioChannelError(s.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(AudioChannel::FL.to_string(), "FL");
+        assert_eq!(AudioChannel::aux(3).unwrap().to_string(), "AUX3");
+        assert_eq!(format!("{:?}", AudioChannel::LFE), "AudioChannel::LFE");
+        assert_eq!("RR".parse(), Ok(AudioChannel::RR));
+        assert_eq!("AUX63".parse(), Ok(AudioChannel::aux(63).unwrap()));
+        assert!("XX".parse::<AudioChannel>().is_err());
+        assert!("AUX".parse::<AudioChannel>().is_err());
+        assert!("AUX4294967295".parse::<AudioChannel>().is_err());
+
+        let last = spa_sys::SPA_AUDIO_CHANNEL_LAST_Aux - spa_sys::SPA_AUDIO_CHANNEL_START_Aux;
+        assert_eq!(AudioChannel::aux(last).unwrap().aux_index(), Some(last));
+        assert_eq!(AudioChannel::aux(last + 1), None);
+        assert_eq!(AudioChannel::aux(u32::MAX), None);
+
+        for (i, (channel, name)) in AudioChannel::NAMED.iter().enumerate() {
+            assert_eq!(channel.as_raw(), i as u32);
+            assert_eq!(channel.to_string(), *name);
+            assert_eq!(name.parse(), Ok(*channel));
+        }
+    }
+
+    #[test]
+    fn position() {
+        let surround = AudioChannel::default_layout(6).unwrap();
+        assert_eq!(
+            AudioChannel::format_position(&surround),
+            "[ FL FR FC LFE RL RR ]"
+        );
+        assert_eq!(
+            AudioChannel::parse_position("[ FL FR FC LFE RL RR ]"),
+            Ok(surround)
+        );
+        assert_eq!(
+            AudioChannel::parse_position("FL,FR"),
+            Ok(vec![AudioChannel::FL, AudioChannel::FR])
+        );
+        assert_eq!(AudioChannel::parse_position("[ ]"), Ok(vec![]));
+        assert_eq!(
+            AudioChannel::parse_position("[ FL XX ]"),
+            Err(ParseAudioChannelError("XX".into()))
+        );
+
+        for channels in 0..=12 {
+            let layout = AudioChannel::default_layout(channels).unwrap();
+            assert_eq!(layout.len(), channels as usize);
+            assert_eq!(
+                AudioChannel::parse_position(&AudioChannel::format_position(&layout)),
+                Ok(layout)
+            );
+        }
+        assert_eq!(AudioChannel::default_layout(u32::MAX), None);
+    }
+
+    #[test]
+    fn info_raw_position() {
+        let mut info = crate::param::audio::AudioInfoRaw::new();
+        let stereo = AudioChannel::parse_position("[ FL FR ]").unwrap();
+        info.set_channels(2);
+        info.set_position(&stereo);
+        assert!(!info
+            .flags()
+            .contains(crate::param::audio::AudioInfoRawFlags::UNPOSITIONED));
+        assert_eq!(info.channel_map(), stereo);
+        assert_eq!(
+            AudioChannel::format_position(&info.channel_map()),
+            "[ FL FR ]"
+        );
+
+        info.set_position(&[]);
+        assert!(info
+            .flags()
+            .contains(crate::param::audio::AudioInfoRawFlags::UNPOSITIONED));
+    }
+}
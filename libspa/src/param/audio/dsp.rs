@@ -0,0 +1,146 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use crate::param::audio::AudioFormat;
+use crate::param::format::{FormatProperties, MediaSubtype, MediaType};
+use crate::param::format_utils::check_media_type;
+use crate::param::{ParamType, ParseError};
+use crate::pod::{deserialize::PodDeserializer, ObjectBuilder, OwnedPod, Pod, Property, Value};
+use crate::utils::{self, SpaTypes};
+
+/// An `audio/dsp` format, as negotiated by the mono ports of filters and DSP nodes.
+///
+/// Unlike [`AudioInfoRaw`](super::AudioInfoRaw), the format only has a sample format,
+/// usually [`AudioFormat::F32P`]: the rate is the one of the graph, and each port carries
+/// a single channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioInfoDsp {
+    pub format: AudioFormat,
+}
+
+impl AudioInfoDsp {
+    pub fn new(format: AudioFormat) -> Self {
+        Self { format }
+    }
+
+    /// Parse a format pod, such as the one received in a `param_changed` callback.
+    ///
+    /// Fails if the format is not an audio/dsp one.
+    pub fn from_pod(format: &Pod) -> Result<Self, ParseError> {
+        check_media_type(format, (MediaType::Audio, MediaSubtype::Dsp))?;
+
+        let Ok((_, Value::Object(object))) =
+            PodDeserializer::deserialize_any_from(format.as_bytes())
+        else {
+            return Err(ParseError::Malformed("invalid object"));
+        };
+
+        let mut info = Self::new(AudioFormat::Unknown);
+        for Property { key, value, .. } in object.properties {
+            match (key, value) {
+                (spa_sys::SPA_FORMAT_AUDIO_format, Value::Id(utils::Id(v))) => {
+                    info.format = AudioFormat::from_raw(v)
+                }
+                (spa_sys::SPA_FORMAT_AUDIO_format, value) => {
+                    return Err(ParseError::wrong_type(
+                        SpaTypes::ObjectParamFormat,
+                        key,
+                        SpaTypes::Id,
+                        &value,
+                    ))
+                }
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+
+    /// Build a format pod with the given `id`, such as [`ParamType::EnumFormat`]
+    /// for the params of a port.
+    pub fn to_pod(&self, id: ParamType) -> OwnedPod {
+        let mut builder = ObjectBuilder::new(SpaTypes::ObjectParamFormat, id)
+            .prop(FormatProperties::MediaType, MediaType::Audio)
+            .prop(FormatProperties::MediaSubtype, MediaSubtype::Dsp);
+        if self.format != AudioFormat::Unknown {
+            builder = builder.prop(FormatProperties::AudioFormat, self.format);
+        }
+        builder.build()
+    }
+}
+
+impl Default for AudioInfoDsp {
+    /// The `F32P` format used by the DSP ports of PipeWire.
+    fn default() -> Self {
+        Self::new(AudioFormat::F32P)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn pod() {
+        let info = AudioInfoDsp::default();
+        let pod = info.to_pod(ParamType::EnumFormat);
+        assert_eq!(AudioInfoDsp::from_pod(&pod), Ok(info));
+
+        let pod = AudioInfoDsp::new(AudioFormat::Unknown).to_pod(ParamType::Format);
+        assert_eq!(
+            AudioInfoDsp::from_pod(&pod),
+            Ok(AudioInfoDsp::new(AudioFormat::Unknown))
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn not_dsp() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::Format)
+            .prop(FormatProperties::MediaType, MediaType::Audio)
+            .prop(FormatProperties::MediaSubtype, MediaSubtype::Raw)
+            .prop(FormatProperties::AudioFormat, AudioFormat::F32LE)
+            .build();
+
+        assert_eq!(
+            AudioInfoDsp::from_pod(&pod),
+            Err(ParseError::WrongMediaType {
+                expected: (MediaType::Audio, MediaSubtype::Dsp),
+                found: (MediaType::Audio, MediaSubtype::Raw),
+            })
+        );
+
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamProps, ParamType::Props).build();
+        assert_eq!(
+            AudioInfoDsp::from_pod(&pod),
+            Err(ParseError::WrongObjectType {
+                expected: SpaTypes::ObjectParamFormat,
+                found: SpaTypes::ObjectParamProps,
+            })
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn format_choice() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+            .prop(FormatProperties::MediaType, MediaType::Audio)
+            .prop(FormatProperties::MediaSubtype, MediaSubtype::Dsp)
+            .prop_choice_enum(
+                FormatProperties::AudioFormat,
+                AudioFormat::F32P,
+                [AudioFormat::F32P, AudioFormat::S16P],
+            )
+            .build();
+
+        assert_eq!(
+            AudioInfoDsp::from_pod(&pod),
+            Err(ParseError::WrongType {
+                object_type: SpaTypes::ObjectParamFormat,
+                key: spa_sys::SPA_FORMAT_AUDIO_format,
+                expected: SpaTypes::Id,
+                found: SpaTypes::Choice,
+            })
+        );
+    }
+}
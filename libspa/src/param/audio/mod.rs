@@ -1,6 +1,10 @@
 // Copyright The pipewire-rs Contributors.
 // SPDX-License-Identifier: MIT
 
+mod channel;
+pub use channel::*;
+mod dsp;
+pub use dsp::*;
 mod raw;
 pub use raw::*;
 
@@ -1,7 +1,9 @@
 // Copyright The pipewire-rs Contributors.
 // SPDX-License-Identifier: MIT
 
-use crate::param::audio::AudioFormat;
+use crate::param::audio::{AudioChannel, AudioFormat};
+use crate::param::format::{MediaSubtype, MediaType};
+use crate::param::{format_utils::check_media_type, ParseError};
 use crate::pod::{Property, Value, ValueArray};
 use crate::utils::{
     self,
@@ -65,9 +67,16 @@ impl AudioInfoRaw {
         self.0.channels
     }
 
-    pub fn set_position(&mut self, position: [u32; 64usize]) {
-        self.0.position = position;
-        if position[0] == 0 {
+    /// Set the position of each channel, and whether the channels are [`AudioInfoRawFlags::UNPOSITIONED`].
+    ///
+    /// The number of channels is not changed, see [`set_channels`](Self::set_channels).
+    /// Positions beyond [`MAX_CHANNELS`](super::MAX_CHANNELS) are ignored.
+    pub fn set_position(&mut self, position: &[AudioChannel]) {
+        self.0.position = [0; 64usize];
+        for (raw, channel) in self.0.position.iter_mut().zip(position) {
+            *raw = channel.as_raw();
+        }
+        if self.0.position[0] == 0 {
             self.0.flags |= AudioInfoRawFlags::UNPOSITIONED.bits();
         } else {
             self.0.flags &= AudioInfoRawFlags::UNPOSITIONED.complement().bits();
@@ -78,12 +87,39 @@ impl AudioInfoRaw {
         self.0.position
     }
 
+    /// The positions of the [`channels`](Self::channels) channels.
+    pub fn channel_positions(&self) -> &[u32] {
+        let channels = (self.0.channels as usize).min(self.0.position.len());
+        &self.0.position[..channels]
+    }
+
+    /// The positions of the [`channels`](Self::channels) channels, as [`AudioChannel`]s.
+    pub fn channel_map(&self) -> Vec<AudioChannel> {
+        self.channel_positions()
+            .iter()
+            .copied()
+            .map(AudioChannel::from_raw)
+            .collect()
+    }
+
     /// helper function to parse format properties type
     pub fn parse(&mut self, format: &crate::pod::Pod) -> Result<SpaSuccess, Error> {
         let res = unsafe { spa_sys::spa_format_audio_raw_parse(format.as_raw_ptr(), &mut self.0) };
         SpaResult::from_c(res).into_result()
     }
 
+    /// Parse a format pod, such as the one received in a `param_changed` callback.
+    ///
+    /// Fails if the format is not an audio/raw one.
+    pub fn from_format_pod(format: &crate::pod::Pod) -> Result<Self, ParseError> {
+        check_media_type(format, (MediaType::Audio, MediaSubtype::Raw))?;
+
+        let mut info = Self::new();
+        info.parse(format)
+            .map_err(|_| ParseError::Malformed("invalid audio/raw format properties"))?;
+        Ok(info)
+    }
+
     /// Obtain an [`AudioInfoRaw`] from a raw `spa_audio_info_raw` variant.
     pub fn from_raw(raw: spa_sys::spa_audio_info_raw) -> Self {
         Self(raw)
@@ -0,0 +1,276 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use crate::{
+    buffer::DataType,
+    param::{error::deserialize_object, ParamType, ParseError},
+    pod::{ChoiceValue, ObjectBuilder, OwnedPod, Pod, Property, Value},
+    utils::{Choice, ChoiceEnum, ChoiceFlags, SpaTypes},
+};
+
+bitflags::bitflags! {
+    /// Mask of the [`DataType`]s allowed for buffer memory, each type `t` being encoded as `1 << t`.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct DataTypes: u32 {
+        /// Pointer to memory.
+        const MEM_PTR = 1 << spa_sys::SPA_DATA_MemPtr;
+        /// Generic fd, `mmap` to get to memory.
+        const MEM_FD = 1 << spa_sys::SPA_DATA_MemFd;
+        /// Fd to `dmabuf` memory.
+        const DMA_BUF = 1 << spa_sys::SPA_DATA_DmaBuf;
+        /// Memory is identified with an id.
+        const MEM_ID = 1 << spa_sys::SPA_DATA_MemId;
+        /// A syncobj.
+        const SYNC_OBJ = 1 << 5;
+    }
+}
+
+impl DataTypes {
+    /// Whether memory of type `data_type` is allowed.
+    pub fn allows(&self, data_type: DataType) -> bool {
+        let mask = Self::from(data_type);
+        !mask.is_empty() && self.contains(mask)
+    }
+}
+
+/// Types which cannot be encoded in the mask, such as [`DataType::Invalid`] or types above 31,
+/// are converted to an empty mask.
+impl From<DataType> for DataTypes {
+    fn from(value: DataType) -> Self {
+        match value {
+            DataType::Invalid => Self::empty(),
+            _ => Self::from_bits_retain(1u32.checked_shl(value.as_raw()).unwrap_or(0)),
+        }
+    }
+}
+
+/// Content of a [`ParamType::Buffers`] param, used to negotiate the buffers of a port.
+///
+/// Each property is either a single value, stored as [`ChoiceEnum::None`], or a choice the other side
+/// picks from. Properties left to `None` are omitted from the pod.
+///
+/// ```no_run
+/// # fn param_changed(stream: &pipewire::stream::StreamRef) {
+/// use libspa::param::{BuffersParam, DataTypes};
+///
+/// let param = BuffersParam::new()
+///     .buffers_range(8, 2, 16)
+///     .blocks(1)
+///     .size(1920 * 1080 * 4)
+///     .stride(1920 * 4)
+///     .data_type(DataTypes::MEM_PTR | DataTypes::MEM_FD);
+///
+/// stream.update_params(&[param.to_pod().as_pod()]).unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BuffersParam {
+    /// Number of buffers.
+    pub buffers: Option<ChoiceEnum<i32>>,
+    /// Number of data blocks per buffer.
+    pub blocks: Option<ChoiceEnum<i32>>,
+    /// Size of a data block memory.
+    pub size: Option<ChoiceEnum<i32>>,
+    /// Stride of data block memory.
+    pub stride: Option<ChoiceEnum<i32>>,
+    /// Alignment of data block memory.
+    pub align: Option<ChoiceEnum<i32>>,
+    /// Allowed memory types.
+    pub data_type: Option<DataTypes>,
+}
+
+impl BuffersParam {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn buffers(mut self, buffers: i32) -> Self {
+        self.buffers = Some(ChoiceEnum::None(buffers));
+        self
+    }
+
+    /// Let the other side pick the number of buffers between `min` and `max`.
+    pub fn buffers_range(mut self, default: i32, min: i32, max: i32) -> Self {
+        self.buffers = Some(ChoiceEnum::Range { default, min, max });
+        self
+    }
+
+    pub fn blocks(mut self, blocks: i32) -> Self {
+        self.blocks = Some(ChoiceEnum::None(blocks));
+        self
+    }
+
+    pub fn size(mut self, size: i32) -> Self {
+        self.size = Some(ChoiceEnum::None(size));
+        self
+    }
+
+    /// Let the other side pick the size of the data blocks between `min` and `max`.
+    pub fn size_range(mut self, default: i32, min: i32, max: i32) -> Self {
+        self.size = Some(ChoiceEnum::Range { default, min, max });
+        self
+    }
+
+    pub fn stride(mut self, stride: i32) -> Self {
+        self.stride = Some(ChoiceEnum::None(stride));
+        self
+    }
+
+    pub fn align(mut self, align: i32) -> Self {
+        self.align = Some(ChoiceEnum::None(align));
+        self
+    }
+
+    pub fn data_type(mut self, data_type: DataTypes) -> Self {
+        self.data_type = Some(data_type);
+        self
+    }
+
+    /// Parse a [`ParamType::Buffers`] param, such as the one proposed by the server.
+    pub fn from_pod(pod: &Pod) -> Result<Self, ParseError> {
+        let object = deserialize_object(pod, SpaTypes::ObjectParamBuffers)?;
+
+        fn int_choice(key: u32, value: Value) -> Result<ChoiceEnum<i32>, ParseError> {
+            match value {
+                Value::Int(value) => Ok(ChoiceEnum::None(value)),
+                Value::Choice(ChoiceValue::Int(Choice(_, choice))) => Ok(choice),
+                value => Err(ParseError::wrong_type(
+                    SpaTypes::ObjectParamBuffers,
+                    key,
+                    SpaTypes::Int,
+                    &value,
+                )),
+            }
+        }
+
+        let mut param = Self::new();
+        for Property { key, value, .. } in object.properties {
+            match key {
+                spa_sys::SPA_PARAM_BUFFERS_buffers => param.buffers = Some(int_choice(key, value)?),
+                spa_sys::SPA_PARAM_BUFFERS_blocks => param.blocks = Some(int_choice(key, value)?),
+                spa_sys::SPA_PARAM_BUFFERS_size => param.size = Some(int_choice(key, value)?),
+                spa_sys::SPA_PARAM_BUFFERS_stride => param.stride = Some(int_choice(key, value)?),
+                spa_sys::SPA_PARAM_BUFFERS_align => param.align = Some(int_choice(key, value)?),
+                spa_sys::SPA_PARAM_BUFFERS_dataType => {
+                    let mask = *int_choice(key, value)?.default_value();
+                    param.data_type = Some(DataTypes::from_bits_retain(mask as u32));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(param)
+    }
+
+    /// Build a [`ParamType::Buffers`] param, to be published with `update_params`.
+    pub fn to_pod(&self) -> OwnedPod {
+        fn int_choice(choice: &ChoiceEnum<i32>) -> Value {
+            match choice {
+                ChoiceEnum::None(value) => Value::Int(*value),
+                choice => Value::Choice(ChoiceValue::Int(Choice(
+                    ChoiceFlags::empty(),
+                    choice.clone(),
+                ))),
+            }
+        }
+
+        let mut builder = ObjectBuilder::new(SpaTypes::ObjectParamBuffers, ParamType::Buffers);
+        for (key, value) in [
+            (spa_sys::SPA_PARAM_BUFFERS_buffers, &self.buffers),
+            (spa_sys::SPA_PARAM_BUFFERS_blocks, &self.blocks),
+            (spa_sys::SPA_PARAM_BUFFERS_size, &self.size),
+            (spa_sys::SPA_PARAM_BUFFERS_stride, &self.stride),
+            (spa_sys::SPA_PARAM_BUFFERS_align, &self.align),
+        ] {
+            if let Some(value) = value {
+                builder = builder.prop(key, int_choice(value));
+            }
+        }
+        if let Some(data_type) = self.data_type {
+            // like SPA_POD_CHOICE_FLAGS_Int() in C
+            builder = builder.prop(
+                spa_sys::SPA_PARAM_BUFFERS_dataType,
+                ChoiceValue::Int(Choice(
+                    ChoiceFlags::empty(),
+                    ChoiceEnum::Flags {
+                        default: data_type.bits() as i32,
+                        flags: Vec::new(),
+                    },
+                )),
+            );
+        }
+
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_types_mask() {
+        assert_eq!(DataTypes::MEM_PTR.bits(), 1 << 1);
+        assert_eq!(DataTypes::MEM_FD.bits(), 1 << 2);
+        assert_eq!(DataTypes::DMA_BUF.bits(), 1 << 3);
+        assert_eq!(DataTypes::from(DataType::DmaBuf), DataTypes::DMA_BUF);
+
+        let mask = DataTypes::MEM_PTR | DataTypes::MEM_FD;
+        assert!(mask.allows(DataType::MemFd));
+        assert!(!mask.allows(DataType::DmaBuf));
+
+        assert_eq!(DataTypes::from(DataType::SyncObj), DataTypes::SYNC_OBJ);
+        assert_eq!(DataTypes::from(DataType::Invalid), DataTypes::empty());
+        assert_eq!(DataTypes::from(DataType::from_raw(32)), DataTypes::empty());
+        assert!(!DataTypes::all().allows(DataType::Invalid));
+        assert!(!DataTypes::all().allows(DataType::from_raw(40)));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn round_trip() {
+        let param = BuffersParam::new()
+            .buffers_range(8, 2, 16)
+            .blocks(1)
+            .size(4096)
+            .stride(64)
+            .align(16)
+            .data_type(DataTypes::MEM_PTR | DataTypes::DMA_BUF);
+
+        let pod = param.to_pod();
+        assert!(pod.is_object());
+        assert_eq!(BuffersParam::from_pod(&pod), Ok(param));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn from_other_object() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamProps, ParamType::Props)
+            .prop(spa_sys::SPA_PROP_volume, 1.0_f32)
+            .build();
+        assert_eq!(
+            BuffersParam::from_pod(&pod),
+            Err(ParseError::WrongObjectType {
+                expected: SpaTypes::ObjectParamBuffers,
+                found: SpaTypes::ObjectParamProps,
+            })
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn wrong_type() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamBuffers, ParamType::Buffers)
+            .prop(spa_sys::SPA_PARAM_BUFFERS_size, 1024_i64)
+            .build();
+        assert_eq!(
+            BuffersParam::from_pod(&pod),
+            Err(ParseError::WrongType {
+                object_type: SpaTypes::ObjectParamBuffers,
+                key: spa_sys::SPA_PARAM_BUFFERS_size,
+                expected: SpaTypes::Int,
+                found: SpaTypes::Long,
+            })
+        );
+    }
+}
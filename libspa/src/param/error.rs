@@ -0,0 +1,276 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::fmt;
+
+use crate::{
+    debug,
+    param::format::{MediaSubtype, MediaType},
+    pod::{deserialize::PodDeserializer, Object, Pod, Value},
+    utils::{result::Error, SpaTypes},
+};
+
+/// Error returned when parsing a param pod into one of the typed params of this module,
+/// such as [`Route`](super::route::Route) or [`AudioInfoRaw`](super::audio::AudioInfoRaw).
+///
+/// Types and property keys are displayed with their names from the SPA type tables,
+/// see [`debug`], falling back to their numeric values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// A mandatory property of the object is missing.
+    MissingProperty { object_type: SpaTypes, key: u32 },
+    /// A property has a value of another type than the expected one,
+    /// such as a choice where a plain value is expected.
+    WrongType {
+        object_type: SpaTypes,
+        key: u32,
+        expected: SpaTypes,
+        found: SpaTypes,
+    },
+    /// The pod is not an object of the expected type, `found` is the type of the object,
+    /// or the type of the pod if it is not an object.
+    WrongObjectType { expected: SpaTypes, found: SpaTypes },
+    /// The format has another media type or subtype than the one being parsed.
+    WrongMediaType {
+        expected: (MediaType, MediaSubtype),
+        found: (MediaType, MediaSubtype),
+    },
+    /// The pod is malformed in another way, as described by the message.
+    Malformed(&'static str),
+}
+
+impl ParseError {
+    /// Build a [`WrongType`](Self::WrongType) error for the property `key` holding `value`.
+    pub(crate) fn wrong_type(
+        object_type: SpaTypes,
+        key: u32,
+        expected: SpaTypes,
+        value: &Value,
+    ) -> Self {
+        Self::WrongType {
+            object_type,
+            key,
+            expected,
+            found: value_type(value),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingProperty { object_type, key } => write!(
+                f,
+                "missing property {} of {}",
+                PropName(*object_type, *key),
+                TypeName(*object_type)
+            ),
+            Self::WrongType {
+                object_type,
+                key,
+                expected,
+                found,
+            } => write!(
+                f,
+                "property {} of {} is a {} instead of a {}",
+                PropName(*object_type, *key),
+                TypeName(*object_type),
+                TypeName(*found),
+                TypeName(*expected)
+            ),
+            Self::WrongObjectType { expected, found } => {
+                write!(
+                    f,
+                    "expected {}, found {}",
+                    TypeName(*expected),
+                    TypeName(*found)
+                )
+            }
+            Self::WrongMediaType { expected, found } => write!(
+                f,
+                "expected format {}, found {}",
+                MediaTypeName(*expected),
+                MediaTypeName(*found)
+            ),
+            Self::Malformed(message) => write!(f, "malformed pod: {message}"),
+        }
+    }
+}
+
+/// Convert to the errno used before [`ParseError`] existed: `EINVAL` for pods of another type
+/// or format, and `EPROTO` for invalid ones.
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        match err {
+            ParseError::WrongObjectType { .. } | ParseError::WrongMediaType { .. } => {
+                Error::new(libc::EINVAL)
+            }
+            ParseError::MissingProperty { .. }
+            | ParseError::WrongType { .. }
+            | ParseError::Malformed(_) => Error::new(libc::EPROTO),
+        }
+    }
+}
+
+struct TypeName(SpaTypes);
+
+impl fmt::Display for TypeName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match debug::type_name(self.0.as_raw()) {
+            Some(name) => f.write_str(name),
+            None => write!(f, "type {}", self.0.as_raw()),
+        }
+    }
+}
+
+struct PropName(SpaTypes, u32);
+
+impl fmt::Display for PropName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match debug::object_prop_name(self.0, self.1) {
+            Some(name) => write!(f, "{name} ({})", self.1),
+            None => write!(f, "{}", self.1),
+        }
+    }
+}
+
+struct MediaTypeName((MediaType, MediaSubtype));
+
+impl fmt::Display for MediaTypeName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (media_type, media_subtype) = self.0;
+        match debug::media_type_name(media_type) {
+            Some(name) => f.write_str(name)?,
+            None => write!(f, "{}", media_type.as_raw())?,
+        }
+        match debug::media_subtype_name(media_subtype) {
+            Some(name) => write!(f, "/{name}"),
+            None => write!(f, "/{}", media_subtype.as_raw()),
+        }
+    }
+}
+
+/// The pod type a [`Value`] was deserialized from.
+fn value_type(value: &Value) -> SpaTypes {
+    match value {
+        Value::None => SpaTypes::None,
+        Value::Bool(_) => SpaTypes::Bool,
+        Value::Id(_) => SpaTypes::Id,
+        Value::Int(_) => SpaTypes::Int,
+        Value::Long(_) => SpaTypes::Long,
+        Value::Float(_) => SpaTypes::Float,
+        Value::Double(_) => SpaTypes::Double,
+        Value::String(_) => SpaTypes::String,
+        Value::Bytes(_) => SpaTypes::Bytes,
+        Value::Rectangle(_) => SpaTypes::Rectangle,
+        Value::Fraction(_) => SpaTypes::Fraction,
+        Value::Fd(_) => SpaTypes::Fd,
+        Value::ValueArray(_) => SpaTypes::Array,
+        Value::Struct(_) => SpaTypes::Struct,
+        Value::Object(_) => SpaTypes::Object,
+        Value::Choice(_) => SpaTypes::Choice,
+        Value::Pointer(_, _) => SpaTypes::Pointer,
+    }
+}
+
+/// Check that `pod` is an object of type `expected`.
+pub(crate) fn check_object_type(pod: &Pod, expected: SpaTypes) -> Result<(), ParseError> {
+    let found = match pod.as_object() {
+        Ok(object) => object.type_(),
+        Err(_) => pod.type_(),
+    };
+    if found == expected {
+        Ok(())
+    } else {
+        Err(ParseError::WrongObjectType { expected, found })
+    }
+}
+
+/// Deserialize `pod` as an object of type `expected`.
+pub(crate) fn deserialize_object(pod: &Pod, expected: SpaTypes) -> Result<Object, ParseError> {
+    check_object_type(pod, expected)?;
+    match PodDeserializer::deserialize_any_from(pod.as_bytes()) {
+        Ok((_, Value::Object(object))) => Ok(object),
+        _ => Err(ParseError::Malformed("invalid object")),
+    }
+}
+
+/// Check that a deserialized object nested in another param has type `expected`.
+pub(crate) fn check_nested_object_type(
+    object: &Object,
+    expected: SpaTypes,
+) -> Result<(), ParseError> {
+    if object.type_ == expected.as_raw() {
+        Ok(())
+    } else {
+        Err(ParseError::WrongObjectType {
+            expected,
+            found: SpaTypes::from_raw(object.type_),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn display() {
+        let err = ParseError::MissingProperty {
+            object_type: SpaTypes::ObjectParamRoute,
+            key: spa_sys::SPA_PARAM_ROUTE_name,
+        };
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "missing property name ({}) of Spa:Pod:Object:Param:Route",
+                spa_sys::SPA_PARAM_ROUTE_name
+            )
+        );
+
+        let err = ParseError::WrongType {
+            object_type: SpaTypes::ObjectParamProfile,
+            key: spa_sys::SPA_PARAM_PROFILE_index,
+            expected: SpaTypes::Int,
+            found: SpaTypes::Choice,
+        };
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "property index ({}) of Spa:Pod:Object:Param:Profile is a Spa:Pod:Choice instead of a Spa:Int",
+                spa_sys::SPA_PARAM_PROFILE_index
+            )
+        );
+
+        let err = ParseError::WrongMediaType {
+            expected: (MediaType::Audio, MediaSubtype::Dsp),
+            found: (MediaType::Audio, MediaSubtype::Raw),
+        };
+        assert_eq!(
+            err.to_string(),
+            "expected format audio/dsp, found audio/raw"
+        );
+
+        // unknown values are displayed as numbers
+        let err = ParseError::WrongObjectType {
+            expected: SpaTypes::ObjectParamRoute,
+            found: SpaTypes::from_raw(0x7fff_ffff),
+        };
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "expected Spa:Pod:Object:Param:Route, found type {}",
+                0x7fff_ffff
+            )
+        );
+
+        assert_eq!(
+            Error::from(ParseError::Malformed("truncated")),
+            Error::new(libc::EPROTO)
+        );
+        assert_eq!(Error::from(err), Error::new(libc::EINVAL));
+    }
+}
@@ -4,9 +4,16 @@
 use std::mem::MaybeUninit;
 
 use crate::{
-    param::format::{MediaSubtype, MediaType},
+    param::{
+        error::check_object_type,
+        format::{MediaSubtype, MediaType},
+        ParseError,
+    },
     pod::Pod,
-    utils::result::{Error, SpaResult},
+    utils::{
+        result::{Error, SpaResult},
+        SpaTypes,
+    },
 };
 
 /// helper function to parse format properties type
@@ -32,3 +39,23 @@ pub fn parse_format(format: &Pod) -> Result<(MediaType, MediaSubtype), Error> {
         }),
     }
 }
+
+/// Error returned when parsing a format pod into a specific format description.
+#[deprecated = "use `ParseError`, which the format parsers return"]
+pub type FormatParseError = ParseError;
+
+/// Check that `format` has the `expected` media type and subtype.
+pub(crate) fn check_media_type(
+    format: &Pod,
+    expected: (MediaType, MediaSubtype),
+) -> Result<(), ParseError> {
+    check_object_type(format, SpaTypes::ObjectParamFormat)?;
+    let found =
+        parse_format(format).map_err(|_| ParseError::Malformed("missing media type or subtype"))?;
+
+    if found == expected {
+        Ok(())
+    } else {
+        Err(ParseError::WrongMediaType { expected, found })
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::{fmt::Debug, mem::MaybeUninit};
+
+use crate::{
+    param::{error::check_object_type, ParamType, ParseError},
+    pod::{ObjectBuilder, OwnedPod, Pod},
+    utils::{result::SpaResult, Direction, Id, SpaTypes},
+};
+
+/// Rust representation of [`spa_sys::spa_latency_info`], the content of a [`ParamType::Latency`] param.
+///
+/// The direction is relative to the port the param is set on:
+/// [`Direction::Input`] is the latency of the data flowing into the graph up to the port (capture latency),
+/// [`Direction::Output`] is the latency from the port to the end of the graph (playback latency).
+/// A node forwards latencies received on its input ports to its output ports, and the other way around,
+/// after adding its own latency.
+#[repr(transparent)]
+#[derive(PartialEq, Clone, Copy)]
+pub struct Latency(spa_sys::spa_latency_info);
+
+impl Latency {
+    /// Create a new latency for `direction` with all values set to 0.
+    pub fn new(direction: Direction) -> Self {
+        Self(spa_sys::spa_latency_info {
+            direction: direction.as_raw(),
+            min_quantum: 0.0,
+            max_quantum: 0.0,
+            min_rate: 0,
+            max_rate: 0,
+            min_ns: 0,
+            max_ns: 0,
+        })
+    }
+
+    pub fn direction(&self) -> Direction {
+        Direction::from_raw(self.0.direction)
+    }
+
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.0.direction = direction.as_raw();
+    }
+
+    /// Latency expressed as a multiple of the graph quantum.
+    pub fn min_quantum(&self) -> f32 {
+        self.0.min_quantum
+    }
+
+    pub fn set_min_quantum(&mut self, min_quantum: f32) {
+        self.0.min_quantum = min_quantum;
+    }
+
+    pub fn max_quantum(&self) -> f32 {
+        self.0.max_quantum
+    }
+
+    pub fn set_max_quantum(&mut self, max_quantum: f32) {
+        self.0.max_quantum = max_quantum;
+    }
+
+    /// Latency expressed in samples of the graph rate.
+    pub fn min_rate(&self) -> u32 {
+        self.0.min_rate
+    }
+
+    pub fn set_min_rate(&mut self, min_rate: u32) {
+        self.0.min_rate = min_rate;
+    }
+
+    pub fn max_rate(&self) -> u32 {
+        self.0.max_rate
+    }
+
+    pub fn set_max_rate(&mut self, max_rate: u32) {
+        self.0.max_rate = max_rate;
+    }
+
+    /// Latency expressed in nanoseconds.
+    pub fn min_ns(&self) -> u64 {
+        self.0.min_ns
+    }
+
+    pub fn set_min_ns(&mut self, min_ns: u64) {
+        self.0.min_ns = min_ns;
+    }
+
+    pub fn max_ns(&self) -> u64 {
+        self.0.max_ns
+    }
+
+    pub fn set_max_ns(&mut self, max_ns: u64) {
+        self.0.max_ns = max_ns;
+    }
+
+    /// Parse a [`ParamType::Latency`] param.
+    ///
+    /// The direction is mandatory, all other properties default to 0 when omitted.
+    pub fn from_pod(pod: &Pod) -> Result<Self, ParseError> {
+        check_object_type(pod, SpaTypes::ObjectParamLatency)?;
+        let object = pod
+            .as_object()
+            .map_err(|_| ParseError::Malformed("invalid object"))?;
+
+        // tell apart the errors spa_latency_parse() reports with the same errno
+        let key = spa_sys::SPA_PARAM_LATENCY_direction;
+        match object.find_prop(Id(key)) {
+            None => {
+                return Err(ParseError::MissingProperty {
+                    object_type: SpaTypes::ObjectParamLatency,
+                    key,
+                })
+            }
+            Some(prop) if !prop.value().is_id() => {
+                return Err(ParseError::WrongType {
+                    object_type: SpaTypes::ObjectParamLatency,
+                    key,
+                    expected: SpaTypes::Id,
+                    found: prop.value().type_(),
+                })
+            }
+            Some(_) => {}
+        }
+
+        let mut info: MaybeUninit<spa_sys::spa_latency_info> = MaybeUninit::zeroed();
+        let res = unsafe { spa_sys::spa_latency_parse(pod.as_raw_ptr(), info.as_mut_ptr()) };
+        SpaResult::from_c(res)
+            .into_sync_result()
+            .map_err(|_| ParseError::Malformed("invalid latency properties"))?;
+        Ok(Self(unsafe { info.assume_init() }))
+    }
+
+    /// Build a [`ParamType::Latency`] param, to be published with `update_params`.
+    pub fn to_pod(&self) -> OwnedPod {
+        ObjectBuilder::new(SpaTypes::ObjectParamLatency, ParamType::Latency)
+            .prop(spa_sys::SPA_PARAM_LATENCY_direction, Id(self.0.direction.0))
+            .prop(spa_sys::SPA_PARAM_LATENCY_minQuantum, self.0.min_quantum)
+            .prop(spa_sys::SPA_PARAM_LATENCY_maxQuantum, self.0.max_quantum)
+            .prop(spa_sys::SPA_PARAM_LATENCY_minRate, self.0.min_rate as i32)
+            .prop(spa_sys::SPA_PARAM_LATENCY_maxRate, self.0.max_rate as i32)
+            .prop(spa_sys::SPA_PARAM_LATENCY_minNs, self.0.min_ns as i64)
+            .prop(spa_sys::SPA_PARAM_LATENCY_maxNs, self.0.max_ns as i64)
+            .build()
+    }
+
+    /// Obtain a [`Latency`] from a raw `spa_latency_info` variant.
+    pub fn from_raw(raw: spa_sys::spa_latency_info) -> Self {
+        Self(raw)
+    }
+
+    /// Get the raw [`spa_sys::spa_latency_info`] representing this `Latency`.
+    pub fn as_raw(&self) -> spa_sys::spa_latency_info {
+        self.0
+    }
+}
+
+impl Debug for Latency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Latency")
+            .field("direction", &self.direction())
+            .field("min_quantum", &self.min_quantum())
+            .field("max_quantum", &self.max_quantum())
+            .field("min_rate", &self.min_rate())
+            .field("max_rate", &self.max_rate())
+            .field("min_ns", &self.min_ns())
+            .field("max_ns", &self.max_ns())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn round_trip() {
+        let mut latency = Latency::new(Direction::Output);
+        latency.set_min_quantum(1.0);
+        latency.set_max_quantum(2.0);
+        latency.set_min_rate(64);
+        latency.set_max_rate(128);
+        latency.set_min_ns(1_000);
+        latency.set_max_ns(2_000);
+
+        let pod = latency.to_pod();
+        assert_eq!(Latency::from_pod(&pod), Ok(latency));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn omitted_properties() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamLatency, ParamType::Latency)
+            .prop(
+                spa_sys::SPA_PARAM_LATENCY_direction,
+                Id(Direction::Input.as_raw().0),
+            )
+            .prop(spa_sys::SPA_PARAM_LATENCY_minRate, 256)
+            .build();
+
+        let mut expected = Latency::new(Direction::Input);
+        expected.set_min_rate(256);
+        assert_eq!(Latency::from_pod(&pod), Ok(expected));
+
+        let no_direction = ObjectBuilder::new(SpaTypes::ObjectParamLatency, ParamType::Latency)
+            .prop(spa_sys::SPA_PARAM_LATENCY_minRate, 256)
+            .build();
+        assert_eq!(
+            Latency::from_pod(&no_direction),
+            Err(ParseError::MissingProperty {
+                object_type: SpaTypes::ObjectParamLatency,
+                key: spa_sys::SPA_PARAM_LATENCY_direction,
+            })
+        );
+
+        let wrong_type = ObjectBuilder::new(SpaTypes::ObjectParamLatency, ParamType::Latency)
+            .prop(spa_sys::SPA_PARAM_LATENCY_direction, 0)
+            .build();
+        assert_eq!(
+            Latency::from_pod(&wrong_type),
+            Err(ParseError::WrongType {
+                object_type: SpaTypes::ObjectParamLatency,
+                key: spa_sys::SPA_PARAM_LATENCY_direction,
+                expected: SpaTypes::Id,
+                found: SpaTypes::Int,
+            })
+        );
+    }
+}
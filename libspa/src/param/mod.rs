@@ -4,14 +4,30 @@
 //! Types for dealing with SPA parameters.
 
 pub mod audio;
+mod buffers;
+pub use buffers::*;
+mod error;
+pub use error::*;
 pub mod format;
 pub mod format_utils;
+mod latency;
+pub use latency::*;
+pub mod port_config;
+pub mod profile;
+pub mod profiler;
+pub mod props;
+pub mod route;
+#[cfg(feature = "v0_3_79")]
+pub mod tag;
 pub mod video;
 
-use std::ffi::CStr;
 use std::fmt::Debug;
 
 /// Different parameter types that can be queried
+///
+/// Any raw id can be represented, including the ones added by a newer version of SPA than the
+/// one this crate was built against: [`ParamType::from_raw`] keeps the value as-is,
+/// so [`ParamType::as_raw`] always returns it unchanged.
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub struct ParamType(pub spa_sys::spa_param_type);
 
@@ -51,6 +67,9 @@ impl ParamType {
     pub const Latency: Self = Self(spa_sys::SPA_PARAM_Latency);
     /// processing latency, a SPA_TYPE_OBJECT_ParamProcessLatency
     pub const ProcessLatency: Self = Self(spa_sys::SPA_PARAM_ProcessLatency);
+    /// tag reporting, a SPA_TYPE_OBJECT_ParamTag
+    #[cfg(feature = "v0_3_79")]
+    pub const Tag: Self = Self(spa_sys::SPA_PARAM_Tag);
 
     /// Obtain a [`ParamType`] from a raw `spa_param_type` variant.
     pub fn from_raw(raw: spa_sys::spa_param_type) -> Self {
@@ -63,26 +82,30 @@ impl ParamType {
     }
 }
 
+impl ParamType {
+    /// The short name of the type, such as `Props`, if it is known.
+    fn short_name(&self) -> Option<&'static str> {
+        crate::debug::param_type_name(*self)
+    }
+}
+
 impl Debug for ParamType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let c_str = unsafe {
-            let c_buf =
-                spa_sys::spa_debug_type_find_short_name(spa_sys::spa_type_param, self.as_raw());
-            if c_buf.is_null() {
-                return f.write_str("Unknown");
-            }
-            CStr::from_ptr(c_buf)
-        };
-        let name = format!("ParamType::{}", c_str.to_string_lossy());
-        f.write_str(&name)
+        match self.short_name() {
+            Some(name) => write!(f, "ParamType::{name}"),
+            None => write!(f, "ParamType::Unknown({})", self.as_raw()),
+        }
     }
 }
 
 bitflags::bitflags! {
     #[derive(Debug, PartialEq, Eq, Clone, Copy)]
     pub struct ParamInfoFlags: u32 {
+        /// Bit toggled by the object each time the parameter changes
         const SERIAL = 1<<0;
+        /// Parameter can be enumerated
         const READ   = 1<<1;
+        /// Parameter can be set
         const WRITE  = 1<<2;
         const READWRITE = Self::READ.bits() | Self::WRITE.bits();
     }
@@ -97,16 +120,174 @@ impl ParamInfo {
         ParamType::from_raw(self.0.id)
     }
 
+    /// Whether the parameter can be enumerated and set, such as [`ParamInfoFlags::WRITE`]
+    /// for [`ParamType::Props`] when the volume of a node can be changed.
     pub fn flags(&self) -> ParamInfoFlags {
         ParamInfoFlags::from_bits_truncate(self.0.flags)
     }
+
+    /// Obtain a [`ParamInfo`] from a raw `spa_param_info`.
+    pub fn from_raw(raw: spa_sys::spa_param_info) -> Self {
+        Self(raw)
+    }
+
+    pub fn as_raw(&self) -> &spa_sys::spa_param_info {
+        &self.0
+    }
 }
 
+/// Print the type and access of the parameter, such as `Props (rw)` or `EnumFormat (r-)`.
 impl Debug for ParamInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("ParamInfo")
-            .field("id", &self.id())
-            .field("flags", &self.flags())
-            .finish()
+        match self.id().short_name() {
+            Some(name) => f.write_str(name)?,
+            None => write!(f, "Unknown({})", self.id().as_raw())?,
+        }
+
+        let flags = self.flags();
+        let read = if flags.contains(ParamInfoFlags::READ) {
+            'r'
+        } else {
+            '-'
+        };
+        let write = if flags.contains(ParamInfoFlags::WRITE) {
+            'w'
+        } else {
+            '-'
+        };
+        write!(f, " ({read}{write})")
+    }
+}
+
+/// Serializes the param info as its type, by name if known, and its access,
+/// such as `{"id":"Props","flags":["read","write"]}`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for ParamInfo {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let flags: Vec<_> = self
+            .flags()
+            .iter_names()
+            .filter(|(_, flag)| *flag != ParamInfoFlags::SERIAL)
+            .map(|(name, _)| name.to_lowercase())
+            .collect();
+
+        let mut info = serializer.serialize_struct("ParamInfo", 2)?;
+        match self.id().short_name() {
+            Some(name) => info.serialize_field("id", name)?,
+            None => info.serialize_field("id", &self.id().as_raw())?,
+        }
+        info.serialize_field("flags", &flags)?;
+        info.end()
+    }
+}
+
+/// Whether a route or a profile can be used, e.g. if the headphones are plugged in.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct ParamAvailability(spa_sys::spa_param_availability);
+
+#[allow(non_upper_case_globals)]
+impl ParamAvailability {
+    /// The availability is not known.
+    pub const Unknown: Self = Self(spa_sys::SPA_PARAM_AVAILABILITY_unknown);
+    /// Not available.
+    pub const No: Self = Self(spa_sys::SPA_PARAM_AVAILABILITY_no);
+    /// Available.
+    pub const Yes: Self = Self(spa_sys::SPA_PARAM_AVAILABILITY_yes);
+
+    /// Obtain a [`ParamAvailability`] from a raw `spa_param_availability` variant.
+    pub fn from_raw(raw: spa_sys::spa_param_availability) -> Self {
+        Self(raw)
+    }
+
+    /// Get the raw [`spa_sys::spa_param_availability`] representing this `ParamAvailability`.
+    pub fn as_raw(&self) -> spa_sys::spa_param_availability {
+        self.0
+    }
+}
+
+impl Debug for ParamAvailability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match *self {
+            Self::Unknown => "Unknown",
+            Self::No => "No",
+            Self::Yes => "Yes",
+            _ => return write!(f, "ParamAvailability({})", self.0),
+        };
+        write!(f, "ParamAvailability::{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(id: ParamType, flags: ParamInfoFlags) -> ParamInfo {
+        let mut raw: spa_sys::spa_param_info = unsafe { std::mem::zeroed() };
+        raw.id = id.as_raw();
+        raw.flags = flags.bits();
+        ParamInfo::from_raw(raw)
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn param_info_debug() {
+        let props = info(ParamType::Props, ParamInfoFlags::READWRITE);
+        assert_eq!(props.id(), ParamType::Props);
+        assert!(props.flags().contains(ParamInfoFlags::WRITE));
+        assert_eq!(format!("{props:?}"), "Props (rw)");
+
+        let format = info(
+            ParamType::EnumFormat,
+            ParamInfoFlags::READ | ParamInfoFlags::SERIAL,
+        );
+        assert_eq!(format!("{format:?}"), "EnumFormat (r-)");
+
+        let unknown = info(ParamType::from_raw(12345), ParamInfoFlags::empty());
+        assert_eq!(format!("{unknown:?}"), "Unknown(12345) (--)");
+    }
+
+    #[test]
+    fn param_type_raw() {
+        for raw in 0..=64 {
+            assert_eq!(ParamType::from_raw(raw).as_raw(), raw);
+        }
+        assert_eq!(
+            ParamType::from_raw(spa_sys::SPA_PARAM_Props),
+            ParamType::Props
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn param_type_debug() {
+        assert_eq!(format!("{:?}", ParamType::Props), "ParamType::Props");
+        #[cfg(feature = "v0_3_79")]
+        assert_eq!(format!("{:?}", ParamType::Tag), "ParamType::Tag");
+        assert_eq!(
+            format!("{:?}", ParamType::from_raw(12345)),
+            "ParamType::Unknown(12345)"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn param_info_serde() {
+        let props = info(
+            ParamType::Props,
+            ParamInfoFlags::READWRITE | ParamInfoFlags::SERIAL,
+        );
+        assert_eq!(
+            serde_json::to_string(&props).unwrap(),
+            r#"{"id":"Props","flags":["read","write"]}"#
+        );
+
+        let unknown = info(ParamType::from_raw(12345), ParamInfoFlags::empty());
+        assert_eq!(
+            serde_json::to_string(&unknown).unwrap(),
+            r#"{"id":12345,"flags":[]}"#
+        );
     }
 }
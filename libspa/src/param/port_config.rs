@@ -0,0 +1,337 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Typed representation of the [`ParamType::PortConfig`] param.
+//!
+//! Setting this param on a node using an adapter, such as a client stream or an ALSA node,
+//! configures the ports it exposes in one direction: for example in DSP mode, with one
+//! mono `F32P` port per channel, as session managers do to link nodes together.
+//!
+//! ```no_run
+//! use libspa::param::{
+//!     audio::{AudioFormat, AudioInfoRaw},
+//!     port_config::{PortConfig, PortConfigMode},
+//! };
+//! use libspa::utils::Direction;
+//!
+//! let mut format = AudioInfoRaw::new();
+//! format.set_format(AudioFormat::F32P);
+//! format.set_rate(48000);
+//! format.set_channels(2);
+//!
+//! let config = PortConfig {
+//!     format: Some(format),
+//!     ..PortConfig::new(Direction::Input, PortConfigMode::Dsp)
+//! };
+//! let pod = config.to_pod();
+//! // node.set_param(ParamType::PortConfig, 0, &pod);
+//! ```
+
+use std::{fmt::Debug, io::Cursor};
+
+use crate::{
+    param::{
+        audio::AudioInfoRaw,
+        error::{check_nested_object_type, deserialize_object},
+        ParamType, ParseError,
+    },
+    pod::{serialize::PodSerializer, Object, ObjectBuilder, OwnedPod, Pod, Property, Value},
+    utils::{Direction, Id, SpaTypes},
+};
+
+/// How the ports of a node are configured.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct PortConfigMode(spa_sys::spa_param_port_config_mode);
+
+#[allow(non_upper_case_globals)]
+impl PortConfigMode {
+    /// No ports.
+    pub const None: Self = Self(spa_sys::SPA_PARAM_PORT_CONFIG_MODE_none);
+    /// The ports of the node are exposed as they are, without conversion.
+    pub const Passthrough: Self = Self(spa_sys::SPA_PARAM_PORT_CONFIG_MODE_passthrough);
+    /// A single port converting to and from the format of the node.
+    pub const Convert: Self = Self(spa_sys::SPA_PARAM_PORT_CONFIG_MODE_convert);
+    /// One mono port for each channel, in the DSP format.
+    pub const Dsp: Self = Self(spa_sys::SPA_PARAM_PORT_CONFIG_MODE_dsp);
+
+    /// Obtain a [`PortConfigMode`] from a raw `spa_param_port_config_mode` variant.
+    pub fn from_raw(raw: spa_sys::spa_param_port_config_mode) -> Self {
+        Self(raw)
+    }
+
+    /// Get the raw [`spa_sys::spa_param_port_config_mode`] representing this `PortConfigMode`.
+    pub fn as_raw(&self) -> spa_sys::spa_param_port_config_mode {
+        self.0
+    }
+}
+
+impl Debug for PortConfigMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match *self {
+            Self::None => "None",
+            Self::Passthrough => "Passthrough",
+            Self::Convert => "Convert",
+            Self::Dsp => "Dsp",
+            _ => return write!(f, "PortConfigMode({})", self.0),
+        };
+        write!(f, "PortConfigMode::{name}")
+    }
+}
+
+/// A [`ParamType::PortConfig`] param.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PortConfig {
+    /// The direction of the ports to configure.
+    pub direction: Direction,
+    pub mode: PortConfigMode,
+    /// Whether input ports also get monitor output ports.
+    pub monitor: bool,
+    /// Whether control ports are exposed.
+    pub control: bool,
+    /// The format of the ports, such as the channels of the ports in DSP mode.
+    pub format: Option<AudioInfoRaw>,
+}
+
+impl PortConfig {
+    /// Create a config of the ports in `direction`, without monitor or control ports and
+    /// without format.
+    pub fn new(direction: Direction, mode: PortConfigMode) -> Self {
+        Self {
+            direction,
+            mode,
+            monitor: false,
+            control: false,
+            format: None,
+        }
+    }
+
+    /// Parse a [`ParamType::PortConfig`] param.
+    ///
+    /// The direction and mode are required, the other properties are optional.
+    /// Only raw audio formats are supported.
+    pub fn from_pod(pod: &Pod) -> Result<Self, ParseError> {
+        let object = deserialize_object(pod, SpaTypes::ObjectParamPortConfig)?;
+
+        let mut direction = None;
+        let mut mode = None;
+        let mut config = Self::new(Direction::Input, PortConfigMode::None);
+        for Property { key, value, .. } in object.properties {
+            match (key, value) {
+                (spa_sys::SPA_PARAM_PORT_CONFIG_direction, Value::Id(Id(v))) => {
+                    direction = Some(Direction::from_raw(spa_sys::spa_direction(v)))
+                }
+                (spa_sys::SPA_PARAM_PORT_CONFIG_mode, Value::Id(Id(v))) => {
+                    mode = Some(PortConfigMode::from_raw(v))
+                }
+                (spa_sys::SPA_PARAM_PORT_CONFIG_monitor, Value::Bool(v)) => config.monitor = v,
+                (spa_sys::SPA_PARAM_PORT_CONFIG_control, Value::Bool(v)) => config.control = v,
+                (spa_sys::SPA_PARAM_PORT_CONFIG_format, Value::Object(format)) => {
+                    config.format = Some(parse_format(format)?)
+                }
+                (key, value) => {
+                    if let Some(expected) = prop_type(key) {
+                        return Err(ParseError::wrong_type(
+                            SpaTypes::ObjectParamPortConfig,
+                            key,
+                            expected,
+                            &value,
+                        ));
+                    }
+                }
+            }
+        }
+
+        let missing = |key| ParseError::MissingProperty {
+            object_type: SpaTypes::ObjectParamPortConfig,
+            key,
+        };
+        config.direction =
+            direction.ok_or_else(|| missing(spa_sys::SPA_PARAM_PORT_CONFIG_direction))?;
+        config.mode = mode.ok_or_else(|| missing(spa_sys::SPA_PARAM_PORT_CONFIG_mode))?;
+        Ok(config)
+    }
+
+    /// Build the [`ParamType::PortConfig`] param, to be set with `Node::set_param`.
+    ///
+    /// The format is nested as a [`ParamType::Format`] object.
+    pub fn to_pod(&self) -> OwnedPod {
+        let mut builder =
+            ObjectBuilder::new(SpaTypes::ObjectParamPortConfig, ParamType::PortConfig)
+                .prop(
+                    spa_sys::SPA_PARAM_PORT_CONFIG_direction,
+                    Id(self.direction.as_raw().0),
+                )
+                .prop(spa_sys::SPA_PARAM_PORT_CONFIG_mode, Id(self.mode.as_raw()))
+                .prop(spa_sys::SPA_PARAM_PORT_CONFIG_monitor, self.monitor)
+                .prop(spa_sys::SPA_PARAM_PORT_CONFIG_control, self.control);
+        if let Some(format) = self.format {
+            builder = builder.prop(
+                spa_sys::SPA_PARAM_PORT_CONFIG_format,
+                Value::Object(Object {
+                    type_: SpaTypes::ObjectParamFormat.as_raw(),
+                    id: ParamType::Format.as_raw(),
+                    properties: format.into(),
+                }),
+            );
+        }
+        builder.build()
+    }
+}
+
+/// Parse the nested format object with the format parser of SPA.
+/// The type of the value of the known properties.
+fn prop_type(key: u32) -> Option<SpaTypes> {
+    match key {
+        spa_sys::SPA_PARAM_PORT_CONFIG_direction | spa_sys::SPA_PARAM_PORT_CONFIG_mode => {
+            Some(SpaTypes::Id)
+        }
+        spa_sys::SPA_PARAM_PORT_CONFIG_monitor | spa_sys::SPA_PARAM_PORT_CONFIG_control => {
+            Some(SpaTypes::Bool)
+        }
+        spa_sys::SPA_PARAM_PORT_CONFIG_format => Some(SpaTypes::Object),
+        _ => None,
+    }
+}
+
+fn parse_format(format: Object) -> Result<AudioInfoRaw, ParseError> {
+    check_nested_object_type(&format, SpaTypes::ObjectParamFormat)?;
+    let (bytes, _) = PodSerializer::serialize(Cursor::new(Vec::new()), &Value::Object(format))
+        .map_err(|_| ParseError::Malformed("format cannot be serialized"))?;
+    let pod = OwnedPod::from_bytes(bytes.into_inner())
+        .ok_or(ParseError::Malformed("format cannot be serialized"))?;
+    AudioInfoRaw::from_format_pod(&pod)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::audio::{AudioChannel, AudioFormat};
+    use crate::param::format::{FormatProperties, MediaSubtype, MediaType};
+
+    fn format() -> AudioInfoRaw {
+        let mut format = AudioInfoRaw::new();
+        format.set_format(AudioFormat::F32P);
+        format.set_rate(48000);
+        format.set_channels(2);
+        format.set_position(&[AudioChannel::FL, AudioChannel::FR]);
+        format
+    }
+
+    /// The param as built by WirePlumber to put a node in DSP mode.
+    fn dsp_pod() -> OwnedPod {
+        ObjectBuilder::new(SpaTypes::ObjectParamPortConfig, ParamType::PortConfig)
+            .prop(
+                spa_sys::SPA_PARAM_PORT_CONFIG_direction,
+                Id(spa_sys::spa_direction::SPA_DIRECTION_INPUT.0),
+            )
+            .prop(
+                spa_sys::SPA_PARAM_PORT_CONFIG_mode,
+                Id(spa_sys::SPA_PARAM_PORT_CONFIG_MODE_dsp),
+            )
+            .prop(spa_sys::SPA_PARAM_PORT_CONFIG_monitor, true)
+            .prop(spa_sys::SPA_PARAM_PORT_CONFIG_control, false)
+            .prop(
+                spa_sys::SPA_PARAM_PORT_CONFIG_format,
+                Value::Object(
+                    ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::Format)
+                        .prop(
+                            spa_sys::SPA_FORMAT_mediaType,
+                            Id(spa_sys::SPA_MEDIA_TYPE_audio),
+                        )
+                        .prop(
+                            spa_sys::SPA_FORMAT_mediaSubtype,
+                            Id(spa_sys::SPA_MEDIA_SUBTYPE_raw),
+                        )
+                        .prop(
+                            spa_sys::SPA_FORMAT_AUDIO_format,
+                            Id(spa_sys::SPA_AUDIO_FORMAT_F32P),
+                        )
+                        .prop(spa_sys::SPA_FORMAT_AUDIO_rate, 48000)
+                        .prop(spa_sys::SPA_FORMAT_AUDIO_channels, 2)
+                        .prop(
+                            spa_sys::SPA_FORMAT_AUDIO_position,
+                            Value::ValueArray(crate::pod::ValueArray::Id(vec![
+                                Id(spa_sys::SPA_AUDIO_CHANNEL_FL),
+                                Id(spa_sys::SPA_AUDIO_CHANNEL_FR),
+                            ])),
+                        )
+                        .into_object(),
+                ),
+            )
+            .build()
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn from_pod() {
+        let config = PortConfig::from_pod(&dsp_pod()).unwrap();
+        assert_eq!(config.direction, Direction::Input);
+        assert_eq!(config.mode, PortConfigMode::Dsp);
+        assert!(config.monitor);
+        assert!(!config.control);
+        assert_eq!(config.format, Some(format()));
+
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamPortConfig, ParamType::PortConfig)
+            .prop(
+                spa_sys::SPA_PARAM_PORT_CONFIG_direction,
+                Id(spa_sys::spa_direction::SPA_DIRECTION_OUTPUT.0),
+            )
+            .build();
+        assert_eq!(
+            PortConfig::from_pod(&pod),
+            Err(ParseError::MissingProperty {
+                object_type: SpaTypes::ObjectParamPortConfig,
+                key: spa_sys::SPA_PARAM_PORT_CONFIG_mode,
+            })
+        );
+
+        // the format must be an audio/raw one
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamPortConfig, ParamType::PortConfig)
+            .prop(
+                spa_sys::SPA_PARAM_PORT_CONFIG_direction,
+                Id(spa_sys::spa_direction::SPA_DIRECTION_INPUT.0),
+            )
+            .prop(
+                spa_sys::SPA_PARAM_PORT_CONFIG_mode,
+                Id(spa_sys::SPA_PARAM_PORT_CONFIG_MODE_dsp),
+            )
+            .prop(
+                spa_sys::SPA_PARAM_PORT_CONFIG_format,
+                ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::Format)
+                    .prop(FormatProperties::MediaType, MediaType::Audio)
+                    .prop(FormatProperties::MediaSubtype, MediaSubtype::Dsp),
+            )
+            .build();
+        assert_eq!(
+            PortConfig::from_pod(&pod),
+            Err(ParseError::WrongMediaType {
+                expected: (MediaType::Audio, MediaSubtype::Raw),
+                found: (MediaType::Audio, MediaSubtype::Dsp),
+            })
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn to_pod() {
+        let config = PortConfig {
+            monitor: true,
+            format: Some(format()),
+            ..PortConfig::new(Direction::Input, PortConfigMode::Dsp)
+        };
+        assert_eq!(config.to_pod(), dsp_pod());
+        assert_eq!(PortConfig::from_pod(&config.to_pod()), Ok(config));
+
+        let config = PortConfig::new(Direction::Output, PortConfigMode::Passthrough);
+        assert_eq!(PortConfig::from_pod(&config.to_pod()), Ok(config));
+    }
+
+    #[test]
+    fn mode_debug() {
+        assert_eq!(format!("{:?}", PortConfigMode::Dsp), "PortConfigMode::Dsp");
+        assert_eq!(
+            format!("{:?}", PortConfigMode::from_raw(42)),
+            "PortConfigMode(42)"
+        );
+    }
+}
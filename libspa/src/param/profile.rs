@@ -0,0 +1,322 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Typed representation of the [`ParamType::Profile`] and [`ParamType::EnumProfile`] params.
+//!
+//! A profile is a configuration of a device, such as `output:analog-stereo+input:analog-stereo`
+//! or `pro-audio` for a sound card. The available profiles are enumerated as
+//! [`ParamType::EnumProfile`] params, and the active one is changed by setting a
+//! [`ParamType::Profile`] param on the device, see [`Profile::to_pod`].
+
+use crate::{
+    param::{error::deserialize_object, ParamAvailability, ParamType, ParseError},
+    pod::{ObjectBuilder, OwnedPod, Pod, Property, Value, ValueArray},
+    utils::{Id, SpaTypes},
+};
+
+/// A class of nodes created by a profile, such as `Audio/Sink`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileClass {
+    /// The media class of the nodes.
+    pub name: String,
+    /// The number of nodes of this class.
+    pub count: i32,
+    /// Indexes of the devices of the nodes, if known.
+    pub devices: Vec<i32>,
+}
+
+/// A [`ParamType::Profile`] or [`ParamType::EnumProfile`] param, as enumerated on devices.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    /// Index of the profile.
+    pub index: i32,
+    /// Name of the profile, such as `pro-audio`.
+    pub name: String,
+    /// Human readable description of the profile.
+    pub description: Option<String>,
+    /// Priority of the profile, higher is better.
+    pub priority: i32,
+    /// Whether the profile can be used.
+    pub available: ParamAvailability,
+    /// Classes of the nodes created by the profile.
+    pub classes: Vec<ProfileClass>,
+    /// Whether the profile is saved by the session manager.
+    pub save: bool,
+}
+
+impl Profile {
+    /// Parse a [`ParamType::Profile`] or [`ParamType::EnumProfile`] param.
+    ///
+    /// The index and name are required, other missing properties are left to their default.
+    pub fn from_pod(pod: &Pod) -> Result<Self, ParseError> {
+        let object = deserialize_object(pod, SpaTypes::ObjectParamProfile)?;
+
+        let mut index = None;
+        let mut name = None;
+        let mut profile = Self {
+            index: 0,
+            name: String::new(),
+            description: None,
+            priority: 0,
+            available: ParamAvailability::Unknown,
+            classes: Vec::new(),
+            save: false,
+        };
+        for Property { key, value, .. } in object.properties {
+            match (key, value) {
+                (spa_sys::SPA_PARAM_PROFILE_index, Value::Int(v)) => index = Some(v),
+                (spa_sys::SPA_PARAM_PROFILE_name, Value::String(v)) => name = Some(v),
+                (spa_sys::SPA_PARAM_PROFILE_description, Value::String(v)) => {
+                    profile.description = Some(v)
+                }
+                (spa_sys::SPA_PARAM_PROFILE_priority, Value::Int(v)) => profile.priority = v,
+                (spa_sys::SPA_PARAM_PROFILE_available, Value::Id(Id(v))) => {
+                    profile.available = ParamAvailability::from_raw(v)
+                }
+                (spa_sys::SPA_PARAM_PROFILE_classes, Value::Struct(fields)) => {
+                    profile.classes = parse_classes(fields)?
+                }
+                (spa_sys::SPA_PARAM_PROFILE_save, Value::Bool(v)) => profile.save = v,
+                (key, value) => {
+                    if let Some(expected) = prop_type(key) {
+                        return Err(ParseError::wrong_type(
+                            SpaTypes::ObjectParamProfile,
+                            key,
+                            expected,
+                            &value,
+                        ));
+                    }
+                }
+            }
+        }
+
+        let missing = |key| ParseError::MissingProperty {
+            object_type: SpaTypes::ObjectParamProfile,
+            key,
+        };
+        profile.index = index.ok_or_else(|| missing(spa_sys::SPA_PARAM_PROFILE_index))?;
+        profile.name = name.ok_or_else(|| missing(spa_sys::SPA_PARAM_PROFILE_name))?;
+        Ok(profile)
+    }
+
+    /// Build the [`ParamType::Profile`] param activating the profile `index`,
+    /// to be set with `Device::set_param`.
+    ///
+    /// If `save` is `true`, the session manager is asked to remember the profile.
+    pub fn to_pod(index: i32, save: bool) -> OwnedPod {
+        ObjectBuilder::new(SpaTypes::ObjectParamProfile, ParamType::Profile)
+            .prop(spa_sys::SPA_PARAM_PROFILE_index, index)
+            .prop(spa_sys::SPA_PARAM_PROFILE_save, save)
+            .build()
+    }
+}
+
+/// Parse the classes struct: the number of classes followed by a struct for each class,
+/// made of its name, its number of nodes and optional key/value pairs.
+/// The type of the value of the known properties.
+fn prop_type(key: u32) -> Option<SpaTypes> {
+    match key {
+        spa_sys::SPA_PARAM_PROFILE_index | spa_sys::SPA_PARAM_PROFILE_priority => {
+            Some(SpaTypes::Int)
+        }
+        spa_sys::SPA_PARAM_PROFILE_name | spa_sys::SPA_PARAM_PROFILE_description => {
+            Some(SpaTypes::String)
+        }
+        spa_sys::SPA_PARAM_PROFILE_available => Some(SpaTypes::Id),
+        spa_sys::SPA_PARAM_PROFILE_classes => Some(SpaTypes::Struct),
+        spa_sys::SPA_PARAM_PROFILE_save => Some(SpaTypes::Bool),
+        _ => None,
+    }
+}
+
+fn parse_classes(fields: Vec<Value>) -> Result<Vec<ProfileClass>, ParseError> {
+    let mut fields = fields.into_iter();
+    let Some(Value::Int(n)) = fields.next() else {
+        return Err(ParseError::Malformed("missing number of profile classes"));
+    };
+
+    // the number of classes is not trusted, it is bounded by the fields of the struct
+    let mut classes = Vec::with_capacity((n.max(0) as usize).min(fields.len()));
+    for _ in 0..n {
+        let Some(Value::Struct(class)) = fields.next() else {
+            return Err(ParseError::Malformed("missing profile class"));
+        };
+        let mut class = class.into_iter();
+        let (Some(Value::String(name)), Some(Value::Int(count))) = (class.next(), class.next())
+        else {
+            return Err(ParseError::Malformed(
+                "profile class without name and count",
+            ));
+        };
+
+        let mut devices = Vec::new();
+        while let Some(key) = class.next() {
+            match (key, class.next()) {
+                (Value::String(key), Some(Value::ValueArray(ValueArray::Int(v))))
+                    if key == "card.profile.devices" =>
+                {
+                    devices = v
+                }
+                (Value::String(_), Some(_)) => {}
+                _ => return Err(ParseError::Malformed("invalid profile class property")),
+            }
+        }
+
+        classes.push(ProfileClass {
+            name,
+            count,
+            devices,
+        });
+    }
+
+    Ok(classes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pod::deserialize::PodDeserializer;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn from_pod() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamProfile, ParamType::EnumProfile)
+            .prop(spa_sys::SPA_PARAM_PROFILE_index, 2)
+            .prop(spa_sys::SPA_PARAM_PROFILE_name, "pro-audio")
+            .prop(spa_sys::SPA_PARAM_PROFILE_description, "Pro Audio")
+            .prop(spa_sys::SPA_PARAM_PROFILE_priority, 1)
+            .prop(
+                spa_sys::SPA_PARAM_PROFILE_available,
+                Id(spa_sys::SPA_PARAM_AVAILABILITY_yes),
+            )
+            .prop(
+                spa_sys::SPA_PARAM_PROFILE_classes,
+                Value::Struct(vec![
+                    Value::Int(2),
+                    Value::Struct(vec![
+                        Value::String("Audio/Sink".into()),
+                        Value::Int(1),
+                        Value::String("card.profile.devices".into()),
+                        Value::ValueArray(ValueArray::Int(vec![0])),
+                    ]),
+                    Value::Struct(vec![Value::String("Audio/Source".into()), Value::Int(1)]),
+                ]),
+            )
+            .build();
+
+        assert_eq!(
+            Profile::from_pod(&pod),
+            Ok(Profile {
+                index: 2,
+                name: "pro-audio".into(),
+                description: Some("Pro Audio".into()),
+                priority: 1,
+                available: ParamAvailability::Yes,
+                classes: vec![
+                    ProfileClass {
+                        name: "Audio/Sink".into(),
+                        count: 1,
+                        devices: vec![0],
+                    },
+                    ProfileClass {
+                        name: "Audio/Source".into(),
+                        count: 1,
+                        devices: Vec::new(),
+                    },
+                ],
+                save: false,
+            })
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn invalid() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamProps, ParamType::Props).build();
+        assert_eq!(
+            Profile::from_pod(&pod),
+            Err(ParseError::WrongObjectType {
+                expected: SpaTypes::ObjectParamProfile,
+                found: SpaTypes::ObjectParamProps,
+            })
+        );
+
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamProfile, ParamType::Profile)
+            .prop(spa_sys::SPA_PARAM_PROFILE_index, 0)
+            .build();
+        assert_eq!(
+            Profile::from_pod(&pod),
+            Err(ParseError::MissingProperty {
+                object_type: SpaTypes::ObjectParamProfile,
+                key: spa_sys::SPA_PARAM_PROFILE_name,
+            })
+        );
+
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamProfile, ParamType::Profile)
+            .prop_choice_range(spa_sys::SPA_PARAM_PROFILE_index, 0, 0, 2)
+            .prop(spa_sys::SPA_PARAM_PROFILE_name, "off")
+            .build();
+        assert_eq!(
+            Profile::from_pod(&pod),
+            Err(ParseError::WrongType {
+                object_type: SpaTypes::ObjectParamProfile,
+                key: spa_sys::SPA_PARAM_PROFILE_index,
+                expected: SpaTypes::Int,
+                found: SpaTypes::Choice,
+            })
+        );
+
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamProfile, ParamType::Profile)
+            .prop(spa_sys::SPA_PARAM_PROFILE_index, 0)
+            .prop(spa_sys::SPA_PARAM_PROFILE_name, "off")
+            .prop(
+                spa_sys::SPA_PARAM_PROFILE_classes,
+                Value::Struct(vec![Value::Int(1)]),
+            )
+            .build();
+        assert_eq!(
+            Profile::from_pod(&pod),
+            Err(ParseError::Malformed("missing profile class"))
+        );
+    }
+
+    #[test]
+    fn classes_count() {
+        // the count is larger than the classes of the struct
+        assert_eq!(
+            parse_classes(vec![Value::Int(i32::MAX)]),
+            Err(ParseError::Malformed("missing profile class"))
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn to_pod() {
+        let pod = Profile::to_pod(2, true);
+        let Ok((_, Value::Object(object))) = PodDeserializer::deserialize_any_from(pod.as_bytes())
+        else {
+            panic!("not an object");
+        };
+        assert_eq!(object.type_, SpaTypes::ObjectParamProfile.as_raw());
+        assert_eq!(object.id, ParamType::Profile.as_raw());
+        assert_eq!(
+            object.properties,
+            vec![
+                Property::new(spa_sys::SPA_PARAM_PROFILE_index, Value::Int(2)),
+                Property::new(spa_sys::SPA_PARAM_PROFILE_save, Value::Bool(true)),
+            ]
+        );
+
+        // a set profile can be parsed back, without a description
+        let profile = Profile::from_pod(
+            &ObjectBuilder::new(SpaTypes::ObjectParamProfile, ParamType::Profile)
+                .prop(spa_sys::SPA_PARAM_PROFILE_index, 2)
+                .prop(spa_sys::SPA_PARAM_PROFILE_name, "pro-audio")
+                .prop(spa_sys::SPA_PARAM_PROFILE_save, true)
+                .build(),
+        )
+        .unwrap();
+        assert_eq!(profile.description, None);
+        assert!(profile.save);
+    }
+}
@@ -0,0 +1,334 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Typed representation of the profiler objects, as emitted by the `profile` event of the profiler.
+//!
+//! Each processing cycle of a driver is reported as a [`SpaTypes::ObjectProfiler`] object with
+//! the global info, the clock of the driver and the timings of the driver and of its followers,
+//! which is what `pw-top` displays.
+//!
+//! Newer versions of PipeWire append fields to the blocks, unknown trailing fields are ignored.
+
+use std::ffi::CStr;
+
+use nix::errno::Errno;
+
+use crate::{
+    param::{error::check_object_type, ParseError},
+    pod::{Pod, PodStruct},
+    utils::{Fraction, SpaTypes},
+};
+
+/// Global info of the profiler.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfilerInfo {
+    /// Counter of the profiler, incremented each cycle.
+    pub counter: i64,
+    /// The fast, medium and slow averages of the cpu load.
+    pub cpu_load: [f32; 3],
+    /// Total number of xruns.
+    pub xrun_count: i32,
+}
+
+/// State of the clock of a driver.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfilerClock {
+    pub flags: i32,
+    pub id: i32,
+    pub name: String,
+    /// Time of the cycle in nanoseconds.
+    pub nsec: i64,
+    pub rate: Fraction,
+    pub position: i64,
+    /// The quantum of the cycle, in samples of `rate`.
+    pub duration: i64,
+    pub delay: i64,
+    pub rate_diff: f64,
+    /// Expected time of the next cycle in nanoseconds.
+    pub next_nsec: i64,
+    /// Not reported by older versions of PipeWire.
+    pub transport_state: Option<i32>,
+    /// Not reported by older versions of PipeWire.
+    pub cycle: Option<i32>,
+    /// Not reported by older versions of PipeWire.
+    pub xrun_duration: Option<i64>,
+}
+
+/// Timings of a driver or follower node in a cycle, in nanoseconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfilerBlock {
+    /// Id of the node.
+    pub id: i32,
+    pub name: String,
+    pub prev_signal: i64,
+    /// When the node was signaled to process.
+    pub signal: i64,
+    /// When the node started processing.
+    pub awake: i64,
+    /// When the node finished processing.
+    pub finish: i64,
+    pub status: i32,
+    pub latency: Fraction,
+    /// Not reported by older versions of PipeWire.
+    pub xrun_count: Option<i32>,
+}
+
+/// A profiler object, describing a cycle of a driver.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProfilerData {
+    pub info: Option<ProfilerInfo>,
+    pub clock: Option<ProfilerClock>,
+    pub driver: Option<ProfilerBlock>,
+    pub followers: Vec<ProfilerBlock>,
+}
+
+impl ProfilerData {
+    /// Parse the pod of a `profile` event, which is a struct of profiler objects,
+    /// or a single profiler object.
+    ///
+    /// Fields of other objects are skipped.
+    pub fn from_pod(pod: &Pod) -> Result<Vec<Self>, ParseError> {
+        if let Ok(fields) = pod.as_struct() {
+            return fields
+                .fields()
+                .filter(|field| {
+                    field
+                        .as_object()
+                        .is_ok_and(|o| o.type_() == SpaTypes::ObjectProfiler)
+                })
+                .map(Self::from_object)
+                .collect();
+        }
+
+        Self::from_object(pod).map(|data| vec![data])
+    }
+
+    fn from_object(pod: &Pod) -> Result<Self, ParseError> {
+        check_object_type(pod, SpaTypes::ObjectProfiler)?;
+        let object = pod
+            .as_object()
+            .map_err(|_| ParseError::Malformed("invalid object"))?;
+
+        let mut data = Self::default();
+        for prop in object.props() {
+            let key = prop.key().0;
+            let fields = || {
+                prop.value().as_struct().map_err(|_| ParseError::WrongType {
+                    object_type: SpaTypes::ObjectProfiler,
+                    key,
+                    expected: SpaTypes::Struct,
+                    found: prop.value().type_(),
+                })
+            };
+            match key {
+                spa_sys::SPA_PROFILER_info => {
+                    let info = parse_info(fields()?)
+                        .map_err(|_| ParseError::Malformed("invalid profiler info"))?;
+                    data.info = Some(info)
+                }
+                spa_sys::SPA_PROFILER_clock => {
+                    let clock = parse_clock(fields()?)
+                        .map_err(|_| ParseError::Malformed("invalid profiler clock"))?;
+                    data.clock = Some(clock)
+                }
+                spa_sys::SPA_PROFILER_driverBlock | spa_sys::SPA_PROFILER_followerBlock => {
+                    let block = parse_block(fields()?)
+                        .map_err(|_| ParseError::Malformed("invalid profiler block"))?;
+                    if key == spa_sys::SPA_PROFILER_driverBlock {
+                        data.driver = Some(block)
+                    } else {
+                        data.followers.push(block)
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+fn get_string(fields: &PodStruct, index: usize) -> Result<String, Errno> {
+    fields
+        .field_string(index)
+        .map(CStr::to_string_lossy)
+        .map(Into::into)
+}
+
+fn parse_info(fields: &PodStruct) -> Result<ProfilerInfo, Errno> {
+    Ok(ProfilerInfo {
+        counter: fields.field_long(0)?,
+        cpu_load: [
+            fields.field_float(1)?,
+            fields.field_float(2)?,
+            fields.field_float(3)?,
+        ],
+        xrun_count: fields.field_int(4)?,
+    })
+}
+
+fn parse_clock(fields: &PodStruct) -> Result<ProfilerClock, Errno> {
+    Ok(ProfilerClock {
+        flags: fields.field_int(0)?,
+        id: fields.field_int(1)?,
+        name: get_string(fields, 2)?,
+        nsec: fields.field_long(3)?,
+        rate: fields.field_fraction(4)?,
+        position: fields.field_long(5)?,
+        duration: fields.field_long(6)?,
+        delay: fields.field_long(7)?,
+        rate_diff: fields.field_double(8)?,
+        next_nsec: fields.field_long(9)?,
+        transport_state: fields.field_int(10).ok(),
+        cycle: fields.field_int(11).ok(),
+        xrun_duration: fields.field_long(12).ok(),
+    })
+}
+
+fn parse_block(fields: &PodStruct) -> Result<ProfilerBlock, Errno> {
+    Ok(ProfilerBlock {
+        id: fields.field_int(0)?,
+        name: get_string(fields, 1)?,
+        prev_signal: fields.field_long(2)?,
+        signal: fields.field_long(3)?,
+        awake: fields.field_long(4)?,
+        finish: fields.field_long(5)?,
+        status: fields.field_int(6)?,
+        latency: fields.field_fraction(7)?,
+        xrun_count: fields.field_int(8).ok(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pod::{serialize::PodSerializer, Object, OwnedPod, Property, Value};
+
+    fn serialize(value: &Value) -> OwnedPod {
+        let (cursor, _) =
+            PodSerializer::serialize(std::io::Cursor::new(Vec::new()), value).unwrap();
+        OwnedPod::from_bytes(cursor.into_inner()).unwrap()
+    }
+
+    fn block(id: i32, name: &str, extra: &[Value]) -> Value {
+        let mut fields = vec![
+            Value::Int(id),
+            Value::String(name.to_owned()),
+            Value::Long(10),
+            Value::Long(20),
+            Value::Long(30),
+            Value::Long(40),
+            Value::Int(3),
+            Value::Fraction(Fraction {
+                num: 1024,
+                denom: 48000,
+            }),
+        ];
+        fields.extend_from_slice(extra);
+        Value::Struct(fields)
+    }
+
+    fn profiler(properties: Vec<(u32, Value)>) -> Value {
+        Value::Object(Object {
+            type_: SpaTypes::ObjectProfiler.as_raw(),
+            id: 0,
+            properties: properties
+                .into_iter()
+                .map(|(key, value)| Property::new(key, value))
+                .collect(),
+        })
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn from_pod() {
+        let clock = Value::Struct(vec![
+            Value::Int(0),
+            Value::Int(30),
+            Value::String("Dummy-Driver".to_owned()),
+            Value::Long(1000),
+            Value::Fraction(Fraction {
+                num: 1,
+                denom: 48000,
+            }),
+            Value::Long(2048),
+            Value::Long(1024),
+            Value::Long(0),
+            Value::Double(1.0),
+            Value::Long(2000),
+        ]);
+        let pod = serialize(&Value::Struct(vec![
+            Value::Int(0),
+            profiler(vec![
+                (
+                    spa_sys::SPA_PROFILER_info,
+                    Value::Struct(vec![
+                        Value::Long(7),
+                        Value::Float(0.25),
+                        Value::Float(0.5),
+                        Value::Float(0.75),
+                        Value::Int(2),
+                    ]),
+                ),
+                (spa_sys::SPA_PROFILER_clock, clock),
+                (
+                    spa_sys::SPA_PROFILER_driverBlock,
+                    block(30, "Dummy-Driver", &[]),
+                ),
+                // newer fields are read, unknown trailing fields are ignored
+                (
+                    spa_sys::SPA_PROFILER_followerBlock,
+                    block(42, "alsa_output", &[Value::Int(1), Value::Long(99)]),
+                ),
+                (spa_sys::SPA_PROFILER_followerBlock, block(43, "app", &[])),
+            ]),
+        ]));
+
+        let data = ProfilerData::from_pod(&pod).unwrap();
+        assert_eq!(data.len(), 1);
+        let data = &data[0];
+        assert_eq!(
+            data.info,
+            Some(ProfilerInfo {
+                counter: 7,
+                cpu_load: [0.25, 0.5, 0.75],
+                xrun_count: 2
+            })
+        );
+        let clock = data.clock.as_ref().unwrap();
+        assert_eq!(clock.name, "Dummy-Driver");
+        assert_eq!(clock.duration, 1024);
+        assert_eq!(clock.next_nsec, 2000);
+        assert_eq!(clock.transport_state, None);
+        assert_eq!(clock.xrun_duration, None);
+        let driver = data.driver.as_ref().unwrap();
+        assert_eq!((driver.id, driver.awake, driver.xrun_count), (30, 30, None));
+        assert_eq!(data.followers.len(), 2);
+        assert_eq!(data.followers[0].name, "alsa_output");
+        assert_eq!(data.followers[0].xrun_count, Some(1));
+        assert_eq!(data.followers[1].id, 43);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn invalid() {
+        let pod = serialize(&Value::Int(0));
+        assert_eq!(
+            ProfilerData::from_pod(&pod),
+            Err(ParseError::WrongObjectType {
+                expected: SpaTypes::ObjectProfiler,
+                found: SpaTypes::Int,
+            })
+        );
+
+        // truncated block
+        let pod = serialize(&profiler(vec![(
+            spa_sys::SPA_PROFILER_driverBlock,
+            Value::Struct(vec![Value::Int(30), Value::String("driver".to_owned())]),
+        )]));
+        assert_eq!(
+            ProfilerData::from_pod(&pod),
+            Err(ParseError::Malformed("invalid profiler block"))
+        );
+    }
+}
@@ -0,0 +1,285 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Typed representation of the [`ParamType::Props`] param.
+
+use crate::{
+    param::{
+        error::{check_nested_object_type, deserialize_object},
+        ParamType, ParseError,
+    },
+    pod::{Object, ObjectBuilder, OwnedPod, Pod, Property, Value, ValueArray},
+    utils::{Id, SpaTypes},
+};
+
+/// The common properties of a [`ParamType::Props`] param, as exposed by nodes and devices.
+///
+/// Properties that are not set are `None` and omitted from the pod.
+/// Properties without a dedicated field are kept in [`other`](Self::other), with their flags.
+/// A parsed param is serialized back in its original order, see [`order`](Self::order).
+#[derive(Debug, Clone, Default)]
+pub struct Props {
+    /// Name of the device.
+    pub device: Option<String>,
+    /// Global volume.
+    pub volume: Option<f32>,
+    /// Global mute.
+    pub mute: Option<bool>,
+    /// Volume of each channel.
+    pub channel_volumes: Option<Vec<f32>>,
+    /// Position of each channel (`spa_audio_channel` ids).
+    pub channel_map: Option<Vec<Id>>,
+    /// Mute of the monitor ports.
+    pub monitor_mute: Option<bool>,
+    /// Volume of each channel of the monitor ports.
+    pub monitor_volumes: Option<Vec<f32>>,
+    /// Software mute, applied on top of the hardware mute.
+    pub soft_mute: Option<bool>,
+    /// Software volume of each channel, applied on top of the hardware volumes.
+    pub soft_volumes: Option<Vec<f32>>,
+    /// All other properties, in their original order.
+    pub other: Vec<Property>,
+    /// The keys of the parsed properties, in the order of the pod.
+    ///
+    /// The properties are serialized in this order, followed by those whose key is not listed,
+    /// the dedicated fields first. It is ignored when comparing two `Props`.
+    pub order: Vec<u32>,
+}
+
+impl PartialEq for Props {
+    fn eq(&self, other: &Self) -> bool {
+        self.device == other.device
+            && self.volume == other.volume
+            && self.mute == other.mute
+            && self.channel_volumes == other.channel_volumes
+            && self.channel_map == other.channel_map
+            && self.monitor_mute == other.monitor_mute
+            && self.monitor_volumes == other.monitor_volumes
+            && self.soft_mute == other.soft_mute
+            && self.soft_volumes == other.soft_volumes
+            && self.other == other.other
+    }
+}
+
+impl Props {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a [`ParamType::Props`] param.
+    ///
+    /// Known properties with an unexpected type are kept in [`other`](Self::other).
+    pub fn from_pod(pod: &Pod) -> Result<Self, ParseError> {
+        Self::from_object(deserialize_object(pod, SpaTypes::ObjectParamProps)?)
+    }
+
+    /// Parse a [`ParamType::Props`] object, e.g. one nested into another param.
+    pub fn from_object(object: Object) -> Result<Self, ParseError> {
+        check_nested_object_type(&object, SpaTypes::ObjectParamProps)?;
+
+        let mut props = Self::new();
+        for Property { key, flags, value } in object.properties {
+            props.order.push(key);
+            match (key, value) {
+                (spa_sys::SPA_PROP_device, Value::String(device)) => props.device = Some(device),
+                (spa_sys::SPA_PROP_volume, Value::Float(volume)) => props.volume = Some(volume),
+                (spa_sys::SPA_PROP_mute, Value::Bool(mute)) => props.mute = Some(mute),
+                (spa_sys::SPA_PROP_channelVolumes, Value::ValueArray(ValueArray::Float(v))) => {
+                    props.channel_volumes = Some(v)
+                }
+                (spa_sys::SPA_PROP_channelMap, Value::ValueArray(ValueArray::Id(map))) => {
+                    props.channel_map = Some(map)
+                }
+                (spa_sys::SPA_PROP_monitorMute, Value::Bool(mute)) => {
+                    props.monitor_mute = Some(mute)
+                }
+                (spa_sys::SPA_PROP_monitorVolumes, Value::ValueArray(ValueArray::Float(v))) => {
+                    props.monitor_volumes = Some(v)
+                }
+                (spa_sys::SPA_PROP_softMute, Value::Bool(mute)) => props.soft_mute = Some(mute),
+                (spa_sys::SPA_PROP_softVolumes, Value::ValueArray(ValueArray::Float(v))) => {
+                    props.soft_volumes = Some(v)
+                }
+                (key, value) => props.other.push(Property { key, flags, value }),
+            }
+        }
+
+        Ok(props)
+    }
+
+    /// Build a [`ParamType::Props`] param, e.g. to be set with `Node::set_param`.
+    pub fn to_pod(&self) -> OwnedPod {
+        self.to_builder().build()
+    }
+
+    /// Build a [`ParamType::Props`] object, e.g. to be nested into another param.
+    pub fn to_object(&self) -> Object {
+        self.to_builder().into_object()
+    }
+
+    fn to_builder(&self) -> ObjectBuilder {
+        let mut properties = Vec::new();
+        let mut push = |key, value: Value| properties.push(Property::new(key, value));
+
+        if let Some(device) = &self.device {
+            push(spa_sys::SPA_PROP_device, Value::String(device.clone()));
+        }
+        if let Some(volume) = self.volume {
+            push(spa_sys::SPA_PROP_volume, Value::Float(volume));
+        }
+        if let Some(mute) = self.mute {
+            push(spa_sys::SPA_PROP_mute, Value::Bool(mute));
+        }
+        if let Some(volumes) = &self.channel_volumes {
+            push(
+                spa_sys::SPA_PROP_channelVolumes,
+                ValueArray::Float(volumes.clone()).into(),
+            );
+        }
+        if let Some(map) = &self.channel_map {
+            push(
+                spa_sys::SPA_PROP_channelMap,
+                ValueArray::Id(map.clone()).into(),
+            );
+        }
+        if let Some(mute) = self.monitor_mute {
+            push(spa_sys::SPA_PROP_monitorMute, Value::Bool(mute));
+        }
+        if let Some(volumes) = &self.monitor_volumes {
+            push(
+                spa_sys::SPA_PROP_monitorVolumes,
+                ValueArray::Float(volumes.clone()).into(),
+            );
+        }
+        if let Some(mute) = self.soft_mute {
+            push(spa_sys::SPA_PROP_softMute, Value::Bool(mute));
+        }
+        if let Some(volumes) = &self.soft_volumes {
+            push(
+                spa_sys::SPA_PROP_softVolumes,
+                ValueArray::Float(volumes.clone()).into(),
+            );
+        }
+        properties.extend(self.other.iter().cloned());
+
+        // a stable sort keeps the relative order of the properties with the same position
+        properties.sort_by_key(|property| {
+            self.order
+                .iter()
+                .position(|key| *key == property.key)
+                .unwrap_or(self.order.len())
+        });
+
+        properties.into_iter().fold(
+            ObjectBuilder::new(SpaTypes::ObjectParamProps, ParamType::Props),
+            |builder, Property { key, flags, value }| builder.prop_with_flags(key, flags, value),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pod::PropertyFlags;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn round_trip() {
+        let props = Props {
+            device: Some("hw:0".into()),
+            volume: Some(0.5),
+            mute: Some(false),
+            channel_volumes: Some(vec![0.25, 0.75]),
+            channel_map: Some(vec![
+                Id(spa_sys::SPA_AUDIO_CHANNEL_FL),
+                Id(spa_sys::SPA_AUDIO_CHANNEL_FR),
+            ]),
+            soft_mute: Some(true),
+            soft_volumes: Some(vec![1.0, 1.0]),
+            other: vec![
+                Property::new(spa_sys::SPA_PROP_START_CUSTOM + 1, Value::Int(42)),
+                Property::new(
+                    spa_sys::SPA_PROP_params,
+                    Value::Struct(vec![Value::String("foo".into())]),
+                ),
+            ],
+            ..Default::default()
+        };
+
+        let pod = props.to_pod();
+        assert_eq!(Props::from_pod(&pod), Ok(props));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn unexpected_types_are_kept() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamProps, ParamType::Props)
+            .prop(spa_sys::SPA_PROP_volume, 1.0_f64)
+            .prop(spa_sys::SPA_PROP_mute, true)
+            .build();
+
+        let props = Props::from_pod(&pod).unwrap();
+        assert_eq!(props.volume, None);
+        assert_eq!(props.mute, Some(true));
+        assert_eq!(
+            props.other,
+            vec![Property::new(spa_sys::SPA_PROP_volume, Value::Double(1.0))]
+        );
+
+        let reserialized = props.to_pod();
+        assert_eq!(Props::from_pod(&reserialized), Ok(props));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn flags_and_order_are_kept() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamProps, ParamType::Props)
+            .prop(spa_sys::SPA_PROP_START_CUSTOM + 1, 42)
+            .prop_with_flags(
+                spa_sys::SPA_PROP_START_CUSTOM + 2,
+                PropertyFlags::READONLY | PropertyFlags::HARDWARE,
+                true,
+            )
+            .prop(spa_sys::SPA_PROP_mute, false)
+            .prop(spa_sys::SPA_PROP_volume, 0.5_f32)
+            .build();
+
+        let props = Props::from_pod(&pod).unwrap();
+        assert_eq!(
+            props.other[1].flags,
+            PropertyFlags::READONLY | PropertyFlags::HARDWARE
+        );
+        assert_eq!(props.to_pod().as_bytes(), pod.as_bytes());
+
+        // properties added afterwards come last
+        let props = Props {
+            soft_mute: Some(true),
+            ..props
+        };
+        let expected = ObjectBuilder::new(SpaTypes::ObjectParamProps, ParamType::Props)
+            .prop(spa_sys::SPA_PROP_START_CUSTOM + 1, 42)
+            .prop_with_flags(
+                spa_sys::SPA_PROP_START_CUSTOM + 2,
+                PropertyFlags::READONLY | PropertyFlags::HARDWARE,
+                true,
+            )
+            .prop(spa_sys::SPA_PROP_mute, false)
+            .prop(spa_sys::SPA_PROP_volume, 0.5_f32)
+            .prop(spa_sys::SPA_PROP_softMute, true)
+            .build();
+        assert_eq!(props.to_pod().as_bytes(), expected.as_bytes());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn from_other_object() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::Format).build();
+        assert_eq!(
+            Props::from_pod(&pod),
+            Err(ParseError::WrongObjectType {
+                expected: SpaTypes::ObjectParamProps,
+                found: SpaTypes::ObjectParamFormat,
+            })
+        );
+    }
+}
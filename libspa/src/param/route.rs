@@ -0,0 +1,355 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Typed representation of the [`ParamType::Route`] and [`ParamType::EnumRoute`] params.
+//!
+//! A route is a destination of a device, such as the speakers or the headphones of a sound card.
+//! The volume of an active route is changed by setting a [`ParamType::Route`] param on the device,
+//! see [`Route::to_set_pod`].
+
+use crate::{
+    param::{error::deserialize_object, props::Props, ParamAvailability, ParamType, ParseError},
+    pod::{ObjectBuilder, OwnedPod, Pod, Property, Value, ValueArray},
+    utils::{Direction, Id, SpaTypes},
+};
+
+/// A [`ParamType::Route`] or [`ParamType::EnumRoute`] param, as enumerated on devices.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    /// Index of the route.
+    pub index: i32,
+    /// Direction of the route.
+    pub direction: Direction,
+    /// Index of the device the route is active on, only set on [`ParamType::Route`].
+    pub device: Option<i32>,
+    /// Name of the route, such as `analog-output-headphones`.
+    pub name: String,
+    /// Human readable description of the route.
+    pub description: Option<String>,
+    /// Priority of the route, higher is better.
+    pub priority: i32,
+    /// Whether the route can be used.
+    pub available: ParamAvailability,
+    /// Extra information, such as the port type, as key/value pairs.
+    pub info: Vec<(String, String)>,
+    /// Indexes of the profiles the route is available in.
+    pub profiles: Vec<i32>,
+    /// Indexes of the devices the route is available on, only set on [`ParamType::EnumRoute`].
+    pub devices: Vec<i32>,
+    /// Properties of the route, such as its volume, only set on [`ParamType::Route`].
+    pub props: Option<Props>,
+    /// Whether the route is saved by the session manager.
+    pub save: bool,
+}
+
+impl Route {
+    /// Parse a [`ParamType::Route`] or [`ParamType::EnumRoute`] param.
+    ///
+    /// The index, direction and name are required, other missing properties are left to their default.
+    pub fn from_pod(pod: &Pod) -> Result<Self, ParseError> {
+        let object = deserialize_object(pod, SpaTypes::ObjectParamRoute)?;
+
+        let mut index = None;
+        let mut direction = None;
+        let mut name = None;
+        let mut route = Self {
+            index: 0,
+            direction: Direction::Input,
+            device: None,
+            name: String::new(),
+            description: None,
+            priority: 0,
+            available: ParamAvailability::Unknown,
+            info: Vec::new(),
+            profiles: Vec::new(),
+            devices: Vec::new(),
+            props: None,
+            save: false,
+        };
+        for Property { key, value, .. } in object.properties {
+            match (key, value) {
+                (spa_sys::SPA_PARAM_ROUTE_index, Value::Int(v)) => index = Some(v),
+                (spa_sys::SPA_PARAM_ROUTE_direction, Value::Id(Id(v))) => {
+                    direction = Some(Direction::from_raw(spa_sys::spa_direction(v)))
+                }
+                (spa_sys::SPA_PARAM_ROUTE_device, Value::Int(v)) => route.device = Some(v),
+                (spa_sys::SPA_PARAM_ROUTE_name, Value::String(v)) => name = Some(v),
+                (spa_sys::SPA_PARAM_ROUTE_description, Value::String(v)) => {
+                    route.description = Some(v)
+                }
+                (spa_sys::SPA_PARAM_ROUTE_priority, Value::Int(v)) => route.priority = v,
+                (spa_sys::SPA_PARAM_ROUTE_available, Value::Id(Id(v))) => {
+                    route.available = ParamAvailability::from_raw(v)
+                }
+                (spa_sys::SPA_PARAM_ROUTE_info, Value::Struct(fields)) => {
+                    route.info = parse_info(fields)?
+                }
+                (spa_sys::SPA_PARAM_ROUTE_profiles, Value::ValueArray(ValueArray::Int(v))) => {
+                    route.profiles = v
+                }
+                (spa_sys::SPA_PARAM_ROUTE_devices, Value::ValueArray(ValueArray::Int(v))) => {
+                    route.devices = v
+                }
+                (spa_sys::SPA_PARAM_ROUTE_props, Value::Object(props)) => {
+                    route.props = Some(Props::from_object(props)?)
+                }
+                (spa_sys::SPA_PARAM_ROUTE_save, Value::Bool(v)) => route.save = v,
+                (key, value) => {
+                    if let Some(expected) = prop_type(key) {
+                        return Err(ParseError::wrong_type(
+                            SpaTypes::ObjectParamRoute,
+                            key,
+                            expected,
+                            &value,
+                        ));
+                    }
+                }
+            }
+        }
+
+        let missing = |key| ParseError::MissingProperty {
+            object_type: SpaTypes::ObjectParamRoute,
+            key,
+        };
+        route.index = index.ok_or_else(|| missing(spa_sys::SPA_PARAM_ROUTE_index))?;
+        route.direction = direction.ok_or_else(|| missing(spa_sys::SPA_PARAM_ROUTE_direction))?;
+        route.name = name.ok_or_else(|| missing(spa_sys::SPA_PARAM_ROUTE_name))?;
+        Ok(route)
+    }
+
+    /// Build the [`ParamType::Route`] param changing the properties of the route `index`
+    /// active on the device `device`, to be set with `Device::set_param`.
+    ///
+    /// Only the properties set in `props` are changed.
+    /// If `save` is `true`, the session manager is asked to remember the new properties.
+    pub fn to_set_pod(index: i32, device: i32, props: &Props, save: bool) -> OwnedPod {
+        ObjectBuilder::new(SpaTypes::ObjectParamRoute, ParamType::Route)
+            .prop(spa_sys::SPA_PARAM_ROUTE_index, index)
+            .prop(spa_sys::SPA_PARAM_ROUTE_device, device)
+            .prop(spa_sys::SPA_PARAM_ROUTE_props, props.to_object())
+            .prop(spa_sys::SPA_PARAM_ROUTE_save, save)
+            .build()
+    }
+}
+
+/// Parse the info struct: the number of pairs followed by the keys and values.
+/// The type of the value of the known properties.
+fn prop_type(key: u32) -> Option<SpaTypes> {
+    match key {
+        spa_sys::SPA_PARAM_ROUTE_index
+        | spa_sys::SPA_PARAM_ROUTE_device
+        | spa_sys::SPA_PARAM_ROUTE_priority => Some(SpaTypes::Int),
+        spa_sys::SPA_PARAM_ROUTE_direction | spa_sys::SPA_PARAM_ROUTE_available => {
+            Some(SpaTypes::Id)
+        }
+        spa_sys::SPA_PARAM_ROUTE_name | spa_sys::SPA_PARAM_ROUTE_description => {
+            Some(SpaTypes::String)
+        }
+        spa_sys::SPA_PARAM_ROUTE_info => Some(SpaTypes::Struct),
+        spa_sys::SPA_PARAM_ROUTE_profiles | spa_sys::SPA_PARAM_ROUTE_devices => {
+            Some(SpaTypes::Array)
+        }
+        spa_sys::SPA_PARAM_ROUTE_props => Some(SpaTypes::Object),
+        spa_sys::SPA_PARAM_ROUTE_save => Some(SpaTypes::Bool),
+        _ => None,
+    }
+}
+
+fn parse_info(fields: Vec<Value>) -> Result<Vec<(String, String)>, ParseError> {
+    let mut fields = fields.into_iter();
+    let Some(Value::Int(n)) = fields.next() else {
+        return Err(ParseError::Malformed("missing number of info items"));
+    };
+
+    // the number of items is not trusted, it is bounded by the fields of the struct
+    let mut info = Vec::with_capacity((n.max(0) as usize).min(fields.len() / 2));
+    for _ in 0..n {
+        match (fields.next(), fields.next()) {
+            (Some(Value::String(key)), Some(Value::String(value))) => info.push((key, value)),
+            _ => return Err(ParseError::Malformed("info item is not a pair of strings")),
+        }
+    }
+
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pod::deserialize::PodDeserializer;
+
+    fn headphones() -> ObjectBuilder {
+        ObjectBuilder::new(SpaTypes::ObjectParamRoute, ParamType::Route)
+            .prop(spa_sys::SPA_PARAM_ROUTE_index, 3)
+            .prop(
+                spa_sys::SPA_PARAM_ROUTE_direction,
+                Id(spa_sys::spa_direction::SPA_DIRECTION_OUTPUT.0),
+            )
+            .prop(spa_sys::SPA_PARAM_ROUTE_device, 4)
+            .prop(spa_sys::SPA_PARAM_ROUTE_name, "analog-output-headphones")
+            .prop(spa_sys::SPA_PARAM_ROUTE_description, "Headphones")
+            .prop(spa_sys::SPA_PARAM_ROUTE_priority, 9900)
+            .prop(
+                spa_sys::SPA_PARAM_ROUTE_available,
+                Id(spa_sys::SPA_PARAM_AVAILABILITY_yes),
+            )
+            .prop(
+                spa_sys::SPA_PARAM_ROUTE_info,
+                Value::Struct(vec![
+                    Value::Int(1),
+                    Value::String("port.type".into()),
+                    Value::String("headphones".into()),
+                ]),
+            )
+            .prop(
+                spa_sys::SPA_PARAM_ROUTE_profiles,
+                ValueArray::Int(vec![1, 2]),
+            )
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn from_pod() {
+        let props = Props {
+            mute: Some(false),
+            channel_volumes: Some(vec![0.5, 0.5]),
+            ..Default::default()
+        };
+        let pod = headphones()
+            .prop(spa_sys::SPA_PARAM_ROUTE_props, props.to_object())
+            .prop(spa_sys::SPA_PARAM_ROUTE_save, true)
+            .build();
+
+        let route = Route::from_pod(&pod).unwrap();
+        assert_eq!(
+            route,
+            Route {
+                index: 3,
+                direction: Direction::Output,
+                device: Some(4),
+                name: "analog-output-headphones".into(),
+                description: Some("Headphones".into()),
+                priority: 9900,
+                available: ParamAvailability::Yes,
+                info: vec![("port.type".into(), "headphones".into())],
+                profiles: vec![1, 2],
+                devices: Vec::new(),
+                props: Some(props),
+                save: true,
+            }
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn invalid() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamProps, ParamType::Props).build();
+        assert_eq!(
+            Route::from_pod(&pod),
+            Err(ParseError::WrongObjectType {
+                expected: SpaTypes::ObjectParamRoute,
+                found: SpaTypes::ObjectParamProps,
+            })
+        );
+
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamRoute, ParamType::Route)
+            .prop(spa_sys::SPA_PARAM_ROUTE_index, 0)
+            .build();
+        assert_eq!(
+            Route::from_pod(&pod),
+            Err(ParseError::MissingProperty {
+                object_type: SpaTypes::ObjectParamRoute,
+                key: spa_sys::SPA_PARAM_ROUTE_direction,
+            })
+        );
+
+        let pod = headphones().prop(spa_sys::SPA_PARAM_ROUTE_save, 1).build();
+        assert_eq!(
+            Route::from_pod(&pod),
+            Err(ParseError::WrongType {
+                object_type: SpaTypes::ObjectParamRoute,
+                key: spa_sys::SPA_PARAM_ROUTE_save,
+                expected: SpaTypes::Bool,
+                found: SpaTypes::Int,
+            })
+        );
+
+        // the nested props must be a props object
+        let pod = headphones()
+            .prop(
+                spa_sys::SPA_PARAM_ROUTE_props,
+                ObjectBuilder::new(SpaTypes::ObjectParamRoute, ParamType::Route),
+            )
+            .build();
+        assert_eq!(
+            Route::from_pod(&pod),
+            Err(ParseError::WrongObjectType {
+                expected: SpaTypes::ObjectParamProps,
+                found: SpaTypes::ObjectParamRoute,
+            })
+        );
+
+        // not a pod of the expected type at all
+        let pod = crate::pod::StructBuilder::new().int(0).build();
+        assert_eq!(
+            Route::from_pod(&pod),
+            Err(ParseError::WrongObjectType {
+                expected: SpaTypes::ObjectParamRoute,
+                found: SpaTypes::Struct,
+            })
+        );
+
+        let pod = headphones()
+            .prop(
+                spa_sys::SPA_PARAM_ROUTE_info,
+                Value::Struct(vec![Value::Int(1), Value::String("port.type".into())]),
+            )
+            .build();
+        assert_eq!(
+            Route::from_pod(&pod),
+            Err(ParseError::Malformed("info item is not a pair of strings"))
+        );
+    }
+
+    #[test]
+    fn info_count() {
+        // the count is larger than the items of the struct
+        assert_eq!(
+            parse_info(vec![
+                Value::Int(i32::MAX),
+                Value::String("port.type".into()),
+                Value::String("headphones".into()),
+            ]),
+            Err(ParseError::Malformed("info item is not a pair of strings"))
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn to_set_pod() {
+        let props = Props {
+            channel_volumes: Some(vec![0.75, 0.75]),
+            ..Default::default()
+        };
+        let pod = Route::to_set_pod(3, 4, &props, true);
+
+        let Ok((_, Value::Object(object))) = PodDeserializer::deserialize_any_from(pod.as_bytes())
+        else {
+            panic!("not an object");
+        };
+        assert_eq!(object.type_, SpaTypes::ObjectParamRoute.as_raw());
+        assert_eq!(object.id, ParamType::Route.as_raw());
+        assert_eq!(
+            object.properties,
+            vec![
+                Property::new(spa_sys::SPA_PARAM_ROUTE_index, Value::Int(3)),
+                Property::new(spa_sys::SPA_PARAM_ROUTE_device, Value::Int(4)),
+                Property::new(
+                    spa_sys::SPA_PARAM_ROUTE_props,
+                    Value::Object(props.to_object())
+                ),
+                Property::new(spa_sys::SPA_PARAM_ROUTE_save, Value::Bool(true)),
+            ]
+        );
+    }
+}
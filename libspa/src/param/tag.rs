@@ -0,0 +1,223 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Typed representation of the [`ParamType::Tag`] param.
+//!
+//! Tags carry metadata about the media of a stream, such as its title or artist,
+//! and are propagated through the graph from node to node.
+//! A stream publishes its tags by passing [`Tag::to_pod`] to `Stream::update_params`,
+//! and the tags of the upstream nodes arrive in the `param_changed` callback,
+//! where they can be parsed with [`Tag::from_pod`].
+
+use std::collections::BTreeMap;
+
+use crate::{
+    param::{error::check_object_type, ParamType, ParseError},
+    pod::{ObjectBuilder, OwnedPod, Pod, PodStruct, PropertyFlags, Value},
+    utils::{Direction, Id, SpaTypes},
+};
+
+/// A [`ParamType::Tag`] param.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag {
+    /// Whether the tags come from the input or go to the output of the node.
+    pub direction: Direction,
+    /// The tags, as a list of dictionaries such as `{ "media.title": "..." }`.
+    pub info: Vec<BTreeMap<String, String>>,
+}
+
+impl Tag {
+    /// Create a tag param without any dictionary.
+    pub fn new(direction: Direction) -> Self {
+        Self {
+            direction,
+            info: Vec::new(),
+        }
+    }
+
+    /// Parse a [`ParamType::Tag`] param.
+    ///
+    /// The direction is required. Keys and values which are not valid UTF-8 are converted lossily.
+    pub fn from_pod(pod: &Pod) -> Result<Self, ParseError> {
+        check_object_type(pod, SpaTypes::ObjectParamTag)?;
+        let object = pod
+            .as_object()
+            .map_err(|_| ParseError::Malformed("invalid object"))?;
+
+        let wrong_type = |key, expected, value: &Pod| ParseError::WrongType {
+            object_type: SpaTypes::ObjectParamTag,
+            key,
+            expected,
+            found: value.type_(),
+        };
+
+        let mut direction = None;
+        let mut info = Vec::new();
+        for prop in object.props() {
+            let key = prop.key().0;
+            match key {
+                spa_sys::SPA_PARAM_TAG_direction => {
+                    let Id(v) = prop
+                        .value()
+                        .get_id()
+                        .map_err(|_| wrong_type(key, SpaTypes::Id, prop.value()))?;
+                    direction = Some(Direction::from_raw(spa_sys::spa_direction(v)));
+                }
+                spa_sys::SPA_PARAM_TAG_info => {
+                    let fields = prop
+                        .value()
+                        .as_struct()
+                        .map_err(|_| wrong_type(key, SpaTypes::Struct, prop.value()))?;
+                    info.push(parse_dict(fields)?)
+                }
+                _ => {}
+            }
+        }
+
+        let direction = direction.ok_or(ParseError::MissingProperty {
+            object_type: SpaTypes::ObjectParamTag,
+            key: spa_sys::SPA_PARAM_TAG_direction,
+        })?;
+        Ok(Self { direction, info })
+    }
+
+    /// Build the [`ParamType::Tag`] param, with one info property per dictionary
+    /// like the `spa_tag_build_add_dict()` C helper does.
+    pub fn to_pod(&self) -> OwnedPod {
+        let mut builder = ObjectBuilder::new(SpaTypes::ObjectParamTag, ParamType::Tag).prop(
+            spa_sys::SPA_PARAM_TAG_direction,
+            Id(self.direction.as_raw().0),
+        );
+
+        for dict in &self.info {
+            let mut fields = Vec::with_capacity(1 + 2 * dict.len());
+            fields.push(Value::Int(dict.len() as i32));
+            for (key, value) in dict {
+                fields.push(Value::String(key.clone()));
+                fields.push(Value::String(value.clone()));
+            }
+            builder = builder.prop_with_flags(
+                spa_sys::SPA_PARAM_TAG_info,
+                PropertyFlags::HINT_DICT,
+                Value::Struct(fields),
+            );
+        }
+
+        builder.build()
+    }
+}
+
+/// Parse a dictionary struct: the number of items followed by the key and value of each item.
+fn parse_dict(fields: &PodStruct) -> Result<BTreeMap<String, String>, ParseError> {
+    let n = fields
+        .field_int(0)
+        .map_err(|_| ParseError::Malformed("missing number of dictionary items"))?;
+
+    let mut items = fields.fields().skip(1);
+    let mut string = || {
+        let item = items
+            .next()
+            .ok_or(ParseError::Malformed("truncated dictionary"))?;
+        item.get_string()
+            .map(|s| s.to_string_lossy().into_owned())
+            .map_err(|_| ParseError::Malformed("dictionary item is not a string"))
+    };
+
+    let mut dict = BTreeMap::new();
+    for _ in 0..n {
+        let key = string()?;
+        let value = string()?;
+        dict.insert(key, value);
+    }
+
+    Ok(dict)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag() -> Tag {
+        Tag {
+            direction: Direction::Output,
+            info: vec![BTreeMap::from([
+                ("media.title".to_owned(), "Song".to_owned()),
+                ("media.artist".to_owned(), "Artist".to_owned()),
+            ])],
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn roundtrip() {
+        let tag = tag();
+        assert_eq!(Tag::from_pod(&tag.to_pod()), Ok(tag));
+
+        let empty = Tag::new(Direction::Input);
+        assert_eq!(Tag::from_pod(&empty.to_pod()), Ok(empty));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn non_utf8() {
+        let mut tag = tag();
+        tag.info[0].insert("media.album".to_owned(), "MARKER".to_owned());
+        let mut bytes = tag.to_pod().into_inner();
+        let marker = bytes.windows(6).position(|w| w == b"MARKER").unwrap();
+        bytes[marker] = 0xff;
+
+        let parsed = Tag::from_pod(Pod::from_bytes(&bytes).unwrap()).unwrap();
+        assert_eq!(parsed.info[0]["media.album"], "\u{fffd}ARKER");
+        assert_eq!(parsed.info[0]["media.title"], "Song");
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn invalid() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamProps, ParamType::Props).build();
+        assert_eq!(
+            Tag::from_pod(&pod),
+            Err(ParseError::WrongObjectType {
+                expected: SpaTypes::ObjectParamTag,
+                found: SpaTypes::ObjectParamProps,
+            })
+        );
+
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamTag, ParamType::Tag).build();
+        assert_eq!(
+            Tag::from_pod(&pod),
+            Err(ParseError::MissingProperty {
+                object_type: SpaTypes::ObjectParamTag,
+                key: spa_sys::SPA_PARAM_TAG_direction,
+            })
+        );
+
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamTag, ParamType::Tag)
+            .prop(spa_sys::SPA_PARAM_TAG_direction, 1)
+            .build();
+        assert_eq!(
+            Tag::from_pod(&pod),
+            Err(ParseError::WrongType {
+                object_type: SpaTypes::ObjectParamTag,
+                key: spa_sys::SPA_PARAM_TAG_direction,
+                expected: SpaTypes::Id,
+                found: SpaTypes::Int,
+            })
+        );
+
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamTag, ParamType::Tag)
+            .prop(
+                spa_sys::SPA_PARAM_TAG_direction,
+                Id(spa_sys::spa_direction::SPA_DIRECTION_OUTPUT.0),
+            )
+            .prop(
+                spa_sys::SPA_PARAM_TAG_info,
+                Value::Struct(vec![Value::Int(1), Value::String("media.title".into())]),
+            )
+            .build();
+        assert_eq!(
+            Tag::from_pod(&pod),
+            Err(ParseError::Malformed("truncated dictionary"))
+        );
+    }
+}
@@ -0,0 +1,300 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::io::Cursor;
+
+use crate::{
+    param::{
+        format::{FormatProperties, MediaSubtype, MediaType},
+        format_utils::check_media_type,
+        video::VideoFormat,
+        ParamType, ParseError,
+    },
+    pod::{
+        deserialize::PodDeserializer, serialize::PodSerializer, ChoiceValue, ObjectBuilder,
+        OwnedPod, Pod, Property, PropertyFlags, Value,
+    },
+    utils::{Choice, ChoiceEnum, ChoiceFlags, Fraction, Rectangle, SpaTypes},
+};
+
+/// Flags of the modifier property in formats proposed for DMA-BUF negotiation.
+fn modifier_flags() -> PropertyFlags {
+    PropertyFlags::MANDATORY | PropertyFlags::DONT_FIXATE
+}
+
+/// Builder for video/raw `EnumFormat` params, including DMA-BUF modifier negotiation.
+///
+/// One pod is built for each format added with [`format`](Self::format), in the order they were added.
+/// Formats with modifiers get a modifier choice property with the `MANDATORY` and `DONT_FIXATE` flags
+/// so the server only picks DMA-BUF compatible formats and lets the client fixate the modifier,
+/// see [`fixate_video_format`].
+/// Formats without modifiers are negotiated for shared memory buffers.
+///
+/// ```
+/// use libspa::{
+///     param::video::{VideoEnumFormatBuilder, VideoFormat},
+///     utils::{Fraction, Rectangle},
+/// };
+///
+/// const DRM_FORMAT_MOD_LINEAR: u64 = 0;
+///
+/// let params = VideoEnumFormatBuilder::new()
+///     .format(VideoFormat::BGRx, &[DRM_FORMAT_MOD_LINEAR])
+///     .format(VideoFormat::BGRx, &[])
+///     .size(
+///         Rectangle { width: 1920, height: 1080 },
+///         Rectangle { width: 1, height: 1 },
+///         Rectangle { width: 8192, height: 8192 },
+///     )
+///     .framerate(
+///         Fraction { num: 60, denom: 1 },
+///         Fraction { num: 0, denom: 1 },
+///         Fraction { num: 360, denom: 1 },
+///     )
+///     .build();
+///
+/// assert_eq!(params.len(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct VideoEnumFormatBuilder {
+    formats: Vec<(VideoFormat, Vec<u64>)>,
+    size: Option<[Rectangle; 3]>,
+    framerate: Option<[Fraction; 3]>,
+    max_framerate: Option<[Fraction; 3]>,
+}
+
+impl VideoEnumFormatBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a format, with the DRM modifiers supported for it or an empty slice for shared memory buffers.
+    ///
+    /// The first modifier is the preferred one.
+    pub fn format(mut self, format: VideoFormat, modifiers: &[u64]) -> Self {
+        self.formats.push((format, modifiers.to_vec()));
+        self
+    }
+
+    /// Set the range of supported sizes.
+    pub fn size(mut self, default: Rectangle, min: Rectangle, max: Rectangle) -> Self {
+        self.size = Some([default, min, max]);
+        self
+    }
+
+    /// Set the range of supported frame rates.
+    pub fn framerate(mut self, default: Fraction, min: Fraction, max: Fraction) -> Self {
+        self.framerate = Some([default, min, max]);
+        self
+    }
+
+    /// Set the range of supported maximum frame rates, for variable frame rate streams.
+    pub fn max_framerate(mut self, default: Fraction, min: Fraction, max: Fraction) -> Self {
+        self.max_framerate = Some([default, min, max]);
+        self
+    }
+
+    /// Build one `EnumFormat` pod per added format.
+    pub fn build(&self) -> Vec<OwnedPod> {
+        self.formats
+            .iter()
+            .map(|(format, modifiers)| {
+                let mut builder =
+                    ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+                        .prop(FormatProperties::MediaType, MediaType::Video)
+                        .prop(FormatProperties::MediaSubtype, MediaSubtype::Raw)
+                        .prop(FormatProperties::VideoFormat, *format);
+
+                if let Some((first, _)) = modifiers.split_first() {
+                    let choice = Choice(
+                        ChoiceFlags::empty(),
+                        ChoiceEnum::Enum {
+                            default: *first as i64,
+                            alternatives: modifiers.iter().map(|m| *m as i64).collect(),
+                        },
+                    );
+                    builder = builder.prop_with_flags(
+                        FormatProperties::VideoModifier,
+                        modifier_flags(),
+                        ChoiceValue::Long(choice),
+                    );
+                }
+
+                if let Some([default, min, max]) = self.size {
+                    builder =
+                        builder.prop_choice_range(FormatProperties::VideoSize, default, min, max);
+                }
+                if let Some([default, min, max]) = self.framerate {
+                    builder = builder.prop_choice_range(
+                        FormatProperties::VideoFramerate,
+                        default,
+                        min,
+                        max,
+                    );
+                }
+                if let Some([default, min, max]) = self.max_framerate {
+                    builder = builder.prop_choice_range(
+                        FormatProperties::VideoMaxFramerate,
+                        default,
+                        min,
+                        max,
+                    );
+                }
+
+                builder.build()
+            })
+            .collect()
+    }
+}
+
+/// Build the fixated `EnumFormat` to send back to the server once a modifier was picked.
+///
+/// `format` is the video/raw format received in the `param_changed` callback while the modifier is
+/// not fixated yet. The returned pod has `modifier` as the only modifier, with the `MANDATORY` flag,
+/// and every other choice property fixated to its default value.
+pub fn fixate_video_format(format: &Pod, modifier: u64) -> Result<OwnedPod, ParseError> {
+    check_media_type(format, (MediaType::Video, MediaSubtype::Raw))?;
+
+    let Ok((_, Value::Object(mut object))) =
+        PodDeserializer::deserialize_any_from(format.as_bytes())
+    else {
+        return Err(ParseError::Malformed("invalid object"));
+    };
+
+    object.id = ParamType::EnumFormat.as_raw();
+    object
+        .properties
+        .retain(|p| p.key != FormatProperties::VideoModifier.as_raw());
+    for property in &mut object.properties {
+        if let Value::Choice(choice) = &property.value {
+            property.value = choice.default_value();
+        }
+    }
+
+    // keep the modifier right after the video format, like the C helpers do
+    let position = object
+        .properties
+        .iter()
+        .position(|p| p.key == FormatProperties::VideoFormat.as_raw())
+        .map_or(object.properties.len(), |i| i + 1);
+    object.properties.insert(
+        position,
+        Property {
+            key: FormatProperties::VideoModifier.as_raw(),
+            flags: PropertyFlags::MANDATORY,
+            value: Value::Long(modifier as i64),
+        },
+    );
+
+    let (cursor, _) = PodSerializer::serialize(Cursor::new(Vec::new()), &Value::Object(object))
+        .map_err(|_| ParseError::Malformed("object cannot be serialized"))?;
+    OwnedPod::from_bytes(cursor.into_inner())
+        .ok_or(ParseError::Malformed("object cannot be serialized"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::video::VideoInfoRaw;
+
+    const MODIFIERS: [u64; 2] = [0x0100_0000_0000_0002, 0];
+
+    fn size() -> [Rectangle; 3] {
+        [
+            Rectangle {
+                width: 1920,
+                height: 1080,
+            },
+            Rectangle {
+                width: 1,
+                height: 1,
+            },
+            Rectangle {
+                width: 8192,
+                height: 8192,
+            },
+        ]
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn build_enum_formats() {
+        let [default, min, max] = size();
+        let params = VideoEnumFormatBuilder::new()
+            .format(VideoFormat::BGRx, &MODIFIERS)
+            .format(VideoFormat::RGBx, &[])
+            .size(default, min, max)
+            .build();
+        assert_eq!(params.len(), 2);
+
+        let (_, Value::Object(dmabuf)) =
+            PodDeserializer::deserialize_any_from(params[0].as_bytes()).unwrap()
+        else {
+            panic!("expected an object");
+        };
+        assert_eq!(dmabuf.id, spa_sys::SPA_PARAM_EnumFormat);
+        let modifier = dmabuf
+            .properties
+            .iter()
+            .find(|p| p.key == spa_sys::SPA_FORMAT_VIDEO_modifier)
+            .unwrap();
+        assert_eq!(
+            modifier.flags.bits(),
+            spa_sys::SPA_POD_PROP_FLAG_MANDATORY | spa_sys::SPA_POD_PROP_FLAG_DONT_FIXATE
+        );
+        assert_eq!(
+            modifier.value,
+            Value::Choice(ChoiceValue::Long(Choice(
+                ChoiceFlags::empty(),
+                ChoiceEnum::Enum {
+                    default: MODIFIERS[0] as i64,
+                    alternatives: MODIFIERS.iter().map(|m| *m as i64).collect(),
+                }
+            )))
+        );
+
+        let (_, Value::Object(shm)) =
+            PodDeserializer::deserialize_any_from(params[1].as_bytes()).unwrap()
+        else {
+            panic!("expected an object");
+        };
+        assert!(shm
+            .properties
+            .iter()
+            .all(|p| p.key != spa_sys::SPA_FORMAT_VIDEO_modifier));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn fixate() {
+        let [default, min, max] = size();
+        let proposal = VideoEnumFormatBuilder::new()
+            .format(VideoFormat::BGRx, &MODIFIERS)
+            .size(default, min, max)
+            .build()
+            .remove(0);
+
+        let fixated = fixate_video_format(&proposal, MODIFIERS[1]).unwrap();
+        let (_, Value::Object(object)) =
+            PodDeserializer::deserialize_any_from(fixated.as_bytes()).unwrap()
+        else {
+            panic!("expected an object");
+        };
+        let modifier = object
+            .properties
+            .iter()
+            .find(|p| p.key == spa_sys::SPA_FORMAT_VIDEO_modifier)
+            .unwrap();
+        assert_eq!(modifier.flags, PropertyFlags::MANDATORY);
+        assert_eq!(modifier.value, Value::Long(MODIFIERS[1] as i64));
+        assert!(object
+            .properties
+            .iter()
+            .all(|p| !matches!(p.value, Value::Choice(_))));
+
+        let info = VideoInfoRaw::from_format_pod(&fixated).unwrap();
+        assert_eq!(info.format(), VideoFormat::BGRx);
+        assert_eq!(info.modifier(), MODIFIERS[1]);
+        assert_eq!(info.size(), default);
+    }
+}
@@ -1,5 +1,7 @@
 // Copyright The pipewire-rs Contributors.
 // SPDX-License-Identifier: MIT
 
+mod enum_format;
+pub use enum_format::*;
 mod raw;
 pub use raw::*;
@@ -1,15 +1,19 @@
 // Copyright The pipewire-rs Contributors.
 // SPDX-License-Identifier: MIT
 
+use crate::param::format::{FormatProperties, MediaSubtype, MediaType};
+use crate::param::ParamType;
+use crate::param::{format_utils::check_media_type, ParseError};
+use crate::pod::{ObjectBuilder, Pod, PropertyFlags, Value};
 use crate::utils::{
     result::{Error, SpaResult, SpaSuccess},
-    Fraction, Rectangle,
+    Choice, Fraction, Id, Rectangle, SpaTypes,
 };
 
 #[cfg(feature = "v0_3_65")]
 use convert_case::{Case, Casing};
 
-use std::{ffi::CStr, fmt::Debug};
+use std::{ffi::CStr, fmt::Debug, io::Cursor};
 
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub struct VideoFormat(pub spa_sys::spa_video_format);
@@ -230,15 +234,15 @@ impl VideoInfoRaw {
             format: VideoFormat::Unknown.as_raw(),
             flags: 0,
             modifier: 0,
-            size: Rectangle {
+            size: spa_sys::spa_rectangle {
                 width: 0,
                 height: 0,
             },
-            framerate: Fraction { num: 0, denom: 0 },
-            max_framerate: Fraction { num: 0, denom: 0 },
+            framerate: spa_sys::spa_fraction { num: 0, denom: 0 },
+            max_framerate: spa_sys::spa_fraction { num: 0, denom: 0 },
             views: 0,
             interlace_mode: VideoInterlaceMode::Progressive.as_raw(),
-            pixel_aspect_ratio: Fraction { num: 0, denom: 0 },
+            pixel_aspect_ratio: spa_sys::spa_fraction { num: 0, denom: 0 },
             multiview_mode: 0,
             multiview_flags: 0,
             chroma_site: 0,
@@ -274,27 +278,27 @@ impl VideoInfoRaw {
     }
 
     pub fn set_size(&mut self, size: Rectangle) {
-        self.0.size = size;
+        self.0.size = size.as_raw();
     }
 
     pub fn size(self) -> Rectangle {
-        self.0.size
+        Rectangle::from_raw(self.0.size)
     }
 
     pub fn set_framerate(&mut self, framerate: Fraction) {
-        self.0.framerate = framerate;
+        self.0.framerate = framerate.as_raw();
     }
 
     pub fn framerate(self) -> Fraction {
-        self.0.framerate
+        Fraction::from_raw(self.0.framerate)
     }
 
     pub fn set_max_framerate(&mut self, max_framerate: Fraction) {
-        self.0.max_framerate = max_framerate;
+        self.0.max_framerate = max_framerate.as_raw();
     }
 
     pub fn max_framerate(self) -> Fraction {
-        self.0.max_framerate
+        Fraction::from_raw(self.0.max_framerate)
     }
 
     pub fn set_views(&mut self, views: u32) {
@@ -314,11 +318,11 @@ impl VideoInfoRaw {
     }
 
     pub fn set_pixel_aspect_ratio(&mut self, pixel_aspect_ratio: Fraction) {
-        self.0.pixel_aspect_ratio = pixel_aspect_ratio;
+        self.0.pixel_aspect_ratio = pixel_aspect_ratio.as_raw();
     }
 
     pub fn pixel_aspect_ratio(self) -> Fraction {
-        self.0.pixel_aspect_ratio
+        Fraction::from_raw(self.0.pixel_aspect_ratio)
     }
 
     pub fn set_multiview_mode(&mut self, multiview_mode: i32) {
@@ -383,6 +387,137 @@ impl VideoInfoRaw {
         SpaResult::from_c(res).into_result()
     }
 
+    /// Parse a format pod, such as the one received in a `param_changed` callback.
+    ///
+    /// Fails if the format is not a video/raw one.
+    /// Properties missing from the format, like the modifier when not using DMA-BUF, keep their default value.
+    pub fn from_format_pod(format: &crate::pod::Pod) -> Result<Self, ParseError> {
+        check_media_type(format, (MediaType::Video, MediaSubtype::Raw))?;
+
+        let mut info = Self::new();
+        info.parse(format)
+            .map_err(|_| ParseError::Malformed("invalid video/raw format properties"))?;
+        Ok(info)
+    }
+
+    /// Serialize the info into `buf` as a fixed [`ParamType::Format`] pod,
+    /// replacing its content, and return the resulting pod.
+    ///
+    /// Like `spa_format_video_raw_build()`, properties with their default value are left out.
+    /// The modifier is only included if the [`VideoFlags`] contain `MODIFIER`,
+    /// which [`from_format_pod`](Self::from_format_pod) sets when the format has a modifier.
+    /// Other flags are not part of the format.
+    pub fn into_pod(self, buf: &mut Vec<u8>) -> &Pod {
+        let size = self.size();
+        let framerate = self.framerate();
+        let builder = self.object_builder(
+            ParamType::Format,
+            (size.width != 0 && size.height != 0).then(|| size.into()),
+            (framerate.denom != 0).then(|| framerate.into()),
+        );
+        write_pod(builder, buf)
+    }
+
+    /// Serialize the info into `buf` as a [`ParamType::EnumFormat`] pod,
+    /// replacing its content, and return the resulting pod.
+    ///
+    /// The size and framerate of the info are replaced by the `size` and `framerate` choices,
+    /// other properties are serialized like in [`into_pod`](Self::into_pod).
+    pub fn into_enum_pod(
+        self,
+        size: Choice<Rectangle>,
+        framerate: Choice<Fraction>,
+        buf: &mut Vec<u8>,
+    ) -> &Pod {
+        let builder = self.object_builder(
+            ParamType::EnumFormat,
+            Some(Value::Choice(size.into())),
+            Some(Value::Choice(framerate.into())),
+        );
+        write_pod(builder, buf)
+    }
+
+    fn object_builder(
+        self,
+        id: ParamType,
+        size: Option<Value>,
+        framerate: Option<Value>,
+    ) -> ObjectBuilder {
+        // the media type and subtype have to come first
+        let mut builder = ObjectBuilder::new(SpaTypes::ObjectParamFormat, id)
+            .prop(FormatProperties::MediaType, MediaType::Video)
+            .prop(FormatProperties::MediaSubtype, MediaSubtype::Raw);
+
+        if self.format() != VideoFormat::Unknown {
+            builder = builder.prop(FormatProperties::VideoFormat, self.format());
+        }
+        if let Some(size) = size {
+            builder = builder.prop(FormatProperties::VideoSize, size);
+        }
+        if let Some(framerate) = framerate {
+            builder = builder.prop(FormatProperties::VideoFramerate, framerate);
+        }
+        // `VideoFlags::MODIFIER` is only defined with the `v0_3_65` feature
+        if self.0.flags & spa_sys::SPA_VIDEO_FLAG_MODIFIER != 0 {
+            #[allow(unused_mut)]
+            let mut flags = PropertyFlags::MANDATORY;
+            #[cfg(feature = "v0_3_75")]
+            if self.0.flags & spa_sys::SPA_VIDEO_FLAG_MODIFIER_FIXATION_REQUIRED != 0 {
+                flags |= PropertyFlags::DONT_FIXATE;
+            }
+            builder = builder.prop_with_flags(
+                FormatProperties::VideoModifier,
+                flags,
+                self.modifier() as i64,
+            );
+        }
+        if self.0.max_framerate.denom != 0 {
+            builder = builder.prop(FormatProperties::VideoMaxFramerate, self.max_framerate());
+        }
+        if self.0.views != 0 {
+            builder = builder.prop(FormatProperties::VideoViews, self.0.views as i32);
+        }
+        if self.0.interlace_mode != 0 {
+            builder = builder.prop(
+                FormatProperties::VideoInterlaceMode,
+                Id(self.0.interlace_mode),
+            );
+        }
+        if self.0.pixel_aspect_ratio.denom != 0 {
+            builder = builder.prop(
+                FormatProperties::VideoPixelAspectRatio,
+                self.pixel_aspect_ratio(),
+            );
+        }
+        for (key, value) in [
+            (
+                FormatProperties::VideoMultiviewMode,
+                self.0.multiview_mode as u32,
+            ),
+            (
+                FormatProperties::VideoMultiviewFlags,
+                self.0.multiview_flags,
+            ),
+            (FormatProperties::VideoChromaSite, self.0.chroma_site),
+            (FormatProperties::VideoColorRange, self.0.color_range),
+            (FormatProperties::VideoColorMatrix, self.0.color_matrix),
+            (
+                FormatProperties::VideoTransferFunction,
+                self.0.transfer_function,
+            ),
+            (
+                FormatProperties::VideoColorPrimaries,
+                self.0.color_primaries,
+            ),
+        ] {
+            if value != 0 {
+                builder = builder.prop(key, Id(value));
+            }
+        }
+
+        builder
+    }
+
     /// Obtain a [`VideoInfoRaw`] from a raw `spa_video_info_raw` variant.
     pub fn from_raw(raw: spa_sys::spa_video_info_raw) -> Self {
         Self(raw)
@@ -394,6 +529,16 @@ impl VideoInfoRaw {
     }
 }
 
+fn write_pod(builder: ObjectBuilder, buf: &mut Vec<u8>) -> &Pod {
+    buf.clear();
+    let (cursor, _) = builder
+        .build_into(Cursor::new(std::mem::take(buf)))
+        .expect("serializing into a Vec cannot fail");
+    *buf = cursor.into_inner();
+
+    Pod::from_bytes(buf).expect("serialized format is a valid pod")
+}
+
 impl Default for VideoInfoRaw {
     fn default() -> Self {
         Self::new()
@@ -456,4 +601,105 @@ mod tests {
             format!("{:?}", VideoInterlaceMode::Progressive)
         );
     }
+
+    fn info() -> VideoInfoRaw {
+        let mut info = VideoInfoRaw::new();
+        info.set_format(VideoFormat::BGRx);
+        info.set_flags(VideoFlags::from_bits_retain(
+            spa_sys::SPA_VIDEO_FLAG_MODIFIER,
+        ));
+        info.set_modifier(0);
+        info.set_size(Rectangle {
+            width: 1280,
+            height: 720,
+        });
+        info.set_framerate(Fraction { num: 30, denom: 1 });
+        info.set_pixel_aspect_ratio(Fraction { num: 1, denom: 1 });
+        info
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn into_pod() {
+        let info = info();
+        let mut buf = Vec::new();
+        let pod = info.into_pod(&mut buf);
+        assert_eq!(VideoInfoRaw::from_format_pod(pod), Ok(info));
+
+        let (_, Value::Object(object)) =
+            crate::pod::deserialize::PodDeserializer::deserialize_any_from(pod.as_bytes()).unwrap()
+        else {
+            panic!("expected an object");
+        };
+        assert_eq!(object.id, ParamType::Format.as_raw());
+        let keys: Vec<_> = object.properties.iter().map(|p| p.key).collect();
+        assert_eq!(
+            keys,
+            [
+                spa_sys::SPA_FORMAT_mediaType,
+                spa_sys::SPA_FORMAT_mediaSubtype,
+                spa_sys::SPA_FORMAT_VIDEO_format,
+                spa_sys::SPA_FORMAT_VIDEO_size,
+                spa_sys::SPA_FORMAT_VIDEO_framerate,
+                spa_sys::SPA_FORMAT_VIDEO_modifier,
+                spa_sys::SPA_FORMAT_VIDEO_pixelAspectRatio,
+            ]
+        );
+
+        // without the modifier flag, there is no modifier
+        let mut shm = info;
+        shm.set_flags(VideoFlags::empty());
+        let pod = shm.into_pod(&mut buf);
+        assert_eq!(VideoInfoRaw::from_format_pod(pod), Ok(shm));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn into_enum_pod() {
+        use crate::utils::{ChoiceEnum, ChoiceFlags};
+
+        let info = info();
+        let size = Choice(
+            ChoiceFlags::empty(),
+            ChoiceEnum::Range {
+                default: info.size(),
+                min: Rectangle {
+                    width: 1,
+                    height: 1,
+                },
+                max: Rectangle {
+                    width: 4096,
+                    height: 4096,
+                },
+            },
+        );
+        let framerate = Choice(
+            ChoiceFlags::empty(),
+            ChoiceEnum::Range {
+                default: info.framerate(),
+                min: Fraction { num: 0, denom: 1 },
+                max: Fraction { num: 60, denom: 1 },
+            },
+        );
+
+        let mut buf = Vec::new();
+        let pod = info.into_enum_pod(size.clone(), framerate, &mut buf);
+        let (_, Value::Object(object)) =
+            crate::pod::deserialize::PodDeserializer::deserialize_any_from(pod.as_bytes()).unwrap()
+        else {
+            panic!("expected an object");
+        };
+        assert_eq!(object.id, ParamType::EnumFormat.as_raw());
+        assert_eq!(object.properties[0].key, spa_sys::SPA_FORMAT_mediaType);
+        assert_eq!(object.properties[1].key, spa_sys::SPA_FORMAT_mediaSubtype);
+        assert_eq!(
+            object
+                .properties
+                .iter()
+                .find(|p| p.key == spa_sys::SPA_FORMAT_VIDEO_size)
+                .unwrap()
+                .value,
+            Value::Choice(size.into())
+        );
+    }
 }
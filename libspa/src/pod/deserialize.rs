@@ -630,6 +630,9 @@ impl<'de> PodDeserializer<'de> {
             self.parse(pair(u32(Endianness::Native), u32(Endianness::Native)))?;
         let (child_size, child_type) =
             self.parse(pair(u32(Endianness::Native), u32(Endianness::Native)))?;
+        if child_size == 0 || len < 16 {
+            return Err(DeserializeError::InvalidType);
+        }
         let num_values = (len - 16) / child_size;
 
         fn create_choice<'de, E>(
@@ -647,7 +650,7 @@ impl<'de> PodDeserializer<'de> {
                     if values.is_empty() {
                         Err(DeserializeError::MissingChoiceValues)
                     } else {
-                        Ok(Choice(ChoiceFlags::empty(), ChoiceEnum::None(values[0])))
+                        Ok(Choice(flags, ChoiceEnum::None(values[0])))
                     }
                 }
                 spa_sys::SPA_CHOICE_Range => {
@@ -770,7 +773,7 @@ impl<'de> PodDeserializer<'de> {
         let len = self.parse(Self::header(spa_sys::SPA_TYPE_Pointer))?;
         let (type_, _padding) =
             self.parse(pair(u32(Endianness::Native), u32(Endianness::Native)))?;
-        let ptr_size = len - 8;
+        let ptr_size = len.checked_sub(8).ok_or(DeserializeError::InvalidType)?;
 
         let res = match ptr_size {
             8 => {
@@ -778,10 +781,10 @@ impl<'de> PodDeserializer<'de> {
                 visitor.visit_pointer(type_, ptr as *const c_void)?
             }
             4 => {
-                let ptr = self.parse(u32(Endianness::Native))?;
+                let ptr = self.parse(terminated(u32(Endianness::Native), take(4u32)))?;
                 visitor.visit_pointer(type_, ptr as *const c_void)?
             }
-            _ => panic!("unsupported pointer size {}", ptr_size),
+            _ => return Err(DeserializeError::InvalidType),
         };
 
         Ok((res, DeserializeSuccess(self)))
@@ -0,0 +1,595 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Human readable printing of raw pods, similar to `spa_debug_pod()` in C.
+
+use std::{borrow::Cow, ffi::CStr, fmt, mem::size_of};
+
+use super::Pod;
+
+/// Nesting depth after which the content of containers is not printed anymore.
+const MAX_DEPTH: usize = 32;
+/// Maximum number of bytes printed as hex for pods that have no other representation.
+const MAX_HEX_BYTES: usize = 256;
+
+type TypeTable = *const spa_sys::spa_type_info;
+
+/// Adapter printing a [`Pod`] as an indented tree, one value per line, returned by [`Pod::display`].
+///
+/// Object types and ids, property keys and the ids contained in properties are printed along with their
+/// name from the SPA type info tables.
+/// Values of unknown types are printed as hex.
+///
+/// The pod is only read within the bounds of its buffer: truncated or corrupt pods are printed
+/// as such, and containers nested too deeply are elided.
+///
+/// ```
+/// use libspa::{
+///     param::{format::{FormatProperties, MediaType}, ParamType},
+///     pod::ObjectBuilder,
+///     utils::SpaTypes,
+/// };
+///
+/// let pod = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+///     .prop(FormatProperties::MediaType, MediaType::Audio)
+///     .build();
+///
+/// assert_eq!(
+///     pod.display().to_string(),
+///     "Object: size 32, type Spa:Pod:Object:Param:Format (262147), id Spa:Enum:ParamId:EnumFormat (3)
+///   Prop: key Spa:Pod:Object:Param:Format:mediaType (1), flags 0x00000000
+///     Id 1 (Spa:Enum:MediaType:audio)"
+/// );
+/// ```
+pub struct PodDisplay<'p>(pub(super) &'p Pod);
+
+impl fmt::Display for PodDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer { f, first: true }.pod(self.0.as_bytes(), std::ptr::null(), 0)
+    }
+}
+
+/// Find `type_` in the type info table `table`, which is null when the values have no names.
+fn find_type(table: TypeTable, type_: u32) -> Option<&'static spa_sys::spa_type_info> {
+    // spa_debug_type_find() would look into the root table instead
+    if table.is_null() {
+        return None;
+    }
+    unsafe { spa_sys::spa_debug_type_find(table, type_).as_ref() }
+}
+
+fn type_info_name(info: &spa_sys::spa_type_info) -> Cow<'static, str> {
+    unsafe { CStr::from_ptr(info.name) }.to_string_lossy()
+}
+
+/// Format `type_` as its name from `table` followed by its value, or only its value if it is unknown.
+fn type_name(table: TypeTable, type_: u32) -> String {
+    match find_type(table, type_) {
+        Some(info) => format!("{} ({})", type_info_name(info), type_),
+        None => type_.to_string(),
+    }
+}
+
+/// Read `N` bytes at `offset`, if `bytes` is large enough.
+fn read<const N: usize>(bytes: &[u8], offset: usize) -> Option<[u8; N]> {
+    bytes.get(offset..offset.checked_add(N)?)?.try_into().ok()
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    read(bytes, offset).map(u32::from_ne_bytes)
+}
+
+/// Size of a pod with a body of `size` bytes, including its header and padding.
+fn padded_pod_size(size: u32) -> Option<usize> {
+    let size = usize::try_from(size).ok()?.checked_add(8)?;
+    size.checked_next_multiple_of(8)
+}
+
+struct Printer<'a, 'f> {
+    f: &'a mut fmt::Formatter<'f>,
+    first: bool,
+}
+
+impl Printer<'_, '_> {
+    fn line(&mut self, depth: usize, args: fmt::Arguments) -> fmt::Result {
+        if !self.first {
+            self.f.write_str("\n")?;
+        }
+        self.first = false;
+        write!(self.f, "{:indent$}{}", "", args, indent = depth * 2)
+    }
+
+    fn hex(&mut self, bytes: &[u8], depth: usize) -> fmt::Result {
+        for chunk in bytes.chunks(16).take(MAX_HEX_BYTES / 16) {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+            self.line(depth, format_args!("{}", hex.join(" ")))?;
+        }
+        if bytes.len() > MAX_HEX_BYTES {
+            self.line(
+                depth,
+                format_args!("... ({} more bytes)", bytes.len() - MAX_HEX_BYTES),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Print the pod at the start of `bytes`, naming ids with `values`.
+    fn pod(&mut self, bytes: &[u8], values: TypeTable, depth: usize) -> fmt::Result {
+        let (Some(size), Some(type_)) = (read_u32(bytes, 0), read_u32(bytes, 4)) else {
+            return self.line(
+                depth,
+                format_args!("<truncated pod header: {} bytes>", bytes.len()),
+            );
+        };
+        let Some(body) = bytes.get(8..).and_then(|b| b.get(..size as usize)) else {
+            return self.line(
+                depth,
+                format_args!(
+                    "<truncated pod: type {}, size {}, {} bytes available>",
+                    type_name(unsafe { spa_sys::spa_types }, type_),
+                    size,
+                    bytes.len() - 8
+                ),
+            );
+        };
+
+        self.body(type_, body, values, depth)
+    }
+
+    /// Print the body of a pod of type `type_`, naming ids with `values`.
+    fn body(&mut self, type_: u32, body: &[u8], values: TypeTable, depth: usize) -> fmt::Result {
+        macro_rules! scalar {
+            ($name:literal, $ty:ty, $fmt:literal) => {
+                match read(body, 0).map(<$ty>::from_ne_bytes) {
+                    Some(value) => self.line(depth, format_args!(concat!($name, " ", $fmt), value)),
+                    None => self.invalid(type_, body, depth),
+                }
+            };
+        }
+
+        match type_ {
+            spa_sys::SPA_TYPE_None => self.line(depth, format_args!("None")),
+            spa_sys::SPA_TYPE_Bool => match read(body, 0).map(i32::from_ne_bytes) {
+                Some(value) => self.line(depth, format_args!("Bool {}", value != 0)),
+                None => self.invalid(type_, body, depth),
+            },
+            spa_sys::SPA_TYPE_Id => match read_u32(body, 0) {
+                Some(id) => self.line(depth, format_args!("Id {}", id_name(values, id))),
+                None => self.invalid(type_, body, depth),
+            },
+            spa_sys::SPA_TYPE_Int => scalar!("Int", i32, "{}"),
+            spa_sys::SPA_TYPE_Long => scalar!("Long", i64, "{}"),
+            spa_sys::SPA_TYPE_Float => scalar!("Float", f32, "{:?}"),
+            spa_sys::SPA_TYPE_Double => scalar!("Double", f64, "{:?}"),
+            spa_sys::SPA_TYPE_Fd => scalar!("Fd", i64, "{}"),
+            spa_sys::SPA_TYPE_String => {
+                // the string may be missing its nul terminator in corrupt pods
+                let len = body.iter().position(|b| *b == 0).unwrap_or(body.len());
+                let string = String::from_utf8_lossy(&body[..len]);
+                self.line(depth, format_args!("String {string:?}"))
+            }
+            spa_sys::SPA_TYPE_Bytes => {
+                self.line(depth, format_args!("Bytes: size {}", body.len()))?;
+                self.hex(body, depth + 1)
+            }
+            spa_sys::SPA_TYPE_Rectangle => match (read_u32(body, 0), read_u32(body, 4)) {
+                (Some(width), Some(height)) => {
+                    self.line(depth, format_args!("Rectangle {width}x{height}"))
+                }
+                _ => self.invalid(type_, body, depth),
+            },
+            spa_sys::SPA_TYPE_Fraction => match (read_u32(body, 0), read_u32(body, 4)) {
+                (Some(num), Some(denom)) => {
+                    self.line(depth, format_args!("Fraction {num}/{denom}"))
+                }
+                _ => self.invalid(type_, body, depth),
+            },
+            spa_sys::SPA_TYPE_Bitmap => {
+                self.line(depth, format_args!("Bitmap: size {}", body.len()))?;
+                self.hex(body, depth + 1)
+            }
+            spa_sys::SPA_TYPE_Pointer => {
+                match (
+                    read_u32(body, 0),
+                    read::<{ size_of::<usize>() }>(body, 8).map(usize::from_ne_bytes),
+                ) {
+                    (Some(pointer_type), Some(value)) => self.line(
+                        depth,
+                        format_args!(
+                            "Pointer: type {}, value {:#x}",
+                            type_name(unsafe { spa_sys::spa_types }, pointer_type),
+                            value
+                        ),
+                    ),
+                    _ => self.invalid(type_, body, depth),
+                }
+            }
+            spa_sys::SPA_TYPE_Array
+            | spa_sys::SPA_TYPE_Struct
+            | spa_sys::SPA_TYPE_Object
+            | spa_sys::SPA_TYPE_Sequence
+            | spa_sys::SPA_TYPE_Choice
+                if depth >= MAX_DEPTH =>
+            {
+                self.line(
+                    depth,
+                    format_args!(
+                        "<{} nested too deeply>",
+                        type_name(unsafe { spa_sys::spa_types }, type_)
+                    ),
+                )
+            }
+            spa_sys::SPA_TYPE_Array => self.array(body, values, depth),
+            spa_sys::SPA_TYPE_Struct => self.struct_(body, depth),
+            spa_sys::SPA_TYPE_Object => self.object(body, depth),
+            spa_sys::SPA_TYPE_Sequence => self.sequence(body, depth),
+            spa_sys::SPA_TYPE_Choice => self.choice(body, values, depth),
+            _ => {
+                self.line(
+                    depth,
+                    format_args!(
+                        "Unknown: type {}, size {}",
+                        type_name(unsafe { spa_sys::spa_types }, type_),
+                        body.len()
+                    ),
+                )?;
+                self.hex(body, depth + 1)
+            }
+        }
+    }
+
+    /// Print a pod whose body is too small for its type.
+    fn invalid(&mut self, type_: u32, body: &[u8], depth: usize) -> fmt::Result {
+        self.line(
+            depth,
+            format_args!(
+                "<invalid {}: size {}>",
+                type_name(unsafe { spa_sys::spa_types }, type_),
+                body.len()
+            ),
+        )?;
+        self.hex(body, depth + 1)
+    }
+
+    /// Print the values of an array or a choice, stored after their `spa_pod` header at `offset`.
+    fn children(
+        &mut self,
+        body: &[u8],
+        offset: usize,
+        values: TypeTable,
+        depth: usize,
+    ) -> fmt::Result {
+        let (Some(size), Some(type_)) = (read_u32(body, offset), read_u32(body, offset + 4)) else {
+            return self.line(depth, format_args!("<truncated child header>"));
+        };
+        self.line(
+            depth,
+            format_args!(
+                "child.size {}, child.type {}",
+                size,
+                type_name(unsafe { spa_sys::spa_types }, type_)
+            ),
+        )?;
+        if size == 0 {
+            return Ok(());
+        }
+
+        let mut children = body[offset + 8..].chunks_exact(size as usize);
+        for child in &mut children {
+            self.body(type_, child, values, depth + 1)?;
+        }
+        if !children.remainder().is_empty() {
+            self.line(
+                depth + 1,
+                format_args!("<{} trailing bytes>", children.remainder().len()),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn array(&mut self, body: &[u8], values: TypeTable, depth: usize) -> fmt::Result {
+        self.line(depth, format_args!("Array: size {}", body.len()))?;
+        // the table of array properties describes their element, like spa_debug_pod() assumes
+        let values = match unsafe { values.as_ref() } {
+            Some(element) if !element.values.is_null() => element.values,
+            _ => values,
+        };
+        self.children(body, 0, values, depth + 1)
+    }
+
+    fn choice(&mut self, body: &[u8], values: TypeTable, depth: usize) -> fmt::Result {
+        let (Some(choice_type), Some(flags)) = (read_u32(body, 0), read_u32(body, 4)) else {
+            return self.invalid(spa_sys::SPA_TYPE_Choice, body, depth);
+        };
+        self.line(
+            depth,
+            format_args!(
+                "Choice: type {}, flags {:#010x}",
+                type_name(unsafe { spa_sys::spa_type_choice }, choice_type),
+                flags
+            ),
+        )?;
+        self.children(body, 8, values, depth + 1)
+    }
+
+    fn struct_(&mut self, body: &[u8], depth: usize) -> fmt::Result {
+        self.line(depth, format_args!("Struct: size {}", body.len()))?;
+
+        let mut offset = 0;
+        while offset < body.len() {
+            let field = &body[offset..];
+            self.pod(field, std::ptr::null(), depth + 1)?;
+            match read_u32(field, 0).and_then(padded_pod_size) {
+                Some(size) if size <= field.len() => offset += size,
+                // the field was printed as truncated
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn object(&mut self, body: &[u8], depth: usize) -> fmt::Result {
+        let (Some(object_type), Some(id)) = (read_u32(body, 0), read_u32(body, 4)) else {
+            return self.invalid(spa_sys::SPA_TYPE_Object, body, depth);
+        };
+
+        // the table of the object type lists its property keys, the key 0 being for its id
+        let keys = find_type(unsafe { spa_sys::spa_types }, object_type)
+            .map_or(std::ptr::null(), |info| info.values);
+        let ids = find_type(keys, 0).map_or(std::ptr::null(), |info| info.values);
+        self.line(
+            depth,
+            format_args!(
+                "Object: size {}, type {}, id {}",
+                body.len(),
+                type_name(unsafe { spa_sys::spa_types }, object_type),
+                type_name(ids, id)
+            ),
+        )?;
+
+        let mut offset = 8;
+        while offset < body.len() {
+            let prop = &body[offset..];
+            let (Some(key), Some(flags)) = (read_u32(prop, 0), read_u32(prop, 4)) else {
+                return self.line(depth + 1, format_args!("<truncated property>"));
+            };
+            let key_info = find_type(keys, key);
+            self.line(
+                depth + 1,
+                format_args!("Prop: key {}, flags {:#010x}", type_name(keys, key), flags),
+            )?;
+
+            let values = key_info.map_or(std::ptr::null(), |info| info.values);
+            self.pod(&prop[8..], values, depth + 2)?;
+            match read_u32(prop, 8).and_then(padded_pod_size) {
+                Some(size) if 8 + size <= prop.len() => offset += 8 + size,
+                // the value was printed as truncated
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn sequence(&mut self, body: &[u8], depth: usize) -> fmt::Result {
+        let Some(unit) = read_u32(body, 0) else {
+            return self.invalid(spa_sys::SPA_TYPE_Sequence, body, depth);
+        };
+        self.line(
+            depth,
+            format_args!("Sequence: size {}, unit {}", body.len(), unit),
+        )?;
+
+        let mut offset = 8;
+        while offset < body.len() {
+            let control = &body[offset..];
+            let (Some(control_offset), Some(control_type)) =
+                (read_u32(control, 0), read_u32(control, 4))
+            else {
+                return self.line(depth + 1, format_args!("<truncated control>"));
+            };
+            self.line(
+                depth + 1,
+                format_args!(
+                    "Control: offset {}, type {}",
+                    control_offset,
+                    type_name(unsafe { spa_sys::spa_type_control }, control_type)
+                ),
+            )?;
+
+            self.pod(&control[8..], std::ptr::null(), depth + 2)?;
+            match read_u32(control, 8).and_then(padded_pod_size) {
+                Some(size) if 8 + size <= control.len() => offset += 8 + size,
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Format an id with its name from `values`, if any.
+fn id_name(values: TypeTable, id: u32) -> String {
+    match find_type(values, id) {
+        Some(info) => format!("{} ({})", id, type_info_name(info)),
+        None => id.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        param::{
+            audio::AudioFormat,
+            format::{FormatProperties, MediaSubtype, MediaType},
+            ParamType,
+        },
+        pod::{serialize::PodSerializer, ObjectBuilder, Value, ValueArray},
+        utils::{Id, SpaTypes},
+    };
+    use std::io::Cursor;
+
+    fn enum_format() -> Vec<u8> {
+        ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+            .prop(FormatProperties::MediaType, MediaType::Audio)
+            .prop(FormatProperties::MediaSubtype, MediaSubtype::Raw)
+            .prop_choice_enum(
+                FormatProperties::AudioFormat,
+                AudioFormat::F32P,
+                [AudioFormat::F32P, AudioFormat::S16P],
+            )
+            .prop_choice_range(FormatProperties::AudioRate, 48000, 1, 384000)
+            .build()
+            .into_inner()
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn format() {
+        let pod = Pod::from_bytes(&enum_format())
+            .unwrap()
+            .display()
+            .to_string();
+        assert_eq!(
+            pod,
+            format!(
+                "\
+Object: size 152, type Spa:Pod:Object:Param:Format ({}), id Spa:Enum:ParamId:EnumFormat ({})
+  Prop: key Spa:Pod:Object:Param:Format:mediaType ({}), flags 0x00000000
+    Id {} (Spa:Enum:MediaType:audio)
+  Prop: key Spa:Pod:Object:Param:Format:mediaSubtype ({}), flags 0x00000000
+    Id {} (Spa:Enum:MediaSubtype:raw)
+  Prop: key Spa:Pod:Object:Param:Format:Audio:format ({}), flags 0x00000000
+    Choice: type Spa:Enum:Choice:Enum ({}), flags 0x00000000
+      child.size 4, child.type Spa:Id ({})
+        Id {f32p} (Spa:Enum:AudioFormat:F32P)
+        Id {f32p} (Spa:Enum:AudioFormat:F32P)
+        Id {} (Spa:Enum:AudioFormat:S16P)
+  Prop: key Spa:Pod:Object:Param:Format:Audio:rate ({}), flags 0x00000000
+    Choice: type Spa:Enum:Choice:Range ({}), flags 0x00000000
+      child.size 4, child.type Spa:Int ({})
+        Int 48000
+        Int 1
+        Int 384000",
+                spa_sys::SPA_TYPE_OBJECT_Format,
+                spa_sys::SPA_PARAM_EnumFormat,
+                spa_sys::SPA_FORMAT_mediaType,
+                spa_sys::SPA_MEDIA_TYPE_audio,
+                spa_sys::SPA_FORMAT_mediaSubtype,
+                spa_sys::SPA_MEDIA_SUBTYPE_raw,
+                spa_sys::SPA_FORMAT_AUDIO_format,
+                spa_sys::SPA_CHOICE_Enum,
+                spa_sys::SPA_TYPE_Id,
+                spa_sys::SPA_AUDIO_FORMAT_S16P,
+                spa_sys::SPA_FORMAT_AUDIO_rate,
+                spa_sys::SPA_CHOICE_Range,
+                spa_sys::SPA_TYPE_Int,
+                f32p = spa_sys::SPA_AUDIO_FORMAT_F32P,
+            )
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn props() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamProps, ParamType::Props)
+            .prop(spa_sys::SPA_PROP_mute, true)
+            .prop(
+                spa_sys::SPA_PROP_channelMap,
+                ValueArray::Id(vec![
+                    Id(spa_sys::SPA_AUDIO_CHANNEL_FL),
+                    Id(spa_sys::SPA_AUDIO_CHANNEL_FR),
+                ]),
+            )
+            .prop(
+                spa_sys::SPA_PROP_params,
+                Value::Struct(vec![Value::String("foo".into()), Value::Double(0.5)]),
+            )
+            .build();
+
+        assert_eq!(
+            pod.display().to_string(),
+            format!(
+                "\
+Object: size 112, type Spa:Pod:Object:Param:Props ({}), id Spa:Enum:ParamId:Props ({})
+  Prop: key Spa:Pod:Object:Param:Props:mute ({}), flags 0x00000000
+    Bool true
+  Prop: key Spa:Pod:Object:Param:Props:channelMap ({}), flags 0x00000000
+    Array: size 16
+      child.size 4, child.type Spa:Id ({})
+        Id {} (Spa:Enum:AudioChannel:FL)
+        Id {} (Spa:Enum:AudioChannel:FR)
+  Prop: key Spa:Pod:Object:Param:Props:params ({}), flags 0x00000000
+    Struct: size 32
+      String \"foo\"
+      Double 0.5",
+                spa_sys::SPA_TYPE_OBJECT_Props,
+                spa_sys::SPA_PARAM_Props,
+                spa_sys::SPA_PROP_mute,
+                spa_sys::SPA_PROP_channelMap,
+                spa_sys::SPA_TYPE_Id,
+                spa_sys::SPA_AUDIO_CHANNEL_FL,
+                spa_sys::SPA_AUDIO_CHANNEL_FR,
+                spa_sys::SPA_PROP_params,
+            )
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn unknown_type() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&4u32.to_ne_bytes());
+        bytes.extend_from_slice(&0x1234u32.to_ne_bytes());
+        bytes.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef, 0, 0, 0, 0]);
+
+        let pod = Pod::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            pod.display().to_string(),
+            "Unknown: type 4660, size 4\n  de ad be ef"
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn truncated() {
+        let mut bytes = enum_format();
+        // make the value of the last property claim more bytes than the object contains
+        let len = bytes.len();
+        bytes[len - 40..len - 36].copy_from_slice(&1000u32.to_ne_bytes());
+
+        let pod = Pod::from_bytes(&bytes).unwrap().display().to_string();
+        assert!(pod.ends_with(&format!(
+            "
+    <truncated pod: type Spa:Choice ({}), size 1000, 32 bytes available>",
+            spa_sys::SPA_TYPE_Choice
+        )));
+
+        // every prefix of the object, with its size adjusted
+        let bytes = enum_format();
+        for len in 8..bytes.len() {
+            let mut prefix = bytes[..len].to_vec();
+            prefix[..4].copy_from_slice(&(len as u32 - 8).to_ne_bytes());
+            prefix.resize(len.next_multiple_of(8), 0);
+            let pod = Pod::from_bytes(&prefix).unwrap();
+            let _ = pod.display().to_string();
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn nested_too_deeply() {
+        let mut value = Value::Int(1);
+        for _ in 0..(MAX_DEPTH + 8) {
+            value = Value::Struct(vec![value]);
+        }
+        let (cursor, _) = PodSerializer::serialize(Cursor::new(Vec::new()), &value).unwrap();
+        let bytes = cursor.into_inner();
+
+        let pod = Pod::from_bytes(&bytes).unwrap().display().to_string();
+        assert_eq!(pod.lines().count(), MAX_DEPTH + 1);
+        assert!(pod.ends_with(&format!(
+            "<Spa:Struct ({}) nested too deeply>",
+            spa_sys::SPA_TYPE_Struct
+        )));
+    }
+}
@@ -9,12 +9,22 @@
 
 pub mod builder;
 pub mod deserialize;
+mod display;
+pub use display::PodDisplay;
+mod object_builder;
+pub use object_builder::*;
 pub mod parser;
+mod sequence;
+pub use sequence::*;
 pub mod serialize;
+mod struct_builder;
+pub use struct_builder::*;
+#[cfg(feature = "serde")]
+mod value_serde;
 
 use std::{
-    ffi::c_void,
-    io::{Seek, Write},
+    ffi::{c_char, c_void, CStr},
+    io::{Cursor, Seek, Write},
     mem::MaybeUninit,
     os::fd::RawFd,
     ptr::addr_of,
@@ -142,6 +152,13 @@ impl Pod {
         unsafe { std::slice::from_raw_parts(ptr, size) }
     }
 
+    /// Get an adapter printing the pod as a readable tree, like `spa_debug_pod()` does.
+    ///
+    /// This is also how the pod is printed by its [`Debug`](std::fmt::Debug) implementation.
+    pub fn display(&self) -> PodDisplay<'_> {
+        PodDisplay(self)
+    }
+
     pub fn type_(&self) -> SpaTypes {
         SpaTypes::from_raw(self.0.type_)
     }
@@ -270,7 +287,18 @@ impl Pod {
         res != 0
     }
 
-    // TODO: to_string
+    pub fn get_string(&self) -> Result<&CStr, Errno> {
+        unsafe {
+            let mut string: MaybeUninit<*const c_char> = MaybeUninit::uninit();
+            let res = spa_sys::spa_pod_get_string(self.as_raw_ptr(), string.as_mut_ptr());
+
+            if res >= 0 {
+                Ok(CStr::from_ptr(string.assume_init()))
+            } else {
+                Err(Errno::from_i32(-res))
+            }
+        }
+    }
 
     pub fn is_bytes(&self) -> bool {
         let res = unsafe { spa_sys::spa_pod_is_bytes(self.as_raw_ptr()) };
@@ -351,7 +379,7 @@ impl Pod {
             let res = spa_sys::spa_pod_get_rectangle(self.as_raw_ptr(), rectangle.as_mut_ptr());
 
             if res >= 0 {
-                Ok(rectangle.assume_init())
+                Ok(Rectangle::from_raw(rectangle.assume_init()))
             } else {
                 Err(Errno::from_i32(-res))
             }
@@ -369,7 +397,7 @@ impl Pod {
             let res = spa_sys::spa_pod_get_fraction(self.as_raw_ptr(), fraction.as_mut_ptr());
 
             if res >= 0 {
-                Ok(fraction.assume_init())
+                Ok(Fraction::from_raw(fraction.assume_init()))
             } else {
                 Err(Errno::from_i32(-res))
             }
@@ -427,6 +455,86 @@ impl Pod {
         let res = unsafe { spa_sys::spa_pod_is_sequence(self.as_raw_ptr()) };
         res != 0
     }
+
+    pub fn as_sequence(&self) -> Result<&PodSequence, Errno> {
+        if self.is_sequence() {
+            // Safety: We already know that the pod is valid, and since it is a sequence, we can
+            //         safely create a PodSequence from it
+            Ok(unsafe {
+                PodSequence::from_raw(self.as_raw_ptr() as *const spa_sys::spa_pod_sequence)
+            })
+        } else {
+            Err(Errno::EINVAL)
+        }
+    }
+
+    /// Copy this object pod, replacing the value of each property which is a choice
+    /// by the default value of the choice.
+    ///
+    /// This is how a format enumerated with choices is turned into a format which can be set.
+    /// The order and the flags of the properties are preserved, except the `DONT_FIXATE` flag
+    /// which is cleared.
+    ///
+    /// Returns [`Errno::EINVAL`] if the pod is not a valid object.
+    pub fn fixate(&self) -> Result<OwnedPod, Errno> {
+        let Ok((_, Value::Object(mut object))) =
+            PodDeserializer::deserialize_any_from(self.as_bytes())
+        else {
+            return Err(Errno::EINVAL);
+        };
+
+        for property in &mut object.properties {
+            if let Value::Choice(choice) = &property.value {
+                property.value = choice.default_value();
+                property.flags.remove(PropertyFlags::DONT_FIXATE);
+            }
+        }
+
+        let (cursor, _) = PodSerializer::serialize(Cursor::new(Vec::new()), &Value::Object(object))
+            .expect("serializing into a Vec cannot fail");
+        OwnedPod::from_bytes(cursor.into_inner()).ok_or(Errno::EINVAL)
+    }
+
+    /// Intersect this pod with `filter`, like `spa_pod_filter()` does.
+    ///
+    /// This is how two `EnumFormat` params, such as the formats of a node and the ones of its peer,
+    /// are negotiated: choices are reduced to the values accepted by both pods, and a choice left
+    /// with a single value becomes a fixed value.
+    /// Object properties present in only one of the pods are kept as is, unless they have the
+    /// `MANDATORY` flag, which requires the property in both pods.
+    ///
+    /// Returns [`Errno::EINVAL`] if the pods have no value in common for a property,
+    /// or a different type.
+    pub fn filter(&self, filter: &Pod) -> Result<OwnedPod, Errno> {
+        let mut data = Vec::new();
+        let mut result: *mut spa_sys::spa_pod = std::ptr::null_mut();
+
+        let builder = builder::Builder::new(&mut data);
+        let res = unsafe {
+            spa_sys::spa_pod_filter(
+                builder.as_raw_ptr(),
+                &mut result,
+                self.as_raw_ptr(),
+                filter.as_raw_ptr(),
+            )
+        };
+        drop(builder);
+
+        if res < 0 {
+            return Err(Errno::from_i32(-res));
+        }
+
+        // The result was built at the start of `data`, make sure its padding is included.
+        let size = std::mem::size_of::<spa_sys::spa_pod>() + unsafe { (*result).size } as usize;
+        data.resize(size.next_multiple_of(8), 0);
+        OwnedPod::from_bytes(data).ok_or(Errno::EINVAL)
+    }
+}
+
+impl std::fmt::Debug for Pod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.display(), f)
+    }
 }
 
 impl<'p> From<&'p PodStruct> for &'p Pod {
@@ -441,6 +549,128 @@ impl<'p> From<&'p PodObject> for &'p Pod {
     }
 }
 
+/// An owned, heap-allocated copy of a single pod, as returned by [`ObjectBuilder::build`]
+/// or by [`Pod::to_owned`](ToOwned::to_owned).
+///
+/// Pods passed to callbacks are only valid for the duration of the call,
+/// copy them into an `OwnedPod` to keep them around for longer.
+#[derive(Clone, PartialEq, Eq)]
+pub struct OwnedPod {
+    // u64 words keep the pod correctly aligned
+    data: Vec<u64>,
+}
+
+impl OwnedPod {
+    /// Copy bytes containing a serialized pod.
+    ///
+    /// Returns `None` if `bytes` does not start with a complete pod of a basic [`SpaTypes`] type.
+    /// Any bytes after the pod and its padding are discarded.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Option<Self> {
+        let mut data = Vec::new();
+        Self::copy_words(&mut data, bytes.as_ref())?;
+        Some(Self { data })
+    }
+
+    /// Replace the pod by a copy of the one in `bytes`, reusing the allocation.
+    ///
+    /// The pod is left unchanged if `bytes` is not valid, see [`from_bytes`](Self::from_bytes).
+    pub(crate) fn replace_bytes(&mut self, bytes: &[u8]) -> Option<()> {
+        Self::copy_words(&mut self.data, bytes)
+    }
+
+    fn copy_words(data: &mut Vec<u64>, bytes: &[u8]) -> Option<()> {
+        const HEADER_SIZE: usize = std::mem::size_of::<spa_sys::spa_pod>();
+        if bytes.len() < HEADER_SIZE {
+            return None;
+        }
+
+        let size = u32::from_ne_bytes(bytes[..4].try_into().unwrap()) as usize;
+        let type_ = u32::from_ne_bytes(bytes[4..8].try_into().unwrap());
+        if !(spa_sys::SPA_TYPE_START + 1..spa_sys::SPA_TYPE_LAST).contains(&type_) {
+            return None;
+        }
+
+        let len = HEADER_SIZE.checked_add(size)?.checked_next_multiple_of(8)?;
+        let bytes = bytes.get(..len)?;
+        data.clear();
+        data.extend(
+            bytes
+                .chunks_exact(8)
+                .map(|chunk| u64::from_ne_bytes(chunk.try_into().unwrap())),
+        );
+
+        Some(())
+    }
+
+    pub fn as_pod(&self) -> &Pod {
+        // Safety: `data` is aligned and always contains a complete pod including its padding.
+        unsafe { Pod::from_raw(self.data.as_ptr().cast()) }
+    }
+
+    /// Get the bytes of the pod, including its padding.
+    pub fn as_bytes(&self) -> &[u8] {
+        // Safety: u64 words can be read as bytes.
+        unsafe { std::slice::from_raw_parts(self.data.as_ptr().cast(), self.data.len() * 8) }
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl std::ops::Deref for OwnedPod {
+    type Target = Pod;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_pod()
+    }
+}
+
+impl AsRef<Pod> for OwnedPod {
+    fn as_ref(&self) -> &Pod {
+        self.as_pod()
+    }
+}
+
+impl std::borrow::Borrow<Pod> for OwnedPod {
+    fn borrow(&self) -> &Pod {
+        self.as_pod()
+    }
+}
+
+impl ToOwned for Pod {
+    type Owned = OwnedPod;
+
+    fn to_owned(&self) -> OwnedPod {
+        let size: usize = self.size().try_into().unwrap();
+        let len = std::mem::size_of::<spa_sys::spa_pod>() + size;
+        let mut data = vec![0u64; len.div_ceil(8)];
+
+        // Safety: `data` is big enough for the pod, and the padding is left zeroed.
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                self.as_raw_ptr().cast::<u8>(),
+                data.as_mut_ptr().cast::<u8>(),
+                len,
+            );
+        }
+
+        OwnedPod { data }
+    }
+}
+
+impl std::fmt::Debug for OwnedPod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self.as_pod(), f)
+    }
+}
+
+impl From<OwnedPod> for Vec<u8> {
+    fn from(value: OwnedPod) -> Self {
+        value.into_inner()
+    }
+}
+
 /// A transparent wrapper around a `spa_sys::spa_pod_struct`.
 #[repr(transparent)]
 pub struct PodStruct(spa_sys::spa_pod_struct);
@@ -473,9 +703,90 @@ impl PodStruct {
         unsafe { Pod::from_raw(addr_of!(self.0.pod)) }
     }
 
+    /// Iterate over the fields of the struct.
+    ///
+    /// The iteration stops at the first field which does not fit in the struct,
+    /// use [`check`](Self::check) to tell whether the struct is truncated.
     pub fn fields(&self) -> PodStructIter<'_> {
         PodStructIter::new(self)
     }
+
+    /// The number of fields of the struct which fit in it.
+    pub fn len(&self) -> usize {
+        self.fields().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields().next().is_none()
+    }
+
+    /// Check that the fields of the struct fill its body, returning the number of fields.
+    ///
+    /// This fails with `EPROTO` if the struct ends with a truncated field.
+    pub fn check(&self) -> Result<usize, Errno> {
+        let mut fields = self.fields();
+        let len = fields.by_ref().count();
+        if fields.is_truncated() {
+            Err(Errno::EPROTO)
+        } else {
+            Ok(len)
+        }
+    }
+
+    /// The field at `index`, or `None` if the struct has less fields.
+    pub fn field(&self, index: usize) -> Option<&Pod> {
+        self.try_field(index).ok()
+    }
+
+    /// The field at `index`.
+    ///
+    /// This fails with `ENOENT` if the struct has less fields, and with `EPROTO`
+    /// if the struct is truncated before the field.
+    pub fn try_field(&self, index: usize) -> Result<&Pod, Errno> {
+        let mut fields = self.fields();
+        match fields.nth(index) {
+            Some(field) => Ok(field),
+            None if fields.is_truncated() => Err(Errno::EPROTO),
+            None => Err(Errno::ENOENT),
+        }
+    }
+
+    /// The field at `index` as a bool, failing with `EINVAL` if it has another type.
+    pub fn field_bool(&self, index: usize) -> Result<bool, Errno> {
+        self.try_field(index)?.get_bool()
+    }
+
+    pub fn field_id(&self, index: usize) -> Result<Id, Errno> {
+        self.try_field(index)?.get_id()
+    }
+
+    pub fn field_int(&self, index: usize) -> Result<i32, Errno> {
+        self.try_field(index)?.get_int()
+    }
+
+    pub fn field_long(&self, index: usize) -> Result<i64, Errno> {
+        self.try_field(index)?.get_long()
+    }
+
+    pub fn field_float(&self, index: usize) -> Result<f32, Errno> {
+        self.try_field(index)?.get_float()
+    }
+
+    pub fn field_double(&self, index: usize) -> Result<f64, Errno> {
+        self.try_field(index)?.get_double()
+    }
+
+    pub fn field_string(&self, index: usize) -> Result<&CStr, Errno> {
+        self.try_field(index)?.get_string()
+    }
+
+    pub fn field_fraction(&self, index: usize) -> Result<Fraction, Errno> {
+        self.try_field(index)?.get_fraction()
+    }
+
+    pub fn field_struct(&self, index: usize) -> Result<&PodStruct, Errno> {
+        self.try_field(index)?.as_struct()
+    }
 }
 
 impl<'p> TryFrom<&'p Pod> for &'p PodStruct {
@@ -506,6 +817,12 @@ impl<'s> PodStructIter<'s> {
             next: first_field,
         }
     }
+
+    /// Whether the iteration stopped before the end of the struct, because of a truncated field.
+    fn is_truncated(&self) -> bool {
+        let end = self.struct_pod.as_pod().body() as usize + self.struct_pod.0.pod.size as usize;
+        (self.next as usize) < end
+    }
 }
 
 impl<'s> Iterator for PodStructIter<'s> {
@@ -1293,6 +1610,34 @@ impl<'de> PodDeserialize<'de> for Value {
 }
 
 /// A typed pod value.
+///
+/// # Serde
+///
+/// With the `serde` feature, values implement `Serialize` and `Deserialize`.
+/// Values are represented with their variant name as tag, so they can be deserialized back into the same
+/// pod types. Using JSON as an example:
+///
+/// - scalars are tagged with their type: `{"Int": 1}`, `{"Id": 3}`, `{"String": "foo"}`,
+///   `{"Bytes": [1, 2]}`, and `"None"` for [`Value::None`],
+/// - the ids of object properties are named like `pw-dump` does when the SPA type info tables
+///   know them, such as `{"Id": "audio"}` for the media type of a format, and numbers otherwise,
+/// - rectangles and fractions are objects: `{"Rectangle": {"width": 1920, "height": 1080}}`,
+///   `{"Fraction": {"num": 30, "denom": 1}}`,
+/// - arrays are tagged with their element type: `{"Array": {"Float": [0.5, 0.5]}}`,
+/// - structs are lists of values: `{"Struct": [{"Int": 1}, {"String": "foo"}]}`,
+/// - objects name their type, id and property keys the same way:
+///   `{"Object": {"type": "Spa:Pod:Object:Param:Props", "id": "Props", "properties": [{"key": "mute", "flags": 0, "value": {"Bool": true}}]}}`,
+///   the `flags` of properties being optional when deserializing,
+///   and names and numbers both being accepted,
+/// - choices are tagged with their element type, then with their kind:
+///   `{"Choice": {"Int": {"flags": 0, "Range": {"default": 2, "min": 1, "max": 4}}}}`,
+///   with `"None"`, `"Range"`, `"Step"` (with a `step`), `"Enum"` (with `alternatives`)
+///   and `"Flags"` (with `flags`) as kinds.
+///
+/// File descriptors and pointers are only meaningful in the process that created the pod,
+/// so their value is not serialized: file descriptors are represented as `null` and deserialized as `-1`,
+/// pointers as `{"Pointer": {"type": "Spa:Pointer:Buffer"}}` and deserialized as null pointers of the
+/// same type.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     /// no value or a NULL pointer.
@@ -1379,6 +1724,23 @@ pub enum ChoiceValue {
     Fd(Choice<Fd>),
 }
 
+impl ChoiceValue {
+    /// The default value of the choice, used when fixating it.
+    pub fn default_value(&self) -> Value {
+        match self {
+            Self::Bool(choice) => Value::Bool(*choice.1.default_value()),
+            Self::Int(choice) => Value::Int(*choice.1.default_value()),
+            Self::Long(choice) => Value::Long(*choice.1.default_value()),
+            Self::Float(choice) => Value::Float(*choice.1.default_value()),
+            Self::Double(choice) => Value::Double(*choice.1.default_value()),
+            Self::Id(choice) => Value::Id(*choice.1.default_value()),
+            Self::Rectangle(choice) => Value::Rectangle(*choice.1.default_value()),
+            Self::Fraction(choice) => Value::Fraction(*choice.1.default_value()),
+            Self::Fd(choice) => Value::Fd(*choice.1.default_value()),
+        }
+    }
+}
+
 /// An object from a pod.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Object {
@@ -1464,7 +1826,9 @@ bitflags! {
         /// Property is mandatory.
         const MANDATORY = spa_sys::SPA_POD_PROP_FLAG_MANDATORY;
         /// Property choices need no fixation.
-        #[cfg(feature = "v0_3_33")]
+        ///
+        /// Used by the modifier of video formats to ask the peer to pick a modifier
+        /// from the choice itself, instead of taking the default one.
         const DONT_FIXATE = spa_sys::SPA_POD_PROP_FLAG_DONT_FIXATE;
     }
 }
@@ -1589,3 +1953,13 @@ macro_rules! __property__ {
 }
 #[doc(inline)]
 pub use __property__ as property;
+
+fn pod_size(len: usize) -> u32 {
+    len.try_into().expect("pod too large")
+}
+
+/// Append `bytes`, padded with zeroes to a multiple of 8 bytes.
+fn push_padded(data: &mut Vec<u8>, bytes: &[u8]) {
+    data.extend_from_slice(bytes);
+    data.resize(data.len() + (8 - bytes.len() % 8) % 8, 0);
+}
@@ -0,0 +1,827 @@
+use std::io::{Cursor, Seek, Write};
+
+use cookie_factory::GenError;
+
+use super::{
+    serialize::PodSerializer, CanonicalFixedSizedPod, ChoiceValue, Object, OwnedPod, Pod, Property,
+    PropertyFlags, Value, ValueArray,
+};
+use crate::{
+    param::{
+        audio::AudioFormat,
+        format::{FormatProperties, MediaSubtype, MediaType},
+        video::VideoFormat,
+        ParamType,
+    },
+    utils::{Choice, ChoiceEnum, ChoiceFlags, Fd, Fraction, Id, Rectangle, SpaTypes},
+};
+
+/// A key usable for a property added through an [`ObjectBuilder`].
+pub trait ObjectPropertyKey {
+    /// The raw key of the property.
+    fn key(&self) -> u32;
+
+    /// The object type this key is defined for, or `None` if it can be used with any object.
+    fn object_type(&self) -> Option<SpaTypes>;
+}
+
+impl ObjectPropertyKey for FormatProperties {
+    fn key(&self) -> u32 {
+        self.as_raw()
+    }
+
+    fn object_type(&self) -> Option<SpaTypes> {
+        Some(SpaTypes::ObjectParamFormat)
+    }
+}
+
+impl ObjectPropertyKey for u32 {
+    fn key(&self) -> u32 {
+        *self
+    }
+
+    fn object_type(&self) -> Option<SpaTypes> {
+        None
+    }
+}
+
+/// A type that can be used as the value of a choice added through an [`ObjectBuilder`].
+pub trait ChoiceElement {
+    /// The pod type the value is serialized as.
+    type Pod: CanonicalFixedSizedPod;
+
+    fn into_pod(self) -> Self::Pod;
+
+    /// Wrap a choice of [`Self::Pod`] into the matching [`ChoiceValue`] variant.
+    fn choice_value(choice: Choice<Self::Pod>) -> ChoiceValue;
+}
+
+macro_rules! impl_choice_element {
+    ($($type_:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl ChoiceElement for $type_ {
+                type Pod = Self;
+
+                fn into_pod(self) -> Self::Pod {
+                    self
+                }
+
+                fn choice_value(choice: Choice<Self::Pod>) -> ChoiceValue {
+                    ChoiceValue::$variant(choice)
+                }
+            }
+
+            impl From<$type_> for Value {
+                fn from(value: $type_) -> Self {
+                    Value::$variant(value)
+                }
+            }
+        )*
+    };
+}
+
+impl_choice_element!(
+    bool => Bool,
+    i32 => Int,
+    i64 => Long,
+    f32 => Float,
+    f64 => Double,
+    Id => Id,
+    Rectangle => Rectangle,
+    Fraction => Fraction,
+    Fd => Fd,
+);
+
+macro_rules! impl_id_choice_element {
+    ($($type_:ty),* $(,)?) => {
+        $(
+            impl ChoiceElement for $type_ {
+                type Pod = Id;
+
+                fn into_pod(self) -> Self::Pod {
+                    Id(self.as_raw())
+                }
+
+                fn choice_value(choice: Choice<Self::Pod>) -> ChoiceValue {
+                    ChoiceValue::Id(choice)
+                }
+            }
+
+            impl From<$type_> for Value {
+                fn from(value: $type_) -> Self {
+                    Value::Id(Id(value.as_raw()))
+                }
+            }
+        )*
+    };
+}
+
+impl_id_choice_element!(MediaType, MediaSubtype, AudioFormat, VideoFormat);
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_owned())
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Value::Bytes(value)
+    }
+}
+
+impl From<ValueArray> for Value {
+    fn from(value: ValueArray) -> Self {
+        Value::ValueArray(value)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Self {
+        Value::Struct(value)
+    }
+}
+
+impl From<Object> for Value {
+    fn from(value: Object) -> Self {
+        Value::Object(value)
+    }
+}
+
+impl From<ChoiceValue> for Value {
+    fn from(value: ChoiceValue) -> Self {
+        Value::Choice(value)
+    }
+}
+
+impl<T: ChoiceElement<Pod = T>> From<Choice<T>> for ChoiceValue {
+    fn from(value: Choice<T>) -> Self {
+        T::choice_value(value)
+    }
+}
+
+impl<T: ChoiceElement<Pod = T>> From<Choice<T>> for Value {
+    fn from(value: Choice<T>) -> Self {
+        Value::Choice(value.into())
+    }
+}
+
+/// A builder for object pods, such as the ones used for params.
+///
+/// ```
+/// use libspa::{
+///     param::{audio::AudioFormat, format::{FormatProperties, MediaSubtype, MediaType}, ParamType},
+///     pod::ObjectBuilder,
+///     utils::SpaTypes,
+/// };
+///
+/// let pod = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+///     .prop(FormatProperties::MediaType, MediaType::Audio)
+///     .prop(FormatProperties::MediaSubtype, MediaSubtype::Raw)
+///     .prop_choice_enum(
+///         FormatProperties::AudioFormat,
+///         AudioFormat::F32LE,
+///         [AudioFormat::F32LE, AudioFormat::S16LE],
+///     )
+///     .prop_choice_range(FormatProperties::AudioRate, 48000, 8000, 192000)
+///     .build();
+///
+/// assert!(pod.is_object());
+/// ```
+///
+/// Objects can be nested by passing a built [`Object`] (see [`ObjectBuilder::into_object`])
+/// as the value of a property.
+///
+/// In debug builds, adding a property whose key is defined for another object type panics.
+///
+/// To build pods repeatedly without allocating, for example from a realtime callback,
+/// keep the builder around and use [`build_ref`](Self::build_ref) and [`reset`](Self::reset),
+/// which reuse the memory of the previous builds.
+#[derive(Clone)]
+pub struct ObjectBuilder {
+    object: Object,
+    // serialized object, and its aligned copy returned by `build_ref`
+    scratch: Vec<u8>,
+    built: Option<OwnedPod>,
+}
+
+impl ObjectBuilder {
+    pub fn new(type_: SpaTypes, id: ParamType) -> Self {
+        Self {
+            object: Object {
+                type_: type_.as_raw(),
+                id: id.as_raw(),
+                properties: Vec::new(),
+            },
+            scratch: Vec::new(),
+            built: None,
+        }
+    }
+
+    /// Remove all properties, keeping the memory allocated for them and for the previous builds.
+    pub fn reset(&mut self) {
+        self.object.properties.clear();
+    }
+
+    /// Add a property.
+    pub fn prop(self, key: impl ObjectPropertyKey, value: impl Into<Value>) -> Self {
+        self.prop_with_flags(key, PropertyFlags::empty(), value)
+    }
+
+    /// Add a property with the given flags.
+    ///
+    /// For example, the modifier of a video format proposing DMA-BUF buffers is
+    /// [`MANDATORY`](PropertyFlags::MANDATORY) and [`DONT_FIXATE`](PropertyFlags::DONT_FIXATE).
+    /// The flags of parsed properties are available in [`Property::flags`].
+    pub fn prop_with_flags(
+        mut self,
+        key: impl ObjectPropertyKey,
+        flags: PropertyFlags,
+        value: impl Into<Value>,
+    ) -> Self {
+        if let Some(object_type) = key.object_type() {
+            debug_assert_eq!(
+                object_type.as_raw(),
+                self.object.type_,
+                "property key {} is not valid for object type {:?}",
+                key.key(),
+                SpaTypes::from_raw(self.object.type_),
+            );
+        }
+
+        self.object.properties.push(Property {
+            key: key.key(),
+            flags,
+            value: value.into(),
+        });
+        self
+    }
+
+    fn prop_choice<T: ChoiceElement>(
+        self,
+        key: impl ObjectPropertyKey,
+        choice: ChoiceEnum<T::Pod>,
+    ) -> Self {
+        let choice = T::choice_value(Choice(ChoiceFlags::empty(), choice));
+        self.prop(key, choice)
+    }
+
+    /// Add a property whose value is a range choice.
+    pub fn prop_choice_range<T: ChoiceElement>(
+        self,
+        key: impl ObjectPropertyKey,
+        default: T,
+        min: T,
+        max: T,
+    ) -> Self {
+        self.prop_choice::<T>(
+            key,
+            ChoiceEnum::Range {
+                default: default.into_pod(),
+                min: min.into_pod(),
+                max: max.into_pod(),
+            },
+        )
+    }
+
+    /// Add a property whose value is a range choice with step.
+    pub fn prop_choice_step<T: ChoiceElement>(
+        self,
+        key: impl ObjectPropertyKey,
+        default: T,
+        min: T,
+        max: T,
+        step: T,
+    ) -> Self {
+        self.prop_choice::<T>(
+            key,
+            ChoiceEnum::Step {
+                default: default.into_pod(),
+                min: min.into_pod(),
+                max: max.into_pod(),
+                step: step.into_pod(),
+            },
+        )
+    }
+
+    /// Add a property whose value is an enum choice.
+    pub fn prop_choice_enum<T: ChoiceElement>(
+        self,
+        key: impl ObjectPropertyKey,
+        default: T,
+        alternatives: impl IntoIterator<Item = T>,
+    ) -> Self {
+        self.prop_choice::<T>(
+            key,
+            ChoiceEnum::Enum {
+                default: default.into_pod(),
+                alternatives: alternatives
+                    .into_iter()
+                    .map(ChoiceElement::into_pod)
+                    .collect(),
+            },
+        )
+    }
+
+    /// Add a property whose value is a flags choice.
+    pub fn prop_choice_flags<T: ChoiceElement>(
+        self,
+        key: impl ObjectPropertyKey,
+        default: T,
+        flags: impl IntoIterator<Item = T>,
+    ) -> Self {
+        self.prop_choice::<T>(
+            key,
+            ChoiceEnum::Flags {
+                default: default.into_pod(),
+                flags: flags.into_iter().map(ChoiceElement::into_pod).collect(),
+            },
+        )
+    }
+
+    /// Return the object built so far, e.g. to nest it into another object.
+    pub fn into_object(self) -> Object {
+        self.object
+    }
+
+    /// Serialize the object into a newly allocated [`OwnedPod`].
+    pub fn build(self) -> OwnedPod {
+        let (cursor, _) = self
+            .build_into(Cursor::new(Vec::new()))
+            .expect("serializing into a Vec cannot fail");
+        OwnedPod::from_bytes(cursor.into_inner()).expect("serialized object is a valid pod")
+    }
+
+    /// Serialize the object into memory owned by the builder, and return the built pod.
+    ///
+    /// Unlike [`build`](Self::build), the builder is kept, and the memory is reused by the
+    /// next calls, so nothing is allocated once it is large enough for the built objects.
+    ///
+    /// ```
+    /// use libspa::{
+    ///     param::{format::FormatProperties, ParamType},
+    ///     pod::ObjectBuilder,
+    ///     utils::SpaTypes,
+    /// };
+    ///
+    /// let mut builder = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::Format);
+    /// for rate in [44100, 48000] {
+    ///     builder.reset();
+    ///     builder = builder.prop(FormatProperties::AudioRate, rate);
+    ///     let pod = builder.build_ref();
+    ///     assert!(pod.is_object());
+    /// }
+    /// ```
+    pub fn build_ref(&mut self) -> &Pod {
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.clear();
+        let (cursor, _) = self
+            .build_into(Cursor::new(scratch))
+            .expect("serializing into a Vec cannot fail");
+        self.scratch = cursor.into_inner();
+
+        match &mut self.built {
+            Some(built) => built
+                .replace_bytes(&self.scratch)
+                .expect("serialized object is a valid pod"),
+            None => {
+                self.built = Some(
+                    OwnedPod::from_bytes(&self.scratch).expect("serialized object is a valid pod"),
+                )
+            }
+        }
+        self.built.as_ref().unwrap()
+    }
+
+    /// Serialize the object into `out`, returning it together with the number of bytes written.
+    ///
+    /// `out` can be a growable buffer, like a `Cursor<Vec<u8>>`, or a caller provided one like `Cursor<&mut [u8]>`,
+    /// in which case an error is returned if the object does not fit.
+    /// A `Cursor<&mut Vec<u8>>` reuses the memory of the vector, and appends to it when the cursor is positioned
+    /// at its end, for example to pack several pods into one arena.
+    pub fn build_into<O: Write + Seek>(&self, out: O) -> Result<(O, u64), GenError> {
+        PodSerializer::serialize(out, &self.object)
+    }
+}
+
+impl std::fmt::Debug for ObjectBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObjectBuilder")
+            .field("object", &self.object)
+            .finish()
+    }
+}
+
+impl From<ObjectBuilder> for Object {
+    fn from(value: ObjectBuilder) -> Self {
+        value.into_object()
+    }
+}
+
+impl From<ObjectBuilder> for Value {
+    fn from(value: ObjectBuilder) -> Self {
+        Value::Object(value.into_object())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pod::{deserialize::PodDeserializer, Pod};
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn build_enum_format() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+            .prop(FormatProperties::MediaType, MediaType::Audio)
+            .prop(FormatProperties::MediaSubtype, MediaSubtype::Raw)
+            .prop_choice_enum(
+                FormatProperties::AudioFormat,
+                AudioFormat::F32LE,
+                [AudioFormat::F32LE, AudioFormat::S16LE],
+            )
+            .prop_choice_range(FormatProperties::AudioRate, 48000, 8000, 192000)
+            .prop(
+                FormatProperties::AudioPosition,
+                ValueArray::Id(vec![
+                    Id(spa_sys::SPA_AUDIO_CHANNEL_FL),
+                    Id(spa_sys::SPA_AUDIO_CHANNEL_FR),
+                ]),
+            )
+            .build();
+
+        let expected = Value::Object(Object {
+            type_: spa_sys::SPA_TYPE_OBJECT_Format,
+            id: spa_sys::SPA_PARAM_EnumFormat,
+            properties: vec![
+                Property::new(
+                    spa_sys::SPA_FORMAT_mediaType,
+                    Value::Id(Id(spa_sys::SPA_MEDIA_TYPE_audio)),
+                ),
+                Property::new(
+                    spa_sys::SPA_FORMAT_mediaSubtype,
+                    Value::Id(Id(spa_sys::SPA_MEDIA_SUBTYPE_raw)),
+                ),
+                Property::new(
+                    spa_sys::SPA_FORMAT_AUDIO_format,
+                    Value::Choice(ChoiceValue::Id(Choice(
+                        ChoiceFlags::empty(),
+                        ChoiceEnum::Enum {
+                            default: Id(spa_sys::SPA_AUDIO_FORMAT_F32_LE),
+                            alternatives: vec![
+                                Id(spa_sys::SPA_AUDIO_FORMAT_F32_LE),
+                                Id(spa_sys::SPA_AUDIO_FORMAT_S16_LE),
+                            ],
+                        },
+                    ))),
+                ),
+                Property::new(
+                    spa_sys::SPA_FORMAT_AUDIO_rate,
+                    Value::Choice(ChoiceValue::Int(Choice(
+                        ChoiceFlags::empty(),
+                        ChoiceEnum::Range {
+                            default: 48000,
+                            min: 8000,
+                            max: 192000,
+                        },
+                    ))),
+                ),
+                Property::new(
+                    spa_sys::SPA_FORMAT_AUDIO_position,
+                    Value::ValueArray(ValueArray::Id(vec![
+                        Id(spa_sys::SPA_AUDIO_CHANNEL_FL),
+                        Id(spa_sys::SPA_AUDIO_CHANNEL_FR),
+                    ])),
+                ),
+            ],
+        });
+
+        assert_eq!(
+            PodDeserializer::deserialize_any_from(pod.as_bytes()),
+            Ok((&[] as &[u8], expected))
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn build_choices() {
+        let fraction = |num, denom| Fraction { num, denom };
+        let rectangle = |width, height| Rectangle { width, height };
+
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+            .prop(
+                FormatProperties::VideoSize,
+                Choice::range(rectangle(640, 480), rectangle(1, 1), rectangle(4096, 4096)),
+            )
+            .prop(
+                FormatProperties::VideoFramerate,
+                Choice::enumerate(fraction(30, 1), [fraction(30, 1), fraction(60, 1)]),
+            )
+            .prop(FormatProperties::AudioChannels, Choice::step(2, 1, 8, 1))
+            .prop(FormatProperties::AudioRate, Choice::none(48000))
+            .prop(
+                FormatProperties::VideoModifier,
+                Choice::flags(0_i64, [1_i64, 2]),
+            )
+            .prop(
+                FormatProperties::VideoFormat,
+                Choice::enumerate(Id(1), [Id(1), Id(2)]),
+            )
+            .build();
+
+        let (_, value) = PodDeserializer::deserialize_any_from(pod.as_bytes()).unwrap();
+        let Value::Object(object) = value else {
+            panic!("expected an object, got {value:?}");
+        };
+        let values: Vec<_> = object
+            .properties
+            .into_iter()
+            .map(|prop| prop.value)
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                Choice::range(rectangle(640, 480), rectangle(1, 1), rectangle(4096, 4096)).into(),
+                Choice::enumerate(fraction(30, 1), [fraction(30, 1), fraction(60, 1)]).into(),
+                Choice::step(2, 1, 8, 1).into(),
+                Choice::none(48000).into(),
+                Choice::flags(0_i64, [1_i64, 2]).into(),
+                Choice::enumerate(Id(1), [Id(1), Id(2)]).into(),
+            ] as Vec<Value>
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn fixate() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+            .prop(FormatProperties::MediaType, MediaType::Audio)
+            .prop_choice_enum(
+                FormatProperties::AudioFormat,
+                AudioFormat::F32LE,
+                [AudioFormat::F32LE, AudioFormat::S16LE],
+            )
+            .prop_with_flags(
+                FormatProperties::AudioRate,
+                PropertyFlags::MANDATORY,
+                Choice::range(48000, 8000, 192000),
+            )
+            .build();
+
+        let fixated = pod.fixate().unwrap();
+        let (_, value) = PodDeserializer::deserialize_any_from(fixated.as_bytes()).unwrap();
+        assert_eq!(
+            value,
+            Value::Object(Object {
+                type_: spa_sys::SPA_TYPE_OBJECT_Format,
+                id: spa_sys::SPA_PARAM_EnumFormat,
+                properties: vec![
+                    Property::new(
+                        spa_sys::SPA_FORMAT_mediaType,
+                        Value::Id(Id(spa_sys::SPA_MEDIA_TYPE_audio)),
+                    ),
+                    Property::new(
+                        spa_sys::SPA_FORMAT_AUDIO_format,
+                        Value::Id(Id(spa_sys::SPA_AUDIO_FORMAT_F32_LE)),
+                    ),
+                    Property {
+                        key: spa_sys::SPA_FORMAT_AUDIO_rate,
+                        flags: PropertyFlags::MANDATORY,
+                        value: Value::Int(48000),
+                    },
+                ],
+            })
+        );
+
+        // only objects can be fixated
+        let not_object = crate::pod::OwnedPod::from_bytes(
+            crate::pod::serialize::PodSerializer::serialize(
+                std::io::Cursor::new(Vec::new()),
+                &Value::Int(1),
+            )
+            .unwrap()
+            .0
+            .into_inner(),
+        )
+        .unwrap();
+        assert_eq!(not_object.fixate(), Err(nix::errno::Errno::EINVAL));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn filter() {
+        let ours = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+            .prop(FormatProperties::MediaType, MediaType::Audio)
+            .prop_choice_enum(
+                FormatProperties::AudioFormat,
+                AudioFormat::F32LE,
+                [AudioFormat::F32LE, AudioFormat::S16LE],
+            )
+            .prop(
+                FormatProperties::AudioRate,
+                Choice::range(48000, 8000, 192000),
+            )
+            .build();
+        let theirs = || {
+            ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+                .prop(FormatProperties::MediaType, MediaType::Audio)
+                .prop(FormatProperties::AudioRate, 44100)
+        };
+
+        let filtered = ours
+            .filter(
+                &theirs()
+                    .prop_choice_enum(
+                        FormatProperties::AudioFormat,
+                        AudioFormat::S16LE,
+                        [AudioFormat::S16LE, AudioFormat::S32LE],
+                    )
+                    .prop(FormatProperties::AudioChannels, 2)
+                    .build(),
+            )
+            .unwrap();
+        let (_, value) =
+            PodDeserializer::deserialize_any_from(filtered.fixate().unwrap().as_bytes()).unwrap();
+        assert_eq!(
+            value,
+            Value::Object(Object {
+                type_: spa_sys::SPA_TYPE_OBJECT_Format,
+                id: spa_sys::SPA_PARAM_EnumFormat,
+                properties: vec![
+                    Property::new(
+                        spa_sys::SPA_FORMAT_mediaType,
+                        Value::Id(Id(spa_sys::SPA_MEDIA_TYPE_audio)),
+                    ),
+                    Property::new(
+                        spa_sys::SPA_FORMAT_AUDIO_format,
+                        Value::Id(Id(spa_sys::SPA_AUDIO_FORMAT_S16_LE)),
+                    ),
+                    Property::new(spa_sys::SPA_FORMAT_AUDIO_rate, Value::Int(44100)),
+                    Property::new(spa_sys::SPA_FORMAT_AUDIO_channels, Value::Int(2)),
+                ],
+            })
+        );
+
+        // no common format
+        let disjoint = theirs()
+            .prop(FormatProperties::AudioFormat, AudioFormat::S32LE)
+            .build();
+        assert_eq!(ours.filter(&disjoint), Err(nix::errno::Errno::EINVAL));
+
+        // the mandatory channels are missing from our format
+        let mandatory = theirs()
+            .prop_with_flags(FormatProperties::AudioChannels, PropertyFlags::MANDATORY, 2)
+            .build();
+        assert_eq!(ours.filter(&mandatory), Err(nix::errno::Errno::EINVAL));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn build_nested_object() {
+        let inner = ObjectBuilder::new(SpaTypes::ObjectParamProps, ParamType::Props)
+            .prop(spa_sys::SPA_PROP_volume, 0.5_f32)
+            .prop(spa_sys::SPA_PROP_device, "hw:0");
+
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamProps, ParamType::Props)
+            .prop(spa_sys::SPA_PROP_params, inner.clone())
+            .build();
+
+        let (_, value) = PodDeserializer::deserialize_any_from(pod.as_bytes()).unwrap();
+        let Value::Object(object) = value else {
+            panic!("expected an object, got {value:?}");
+        };
+        assert_eq!(
+            object.properties,
+            vec![Property::new(
+                spa_sys::SPA_PROP_params,
+                Value::Object(inner.into_object())
+            )]
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn build_into_slice() {
+        let builder = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::Format)
+            .prop(FormatProperties::MediaType, MediaType::Video);
+
+        let mut small = [0u8; 8];
+        assert!(builder
+            .clone()
+            .build_into(Cursor::new(&mut small[..]))
+            .is_err());
+
+        let mut data = [0u8; 64];
+        let (_, len) = builder
+            .clone()
+            .build_into(Cursor::new(&mut data[..]))
+            .unwrap();
+        assert_eq!(&data[..len as usize], builder.build().as_bytes());
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    fn invalid_key_for_object_type() {
+        let _ = ObjectBuilder::new(SpaTypes::ObjectParamProps, ParamType::Props)
+            .prop(FormatProperties::MediaType, MediaType::Audio);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn to_owned() {
+        let mut stored = Vec::new();
+        let mut callback = |pod: &Pod| stored.push(pod.to_owned());
+
+        // pods passed to callbacks only live during the call
+        for rate in [44100, 48000] {
+            let bytes = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::Format)
+                .prop(FormatProperties::AudioRate, rate)
+                .build()
+                .into_inner();
+            callback(Pod::from_bytes(&bytes).unwrap());
+        }
+        // a string pod whose size is not a multiple of 8
+        let (cursor, _) = crate::pod::serialize::PodSerializer::serialize(
+            std::io::Cursor::new(Vec::new()),
+            &Value::String("abc".into()),
+        )
+        .unwrap();
+        callback(Pod::from_bytes(&cursor.into_inner()).unwrap());
+
+        let rates: Vec<_> = stored[..2]
+            .iter()
+            .map(|pod| {
+                let prop = pod.as_object().unwrap().props().next().unwrap();
+                prop.value().get_int().unwrap()
+            })
+            .collect();
+        assert_eq!(rates, [44100, 48000]);
+        assert_eq!(stored[2].get_string().unwrap().to_str(), Ok("abc"));
+        assert_eq!(stored[2].as_bytes().len() % 8, 0);
+        assert_eq!(stored[2].clone(), stored[2]);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn owned_pod_from_bytes() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::Format)
+            .prop(FormatProperties::AudioRate, 48000)
+            .build();
+        let bytes = pod.as_bytes();
+
+        // trailing bytes are discarded, and unaligned input is copied
+        let mut unaligned = vec![0];
+        unaligned.extend_from_slice(bytes);
+        unaligned.extend_from_slice(&[0; 8]);
+        assert_eq!(OwnedPod::from_bytes(&unaligned[1..]), Some(pod.clone()));
+
+        // incomplete pods and unknown types are rejected
+        assert_eq!(OwnedPod::from_bytes(&bytes[..4]), None);
+        assert_eq!(OwnedPod::from_bytes(&bytes[..bytes.len() - 8]), None);
+        let mut unknown = bytes.to_vec();
+        unknown[4..8].copy_from_slice(&u32::MAX.to_ne_bytes());
+        assert_eq!(OwnedPod::from_bytes(unknown), None);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn build_ref_reuses_memory() {
+        let format = |rate: i32| {
+            ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::Format)
+                .prop(FormatProperties::MediaType, MediaType::Audio)
+                .prop(FormatProperties::AudioRate, rate)
+        };
+
+        let mut builder = format(44100);
+        let first = builder.build_ref().to_owned();
+        assert_eq!(first, format(44100).build());
+        let ptr = builder.build_ref().as_raw_ptr();
+
+        builder.reset();
+        builder = builder.prop(FormatProperties::AudioChannels, 2);
+        assert_eq!(
+            builder.build_ref().as_bytes(),
+            ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::Format)
+                .prop(FormatProperties::AudioChannels, 2)
+                .build()
+                .as_bytes()
+        );
+        // a smaller object is built in the same memory
+        assert_eq!(builder.build_ref().as_raw_ptr(), ptr);
+
+        // appending to an arena
+        let mut arena = Vec::new();
+        let (_, len) = builder.build_into(Cursor::new(&mut arena)).unwrap();
+        let mut cursor = Cursor::new(&mut arena);
+        cursor.seek(std::io::SeekFrom::End(0)).unwrap();
+        format(48000).build_into(cursor).unwrap();
+        assert_eq!(&arena[len as usize..], format(48000).build().as_bytes());
+    }
+}
@@ -5,11 +5,15 @@ use std::{
     ffi::{c_char, c_double, c_float, c_void, CStr},
     marker::PhantomData,
     mem::MaybeUninit,
+    ptr::NonNull,
 };
 
 use nix::errno::Errno;
 
-use crate::utils::{Fraction, Id, Rectangle};
+use crate::{
+    pod::{Pod, PodProp},
+    utils::{Fraction, Id, Rectangle, SpaTypes},
+};
 
 /// Low-level wrapper around `spa_pod_parser`.
 ///
@@ -280,7 +284,7 @@ impl<'d> Parser<'d> {
             let mut rect: MaybeUninit<spa_sys::spa_rectangle> = MaybeUninit::uninit();
             let res = spa_sys::spa_pod_parser_get_rectangle(self.as_raw_ptr(), rect.as_mut_ptr());
             if res >= 0 {
-                Ok(rect.assume_init())
+                Ok(Rectangle::from_raw(rect.assume_init()))
             } else {
                 Err(Errno::from_i32(-res))
             }
@@ -292,7 +296,7 @@ impl<'d> Parser<'d> {
             let mut frac: MaybeUninit<spa_sys::spa_fraction> = MaybeUninit::uninit();
             let res = spa_sys::spa_pod_parser_get_fraction(self.as_raw_ptr(), frac.as_mut_ptr());
             if res >= 0 {
-                Ok(frac.assume_init())
+                Ok(Fraction::from_raw(frac.assume_init()))
             } else {
                 Err(Errno::from_i32(-res))
             }
@@ -537,11 +541,232 @@ macro_rules! __parser_get__ {
     // TODO: Object
     // TODO: ($parser:expr, Option( $type_:tt $val:tt )) or similar for optional values
 }
+/// Error returned by [`PodParser`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// There is no value left in the pod, struct or object being parsed.
+    End,
+    /// The next value is not of the requested type.
+    WrongType,
+    /// The next value does not fit in the pod, struct or object being parsed.
+    Malformed,
+    /// [`PodParser::pop`] was called without a pushed struct or object.
+    NotPushed,
+}
+
+impl ParseError {
+    fn from_errno(err: Errno) -> Self {
+        match err {
+            Errno::EPIPE => Self::End,
+            Errno::EINVAL | Errno::EPROTO => Self::WrongType,
+            _ => Self::Malformed,
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::End => "no value left to parse",
+            Self::WrongType => "value has an unexpected type",
+            Self::Malformed => "malformed value",
+            Self::NotPushed => "no struct or object to pop",
+        })
+    }
+}
+
+/// Safe sequential parser of pods with a known layout, like `spa_pod_parser` is used in C.
+///
+/// Each `get_*` method reads the next value and advances past it if it has the requested type.
+/// All reads are checked against the bounds of the pod and of the struct or object being parsed,
+/// so malformed pods result in errors instead of out of bounds reads.
+/// Reading values does not allocate.
+///
+/// ```
+/// # use libspa::pod::{parser::PodParser, serialize::PodSerializer, Pod, Value};
+/// # let (cursor, _) = PodSerializer::serialize(
+/// #     std::io::Cursor::new(Vec::new()),
+/// #     &Value::Struct(vec![Value::Int(1), Value::String("name".into())]),
+/// # )
+/// # .unwrap();
+/// # let bytes = cursor.into_inner();
+/// # let pod = Pod::from_bytes(&bytes).unwrap();
+/// let mut parser = PodParser::new(pod);
+/// parser.push_struct()?;
+/// let id = parser.get_int()?;
+/// let name = parser.get_string()?;
+/// parser.pop()?;
+/// # assert_eq!((id, name.to_str().unwrap()), (1, "name"));
+/// # Ok::<(), libspa::pod::parser::ParseError>(())
+/// ```
+pub struct PodParser<'d> {
+    parser: Parser<'d>,
+    // The parser points to the pushed frames, so they are kept at a stable address.
+    frames: Vec<NonNull<spa_sys::spa_pod_frame>>,
+}
+
+impl<'d> PodParser<'d> {
+    pub fn new(pod: &'d Pod) -> Self {
+        Self {
+            parser: Parser::from_pod(pod),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Enter the next value, which must be a struct.
+    ///
+    /// The fields of the struct are then read one by one until [`pop`](Self::pop) is called.
+    pub fn push_struct(&mut self) -> Result<(), ParseError> {
+        self.push(|parser, frame| unsafe { parser.push_struct(frame) })
+    }
+
+    /// Enter the next value, which must be an object of type `type_`, and return the id of the object.
+    ///
+    /// The properties of the object are then read with [`get_prop`](Self::get_prop) until
+    /// [`pop`](Self::pop) is called.
+    pub fn push_object(&mut self, type_: SpaTypes) -> Result<Id, ParseError> {
+        self.push(|parser, frame| unsafe { parser.push_object(frame, type_.as_raw()) })
+    }
+
+    fn push<T>(
+        &mut self,
+        f: impl FnOnce(&mut Parser<'d>, &mut MaybeUninit<spa_sys::spa_pod_frame>) -> Result<T, Errno>,
+    ) -> Result<T, ParseError> {
+        let frame = Box::into_raw(Box::new(MaybeUninit::uninit()));
+        // Safety: The frame was just allocated, and is only freed once popped or on drop.
+        match f(&mut self.parser, unsafe { &mut *frame }) {
+            Ok(res) => {
+                self.frames.push(NonNull::new(frame.cast()).unwrap());
+                Ok(res)
+            }
+            Err(err) => {
+                drop(unsafe { Box::from_raw(frame) });
+                Err(ParseError::from_errno(err))
+            }
+        }
+    }
+
+    /// Leave the struct or object entered last, skipping its remaining values.
+    pub fn pop(&mut self) -> Result<(), ParseError> {
+        let frame = self.frames.pop().ok_or(ParseError::NotPushed)?;
+        // Safety: The frame is the one pushed last, and it was initialized by the push.
+        unsafe {
+            let mut frame = Box::from_raw(frame.as_ptr());
+            self.parser.pop(&mut frame).map_err(ParseError::from_errno)
+        }
+    }
+
+    /// Read the next property of the object entered last.
+    pub fn get_prop(&mut self) -> Result<&'d PodProp, ParseError> {
+        const PROP_SIZE: usize = std::mem::size_of::<spa_sys::spa_pod_prop>();
+        const HEADER_SIZE: usize = std::mem::size_of::<spa_sys::spa_pod>();
+
+        let frame = self.frames.last().ok_or(ParseError::WrongType)?;
+        // Safety: Pushed frames are initialized.
+        let frame = unsafe { frame.as_ref() };
+        if frame.pod.type_ != spa_sys::SPA_TYPE_Object {
+            return Err(ParseError::WrongType);
+        }
+
+        // The parser checked that the object fits in the pod when pushing it.
+        let end = frame.offset as usize + HEADER_SIZE + frame.pod.size as usize;
+        let raw = &mut self.parser.parser;
+        let offset = raw.state.offset as usize;
+        if offset + PROP_SIZE > end {
+            return Err(ParseError::End);
+        }
+
+        // Safety: The prop header is within the object, and offsets in the parser are 8-byte aligned.
+        let prop = unsafe {
+            PodProp::from_raw(
+                raw.data
+                    .cast::<u8>()
+                    .add(offset)
+                    .cast::<spa_sys::spa_pod_prop>(),
+            )
+        };
+        let size = prop.value().size() as usize;
+        if size > end - offset - PROP_SIZE {
+            return Err(ParseError::Malformed);
+        }
+
+        raw.state.offset = (offset + PROP_SIZE + size).next_multiple_of(8) as u32;
+        Ok(prop)
+    }
+
+    pub fn get_bool(&mut self) -> Result<bool, ParseError> {
+        self.parser.get_bool().map_err(ParseError::from_errno)
+    }
+
+    pub fn get_id(&mut self) -> Result<Id, ParseError> {
+        self.parser.get_id().map_err(ParseError::from_errno)
+    }
+
+    pub fn get_int(&mut self) -> Result<i32, ParseError> {
+        self.parser.get_int().map_err(ParseError::from_errno)
+    }
+
+    pub fn get_long(&mut self) -> Result<i64, ParseError> {
+        self.parser.get_long().map_err(ParseError::from_errno)
+    }
+
+    pub fn get_float(&mut self) -> Result<f32, ParseError> {
+        self.parser.get_float().map_err(ParseError::from_errno)
+    }
+
+    pub fn get_double(&mut self) -> Result<f64, ParseError> {
+        self.parser.get_double().map_err(ParseError::from_errno)
+    }
+
+    pub fn get_string(&mut self) -> Result<&'d CStr, ParseError> {
+        self.parser.get_string_raw().map_err(ParseError::from_errno)
+    }
+
+    pub fn get_bytes(&mut self) -> Result<&'d [u8], ParseError> {
+        self.parser.get_bytes().map_err(ParseError::from_errno)
+    }
+
+    pub fn get_fd(&mut self) -> Result<i64, ParseError> {
+        self.parser.get_fd().map_err(ParseError::from_errno)
+    }
+
+    pub fn get_rectangle(&mut self) -> Result<Rectangle, ParseError> {
+        self.parser.get_rectangle().map_err(ParseError::from_errno)
+    }
+
+    pub fn get_fraction(&mut self) -> Result<Fraction, ParseError> {
+        self.parser.get_fraction().map_err(ParseError::from_errno)
+    }
+
+    /// Read the next value as a pod of any type, e.g. to parse it with another parser.
+    pub fn get_pod(&mut self) -> Result<&'d Pod, ParseError> {
+        self.parser.get_pod().map_err(ParseError::from_errno)
+    }
+}
+
+impl Drop for PodParser<'_> {
+    fn drop(&mut self) {
+        for frame in self.frames.drain(..) {
+            drop(unsafe { Box::from_raw(frame.as_ptr()) });
+        }
+    }
+}
+
 pub use __parser_get__ as parser_get;
 
 #[cfg(test)]
 mod tests {
-    use super::{parser_get, Parser};
+    use super::{parser_get, ParseError, Parser, PodParser};
+    use crate::{
+        param::{format::FormatProperties, ParamType},
+        pod::{
+            deserialize::PodDeserializer, serialize::PodSerializer, ObjectBuilder, OwnedPod, Pod,
+            Value,
+        },
+        utils::{Id, SpaTypes},
+    };
 
     // FIXME: The way we construct raw pods here is rather crude and error-prone.
     //        Maybe replace it with the pod builder in the future, and share the tests with it.
@@ -696,4 +921,160 @@ mod tests {
             }
         );
     }
+
+    fn serialize(value: &Value) -> OwnedPod {
+        let (cursor, _) =
+            PodSerializer::serialize(std::io::Cursor::new(Vec::new()), value).unwrap();
+        OwnedPod::from_bytes(cursor.into_inner()).unwrap()
+    }
+
+    fn complex_pod() -> OwnedPod {
+        let object = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::Format)
+            .prop(FormatProperties::AudioRate, 48000)
+            .prop(FormatProperties::AudioChannels, 2)
+            .build();
+        let (_, object) = PodDeserializer::deserialize_any_from(object.as_bytes()).unwrap();
+
+        serialize(&Value::Struct(vec![
+            Value::Int(313),
+            Value::String("node.name".into()),
+            Value::Struct(vec![Value::Long(-1), Value::Bytes(vec![1, 2, 3])]),
+            Value::Fraction(crate::utils::Fraction { num: 1, denom: 60 }),
+            Value::Id(Id(7)),
+            Value::Bool(true),
+            Value::Double(0.5),
+            object,
+        ]))
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn pod_parser() {
+        let pod = complex_pod();
+        let mut parser = PodParser::new(&pod);
+
+        assert_eq!(parser.get_int(), Err(ParseError::WrongType));
+        parser.push_struct().unwrap();
+        assert_eq!(parser.get_string(), Err(ParseError::WrongType));
+        assert_eq!(parser.get_int(), Ok(313));
+        assert_eq!(parser.get_string().unwrap().to_str(), Ok("node.name"));
+        parser.push_struct().unwrap();
+        assert_eq!(parser.get_long(), Ok(-1));
+        assert_eq!(parser.get_bytes(), Ok(&[1u8, 2, 3][..]));
+        assert_eq!(parser.get_int(), Err(ParseError::End));
+        parser.pop().unwrap();
+        assert_eq!(
+            parser.get_fraction(),
+            Ok(crate::utils::Fraction { num: 1, denom: 60 })
+        );
+        assert_eq!(parser.get_id(), Ok(Id(7)));
+        // popping skips the remaining values
+        parser.pop().unwrap();
+        assert_eq!(parser.pop(), Err(ParseError::NotPushed));
+        assert_eq!(parser.get_int(), Err(ParseError::End));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn pod_parser_object() {
+        let pod = complex_pod();
+        let mut parser = PodParser::new(&pod);
+        parser.push_struct().unwrap();
+        for _ in 0..7 {
+            parser.get_pod().unwrap();
+        }
+
+        assert_eq!(parser.get_prop().err(), Some(ParseError::WrongType));
+        assert_eq!(
+            parser.push_object(SpaTypes::ObjectParamProps),
+            Err(ParseError::WrongType)
+        );
+        assert_eq!(
+            parser.push_object(SpaTypes::ObjectParamFormat),
+            Ok(Id(ParamType::Format.as_raw()))
+        );
+        let rate = parser.get_prop().unwrap();
+        assert_eq!(rate.key(), Id(FormatProperties::AudioRate.as_raw()));
+        assert_eq!(rate.value().get_int(), Ok(48000));
+        let channels = parser.get_prop().unwrap();
+        assert_eq!(channels.key(), Id(FormatProperties::AudioChannels.as_raw()));
+        assert_eq!(parser.get_prop().err(), Some(ParseError::End));
+        parser.pop().unwrap();
+        parser.pop().unwrap();
+    }
+
+    /// Read everything readable from the parser, recursing into structs and objects.
+    fn walk(parser: &mut PodParser, depth: u32) {
+        for _ in 0..64 {
+            if depth < 8 && parser.push_struct().is_ok() {
+                walk(parser, depth + 1);
+                let _ = parser.pop();
+                continue;
+            }
+
+            let Ok(pod) = parser.get_pod() else {
+                break;
+            };
+            let mut inner = PodParser::new(pod);
+            if let Ok(object) = pod.as_object() {
+                if depth < 8 && inner.push_object(object.type_()).is_ok() {
+                    while let Ok(prop) = inner.get_prop() {
+                        walk(&mut PodParser::new(prop.value()), depth + 1);
+                    }
+                }
+                continue;
+            }
+
+            let _ = inner.get_bool().is_ok()
+                || inner.get_id().is_ok()
+                || inner.get_int().is_ok()
+                || inner.get_long().is_ok()
+                || inner.get_float().is_ok()
+                || inner.get_double().is_ok()
+                || inner.get_string().map(|s| s.to_bytes().len()).is_ok()
+                || inner.get_bytes().map(|b| b.len()).is_ok()
+                || inner.get_fd().is_ok()
+                || inner.get_rectangle().is_ok()
+                || inner.get_fraction().is_ok();
+        }
+    }
+
+    /// A xorshift generator, so the fuzzing is reproducible.
+    fn random(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn pod_parser_fuzz() {
+        let mut state = 0x2545_f491_4f6c_dd1d;
+        let valid = complex_pod().into_inner();
+        walk(&mut PodParser::new(Pod::from_bytes(&valid).unwrap()), 0);
+
+        // corrupt bytes of a valid pod
+        for _ in 0..2000 {
+            let mut bytes = valid.clone();
+            for _ in 0..1 + random(&mut state) % 8 {
+                let i = random(&mut state) as usize % bytes.len();
+                bytes[i] = random(&mut state) as u8;
+            }
+            if let Some(pod) = OwnedPod::from_bytes(&bytes) {
+                walk(&mut PodParser::new(&pod), 0);
+            }
+        }
+
+        // random bodies with a valid struct header
+        for _ in 0..500 {
+            let len = 8 * (random(&mut state) as usize % 32);
+            let mut bytes = Vec::with_capacity(8 + len);
+            bytes.extend_from_slice(&(len as u32).to_ne_bytes());
+            bytes.extend_from_slice(&spa_sys::SPA_TYPE_Struct.to_ne_bytes());
+            bytes.extend((0..len).map(|_| random(&mut state) as u8 % 24));
+            let pod = OwnedPod::from_bytes(&bytes).unwrap();
+            walk(&mut PodParser::new(&pod), 0);
+        }
+    }
 }
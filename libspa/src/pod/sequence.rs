@@ -0,0 +1,360 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Sequences of timed controls, such as the MIDI events carried by the buffers of control streams.
+
+use std::ptr::addr_of;
+
+use nix::errno::Errno;
+
+use super::{pod_size, push_padded, OwnedPod, Pod};
+
+/// The type of a control in a [`PodSequence`], which tells how to interpret its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ControlType(pub u32);
+
+#[allow(non_upper_case_globals)]
+impl ControlType {
+    pub const Invalid: Self = Self(spa_sys::SPA_CONTROL_Invalid);
+    /// The value is a `Props` object with the properties to change.
+    pub const Properties: Self = Self(spa_sys::SPA_CONTROL_Properties);
+    /// The value is a bytes pod with a legacy MIDI message.
+    pub const Midi: Self = Self(spa_sys::SPA_CONTROL_Midi);
+    /// The value is a bytes pod with an OSC packet.
+    pub const OSC: Self = Self(spa_sys::SPA_CONTROL_OSC);
+    /// The value is a bytes pod with MIDI 2.0 Universal MIDI Packets.
+    ///
+    /// Only used since PipeWire 1.1, older headers do not define it.
+    pub const UMP: Self = Self(4);
+
+    pub fn from_raw(raw: u32) -> Self {
+        Self(raw)
+    }
+
+    pub fn as_raw(&self) -> u32 {
+        self.0
+    }
+}
+
+/// A transparent wrapper around a `spa_sys::spa_pod_sequence`.
+#[repr(transparent)]
+pub struct PodSequence(spa_sys::spa_pod_sequence);
+
+impl PodSequence {
+    /// # Safety
+    ///
+    /// The provided pointer must point to a valid, well-aligned pod of type sequence.
+    ///
+    /// All restrictions from [`Pod::from_raw`] also apply here.
+    pub unsafe fn from_raw(pod: *const spa_sys::spa_pod_sequence) -> &'static Self {
+        pod.cast::<Self>().as_ref().unwrap()
+    }
+
+    /// # Safety
+    ///
+    /// The provided pointer must point to a valid, well-aligned pod of type sequence.
+    ///
+    /// All restrictions from [`Pod::from_raw_mut`] also apply here.
+    pub unsafe fn from_raw_mut(pod: *mut spa_sys::spa_pod_sequence) -> &'static mut Self {
+        pod.cast::<Self>().as_mut().unwrap()
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut spa_sys::spa_pod_sequence {
+        std::ptr::addr_of!(self.0).cast_mut()
+    }
+
+    pub fn as_pod(&self) -> &Pod {
+        // Safety: Since this is a valid spa_pod_sequence, it must also be a valid spa_pod
+        unsafe { Pod::from_raw(addr_of!(self.0.pod)) }
+    }
+
+    /// The unit of the offsets of the controls, 0 for samples of the stream.
+    pub fn unit(&self) -> u32 {
+        self.0.body.unit
+    }
+
+    /// Iterate over the controls, in the order of their offsets.
+    pub fn controls(&self) -> PodSequenceIter<'_> {
+        PodSequenceIter::new(self)
+    }
+}
+
+impl<'p> TryFrom<&'p Pod> for &'p PodSequence {
+    type Error = Errno;
+
+    fn try_from(value: &'p Pod) -> Result<Self, Self::Error> {
+        value.as_sequence()
+    }
+}
+
+impl AsRef<Pod> for PodSequence {
+    fn as_ref(&self) -> &Pod {
+        self.as_pod()
+    }
+}
+
+pub struct PodSequenceIter<'s> {
+    sequence: &'s PodSequence,
+    next: *mut spa_sys::spa_pod_control,
+}
+
+impl<'s> PodSequenceIter<'s> {
+    fn new(sequence: &'s PodSequence) -> Self {
+        let first_control = unsafe { spa_sys::spa_pod_control_first(addr_of!(sequence.0.body)) };
+
+        Self {
+            sequence,
+            next: first_control,
+        }
+    }
+}
+
+impl<'s> Iterator for PodSequenceIter<'s> {
+    type Item = &'s PodControl;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Check if the iterator has at least one element left that we can return
+        let has_next = unsafe {
+            spa_sys::spa_pod_control_is_inside(
+                addr_of!(self.sequence.0.body),
+                self.sequence.0.pod.size,
+                self.next,
+            )
+        };
+
+        if has_next {
+            let res = unsafe { PodControl::from_raw(self.next.cast_const()) };
+
+            // Advance iter to next control
+            self.next = unsafe { spa_sys::spa_pod_control_next(self.next) };
+
+            Some(res)
+        } else {
+            None
+        }
+    }
+}
+
+/// A transparent wrapper around a `spa_sys::spa_pod_control`, a timed event of a [`PodSequence`].
+#[repr(transparent)]
+pub struct PodControl(spa_sys::spa_pod_control);
+
+impl PodControl {
+    /// # Safety
+    ///
+    /// The provided pointer must point to a valid, well-aligned [`spa_sys::spa_pod_control`].
+    ///
+    /// While this struct doesn't represent a full pod, all restrictions from [`Pod::from_raw`] also apply
+    /// to this struct and the contained `value` pod.
+    pub unsafe fn from_raw(control: *const spa_sys::spa_pod_control) -> &'static Self {
+        control.cast::<Self>().as_ref().unwrap()
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut spa_sys::spa_pod_control {
+        std::ptr::addr_of!(self.0).cast_mut()
+    }
+
+    /// The offset of the control in the unit of its sequence, from the start of the buffer.
+    pub fn offset(&self) -> u32 {
+        self.0.offset
+    }
+
+    pub fn type_(&self) -> ControlType {
+        ControlType(self.0.type_)
+    }
+
+    pub fn value(&self) -> &Pod {
+        // Safety: Since PodControl may only be constructed around valid Pods, the contained value must also be valid.
+        //         We don't mutate the pod and neither can the returned reference.
+        //         The returned lifetime is properly shortened by this methods signature.
+        unsafe { Pod::from_raw(addr_of!(self.0.value)) }
+    }
+
+    /// The raw bytes of the value, for the controls carrying a bytes pod such as
+    /// [`ControlType::Midi`] and [`ControlType::UMP`].
+    pub fn bytes(&self) -> Option<&[u8]> {
+        self.value().get_bytes().ok()
+    }
+}
+
+impl std::fmt::Debug for PodControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PodControl")
+            .field("offset", &self.offset())
+            .field("type", &self.type_())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+/// Builds a sequence pod, one control at a time.
+///
+/// Controls must be added in the order of their offsets.
+///
+/// ```
+/// use libspa::pod::{ControlType, SequenceBuilder};
+///
+/// // a note on, then its note off 480 samples later
+/// let sequence = SequenceBuilder::new()
+///     .bytes(0, ControlType::Midi, &[0x90, 60, 100])
+///     .bytes(480, ControlType::Midi, &[0x80, 60, 0])
+///     .build();
+///
+/// let controls: Vec<_> = sequence
+///     .as_sequence()
+///     .unwrap()
+///     .controls()
+///     .map(|control| (control.offset(), control.bytes().unwrap()))
+///     .collect();
+/// assert_eq!(controls, [(0, &[0x90, 60, 100][..]), (480, &[0x80, 60, 0][..])]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SequenceBuilder {
+    unit: u32,
+    controls: Vec<u8>,
+}
+
+impl SequenceBuilder {
+    /// Start a sequence with offsets in samples.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the unit of the offsets, 0 for samples.
+    pub fn unit(mut self, unit: u32) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Add a control at `offset` with `value`.
+    pub fn control(mut self, offset: u32, type_: ControlType, value: &Pod) -> Self {
+        self.controls.extend_from_slice(&offset.to_ne_bytes());
+        self.controls
+            .extend_from_slice(&type_.as_raw().to_ne_bytes());
+        push_padded(&mut self.controls, value.as_bytes());
+        self
+    }
+
+    /// Add a control at `offset` with a bytes pod holding `bytes`, such as a MIDI message.
+    pub fn bytes(mut self, offset: u32, type_: ControlType, bytes: &[u8]) -> Self {
+        self.controls.extend_from_slice(&offset.to_ne_bytes());
+        self.controls
+            .extend_from_slice(&type_.as_raw().to_ne_bytes());
+        self.controls
+            .extend_from_slice(&pod_size(bytes.len()).to_ne_bytes());
+        self.controls
+            .extend_from_slice(&spa_sys::SPA_TYPE_Bytes.to_ne_bytes());
+        push_padded(&mut self.controls, bytes);
+        self
+    }
+
+    pub fn build(self) -> OwnedPod {
+        // the body starts with the unit and padding
+        let size = 8 + self.controls.len();
+
+        let mut data = Vec::with_capacity(8 + size);
+        data.extend_from_slice(&pod_size(size).to_ne_bytes());
+        data.extend_from_slice(&spa_sys::SPA_TYPE_Sequence.to_ne_bytes());
+        data.extend_from_slice(&self.unit.to_ne_bytes());
+        data.extend_from_slice(&0u32.to_ne_bytes());
+        data.extend_from_slice(&self.controls);
+
+        OwnedPod::from_bytes(data).expect("the sequence is a valid pod")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sequence in samples with a MIDI note on at 0, and a property change at 256.
+    fn fixture() -> Vec<u8> {
+        let words: [u32; 22] = [
+            72,
+            spa_sys::SPA_TYPE_Sequence,
+            // unit, padding
+            0,
+            0,
+            // control: offset, type, value pod with 3 bytes and padding
+            0,
+            spa_sys::SPA_CONTROL_Midi,
+            3,
+            spa_sys::SPA_TYPE_Bytes,
+            u32::from_ne_bytes([0x90, 0x3c, 0x64, 0]),
+            0,
+            // control: offset, type, value pod with an int and padding
+            256,
+            spa_sys::SPA_CONTROL_Properties,
+            4,
+            spa_sys::SPA_TYPE_Int,
+            42,
+            0,
+            // control with an unknown type and an empty bytes pod
+            512,
+            7,
+            0,
+            spa_sys::SPA_TYPE_Bytes,
+            // trailing garbage past the end of the pod
+            0xdead,
+            0xbeef,
+        ];
+        words.iter().flat_map(|word| word.to_ne_bytes()).collect()
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn parse_sequence() {
+        let data = OwnedPod::from_bytes(fixture()).unwrap();
+        let sequence = data.as_sequence().unwrap();
+        assert_eq!(sequence.unit(), 0);
+
+        let controls: Vec<_> = sequence.controls().collect();
+        assert_eq!(controls.len(), 3);
+
+        assert_eq!(controls[0].offset(), 0);
+        assert_eq!(controls[0].type_(), ControlType::Midi);
+        assert_eq!(controls[0].bytes(), Some(&[0x90, 0x3c, 0x64][..]));
+
+        assert_eq!(controls[1].offset(), 256);
+        assert_eq!(controls[1].type_(), ControlType::Properties);
+        assert_eq!(controls[1].bytes(), None);
+        assert_eq!(controls[1].value().get_int(), Ok(42));
+
+        assert_eq!(controls[2].offset(), 512);
+        assert_eq!(controls[2].type_(), ControlType(7));
+        assert_eq!(controls[2].bytes(), Some(&[][..]));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn truncated_sequence() {
+        let mut data = fixture();
+        // the size of the pod ends in the middle of the value of the second control
+        data[..4].copy_from_slice(&48u32.to_ne_bytes());
+        let data = OwnedPod::from_bytes(data).unwrap();
+
+        let controls: Vec<_> = data.as_sequence().unwrap().controls().collect();
+        assert_eq!(controls.len(), 1);
+        assert_eq!(controls[0].type_(), ControlType::Midi);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn build_sequence() {
+        let mut fixture = fixture();
+        fixture.truncate(8 + 72);
+        let int = OwnedPod::from_bytes(&fixture[48..64]).unwrap();
+
+        let sequence = SequenceBuilder::new()
+            .bytes(0, ControlType::Midi, &[0x90, 0x3c, 0x64])
+            .control(256, ControlType::Properties, &int)
+            .bytes(512, ControlType(7), &[])
+            .build();
+        assert_eq!(sequence.as_bytes(), &fixture[..]);
+
+        let empty = SequenceBuilder::new().unit(1).build();
+        let empty = empty.as_sequence().unwrap();
+        assert_eq!(empty.unit(), 1);
+        assert_eq!(empty.controls().count(), 0);
+    }
+}
@@ -31,7 +31,7 @@ use crate::{
     utils::{Choice, ChoiceEnum},
 };
 
-use super::{CanonicalFixedSizedPod, FixedSizedPod, PropertyFlags, Value, ValueArray};
+use super::{CanonicalFixedSizedPod, FixedSizedPod, Object, PropertyFlags, Value, ValueArray};
 
 /// Implementors of this trait are able to serialize themselves into a SPA pod by using a [`PodSerializer`].
 ///
@@ -184,13 +184,7 @@ impl PodSerialize for Value {
                 }
                 struct_serializer.end()
             }
-            Value::Object(object) => {
-                let mut object_serializer = serializer.serialize_object(object.type_, object.id)?;
-                for prop in object.properties.iter() {
-                    object_serializer.serialize_property(prop.key, &prop.value, prop.flags)?;
-                }
-                object_serializer.end()
-            }
+            Value::Object(object) => object.serialize(serializer),
             Value::Choice(choice) => match choice {
                 ChoiceValue::Bool(choice) => serializer.serialize_choice(choice),
                 ChoiceValue::Int(choice) => serializer.serialize_choice(choice),
@@ -207,6 +201,19 @@ impl PodSerialize for Value {
     }
 }
 
+impl PodSerialize for Object {
+    fn serialize<O: Write + Seek>(
+        &self,
+        serializer: PodSerializer<O>,
+    ) -> Result<SerializeSuccess<O>, GenError> {
+        let mut object_serializer = serializer.serialize_object(self.type_, self.id)?;
+        for prop in self.properties.iter() {
+            object_serializer.serialize_property(prop.key, &prop.value, prop.flags)?;
+        }
+        object_serializer.end()
+    }
+}
+
 impl<P: FixedSizedPod> PodSerialize for [P] {
     fn serialize<O: Write + Seek>(
         &self,
@@ -500,6 +507,8 @@ impl<O: Write + Seek> PodSerializer<O> {
             8 => self.gen(ne_u64(ptr as u64))?,
             _ => panic!("unsupported pointer size {}", ptr_size),
         };
+        // Pointers are only 4 bytes large on 32-bit platforms, so the body has to be padded.
+        written += self.gen(Self::padding((8 - len % 8) % 8))?;
 
         Ok(SerializeSuccess {
             serializer: self,
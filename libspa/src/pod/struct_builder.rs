@@ -0,0 +1,278 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use super::{pod_size, push_padded, OwnedPod, Pod};
+use crate::utils::{Fraction, Id, Rectangle};
+
+/// Build a struct pod by appending its fields one after the other,
+/// to be read back by index with [`PodStruct`](super::PodStruct).
+///
+/// ```rust
+/// use libspa::pod::StructBuilder;
+///
+/// let pod = StructBuilder::new().int(30).string("driver").long(1000).build();
+///
+/// let fields = pod.as_struct().unwrap();
+/// assert_eq!(fields.len(), 3);
+/// assert_eq!(fields.field_int(0), Ok(30));
+/// assert_eq!(fields.field_string(1).unwrap().to_str(), Ok("driver"));
+/// assert_eq!(fields.field_long(2), Ok(1000));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StructBuilder {
+    fields: Vec<u8>,
+}
+
+impl StructBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a copy of `value`, which can be any pod, such as a nested struct.
+    pub fn field(mut self, value: &Pod) -> Self {
+        push_padded(&mut self.fields, value.as_bytes());
+        self
+    }
+
+    pub fn none(self) -> Self {
+        self.primitive(spa_sys::SPA_TYPE_None, &[])
+    }
+
+    pub fn bool(self, value: bool) -> Self {
+        self.primitive(spa_sys::SPA_TYPE_Bool, &i32::from(value).to_ne_bytes())
+    }
+
+    pub fn id(self, value: Id) -> Self {
+        self.primitive(spa_sys::SPA_TYPE_Id, &value.0.to_ne_bytes())
+    }
+
+    pub fn int(self, value: i32) -> Self {
+        self.primitive(spa_sys::SPA_TYPE_Int, &value.to_ne_bytes())
+    }
+
+    pub fn long(self, value: i64) -> Self {
+        self.primitive(spa_sys::SPA_TYPE_Long, &value.to_ne_bytes())
+    }
+
+    pub fn float(self, value: f32) -> Self {
+        self.primitive(spa_sys::SPA_TYPE_Float, &value.to_ne_bytes())
+    }
+
+    pub fn double(self, value: f64) -> Self {
+        self.primitive(spa_sys::SPA_TYPE_Double, &value.to_ne_bytes())
+    }
+
+    /// Append a string field.
+    ///
+    /// # Panics
+    /// Panics if `value` contains a null byte.
+    pub fn string(mut self, value: &str) -> Self {
+        assert!(!value.contains('\0'), "Null byte in string field");
+
+        self.header(spa_sys::SPA_TYPE_String, value.len() + 1);
+        let start = self.fields.len();
+        self.fields.extend_from_slice(value.as_bytes());
+        self.fields.push(0);
+        self.pad_from(start);
+        self
+    }
+
+    pub fn bytes(self, value: &[u8]) -> Self {
+        self.primitive(spa_sys::SPA_TYPE_Bytes, value)
+    }
+
+    pub fn rectangle(mut self, value: Rectangle) -> Self {
+        self.header(spa_sys::SPA_TYPE_Rectangle, 8);
+        self.fields.extend_from_slice(&value.width.to_ne_bytes());
+        self.fields.extend_from_slice(&value.height.to_ne_bytes());
+        self
+    }
+
+    pub fn fraction(mut self, value: Fraction) -> Self {
+        self.header(spa_sys::SPA_TYPE_Fraction, 8);
+        self.fields.extend_from_slice(&value.num.to_ne_bytes());
+        self.fields.extend_from_slice(&value.denom.to_ne_bytes());
+        self
+    }
+
+    pub fn build(self) -> OwnedPod {
+        let mut data = Vec::with_capacity(8 + self.fields.len());
+        data.extend_from_slice(&pod_size(self.fields.len()).to_ne_bytes());
+        data.extend_from_slice(&spa_sys::SPA_TYPE_Struct.to_ne_bytes());
+        data.extend_from_slice(&self.fields);
+
+        OwnedPod::from_bytes(data).expect("the struct is a valid pod")
+    }
+
+    fn primitive(mut self, type_: u32, body: &[u8]) -> Self {
+        self.header(type_, body.len());
+        push_padded(&mut self.fields, body);
+        self
+    }
+
+    fn header(&mut self, type_: u32, size: usize) {
+        self.fields.extend_from_slice(&pod_size(size).to_ne_bytes());
+        self.fields.extend_from_slice(&type_.to_ne_bytes());
+    }
+
+    /// Pad the body started at `start` to a multiple of 8 bytes.
+    fn pad_from(&mut self, start: usize) {
+        let len = self.fields.len() - start;
+        self.fields.resize(self.fields.len() + (8 - len % 8) % 8, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nix::errno::Errno;
+
+    use super::*;
+    use crate::pod::serialize::PodSerializer;
+    use crate::pod::Value;
+
+    fn fixture() -> OwnedPod {
+        StructBuilder::new()
+            .int(30)
+            .string("driver")
+            .long(-1)
+            .fraction(Fraction {
+                num: 1,
+                denom: 48000,
+            })
+            .field(&StructBuilder::new().float(0.5).bool(true).build())
+            .build()
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn same_as_serializer() {
+        let value = Value::Struct(vec![
+            Value::Int(30),
+            Value::String("driver".to_owned()),
+            Value::Long(-1),
+            Value::Fraction(Fraction {
+                num: 1,
+                denom: 48000,
+            }),
+            Value::Struct(vec![Value::Float(0.5), Value::Bool(true)]),
+        ]);
+        let serialized = PodSerializer::serialize(std::io::Cursor::new(Vec::new()), &value)
+            .unwrap()
+            .0
+            .into_inner();
+
+        assert_eq!(fixture().as_bytes(), &serialized[..]);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn fields() {
+        let pod = fixture();
+        let fields = pod.as_struct().unwrap();
+
+        assert_eq!(fields.len(), 5);
+        assert!(!fields.is_empty());
+        assert_eq!(fields.check(), Ok(5));
+        assert_eq!(fields.fields().count(), 5);
+
+        assert_eq!(fields.field_int(0), Ok(30));
+        assert_eq!(fields.field_string(1).unwrap().to_bytes(), b"driver");
+        assert_eq!(fields.field_long(2), Ok(-1));
+        assert_eq!(
+            fields.field_fraction(3),
+            Ok(Fraction {
+                num: 1,
+                denom: 48000
+            })
+        );
+        let nested = fields.field_struct(4).unwrap();
+        assert_eq!(nested.field_float(0), Ok(0.5));
+        assert_eq!(nested.field_bool(1), Ok(true));
+
+        // wrong type, then out of bounds
+        assert_eq!(fields.field_string(0), Err(Errno::EINVAL));
+        assert_eq!(fields.try_field(5).unwrap_err(), Errno::ENOENT);
+        assert!(fields.field(5).is_none());
+        assert_eq!(fields.field_int(usize::MAX), Err(Errno::ENOENT));
+
+        let empty = StructBuilder::new().build();
+        let empty = empty.as_struct().unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.check(), Ok(0));
+        assert_eq!(empty.field_int(0), Err(Errno::ENOENT));
+    }
+
+    /// Replace the word at `index` of `pod`, returning `None` if the result is not a valid pod.
+    fn corrupt(pod: &OwnedPod, index: usize, word: u32) -> Option<OwnedPod> {
+        let mut bytes = pod.as_bytes().to_vec();
+        bytes[index * 4..][..4].copy_from_slice(&word.to_ne_bytes());
+        OwnedPod::from_bytes(bytes)
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn truncated() {
+        let pod = fixture();
+
+        // the string claims to be larger than the rest of the struct
+        let corrupted = corrupt(&pod, 6, 1024).unwrap();
+        let fields = corrupted.as_struct().unwrap();
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields.check(), Err(Errno::EPROTO));
+        assert_eq!(fields.field_int(0), Ok(30));
+        assert_eq!(fields.field_string(1), Err(Errno::EPROTO));
+        assert_eq!(fields.field_long(2), Err(Errno::EPROTO));
+
+        // the struct ends in the middle of the header of the long
+        let size = 16 + 16;
+        let mut corrupted = corrupt(&pod, 0, size + 4).unwrap();
+        let fields = corrupted.as_struct().unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields.check(), Err(Errno::EPROTO));
+        assert_eq!(fields.field_long(2), Err(Errno::EPROTO));
+
+        // a struct ending right after a field is not truncated
+        corrupted = corrupt(&pod, 0, size).unwrap();
+        let fields = corrupted.as_struct().unwrap();
+        assert_eq!(fields.check(), Ok(2));
+        assert_eq!(fields.field_long(2), Err(Errno::ENOENT));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn corrupted() {
+        let pod = fixture();
+        let words = pod.as_bytes().len() / 4;
+
+        // a fixed xorshift sequence, so that failures are reproducible
+        let mut state = 0x2545_f491_u32;
+        let mut random = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+
+        // the header of the struct is left alone, so that it stays a struct
+        for index in 2..words {
+            for word in [0, 1, 7, 8, 9, u32::MAX, random(), random() % 64] {
+                let Some(corrupted) = corrupt(&pod, index, word) else {
+                    continue;
+                };
+                let fields = corrupted.as_struct().unwrap();
+
+                // all accessors are bounds checked and agree with each other
+                let len = fields.len();
+                if let Ok(checked) = fields.check() {
+                    assert_eq!(checked, len);
+                }
+                for i in 0..=len {
+                    assert_eq!(fields.field(i).is_some(), i < len);
+                    let _ = fields.field_int(i);
+                    let _ = fields.field_string(i);
+                    let _ = fields.field_struct(i).map(|s| s.check());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,690 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! `serde` support for [`Value`] and its container types, enabled with the `serde` feature.
+//!
+//! The serialized representation is described in the documentation of [`Value`].
+//! It goes through private mirror types deriving the `serde` traits.
+//!
+//! Object types, ids and property keys, and the ids of properties, are represented by their
+//! names from the type info tables, looked up with the [`debug`](crate::debug) module.
+//! Names which are ambiguous, such as the `format` key of both audio and video formats,
+//! or unknown to the SPA version in use are represented as numbers, so that the values
+//! always deserialize back to the same pod.
+
+use std::ptr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{
+    deserialize::PodDeserializer, CanonicalFixedSizedPod, ChoiceValue, Object, OwnedPod, Pod,
+    Property, PropertyFlags, Value, ValueArray,
+};
+use crate::{
+    debug::{self, TypeTable},
+    utils::{Choice, ChoiceEnum, ChoiceFlags, Fd, Fraction, Id, Rectangle},
+};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Value")]
+enum ValueRepr {
+    None,
+    Bool(bool),
+    Id(IdRepr),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Rectangle(RectangleRepr),
+    Fraction(FractionRepr),
+    Fd(FdPlaceholder),
+    Array(ValueArrayRepr),
+    Struct(Vec<ValueRepr>),
+    Object(ObjectRepr),
+    Choice(ChoiceValueRepr),
+    Pointer(PointerPlaceholder),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "ValueArray")]
+enum ValueArrayRepr {
+    None(Vec<()>),
+    Bool(Vec<bool>),
+    Id(Vec<IdRepr>),
+    Int(Vec<i32>),
+    Long(Vec<i64>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+    Rectangle(Vec<RectangleRepr>),
+    Fraction(Vec<FractionRepr>),
+    Fd(Vec<FdPlaceholder>),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "ChoiceValue")]
+enum ChoiceValueRepr {
+    Bool(ChoiceRepr<bool>),
+    Int(ChoiceRepr<i32>),
+    Long(ChoiceRepr<i64>),
+    Float(ChoiceRepr<f32>),
+    Double(ChoiceRepr<f64>),
+    Id(ChoiceRepr<IdRepr>),
+    Rectangle(ChoiceRepr<RectangleRepr>),
+    Fraction(ChoiceRepr<FractionRepr>),
+    Fd(ChoiceRepr<FdPlaceholder>),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Choice")]
+struct ChoiceRepr<T> {
+    flags: u32,
+    #[serde(flatten)]
+    choice: ChoiceEnumRepr<T>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "ChoiceEnum")]
+enum ChoiceEnumRepr<T> {
+    None(T),
+    Range { default: T, min: T, max: T },
+    Step { default: T, min: T, max: T, step: T },
+    Enum { default: T, alternatives: Vec<T> },
+    Flags { default: T, flags: Vec<T> },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Object")]
+struct ObjectRepr {
+    #[serde(rename = "type")]
+    type_: IdRepr,
+    id: IdRepr,
+    properties: Vec<PropertyRepr>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Property")]
+struct PropertyRepr {
+    key: IdRepr,
+    #[serde(default)]
+    flags: u32,
+    value: ValueRepr,
+}
+
+/// An id, or a type, represented by its name when it has one.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum IdRepr {
+    Value(u32),
+    Name(String),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Rectangle")]
+struct RectangleRepr {
+    width: u32,
+    height: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Fraction")]
+struct FractionRepr {
+    num: u32,
+    denom: u32,
+}
+
+/// Placeholder for the value of a file descriptor.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Fd")]
+struct FdPlaceholder;
+
+/// Placeholder for a pointer, only keeping its type.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Pointer")]
+struct PointerPlaceholder {
+    #[serde(rename = "type")]
+    type_: IdRepr,
+}
+
+impl From<Rectangle> for RectangleRepr {
+    fn from(value: Rectangle) -> Self {
+        Self {
+            width: value.width,
+            height: value.height,
+        }
+    }
+}
+
+impl From<RectangleRepr> for Rectangle {
+    fn from(value: RectangleRepr) -> Self {
+        Self {
+            width: value.width,
+            height: value.height,
+        }
+    }
+}
+
+impl From<Fraction> for FractionRepr {
+    fn from(value: Fraction) -> Self {
+        Self {
+            num: value.num,
+            denom: value.denom,
+        }
+    }
+}
+
+impl From<FractionRepr> for Fraction {
+    fn from(value: FractionRepr) -> Self {
+        Self {
+            num: value.num,
+            denom: value.denom,
+        }
+    }
+}
+
+impl From<Fd> for FdPlaceholder {
+    fn from(_: Fd) -> Self {
+        Self
+    }
+}
+
+impl From<FdPlaceholder> for Fd {
+    fn from(_: FdPlaceholder) -> Self {
+        Self(-1)
+    }
+}
+
+impl IdRepr {
+    /// Represent `id` by its short name in `names`, such as `EnumFormat` or `mediaType`.
+    fn new(id: u32, names: TypeTable) -> Self {
+        match debug::unique_short_name(names, id) {
+            Some(name) => Self::Name(name.to_owned()),
+            None => Self::Value(id),
+        }
+    }
+
+    /// Represent `type_` by its qualified name, such as `Spa:Pod:Object:Param:Format`.
+    fn new_type(type_: u32) -> Self {
+        match debug::type_name(type_).filter(|name| debug::type_by_name(name) == Some(type_)) {
+            Some(name) => Self::Name(name.to_owned()),
+            None => Self::Value(type_),
+        }
+    }
+
+    fn resolve(self, names: TypeTable) -> Result<u32, String> {
+        match self {
+            Self::Value(id) => Ok(id),
+            Self::Name(name) => {
+                debug::value_by_short_name(names, &name).ok_or_else(|| unknown_name(&name))
+            }
+        }
+    }
+
+    fn resolve_type(self) -> Result<u32, String> {
+        match self {
+            Self::Value(type_) => Ok(type_),
+            Self::Name(name) => debug::type_by_name(&name).ok_or_else(|| unknown_name(&name)),
+        }
+    }
+}
+
+fn unknown_name(name: &str) -> String {
+    format!("unknown name {name:?}")
+}
+
+fn map_vec<T, U>(values: Vec<T>, f: impl FnMut(T) -> U) -> Vec<U> {
+    values.into_iter().map(f).collect()
+}
+
+fn try_map_vec<T, U>(
+    values: Vec<T>,
+    f: impl FnMut(T) -> Result<U, String>,
+) -> Result<Vec<U>, String> {
+    values.into_iter().map(f).collect()
+}
+
+/// The table naming the elements of an array whose property names its values with `names`.
+fn element_names(names: TypeTable) -> TypeTable {
+    // the table of array properties describes their element, like spa_debug_pod() assumes
+    match unsafe { names.as_ref() } {
+        Some(element) if !element.values.is_null() => element.values,
+        _ => names,
+    }
+}
+
+fn choice_to_repr<T: CanonicalFixedSizedPod, U>(
+    Choice(flags, choice): Choice<T>,
+    mut f: impl FnMut(T) -> U,
+) -> ChoiceRepr<U> {
+    let choice = match choice {
+        ChoiceEnum::None(value) => ChoiceEnumRepr::None(f(value)),
+        ChoiceEnum::Range { default, min, max } => ChoiceEnumRepr::Range {
+            default: f(default),
+            min: f(min),
+            max: f(max),
+        },
+        ChoiceEnum::Step {
+            default,
+            min,
+            max,
+            step,
+        } => ChoiceEnumRepr::Step {
+            default: f(default),
+            min: f(min),
+            max: f(max),
+            step: f(step),
+        },
+        ChoiceEnum::Enum {
+            default,
+            alternatives,
+        } => ChoiceEnumRepr::Enum {
+            default: f(default),
+            alternatives: map_vec(alternatives, f),
+        },
+        ChoiceEnum::Flags { default, flags } => ChoiceEnumRepr::Flags {
+            default: f(default),
+            flags: map_vec(flags, f),
+        },
+    };
+
+    ChoiceRepr {
+        flags: flags.bits(),
+        choice,
+    }
+}
+
+fn choice_from_repr<U, T: CanonicalFixedSizedPod>(
+    ChoiceRepr { flags, choice }: ChoiceRepr<U>,
+    mut f: impl FnMut(U) -> Result<T, String>,
+) -> Result<Choice<T>, String> {
+    let choice = match choice {
+        ChoiceEnumRepr::None(value) => ChoiceEnum::None(f(value)?),
+        ChoiceEnumRepr::Range { default, min, max } => ChoiceEnum::Range {
+            default: f(default)?,
+            min: f(min)?,
+            max: f(max)?,
+        },
+        ChoiceEnumRepr::Step {
+            default,
+            min,
+            max,
+            step,
+        } => ChoiceEnum::Step {
+            default: f(default)?,
+            min: f(min)?,
+            max: f(max)?,
+            step: f(step)?,
+        },
+        ChoiceEnumRepr::Enum {
+            default,
+            alternatives,
+        } => ChoiceEnum::Enum {
+            default: f(default)?,
+            alternatives: try_map_vec(alternatives, f)?,
+        },
+        ChoiceEnumRepr::Flags { default, flags } => ChoiceEnum::Flags {
+            default: f(default)?,
+            flags: try_map_vec(flags, f)?,
+        },
+    };
+
+    Ok(Choice(ChoiceFlags::from_bits_retain(flags), choice))
+}
+
+/// Convert a choice, naming its ids with `names`.
+fn choice_value_to_repr(value: ChoiceValue, names: TypeTable) -> ChoiceValueRepr {
+    let id = |id: Id| IdRepr::new(id.0, names);
+    match value {
+        ChoiceValue::Bool(choice) => ChoiceValueRepr::Bool(choice_to_repr(choice, |v| v)),
+        ChoiceValue::Int(choice) => ChoiceValueRepr::Int(choice_to_repr(choice, |v| v)),
+        ChoiceValue::Long(choice) => ChoiceValueRepr::Long(choice_to_repr(choice, |v| v)),
+        ChoiceValue::Float(choice) => ChoiceValueRepr::Float(choice_to_repr(choice, |v| v)),
+        ChoiceValue::Double(choice) => ChoiceValueRepr::Double(choice_to_repr(choice, |v| v)),
+        ChoiceValue::Id(choice) => ChoiceValueRepr::Id(choice_to_repr(choice, id)),
+        ChoiceValue::Rectangle(choice) => {
+            ChoiceValueRepr::Rectangle(choice_to_repr(choice, RectangleRepr::from))
+        }
+        ChoiceValue::Fraction(choice) => {
+            ChoiceValueRepr::Fraction(choice_to_repr(choice, FractionRepr::from))
+        }
+        ChoiceValue::Fd(choice) => ChoiceValueRepr::Fd(choice_to_repr(choice, FdPlaceholder::from)),
+    }
+}
+
+fn choice_value_from_repr(repr: ChoiceValueRepr, names: TypeTable) -> Result<ChoiceValue, String> {
+    let id = |id: IdRepr| id.resolve(names).map(Id);
+    Ok(match repr {
+        ChoiceValueRepr::Bool(choice) => ChoiceValue::Bool(choice_from_repr(choice, Ok)?),
+        ChoiceValueRepr::Int(choice) => ChoiceValue::Int(choice_from_repr(choice, Ok)?),
+        ChoiceValueRepr::Long(choice) => ChoiceValue::Long(choice_from_repr(choice, Ok)?),
+        ChoiceValueRepr::Float(choice) => ChoiceValue::Float(choice_from_repr(choice, Ok)?),
+        ChoiceValueRepr::Double(choice) => ChoiceValue::Double(choice_from_repr(choice, Ok)?),
+        ChoiceValueRepr::Id(choice) => ChoiceValue::Id(choice_from_repr(choice, id)?),
+        ChoiceValueRepr::Rectangle(choice) => {
+            ChoiceValue::Rectangle(choice_from_repr(choice, |v| Ok(v.into()))?)
+        }
+        ChoiceValueRepr::Fraction(choice) => {
+            ChoiceValue::Fraction(choice_from_repr(choice, |v| Ok(v.into()))?)
+        }
+        ChoiceValueRepr::Fd(choice) => ChoiceValue::Fd(choice_from_repr(choice, |v| Ok(v.into()))?),
+    })
+}
+
+/// Convert an array, naming its ids with `names`.
+fn array_to_repr(value: ValueArray, names: TypeTable) -> ValueArrayRepr {
+    match value {
+        ValueArray::None(values) => ValueArrayRepr::None(values),
+        ValueArray::Bool(values) => ValueArrayRepr::Bool(values),
+        ValueArray::Id(values) => {
+            ValueArrayRepr::Id(map_vec(values, |id| IdRepr::new(id.0, names)))
+        }
+        ValueArray::Int(values) => ValueArrayRepr::Int(values),
+        ValueArray::Long(values) => ValueArrayRepr::Long(values),
+        ValueArray::Float(values) => ValueArrayRepr::Float(values),
+        ValueArray::Double(values) => ValueArrayRepr::Double(values),
+        ValueArray::Rectangle(values) => ValueArrayRepr::Rectangle(map_vec(values, From::from)),
+        ValueArray::Fraction(values) => ValueArrayRepr::Fraction(map_vec(values, From::from)),
+        ValueArray::Fd(values) => ValueArrayRepr::Fd(map_vec(values, From::from)),
+    }
+}
+
+fn array_from_repr(repr: ValueArrayRepr, names: TypeTable) -> Result<ValueArray, String> {
+    Ok(match repr {
+        ValueArrayRepr::None(values) => ValueArray::None(values),
+        ValueArrayRepr::Bool(values) => ValueArray::Bool(values),
+        ValueArrayRepr::Id(values) => {
+            ValueArray::Id(try_map_vec(values, |id| id.resolve(names).map(Id))?)
+        }
+        ValueArrayRepr::Int(values) => ValueArray::Int(values),
+        ValueArrayRepr::Long(values) => ValueArray::Long(values),
+        ValueArrayRepr::Float(values) => ValueArray::Float(values),
+        ValueArrayRepr::Double(values) => ValueArray::Double(values),
+        ValueArrayRepr::Rectangle(values) => ValueArray::Rectangle(map_vec(values, From::from)),
+        ValueArrayRepr::Fraction(values) => ValueArray::Fraction(map_vec(values, From::from)),
+        ValueArrayRepr::Fd(values) => ValueArray::Fd(map_vec(values, From::from)),
+    })
+}
+
+/// Convert a property, naming its key with `keys` and the ids of its value with the table
+/// of its key.
+fn property_to_repr(property: Property, keys: TypeTable) -> PropertyRepr {
+    PropertyRepr {
+        key: IdRepr::new(property.key, keys),
+        flags: property.flags.bits(),
+        value: value_to_repr(property.value, debug::values_table(keys, property.key)),
+    }
+}
+
+fn property_from_repr(repr: PropertyRepr, keys: TypeTable) -> Result<Property, String> {
+    let key = repr.key.resolve(keys)?;
+    Ok(Property {
+        key,
+        flags: PropertyFlags::from_bits_retain(repr.flags),
+        value: value_from_repr(repr.value, debug::values_table(keys, key))?,
+    })
+}
+
+/// The table of the property keys of objects of type `type_`, the key 0 naming their ids.
+fn object_keys(type_: u32) -> TypeTable {
+    debug::values_table(unsafe { spa_sys::spa_types }, type_)
+}
+
+fn object_to_repr(object: Object) -> ObjectRepr {
+    let keys = object_keys(object.type_);
+    ObjectRepr {
+        type_: IdRepr::new_type(object.type_),
+        id: IdRepr::new(object.id, debug::values_table(keys, 0)),
+        properties: map_vec(object.properties, |property| {
+            property_to_repr(property, keys)
+        }),
+    }
+}
+
+fn object_from_repr(repr: ObjectRepr) -> Result<Object, String> {
+    let type_ = repr.type_.resolve_type()?;
+    let keys = object_keys(type_);
+    Ok(Object {
+        type_,
+        id: repr.id.resolve(debug::values_table(keys, 0))?,
+        properties: try_map_vec(repr.properties, |property| {
+            property_from_repr(property, keys)
+        })?,
+    })
+}
+
+/// Convert a value, naming its ids with `names`.
+fn value_to_repr(value: Value, names: TypeTable) -> ValueRepr {
+    match value {
+        Value::None => ValueRepr::None,
+        Value::Bool(value) => ValueRepr::Bool(value),
+        Value::Id(value) => ValueRepr::Id(IdRepr::new(value.0, names)),
+        Value::Int(value) => ValueRepr::Int(value),
+        Value::Long(value) => ValueRepr::Long(value),
+        Value::Float(value) => ValueRepr::Float(value),
+        Value::Double(value) => ValueRepr::Double(value),
+        Value::String(value) => ValueRepr::String(value),
+        Value::Bytes(value) => ValueRepr::Bytes(value),
+        Value::Rectangle(value) => ValueRepr::Rectangle(value.into()),
+        Value::Fraction(value) => ValueRepr::Fraction(value.into()),
+        Value::Fd(value) => ValueRepr::Fd(value.into()),
+        Value::ValueArray(value) => ValueRepr::Array(array_to_repr(value, element_names(names))),
+        Value::Struct(values) => {
+            ValueRepr::Struct(map_vec(values, |value| value_to_repr(value, ptr::null())))
+        }
+        Value::Object(value) => ValueRepr::Object(object_to_repr(value)),
+        Value::Choice(value) => ValueRepr::Choice(choice_value_to_repr(value, names)),
+        Value::Pointer(type_, _) => ValueRepr::Pointer(PointerPlaceholder {
+            type_: IdRepr::new_type(type_),
+        }),
+    }
+}
+
+fn value_from_repr(repr: ValueRepr, names: TypeTable) -> Result<Value, String> {
+    Ok(match repr {
+        ValueRepr::None => Value::None,
+        ValueRepr::Bool(value) => Value::Bool(value),
+        ValueRepr::Id(value) => Value::Id(Id(value.resolve(names)?)),
+        ValueRepr::Int(value) => Value::Int(value),
+        ValueRepr::Long(value) => Value::Long(value),
+        ValueRepr::Float(value) => Value::Float(value),
+        ValueRepr::Double(value) => Value::Double(value),
+        ValueRepr::String(value) => Value::String(value),
+        ValueRepr::Bytes(value) => Value::Bytes(value),
+        ValueRepr::Rectangle(value) => Value::Rectangle(value.into()),
+        ValueRepr::Fraction(value) => Value::Fraction(value.into()),
+        ValueRepr::Fd(value) => Value::Fd(value.into()),
+        ValueRepr::Array(value) => Value::ValueArray(array_from_repr(value, element_names(names))?),
+        ValueRepr::Struct(values) => Value::Struct(try_map_vec(values, |value| {
+            value_from_repr(value, ptr::null())
+        })?),
+        ValueRepr::Object(value) => Value::Object(object_from_repr(value)?),
+        ValueRepr::Choice(value) => Value::Choice(choice_value_from_repr(value, names)?),
+        ValueRepr::Pointer(PointerPlaceholder { type_ }) => {
+            Value::Pointer(type_.resolve_type()?, ptr::null())
+        }
+    })
+}
+
+macro_rules! impl_serde {
+    ($ty:ty, $repr:ty, $to_repr:expr, $from_repr:expr) => {
+        /// Serialized as described in the documentation of [`Value`].
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                ($to_repr)(self.clone()).serialize(serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let repr = <$repr>::deserialize(deserializer)?;
+                ($from_repr)(repr).map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+impl_serde!(
+    Value,
+    ValueRepr,
+    |value| value_to_repr(value, ptr::null()),
+    |repr| value_from_repr(repr, ptr::null())
+);
+impl_serde!(
+    ValueArray,
+    ValueArrayRepr,
+    |value| array_to_repr(value, ptr::null()),
+    |repr| array_from_repr(repr, ptr::null())
+);
+impl_serde!(
+    ChoiceValue,
+    ChoiceValueRepr,
+    |value| choice_value_to_repr(value, ptr::null()),
+    |repr| choice_value_from_repr(repr, ptr::null())
+);
+impl_serde!(Object, ObjectRepr, object_to_repr, object_from_repr);
+// without its object, the key of a property has no name
+impl_serde!(
+    Property,
+    PropertyRepr,
+    |property| property_to_repr(property, ptr::null()),
+    |repr| property_from_repr(repr, ptr::null())
+);
+
+/// Serialized as the [`Value`] it contains.
+impl Serialize for Pod {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (_, value) = PodDeserializer::deserialize_any_from(self.as_bytes())
+            .map_err(|_| serde::ser::Error::custom("invalid pod"))?;
+        value_to_repr(value, ptr::null()).serialize(serializer)
+    }
+}
+
+/// Serialized as the [`Value`] it contains.
+impl Serialize for OwnedPod {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_pod().serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{
+        param::{format::FormatProperties, ParamType},
+        pod::{deserialize::PodDeserializer, serialize::PodSerializer, ObjectBuilder},
+        utils::SpaTypes,
+    };
+
+    #[test]
+    fn json_shape() {
+        let value = Value::Struct(vec![
+            Value::Id(Id(3)),
+            Value::Fraction(Fraction { num: 30, denom: 1 }),
+            Value::ValueArray(ValueArray::Float(vec![0.5, 0.25])),
+            Value::Choice(ChoiceValue::Int(Choice(
+                ChoiceFlags::empty(),
+                ChoiceEnum::Range {
+                    default: 2,
+                    min: 1,
+                    max: 4,
+                },
+            ))),
+            Value::Fd(Fd(5)),
+        ]);
+
+        assert_eq!(
+            serde_json::to_string(&value).unwrap(),
+            r#"{"Struct":[{"Id":3},{"Fraction":{"num":30,"denom":1}},{"Array":{"Float":[0.5,0.25]}},{"Choice":{"Int":{"flags":0,"Range":{"default":2,"min":1,"max":4}}}},{"Fd":null}]}"#
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn names() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamProps, ParamType::Props)
+            .prop(spa_sys::SPA_PROP_mute, true)
+            .prop(
+                spa_sys::SPA_PROP_channelMap,
+                ValueArray::Id(vec![
+                    Id(spa_sys::SPA_AUDIO_CHANNEL_FL),
+                    Id(spa_sys::SPA_AUDIO_CHANNEL_FR),
+                ]),
+            )
+            .prop(spa_sys::SPA_PROP_START_CUSTOM + 1, Id(3))
+            .build();
+        let (_, value) = PodDeserializer::deserialize_any_from(pod.as_bytes()).unwrap();
+
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(
+            json,
+            format!(
+                r#"{{"Object":{{"type":"Spa:Pod:Object:Param:Props","id":"Props","properties":[{{"key":"mute","flags":0,"value":{{"Bool":true}}}},{{"key":"channelMap","flags":0,"value":{{"Array":{{"Id":["FL","FR"]}}}}}},{{"key":{},"flags":0,"value":{{"Id":3}}}}]}}}}"#,
+                spa_sys::SPA_PROP_START_CUSTOM + 1
+            )
+        );
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+
+        // numbers are accepted as well, unknown names are rejected
+        let object: Object = serde_json::from_str(&format!(
+            r#"{{"type":{},"id":"Props","properties":[{{"key":{},"value":{{"Bool":true}}}}]}}"#,
+            spa_sys::SPA_TYPE_OBJECT_Props,
+            spa_sys::SPA_PROP_mute
+        ))
+        .unwrap();
+        assert_eq!(object.properties[0].key, spa_sys::SPA_PROP_mute);
+        assert!(serde_json::from_str::<Object>(
+            r#"{"type":"Spa:Pod:Object:Param:Props","id":"Props","properties":[{"key":"foo","value":"None"}]}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn property_flags_are_optional() {
+        let property: Property =
+            serde_json::from_str(r#"{"key": 1, "value": {"Bool": true}}"#).unwrap();
+        assert_eq!(property, Property::new(1, Value::Bool(true)));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn pod_round_trip() {
+        let pod = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+            .prop(
+                FormatProperties::MediaType,
+                Id(spa_sys::SPA_MEDIA_TYPE_video),
+            )
+            .prop_choice_range(
+                FormatProperties::VideoSize,
+                Rectangle {
+                    width: 320,
+                    height: 240,
+                },
+                Rectangle {
+                    width: 1,
+                    height: 1,
+                },
+                Rectangle {
+                    width: 4096,
+                    height: 4096,
+                },
+            )
+            .prop_choice_enum(FormatProperties::VideoModifier, 0_i64, [0_i64, 1])
+            .prop(spa_sys::SPA_FORMAT_START_Video + 0x100, "foo")
+            .build();
+        let (_, value) = PodDeserializer::deserialize_any_from(pod.as_bytes()).unwrap();
+
+        let json = serde_json::to_string_pretty(&value).unwrap();
+        let deserialized: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, value);
+
+        let (cursor, _) = PodSerializer::serialize(Cursor::new(Vec::new()), &deserialized).unwrap();
+        assert_eq!(cursor.into_inner(), pod.as_bytes());
+
+        // pods serialize as the value they contain
+        assert_eq!(serde_json::to_string_pretty(&pod).unwrap(), json);
+        assert_eq!(serde_json::to_string_pretty(&*pod).unwrap(), json);
+    }
+}
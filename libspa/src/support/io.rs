@@ -0,0 +1,372 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! IO areas shared between a node and the server, such as the clock and the position of the graph.
+//!
+//! IO areas are handed to streams and filters with their `io_changed` event,
+//! as an `id`, a pointer and a size which are checked by [`IoClock::from_area`] and
+//! [`IoPosition::from_area`] before giving access to the area.
+//!
+//! # Tearing
+//!
+//! The memory of the areas is shared with the server, which updates it concurrently.
+//! The accessors use volatile reads so every call observes the current value of a field,
+//! but successive calls may observe values from different cycles.
+//! Use [`IoClock::snapshot`] or [`IoPosition::snapshot`] to copy an area once and read consistent values,
+//! ideally from the `process` callback, during which the area is not updated.
+
+use std::{
+    cell::UnsafeCell,
+    ffi::{c_void, CStr},
+    fmt::Debug,
+    mem::{align_of, size_of},
+    ptr::addr_of,
+};
+
+use crate::utils::Fraction;
+
+/// The type of an IO area, the `id` of the `io_changed` event.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct IoType(spa_sys::spa_io_type);
+
+#[allow(non_upper_case_globals)]
+impl IoType {
+    pub const Invalid: Self = Self(spa_sys::SPA_IO_Invalid);
+    /// Area to exchange buffers.
+    pub const Buffers: Self = Self(spa_sys::SPA_IO_Buffers);
+    /// Expected byte range.
+    pub const Range: Self = Self(spa_sys::SPA_IO_Range);
+    /// An [`IoClock`].
+    pub const Clock: Self = Self(spa_sys::SPA_IO_Clock);
+    /// Latency reporting.
+    pub const Latency: Self = Self(spa_sys::SPA_IO_Latency);
+    /// Area for control messages.
+    pub const Control: Self = Self(spa_sys::SPA_IO_Control);
+    /// Area for notify messages.
+    pub const Notify: Self = Self(spa_sys::SPA_IO_Notify);
+    /// An [`IoPosition`].
+    pub const Position: Self = Self(spa_sys::SPA_IO_Position);
+    /// Rate matching between nodes.
+    pub const RateMatch: Self = Self(spa_sys::SPA_IO_RateMatch);
+    /// Memory pointer.
+    pub const Memory: Self = Self(spa_sys::SPA_IO_Memory);
+
+    pub fn from_raw(raw: spa_sys::spa_io_type) -> Self {
+        Self(raw)
+    }
+
+    pub fn as_raw(&self) -> spa_sys::spa_io_type {
+        self.0
+    }
+}
+
+impl Debug for IoType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = format!(
+            "IoType::{}",
+            match *self {
+                Self::Invalid => "Invalid",
+                Self::Buffers => "Buffers",
+                Self::Range => "Range",
+                Self::Clock => "Clock",
+                Self::Latency => "Latency",
+                Self::Control => "Control",
+                Self::Notify => "Notify",
+                Self::Position => "Position",
+                Self::RateMatch => "RateMatch",
+                Self::Memory => "Memory",
+                _ => "Unknown",
+            }
+        );
+        f.write_str(&name)
+    }
+}
+
+/// Cast an IO area to a `T`, checking its size and alignment.
+///
+/// # Safety
+///
+/// If not null, `area` must point to `size` bytes valid for reads for the lifetime `'a`.
+unsafe fn cast_area<'a, T>(area: *mut c_void, size: u32) -> Option<&'a T> {
+    if area.is_null()
+        || usize::try_from(size).ok()? < size_of::<T>()
+        || area as usize % align_of::<T>() != 0
+    {
+        return None;
+    }
+    Some(&*(area as *const T))
+}
+
+bitflags::bitflags! {
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct IoClockFlags: u32 {
+        /// Graph is freewheeling
+        const FREEWHEEL = 1<<0;
+    }
+}
+
+/// The clock of the driver of the graph, of type [`IoType::Clock`].
+///
+/// See the [module documentation](self) about concurrent updates.
+#[repr(transparent)]
+pub struct IoClock(UnsafeCell<spa_sys::spa_io_clock>);
+
+impl IoClock {
+    /// Get the clock from the area of an `io_changed` event with id [`IoType::Clock`].
+    ///
+    /// Returns `None` if `area` is null, or is too small or misaligned for a clock.
+    ///
+    /// # Safety
+    ///
+    /// If not null, `area` must point to `size` bytes valid for reads for the lifetime `'a`.
+    pub unsafe fn from_area<'a>(area: *mut c_void, size: u32) -> Option<&'a Self> {
+        cast_area(area, size)
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut spa_sys::spa_io_clock {
+        self.0.get()
+    }
+
+    /// Copy the clock once, so the values read from the copy are consistent.
+    pub fn snapshot(&self) -> Self {
+        Self(UnsafeCell::new(unsafe { self.0.get().read_volatile() }))
+    }
+
+    pub fn flags(&self) -> IoClockFlags {
+        IoClockFlags::from_bits_retain(unsafe { addr_of!((*self.0.get()).flags).read_volatile() })
+    }
+
+    /// Unique clock id, set by the host application.
+    pub fn id(&self) -> u32 {
+        unsafe { addr_of!((*self.0.get()).id).read_volatile() }
+    }
+
+    /// Clock name, prefixed with the API.
+    pub fn name(&self) -> String {
+        let name = unsafe { addr_of!((*self.0.get()).name).read_volatile() };
+        let bytes = name.map(|c| c as u8);
+        match CStr::from_bytes_until_nul(&bytes) {
+            Ok(name) => name.to_string_lossy().into_owned(),
+            Err(_) => String::from_utf8_lossy(&bytes).into_owned(),
+        }
+    }
+
+    /// Time in nanoseconds against the monotonic clock.
+    pub fn clock_time_ns(&self) -> u64 {
+        unsafe { addr_of!((*self.0.get()).nsec).read_volatile() }
+    }
+
+    /// Rate of the position, duration and delay.
+    pub fn rate(&self) -> Fraction {
+        Fraction::from_raw(unsafe { addr_of!((*self.0.get()).rate).read_volatile() })
+    }
+
+    /// Current position, in samples at [`rate`](Self::rate).
+    pub fn position(&self) -> u64 {
+        unsafe { addr_of!((*self.0.get()).position).read_volatile() }
+    }
+
+    /// Duration of the current cycle, in samples at [`rate`](Self::rate).
+    pub fn duration(&self) -> u64 {
+        unsafe { addr_of!((*self.0.get()).duration).read_volatile() }
+    }
+
+    /// Delay between the position and the hardware, in samples at [`rate`](Self::rate).
+    pub fn delay(&self) -> i64 {
+        unsafe { addr_of!((*self.0.get()).delay).read_volatile() }
+    }
+
+    /// Rate difference between the clock and the monotonic time.
+    pub fn rate_diff(&self) -> f64 {
+        unsafe { addr_of!((*self.0.get()).rate_diff).read_volatile() }
+    }
+
+    /// Estimated time of the next wakeup, in nanoseconds.
+    pub fn next_nsec(&self) -> u64 {
+        unsafe { addr_of!((*self.0.get()).next_nsec).read_volatile() }
+    }
+}
+
+impl Debug for IoClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let clock = self.snapshot();
+        f.debug_struct("IoClock")
+            .field("flags", &clock.flags())
+            .field("id", &clock.id())
+            .field("name", &clock.name())
+            .field("nsec", &clock.clock_time_ns())
+            .field("rate", &clock.rate())
+            .field("position", &clock.position())
+            .field("duration", &clock.duration())
+            .field("delay", &clock.delay())
+            .field("rate_diff", &clock.rate_diff())
+            .field("next_nsec", &clock.next_nsec())
+            .finish()
+    }
+}
+
+/// The state of the transport of an [`IoPosition`].
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct IoPositionState(spa_sys::spa_io_position_state);
+
+#[allow(non_upper_case_globals)]
+impl IoPositionState {
+    pub const Stopped: Self = Self(spa_sys::SPA_IO_POSITION_STATE_STOPPED);
+    pub const Starting: Self = Self(spa_sys::SPA_IO_POSITION_STATE_STARTING);
+    pub const Running: Self = Self(spa_sys::SPA_IO_POSITION_STATE_RUNNING);
+
+    pub fn from_raw(raw: spa_sys::spa_io_position_state) -> Self {
+        Self(raw)
+    }
+
+    pub fn as_raw(&self) -> spa_sys::spa_io_position_state {
+        self.0
+    }
+}
+
+impl Debug for IoPositionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = format!(
+            "IoPositionState::{}",
+            match *self {
+                Self::Stopped => "Stopped",
+                Self::Starting => "Starting",
+                Self::Running => "Running",
+                _ => "Unknown",
+            }
+        );
+        f.write_str(&name)
+    }
+}
+
+/// The position of the graph, of type [`IoType::Position`].
+///
+/// See the [module documentation](self) about concurrent updates.
+#[repr(transparent)]
+pub struct IoPosition(UnsafeCell<spa_sys::spa_io_position>);
+
+impl IoPosition {
+    /// Get the position from the area of an `io_changed` event with id [`IoType::Position`].
+    ///
+    /// Returns `None` if `area` is null, or is too small or misaligned for a position.
+    ///
+    /// # Safety
+    ///
+    /// If not null, `area` must point to `size` bytes valid for reads for the lifetime `'a`.
+    pub unsafe fn from_area<'a>(area: *mut c_void, size: u32) -> Option<&'a Self> {
+        cast_area(area, size)
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut spa_sys::spa_io_position {
+        self.0.get()
+    }
+
+    /// Copy the position once, so the values read from the copy are consistent.
+    pub fn snapshot(&self) -> Self {
+        Self(UnsafeCell::new(unsafe { self.0.get().read_volatile() }))
+    }
+
+    /// The clock of the driver.
+    pub fn clock(&self) -> &IoClock {
+        // IoClock is a transparent wrapper of an UnsafeCell, so it can point into the position
+        unsafe { &*(addr_of!((*self.0.get()).clock) as *const IoClock) }
+    }
+
+    /// Offset of the position, in nanoseconds, relative to the clock time.
+    pub fn offset(&self) -> i64 {
+        unsafe { addr_of!((*self.0.get()).offset).read_volatile() }
+    }
+
+    /// The state of the transport.
+    pub fn state(&self) -> IoPositionState {
+        IoPositionState::from_raw(unsafe { addr_of!((*self.0.get()).state).read_volatile() })
+    }
+}
+
+impl Debug for IoPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let position = self.snapshot();
+        f.debug_struct("IoPosition")
+            .field("clock", position.clock())
+            .field("offset", &position.offset())
+            .field("state", &position.state())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position() -> Box<spa_sys::spa_io_position> {
+        let mut position: Box<spa_sys::spa_io_position> = Box::new(unsafe { std::mem::zeroed() });
+        position.clock.id = 42;
+        for (c, n) in position.clock.name.iter_mut().zip(b"test.clock\0") {
+            *c = *n as _;
+        }
+        position.clock.nsec = 1_000_000;
+        position.clock.rate = Fraction {
+            num: 1,
+            denom: 48000,
+        }
+        .as_raw();
+        position.clock.position = 4096;
+        position.clock.duration = 1024;
+        position.offset = -10;
+        position.state = spa_sys::SPA_IO_POSITION_STATE_RUNNING;
+        position
+    }
+
+    #[test]
+    fn clock() {
+        let mut raw = position();
+        let area = addr_of!(raw.clock) as *mut c_void;
+        let clock =
+            unsafe { IoClock::from_area(area, size_of::<spa_sys::spa_io_clock>() as u32) }.unwrap();
+
+        assert_eq!(clock.id(), 42);
+        assert_eq!(clock.name(), "test.clock");
+        assert_eq!(clock.clock_time_ns(), 1_000_000);
+        assert_eq!(
+            clock.rate(),
+            Fraction {
+                num: 1,
+                denom: 48000
+            }
+        );
+        assert_eq!(clock.position(), 4096);
+        assert_eq!(clock.duration(), 1024);
+
+        let snapshot = clock.snapshot();
+        raw.clock.duration = 2048;
+        assert_eq!(snapshot.duration(), 1024);
+    }
+
+    #[test]
+    fn position_area() {
+        let mut raw = position();
+        let area = addr_of!(*raw) as *mut c_void;
+        let size = size_of::<spa_sys::spa_io_position>() as u32;
+        let position = unsafe { IoPosition::from_area(area, size) }.unwrap();
+
+        assert_eq!(position.clock().duration(), 1024);
+        assert_eq!(position.offset(), -10);
+        assert_eq!(position.state(), IoPositionState::Running);
+
+        raw.state = spa_sys::SPA_IO_POSITION_STATE_STOPPED;
+        let area = addr_of!(*raw) as *mut c_void;
+        let position = unsafe { IoPosition::from_area(area, size) }.unwrap();
+        assert_eq!(position.state(), IoPositionState::Stopped);
+    }
+
+    #[test]
+    fn invalid_area() {
+        let raw = position();
+        let area = addr_of!(*raw) as *mut c_void;
+        let size = size_of::<spa_sys::spa_io_position>() as u32;
+
+        assert!(unsafe { IoPosition::from_area(std::ptr::null_mut(), size) }.is_none());
+        assert!(unsafe { IoPosition::from_area(area, size - 1) }.is_none());
+        assert!(unsafe { IoClock::from_area(area.cast::<u8>().add(1).cast(), size - 1) }.is_none());
+    }
+}
@@ -1,4 +1,5 @@
 // Copyright The pipewire-rs Contributors.
 // SPDX-License-Identifier: MIT
 
+pub mod io;
 pub mod system;
@@ -1,4 +1,9 @@
+use std::time::Duration;
+
 use bitflags::bitflags;
+use nix::errno::Errno;
+
+use crate::utils::result::{Error, SpaResult};
 
 bitflags! {
     /// Flags used to specify different IO events.
@@ -14,3 +19,116 @@ bitflags! {
         const HUP = spa_sys::SPA_IO_HUP;
     }
 }
+
+/// A clock which can be read with [`SystemRef::clock_gettime`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum ClockId {
+    /// The wall clock time.
+    Realtime,
+    /// A clock which never goes backwards, used by the timers of a loop.
+    Monotonic,
+    /// Like `Monotonic`, without the frequency adjustments made by NTP.
+    MonotonicRaw,
+    /// Like `Monotonic`, including the time the system was suspended.
+    Boottime,
+    /// The International Atomic Time.
+    Tai,
+    /// Another clock id, such as one only known by a custom system.
+    Other(i32),
+}
+
+impl ClockId {
+    pub fn from_raw(raw: i32) -> Self {
+        match raw {
+            libc::CLOCK_REALTIME => Self::Realtime,
+            libc::CLOCK_MONOTONIC => Self::Monotonic,
+            libc::CLOCK_MONOTONIC_RAW => Self::MonotonicRaw,
+            libc::CLOCK_BOOTTIME => Self::Boottime,
+            libc::CLOCK_TAI => Self::Tai,
+            other => Self::Other(other),
+        }
+    }
+
+    pub fn as_raw(&self) -> i32 {
+        match self {
+            Self::Realtime => libc::CLOCK_REALTIME,
+            Self::Monotonic => libc::CLOCK_MONOTONIC,
+            Self::MonotonicRaw => libc::CLOCK_MONOTONIC_RAW,
+            Self::Boottime => libc::CLOCK_BOOTTIME,
+            Self::Tai => libc::CLOCK_TAI,
+            Self::Other(raw) => *raw,
+        }
+    }
+}
+
+/// A transparent wrapper around a [`spa_system`](spa_sys::spa_system), the interface used by
+/// loops to access clocks and file descriptors.
+///
+/// It is usually only seen in a reference (`&SystemRef`), borrowed from the loop using it.
+#[repr(transparent)]
+pub struct SystemRef(spa_sys::spa_system);
+
+impl SystemRef {
+    /// Borrow the system at `ptr`.
+    ///
+    /// # Safety
+    /// `ptr` must point to a valid `spa_system` which lives for `'a`.
+    pub unsafe fn from_raw<'a>(ptr: *mut spa_sys::spa_system) -> &'a Self {
+        &*ptr.cast::<Self>()
+    }
+
+    pub fn as_raw(&self) -> &spa_sys::spa_system {
+        &self.0
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut spa_sys::spa_system {
+        std::ptr::addr_of!(self.0).cast_mut()
+    }
+
+    /// Read the current time of `clock`.
+    ///
+    /// Fails with the errno reported by the system, such as `EINVAL` for an unknown clock.
+    pub fn clock_gettime(&self, clock: ClockId) -> Result<Duration, Error> {
+        let mut value: spa_sys::timespec = unsafe { std::mem::zeroed() };
+        let res = unsafe {
+            crate::spa_interface_call_method!(
+                self.as_raw_ptr(),
+                spa_sys::spa_system_methods,
+                clock_gettime,
+                clock.as_raw(),
+                &mut value
+            )
+        };
+        SpaResult::from_c(res).into_sync_result()?;
+
+        Ok(Duration::new(
+            value.tv_sec.try_into().map_err(|_| Errno::ERANGE)?,
+            value.tv_nsec.try_into().map_err(|_| Errno::ERANGE)?,
+        ))
+    }
+}
+
+impl std::fmt::Debug for SystemRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SystemRef").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_id_raw() {
+        for clock in [
+            ClockId::Realtime,
+            ClockId::Monotonic,
+            ClockId::MonotonicRaw,
+            ClockId::Boottime,
+            ClockId::Tai,
+            ClockId::Other(0x1234),
+        ] {
+            assert_eq!(ClockId::from_raw(clock.as_raw()), clock);
+        }
+    }
+}
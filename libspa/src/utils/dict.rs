@@ -6,7 +6,13 @@
 use bitflags::bitflags;
 // re-exported as used in the static_dict! macro implementation
 pub use spa_sys::spa_dict_item;
-use std::{convert::TryInto, ffi::CStr, fmt, marker::PhantomData, ptr};
+use std::{
+    convert::TryInto,
+    ffi::{CStr, CString},
+    fmt,
+    marker::PhantomData,
+    ptr,
+};
 
 #[repr(transparent)]
 pub struct DictRef(spa_sys::spa_dict);
@@ -155,6 +161,22 @@ impl std::fmt::Debug for DictRef {
     }
 }
 
+/// Serializes the dictionary as a map of strings.
+///
+/// Keys and values that are not valid utf-8 are converted lossily.
+#[cfg(feature = "serde")]
+impl serde::Serialize for DictRef {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (k, v) in self.iter_cstr() {
+            map.serialize_entry(&k.to_string_lossy(), &v.to_string_lossy())?;
+        }
+        map.end()
+    }
+}
+
 /// An error raised by [`DictRef::parse`] if the value cannot be converted to the requested type.
 #[derive(Debug, Eq, PartialEq)]
 pub struct ParseValueError {
@@ -414,9 +436,214 @@ impl fmt::Debug for StaticDict {
 unsafe impl Send for StaticDict {}
 unsafe impl Sync for StaticDict {}
 
+/// An owned, growable dictionary.
+///
+/// Unlike `pipewire::properties::Properties`, this type is allocated and managed on the Rust side
+/// and does not require libpipewire.
+///
+/// # Examples
+/// ```rust
+/// use libspa::{dict, utils::dict::Dict};
+///
+/// let mut dict = dict! {
+///     "Key" => "Value",
+/// };
+/// dict.insert("OtherKey", "OtherValue");
+///
+/// assert_eq!(Some("Value"), dict.get("Key"));
+/// assert_eq!(Some("OtherValue"), dict.get("OtherKey"));
+///
+/// dict.remove("Key");
+/// assert_eq!(None, dict.get("Key"));
+/// ```
+pub struct Dict {
+    // The heap allocations of the strings do not move when the vector grows,
+    // so the items can keep pointing to them.
+    entries: Vec<(CString, CString)>,
+    items: Vec<spa_sys::spa_dict_item>,
+    raw: spa_sys::spa_dict,
+}
+
+impl Dict {
+    /// Create a new, empty `Dict`.
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            items: Vec::new(),
+            raw: spa_sys::spa_dict {
+                flags: Flags::empty().bits(),
+                n_items: 0,
+                items: ptr::null(),
+            },
+        }
+    }
+
+    /// Insert a key-value pair, replacing the previous value if the key was already present.
+    ///
+    /// # Panics
+    /// If the key or value contain a null byte.
+    pub fn insert<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<Vec<u8>>,
+        V: Into<Vec<u8>>,
+    {
+        let key = CString::new(key).expect("Null byte in key");
+        let value = CString::new(value).expect("Null byte in value");
+
+        match self.entries.iter().position(|(k, _)| *k == key) {
+            Some(index) => {
+                self.items[index].value = value.as_ptr();
+                self.entries[index].1 = value;
+            }
+            None => {
+                self.items.push(spa_sys::spa_dict_item {
+                    key: key.as_ptr(),
+                    value: value.as_ptr(),
+                });
+                self.entries.push((key, value));
+            }
+        }
+
+        self.update_raw();
+    }
+
+    /// Remove the key-value pair with the provided key, if it is present.
+    pub fn remove<K>(&mut self, key: K)
+    where
+        K: Into<Vec<u8>>,
+    {
+        let key: Vec<u8> = key.into();
+
+        if let Some(index) = self
+            .entries
+            .iter()
+            .position(|(k, _)| k.as_bytes() == key.as_slice())
+        {
+            self.items.remove(index);
+            self.entries.remove(index);
+            self.update_raw();
+        }
+    }
+
+    /// Remove all key-value pairs.
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.entries.clear();
+        self.update_raw();
+    }
+
+    fn update_raw(&mut self) {
+        self.raw.n_items = self.items.len().try_into().unwrap();
+        self.raw.items = if self.items.is_empty() {
+            ptr::null()
+        } else {
+            self.items.as_ptr()
+        };
+    }
+}
+
+impl Default for Dict {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::ops::Deref for Dict {
+    type Target = DictRef;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*(ptr::addr_of!(self.raw).cast::<DictRef>()) }
+    }
+}
+
+impl AsRef<DictRef> for Dict {
+    fn as_ref(&self) -> &DictRef {
+        self
+    }
+}
+
+impl Clone for Dict {
+    fn clone(&self) -> Self {
+        Self::from(self.as_ref())
+    }
+}
+
+impl From<&DictRef> for Dict {
+    /// Create a new `Dict` by copying all entries of `dict`.
+    fn from(dict: &DictRef) -> Self {
+        dict.iter_cstr()
+            .map(|(k, v)| (k.to_bytes(), v.to_bytes()))
+            .collect()
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for Dict
+where
+    K: Into<Vec<u8>>,
+    V: Into<Vec<u8>>,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut dict = Self::new();
+        dict.extend(iter);
+
+        dict
+    }
+}
+
+impl<K, V> Extend<(K, V)> for Dict
+where
+    K: Into<Vec<u8>>,
+    V: Into<Vec<u8>>,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl fmt::Debug for Dict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dict: &DictRef = self.as_ref();
+        f.debug_tuple("Dict").field(dict).finish()
+    }
+}
+
+// Safety: All the data the raw pointers point to is owned by the dict itself.
+unsafe impl Send for Dict {}
+unsafe impl Sync for Dict {}
+
+/// A macro for creating a new [`Dict`] with predefined key-value pairs.
+///
+/// The macro accepts a list of `Key => Value` pairs, separated by commas.
+/// Any expression that evaluates to a `impl Into<Vec<u8>>` can be used for both keys and values.
+///
+/// # Examples:
+/// ```rust
+/// use libspa::dict;
+///
+/// let dict = dict! {
+///    "Key1" => "Value1",
+///    String::from("Key2") => "Value2",
+/// };
+///
+/// assert_eq!(dict.len(), 2);
+/// ```
+#[macro_export]
+macro_rules! dict {
+    {$($k:expr => $v:expr),* $(,)?} => {{
+        #[allow(unused_mut)]
+        let mut dict = $crate::utils::dict::Dict::new();
+        $(
+            dict.insert($k, $v);
+        )*
+        dict
+    }};
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{DictRef, Flags, StaticDict};
+    use super::{Dict, DictRef, Flags, StaticDict};
     use spa_sys::spa_dict;
     use std::ptr;
 
@@ -449,6 +676,18 @@ mod tests {
         assert_eq!(None, iter.next());
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let dict = static_dict! {
+            "K0" => "V0",
+            "K1" => "V1"
+        };
+
+        let json = serde_json::to_string(&*dict).unwrap();
+        assert_eq!(json, r#"{"K0":"V0","K1":"V1"}"#);
+    }
+
     #[test]
     fn test_iterators() {
         let dict = static_dict! {
@@ -518,6 +757,62 @@ mod tests {
         assert_eq!(DICT.get("K1"), Some("V1"));
     }
 
+    #[test]
+    fn owned_dict() {
+        let mut dict = dict! {
+            "K0" => "V0",
+            "K1" => "V1",
+        };
+        assert_eq!(dict.len(), 2);
+        assert_eq!(dict.get("K0"), Some("V0"));
+
+        // Grow the dict enough to force reallocations of the items.
+        for i in 2..64 {
+            dict.insert(format!("K{i}"), format!("V{i}"));
+        }
+        assert_eq!(dict.len(), 64);
+        assert_eq!(dict.get("K1"), Some("V1"));
+        assert_eq!(dict.get("K63"), Some("V63"));
+
+        // Replace an existing value.
+        dict.insert("K1", "V1'");
+        assert_eq!(dict.len(), 64);
+        assert_eq!(dict.get("K1"), Some("V1'"));
+
+        dict.remove("K0");
+        assert_eq!(dict.len(), 63);
+        assert_eq!(dict.get("K0"), None);
+        assert_eq!(dict.iter().next(), Some(("K1", "V1'")));
+
+        let copy = dict.clone();
+        dict.clear();
+        assert!(dict.is_empty());
+        assert_eq!(dict.iter().next(), None);
+        assert_eq!(copy.len(), 63);
+        assert_eq!(copy.get("K2"), Some("V2"));
+
+        let empty = dict! {};
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn owned_dict_conversions() {
+        let static_dict = static_dict! {
+            "K0" => "V0"
+        };
+        let dict = Dict::from(&*static_dict);
+        assert_eq!(dict.get("K0"), Some("V0"));
+
+        let dict: Dict = vec![("K0", "V0"), ("K1", "V1")].into_iter().collect();
+        let dict_ref: &DictRef = &dict;
+        assert_eq!(dict_ref.len(), 2);
+
+        assert_eq!(
+            r#"Dict(DictRef { flags: Flags(0x0), entries: {"K0": "V0", "K1": "V1"} })"#,
+            &format!("{:?}", dict)
+        );
+    }
+
     #[test]
     fn parse() {
         use super::ParseValueError;
@@ -8,8 +8,8 @@ pub struct Direction(spa_sys::spa_direction);
 
 #[allow(non_upper_case_globals)]
 impl Direction {
-    pub const Input: Self = Self(spa_sys::SPA_DIRECTION_INPUT);
-    pub const Output: Self = Self(spa_sys::SPA_DIRECTION_OUTPUT);
+    pub const Input: Self = Self(spa_sys::spa_direction::SPA_DIRECTION_INPUT);
+    pub const Output: Self = Self(spa_sys::spa_direction::SPA_DIRECTION_OUTPUT);
 
     pub fn from_raw(raw: spa_sys::spa_direction) -> Self {
         Self(raw)
@@ -51,19 +51,25 @@ mod tests {
 
     #[test]
     fn as_raw() {
-        assert_eq!(Direction::Input.as_raw(), spa_sys::SPA_DIRECTION_INPUT);
-        assert_eq!(Direction::Output.as_raw(), spa_sys::SPA_DIRECTION_OUTPUT);
+        assert_eq!(
+            Direction::Input.as_raw(),
+            spa_sys::spa_direction::SPA_DIRECTION_INPUT
+        );
+        assert_eq!(
+            Direction::Output.as_raw(),
+            spa_sys::spa_direction::SPA_DIRECTION_OUTPUT
+        );
     }
 
     #[test]
     fn from_raw() {
         assert_eq!(
             Direction::Input,
-            Direction::from_raw(spa_sys::SPA_DIRECTION_INPUT)
+            Direction::from_raw(spa_sys::spa_direction::SPA_DIRECTION_INPUT)
         );
         assert_eq!(
             Direction::Output,
-            Direction::from_raw(spa_sys::SPA_DIRECTION_OUTPUT)
+            Direction::from_raw(spa_sys::spa_direction::SPA_DIRECTION_OUTPUT)
         );
     }
 
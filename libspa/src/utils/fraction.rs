@@ -0,0 +1,300 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! SPA fractions.
+
+use std::{cmp::Ordering, fmt, ops::Mul, str::FromStr};
+
+/// A fraction, such as a frame rate or a latency relative to a rate, with the layout of
+/// [`spa_sys::spa_fraction`].
+///
+/// Equality is structural: `1/2` and `2/4` are different fractions, use [`reduce`](Self::reduce)
+/// to compare their values, or [`Ord`] which compares them by value first.
+///
+/// Fractions are formatted and parsed in the `"num/denom"` form used in properties:
+///
+/// ```
+/// use libspa::utils::Fraction;
+///
+/// let latency: Fraction = "1024/48000".parse().unwrap();
+/// assert_eq!(latency.reduce(), Fraction { num: 8, denom: 375 });
+/// assert_eq!(latency.to_string(), "1024/48000");
+/// ```
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Fraction {
+    /// The numerator.
+    pub num: u32,
+    /// The denominator.
+    pub denom: u32,
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl Fraction {
+    /// Return the equivalent fraction in its lowest terms.
+    ///
+    /// Fractions with a zero denominator are returned unchanged.
+    pub fn reduce(self) -> Self {
+        if self.denom == 0 {
+            return self;
+        }
+        let gcd = gcd(self.num, self.denom);
+        Self {
+            num: self.num / gcd,
+            denom: self.denom / gcd,
+        }
+    }
+
+    /// The value of the fraction, infinite or NaN if the denominator is zero.
+    pub fn as_f64(&self) -> f64 {
+        f64::from(self.num) / f64::from(self.denom)
+    }
+
+    /// Multiply the fraction by `rhs`, returning `None` if the numerator overflows.
+    ///
+    /// `rhs` is simplified with the denominator first, so `1/48000 * 48000` is `1/1`
+    /// rather than `48000/48000`.
+    pub fn checked_mul(self, rhs: u32) -> Option<Self> {
+        let gcd = if self.denom == 0 {
+            1
+        } else {
+            gcd(rhs, self.denom)
+        };
+        Some(Self {
+            num: self.num.checked_mul(rhs / gcd)?,
+            denom: self.denom / gcd,
+        })
+    }
+
+    /// Obtain a [`Fraction`] from a raw `spa_fraction`.
+    pub fn from_raw(raw: spa_sys::spa_fraction) -> Self {
+        Self {
+            num: raw.num,
+            denom: raw.denom,
+        }
+    }
+
+    /// Get the raw [`spa_sys::spa_fraction`] representing this `Fraction`.
+    pub fn as_raw(&self) -> spa_sys::spa_fraction {
+        spa_sys::spa_fraction {
+            num: self.num,
+            denom: self.denom,
+        }
+    }
+}
+
+/// Multiply the fraction by an integer, see [`Fraction::checked_mul`].
+///
+/// # Panics
+///
+/// Panics if the numerator overflows.
+impl Mul<u32> for Fraction {
+    type Output = Self;
+
+    fn mul(self, rhs: u32) -> Self::Output {
+        self.checked_mul(rhs)
+            .expect("attempt to multiply fraction with overflow")
+    }
+}
+
+/// Fractions are ordered by value, fractions with the same value by numerator.
+///
+/// Fractions with a zero denominator are greater than all others.
+impl Ord for Fraction {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let value = match (self.denom, other.denom) {
+            (0, 0) => Ordering::Equal,
+            (0, _) => Ordering::Greater,
+            (_, 0) => Ordering::Less,
+            // cannot overflow as the product of two u32 fits in a u64
+            _ => (u64::from(self.num) * u64::from(other.denom))
+                .cmp(&(u64::from(other.num) * u64::from(self.denom))),
+        };
+        value
+            .then(self.num.cmp(&other.num))
+            .then(self.denom.cmp(&other.denom))
+    }
+}
+
+impl PartialOrd for Fraction {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Fraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.num, self.denom)
+    }
+}
+
+/// Error returned when parsing a [`Fraction`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseFractionError {
+    /// The string is not in the `"num/denom"` form.
+    InvalidFormat,
+    /// The numerator or the denominator is not a valid `u32`.
+    InvalidInteger(std::num::ParseIntError),
+    /// The denominator is zero.
+    ZeroDenominator,
+}
+
+impl fmt::Display for ParseFractionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFormat => f.write_str("fraction is not in the num/denom form"),
+            Self::InvalidInteger(err) => write!(f, "invalid fraction term: {err}"),
+            Self::ZeroDenominator => f.write_str("fraction has a zero denominator"),
+        }
+    }
+}
+
+impl std::error::Error for ParseFractionError {}
+
+/// Parse a fraction in the `"num/denom"` form, ignoring whitespace around the terms.
+impl FromStr for Fraction {
+    type Err = ParseFractionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (num, denom) = s.split_once('/').ok_or(ParseFractionError::InvalidFormat)?;
+        let num = num
+            .trim()
+            .parse()
+            .map_err(ParseFractionError::InvalidInteger)?;
+        let denom = denom
+            .trim()
+            .parse()
+            .map_err(ParseFractionError::InvalidInteger)?;
+        if denom == 0 {
+            return Err(ParseFractionError::ZeroDenominator);
+        }
+
+        Ok(Self { num, denom })
+    }
+}
+
+impl From<spa_sys::spa_fraction> for Fraction {
+    fn from(value: spa_sys::spa_fraction) -> Self {
+        Self::from_raw(value)
+    }
+}
+
+impl From<Fraction> for spa_sys::spa_fraction {
+    fn from(value: Fraction) -> Self {
+        value.as_raw()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn f(num: u32, denom: u32) -> Fraction {
+        Fraction { num, denom }
+    }
+
+    #[test]
+    fn reduce() {
+        assert_eq!(f(1024, 48000).reduce(), f(8, 375));
+        assert_eq!(f(30, 1).reduce(), f(30, 1));
+        assert_eq!(f(0, 5).reduce(), f(0, 1));
+        assert_eq!(f(u32::MAX, u32::MAX).reduce(), f(1, 1));
+        assert_eq!(f(3, 0).reduce(), f(3, 0));
+        assert_eq!(f(0, 0).reduce(), f(0, 0));
+    }
+
+    #[test]
+    fn as_f64() {
+        assert_eq!(f(1, 4).as_f64(), 0.25);
+        assert_eq!(f(30000, 1001).as_f64(), 30000.0 / 1001.0);
+        assert_eq!(f(1, 0).as_f64(), f64::INFINITY);
+        assert!(f(0, 0).as_f64().is_nan());
+    }
+
+    #[test]
+    fn mul() {
+        assert_eq!(f(1, 48000) * 48000, f(1, 1));
+        assert_eq!(f(1024, 48000) * 2, f(1024, 24000));
+        assert_eq!(f(3, 4) * 6, f(9, 2));
+        assert_eq!(f(3, 4) * 0, f(0, 1));
+        assert_eq!(f(3, 0) * 2, f(6, 0));
+        assert_eq!(f(u32::MAX, 2).checked_mul(2), Some(f(u32::MAX, 1)));
+        assert_eq!(f(u32::MAX, 3).checked_mul(2), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow")]
+    fn mul_overflow() {
+        let _ = f(u32::MAX, 1) * 2;
+    }
+
+    #[test]
+    fn ord() {
+        assert!(f(1, 3) < f(1, 2));
+        assert!(f(30000, 1001) < f(30, 1));
+        assert!(f(60, 1) > f(30, 1));
+        // values are compared without overflowing
+        assert!(f(u32::MAX, u32::MAX - 1) > f(u32::MAX - 1, u32::MAX));
+        assert!(f(u32::MAX, 1) > f(u32::MAX - 1, 1));
+        // consistent with Eq for equal values
+        assert!(f(1, 2) < f(2, 4));
+        assert_eq!(f(2, 4).cmp(&f(2, 4)), Ordering::Equal);
+        // zero denominators
+        assert!(f(1, 0) > f(u32::MAX, 1));
+        assert!(f(0, 0) < f(1, 0));
+
+        let mut rates = vec![f(60, 1), f(0, 1), f(30000, 1001), f(1, 0), f(30, 1)];
+        rates.sort();
+        assert_eq!(
+            rates,
+            vec![f(0, 1), f(30000, 1001), f(30, 1), f(60, 1), f(1, 0)]
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(f(256, 48000).to_string(), "256/48000");
+        assert_eq!(f(0, 0).to_string(), "0/0");
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!("256/48000".parse(), Ok(f(256, 48000)));
+        assert_eq!(" 1 / 30 ".parse(), Ok(f(1, 30)));
+        assert_eq!("4294967295/1".parse(), Ok(f(u32::MAX, 1)));
+
+        assert_eq!(
+            "1/0".parse::<Fraction>(),
+            Err(ParseFractionError::ZeroDenominator)
+        );
+        assert_eq!(
+            "48000".parse::<Fraction>(),
+            Err(ParseFractionError::InvalidFormat)
+        );
+        assert!(matches!(
+            "4294967296/1".parse::<Fraction>(),
+            Err(ParseFractionError::InvalidInteger(_))
+        ));
+        assert!(matches!(
+            "1/-2".parse::<Fraction>(),
+            Err(ParseFractionError::InvalidInteger(_))
+        ));
+        assert!(matches!(
+            "1/2/3".parse::<Fraction>(),
+            Err(ParseFractionError::InvalidInteger(_))
+        ));
+    }
+
+    #[test]
+    fn raw() {
+        let raw = f(1, 2).as_raw();
+        assert_eq!((raw.num, raw.denom), (1, 2));
+        assert_eq!(Fraction::from_raw(raw), f(1, 2));
+    }
+}
@@ -6,8 +6,13 @@
 use crate::utils::list;
 
 /// Remove a hook
+///
+/// Nothing is removed if the hook is not in a list, either because it was never added to one
+/// or because it was reset by [`reset_on_remove`].
 pub fn remove(mut hook: spa_sys::spa_hook) {
-    list::remove(&hook.link);
+    if !hook.link.prev.is_null() {
+        list::remove(&hook.link);
+    }
 
     if let Some(removed) = hook.removed {
         unsafe {
@@ -16,6 +21,41 @@ pub fn remove(mut hook: spa_sys::spa_hook) {
     }
 }
 
+/// Reset the link of `hook` when it is removed from its list.
+///
+/// Objects such as PipeWire proxies remove all their hooks when they are destroyed, leaving the links
+/// of the hooks pointing to freed memory. Resetting the link makes a later [`remove`] a no-op.
+///
+/// This must be called after adding the hook to a list, as adding it clears the hook.
+pub fn reset_on_remove(hook: &mut spa_sys::spa_hook) {
+    unsafe extern "C" fn reset(hook: *mut spa_sys::spa_hook) {
+        (*hook).link.prev = std::ptr::null_mut();
+        (*hook).link.next = std::ptr::null_mut();
+    }
+
+    hook.removed = Some(reset);
+}
+
+/// Stop calling the callbacks of `hook`, until [`unblock`] is called.
+///
+/// The hook stays in its list, but SPA skips hooks without callbacks when emitting events.
+pub fn block(hook: &mut spa_sys::spa_hook) {
+    hook.cb.funcs = std::ptr::null();
+}
+
+/// Call the callbacks of a hook blocked by [`block`] again.
+///
+/// # Safety
+/// `funcs` must be the events struct the hook was added with, and stay valid while the hook is in its list.
+pub unsafe fn unblock(hook: &mut spa_sys::spa_hook, funcs: *const std::ffi::c_void) {
+    hook.cb.funcs = funcs;
+}
+
+/// Whether the callbacks of `hook` are blocked by [`block`].
+pub fn is_blocked(hook: &spa_sys::spa_hook) -> bool {
+    hook.cb.funcs.is_null()
+}
+
 /// Call a method on a spa_interface.
 ///
 /// This needs to be called from within an `unsafe` block.
@@ -59,3 +99,51 @@ macro_rules! spa_interface_call_method {
         f((*iface).cb.data, $($arg),*)
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remove_unlinked() {
+        // a hook never added to a list
+        let hook: spa_sys::spa_hook = unsafe { std::mem::zeroed() };
+        remove(hook);
+
+        let mut head: spa_sys::spa_list = unsafe { std::mem::zeroed() };
+        let mut hook: spa_sys::spa_hook = unsafe { std::mem::zeroed() };
+        head.prev = &mut hook.link;
+        head.next = &mut hook.link;
+        hook.link.prev = &mut head;
+        hook.link.next = &mut head;
+        reset_on_remove(&mut hook);
+
+        // the list removes the hook, as when destroying its object
+        remove(hook);
+        unsafe { hook.removed.unwrap()(&mut hook) };
+        assert!(hook.link.prev.is_null() && hook.link.next.is_null());
+
+        // removing it again does not touch the list
+        head.prev = std::ptr::null_mut();
+        head.next = std::ptr::null_mut();
+        remove(hook);
+        assert!(head.prev.is_null() && head.next.is_null());
+    }
+
+    #[test]
+    fn block_unblock() {
+        let events = 0u32;
+        let funcs = &events as *const u32 as *const std::ffi::c_void;
+        let mut hook: spa_sys::spa_hook = unsafe { std::mem::zeroed() };
+        hook.cb.funcs = funcs;
+        assert!(!is_blocked(&hook));
+
+        block(&mut hook);
+        assert!(is_blocked(&hook));
+        assert!(hook.cb.funcs.is_null());
+
+        unsafe { unblock(&mut hook, funcs) };
+        assert!(!is_blocked(&hook));
+        assert_eq!(hook.cb.funcs, funcs);
+    }
+}
@@ -3,17 +3,20 @@
 pub mod dict;
 mod direction;
 pub use direction::*;
+mod fraction;
+pub use fraction::*;
 pub mod hook;
 pub mod list;
+mod point;
+pub use point::*;
+mod rectangle;
+pub use rectangle::*;
 pub mod result;
 
 use bitflags::bitflags;
 use convert_case::{Case, Casing};
 use std::{ffi::CStr, fmt::Debug, os::raw::c_uint};
 
-pub use spa_sys::spa_fraction as Fraction;
-pub use spa_sys::spa_rectangle as Rectangle;
-
 use crate::pod::CanonicalFixedSizedPod;
 
 /// An enumerated value in a pod
@@ -80,6 +83,88 @@ pub enum ChoiceEnum<T: CanonicalFixedSizedPod> {
     },
 }
 
+/// Typed constructors, ensuring each kind of choice has its expected values.
+///
+/// The first value of each choice is its default. Choices can be used as property values in an
+/// [`ObjectBuilder`](crate::pod::ObjectBuilder):
+///
+/// ```
+/// use libspa::{param::ParamType, pod::ObjectBuilder, utils::{Choice, Fraction, SpaTypes}};
+///
+/// let pod = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+///     .prop(libspa::sys::SPA_FORMAT_AUDIO_rate, Choice::range(48000, 8000, 192000))
+///     .prop(
+///         libspa::sys::SPA_FORMAT_VIDEO_framerate,
+///         Choice::enumerate(
+///             Fraction { num: 30, denom: 1 },
+///             [Fraction { num: 30, denom: 1 }, Fraction { num: 60, denom: 1 }],
+///         ),
+///     )
+///     .build();
+/// ```
+impl<T: CanonicalFixedSizedPod> Choice<T> {
+    /// A single value, without choice.
+    pub fn none(value: T) -> Self {
+        Self(ChoiceFlags::empty(), ChoiceEnum::None(value))
+    }
+
+    /// Any value between `min` and `max`.
+    pub fn range(default: T, min: T, max: T) -> Self {
+        Self(
+            ChoiceFlags::empty(),
+            ChoiceEnum::Range { default, min, max },
+        )
+    }
+
+    /// Any value between `min` and `max` in increments of `step`.
+    pub fn step(default: T, min: T, max: T, step: T) -> Self {
+        Self(
+            ChoiceFlags::empty(),
+            ChoiceEnum::Step {
+                default,
+                min,
+                max,
+                step,
+            },
+        )
+    }
+
+    /// One of the `alternatives`.
+    pub fn enumerate(default: T, alternatives: impl IntoIterator<Item = T>) -> Self {
+        Self(
+            ChoiceFlags::empty(),
+            ChoiceEnum::Enum {
+                default,
+                alternatives: alternatives.into_iter().collect(),
+            },
+        )
+    }
+
+    /// A combination of the `flags`.
+    pub fn flags(default: T, flags: impl IntoIterator<Item = T>) -> Self {
+        Self(
+            ChoiceFlags::empty(),
+            ChoiceEnum::Flags {
+                default,
+                flags: flags.into_iter().collect(),
+            },
+        )
+    }
+}
+
+impl<T: CanonicalFixedSizedPod> ChoiceEnum<T> {
+    /// The value used when fixating the choice.
+    pub fn default_value(&self) -> &T {
+        match self {
+            Self::None(value) => value,
+            Self::Range { default, .. }
+            | Self::Step { default, .. }
+            | Self::Enum { default, .. }
+            | Self::Flags { default, .. } => default,
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub struct SpaTypes(pub c_uint);
 
@@ -133,6 +218,8 @@ impl SpaTypes {
     pub const ObjectProfiler: Self = Self(spa_sys::SPA_TYPE_OBJECT_Profiler);
     pub const ObjectParamLatency: Self = Self(spa_sys::SPA_TYPE_OBJECT_ParamLatency);
     pub const ObjectParamProcessLatency: Self = Self(spa_sys::SPA_TYPE_OBJECT_ParamProcessLatency);
+    #[cfg(feature = "v0_3_79")]
+    pub const ObjectParamTag: Self = Self(spa_sys::SPA_TYPE_OBJECT_ParamTag);
 
     /* vendor extensions */
     pub const VendorPipeWire: Self = Self(spa_sys::SPA_TYPE_VENDOR_PipeWire);
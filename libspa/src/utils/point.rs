@@ -0,0 +1,41 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! SPA points.
+
+/// A position, such as the position of a region in a video frame, with the layout of [`spa_sys::spa_point`].
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Point {
+    /// The horizontal position.
+    pub x: i32,
+    /// The vertical position.
+    pub y: i32,
+}
+
+impl Point {
+    /// Obtain a [`Point`] from a raw `spa_point`.
+    pub fn from_raw(raw: spa_sys::spa_point) -> Self {
+        Self { x: raw.x, y: raw.y }
+    }
+
+    /// Get the raw [`spa_sys::spa_point`] representing this `Point`.
+    pub fn as_raw(&self) -> spa_sys::spa_point {
+        spa_sys::spa_point {
+            x: self.x,
+            y: self.y,
+        }
+    }
+}
+
+impl From<spa_sys::spa_point> for Point {
+    fn from(value: spa_sys::spa_point) -> Self {
+        Self::from_raw(value)
+    }
+}
+
+impl From<Point> for spa_sys::spa_point {
+    fn from(value: Point) -> Self {
+        value.as_raw()
+    }
+}
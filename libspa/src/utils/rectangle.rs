@@ -0,0 +1,176 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! SPA rectangles.
+
+use std::{fmt, str::FromStr};
+
+/// A size, such as the size of a video frame, with the layout of [`spa_sys::spa_rectangle`].
+///
+/// Rectangles are formatted and parsed in the `"WxH"` form:
+///
+/// ```
+/// use libspa::utils::Rectangle;
+///
+/// let size: Rectangle = "1920x1080".parse().unwrap();
+/// assert_eq!(size, Rectangle { width: 1920, height: 1080 });
+/// assert_eq!(size.area(), 2_073_600);
+/// assert_eq!(size.to_string(), "1920x1080");
+/// ```
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rectangle {
+    /// The width.
+    pub width: u32,
+    /// The height.
+    pub height: u32,
+}
+
+impl Rectangle {
+    /// The number of pixels in the rectangle.
+    pub fn area(&self) -> u64 {
+        u64::from(self.width) * u64::from(self.height)
+    }
+
+    /// Whether the rectangle is neither wider nor higher than `other`.
+    pub fn fits_in(&self, other: &Rectangle) -> bool {
+        self.width <= other.width && self.height <= other.height
+    }
+
+    /// Obtain a [`Rectangle`] from a raw `spa_rectangle`.
+    pub fn from_raw(raw: spa_sys::spa_rectangle) -> Self {
+        Self {
+            width: raw.width,
+            height: raw.height,
+        }
+    }
+
+    /// Get the raw [`spa_sys::spa_rectangle`] representing this `Rectangle`.
+    pub fn as_raw(&self) -> spa_sys::spa_rectangle {
+        spa_sys::spa_rectangle {
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+impl fmt::Display for Rectangle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// Error returned when parsing a [`Rectangle`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseRectangleError {
+    /// The string is not in the `"WxH"` form.
+    InvalidFormat,
+    /// The width or the height is not a valid `u32`.
+    InvalidInteger(std::num::ParseIntError),
+}
+
+impl fmt::Display for ParseRectangleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFormat => f.write_str("rectangle is not in the WxH form"),
+            Self::InvalidInteger(err) => write!(f, "invalid rectangle dimension: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseRectangleError {}
+
+/// Parse a rectangle in the `"WxH"` form, ignoring whitespace around the dimensions.
+impl FromStr for Rectangle {
+    type Err = ParseRectangleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = s
+            .split_once('x')
+            .ok_or(ParseRectangleError::InvalidFormat)?;
+
+        Ok(Self {
+            width: width
+                .trim()
+                .parse()
+                .map_err(ParseRectangleError::InvalidInteger)?,
+            height: height
+                .trim()
+                .parse()
+                .map_err(ParseRectangleError::InvalidInteger)?,
+        })
+    }
+}
+
+impl From<spa_sys::spa_rectangle> for Rectangle {
+    fn from(value: spa_sys::spa_rectangle) -> Self {
+        Self::from_raw(value)
+    }
+}
+
+impl From<Rectangle> for spa_sys::spa_rectangle {
+    fn from(value: Rectangle) -> Self {
+        value.as_raw()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn r(width: u32, height: u32) -> Rectangle {
+        Rectangle { width, height }
+    }
+
+    #[test]
+    fn area() {
+        assert_eq!(r(1920, 1080).area(), 2_073_600);
+        assert_eq!(r(0, 1080).area(), 0);
+        assert_eq!(
+            r(u32::MAX, u32::MAX).area(),
+            u64::from(u32::MAX) * u64::from(u32::MAX)
+        );
+    }
+
+    #[test]
+    fn fits_in() {
+        let max = r(1920, 1080);
+        assert!(r(1280, 720).fits_in(&max));
+        assert!(max.fits_in(&max));
+        assert!(!r(1921, 1).fits_in(&max));
+        assert!(!r(1, 1081).fits_in(&max));
+        assert!(!r(1080, 1920).fits_in(&max));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(r(1920, 1080).to_string(), "1920x1080");
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!("1920x1080".parse(), Ok(r(1920, 1080)));
+        assert_eq!(" 640 x 480 ".parse(), Ok(r(640, 480)));
+        assert_eq!("0x0".parse(), Ok(r(0, 0)));
+
+        assert_eq!(
+            "1920*1080".parse::<Rectangle>(),
+            Err(ParseRectangleError::InvalidFormat)
+        );
+        assert!(matches!(
+            "1920x".parse::<Rectangle>(),
+            Err(ParseRectangleError::InvalidInteger(_))
+        ));
+        assert!(matches!(
+            "4294967296x1".parse::<Rectangle>(),
+            Err(ParseRectangleError::InvalidInteger(_))
+        ));
+    }
+
+    #[test]
+    fn raw() {
+        let raw = r(1, 2).as_raw();
+        assert_eq!((raw.width, raw.height), (1, 2));
+        assert_eq!(Rectangle::from_raw(raw), r(1, 2));
+    }
+}
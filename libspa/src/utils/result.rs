@@ -130,11 +130,22 @@ impl SpaResult {
 pub struct Error(Errno);
 
 impl Error {
-    fn new(e: i32) -> Self {
+    pub(crate) fn new(e: i32) -> Self {
         assert!(e > 0);
 
         Self(Errno::from_i32(e))
     }
+
+    /// The errno of the error.
+    pub fn errno(&self) -> Errno {
+        self.0
+    }
+}
+
+impl From<Errno> for Error {
+    fn from(errno: Errno) -> Self {
+        Self(errno)
+    }
 }
 
 impl std::error::Error for Error {}
@@ -171,6 +182,7 @@ mod tests {
 
         let err = SpaResult::from_c(-libc::EBUSY).into_result().unwrap_err();
         assert_eq!(format!("{}", err), "EBUSY: Device or resource busy",);
+        assert_eq!(err.errno(), Errno::EBUSY);
 
         let res = SpaResult::from_c(-1).into_sync_result();
         assert!(res.is_err());
@@ -4,6 +4,7 @@
 #include <spa/pod/builder.h>
 #include <spa/debug/pod.h>
 #include <spa/param/audio/format-utils.h>
+#include <spa/param/video/format-utils.h>
 
 int build_none(uint8_t *buffer, size_t len)
 {
@@ -110,6 +111,42 @@ struct spa_pod *build_test_object(uint8_t *buffer, size_t len)
 									  SPA_PROP_frequency, SPA_POD_Float(440.0f));
 }
 
+struct spa_pod *build_test_object_blobs(uint8_t *buffer, size_t len, const void *bytes, size_t bytes_len, int64_t fd, uint32_t pointer_type, const void *pointer)
+{
+	struct spa_pod_builder b = SPA_POD_BUILDER_INIT(buffer, len);
+
+	return spa_pod_builder_add_object(&b,
+									  SPA_TYPE_OBJECT_Props, SPA_PARAM_Props,
+									  SPA_PROP_START_CUSTOM, SPA_POD_Bytes(bytes, bytes_len),
+									  SPA_PROP_START_CUSTOM + 1, SPA_POD_Fd(fd),
+									  SPA_PROP_START_CUSTOM + 2, SPA_POD_Pointer(pointer_type, pointer),
+									  SPA_PROP_frequency, SPA_POD_Float(440.0f));
+}
+
+struct spa_pod *build_test_object_prop_flags(uint8_t *buffer, size_t len, uint64_t modifier)
+{
+	struct spa_pod_builder b = SPA_POD_BUILDER_INIT(buffer, len);
+	struct spa_pod_frame f[2];
+
+	spa_pod_builder_push_object(&b, &f[0], SPA_TYPE_OBJECT_Format, SPA_PARAM_EnumFormat);
+	spa_pod_builder_add(&b,
+						SPA_FORMAT_mediaType, SPA_POD_Id(SPA_MEDIA_TYPE_video),
+						SPA_FORMAT_mediaSubtype, SPA_POD_Id(SPA_MEDIA_SUBTYPE_raw),
+						SPA_FORMAT_VIDEO_format, SPA_POD_Id(SPA_VIDEO_FORMAT_BGRx),
+						0);
+	spa_pod_builder_prop(&b, SPA_FORMAT_VIDEO_modifier,
+						 SPA_POD_PROP_FLAG_MANDATORY | SPA_POD_PROP_FLAG_DONT_FIXATE);
+	spa_pod_builder_push_choice(&b, &f[1], SPA_CHOICE_Enum, 0);
+	spa_pod_builder_long(&b, modifier);
+	spa_pod_builder_long(&b, modifier);
+	spa_pod_builder_long(&b, 0);
+	spa_pod_builder_pop(&b, &f[1]);
+	spa_pod_builder_prop(&b, SPA_FORMAT_VIDEO_size, SPA_POD_PROP_FLAG_READONLY);
+	spa_pod_builder_rectangle(&b, 1920, 1080);
+
+	return spa_pod_builder_pop(&b, &f[0]);
+}
+
 struct spa_pod *build_choice_i32(uint8_t *buffer, size_t len, uint32_t choice_type, uint32_t flags, uint32_t n_elems, uint32_t *elems)
 {
 	struct spa_pod_builder b = SPA_POD_BUILDER_INIT(buffer, len);
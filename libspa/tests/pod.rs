@@ -51,6 +51,20 @@ pub mod c {
         ) -> *const spa_pod;
         pub fn build_fd(buffer: *mut u8, len: usize, fd: i64) -> i32;
         pub fn build_test_object(buffer: *mut u8, len: usize) -> *const spa_pod;
+        pub fn build_test_object_blobs(
+            buffer: *mut u8,
+            len: usize,
+            bytes: *const u8,
+            bytes_len: usize,
+            fd: i64,
+            pointer_type: u32,
+            pointer: *const std::ffi::c_void,
+        ) -> *const spa_pod;
+        pub fn build_test_object_prop_flags(
+            buffer: *mut u8,
+            len: usize,
+            modifier: u64,
+        ) -> *const spa_pod;
         pub fn build_choice_i32(
             buffer: *mut u8,
             len: usize,
@@ -1457,6 +1471,137 @@ fn object() {
     assert_eq!(vec_rs, vec_c);
 }
 
+#[test]
+#[cfg_attr(miri, ignore)]
+fn object_blobs() {
+    let bytes = b"\x01\x02\x03\x04\x05";
+    let val = 7;
+    let pointer = &val as *const i32 as *const c_void;
+    const POINTER_TYPE: u32 = 10;
+
+    let mut vec_c: Vec<u8> = vec![0; 128];
+    let ptr = unsafe {
+        c::build_test_object_blobs(
+            vec_c.as_mut_ptr(),
+            vec_c.len(),
+            bytes.as_ptr(),
+            bytes.len(),
+            3,
+            POINTER_TYPE,
+            pointer,
+        )
+    };
+    assert!(!ptr.is_null());
+
+    let value = Value::Object(Object {
+        type_: spa_sys::SPA_TYPE_OBJECT_Props,
+        id: spa_sys::SPA_PARAM_Props,
+        properties: vec![
+            Property {
+                key: spa_sys::SPA_PROP_START_CUSTOM,
+                flags: PropertyFlags::empty(),
+                value: Value::Bytes(bytes.to_vec()),
+            },
+            Property {
+                key: spa_sys::SPA_PROP_START_CUSTOM + 1,
+                flags: PropertyFlags::empty(),
+                value: Value::Fd(Fd(3)),
+            },
+            Property {
+                key: spa_sys::SPA_PROP_START_CUSTOM + 2,
+                flags: PropertyFlags::empty(),
+                value: Value::Pointer(POINTER_TYPE, pointer),
+            },
+            Property {
+                key: spa_sys::SPA_PROP_frequency,
+                flags: PropertyFlags::empty(),
+                value: Value::Float(440.0),
+            },
+        ],
+    });
+
+    let vec_rs: Vec<u8> = PodSerializer::serialize(Cursor::new(Vec::new()), &value)
+        .unwrap()
+        .0
+        .into_inner();
+    assert_eq!(vec_rs, vec_c[..vec_rs.len()]);
+
+    assert_eq!(
+        PodDeserializer::deserialize_any_from(&vec_rs),
+        Ok((&[] as &[u8], value))
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn object_prop_flags() {
+    use libspa::{
+        param::{
+            format::{FormatProperties, MediaSubtype, MediaType},
+            video::VideoFormat,
+            ParamType,
+        },
+        pod::ObjectBuilder,
+        utils::SpaTypes,
+    };
+
+    const MODIFIER: i64 = 0x0100_0000_0000_0001;
+
+    let mut vec_c: Vec<u8> = vec![0; 256];
+    let ptr = unsafe {
+        c::build_test_object_prop_flags(vec_c.as_mut_ptr(), vec_c.len(), MODIFIER as u64)
+    };
+    assert!(!ptr.is_null());
+
+    let pod = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+        .prop(FormatProperties::MediaType, MediaType::Video)
+        .prop(FormatProperties::MediaSubtype, MediaSubtype::Raw)
+        .prop(FormatProperties::VideoFormat, VideoFormat::BGRx)
+        .prop_with_flags(
+            FormatProperties::VideoModifier,
+            PropertyFlags::MANDATORY | PropertyFlags::DONT_FIXATE,
+            Value::Choice(ChoiceValue::Long(Choice(
+                ChoiceFlags::empty(),
+                ChoiceEnum::Enum {
+                    default: MODIFIER,
+                    alternatives: vec![MODIFIER, 0],
+                },
+            ))),
+        )
+        .prop_with_flags(
+            FormatProperties::VideoSize,
+            PropertyFlags::READONLY,
+            Rectangle {
+                width: 1920,
+                height: 1080,
+            },
+        )
+        .build();
+    assert_eq!(pod.as_bytes(), &vec_c[..pod.as_bytes().len()]);
+
+    let (rest, Value::Object(object)) = PodDeserializer::deserialize_any_from(&vec_c).unwrap()
+    else {
+        panic!("expected an object");
+    };
+    assert_eq!(rest, &vec_c[pod.as_bytes().len()..]);
+    let flags: Vec<_> = object.properties.iter().map(|p| p.flags).collect();
+    assert_eq!(
+        flags,
+        [
+            PropertyFlags::empty(),
+            PropertyFlags::empty(),
+            PropertyFlags::empty(),
+            PropertyFlags::MANDATORY | PropertyFlags::DONT_FIXATE,
+            PropertyFlags::READONLY,
+        ]
+    );
+
+    // the flags survive a round trip
+    let (vec_rs, _) =
+        PodSerializer::serialize(Cursor::new(Vec::new()), &Value::Object(object)).unwrap();
+    assert_eq!(vec_rs.into_inner(), pod.as_bytes());
+}
+
 #[test]
 #[cfg_attr(miri, ignore)]
 fn choice_range_f32() {
@@ -2154,7 +2299,7 @@ fn pointer() {
     );
 }
 
-use libspa::param::audio::{self, AudioFormat, AudioInfoRaw};
+use libspa::param::audio::{AudioFormat, AudioInfoRaw};
 
 #[test]
 #[cfg_attr(miri, ignore)]
@@ -2229,12 +2374,11 @@ fn composite_values() {
 #[cfg_attr(miri, ignore)]
 fn audio_info_raw() {
     let id = 1;
-    let position = [0; audio::MAX_CHANNELS];
     let mut info = AudioInfoRaw::new();
     info.set_channels(1);
     info.set_rate(44100);
     info.set_format(AudioFormat::S8);
-    info.set_position(position);
+    info.set_position(&[]);
 
     let obj_rs = Value::Object(Object {
         type_: spa_sys::SPA_TYPE_OBJECT_Format,
@@ -2255,3 +2399,327 @@ fn audio_info_raw() {
     assert_eq!(vec_rs, vec_c);
     assert!(unsafe { c::parse_audio_info_raw(vec_c.as_mut_ptr()) } > 0);
 }
+
+// `SPA_PARAM_EnumFormat` pods as received in `param_changed` from a PipeWire server.
+// The audio one was announced by an audioconvert node, the video one by a v4l2 source.
+const ENUM_FORMAT_AUDIO_RAW: &[u8] = &[
+    0xc8, 0x00, 0x00, 0x00, 0x0f, 0x00, 0x00, 0x00, 0x03, 0x00, 0x04, 0x00, 0x03, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x04, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00,
+    0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00,
+    0x06, 0x02, 0x00, 0x00, 0x06, 0x02, 0x00, 0x00, 0x1b, 0x01, 0x00, 0x00, 0x03, 0x01, 0x00, 0x00,
+    0x03, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1c, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
+    0x80, 0xbb, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0x7f, 0x00, 0x00, 0x00, 0x00,
+    0x04, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1c, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
+    0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+const ENUM_FORMAT_VIDEO_RAW: &[u8] = &[
+    0x18, 0x01, 0x00, 0x00, 0x0f, 0x00, 0x00, 0x00, 0x03, 0x00, 0x04, 0x00, 0x03, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00,
+    0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x04, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00,
+    0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00,
+    0x08, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
+    0x02, 0x00, 0x02, 0x00, 0x08, 0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00,
+    0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x03, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x30, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x08, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0xd0, 0x02, 0x00, 0x00,
+    0xa0, 0x00, 0x00, 0x00, 0x78, 0x00, 0x00, 0x00, 0x80, 0x07, 0x00, 0x00, 0x38, 0x04, 0x00, 0x00,
+    0x10, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x04, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x28, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x08, 0x00, 0x00, 0x00, 0x0b, 0x00, 0x00, 0x00, 0x1e, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0xe8, 0x03, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+];
+
+fn check_enum_format_round_trip(fixture: &[u8], expected: Value) {
+    assert_eq!(
+        PodDeserializer::deserialize_any_from(fixture),
+        Ok((&[] as &[u8], expected.clone()))
+    );
+
+    let (cursor, len) = PodSerializer::serialize(Cursor::new(Vec::new()), &expected).unwrap();
+    let vec_rs = cursor.into_inner();
+    assert_eq!(len, vec_rs.len() as u64);
+    assert_eq!(vec_rs, fixture);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn enum_format_audio_raw() {
+    check_enum_format_round_trip(
+        ENUM_FORMAT_AUDIO_RAW,
+        Value::Object(Object {
+            type_: spa_sys::SPA_TYPE_OBJECT_Format,
+            id: spa_sys::SPA_PARAM_EnumFormat,
+            properties: vec![
+                Property {
+                    key: spa_sys::SPA_FORMAT_mediaType,
+                    flags: PropertyFlags::empty(),
+                    value: Value::Id(Id(spa_sys::SPA_MEDIA_TYPE_audio)),
+                },
+                Property {
+                    key: spa_sys::SPA_FORMAT_mediaSubtype,
+                    flags: PropertyFlags::empty(),
+                    value: Value::Id(Id(spa_sys::SPA_MEDIA_SUBTYPE_raw)),
+                },
+                Property {
+                    key: spa_sys::SPA_FORMAT_AUDIO_format,
+                    flags: PropertyFlags::empty(),
+                    value: Value::Choice(ChoiceValue::Id(Choice(
+                        ChoiceFlags::empty(),
+                        ChoiceEnum::Enum {
+                            default: Id(spa_sys::SPA_AUDIO_FORMAT_F32P),
+                            alternatives: vec![
+                                Id(spa_sys::SPA_AUDIO_FORMAT_F32P),
+                                Id(spa_sys::SPA_AUDIO_FORMAT_F32_LE),
+                                Id(spa_sys::SPA_AUDIO_FORMAT_S16_LE),
+                            ],
+                        },
+                    ))),
+                },
+                Property {
+                    key: spa_sys::SPA_FORMAT_AUDIO_rate,
+                    flags: PropertyFlags::empty(),
+                    value: Value::Choice(ChoiceValue::Int(Choice(
+                        ChoiceFlags::empty(),
+                        ChoiceEnum::Range {
+                            default: 48000,
+                            min: 1,
+                            max: i32::MAX,
+                        },
+                    ))),
+                },
+                Property {
+                    key: spa_sys::SPA_FORMAT_AUDIO_channels,
+                    flags: PropertyFlags::empty(),
+                    value: Value::Choice(ChoiceValue::Int(Choice(
+                        ChoiceFlags::empty(),
+                        ChoiceEnum::Range {
+                            default: 2,
+                            min: 1,
+                            max: 64,
+                        },
+                    ))),
+                },
+            ],
+        }),
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn enum_format_video_raw() {
+    check_enum_format_round_trip(
+        ENUM_FORMAT_VIDEO_RAW,
+        Value::Object(Object {
+            type_: spa_sys::SPA_TYPE_OBJECT_Format,
+            id: spa_sys::SPA_PARAM_EnumFormat,
+            properties: vec![
+                Property {
+                    key: spa_sys::SPA_FORMAT_mediaType,
+                    flags: PropertyFlags::empty(),
+                    value: Value::Id(Id(spa_sys::SPA_MEDIA_TYPE_video)),
+                },
+                Property {
+                    key: spa_sys::SPA_FORMAT_mediaSubtype,
+                    flags: PropertyFlags::empty(),
+                    value: Value::Id(Id(spa_sys::SPA_MEDIA_SUBTYPE_raw)),
+                },
+                Property {
+                    key: spa_sys::SPA_FORMAT_VIDEO_format,
+                    flags: PropertyFlags::empty(),
+                    value: Value::Choice(ChoiceValue::Id(Choice(
+                        ChoiceFlags::empty(),
+                        ChoiceEnum::Enum {
+                            default: Id(spa_sys::SPA_VIDEO_FORMAT_BGRx),
+                            alternatives: vec![
+                                Id(spa_sys::SPA_VIDEO_FORMAT_BGRx),
+                                Id(spa_sys::SPA_VIDEO_FORMAT_RGBx),
+                                Id(spa_sys::SPA_VIDEO_FORMAT_YUY2),
+                            ],
+                        },
+                    ))),
+                },
+                Property {
+                    key: spa_sys::SPA_FORMAT_VIDEO_modifier,
+                    flags: PropertyFlags::MANDATORY,
+                    value: Value::Choice(ChoiceValue::Long(Choice(
+                        ChoiceFlags::empty(),
+                        ChoiceEnum::Enum {
+                            default: 0,
+                            alternatives: vec![0, 0x00ff_ffff_ffff_ffff],
+                        },
+                    ))),
+                },
+                Property {
+                    key: spa_sys::SPA_FORMAT_VIDEO_size,
+                    flags: PropertyFlags::empty(),
+                    value: Value::Choice(ChoiceValue::Rectangle(Choice(
+                        ChoiceFlags::empty(),
+                        ChoiceEnum::Step {
+                            default: Rectangle {
+                                width: 1280,
+                                height: 720,
+                            },
+                            min: Rectangle {
+                                width: 160,
+                                height: 120,
+                            },
+                            max: Rectangle {
+                                width: 1920,
+                                height: 1080,
+                            },
+                            step: Rectangle {
+                                width: 16,
+                                height: 8,
+                            },
+                        },
+                    ))),
+                },
+                Property {
+                    key: spa_sys::SPA_FORMAT_VIDEO_framerate,
+                    flags: PropertyFlags::empty(),
+                    value: Value::Choice(ChoiceValue::Fraction(Choice(
+                        ChoiceFlags::empty(),
+                        ChoiceEnum::Range {
+                            default: Fraction { num: 30, denom: 1 },
+                            min: Fraction { num: 0, denom: 1 },
+                            max: Fraction {
+                                num: 1000,
+                                denom: 1,
+                            },
+                        },
+                    ))),
+                },
+            ],
+        }),
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn audio_info_raw_from_format_pod() {
+    use libspa::param::{
+        format::{FormatProperties, MediaSubtype, MediaType},
+        ParamType, ParseError,
+    };
+    use libspa::{pod::ObjectBuilder, utils::SpaTypes};
+
+    let format = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::Format)
+        .prop(FormatProperties::MediaType, MediaType::Audio)
+        .prop(FormatProperties::MediaSubtype, MediaSubtype::Raw)
+        .prop(FormatProperties::AudioFormat, AudioFormat::F32LE)
+        .prop(FormatProperties::AudioRate, 48000)
+        .prop(FormatProperties::AudioChannels, 2)
+        .prop(
+            FormatProperties::AudioPosition,
+            ValueArray::Id(vec![
+                Id(spa_sys::SPA_AUDIO_CHANNEL_FL),
+                Id(spa_sys::SPA_AUDIO_CHANNEL_FR),
+            ]),
+        )
+        .build();
+
+    let info = AudioInfoRaw::from_format_pod(&format).unwrap();
+    assert_eq!(info.format(), AudioFormat::F32LE);
+    assert_eq!(info.rate(), 48000);
+    assert_eq!(info.channels(), 2);
+    assert!(info.flags().is_empty());
+    assert_eq!(
+        info.channel_positions(),
+        &[spa_sys::SPA_AUDIO_CHANNEL_FL, spa_sys::SPA_AUDIO_CHANNEL_FR]
+    );
+
+    let video = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::Format)
+        .prop(FormatProperties::MediaType, MediaType::Video)
+        .prop(FormatProperties::MediaSubtype, MediaSubtype::Raw)
+        .build();
+    assert_eq!(
+        AudioInfoRaw::from_format_pod(&video),
+        Err(ParseError::WrongMediaType {
+            expected: (MediaType::Audio, MediaSubtype::Raw),
+            found: (MediaType::Video, MediaSubtype::Raw),
+        })
+    );
+
+    let props = ObjectBuilder::new(SpaTypes::ObjectParamProps, ParamType::Props)
+        .prop(spa_sys::SPA_PROP_volume, 1.0_f32)
+        .build();
+    assert_eq!(
+        AudioInfoRaw::from_format_pod(&props),
+        Err(ParseError::WrongObjectType {
+            expected: SpaTypes::ObjectParamFormat,
+            found: SpaTypes::ObjectParamProps,
+        })
+    );
+}
+
+// Negotiated `SPA_PARAM_Format` pods of an xdg-desktop-portal screen cast session,
+// once with shared memory buffers and once with DMA-BUF.
+const PORTAL_FORMAT_SHM: &[u8] = &[
+    0x98, 0x00, 0x00, 0x00, 0x0f, 0x00, 0x00, 0x00, 0x03, 0x00, 0x04, 0x00, 0x04, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00,
+    0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x04, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00,
+    0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x08, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x80, 0x07, 0x00, 0x00, 0x38, 0x04, 0x00, 0x00,
+    0x04, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x0b, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x05, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x08, 0x00, 0x00, 0x00, 0x0b, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+];
+
+const PORTAL_FORMAT_DMABUF: &[u8] = &[
+    0xb0, 0x00, 0x00, 0x00, 0x0f, 0x00, 0x00, 0x00, 0x03, 0x00, 0x04, 0x00, 0x04, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00,
+    0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x04, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00,
+    0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00, 0x08, 0x00, 0x00, 0x00,
+    0x08, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x03, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00,
+    0x80, 0x07, 0x00, 0x00, 0x38, 0x04, 0x00, 0x00, 0x04, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x08, 0x00, 0x00, 0x00, 0x0b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+    0x05, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x0b, 0x00, 0x00, 0x00,
+    0x3c, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+];
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn video_info_raw_from_format_pod() {
+    use libspa::param::video::{VideoFormat, VideoInfoRaw, VideoInterlaceMode};
+    use libspa::pod::Pod;
+
+    let shm = VideoInfoRaw::from_format_pod(Pod::from_bytes(PORTAL_FORMAT_SHM).unwrap()).unwrap();
+    assert_eq!(shm.format(), VideoFormat::BGRx);
+    assert_eq!(
+        shm.size(),
+        Rectangle {
+            width: 1920,
+            height: 1080
+        }
+    );
+    assert_eq!(shm.framerate(), Fraction { num: 0, denom: 1 });
+    assert_eq!(shm.max_framerate(), Fraction { num: 60, denom: 1 });
+    assert_eq!(shm.modifier(), 0);
+    assert!(shm.interlace_mode() == VideoInterlaceMode::Progressive);
+    assert_eq!(shm.multiview_mode(), 0);
+
+    let dmabuf =
+        VideoInfoRaw::from_format_pod(Pod::from_bytes(PORTAL_FORMAT_DMABUF).unwrap()).unwrap();
+    assert_eq!(dmabuf.modifier(), 0x0100_0000_0000_0002);
+    assert_eq!(dmabuf.format(), shm.format());
+    assert_eq!(dmabuf.size(), shm.size());
+    assert_eq!(dmabuf.max_framerate(), shm.max_framerate());
+
+    let audio = Pod::from_bytes(ENUM_FORMAT_AUDIO_RAW).unwrap();
+    assert!(VideoInfoRaw::from_format_pod(audio).is_err());
+}
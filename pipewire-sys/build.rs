@@ -1,8 +1,53 @@
 // Copyright The pipewire-rs Contributors.
 // SPDX-License-Identifier: MIT
 
+use std::collections::HashMap;
 use std::env;
-use std::path::PathBuf;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The keys added after the oldest supported version of libpipewire,
+/// with the cargo feature of the version adding them.
+///
+/// Keys which are not listed are available whenever the headers define them.
+const KEY_FEATURES: &[(&str, &str)] = &[
+    ("OBJECT_REGISTER", "v0_3_32"),
+    ("NODE_LINK_GROUP", "v0_3_32"),
+    ("AUDIO_RATE", "v0_3_32"),
+    ("NODE_LOCK_QUANTUM", "v0_3_33"),
+    ("NODE_RATE", "v0_3_33"),
+    ("NODE_LOCK_RATE", "v0_3_33"),
+    ("NODE_WANT_DRIVER", "v0_3_33"),
+    ("NODE_NETWORK", "v0_3_39"),
+    ("OBJECT_SERIAL", "v0_3_41"),
+    ("NODE_TRIGGER", "v0_3_41"),
+    ("AUDIO_ALLOWED_RATES", "v0_3_43"),
+    ("NODE_SUSPEND_ON_IDLE", "v0_3_44"),
+    ("NODE_TRANSPORT_SYNC", "v0_3_44"),
+    ("TARGET_OBJECT", "v0_3_44"),
+    ("NODE_FORCE_QUANTUM", "v0_3_45"),
+    ("NODE_FORCE_RATE", "v0_3_45"),
+    ("DEVICE_SYSFS_PATH", "v0_3_53"),
+    ("CONFIG_OVERRIDE_PREFIX", "v0_3_57"),
+    ("CONFIG_OVERRIDE_NAME", "v0_3_57"),
+    ("NODE_CHANNELNAMES", "v0_3_64"),
+    ("SEC_ENGINE", "v0_3_77"),
+];
+
+/// Collect the names of the `PW_KEY_*` string macros seen by bindgen,
+/// which are the keys defined with the current configuration of the headers.
+#[derive(Debug, Default)]
+struct KeyMacros(Arc<Mutex<Vec<String>>>);
+
+impl bindgen::callbacks::ParseCallbacks for KeyMacros {
+    fn str_macro(&self, name: &str, _value: &[u8]) {
+        if name.starts_with("PW_KEY_") {
+            self.0.lock().unwrap().push(name.to_owned());
+        }
+    }
+}
 
 fn main() {
     let libs = system_deps::Config::new()
@@ -13,11 +58,15 @@ fn main() {
     // Tell cargo to invalidate the built crate whenever the wrapper changes
     println!("cargo:rerun-if-changed=wrapper.h");
 
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let key_macros = Arc::default();
+
     let builder = bindgen::Builder::default()
         .header("wrapper.h")
         // Tell cargo to invalidate the built crate whenever any of the
         // included header files changed.
         .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
+        .parse_callbacks(Box::new(KeyMacros(Arc::clone(&key_macros))))
         .size_t_is_usize(true)
         .allowlist_function("pw_.*")
         .allowlist_type("pw_.*")
@@ -26,7 +75,25 @@ fn main() {
         .blocklist_function("spa_.*")
         .blocklist_type("spa_.*")
         .blocklist_item("spa_.*")
-        .raw_line("use spa_sys::*;");
+        .raw_line("use spa_sys::*;")
+        // Generate the enums as newtypes with associated constants, such as
+        // `pw_stream_state::PW_STREAM_STATE_STREAMING`. Unlike Rust enums they accept the
+        // values added by a newer libpipewire, and the flags can be combined.
+        .default_enum_style(bindgen::EnumVariation::NewType {
+            is_bitfield: false,
+            is_global: false,
+        })
+        .bitfield_enum("pw_.*_flags")
+        .derive_copy(true)
+        .derive_debug(true)
+        // Implement `Debug` manually for the types that can't derive it, such as those with unions.
+        .impl_debug(true)
+        // Create callable wrapper functions around the `static inline` functions of libpipewire,
+        // such as `pw_properties_parse_bool()`, so they can be called via FFI.
+        // The `spa_*` ones are already wrapped by libspa-sys.
+        .wrap_static_fns(true)
+        .wrap_static_fns_suffix("_pipewire_rs")
+        .wrap_static_fns_path(out_path.join("static_fns"));
 
     let builder = libpipewire
         .include_paths
@@ -39,8 +106,98 @@ fn main() {
     let bindings = builder.generate().expect("Unable to generate bindings");
 
     // Write the bindings to the $OUT_DIR/bindings.rs file.
-    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
     bindings
         .write_to_file(out_path.join("bindings.rs"))
         .expect("Couldn't write bindings!");
+
+    // Generate the constants of the `keys` module of the pipewire crate, which it includes
+    // from the path exposed as `DEP_PIPEWIRE_0.3_KEYS`.
+    let keys_h = libpipewire
+        .include_paths
+        .iter()
+        .map(|path| path.join("pipewire/keys.h"))
+        .find(|path| path.exists())
+        .expect("Cannot find pipewire/keys.h");
+    let keys_path = out_path.join("keys.rs");
+    fs::write(
+        &keys_path,
+        generate_keys(&keys_h, &key_macros.lock().unwrap()),
+    )
+    .expect("Couldn't write keys!");
+    println!("cargo:keys={}", keys_path.display());
+
+    // Compile the wrappers generated for the inline functions, using the same include paths
+    // as the bindings so they match the installed headers.
+    let mut cc = cc::Build::new();
+    cc.file(out_path.join("static_fns.c"));
+    cc.include(env!("CARGO_MANIFEST_DIR"));
+    cc.includes(&libpipewire.include_paths);
+    cc.compile("pipewire-rs-reexports");
+}
+
+/// Generate a `key_constant!` call for each of `macros`, documented with the comment of the key
+/// in `keys_h` and gated behind the feature of its version, if any.
+fn generate_keys(keys_h: &Path, macros: &[String]) -> String {
+    let header = fs::read_to_string(keys_h).expect("Couldn't read pipewire/keys.h");
+    let docs = parse_key_docs(&header);
+    let features: HashMap<_, _> = KEY_FEATURES.iter().copied().collect();
+
+    let mut keys = String::new();
+    for symbol in macros {
+        let name = symbol.trim_start_matches("PW_KEY_");
+        // like a `///` comment, the doc starts with a space
+        let doc = docs
+            .get(symbol.as_str())
+            .map(|doc| format!(" {doc}"))
+            .unwrap_or_default();
+        if let Some(feature) = features.get(name) {
+            writeln!(keys, "#[cfg(feature = {feature:?})]").unwrap();
+        }
+        writeln!(keys, "key_constant!({name}, {symbol}, #[doc = {doc:?}]);").unwrap();
+    }
+    keys
+}
+
+/// Extract the comment of each `#define PW_KEY_*` of `header`, either following the define
+/// as `/**< ... */` or preceding it as `/** ... */`.
+fn parse_key_docs(header: &str) -> HashMap<&str, String> {
+    let mut docs = HashMap::new();
+    let mut lines = header.lines().map(str::trim);
+    let mut previous_comment = None;
+
+    while let Some(line) = lines.next() {
+        if !line.starts_with("#define PW_KEY_") {
+            previous_comment = match line.strip_prefix("/**") {
+                Some(comment) => Some(read_comment(comment, &mut lines)),
+                None if line.is_empty() => previous_comment,
+                None => None,
+            };
+            continue;
+        }
+
+        let symbol = line.split_whitespace().nth(1).unwrap();
+        let doc = match line.find("/**<") {
+            Some(start) => Some(read_comment(&line[start + "/**<".len()..], &mut lines)),
+            None => previous_comment.take(),
+        };
+        if let Some(doc) = doc {
+            docs.insert(symbol, doc);
+        }
+        previous_comment = None;
+    }
+    docs
+}
+
+/// Read a comment starting with `first`, continued on the next `lines` until its end,
+/// with its whitespace and leading `*` collapsed into single spaces.
+fn read_comment<'a>(first: &'a str, lines: &mut impl Iterator<Item = &'a str>) -> String {
+    let mut comment = vec![first];
+    while !comment.last().unwrap().contains("*/") {
+        let Some(next) = lines.next() else { break };
+        comment.push(next.trim_start_matches('*'));
+    }
+
+    let comment = comment.join(" ");
+    let comment = comment.split("*/").next().unwrap();
+    comment.split_whitespace().collect::<Vec<_>>().join(" ")
 }
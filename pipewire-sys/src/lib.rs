@@ -24,4 +24,36 @@ mod tests {
             pw_deinit();
         }
     }
+
+    // The enums are passed by value to and from C, they must keep the layout of a C enum.
+    const _: () = {
+        use std::mem::{align_of, size_of};
+        use std::os::raw::c_uint;
+
+        assert!(size_of::<pw_stream_state>() == size_of::<c_uint>());
+        assert!(align_of::<pw_stream_state>() == align_of::<c_uint>());
+        assert!(size_of::<pw_stream_flags>() == size_of::<c_uint>());
+        assert!(align_of::<pw_stream_flags>() == align_of::<c_uint>());
+        assert!(size_of::<spa_direction>() == size_of::<c_uint>());
+        assert!(align_of::<spa_direction>() == align_of::<c_uint>());
+    };
+
+    #[test]
+    fn enum_values() {
+        let time: pw_time = unsafe { std::mem::zeroed() };
+        let copy = time;
+        assert_eq!(format!("{:?}", time), format!("{:?}", copy));
+
+        assert_eq!(pw_stream_state::PW_STREAM_STATE_STREAMING.0, 3);
+        // values unknown to the headers are representable
+        assert_ne!(pw_node_state(42), pw_node_state::PW_NODE_STATE_RUNNING);
+
+        let flags = pw_stream_flags::PW_STREAM_FLAG_AUTOCONNECT
+            | pw_stream_flags::PW_STREAM_FLAG_MAP_BUFFERS;
+        assert_eq!(
+            flags.0,
+            pw_stream_flags::PW_STREAM_FLAG_AUTOCONNECT.0
+                | pw_stream_flags::PW_STREAM_FLAG_MAP_BUFFERS.0
+        );
+    }
 }
@@ -3,4 +3,7 @@
 #include <pipewire/extensions/metadata.h>
 #include <pipewire/extensions/profiler.h>
 #include <pipewire/extensions/protocol-native.h>
+#if PW_CHECK_VERSION(0,3,77)
+#include <pipewire/extensions/security-context.h>
+#endif
 #include <pipewire/extensions/session-manager.h>
\ No newline at end of file
@@ -0,0 +1,11 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+use std::env;
+
+fn main() {
+    // The constants of the `keys` module are generated by pipewire-sys from the headers of
+    // libpipewire, see `generate_keys()` in its build script.
+    let keys = env::var("DEP_PIPEWIRE_0.3_KEYS").expect("pipewire-sys did not generate the keys");
+    println!("cargo:rustc-env=PIPEWIRE_RS_KEYS={keys}");
+}
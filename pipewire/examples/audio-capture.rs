@@ -7,28 +7,35 @@
 
 use clap::Parser;
 use pipewire as pw;
-use pw::{properties::properties, spa};
-use spa::param::format::{MediaSubtype, MediaType};
-use spa::param::format_utils;
-use spa::pod::Pod;
-#[cfg(feature = "v0_3_44")]
-use spa::WritableDict;
+use pw::{properties::properties, spa, stream::Target};
+use spa::param::audio::{AudioFormat, AudioInfoRaw};
+use spa::param::format::{FormatProperties, MediaSubtype, MediaType};
+use spa::param::{Latency, ParamType};
+use spa::pod::ObjectBuilder;
+use spa::utils::SpaTypes;
 use std::convert::TryInto;
 use std::mem;
 
 struct UserData {
-    format: spa::param::audio::AudioInfoRaw,
+    format: AudioInfoRaw,
     cursor_move: bool,
 }
 
 #[derive(Parser)]
 #[clap(name = "audio-capture", about = "Audio stream capture example")]
 struct Opt {
-    #[clap(short, long, help = "The target object id to connect to")]
+    #[clap(
+        short,
+        long,
+        help = "The serial or name of the target object to connect to"
+    )]
     target: Option<String>,
 }
 
 pub fn main() -> Result<(), pw::Error> {
+    #[cfg(feature = "v0_3_44")]
+    let opt = Opt::parse();
+
     pw::init();
 
     let mainloop = pw::main_loop::MainLoop::new(None)?;
@@ -46,31 +53,16 @@ pub fn main() -> Result<(), pw::Error> {
      * If you plan to autoconnect your stream, you need to provide at least
      * media, category and role properties.
      *
-     * Pass your events and a user_data pointer as the last arguments. This
-     * will inform you about the stream state. The most important event
-     * you need to listen to is the process event where you need to produce
-     * the data.
+     * The listener owns the user data, every callback receives it mutably
+     * as its second argument, even the events emitted from another callback
+     * which are delivered once it returns. The most important event you need
+     * to listen to is the process event where you need to consume the data.
      */
-    #[cfg(not(feature = "v0_3_44"))]
     let props = properties! {
         *pw::keys::MEDIA_TYPE => "Audio",
         *pw::keys::MEDIA_CATEGORY => "Capture",
         *pw::keys::MEDIA_ROLE => "Music",
     };
-    #[cfg(feature = "v0_3_44")]
-    let props = {
-        let opt = Opt::parse();
-
-        let mut props = properties! {
-            *pw::keys::MEDIA_TYPE => "Audio",
-            *pw::keys::MEDIA_CATEGORY => "Capture",
-            *pw::keys::MEDIA_ROLE => "Music",
-        };
-        if let Some(target) = opt.target {
-            props.insert(*pw::keys::TARGET_OBJECT, target);
-        }
-        props
-    };
 
     // uncomment if you want to capture from the sink monitor ports
     // props.insert(*pw::keys::STREAM_CAPTURE_SINK, "true");
@@ -79,30 +71,32 @@ pub fn main() -> Result<(), pw::Error> {
 
     let _listener = stream
         .add_local_listener_with_user_data(data)
-        .param_changed(|_, user_data, id, param| {
+        .param_changed(|stream, user_data, id, param| {
             // NULL means to clear the format
             let Some(param) = param else {
                 return;
             };
-            if id != pw::spa::param::ParamType::Format.as_raw() {
+            if id == ParamType::Latency.as_raw() {
+                let Ok(latency) = Latency::from_pod(param) else {
+                    return;
+                };
+                println!("latency changed: {:?}", latency);
+                // We do not add any latency of our own, so we just report the latency we got.
+                let latency = latency.to_pod();
+                if let Err(err) = stream.update_params(&[latency.as_pod()]) {
+                    eprintln!("failed to update latency: {err}");
+                }
                 return;
             }
-
-            let (media_type, media_subtype) = match format_utils::parse_format(param) {
-                Ok(v) => v,
-                Err(_) => return,
-            };
-
-            // only accept raw audio
-            if media_type != MediaType::Audio || media_subtype != MediaSubtype::Raw {
+            if id != ParamType::Format.as_raw() {
                 return;
             }
 
-            // call a helper function to parse the format for us.
-            user_data
-                .format
-                .parse(param)
-                .expect("Failed to parse param changed to AudioInfoRaw");
+            // only accept raw audio, and let the helper parse the format for us.
+            user_data.format = match AudioInfoRaw::from_format_pod(param) {
+                Ok(format) => format,
+                Err(_) => return,
+            };
 
             println!(
                 "capturing rate:{} channels:{}",
@@ -159,28 +153,31 @@ pub fn main() -> Result<(), pw::Error> {
      * id means that this is a format enumeration (of 1 value).
      * We leave the channels and rate empty to accept the native graph
      * rate and channels. */
-    let mut audio_info = spa::param::audio::AudioInfoRaw::new();
-    audio_info.set_format(spa::param::audio::AudioFormat::F32LE);
-    let obj = pw::spa::pod::Object {
-        type_: pw::spa::utils::SpaTypes::ObjectParamFormat.as_raw(),
-        id: pw::spa::param::ParamType::EnumFormat.as_raw(),
-        properties: audio_info.into(),
-    };
-    let values: Vec<u8> = pw::spa::pod::serialize::PodSerializer::serialize(
-        std::io::Cursor::new(Vec::new()),
-        &pw::spa::pod::Value::Object(obj),
-    )
-    .unwrap()
-    .0
-    .into_inner();
+    let format = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+        .prop(FormatProperties::MediaType, MediaType::Audio)
+        .prop(FormatProperties::MediaSubtype, MediaSubtype::Raw)
+        .prop(FormatProperties::AudioFormat, AudioFormat::F32LE)
+        .build();
 
-    let mut params = [Pod::from_bytes(&values).unwrap()];
+    let mut params = [format.as_pod()];
 
     /* Now connect this stream. We ask that our process function is
      * called in a realtime thread. */
-    stream.connect(
+    // Serials and names identify the target even if it is destroyed and its id reused.
+    #[cfg(feature = "v0_3_44")]
+    let target = match opt.target {
+        Some(target) => match target.parse() {
+            Ok(serial) => Target::Serial(serial),
+            Err(_) => Target::Name(target),
+        },
+        None => Target::Any,
+    };
+    #[cfg(not(feature = "v0_3_44"))]
+    let target = Target::Any;
+
+    stream.connect_target(
         spa::utils::Direction::Input,
-        None,
+        target,
         pw::stream::StreamFlags::AUTOCONNECT
             | pw::stream::StreamFlags::MAP_BUFFERS
             | pw::stream::StreamFlags::RT_PROCESS,
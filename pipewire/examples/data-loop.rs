@@ -0,0 +1,59 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Run a timer on a [`DataLoop`](pw::data_loop::DataLoop), in its own thread,
+//! while the main loop runs in the main thread and stops it after a second.
+
+use pipewire as pw;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+fn main() -> Result<(), pw::Error> {
+    pw::init();
+
+    let mainloop = pw::main_loop::MainLoop::new(None)?;
+    // Safety: the only source of the loop is the timer below, which only uses an `Arc` of an atomic,
+    // and it is added before the loop is started.
+    let data_loop = unsafe { pw::data_loop::DataLoop::new(None)? };
+    let ticks = Arc::new(AtomicU64::new(0));
+
+    // The loop has no lock: add the timer before starting it.
+    let tick_timer = data_loop.loop_().add_timer({
+        let ticks = ticks.clone();
+        move |expirations| {
+            let count = ticks.fetch_add(expirations, Ordering::Relaxed) + expirations;
+            println!("tick {count} on {:?}", std::thread::current().id());
+        }
+    })?;
+    tick_timer
+        .update_timer(
+            Some(Duration::from_millis(100)),
+            Some(Duration::from_millis(100)),
+        )
+        .into_sync_result()?;
+
+    println!("main loop on {:?}", std::thread::current().id());
+    data_loop.start()?;
+
+    let quit_timer = mainloop.loop_().add_timer({
+        let mainloop = mainloop.clone();
+        move |_| mainloop.quit()
+    })?;
+    quit_timer
+        .update_timer(Some(Duration::from_secs(1)), None)
+        .into_sync_result()?;
+
+    mainloop.run();
+    data_loop.stop()?;
+
+    // Once stopped, the loop can be used from this thread again.
+    data_loop.invoke(|| println!("invoked after stop"))?;
+    println!("{} ticks", ticks.load(Ordering::Relaxed));
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Print the default audio sink whenever it changes, until interrupted with Ctrl+C.
+
+use pipewire as pw;
+use pw::{
+    loop_::Signal,
+    metadata::{DefaultKind, DefaultsTracker},
+};
+
+fn main() -> Result<(), pw::Error> {
+    pw::init();
+
+    let mainloop = pw::main_loop::MainLoop::new(None)?;
+    let context = pw::context::Context::new(&mainloop)?;
+    let core = context.connect(None)?;
+
+    let _tracker = DefaultsTracker::new(&core, |kind, target| {
+        if kind != DefaultKind::AudioSink {
+            return;
+        }
+        match target {
+            Some(target) => match target.node_serial {
+                Some(serial) => println!("Default sink: {} (serial {serial})", target.name),
+                None => println!("Default sink: {} (no such node)", target.name),
+            },
+            None => println!("No default sink"),
+        }
+    })?;
+
+    let _sig = mainloop.loop_().add_signal_local(Signal::SIGINT, {
+        let mainloop = mainloop.clone();
+        move || mainloop.quit()
+    })?;
+    mainloop.run();
+
+    Ok(())
+}
@@ -0,0 +1,172 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Dump the objects of the graph as JSON on stdout, like a minimal `pw-dump`.
+//!
+//! Each global is printed with its info, and for nodes, ports and devices, with its params.
+//! This requires the `serde` feature: `cargo run --features serde --example dump`.
+
+use pipewire as pw;
+use pw::{
+    client::Client,
+    device::Device,
+    link::Link,
+    node::Node,
+    port::Port,
+    proxy::{Listener, ProxyT},
+    registry::{GlobalObject, Registry},
+    spa::{param::ParamType, pod::Pod, utils::dict::DictRef},
+    types::ObjectType,
+};
+use serde_json::Value;
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    rc::Rc,
+};
+
+/// The JSON of the globals by id, with their `info` and `params` once they are received.
+type Objects = Rc<RefCell<BTreeMap<u32, Value>>>;
+
+fn main() -> Result<(), pw::Error> {
+    pw::init();
+
+    let mainloop = pw::main_loop::MainLoop::new(None)?;
+    let context = pw::context::Context::new(&mainloop)?;
+    let core = context.connect(None)?;
+    let registry = Rc::new(core.get_registry()?);
+    let registry_weak = Rc::downgrade(&registry);
+
+    let objects = Objects::default();
+    // Proxies and their listeners need to stay alive to receive the infos and params.
+    let proxies: Rc<RefCell<Vec<(Box<dyn ProxyT>, Box<dyn Listener>)>>> = Rc::default();
+
+    let _registry_listener = registry
+        .add_listener_local()
+        .global({
+            let objects = objects.clone();
+            let proxies = proxies.clone();
+            move |global| {
+                let mut object = serde_json::to_value(global).unwrap();
+                object["info"] = Value::Null;
+                object["params"] = Value::Array(Vec::new());
+                objects.borrow_mut().insert(global.id, object);
+
+                let Some(registry) = registry_weak.upgrade() else {
+                    return;
+                };
+                if let Some(proxy) = bind(&registry, global, &objects) {
+                    proxies.borrow_mut().push(proxy);
+                }
+            }
+        })
+        .global_remove({
+            let objects = objects.clone();
+            move |id| {
+                objects.borrow_mut().remove(&id);
+            }
+        })
+        .register();
+
+    // The first roundtrip announces the globals, the second one their infos and params.
+    for _ in 0..2 {
+        let done = Rc::new(Cell::new(false));
+        let pending = core.sync(0)?;
+        let _core_listener = core
+            .add_listener_local()
+            .done({
+                let done = done.clone();
+                move |id, seq| {
+                    if id == pw::core::PW_ID_CORE && seq == pending {
+                        done.set(true);
+                    }
+                }
+            })
+            .register();
+        mainloop.run_until(|_| done.get());
+    }
+
+    let objects: Vec<_> = objects.take().into_values().collect();
+    println!("{}", serde_json::to_string_pretty(&objects).unwrap());
+
+    Ok(())
+}
+
+/// Bind the objects with an info, storing their info and params in `objects`.
+fn bind(
+    registry: &Registry,
+    global: &GlobalObject<&DictRef>,
+    objects: &Objects,
+) -> Option<(Box<dyn ProxyT>, Box<dyn Listener>)> {
+    let id = global.id;
+    let set_info = {
+        let objects = objects.clone();
+        move |info: Value| {
+            if let Some(object) = objects.borrow_mut().get_mut(&id) {
+                object["info"] = info;
+            }
+        }
+    };
+    let add_param = {
+        let objects = objects.clone();
+        move |_: i32, _: ParamType, _: u32, _: u32, param: Option<&Pod>| {
+            let mut objects = objects.borrow_mut();
+            if let (Some(Value::Array(params)), Some(param)) = (
+                objects.get_mut(&id).map(|object| &mut object["params"]),
+                param,
+            ) {
+                params.push(serde_json::to_value(param).unwrap());
+            }
+        }
+    };
+
+    match global.type_ {
+        ObjectType::Node => {
+            let node: Node = registry.bind(global).ok()?;
+            let listener = node
+                .add_listener_local()
+                .info(move |info| set_info(serde_json::to_value(info).unwrap()))
+                .param(add_param)
+                .register();
+            node.enum_params(0, None, 0, u32::MAX, None).ok()?;
+            Some((Box::new(node), Box::new(listener)))
+        }
+        ObjectType::Port => {
+            let port: Port = registry.bind(global).ok()?;
+            let listener = port
+                .add_listener_local()
+                .info(move |info| set_info(serde_json::to_value(info).unwrap()))
+                .param(add_param)
+                .register();
+            port.enum_params(0, None, 0, u32::MAX, None).ok()?;
+            Some((Box::new(port), Box::new(listener)))
+        }
+        ObjectType::Device => {
+            let device: Device = registry.bind(global).ok()?;
+            let listener = device
+                .add_listener_local()
+                .info(move |info| set_info(serde_json::to_value(info).unwrap()))
+                .param(add_param)
+                .register();
+            device.enum_params(0, None, 0, u32::MAX).ok()?;
+            Some((Box::new(device), Box::new(listener)))
+        }
+        ObjectType::Link => {
+            let link: Link = registry.bind(global).ok()?;
+            let listener = link
+                .add_listener_local()
+                .info(move |info| set_info(serde_json::to_value(info).unwrap()))
+                .register();
+            Some((Box::new(link), Box::new(listener)))
+        }
+        ObjectType::Client => {
+            let client: Client = registry.bind(global).ok()?;
+            let listener = client
+                .add_listener_local()
+                .info(move |info| set_info(serde_json::to_value(info).unwrap()))
+                .register();
+            Some((Box::new(client), Box::new(listener)))
+        }
+        _ => None,
+    }
+}
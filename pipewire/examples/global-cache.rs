@@ -0,0 +1,85 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Keep a cache of the globals announced by the registry, updated as globals are added and removed.
+//!
+//! The cache is printed each time it changes, with the name of the globals when they have one.
+
+use pipewire as pw;
+use pw::{
+    properties::Properties,
+    registry::{GlobalObject, Listener, Registry},
+};
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+/// The globals currently known to the registry, by id.
+#[derive(Default)]
+struct GlobalCache {
+    globals: BTreeMap<u32, GlobalObject<Properties>>,
+}
+
+impl GlobalCache {
+    /// Keep `cache` up to date with the globals of `registry`, for as long as the listener is alive.
+    fn listen(cache: &Rc<RefCell<Self>>, registry: &Registry) -> Listener {
+        registry
+            .add_listener_local()
+            .global_owned({
+                let cache = cache.clone();
+                move |global| {
+                    let mut cache = cache.borrow_mut();
+                    println!("added: {}", describe(&global));
+                    // ids are reused once their object is destroyed, serials are not
+                    if let Some(old) = cache.globals.insert(global.id, global) {
+                        println!("replaced: {}, serial {:?}", describe(&old), old.serial());
+                    }
+                    cache.print();
+                }
+            })
+            .global_remove({
+                let cache = cache.clone();
+                move |id| {
+                    let mut cache = cache.borrow_mut();
+                    if let Some(global) = cache.globals.remove(&id) {
+                        println!("removed: {}", describe(&global));
+                        cache.print();
+                    }
+                }
+            })
+            .register()
+    }
+
+    fn print(&self) {
+        println!("{} globals", self.globals.len());
+        for global in self.globals.values() {
+            println!("  {}", describe(global));
+        }
+    }
+}
+
+fn describe(global: &GlobalObject<Properties>) -> String {
+    let name = global.props.as_ref().and_then(|props| {
+        ["node.name", "device.name", "client.name", "module.name"]
+            .into_iter()
+            .find_map(|key| props.get(key))
+    });
+    match name {
+        Some(name) => format!("{} {} ({})", global.id, global.type_, name),
+        None => format!("{} {}", global.id, global.type_),
+    }
+}
+
+fn main() {
+    pw::init();
+
+    let mainloop = pw::main_loop::MainLoop::new(None).expect("Failed to create main loop");
+    let context = pw::context::Context::new(&mainloop).expect("Failed to create context");
+    let core = context.connect(None).expect("Failed to connect to core");
+    let registry = core.get_registry().expect("Failed to get Registry");
+
+    let cache = Rc::new(RefCell::new(GlobalCache::default()));
+    let _listener = GlobalCache::listen(&cache, &registry);
+
+    mainloop.run();
+
+    unsafe { pw::deinit() };
+}
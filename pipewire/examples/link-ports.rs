@@ -0,0 +1,93 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Link an output port to an input port, given by name like `pw-link` does.
+//!
+//! For example, `link-ports my-source:output_FL my-sink:playback_FL` links the `output_FL` port
+//! of the node named `my-source` to the `playback_FL` port of the node named `my-sink`.
+
+use clap::Parser;
+use pipewire as pw;
+use pw::{
+    keys, link::LinkBuilder, loop_::Signal, properties::Properties, registry::GlobalObject,
+    types::ObjectType,
+};
+
+#[derive(Parser)]
+#[clap(name = "link-ports", about = "Link an output port to an input port")]
+struct Opt {
+    #[clap(help = "The output port, as <node name>:<port name>")]
+    output: String,
+    #[clap(help = "The input port, as <node name>:<port name>")]
+    input: String,
+    #[clap(short, long, help = "Keep the link once the program exits")]
+    linger: bool,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::parse();
+
+    pw::init();
+
+    let mainloop = pw::main_loop::MainLoop::new(None)?;
+    let context = pw::context::Context::new(&mainloop)?;
+    let core = context.connect(None)?;
+    let registry = core.get_registry()?;
+    let globals = registry.snapshot(&core, &mainloop)?;
+
+    let (output_node, output_port) = find_port(&globals, &opt.output, "out")?;
+    let (input_node, input_port) = find_port(&globals, &opt.input, "in")?;
+
+    let link = LinkBuilder::new(output_node, output_port, input_node, input_port)
+        .linger(opt.linger)
+        .create(&core)?;
+    let id = link.wait(&mainloop)?;
+    println!("Created link {id}");
+
+    if !opt.linger {
+        println!("Press Ctrl+C to remove the link");
+        let _sig = mainloop.loop_().add_signal_local(Signal::SIGINT, {
+            let mainloop = mainloop.clone();
+            move || mainloop.quit()
+        })?;
+        mainloop.run();
+    }
+
+    Ok(())
+}
+
+/// Find the ids of the node and of the port named `name`, as `<node name>:<port name>`,
+/// with the `direction` "in" or "out".
+fn find_port(
+    globals: &[GlobalObject<Properties>],
+    name: &str,
+    direction: &str,
+) -> Result<(u32, u32), String> {
+    let (node_name, port_name) = name
+        .split_once(':')
+        .ok_or_else(|| format!("{name} is not <node name>:<port name>"))?;
+
+    let node = globals
+        .iter()
+        .find(|global| {
+            global.type_ == ObjectType::Node && prop(global, *keys::NODE_NAME) == Some(node_name)
+        })
+        .ok_or_else(|| format!("no node named {node_name}"))?;
+    let node_id = node.id.to_string();
+
+    let port = globals
+        .iter()
+        .find(|global| {
+            global.type_ == ObjectType::Port
+                && prop(global, *keys::NODE_ID) == Some(node_id.as_str())
+                && prop(global, *keys::PORT_NAME) == Some(port_name)
+                && prop(global, *keys::PORT_DIRECTION) == Some(direction)
+        })
+        .ok_or_else(|| format!("no {direction}put port named {port_name} on {node_name}"))?;
+
+    Ok((node.id, port.id))
+}
+
+fn prop<'a>(global: &'a GlobalObject<Properties>, key: &str) -> Option<&'a str> {
+    global.props.as_ref().and_then(|props| props.get(key))
+}
@@ -0,0 +1,87 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Bind every factory announced by the registry and print its info.
+//!
+//! This can be used to check whether a factory such as `link-factory` or
+//! `support.null-audio-sink` is available before calling `create_object`.
+
+use pipewire as pw;
+use pw::{factory::Factory, factory::FactoryListener, types::ObjectType};
+use std::{cell::Cell, cell::RefCell, rc::Rc};
+
+fn main() {
+    pw::init();
+
+    list_factories();
+
+    unsafe { pw::deinit() };
+}
+
+fn list_factories() {
+    let mainloop = pw::main_loop::MainLoop::new(None).expect("Failed to create main loop");
+    let context = pw::context::Context::new(&mainloop).expect("Failed to create context");
+    let core = context.connect(None).expect("Failed to connect to core");
+    let registry = Rc::new(core.get_registry().expect("Failed to get Registry"));
+    let registry_weak = Rc::downgrade(&registry);
+
+    // Factories and their listeners need to stay alive until the info event arrived.
+    let factories: Rc<RefCell<Vec<(Factory, FactoryListener)>>> = Rc::new(RefCell::new(Vec::new()));
+    let factories_clone = factories.clone();
+
+    let _listener_reg = registry
+        .add_listener_local()
+        .global(move |global| {
+            if global.type_ != ObjectType::Factory {
+                return;
+            }
+
+            if let Some(registry) = registry_weak.upgrade() {
+                let factory: Factory = registry.bind(global).expect("Failed to bind factory");
+                let listener = factory
+                    .add_listener_local()
+                    .info(|info| {
+                        println!(
+                            "factory: id:{} name:{} type:{}/{}",
+                            info.id(),
+                            info.name().unwrap_or_default(),
+                            info.type_(),
+                            info.version()
+                        );
+                    })
+                    .register();
+
+                factories_clone.borrow_mut().push((factory, listener));
+            }
+        })
+        .register();
+
+    // The first roundtrip makes sure all globals were announced and bound,
+    // the second one that the info events of the bound factories arrived.
+    do_roundtrip(&mainloop, &core);
+    do_roundtrip(&mainloop, &core);
+}
+
+/// Do a single roundtrip to process all events.
+/// See the example in roundtrip.rs for more details on this.
+fn do_roundtrip(mainloop: &pw::main_loop::MainLoop, core: &pw::core::Core) {
+    let done = Rc::new(Cell::new(false));
+    let done_clone = done.clone();
+    let loop_clone = mainloop.clone();
+
+    let pending = core.sync(0).expect("sync failed");
+
+    let _listener_core = core
+        .add_listener_local()
+        .done(move |id, seq| {
+            if id == pw::core::PW_ID_CORE && seq == pending {
+                done_clone.set(true);
+                loop_clone.quit();
+            }
+        })
+        .register();
+
+    while !done.get() {
+        mainloop.run();
+    }
+}
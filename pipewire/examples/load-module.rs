@@ -0,0 +1,60 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Load `libpipewire-module-loopback` into our own context and wait for the node
+//! it creates to show up in the registry.
+
+use pipewire as pw;
+use pw::types::ObjectType;
+
+const NODE_NAME: &str = "pipewire-rs.loopback.capture";
+
+fn main() {
+    pw::init();
+
+    load_module();
+
+    unsafe { pw::deinit() };
+}
+
+fn load_module() {
+    let mainloop = pw::main_loop::MainLoop::new(None).expect("Failed to create main loop");
+    let context = pw::context::Context::new(&mainloop).expect("Failed to create context");
+    let core = context.connect(None).expect("Failed to connect to core");
+    let registry = core.get_registry().expect("Failed to get Registry");
+
+    let mainloop_clone = mainloop.clone();
+    let _listener_reg = registry
+        .add_listener_local()
+        .global(move |global| {
+            if global.type_ != ObjectType::Node {
+                return;
+            }
+
+            if let Some(props) = global.props {
+                if props.get(*pw::keys::NODE_NAME) == Some(NODE_NAME) {
+                    println!("loopback node appeared: id:{}", global.id);
+                    mainloop_clone.quit();
+                }
+            }
+        })
+        .register();
+
+    // The module creates its streams on the core we are connected with,
+    // so it has to be loaded after connecting.
+    let module = context
+        .load_module(
+            "libpipewire-module-loopback",
+            Some(&format!(
+                "{{ node.description = \"pipewire-rs loopback\" capture.props = {{ node.name = \"{NODE_NAME}\" }} }}"
+            )),
+            None,
+        )
+        .expect("Failed to load loopback module");
+    println!("loaded module: {:?}", module);
+
+    mainloop.run();
+
+    // Dropping the module unloads it again, which removes the loopback node.
+    drop(module);
+}
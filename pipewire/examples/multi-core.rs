@@ -0,0 +1,105 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Connect to several PipeWire sockets from a single main loop, and print the number of
+//! clients seen through each of them.
+//!
+//! The regular socket and the manager socket are used by default, other socket names or paths
+//! can be given as arguments.
+
+use std::{cell::Cell, rc::Rc};
+
+use pipewire::{
+    context::{Context, Remote},
+    core::{self, Core, PW_ID_CORE},
+    main_loop::MainLoop,
+    registry::{self, Registry},
+    types::ObjectType,
+};
+
+/// A connection to one socket, counting the clients announced by its registry.
+struct Connection {
+    name: String,
+    clients: Rc<Cell<usize>>,
+    done: Rc<Cell<bool>>,
+    // dropped in order, the core last
+    _listeners: (registry::Listener, core::Listener),
+    _registry: Registry,
+    _core: Core,
+}
+
+impl Connection {
+    fn new(context: &Context, name: &str) -> Result<Self, pipewire::Error> {
+        let core = context.connect_to(Remote::Name(name), None)?;
+        let registry = core.get_registry()?;
+
+        let clients = Rc::new(Cell::new(0));
+        let registry_listener = registry
+            .add_listener_local()
+            .global({
+                let clients = clients.clone();
+                move |global| {
+                    if global.type_ == ObjectType::Client {
+                        clients.set(clients.get() + 1);
+                    }
+                }
+            })
+            .register();
+
+        // all the globals have been announced once the server answered the sync
+        let pending = core.sync(0)?;
+        let done = Rc::new(Cell::new(false));
+        let core_listener = core
+            .add_listener_local()
+            .done({
+                let done = done.clone();
+                move |id, seq| {
+                    if id == PW_ID_CORE && seq == pending {
+                        done.set(true);
+                    }
+                }
+            })
+            .register();
+
+        Ok(Self {
+            name: name.to_owned(),
+            _core: core,
+            _registry: registry,
+            _listeners: (registry_listener, core_listener),
+            clients,
+            done,
+        })
+    }
+}
+
+fn main() -> Result<(), pipewire::Error> {
+    pipewire::init();
+
+    let mut names: Vec<String> = std::env::args().skip(1).collect();
+    if names.is_empty() {
+        names = vec!["pipewire-0".to_owned(), "pipewire-0-manager".to_owned()];
+    }
+
+    let mainloop = MainLoop::new(None)?;
+    let context = Context::new(&mainloop)?;
+
+    let connections: Vec<Connection> = names
+        .iter()
+        .filter_map(|name| match Connection::new(&context, name) {
+            Ok(connection) => Some(connection),
+            Err(err) => {
+                eprintln!("{name}: failed to connect: {err}");
+                None
+            }
+        })
+        .collect();
+
+    // the cores are all dispatched by the same loop
+    mainloop.run_until(|_| connections.iter().all(|c| c.done.get()));
+
+    for connection in &connections {
+        println!("{}: {} clients", connection.name, connection.clients.get());
+    }
+
+    Ok(())
+}
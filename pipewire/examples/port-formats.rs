@@ -0,0 +1,76 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! List the output ports which can feed each input port, like a patchbay would before offering
+//! to link them.
+//!
+//! Two ports can be linked if they have at least one format in common, which is found by
+//! intersecting the `EnumFormat` params of both ports.
+
+use pipewire as pw;
+use pw::{
+    keys,
+    port::{formats_compatible, Port},
+    properties::Properties,
+    registry::GlobalObject,
+    spa::pod::OwnedPod,
+    types::ObjectType,
+};
+
+struct PortFormats {
+    name: String,
+    direction: String,
+    formats: Vec<OwnedPod>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    pw::init();
+
+    let mainloop = pw::main_loop::MainLoop::new(None)?;
+    let context = pw::context::Context::new(&mainloop)?;
+    let core = context.connect(None)?;
+    let registry = core.get_registry()?;
+    let globals = registry.snapshot(&core, &mainloop)?;
+
+    let mut ports = Vec::new();
+    for global in globals.iter().filter(|g| g.type_ == ObjectType::Port) {
+        let port: Port = registry.bind(global)?;
+        ports.push(PortFormats {
+            name: port_name(&globals, global),
+            direction: prop(global, *keys::PORT_DIRECTION)
+                .unwrap_or_default()
+                .to_string(),
+            formats: port.formats(&core, &mainloop)?,
+        });
+    }
+
+    for input in ports.iter().filter(|port| port.direction == "in") {
+        println!("{} ({} formats)", input.name, input.formats.len());
+        for output in ports.iter().filter(|port| port.direction == "out") {
+            if formats_compatible(&output.formats, &input.formats) {
+                println!("    <- {}", output.name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The name of `port` as `<node name>:<port name>`.
+fn port_name(globals: &[GlobalObject<Properties>], port: &GlobalObject<Properties>) -> String {
+    let node_name = prop(port, *keys::NODE_ID).and_then(|node_id| {
+        globals
+            .iter()
+            .find(|global| global.type_ == ObjectType::Node && global.id.to_string() == node_id)
+            .and_then(|node| prop(node, *keys::NODE_NAME))
+    });
+    format!(
+        "{}:{}",
+        node_name.unwrap_or("?"),
+        prop(port, *keys::PORT_NAME).unwrap_or("?")
+    )
+}
+
+fn prop<'a>(global: &'a GlobalObject<Properties>, key: &str) -> Option<&'a str> {
+    global.props.as_ref().and_then(|props| props.get(key))
+}
@@ -0,0 +1,168 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! List the profiles of a device, or switch it to another profile.
+//!
+//! For example, `profile-switch 42 pro-audio` switches the sound card with id 42
+//! to its "Pro Audio" profile.
+
+use clap::Parser;
+use pipewire as pw;
+use pw::{
+    device::{Device, DeviceListener},
+    spa::param::{profile::Profile, ParamType},
+    types::ObjectType,
+};
+use std::{cell::Cell, cell::RefCell, rc::Rc};
+
+#[derive(Parser)]
+#[clap(
+    name = "profile-switch",
+    about = "List or switch the profiles of a device"
+)]
+struct Opt {
+    #[clap(help = "The id of the device")]
+    device: u32,
+    #[clap(help = "The name of the profile to switch to, list the profiles if missing")]
+    profile: Option<String>,
+}
+
+fn main() {
+    let opt = Opt::parse();
+
+    pw::init();
+
+    profile_switch(opt);
+
+    unsafe { pw::deinit() };
+}
+
+fn profile_switch(opt: Opt) {
+    let mainloop = pw::main_loop::MainLoop::new(None).expect("Failed to create main loop");
+    let context = pw::context::Context::new(&mainloop).expect("Failed to create context");
+    let core = context.connect(None).expect("Failed to connect to core");
+    let registry = Rc::new(core.get_registry().expect("Failed to get Registry"));
+    let registry_weak = Rc::downgrade(&registry);
+
+    // The device and its listener need to stay alive until its params arrived.
+    let device: Rc<RefCell<Option<(Device, DeviceListener)>>> = Rc::new(RefCell::new(None));
+    let device_clone = device.clone();
+    // The available profiles, and the index of the active one.
+    let profiles: Rc<RefCell<Vec<Profile>>> = Rc::new(RefCell::new(Vec::new()));
+    let profiles_clone = profiles.clone();
+    let active = Rc::new(Cell::new(None));
+    let active_clone = active.clone();
+
+    let device_id = opt.device;
+    let _listener_reg = registry
+        .add_listener_local()
+        .global(move |global| {
+            if global.type_ != ObjectType::Device || global.id != device_id {
+                return;
+            }
+
+            if let Some(registry) = registry_weak.upgrade() {
+                let bound: Device = registry.bind(global).expect("Failed to bind device");
+                let profiles = profiles_clone.clone();
+                let active = active_clone.clone();
+                let listener = bound
+                    .add_listener_local()
+                    .param(move |_seq, id, _index, _next, param| {
+                        let Some(param) = param else { return };
+                        let profile = match Profile::from_pod(param) {
+                            Ok(profile) => profile,
+                            Err(err) => {
+                                eprintln!("Invalid profile: {err}");
+                                return;
+                            }
+                        };
+
+                        if id == ParamType::EnumProfile {
+                            profiles.borrow_mut().push(profile);
+                        } else if id == ParamType::Profile {
+                            active.set(Some(profile.index));
+                        }
+                    })
+                    .register();
+
+                for id in [ParamType::EnumProfile, ParamType::Profile] {
+                    bound
+                        .enum_params(0, Some(id), 0, u32::MAX)
+                        .expect("Failed to enumerate profiles");
+                }
+
+                *device_clone.borrow_mut() = Some((bound, listener));
+            }
+        })
+        .register();
+
+    // The first roundtrip makes sure the device was announced and bound,
+    // the second one that its profiles arrived.
+    do_roundtrip(&mainloop, &core);
+    do_roundtrip(&mainloop, &core);
+
+    let device = device.borrow();
+    let Some((device, _)) = device.as_ref() else {
+        eprintln!("No device with id {device_id}");
+        return;
+    };
+    let profiles = profiles.take();
+
+    let Some(name) = opt.profile else {
+        for profile in &profiles {
+            println!(
+                "{} {}: {} ({:?}, priority {})",
+                if active.get() == Some(profile.index) {
+                    '*'
+                } else {
+                    ' '
+                },
+                profile.name,
+                profile.description.as_deref().unwrap_or_default(),
+                profile.available,
+                profile.priority
+            );
+        }
+        return;
+    };
+
+    let Some(profile) = profiles.iter().find(|profile| profile.name == name) else {
+        eprintln!("No profile named {name}");
+        return;
+    };
+
+    device
+        .set_param(ParamType::Profile, 0, &Profile::to_pod(profile.index, true))
+        .expect("Failed to set profile");
+    println!(
+        "Switched to {}",
+        profile.description.as_deref().unwrap_or(&profile.name)
+    );
+
+    // Make sure the new profile was sent to the server before exiting.
+    do_roundtrip(&mainloop, &core);
+}
+
+/// Do a single roundtrip to process all events.
+/// See the example in roundtrip.rs for more details on this.
+fn do_roundtrip(mainloop: &pw::main_loop::MainLoop, core: &pw::core::Core) {
+    let done = Rc::new(Cell::new(false));
+    let done_clone = done.clone();
+    let loop_clone = mainloop.clone();
+
+    let pending = core.sync(0).expect("sync failed");
+
+    let _listener_core = core
+        .add_listener_local()
+        .done(move |id, seq| {
+            if id == pw::core::PW_ID_CORE && seq == pending {
+                done_clone.set(true);
+                loop_clone.quit();
+            }
+        })
+        .register();
+
+    while !done.get() {
+        mainloop.run();
+    }
+}
@@ -0,0 +1,78 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Print the timings of each cycle of the drivers of the graph, like a minimal `pw-top`.
+//!
+//! This requires `libpipewire-module-profiler` to be loaded in the server, which it is by default.
+
+use pipewire as pw;
+use pw::{
+    profiler::{Profiler, ProfilerListener},
+    spa::param::profiler::ProfilerData,
+    types::ObjectType,
+};
+use std::{cell::RefCell, rc::Rc};
+
+fn main() -> Result<(), pw::Error> {
+    pw::init();
+
+    let mainloop = pw::main_loop::MainLoop::new(None)?;
+    let context = pw::context::Context::new(&mainloop)?;
+    let core = context.connect(None)?;
+    let registry = Rc::new(core.get_registry()?);
+    let registry_weak = Rc::downgrade(&registry);
+
+    // The profiler and its listener need to stay alive to receive events.
+    let profiler: Rc<RefCell<Option<(Profiler, ProfilerListener)>>> = Rc::new(RefCell::new(None));
+    let profiler_clone = profiler.clone();
+
+    let _listener = registry
+        .add_listener_local()
+        .global(move |global| {
+            if global.type_ != ObjectType::Profiler || profiler_clone.borrow().is_some() {
+                return;
+            }
+            let Some(registry) = registry_weak.upgrade() else {
+                return;
+            };
+
+            let bound: Profiler = registry.bind(global).expect("Failed to bind profiler");
+            let listener = bound
+                .add_listener_local()
+                .profile(|pod| match ProfilerData::from_pod(pod) {
+                    Ok(data) => data.iter().for_each(print_cycle),
+                    Err(err) => eprintln!("Invalid profile: {err}"),
+                })
+                .register();
+            *profiler_clone.borrow_mut() = Some((bound, listener));
+        })
+        .register();
+
+    mainloop.run();
+
+    Ok(())
+}
+
+fn print_cycle(data: &ProfilerData) {
+    let (Some(clock), Some(driver)) = (&data.clock, &data.driver) else {
+        return;
+    };
+
+    println!(
+        "{:>5} {:<40} quantum {:>5} rate {:>6} busy {:>8}ns",
+        driver.id,
+        driver.name,
+        clock.duration,
+        clock.rate.denom,
+        driver.finish - driver.signal
+    );
+    for follower in &data.followers {
+        println!(
+            "{:>5}  + {:<37} wait {:>8}ns busy {:>8}ns",
+            follower.id,
+            follower.name,
+            follower.awake - follower.signal,
+            follower.finish - follower.awake
+        );
+    }
+}
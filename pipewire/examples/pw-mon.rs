@@ -12,6 +12,7 @@ use pw::{
     link::Link,
     loop_::Signal,
     metadata::Metadata,
+    module::Module,
     node::Node,
     port::Port,
     properties::properties,
@@ -59,11 +60,13 @@ fn monitor(remote: Option<String>) -> Result<()> {
     let main_loop = pw::main_loop::MainLoop::new(None)?;
 
     let main_loop_weak = main_loop.downgrade();
-    let _sig_int = main_loop.loop_().add_signal_local(Signal::SIGINT, move || {
-        if let Some(main_loop) = main_loop_weak.upgrade() {
-            main_loop.quit();
-        }
-    });
+    let _sig_int = main_loop
+        .loop_()
+        .add_signal_local(Signal::SIGINT, move || {
+            if let Some(main_loop) = main_loop_weak.upgrade() {
+                main_loop.quit();
+            }
+        })?;
     let main_loop_weak = main_loop.downgrade();
     let _sig_term = main_loop
         .loop_()
@@ -71,7 +74,7 @@ fn monitor(remote: Option<String>) -> Result<()> {
             if let Some(main_loop) = main_loop_weak.upgrade() {
                 main_loop.quit();
             }
-        });
+        })?;
 
     let context = pw::context::Context::new(&main_loop)?;
     let props = remote.map(|remote| {
@@ -90,10 +93,10 @@ fn monitor(remote: Option<String>) -> Result<()> {
         .done(|_id, _seq| {
             // TODO
         })
-        .error(move |id, seq, res, message| {
-            eprintln!("error id:{} seq:{} res:{}: {}", id, seq, res, message);
+        .core_error(move |error| {
+            eprintln!("error seq:{}: {}", error.seq, error);
 
-            if id == 0 {
+            if error.id == pw::core::PW_ID_CORE {
                 if let Some(main_loop) = main_loop_weak.upgrade() {
                     main_loop.quit();
                 }
@@ -164,10 +167,18 @@ fn monitor(remote: Option<String>) -> Result<()> {
 
                         Some((Box::new(metadata), Box::new(obj_listener)))
                     }
-                    ObjectType::Module
-                    | ObjectType::Device
-                    | ObjectType::Factory
-                    | ObjectType::Client => {
+                    ObjectType::Module => {
+                        let module: Module = registry.bind(obj).unwrap();
+                        let obj_listener = module
+                            .add_listener_local()
+                            .info(|info| {
+                                dbg!(info);
+                            })
+                            .register();
+
+                        Some((Box::new(module), Box::new(obj_listener)))
+                    }
+                    ObjectType::Device | ObjectType::Factory | ObjectType::Client => {
                         // TODO
                         None
                     }
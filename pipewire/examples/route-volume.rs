@@ -0,0 +1,161 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Raise the volume of the active headphones route of every device by 5%.
+//!
+//! The active routes are enumerated as `Route` params of the devices, and the
+//! volume is changed by setting a `Route` param with the new props on the device.
+
+use pipewire as pw;
+use pw::{
+    device::{Device, DeviceListener},
+    spa::{
+        param::{props::Props, route::Route, ParamType},
+        utils::Direction,
+    },
+    types::ObjectType,
+};
+use std::{cell::Cell, cell::RefCell, rc::Rc};
+
+/// The volume step, in the cubic scale used by volume controls.
+const STEP: f32 = 0.05;
+
+fn main() {
+    pw::init();
+
+    raise_headphones_volume();
+
+    unsafe { pw::deinit() };
+}
+
+fn raise_headphones_volume() {
+    let mainloop = pw::main_loop::MainLoop::new(None).expect("Failed to create main loop");
+    let context = pw::context::Context::new(&mainloop).expect("Failed to create context");
+    let core = context.connect(None).expect("Failed to connect to core");
+    let registry = Rc::new(core.get_registry().expect("Failed to get Registry"));
+    let registry_weak = Rc::downgrade(&registry);
+
+    // Devices and their listeners need to stay alive until their params arrived.
+    let devices: Rc<RefCell<Vec<(Device, DeviceListener)>>> = Rc::new(RefCell::new(Vec::new()));
+    let devices_clone = devices.clone();
+    // The headphones routes found so far, with the index of their device.
+    let routes: Rc<RefCell<Vec<(usize, Route)>>> = Rc::new(RefCell::new(Vec::new()));
+    let routes_clone = routes.clone();
+
+    let _listener_reg = registry
+        .add_listener_local()
+        .global(move |global| {
+            if global.type_ != ObjectType::Device {
+                return;
+            }
+
+            if let Some(registry) = registry_weak.upgrade() {
+                let device: Device = registry.bind(global).expect("Failed to bind device");
+                let device_index = devices_clone.borrow().len();
+                let routes = routes_clone.clone();
+                let listener = device
+                    .add_listener_local()
+                    .param(move |_seq, id, _index, _next, param| {
+                        let Some(param) = param else { return };
+                        if id != ParamType::Route {
+                            return;
+                        }
+
+                        match Route::from_pod(param) {
+                            Ok(route) if is_headphones(&route) => {
+                                routes.borrow_mut().push((device_index, route))
+                            }
+                            Ok(_) => {}
+                            Err(err) => eprintln!("Invalid route: {err}"),
+                        }
+                    })
+                    .register();
+                device
+                    .enum_params(0, Some(ParamType::Route), 0, u32::MAX)
+                    .expect("Failed to enumerate routes");
+
+                devices_clone.borrow_mut().push((device, listener));
+            }
+        })
+        .register();
+
+    // The first roundtrip makes sure all globals were announced and bound,
+    // the second one that the routes of the bound devices arrived.
+    do_roundtrip(&mainloop, &core);
+    do_roundtrip(&mainloop, &core);
+
+    let routes = routes.take();
+    if routes.is_empty() {
+        println!("No active headphones route found");
+        return;
+    }
+
+    for (device_index, route) in routes.iter() {
+        let (Some(device), Some(volumes)) = (
+            route.device,
+            route
+                .props
+                .as_ref()
+                .and_then(|p| p.channel_volumes.as_ref()),
+        ) else {
+            continue;
+        };
+
+        let volumes: Vec<f32> = volumes.iter().map(|v| raise(*v)).collect();
+        println!(
+            "{}: setting channel volumes to {:?}",
+            route.description.as_deref().unwrap_or(&route.name),
+            volumes
+        );
+
+        let props = Props {
+            channel_volumes: Some(volumes),
+            ..Default::default()
+        };
+        let pod = Route::to_set_pod(route.index, device, &props, true);
+        devices.borrow()[*device_index]
+            .0
+            .set_param(ParamType::Route, 0, &pod)
+            .expect("Failed to set route");
+    }
+
+    // Make sure the new params were sent to the server before exiting.
+    do_roundtrip(&mainloop, &core);
+}
+
+fn is_headphones(route: &Route) -> bool {
+    route.direction == Direction::Output
+        && route
+            .info
+            .iter()
+            .any(|(key, value)| key == "port.type" && value == "headphones")
+}
+
+/// Raise a linear volume by [`STEP`] in the cubic scale, up to 100%.
+fn raise(volume: f32) -> f32 {
+    (volume.cbrt() + STEP).min(1.0).powi(3)
+}
+
+/// Do a single roundtrip to process all events.
+/// See the example in roundtrip.rs for more details on this.
+fn do_roundtrip(mainloop: &pw::main_loop::MainLoop, core: &pw::core::Core) {
+    let done = Rc::new(Cell::new(false));
+    let done_clone = done.clone();
+    let loop_clone = mainloop.clone();
+
+    let pending = core.sync(0).expect("sync failed");
+
+    let _listener_core = core
+        .add_listener_local()
+        .done(move |id, seq| {
+            if id == pw::core::PW_ID_CORE && seq == pending {
+                done_clone.set(true);
+                loop_clone.quit();
+            }
+        })
+        .register();
+
+    while !done.get() {
+        mainloop.run();
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Create a security context on the server, listening on a new socket, like `pw-container`.
+//!
+//! Clients connecting with `PIPEWIRE_REMOTE=<socket>` get the `pipewire.access` property of the
+//! context, which the session manager uses to restrict what they can see. The context is
+//! destroyed when this example exits.
+
+use clap::Parser;
+use pipewire as pw;
+use pw::{properties::properties, security_context::SecurityContext, types::ObjectType};
+use std::{
+    cell::RefCell,
+    os::{
+        fd::AsFd,
+        unix::net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+    rc::Rc,
+};
+
+#[derive(Parser)]
+#[clap(name = "security-context", about = "Create a restricted socket")]
+struct Opt {
+    /// Path of the socket to create
+    #[clap(default_value = "pipewire-0-restricted")]
+    socket: PathBuf,
+    /// Value of the `pipewire.access` property of the clients
+    #[clap(short, long, default_value = "restricted")]
+    access: String,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::parse();
+
+    pw::init();
+
+    let mainloop = pw::main_loop::MainLoop::new(None)?;
+    let context = pw::context::Context::new(&mainloop)?;
+    let core = context.connect(None)?;
+    let registry = Rc::new(core.get_registry()?);
+    let registry_weak = Rc::downgrade(&registry);
+
+    // The server accepts clients on this socket, and revokes the context once the other end
+    // of the close socket is closed, which happens when this process exits.
+    let listener = UnixListener::bind(&opt.socket)?;
+    listener.set_nonblocking(true)?;
+    let (_close, close_peer) = UnixStream::pair()?;
+
+    let security_context: Rc<RefCell<Option<SecurityContext>>> = Rc::new(RefCell::new(None));
+    let security_context_clone = security_context.clone();
+    let props = properties! {
+        "pipewire.sec.engine" => "org.pipewire.rs.example",
+        "pipewire.access" => opt.access.as_str(),
+    };
+
+    let _registry_listener = registry
+        .add_listener_local()
+        .global(move |global| {
+            if global.type_ != ObjectType::SecurityContext
+                || security_context_clone.borrow().is_some()
+            {
+                return;
+            }
+            let Some(registry) = registry_weak.upgrade() else {
+                return;
+            };
+
+            let bound: SecurityContext = registry
+                .bind(global)
+                .expect("Failed to bind security context");
+            // The fds are sent when the connection is flushed, they stay open until the end
+            // of the example.
+            bound
+                .create(listener.as_fd(), close_peer.as_fd(), props.dict())
+                .expect("Failed to create security context");
+            println!(
+                "Listening on {}, clients get pipewire.access = {}",
+                opt.socket.display(),
+                opt.access
+            );
+            *security_context_clone.borrow_mut() = Some(bound);
+        })
+        .register();
+
+    mainloop.run();
+
+    Ok(())
+}
@@ -7,7 +7,15 @@
 
 use pipewire as pw;
 use pw::{properties::properties, spa};
-use spa::pod::Pod;
+use spa::{
+    param::{
+        audio::AudioFormat,
+        format::{FormatProperties, MediaSubtype, MediaType},
+        ParamType,
+    },
+    pod::{ObjectBuilder, ValueArray},
+    utils::{Id, SpaTypes},
+};
 
 pub const DEFAULT_RATE: u32 = 44100;
 pub const DEFAULT_CHANNELS: u32 = 2;
@@ -15,6 +23,18 @@ pub const DEFAULT_VOLUME: f64 = 0.7;
 pub const PI_2: f64 = std::f64::consts::PI + std::f64::consts::PI;
 pub const CHAN_SIZE: usize = std::mem::size_of::<i16>();
 
+/// The number of frames requested by the stream, or `None` if it did not specify it.
+fn requested_frames(buffer: &pw::buffer::Buffer) -> Option<usize> {
+    // 0 means that the stream did not specify how much data it wants
+    #[cfg(feature = "v0_3_49")]
+    if buffer.requested() != 0 {
+        return Some(buffer.requested() as usize);
+    }
+
+    let _ = buffer;
+    None
+}
+
 pub fn main() -> Result<(), pw::Error> {
     pw::init();
     let mainloop = pw::main_loop::MainLoop::new(None)?;
@@ -36,14 +56,20 @@ pub fn main() -> Result<(), pw::Error> {
 
     let _listener = stream
         .add_local_listener_with_user_data(data)
+        // callbacks which do not need the phase of the tone ignore it
+        .state_changed(|_, _, old, new| println!("stream state: {old:?} -> {new:?}"))
         .process(|stream, acc| match stream.dequeue_buffer() {
             None => println!("No buffer received"),
             Some(mut buffer) => {
+                let requested = requested_frames(&buffer);
                 let datas = buffer.datas_mut();
                 let stride = CHAN_SIZE * DEFAULT_CHANNELS as usize;
                 let data = &mut datas[0];
                 let n_frames = if let Some(slice) = data.data() {
-                    let n_frames = slice.len() / stride;
+                    // Fill exactly the requested frames, or the whole buffer when unspecified.
+                    let max_frames = slice.len() / stride;
+                    let n_frames =
+                        requested.map_or(max_frames, |requested| requested.min(max_frames));
                     for i in 0..n_frames {
                         *acc += PI_2 * 440.0 / DEFAULT_RATE as f64;
                         if *acc >= PI_2 {
@@ -61,40 +87,34 @@ pub fn main() -> Result<(), pw::Error> {
                 } else {
                     0
                 };
-                let chunk = data.chunk_mut();
-                *chunk.offset_mut() = 0;
-                *chunk.stride_mut() = stride as _;
-                *chunk.size_mut() = (stride * n_frames) as _;
+                let mut chunk = data.chunk_mut();
+                chunk.set_offset(0);
+                chunk.set_stride(stride as _);
+                chunk.set_size((stride * n_frames) as _);
             }
         })
         .register()?;
 
-    let mut audio_info = spa::param::audio::AudioInfoRaw::new();
-    audio_info.set_format(spa::param::audio::AudioFormat::S16LE);
-    audio_info.set_rate(DEFAULT_RATE);
-    audio_info.set_channels(DEFAULT_CHANNELS);
-    let mut position = [0; spa::param::audio::MAX_CHANNELS];
-    position[0] = spa_sys::SPA_AUDIO_CHANNEL_FL;
-    position[1] = spa_sys::SPA_AUDIO_CHANNEL_FR;
-    audio_info.set_position(position);
-
-    let values: Vec<u8> = pw::spa::pod::serialize::PodSerializer::serialize(
-        std::io::Cursor::new(Vec::new()),
-        &pw::spa::pod::Value::Object(pw::spa::pod::Object {
-            type_: spa_sys::SPA_TYPE_OBJECT_Format,
-            id: spa_sys::SPA_PARAM_EnumFormat,
-            properties: audio_info.into(),
-        }),
-    )
-    .unwrap()
-    .0
-    .into_inner();
+    let format = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+        .prop(FormatProperties::MediaType, MediaType::Audio)
+        .prop(FormatProperties::MediaSubtype, MediaSubtype::Raw)
+        .prop(FormatProperties::AudioFormat, AudioFormat::S16LE)
+        .prop(FormatProperties::AudioRate, DEFAULT_RATE as i32)
+        .prop(FormatProperties::AudioChannels, DEFAULT_CHANNELS as i32)
+        .prop(
+            FormatProperties::AudioPosition,
+            ValueArray::Id(vec![
+                Id(spa_sys::SPA_AUDIO_CHANNEL_FL),
+                Id(spa_sys::SPA_AUDIO_CHANNEL_FR),
+            ]),
+        )
+        .build();
 
-    let mut params = [Pod::from_bytes(&values).unwrap()];
+    let mut params = [format.as_pod()];
 
-    stream.connect(
+    stream.connect_target(
         spa::utils::Direction::Output,
-        None,
+        pw::stream::Target::Any,
         pw::stream::StreamFlags::AUTOCONNECT
             | pw::stream::StreamFlags::MAP_BUFFERS
             | pw::stream::StreamFlags::RT_PROCESS,
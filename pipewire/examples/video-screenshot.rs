@@ -0,0 +1,195 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Capture a single frame of a video source, such as a camera or a screen cast,
+//! and save it as a PNG file.
+//!
+//! The pixels are read with [`VideoFrame`], which locates them in the buffer
+//! according to the negotiated format.
+
+use clap::Parser;
+use pipewire as pw;
+use pw::{properties::properties, spa, video_frame::VideoFrame};
+use spa::{
+    param::{
+        format::{FormatProperties, MediaSubtype, MediaType},
+        video::{VideoFormat, VideoInfoRaw},
+        ParamType,
+    },
+    pod::ObjectBuilder,
+    utils::{Fraction, Rectangle, SpaTypes},
+};
+use std::path::{Path, PathBuf};
+
+const FORMATS: [VideoFormat; 6] = [
+    VideoFormat::RGBx,
+    VideoFormat::BGRx,
+    VideoFormat::RGBA,
+    VideoFormat::BGRA,
+    VideoFormat::RGB,
+    VideoFormat::BGR,
+];
+
+#[derive(Parser)]
+#[clap(
+    name = "video-screenshot",
+    about = "Save a frame of a video source as PNG"
+)]
+struct Opt {
+    #[clap(short, long, help = "The target object id to connect to")]
+    target: Option<u32>,
+    #[clap(default_value = "screenshot.png", help = "The PNG file to write")]
+    output: PathBuf,
+}
+
+#[derive(Default)]
+struct UserData {
+    format: Option<VideoInfoRaw>,
+    saved: bool,
+}
+
+/// Convert a pixel of one of the negotiated [`FORMATS`] to RGBA.
+fn to_rgba(format: VideoFormat, pixel: &[u8]) -> [u8; 4] {
+    match format {
+        VideoFormat::RGBA => [pixel[0], pixel[1], pixel[2], pixel[3]],
+        VideoFormat::BGRA => [pixel[2], pixel[1], pixel[0], pixel[3]],
+        VideoFormat::BGRx | VideoFormat::BGR => [pixel[2], pixel[1], pixel[0], 255],
+        _ => [pixel[0], pixel[1], pixel[2], 255],
+    }
+}
+
+fn save_frame(frame: &mut VideoFrame, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let (width, height, format) = (frame.width(), frame.height(), frame.format());
+    let bytes_per_pixel = match format {
+        VideoFormat::RGB | VideoFormat::BGR => 3,
+        _ => 4,
+    };
+
+    let mut plane = frame.plane(0)?;
+    let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+    for row in plane.rows().take(height as usize) {
+        for pixel in row.chunks_exact(bytes_per_pixel).take(width as usize) {
+            rgba.extend_from_slice(&to_rgba(format, pixel));
+        }
+    }
+
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&rgba)?;
+    Ok(())
+}
+
+pub fn main() -> Result<(), pw::Error> {
+    pw::init();
+
+    let opt = Opt::parse();
+
+    let mainloop = pw::main_loop::MainLoop::new(None)?;
+    let context = pw::context::Context::new(&mainloop)?;
+    let core = context.connect(None)?;
+
+    let stream = pw::stream::Stream::new(
+        &core,
+        "video-screenshot",
+        properties! {
+            *pw::keys::MEDIA_TYPE => "Video",
+            *pw::keys::MEDIA_CATEGORY => "Capture",
+            *pw::keys::MEDIA_ROLE => "Screen",
+        },
+    )?;
+
+    let _listener = stream
+        .add_local_listener_with_user_data(UserData::default())
+        .param_changed(|_, user_data, id, param| {
+            let Some(param) = param else {
+                return;
+            };
+            if id != ParamType::Format.as_raw() {
+                return;
+            }
+            if let Ok(format) = VideoInfoRaw::from_format_pod(param) {
+                println!(
+                    "got video format {:?} {}x{}",
+                    format.format(),
+                    format.size().width,
+                    format.size().height
+                );
+                user_data.format = Some(format);
+            }
+        })
+        .process({
+            let mainloop = mainloop.clone();
+            let output = opt.output.clone();
+            move |stream, user_data| {
+                let Some(mut buffer) = stream.dequeue_buffer() else {
+                    return;
+                };
+                let Some(format) = user_data.format else {
+                    return;
+                };
+                // Sources may send empty buffers, such as screen casts when nothing changed.
+                if user_data.saved
+                    || buffer
+                        .datas_mut()
+                        .first()
+                        .map_or(true, |data| data.chunk().size() == 0)
+                {
+                    return;
+                }
+
+                let result = VideoFrame::new(&format, &mut buffer)
+                    .map_err(Into::into)
+                    .and_then(|mut frame| save_frame(&mut frame, &output));
+                match result {
+                    Ok(()) => println!("saved {}", output.display()),
+                    Err(err) => eprintln!("failed to save the frame: {err}"),
+                }
+                user_data.saved = true;
+                mainloop.quit();
+            }
+        })
+        .register()?;
+
+    let format = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+        .prop(FormatProperties::MediaType, MediaType::Video)
+        .prop(FormatProperties::MediaSubtype, MediaSubtype::Raw)
+        .prop_choice_enum(FormatProperties::VideoFormat, FORMATS[0], FORMATS)
+        .prop_choice_range(
+            FormatProperties::VideoSize,
+            Rectangle {
+                width: 320,
+                height: 240,
+            },
+            Rectangle {
+                width: 1,
+                height: 1,
+            },
+            Rectangle {
+                width: 8192,
+                height: 8192,
+            },
+        )
+        .prop_choice_range(
+            FormatProperties::VideoFramerate,
+            Fraction { num: 25, denom: 1 },
+            Fraction { num: 0, denom: 1 },
+            Fraction {
+                num: 1000,
+                denom: 1,
+            },
+        )
+        .build();
+
+    stream.connect(
+        spa::utils::Direction::Input,
+        opt.target,
+        pw::stream::StreamFlags::AUTOCONNECT | pw::stream::StreamFlags::MAP_BUFFERS,
+        &mut [format.as_pod()],
+    )?;
+
+    mainloop.run();
+
+    Ok(())
+}
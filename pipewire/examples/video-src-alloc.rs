@@ -0,0 +1,161 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! A video source providing the memory of its buffers itself, allocated with memfd.
+//!
+//! This is a rustic interpretation of `video-src-alloc.c` from the PipeWire examples.
+//! Display the video with e.g. `gst-launch-1.0 pipewiresrc ! videoconvert ! autovideosink`.
+
+use pipewire as pw;
+use pw::{
+    buffer::BufferRef,
+    mem::{self, MappedMem},
+    properties::properties,
+    spa,
+};
+use spa::{
+    param::{
+        format::{FormatProperties, MediaSubtype, MediaType},
+        video::VideoFormat,
+        BuffersParam, DataTypes, ParamType,
+    },
+    pod::{ObjectBuilder, Pod},
+    utils::{Fraction, Rectangle, SpaTypes},
+};
+use std::os::fd::{AsFd, OwnedFd};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+const BPP: u32 = 4;
+const STRIDE: u32 = WIDTH * BPP;
+
+#[derive(Default)]
+struct UserData {
+    /// The memory of each buffer, by buffer.
+    memory: Vec<(*mut pw::sys::pw_buffer, OwnedFd, MappedMem)>,
+    frame: u32,
+}
+
+fn add_buffer(user_data: &mut UserData, buffer: &mut BufferRef) {
+    let ptr = buffer.as_raw_ptr();
+    let Some(data) = buffer.datas_mut().first_mut() else {
+        return;
+    };
+
+    let (fd, mut mapped) =
+        mem::alloc_memfd(data.maxsize() as usize).expect("Failed to allocate memfd");
+    if let Err(err) = mem::attach_memfd(data, fd.as_fd(), &mut mapped) {
+        eprintln!("Cannot use memfd for buffer: {err}");
+        return;
+    }
+    println!("Added buffer of {} bytes", mapped.len());
+
+    user_data.memory.push((ptr, fd, mapped));
+}
+
+fn remove_buffer(user_data: &mut UserData, buffer: &mut BufferRef) {
+    let ptr = buffer.as_raw_ptr();
+    // dropping the memory unmaps and closes it
+    user_data.memory.retain(|(buffer, _, _)| *buffer != ptr);
+}
+
+pub fn main() -> Result<(), pw::Error> {
+    pw::init();
+
+    let mainloop = pw::main_loop::MainLoop::new(None)?;
+    let context = pw::context::Context::new(&mainloop)?;
+    let core = context.connect(None)?;
+
+    let stream = pw::stream::Stream::new(
+        &core,
+        "video-src-alloc",
+        properties! {
+            *pw::keys::MEDIA_CLASS => "Video/Source",
+        },
+    )?;
+
+    let _listener = stream
+        .add_local_listener::<UserData>()
+        .state_changed(|_, _, old, new| {
+            println!("State changed: {:?} -> {:?}", old, new);
+        })
+        .param_changed(|stream, _, id, param| {
+            if id != ParamType::Format.as_raw() || param.is_none() {
+                return;
+            }
+
+            // ask for buffers of a frame, in memory provided by add_buffer
+            let buffers = BuffersParam::new()
+                .buffers_range(8, 2, 16)
+                .blocks(1)
+                .size((STRIDE * HEIGHT) as i32)
+                .stride(STRIDE as i32)
+                .data_type(DataTypes::MEM_FD)
+                .to_pod();
+            stream
+                .update_params(&[buffers.as_pod()])
+                .expect("Failed to update params");
+        })
+        .add_buffer(|_, user_data, buffer| add_buffer(user_data, buffer))
+        .remove_buffer(|_, user_data, buffer| remove_buffer(user_data, buffer))
+        .process(|stream, user_data| {
+            let Some(mut buffer) = stream.dequeue_buffer() else {
+                println!("out of buffers");
+                return;
+            };
+            let Some(data) = buffer.datas_mut().first_mut() else {
+                return;
+            };
+            let Some(frame) = data.data() else {
+                return;
+            };
+
+            // a moving gradient
+            user_data.frame = user_data.frame.wrapping_add(1);
+            for (y, line) in frame
+                .chunks_exact_mut(STRIDE as usize)
+                .take(HEIGHT as usize)
+                .enumerate()
+            {
+                for (x, pixel) in line.chunks_exact_mut(BPP as usize).enumerate() {
+                    let v = (x as u32 + y as u32 + user_data.frame) as u8;
+                    pixel.copy_from_slice(&[v, v.wrapping_mul(2), 255 - v, 255]);
+                }
+            }
+
+            let mut chunk = data.chunk_mut();
+            chunk.set_offset(0);
+            chunk.set_size(STRIDE * HEIGHT);
+            chunk.set_stride(STRIDE as i32);
+        })
+        .register()?;
+
+    let format = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+        .prop(FormatProperties::MediaType, MediaType::Video)
+        .prop(FormatProperties::MediaSubtype, MediaSubtype::Raw)
+        .prop(FormatProperties::VideoFormat, VideoFormat::BGRx)
+        .prop(
+            FormatProperties::VideoSize,
+            Rectangle {
+                width: WIDTH,
+                height: HEIGHT,
+            },
+        )
+        .prop(
+            FormatProperties::VideoFramerate,
+            Fraction { num: 25, denom: 1 },
+        )
+        .build();
+    let mut params: [&Pod; 1] = [&format];
+
+    stream.connect(
+        spa::utils::Direction::Output,
+        None,
+        pw::stream::StreamFlags::ALLOC_BUFFERS,
+        &mut params,
+    )?;
+
+    mainloop.run();
+
+    Ok(())
+}
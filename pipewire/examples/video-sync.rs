@@ -0,0 +1,175 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Consume the DMA-BUF frames of a video source using explicit sync.
+//!
+//! The stream asks for the sync timeline metadata, so producers supporting explicit sync
+//! attach an acquire and a release syncobj to each buffer. For each frame, the consumer:
+//!
+//! 1. waits for the acquire point, until the producer is done writing the frame,
+//! 2. reads the frame,
+//! 3. signals the release point, so the producer can reuse the buffer,
+//! 4. queues the buffer back, when it is dropped.
+//!
+//! Buffers without syncobjs are read right away, as with implicit sync.
+
+use clap::Parser;
+use pipewire as pw;
+use pw::{buffer::BufferRef, drm_syncobj::DrmDevice, properties::properties, spa};
+use spa::{
+    buffer::{MetaSyncTimeline, MetaType},
+    param::{
+        video::{VideoEnumFormatBuilder, VideoFormat},
+        BuffersParam, DataTypes, ParamType,
+    },
+    pod::ObjectBuilder,
+    utils::{Fraction, Id, Rectangle, SpaTypes},
+};
+use std::{os::fd::BorrowedFd, path::PathBuf, time::Duration};
+
+/// The modifier of buffers with a linear layout, `DRM_FORMAT_MOD_LINEAR`.
+const MODIFIER_LINEAR: u64 = 0;
+/// The modifier of buffers with an implicit layout, `DRM_FORMAT_MOD_INVALID`.
+const MODIFIER_INVALID: u64 = (1 << 56) - 1;
+
+#[derive(Parser)]
+#[clap(
+    name = "video-sync",
+    about = "Consume DMA-BUF frames with explicit sync"
+)]
+struct Opt {
+    #[clap(short, long, help = "The target object id to connect to")]
+    target: Option<u32>,
+    #[clap(
+        short,
+        long,
+        default_value = "/dev/dri/renderD128",
+        help = "The DRM render node used to wait for and signal the syncobjs"
+    )]
+    device: PathBuf,
+}
+
+/// Read a frame, following the explicit sync handshake if the buffer has syncobjs.
+fn consume(device: &DrmDevice, buffer: &mut BufferRef) -> Result<(), pw::Error> {
+    let sync = buffer.sync_objs().zip(buffer.meta_sync_timeline().copied());
+    let release = match sync {
+        Some(((acquire, release), timeline)) => {
+            // Safety: the fds of the datas stay open while the buffer exists.
+            let acquire = device.import(unsafe { BorrowedFd::borrow_raw(acquire) })?;
+            let release = device.import(unsafe { BorrowedFd::borrow_raw(release) })?;
+            acquire.wait(timeline.acquire_point(), Duration::from_millis(100))?;
+            Some((release, timeline.release_point()))
+        }
+        None => None,
+    };
+
+    // A real consumer would import the DMA-BUF into its graphics API here.
+    if let Some(data) = buffer.datas().first() {
+        println!(
+            "frame: fd {} size {} explicit sync {}",
+            data.fd(),
+            data.chunk().size(),
+            release.is_some()
+        );
+    }
+
+    if let Some((release, point)) = release {
+        release.signal(point)?;
+    }
+    Ok(())
+}
+
+pub fn main() -> Result<(), pw::Error> {
+    pw::init();
+
+    let opt = Opt::parse();
+    let device = DrmDevice::open(&opt.device)?;
+
+    let mainloop = pw::main_loop::MainLoop::new(None)?;
+    let context = pw::context::Context::new(&mainloop)?;
+    let core = context.connect(None)?;
+
+    let stream = pw::stream::Stream::new(
+        &core,
+        "video-sync",
+        properties! {
+            *pw::keys::MEDIA_TYPE => "Video",
+            *pw::keys::MEDIA_CATEGORY => "Capture",
+            *pw::keys::MEDIA_ROLE => "Screen",
+        },
+    )?;
+
+    let _listener = stream
+        .add_local_listener::<()>()
+        .param_changed(|stream, _, id, param| {
+            if param.is_none() || id != ParamType::Format.as_raw() {
+                return;
+            }
+            // DMA-BUF buffers, with the metadata telling the timeline points of their syncobjs
+            let buffers = BuffersParam::new()
+                .buffers_range(4, 2, 16)
+                .data_type(DataTypes::DMA_BUF)
+                .to_pod();
+            let meta = ObjectBuilder::new(SpaTypes::ObjectParamMeta, ParamType::Meta)
+                .prop(
+                    spa::sys::SPA_PARAM_META_type,
+                    Id(MetaType::SyncTimeline.as_raw()),
+                )
+                .prop(
+                    spa::sys::SPA_PARAM_META_size,
+                    std::mem::size_of::<MetaSyncTimeline>() as i32,
+                )
+                .build();
+            if let Err(err) = stream.update_params(&[buffers.as_pod(), meta.as_pod()]) {
+                eprintln!("failed to update the params: {err}");
+            }
+        })
+        .process(move |stream, _| {
+            let Some(mut buffer) = stream.dequeue_buffer() else {
+                return;
+            };
+            if let Err(err) = consume(&device, &mut buffer) {
+                eprintln!("failed to consume the frame: {err}");
+            }
+        })
+        .register()?;
+
+    let formats = VideoEnumFormatBuilder::new()
+        .format(VideoFormat::BGRx, &[MODIFIER_LINEAR, MODIFIER_INVALID])
+        .format(VideoFormat::BGRA, &[MODIFIER_LINEAR, MODIFIER_INVALID])
+        .size(
+            Rectangle {
+                width: 1920,
+                height: 1080,
+            },
+            Rectangle {
+                width: 1,
+                height: 1,
+            },
+            Rectangle {
+                width: 8192,
+                height: 8192,
+            },
+        )
+        .framerate(
+            Fraction { num: 30, denom: 1 },
+            Fraction { num: 0, denom: 1 },
+            Fraction {
+                num: 1000,
+                denom: 1,
+            },
+        )
+        .build();
+    let mut params: Vec<_> = formats.iter().map(|format| format.as_pod()).collect();
+
+    stream.connect(
+        spa::utils::Direction::Input,
+        opt.target,
+        pw::stream::StreamFlags::AUTOCONNECT,
+        &mut params,
+    )?;
+
+    mainloop.run();
+
+    Ok(())
+}
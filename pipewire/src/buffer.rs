@@ -1,52 +1,385 @@
 use super::stream::StreamRef;
+use crate::Error;
 
-use spa::buffer::Data;
+use nix::errno::Errno;
+use spa::buffer::{
+    Data, DataType, Meta, MetaBusy, MetaCursor, MetaHeader, MetaRegion, MetaSyncTimeline, MetaType,
+};
+use spa::utils::result::SpaResult;
 use std::convert::TryFrom;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::os::fd::RawFd;
 use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU64, Ordering};
 
+/// A buffer dequeued from a stream, queued back when it is dropped.
+///
+/// Its content is accessed through [`BufferRef`], or with [`get`](Self::get) and
+/// [`get_mut`](Self::get_mut) to find out whether the stream reclaimed the buffer.
+/// The buffer can also be queued explicitly with [`queue`](Self::queue), kept dequeued
+/// with [`discard`](Self::discard), or handed over as a raw pointer with [`into_raw`](Self::into_raw).
+///
+/// If the stream reclaims its buffers before the buffer is queued, for example when it is
+/// disconnected, the buffer is not queued back, and its content is empty from then on:
+/// it has no datas nor metas.
 pub struct Buffer<'s> {
     buf: NonNull<pw_sys::pw_buffer>,
 
     /// In Pipewire, buffers are owned by the stream that generated them.
     /// This reference ensures that this rule is respected.
     stream: &'s StreamRef,
+
+    /// The buffer generation of the stream, and the generation the buffer was dequeued in.
+    generation: Option<(&'s AtomicU64, u64)>,
+
+    /// The content of the buffer once it was reclaimed.
+    empty: pw_sys::pw_buffer,
 }
 
-impl Buffer<'_> {
+impl<'s> Buffer<'s> {
     pub(crate) unsafe fn from_raw(
         buf: *mut pw_sys::pw_buffer,
-        stream: &StreamRef,
-    ) -> Option<Buffer<'_>> {
-        NonNull::new(buf).map(|buf| Buffer { buf, stream })
+        stream: &'s StreamRef,
+    ) -> Option<Buffer<'s>> {
+        let buf = NonNull::new(buf)?;
+        let generation = stream
+            .buffer_generation(buf.as_ptr())
+            .map(|current| (current, current.load(Ordering::Acquire)));
+        Some(Buffer {
+            buf,
+            stream,
+            generation,
+            empty: std::mem::zeroed(),
+        })
     }
 
-    pub fn datas_mut(&mut self) -> &mut [Data] {
-        let buffer: *mut spa_sys::spa_buffer = unsafe { self.buf.as_ref().buffer };
+    /// The content of the buffer, or `None` if the stream reclaimed its buffers since the buffer
+    /// was dequeued.
+    pub fn get(&self) -> Option<&BufferRef> {
+        if self.is_reclaimed() {
+            return None;
+        }
+        Some(unsafe { self.buf.cast::<BufferRef>().as_ref() })
+    }
+
+    /// The mutable content of the buffer, or `None` if the stream reclaimed its buffers
+    /// since the buffer was dequeued.
+    pub fn get_mut(&mut self) -> Option<&mut BufferRef> {
+        if self.is_reclaimed() {
+            return None;
+        }
+        Some(unsafe { self.buf.cast::<BufferRef>().as_mut() })
+    }
+
+    /// Queue the buffer back to its stream, as dropping it does.
+    ///
+    /// Fails with `EPIPE` if the stream reclaimed its buffers since the buffer was dequeued.
+    pub fn queue(self) -> Result<(), Error> {
+        let mut buffer = ManuallyDrop::new(self);
+        let res = buffer.queue_inner();
+        buffer.generation = None;
+        res
+    }
+
+    /// Keep the buffer dequeued, without queuing it back to the stream.
+    ///
+    /// The stream gets the buffer back when it reclaims its buffers, for example when it is
+    /// disconnected. Until then, it has one buffer less to process.
+    pub fn discard(self) {
+        self.into_raw();
+    }
+
+    /// Keep the buffer dequeued, returning its raw pointer.
+    ///
+    /// The buffer must be queued later with [`StreamRef::queue_raw_buffer`], otherwise
+    /// the stream stalls once it runs out of buffers.
+    pub fn into_raw(self) -> *mut pw_sys::pw_buffer {
+        let mut buffer = ManuallyDrop::new(self);
+        buffer.generation = None;
+        buffer.buf.as_ptr()
+    }
+
+    /// Whether the stream reclaimed its buffers since the buffer was dequeued.
+    pub fn is_reclaimed(&self) -> bool {
+        self.generation
+            .as_ref()
+            .is_some_and(|(current, generation)| current.load(Ordering::Acquire) != *generation)
+    }
+
+    fn queue_inner(&self) -> Result<(), Error> {
+        let state =
+            unsafe { pw_sys::pw_stream_get_state(self.stream.as_raw_ptr(), std::ptr::null_mut()) };
+        // the buffers were reclaimed by the stream when it was disconnected, or renegotiated
+        if self.is_reclaimed() || state == pw_sys::pw_stream_state::PW_STREAM_STATE_UNCONNECTED {
+            return Err(Errno::EPIPE.into());
+        }
+
+        let res =
+            unsafe { pw_sys::pw_stream_queue_buffer(self.stream.as_raw_ptr(), self.buf.as_ptr()) };
+        SpaResult::from_c(res).into_sync_result()?;
+        Ok(())
+    }
+}
+
+impl Deref for Buffer<'_> {
+    type Target = BufferRef;
+
+    fn deref(&self) -> &Self::Target {
+        match self.get() {
+            Some(buffer) => buffer,
+            None => unsafe { NonNull::from(&self.empty).cast::<BufferRef>().as_ref() },
+        }
+    }
+}
 
-        let slice_of_data = if !buffer.is_null()
-            && unsafe { (*buffer).n_datas > 0 && !(*buffer).datas.is_null() }
-        {
+impl DerefMut for Buffer<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        if self.is_reclaimed() {
+            return unsafe { NonNull::from(&mut self.empty).cast::<BufferRef>().as_mut() };
+        }
+        unsafe { self.buf.cast::<BufferRef>().as_mut() }
+    }
+}
+
+impl Drop for Buffer<'_> {
+    fn drop(&mut self) {
+        let _ = self.queue_inner();
+    }
+}
+
+/// A buffer of a stream, as dequeued in a [`Buffer`] or passed to the `add_buffer`
+/// and `remove_buffer` callbacks of the stream listener.
+#[repr(transparent)]
+pub struct BufferRef(pw_sys::pw_buffer);
+
+impl BufferRef {
+    /// Obtain a [`BufferRef`] from a raw `pw_buffer`.
+    ///
+    /// # Safety
+    /// `buf` must point to a valid `pw_buffer` of a stream, which is not accessed otherwise
+    /// during the lifetime `'a`.
+    pub unsafe fn from_raw_mut<'a>(buf: NonNull<pw_sys::pw_buffer>) -> &'a mut Self {
+        buf.cast::<Self>().as_mut()
+    }
+
+    pub fn as_raw(&self) -> &pw_sys::pw_buffer {
+        &self.0
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut pw_sys::pw_buffer {
+        std::ptr::addr_of!(self.0).cast_mut()
+    }
+
+    fn datas_ptr(&self) -> Option<(*mut Data, usize)> {
+        let buffer: *mut spa_sys::spa_buffer = self.0.buffer;
+
+        if !buffer.is_null() && unsafe { (*buffer).n_datas > 0 && !(*buffer).datas.is_null() } {
             unsafe {
-                let datas = (*buffer).datas as *mut Data;
-                std::slice::from_raw_parts_mut(datas, usize::try_from((*buffer).n_datas).unwrap())
+                Some((
+                    (*buffer).datas as *mut Data,
+                    usize::try_from((*buffer).n_datas).unwrap(),
+                ))
             }
         } else {
-            &mut []
+            None
+        }
+    }
+
+    /// The datas of the buffer.
+    pub fn datas(&self) -> &[Data] {
+        match self.datas_ptr() {
+            Some((datas, len)) => unsafe { std::slice::from_raw_parts(datas, len) },
+            None => &[],
+        }
+    }
+
+    pub fn datas_mut(&mut self) -> &mut [Data] {
+        match self.datas_ptr() {
+            Some((datas, len)) => unsafe { std::slice::from_raw_parts_mut(datas, len) },
+            None => &mut [],
+        }
+    }
+
+    /// The fds of the acquire and release syncobjs of a buffer using explicit sync,
+    /// its last two datas when they are of type [`DataType::SyncObj`].
+    ///
+    /// Their timeline points are in the [`meta_sync_timeline`](Self::meta_sync_timeline).
+    /// Returns `None` for buffers without syncobjs.
+    pub fn sync_objs(&self) -> Option<(RawFd, RawFd)> {
+        let [.., acquire, release] = self.datas() else {
+            return None;
         };
+        if acquire.type_() != DataType::SyncObj || release.type_() != DataType::SyncObj {
+            return None;
+        }
+        Some((
+            RawFd::try_from(acquire.fd()).ok()?,
+            RawFd::try_from(release.fd()).ok()?,
+        ))
+    }
+
+    fn metas_ptr(&self) -> Option<(*mut Meta, usize)> {
+        let buffer: *mut spa_sys::spa_buffer = self.0.buffer;
+
+        if !buffer.is_null() && unsafe { (*buffer).n_metas > 0 && !(*buffer).metas.is_null() } {
+            unsafe {
+                Some((
+                    (*buffer).metas as *mut Meta,
+                    usize::try_from((*buffer).n_metas).unwrap(),
+                ))
+            }
+        } else {
+            None
+        }
+    }
+
+    /// The metadata attached to the buffer.
+    pub fn metas(&self) -> &[Meta] {
+        match self.metas_ptr() {
+            Some((metas, len)) => unsafe { std::slice::from_raw_parts(metas, len) },
+            None => &[],
+        }
+    }
+
+    pub fn metas_mut(&mut self) -> &mut [Meta] {
+        match self.metas_ptr() {
+            Some((metas, len)) => unsafe { std::slice::from_raw_parts_mut(metas, len) },
+            None => &mut [],
+        }
+    }
 
-        slice_of_data
+    /// Find the metadata of type `type_`, if the buffer has some.
+    ///
+    /// Use [`Meta::get`] to access its typed content.
+    pub fn find_meta(&self, type_: MetaType) -> Option<&Meta> {
+        self.metas().iter().find(|meta| meta.type_() == type_)
     }
 
+    pub fn find_meta_mut(&mut self, type_: MetaType) -> Option<&mut Meta> {
+        self.metas_mut()
+            .iter_mut()
+            .find(|meta| meta.type_() == type_)
+    }
+
+    pub fn meta_header(&self) -> Option<&MetaHeader> {
+        self.find_meta(MetaType::Header)?.get()
+    }
+
+    pub fn meta_header_mut(&mut self) -> Option<&mut MetaHeader> {
+        self.find_meta_mut(MetaType::Header)?.get_mut()
+    }
+
+    /// The first region of [`MetaType::VideoCrop`] or [`MetaType::VideoDamage`] metadata.
+    ///
+    /// Use [`Meta::regions`] on [`find_meta`](Self::find_meta) to access all damaged regions.
+    pub fn meta_region(&self, type_: MetaType) -> Option<&MetaRegion> {
+        self.find_meta(type_)?.get()
+    }
+
+    pub fn meta_region_mut(&mut self, type_: MetaType) -> Option<&mut MetaRegion> {
+        self.find_meta_mut(type_)?.get_mut()
+    }
+
+    /// The cursor metadata, use [`Meta::cursor_bitmap`] on [`find_meta`](Self::find_meta)
+    /// to access its bitmap.
+    pub fn meta_cursor(&self) -> Option<&MetaCursor> {
+        self.find_meta(MetaType::Cursor)?.get()
+    }
+
+    pub fn meta_cursor_mut(&mut self) -> Option<&mut MetaCursor> {
+        self.find_meta_mut(MetaType::Cursor)?.get_mut()
+    }
+
+    pub fn meta_busy(&self) -> Option<&MetaBusy> {
+        self.find_meta(MetaType::Busy)?.get()
+    }
+
+    pub fn meta_busy_mut(&mut self) -> Option<&mut MetaBusy> {
+        self.find_meta_mut(MetaType::Busy)?.get_mut()
+    }
+
+    /// The timeline points of the [`sync_objs`](Self::sync_objs) of the buffer.
+    pub fn meta_sync_timeline(&self) -> Option<&MetaSyncTimeline> {
+        self.find_meta(MetaType::SyncTimeline)?.get()
+    }
+
+    pub fn meta_sync_timeline_mut(&mut self) -> Option<&mut MetaSyncTimeline> {
+        self.find_meta_mut(MetaType::SyncTimeline)?.get_mut()
+    }
+
+    /// The size of the data in the buffer, as set with [`set_size`](Self::set_size).
+    pub fn size(&self) -> u64 {
+        self.0.size
+    }
+
+    /// Set the size of the data in the buffer, before it is queued.
+    ///
+    /// The sizes of the queued buffers are summed up in the time information of the stream,
+    /// for audio it should be the number of frames in the buffer.
+    pub fn set_size(&mut self, size: u64) {
+        self.0.size = size;
+    }
+
+    /// For playback streams, the number of frames the stream wants in this buffer.
+    ///
+    /// This is usually the size of the current quantum. It is 0 when the stream does not specify
+    /// how much data it wants, in which case the buffer should be filled according to its
+    /// available space instead, such as the `maxsize` of its data divided by the stride.
+    /// Writing more or less than requested results in audible glitches.
     #[cfg(feature = "v0_3_49")]
     pub fn requested(&self) -> u64 {
-        unsafe { self.buf.as_ref().requested }
+        self.0.requested
     }
 }
 
-impl Drop for Buffer<'_> {
-    fn drop(&mut self) {
-        unsafe {
-            self.stream.queue_raw_buffer(self.buf.as_ptr());
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(type_: DataType, fd: i64) -> spa_sys::spa_data {
+        let mut data: spa_sys::spa_data = unsafe { std::mem::zeroed() };
+        data.type_ = type_.as_raw();
+        data.fd = fd;
+        data
+    }
+
+    #[test]
+    fn sync_objs() {
+        let mut datas = [
+            data(DataType::DmaBuf, 10),
+            data(DataType::SyncObj, 11),
+            data(DataType::SyncObj, 12),
+        ];
+        let mut timeline = MetaSyncTimeline::default();
+        timeline.set_acquire_point(1);
+        timeline.set_release_point(2);
+        let mut metas = [spa_sys::spa_meta {
+            type_: MetaType::SyncTimeline.as_raw(),
+            size: std::mem::size_of::<MetaSyncTimeline>() as u32,
+            data: std::ptr::addr_of_mut!(timeline).cast(),
+        }];
+        let mut spa_buffer: spa_sys::spa_buffer = unsafe { std::mem::zeroed() };
+        spa_buffer.n_datas = datas.len() as u32;
+        spa_buffer.datas = datas.as_mut_ptr();
+        spa_buffer.n_metas = metas.len() as u32;
+        spa_buffer.metas = metas.as_mut_ptr();
+        let mut raw: pw_sys::pw_buffer = unsafe { std::mem::zeroed() };
+        raw.buffer = &mut spa_buffer;
+
+        let buffer = unsafe { BufferRef::from_raw_mut(NonNull::from(&mut raw)) };
+        assert_eq!(buffer.datas().len(), 3);
+        assert_eq!(buffer.sync_objs(), Some((11, 12)));
+        let timeline = buffer.meta_sync_timeline().unwrap();
+        assert_eq!((timeline.acquire_point(), timeline.release_point()), (1, 2));
+
+        // buffers without syncobjs
+        buffer.datas_mut()[1].set_type(DataType::DmaBuf);
+        assert_eq!(buffer.sync_objs(), None);
+        spa_buffer.n_datas = 1;
+        spa_buffer.n_metas = 0;
+        let buffer = unsafe { BufferRef::from_raw_mut(NonNull::from(&mut raw)) };
+        assert_eq!(buffer.sync_objs(), None);
+        assert!(buffer.meta_sync_timeline().is_none());
     }
 }
@@ -8,6 +8,9 @@
 //! The returned receiver can then be attached to a pipewire loop, and the sender can be used to send messages to
 //! the receiver.
 //!
+//! Messages flowing out of a loop, such as events of PipeWire objects, can be consumed by async code with
+//! the channel created by [`async_channel`], whose [`AsyncReceiver`] is awaited instead.
+//!
 //! # Examples
 //! This program will print "Hello" three times before terminating, using two threads:
 // ignored because https://gitlab.freedesktop.org/pipewire/pipewire-rs/-/issues/19
@@ -48,12 +51,12 @@
 //!     let _receiver = pw_receiver.attach(mainloop.loop_(), {
 //!         let mainloop = mainloop.clone();
 //!         move |_| mainloop.quit()
-//!     });
+//!     }).expect("Failed to attach receiver");
 //!
 //!     // Every 100ms, send `"Hello"` to the main thread.
 //!     let timer = mainloop.loop_().add_timer(move |_| {
 //!         main_sender.send(String::from("Hello"));
-//!     });
+//!     }).expect("Failed to add timer");
 //!     timer.update_timer(
 //!         Some(Duration::from_millis(1)), // Send the first message immediately
 //!         Some(Duration::from_millis(100))
@@ -65,11 +68,16 @@
 
 use std::{
     collections::VecDeque,
+    future::poll_fn,
     os::unix::prelude::*,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll, Waker},
 };
 
-use crate::loop_::{IoSource, LoopRef};
+use crate::{
+    loop_::{IoSource, LoopRef},
+    Error,
+};
 use spa::support::system::IoFlags;
 
 /// A receiver that has not been attached to a loop.
@@ -83,10 +91,11 @@ impl<T: 'static> Receiver<T> {
     /// Attach the receiver to a loop with a callback.
     ///
     /// This will make the loop call the callback with any messages that get sent to the receiver.
-    #[must_use]
-    pub fn attach<F>(self, loop_: &LoopRef, callback: F) -> AttachedReceiver<T>
+    ///
+    /// Fails if the loop cannot watch the channel.
+    pub fn attach<F>(self, loop_: &LoopRef, mut callback: F) -> Result<AttachedReceiver<T>, Error>
     where
-        F: Fn(T) + 'static,
+        F: FnMut(T) + 'static,
     {
         let channel = self.channel.clone();
         let readfd = channel.lock().expect("Channel mutex lock poisoned").readfd;
@@ -99,13 +108,14 @@ impl<T: 'static> Receiver<T> {
             // Read from the pipe to make it block until written to again.
             let _ = nix::unistd::read(channel.readfd, &mut [0]);
 
-            channel.queue.drain(..).for_each(&callback);
-        });
+            channel.queue.drain(..).for_each(&mut callback);
+        })?;
+        iosource.set_name("channel receiver");
 
-        AttachedReceiver {
-            _source: iosource,
+        Ok(AttachedReceiver {
+            source: iosource,
             receiver: self,
-        }
+        })
     }
 }
 
@@ -116,7 +126,7 @@ pub struct AttachedReceiver<'l, T>
 where
     T: 'static,
 {
-    _source: IoSource<'l, RawFd>,
+    source: IoSource<'l, RawFd>,
     receiver: Receiver<T>,
 }
 
@@ -131,6 +141,12 @@ where
     pub fn deattach(self) -> Receiver<T> {
         self.receiver
     }
+
+    /// Set the name reported for the callback of the receiver, `"channel receiver"` by default,
+    /// see [`LoopRef::set_dispatch_observer`].
+    pub fn set_name(&self, name: &str) {
+        self.source.set_name(name);
+    }
 }
 
 /// A `Sender` can be used to send messages to its associated [`Receiver`].
@@ -219,3 +235,298 @@ where
         Receiver { channel },
     )
 }
+
+/// Shared state between the [`AsyncSender`]s and the [`AsyncReceiver`].
+struct AsyncChannel<T> {
+    queue: VecDeque<T>,
+    /// Waker of the task waiting for messages.
+    waker: Option<Waker>,
+    senders: usize,
+    receiver_dropped: bool,
+}
+
+/// Lock `channel`, even if a thread panicked while holding the lock,
+/// as no code panics while the channel is in an inconsistent state.
+fn lock_async<T>(channel: &Mutex<AsyncChannel<T>>) -> MutexGuard<'_, AsyncChannel<T>> {
+    channel.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Wake the task waiting for messages, once the lock of the channel has been released,
+/// as an executor may poll the task from `wake`.
+fn wake(waker: Option<Waker>) {
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+/// An `AsyncSender` sends messages to its [`AsyncReceiver`], without blocking.
+///
+/// It can be used from the callbacks of a loop, and be freely cloned.
+pub struct AsyncSender<T> {
+    channel: Arc<Mutex<AsyncChannel<T>>>,
+}
+
+impl<T> AsyncSender<T> {
+    /// Send a message to the associated receiver, waking the task awaiting it.
+    ///
+    /// Fails, returning the message, if the receiver was dropped.
+    pub fn send(&self, t: T) -> Result<(), T> {
+        let mut channel = lock_async(&self.channel);
+        if channel.receiver_dropped {
+            return Err(t);
+        }
+        channel.queue.push_back(t);
+        let waker = channel.waker.take();
+        drop(channel);
+
+        wake(waker);
+        Ok(())
+    }
+}
+
+impl<T> Clone for AsyncSender<T> {
+    fn clone(&self) -> Self {
+        lock_async(&self.channel).senders += 1;
+        Self {
+            channel: self.channel.clone(),
+        }
+    }
+}
+
+impl<T> Drop for AsyncSender<T> {
+    fn drop(&mut self) {
+        let mut channel = lock_async(&self.channel);
+        channel.senders -= 1;
+        // the receiver sees the end of the channel
+        let waker = if channel.senders == 0 {
+            channel.waker.take()
+        } else {
+            None
+        };
+        drop(channel);
+
+        wake(waker);
+    }
+}
+
+/// An `AsyncReceiver` receives the messages of its [`AsyncSender`]s from async code.
+///
+/// The channel ends, [`recv`](Self::recv) returning `None`, once all the senders are dropped and the
+/// remaining messages are received, such as when the objects holding the senders are dropped at the
+/// end of the thread running the loop.
+///
+/// With the `futures` feature, it implements the `Stream` trait of `futures-core`.
+pub struct AsyncReceiver<T> {
+    channel: Arc<Mutex<AsyncChannel<T>>>,
+}
+
+impl<T> AsyncReceiver<T> {
+    /// Receive the next message, or `None` once the channel ended.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Receive the next message if one is waiting, without waiting for one.
+    pub fn try_recv(&mut self) -> Option<T> {
+        lock_async(&self.channel).queue.pop_front()
+    }
+
+    /// Poll for the next message, registering the waker of `cx` to be woken when one is sent.
+    ///
+    /// Returns `Poll::Ready(None)` once the channel ended.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut channel = lock_async(&self.channel);
+        if let Some(t) = channel.queue.pop_front() {
+            return Poll::Ready(Some(t));
+        }
+        if channel.senders == 0 {
+            return Poll::Ready(None);
+        }
+        // registered while the lock is held, so no message can be sent in between
+        channel.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for AsyncReceiver<T> {
+    fn drop(&mut self) {
+        let mut channel = lock_async(&self.channel);
+        channel.receiver_dropped = true;
+        // the messages are dropped once the lock is released, as their drop may send other messages
+        let queue = std::mem::take(&mut channel.queue);
+        drop(channel);
+        drop(queue);
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<T> futures_core::Stream for AsyncReceiver<T> {
+    type Item = T;
+
+    fn poll_next(self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.get_mut().poll_recv(cx)
+    }
+}
+
+/// Create a channel whose messages are received by async code, such as the events of PipeWire objects
+/// sent from the callbacks of a loop.
+///
+/// ```no_run
+/// # async fn globals(registry: &pipewire::registry::Registry) {
+/// let (sender, mut receiver) = pipewire::channel::async_channel();
+/// let _listener = registry
+///     .add_listener_local()
+///     .global(move |global| {
+///         let _ = sender.send(global.id);
+///     })
+///     .register();
+///
+/// // usually awaited in another thread than the one of the loop
+/// while let Some(id) = receiver.recv().await {
+///     println!("global {id}");
+/// }
+/// # }
+/// ```
+pub fn async_channel<T>() -> (AsyncSender<T>, AsyncReceiver<T>) {
+    let channel = Arc::new(Mutex::new(AsyncChannel {
+        queue: VecDeque::new(),
+        waker: None,
+        senders: 1,
+        receiver_dropped: false,
+    }));
+
+    (
+        AsyncSender {
+            channel: channel.clone(),
+        },
+        AsyncReceiver { channel },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Future,
+        task::Wake,
+        thread::{self, Thread},
+    };
+
+    use super::*;
+
+    /// Wakes the thread blocked in [`block_on`].
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn async_channel_ends() {
+        let (sender, mut receiver) = async_channel();
+        let other = sender.clone();
+        sender.send(1).unwrap();
+        drop(sender);
+        other.send(2).unwrap();
+        assert_eq!(receiver.try_recv(), Some(1));
+
+        thread::spawn(move || other.send(3).unwrap());
+        assert_eq!(block_on(receiver.recv()), Some(2));
+        assert_eq!(block_on(receiver.recv()), Some(3));
+        assert_eq!(block_on(receiver.recv()), None);
+
+        let (sender, receiver) = async_channel();
+        drop(receiver);
+        assert_eq!(sender.send(1), Err(1));
+    }
+
+    /// Reads the channel when woken, as executors polling the task from `wake` do.
+    struct InlineWaker {
+        channel: Arc<Mutex<AsyncChannel<i32>>>,
+        queued: Mutex<Vec<usize>>,
+    }
+
+    impl Wake for InlineWaker {
+        fn wake(self: Arc<Self>) {
+            self.wake_by_ref();
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            let queued = lock_async(&self.channel).queue.len();
+            self.queued.lock().unwrap().push(queued);
+        }
+    }
+
+    #[test]
+    fn async_channel_wakes_unlocked() {
+        let (sender, mut receiver) = async_channel();
+        let inline = Arc::new(InlineWaker {
+            channel: receiver.channel.clone(),
+            queued: Mutex::new(Vec::new()),
+        });
+        let waker = Waker::from(inline.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert_eq!(receiver.poll_recv(&mut cx), Poll::Pending);
+        sender.send(1).unwrap();
+        assert_eq!(receiver.poll_recv(&mut cx), Poll::Ready(Some(1)));
+        assert_eq!(receiver.poll_recv(&mut cx), Poll::Pending);
+        drop(sender);
+        assert_eq!(receiver.poll_recv(&mut cx), Poll::Ready(None));
+        assert_eq!(*inline.queued.lock().unwrap(), [1, 0]);
+    }
+
+    #[test]
+    fn registry_globals() {
+        let (sender, mut receiver) = async_channel();
+
+        // the loop thread ends once the globals present at startup were announced
+        let pw_thread = thread::spawn(move || {
+            let mainloop = crate::main_loop::MainLoop::new(None).unwrap();
+            let context = crate::context::Context::new(&mainloop).unwrap();
+            let core = context.connect_self(None).unwrap();
+            let registry = core.get_registry().unwrap();
+            let _listener = registry
+                .add_listener_local()
+                .global(move |global| {
+                    let _ = sender.send((global.id, global.type_.clone()));
+                })
+                .register();
+
+            let pending = core.sync(0).unwrap();
+            let done = std::rc::Rc::new(std::cell::Cell::new(false));
+            let _core_listener = core
+                .add_listener_local()
+                .done({
+                    let done = done.clone();
+                    move |_, seq| done.set(done.get() || seq == pending)
+                })
+                .register();
+            mainloop.run_until(|_| done.get());
+        });
+
+        let globals = block_on(async {
+            let mut globals = Vec::new();
+            while let Some(global) = receiver.recv().await {
+                globals.push(global);
+            }
+            globals
+        });
+        pw_thread.join().unwrap();
+
+        assert!(globals.contains(&(crate::core::PW_ID_CORE, crate::types::ObjectType::Core)));
+    }
+}
@@ -13,12 +13,15 @@ use std::{fmt, mem};
 
 use crate::{
     permissions::Permission,
-    proxy::{Listener, Proxy, ProxyT},
+    proxy::{impl_listener, impl_transparent_proxy, Proxy, ProxyT},
+    thread_loop::LoopHandle,
     types::ObjectType,
+    Error,
 };
-use spa::spa_interface_call_method;
+use spa::{spa_interface_call_method, utils::result::SpaResult};
 
 #[derive(Debug)]
+#[repr(transparent)]
 pub struct Client {
     proxy: Proxy,
 }
@@ -44,6 +47,8 @@ impl ProxyT for Client {
     }
 }
 
+impl_transparent_proxy!(Client);
+
 impl Client {
     // TODO: add non-local version when we'll bind pw_thread_loop_start()
     #[must_use]
@@ -54,69 +59,86 @@ impl Client {
         }
     }
 
-    pub fn error(&self, id: u32, res: i32, message: &str) {
+    pub fn error(&self, id: u32, res: i32, message: &str) -> Result<(), Error> {
         let message = CString::new(message).expect("Null byte in message parameter");
         let message_cstr = message.as_c_str();
         Client::error_cstr(self, id, res, message_cstr)
     }
 
-    pub fn error_cstr(&self, id: u32, res: i32, message: &CStr) {
-        unsafe {
+    pub fn error_cstr(&self, id: u32, res: i32, message: &CStr) -> Result<(), Error> {
+        let res = unsafe {
             spa_interface_call_method!(
-                self.proxy.as_ptr(),
+                self.proxy.checked_ptr()?,
                 pw_sys::pw_client_methods,
                 error,
                 id,
                 res,
                 message.as_ptr() as *const _
-            );
+            )
         };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
     }
 
-    pub fn update_properties(&self, properties: &spa::utils::dict::DictRef) {
-        unsafe {
+    pub fn update_properties(&self, properties: &spa::utils::dict::DictRef) -> Result<(), Error> {
+        let res = unsafe {
             spa_interface_call_method!(
-                self.proxy.as_ptr(),
+                self.proxy.checked_ptr()?,
                 pw_sys::pw_client_methods,
                 update_properties,
                 properties.as_raw_ptr()
-            );
-        }
+            )
+        };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
     }
 
-    pub fn get_permissions(&self, index: u32, num: u32) {
-        unsafe {
+    pub fn get_permissions(&self, index: u32, num: u32) -> Result<(), Error> {
+        let res = unsafe {
             spa_interface_call_method!(
-                self.proxy.as_ptr(),
+                self.proxy.checked_ptr()?,
                 pw_sys::pw_client_methods,
                 get_permissions,
                 index,
                 num
-            );
-        }
+            )
+        };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
     }
 
-    pub fn update_permissions(&self, permissions: &[Permission]) {
-        unsafe {
+    pub fn update_permissions(&self, permissions: &[Permission]) -> Result<(), Error> {
+        let res = unsafe {
             spa_interface_call_method!(
-                self.proxy.as_ptr(),
+                self.proxy.checked_ptr()?,
                 pw_sys::pw_client_methods,
                 update_permissions,
                 permissions.len() as u32,
                 permissions.as_ptr().cast()
-            );
-        }
+            )
+        };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
     }
 }
 
 #[derive(Default)]
 struct ListenerLocalCallbacks {
     #[allow(clippy::type_complexity)]
-    info: Option<Box<dyn Fn(&ClientInfoRef)>>,
+    info: Option<Box<dyn FnMut(&ClientInfoRef)>>,
     #[allow(clippy::type_complexity)]
-    permissions: Option<Box<dyn Fn(u32, &[Permission])>>,
+    permissions: Option<Box<dyn FnMut(u32, &[Permission])>>,
 }
 
+/// Builder of a listener for the events of a [`Client`], see [`Client::add_listener_local`].
+///
+/// **Events emitted while one of the callbacks is running are lost.** The callbacks are never
+/// called re-entrantly: an event received when a callback iterates the loop, e.g. to wait for a
+/// reply, or emitted synchronously by a method it calls, is dropped without notice.
 pub struct ClientListenerLocalBuilder<'a> {
     client: &'a Client,
     cbs: ListenerLocalCallbacks,
@@ -138,8 +160,12 @@ impl ClientInfoRef {
         self.0.id
     }
 
+    /// What changed since the previous info event, e.g. [`ClientChangeMask::PROPS`]
+    /// after the properties of the client were updated.
+    ///
+    /// The first info event of a listener has all bits set.
     pub fn change_mask(&self) -> ClientChangeMask {
-        ClientChangeMask::from_bits(self.0.change_mask).expect("invalid change_mask")
+        ClientChangeMask::from_bits_retain(self.0.change_mask)
     }
 
     pub fn props(&self) -> Option<&spa::utils::dict::DictRef> {
@@ -158,6 +184,28 @@ impl fmt::Debug for ClientInfoRef {
     }
 }
 
+/// Serializes the info with the keys used by `pw-dump`.
+///
+/// The fields that are not flagged in the change mask are not valid and are serialized as `null`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for ClientInfoRef {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mask = self.change_mask();
+        let mut info = serializer.serialize_struct("ClientInfo", 3)?;
+        info.serialize_field("id", &self.id())?;
+        info.serialize_field("change-mask", &crate::utils::FlagNames(mask))?;
+        info.serialize_field(
+            "props",
+            &self
+                .props()
+                .filter(|_| mask.contains(ClientChangeMask::PROPS)),
+        )?;
+        info.end()
+    }
+}
+
 pub struct ClientInfo {
     ptr: ptr::NonNull<pw_sys::pw_client_info>,
 }
@@ -221,13 +269,15 @@ pub struct ClientListener {
     events: Pin<Box<pw_sys::pw_client_events>>,
     listener: Pin<Box<spa_sys::spa_hook>>,
     #[allow(dead_code)]
-    data: Box<ListenerLocalCallbacks>,
+    data: Box<crate::utils::Guarded<ListenerLocalCallbacks>>,
+    loop_: LoopHandle,
 }
 
-impl Listener for ClientListener {}
+impl_listener!(ClientListener);
 
 impl Drop for ClientListener {
     fn drop(&mut self) {
+        let _lock = self.loop_.lock();
         spa::utils::hook::remove(*self.listener);
     }
 }
@@ -236,15 +286,20 @@ impl<'a> ClientListenerLocalBuilder<'a> {
     #[must_use]
     pub fn info<F>(mut self, info: F) -> Self
     where
-        F: Fn(&ClientInfoRef) + 'static,
+        F: FnMut(&ClientInfoRef) + 'static,
     {
         self.cbs.info = Some(Box::new(info));
         self
     }
 
+    /// Called with the permissions of the client, starting at `index`.
+    ///
+    /// This is emitted in reply to [`Client::get_permissions`], and when the permissions
+    /// of the client are modified, e.g. by the session manager.
+    #[must_use]
     pub fn permissions<F>(mut self, permissions: F) -> Self
     where
-        F: Fn(u32, &[Permission]) + 'static,
+        F: FnMut(u32, &[Permission]) + 'static,
     {
         self.cbs.permissions = Some(Box::new(permissions));
         self
@@ -256,11 +311,17 @@ impl<'a> ClientListenerLocalBuilder<'a> {
             data: *mut c_void,
             info: *const pw_sys::pw_client_info,
         ) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            let info =
-                ptr::NonNull::new(info as *mut pw_sys::pw_client_info).expect("info is NULL");
-            let info = info.cast::<ClientInfoRef>().as_ref();
-            callbacks.info.as_ref().unwrap()(info);
+            crate::utils::catch_callback_panic("client info", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                let info =
+                    ptr::NonNull::new(info as *mut pw_sys::pw_client_info).expect("info is NULL");
+                let info = info.cast::<ClientInfoRef>().as_ref();
+                callbacks.info.as_mut().unwrap()(info);
+            })
         }
 
         unsafe extern "C" fn client_events_permissions(
@@ -269,11 +330,20 @@ impl<'a> ClientListenerLocalBuilder<'a> {
             n_permissions: u32,
             permissions: *const pw_sys::pw_permission,
         ) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            let permissions =
-                std::slice::from_raw_parts(permissions.cast(), n_permissions as usize);
-
-            callbacks.permissions.as_ref().unwrap()(index, permissions);
+            crate::utils::catch_callback_panic("client permissions", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                let permissions = if permissions.is_null() {
+                    &[]
+                } else {
+                    std::slice::from_raw_parts(permissions.cast(), n_permissions as usize)
+                };
+
+                callbacks.permissions.as_mut().unwrap()(index, permissions);
+            })
         }
 
         let e = unsafe {
@@ -293,18 +363,21 @@ impl<'a> ClientListenerLocalBuilder<'a> {
         let (listener, data) = unsafe {
             let client = &self.client.proxy.as_ptr();
 
-            let data = Box::into_raw(Box::new(self.cbs));
+            let data = Box::into_raw(Box::new(crate::utils::Guarded::new(self.cbs)));
             let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
             let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
 
-            spa_interface_call_method!(
-                client,
-                pw_sys::pw_client_methods,
-                add_listener,
-                listener_ptr.cast(),
-                e.as_ref().get_ref(),
-                data as *mut _
-            );
+            if self.client.proxy.is_connected() {
+                spa_interface_call_method!(
+                    client,
+                    pw_sys::pw_client_methods,
+                    add_listener,
+                    listener_ptr.cast(),
+                    e.as_ref().get_ref(),
+                    data as *mut _
+                );
+                spa::utils::hook::reset_on_remove(&mut *listener_ptr);
+            }
 
             (listener, Box::from_raw(data))
         };
@@ -313,6 +386,91 @@ impl<'a> ClientListenerLocalBuilder<'a> {
             events: e,
             listener,
             data,
+            loop_: self.client.proxy.loop_handle(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        context::Context, core::CoreRef, main_loop::MainLoop, permissions::PermissionFlags,
+        properties::properties,
+    };
+    use std::{cell::Cell, cell::RefCell, rc::Rc};
+
+    fn roundtrip(core: &CoreRef, mainloop: &MainLoop) {
+        let done = Rc::new(Cell::new(false));
+        let done_clone = done.clone();
+        let pending = core.sync(0).unwrap();
+        let _listener = core
+            .add_listener_local()
+            .done(move |id, seq| {
+                if id == crate::core::PW_ID_CORE && seq == pending {
+                    done_clone.set(true);
+                }
+            })
+            .register();
+        mainloop.run_until(|_| done.get());
+    }
+
+    #[test]
+    fn change_mask_and_permissions() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context
+            .connect_self(Some(properties! {
+                "application.name" => "pipewire-rs.test.client",
+            }))
+            .unwrap();
+        let registry = core.get_registry().unwrap();
+
+        let globals = registry.snapshot(&core, &mainloop).unwrap();
+        let global = globals
+            .iter()
+            .find(|global| {
+                global.type_ == ObjectType::Client
+                    && global
+                        .props
+                        .as_ref()
+                        .and_then(|props| props.get("application.name"))
+                        == Some("pipewire-rs.test.client")
+            })
+            .expect("own client not announced");
+        let client: Client = registry.bind(global).unwrap();
+
+        let masks = Rc::new(RefCell::new(Vec::new()));
+        let masks_clone = masks.clone();
+        let permissions = Rc::new(RefCell::new(Vec::new()));
+        let permissions_clone = permissions.clone();
+        let _listener = client
+            .add_listener_local()
+            .info(move |info| masks_clone.borrow_mut().push(info.change_mask()))
+            .permissions(move |index, permissions| {
+                assert_eq!(index, 0);
+                permissions_clone
+                    .borrow_mut()
+                    .extend_from_slice(permissions);
+            })
+            .register();
+
+        roundtrip(&core, &mainloop);
+        assert_eq!(*masks.borrow(), vec![ClientChangeMask::all()]);
+
+        client
+            .update_properties(properties! { "pipewire-rs.test" => "1" }.dict())
+            .unwrap();
+        roundtrip(&core, &mainloop);
+        assert_eq!(masks.borrow().last(), Some(&ClientChangeMask::PROPS));
+        assert_eq!(masks.borrow().len(), 2);
+
+        client.get_permissions(0, u32::MAX).unwrap();
+        roundtrip(&core, &mainloop);
+        assert!(!permissions.borrow().is_empty());
+        assert!(permissions
+            .borrow()
+            .iter()
+            .all(|permission| permission.permission_flags() != PermissionFlags::empty()));
+    }
+}
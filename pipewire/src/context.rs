@@ -2,17 +2,22 @@
 // SPDX-License-Identifier: MIT
 
 use std::{
-    fmt,
+    any::Any,
+    ffi::CString,
+    fmt, mem,
     ops::Deref,
     os::unix::prelude::{IntoRawFd, OwnedFd},
     ptr,
     rc::Rc,
+    sync::{atomic::AtomicPtr, Arc},
 };
 
-use crate::core::Core;
+use crate::core::{Core, CORE_USER_DATA_SIZE};
 use crate::error::Error;
+use crate::impl_module::ImplModule;
 use crate::loop_::{IsLoopRc, LoopRef};
 use crate::properties::{Properties, PropertiesRef};
+use crate::thread_loop::ThreadLoop;
 
 #[repr(transparent)]
 pub struct ContextRef(pw_sys::pw_context);
@@ -26,6 +31,7 @@ impl ContextRef {
         std::ptr::addr_of!(self.0).cast_mut()
     }
 
+    /// Get the properties of the context.
     pub fn properties(&self) -> &PropertiesRef {
         unsafe {
             let props = pw_sys::pw_context_get_properties(self.as_raw_ptr());
@@ -33,13 +39,113 @@ impl ContextRef {
             props.cast().as_ref()
         }
     }
-    pub fn update_properties(&self, properties: &spa::utils::dict::DictRef) {
+
+    /// Update the properties of the context with the entries of `properties`.
+    ///
+    /// Entries with a `NULL` value are removed.
+    /// Returns the number of properties that were changed.
+    pub fn update_properties(&self, properties: &spa::utils::dict::DictRef) -> u32 {
+        let changed = unsafe {
+            pw_sys::pw_context_update_properties(self.as_raw_ptr(), properties.as_raw_ptr())
+        };
+
+        changed.try_into().unwrap_or(0)
+    }
+
+    /// Get the raw SPA JSON text of the `section` of the config of the context,
+    /// e.g. `context.properties`, or `None` if no config file defines it.
+    ///
+    /// When drop-in files of `pipewire.conf.d` define the section too, the text of each of them
+    /// follows the one of the main file, separated by a newline.
+    /// The text can be parsed with [`JsonValue`](spa::json::JsonValue).
+    ///
+    /// # Panics
+    /// If `section` contains a null byte.
+    #[cfg(feature = "v0_3_45")]
+    pub fn conf_section(&self, section: &str) -> Option<String> {
+        unsafe extern "C" fn collect(
+            data: *mut std::os::raw::c_void,
+            _location: *const std::os::raw::c_char,
+            _section: *const std::os::raw::c_char,
+            str_: *const std::os::raw::c_char,
+            len: usize,
+        ) -> i32 {
+            let fragments = &mut *(data as *mut Vec<String>);
+            let bytes = std::slice::from_raw_parts(str_.cast::<u8>(), len);
+            fragments.push(String::from_utf8_lossy(bytes).into_owned());
+            0
+        }
+
+        let section = CString::new(section).expect("Null byte in section parameter");
+        let mut fragments: Vec<String> = Vec::new();
         unsafe {
-            pw_sys::pw_context_update_properties(self.as_raw_ptr(), properties.as_raw_ptr());
+            pw_sys::pw_context_conf_section_for_each(
+                self.as_raw_ptr(),
+                section.as_ptr(),
+                Some(collect),
+                &mut fragments as *mut _ as *mut _,
+            );
+        }
+
+        (!fragments.is_empty()).then(|| fragments.join("\n"))
+    }
+
+    /// Update `properties` with the entries of the `section` of the config of the context,
+    /// including drop-in files, like C modules read their config.
+    ///
+    /// Returns the number of properties that were changed, which is 0 if the section does not exist.
+    ///
+    /// # Panics
+    /// If `section` contains a null byte.
+    #[cfg(feature = "v0_3_45")]
+    pub fn conf_update_props(&self, section: &str, properties: &mut Properties) -> u32 {
+        let section = CString::new(section).expect("Null byte in section parameter");
+        let changed = unsafe {
+            pw_sys::pw_context_conf_update_props(
+                self.as_raw_ptr(),
+                section.as_ptr(),
+                properties.as_raw_ptr(),
+            )
+        };
+
+        changed.try_into().unwrap_or(0)
+    }
+
+    /// Get the thread utilities of the context.
+    ///
+    /// They are provided by `libpipewire-module-rt` when it is loaded, acquiring realtime
+    /// through RTKit or the realtime portal if needed, or are the
+    /// [global](crate::thread::ThreadUtils::global) ones otherwise.
+    #[cfg(feature = "v0_3_32")]
+    pub fn thread_utils(&self) -> crate::thread::ThreadUtils<'_> {
+        // SPA_TYPE_INTERFACE_ThreadUtils
+        let object = unsafe {
+            pw_sys::pw_context_get_object(
+                self.as_raw_ptr(),
+                c"Spa:Pointer:Interface:ThreadUtils".as_ptr(),
+            )
+        };
+
+        match ptr::NonNull::new(object.cast()) {
+            Some(ptr) => unsafe { crate::thread::ThreadUtils::from_raw(ptr) },
+            None => crate::thread::ThreadUtils::global(),
         }
     }
 }
 
+/// The PipeWire instance to connect to with [`Context::connect_to`].
+#[derive(Debug)]
+pub enum Remote<'a> {
+    /// The remote used by [`Context::connect`], from the `remote.name` property,
+    /// the `PIPEWIRE_REMOTE` environment variable or the default socket.
+    Default,
+    /// The socket with this name in the runtime directory, such as `pipewire-0-manager`,
+    /// or an absolute path to a socket.
+    Name(&'a str),
+    /// An already connected socket, as with [`Context::connect_fd`].
+    Fd(OwnedFd),
+}
+
 #[derive(Clone, Debug)]
 pub struct Context {
     inner: Rc<ContextInner>,
@@ -62,12 +168,27 @@ impl fmt::Debug for ContextInner {
 
 impl Context {
     fn new_internal<T: IsLoopRc>(loop_: &T, properties: Option<Properties>) -> Result<Self, Error> {
+        // kept alive by the thread loop, which the context keeps alive
+        let thread_loop = (loop_ as &dyn Any)
+            .downcast_ref::<ThreadLoop>()
+            .map_or(ptr::null(), |thread_loop| {
+                Arc::as_ptr(thread_loop.shared_ptr())
+            });
         let loop_: Box<dyn AsRef<LoopRef>> = Box::new(loop_.clone());
         let props = properties.map_or(ptr::null(), |props| props.into_raw()) as *mut _;
         let context = unsafe {
-            pw_sys::pw_context_new((*loop_).as_ref().as_raw() as *const _ as *mut _, props, 0)
+            pw_sys::pw_context_new(
+                (*loop_).as_ref().as_raw() as *const _ as *mut _,
+                props,
+                mem::size_of::<*const AtomicPtr<pw_sys::pw_thread_loop>>(),
+            )
         };
         let context = ptr::NonNull::new(context).ok_or(Error::CreationFailed)?;
+        unsafe {
+            pw_sys::pw_context_get_user_data(context.as_ptr())
+                .cast::<*const AtomicPtr<pw_sys::pw_thread_loop>>()
+                .write(thread_loop);
+        }
 
         Ok(Context {
             inner: Rc::new(ContextInner {
@@ -77,36 +198,138 @@ impl Context {
         })
     }
 
+    /// Create a context running on `loop_`, configured from the default `client.conf`.
     pub fn new<T: IsLoopRc>(loop_: &T) -> Result<Self, Error> {
         Self::new_internal(loop_, None)
     }
 
+    /// Create a context running on `loop_` with the given properties, which are then available
+    /// with [`properties`](ContextRef::properties).
+    ///
+    /// The properties override the `context.properties` of the configuration file, such as
+    /// `log.level` or `mem.allow-mlock`, and `config.name` selects the configuration file.
+    /// As with `pw_context_new()`, the context takes ownership of the properties.
     pub fn with_properties<T: IsLoopRc>(loop_: &T, properties: Properties) -> Result<Self, Error> {
         Self::new_internal(loop_, Some(properties))
     }
 
+    /// Connect to a PipeWire instance.
+    ///
+    /// The remote to connect to is resolved from the `remote.name` property,
+    /// falling back to the `PIPEWIRE_REMOTE` environment variable and then to the default socket.
+    ///
+    /// This fails when no server is listening on the socket. The context can connect again,
+    /// both after such a failure and after a previous core lost its connection, so reconnecting
+    /// after the daemon restarted reuses the same context and loop,
+    /// see [`ListenerLocalBuilder::disconnected`](crate::core::ListenerLocalBuilder::disconnected)
+    /// and [`ReconnectingCore`](crate::reconnect::ReconnectingCore).
     pub fn connect(&self, properties: Option<Properties>) -> Result<Core, Error> {
         let properties = properties.map_or(ptr::null_mut(), |p| p.into_raw());
 
         unsafe {
-            let core = pw_sys::pw_context_connect(self.as_raw_ptr(), properties, 0);
-            let ptr = ptr::NonNull::new(core).ok_or(Error::CreationFailed)?;
+            let core =
+                pw_sys::pw_context_connect(self.as_raw_ptr(), properties, CORE_USER_DATA_SIZE);
+            let ptr = ptr::NonNull::new(core).ok_or_else(Error::last_os_error)?;
 
             Ok(Core::from_ptr(ptr, self.clone()))
         }
     }
 
+    /// Connect to the PipeWire instance selected by `remote`.
+    ///
+    /// A context can be connected several times, for example to both the regular socket and
+    /// the `pipewire-0-manager` socket of the daemon. Each [`Core`] has its own proxies and
+    /// listeners, and all of them are dispatched by the loop of the context.
+    ///
+    /// With [`Remote::Name`], the name is set as the `remote.name` property,
+    /// overriding the one in `properties`.
+    ///
+    /// # Panics
+    /// If the name of a [`Remote::Name`] contains a null byte.
+    pub fn connect_to(
+        &self,
+        remote: Remote<'_>,
+        properties: Option<Properties>,
+    ) -> Result<Core, Error> {
+        match remote {
+            Remote::Default => self.connect(properties),
+            Remote::Name(name) => {
+                let mut properties = properties.unwrap_or_default();
+                properties.insert(*crate::keys::REMOTE_NAME, name);
+                self.connect(Some(properties))
+            }
+            Remote::Fd(fd) => self.connect_fd(fd, properties),
+        }
+    }
+
+    /// Connect to a PipeWire instance on an already connected socket.
+    ///
+    /// Ownership of `fd` is transferred to PipeWire, which closes it on disconnect or error.
     pub fn connect_fd(&self, fd: OwnedFd, properties: Option<Properties>) -> Result<Core, Error> {
         let properties = properties.map_or(ptr::null_mut(), |p| p.into_raw());
 
         unsafe {
             let raw_fd = fd.into_raw_fd();
-            let core = pw_sys::pw_context_connect_fd(self.as_raw_ptr(), raw_fd, properties, 0);
-            let ptr = ptr::NonNull::new(core).ok_or(Error::CreationFailed)?;
+            let core = pw_sys::pw_context_connect_fd(
+                self.as_raw_ptr(),
+                raw_fd,
+                properties,
+                CORE_USER_DATA_SIZE,
+            );
+            let ptr = ptr::NonNull::new(core).ok_or_else(Error::last_os_error)?;
+
+            Ok(Core::from_ptr(ptr, self.clone()))
+        }
+    }
+
+    /// Connect to the PipeWire instance running in this context.
+    ///
+    /// This is only useful when the context itself acts as a server, for example
+    /// after loading the modules of a PipeWire daemon into it.
+    pub fn connect_self(&self, properties: Option<Properties>) -> Result<Core, Error> {
+        let properties = properties.map_or(ptr::null_mut(), |p| p.into_raw());
+
+        unsafe {
+            let core =
+                pw_sys::pw_context_connect_self(self.as_raw_ptr(), properties, CORE_USER_DATA_SIZE);
+            let ptr = ptr::NonNull::new(core).ok_or_else(Error::last_os_error)?;
 
             Ok(Core::from_ptr(ptr, self.clone()))
         }
     }
+
+    /// Load a module into the context.
+    ///
+    /// `name` is the name of the module, for example `libpipewire-module-loopback`.
+    /// The optional `args` are passed to the module as is, they usually use the SPA JSON syntax
+    /// and can be built with [`JsonValue`](spa::json::JsonValue).
+    ///
+    /// The module is unloaded again when the returned [`ImplModule`] is dropped.
+    ///
+    /// # Panics
+    /// If `name` or `args` contain a null byte.
+    pub fn load_module(
+        &self,
+        name: &str,
+        args: Option<&str>,
+        properties: Option<Properties>,
+    ) -> Result<ImplModule, Error> {
+        let name = CString::new(name).expect("Null byte in name parameter");
+        let args = args.map(|args| CString::new(args).expect("Null byte in args parameter"));
+        let properties = properties.map_or(ptr::null_mut(), |p| p.into_raw());
+
+        unsafe {
+            let module = pw_sys::pw_context_load_module(
+                self.as_raw_ptr(),
+                name.as_ptr(),
+                args.as_ref().map_or(ptr::null(), |args| args.as_ptr()),
+                properties,
+            );
+            let ptr = ptr::NonNull::new(module).ok_or_else(Error::last_os_error)?;
+
+            Ok(ImplModule::from_ptr(ptr, self.clone()))
+        }
+    }
 }
 
 impl std::convert::AsRef<ContextRef> for Context {
@@ -137,8 +360,157 @@ impl std::ops::Deref for ContextInner {
     }
 }
 
+/// The thread loop running `context`, if it was created with a [`ThreadLoop`].
+///
+/// # Safety
+/// `context` must be a valid context created by [`Context::new`] or [`Context::with_properties`].
+pub(crate) unsafe fn thread_loop_of(
+    context: *mut pw_sys::pw_context,
+) -> Option<Arc<AtomicPtr<pw_sys::pw_thread_loop>>> {
+    let thread_loop = *pw_sys::pw_context_get_user_data(context)
+        .cast::<*const AtomicPtr<pw_sys::pw_thread_loop>>();
+    if thread_loop.is_null() {
+        return None;
+    }
+    Arc::increment_strong_count(thread_loop);
+    Some(Arc::from_raw(thread_loop))
+}
+
 impl Drop for ContextInner {
     fn drop(&mut self) {
         unsafe { pw_sys::pw_context_destroy(self.ptr.as_ptr()) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::main_loop::MainLoop;
+
+    #[test]
+    fn with_properties() {
+        let mainloop = MainLoop::new(Some(
+            crate::properties::properties! {
+                "loop.name" => "pipewire-rs-test-loop",
+            }
+            .dict(),
+        ))
+        .unwrap();
+        let context = Context::with_properties(
+            &mainloop,
+            crate::properties::properties! {
+                "pipewire-rs.test" => "1",
+                "config.name" => "client.conf",
+            },
+        )
+        .unwrap();
+        assert_eq!(context.properties().get("pipewire-rs.test"), Some("1"));
+        assert_eq!(context.properties().get("config.name"), Some("client.conf"));
+    }
+
+    #[test]
+    fn update_properties() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        assert_eq!(context.properties().get("pipewire-rs.test"), None);
+
+        let props = crate::properties::properties! {
+            "pipewire-rs.test" => "1"
+        };
+        assert_eq!(context.update_properties(props.dict()), 1);
+        assert_eq!(context.properties().get("pipewire-rs.test"), Some("1"));
+
+        // Setting the same value again does not change anything.
+        assert_eq!(context.update_properties(props.dict()), 0);
+
+        let props = crate::properties::properties! {
+            "pipewire-rs.test" => "2"
+        };
+        assert_eq!(context.update_properties(props.dict()), 1);
+        assert_eq!(context.properties().get("pipewire-rs.test"), Some("2"));
+    }
+
+    #[test]
+    #[cfg(feature = "v0_3_45")]
+    fn missing_conf_section() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+
+        assert_eq!(context.conf_section("pipewire-rs.missing"), None);
+
+        let mut props = crate::properties::properties! {
+            "pipewire-rs.test" => "1"
+        };
+        assert_eq!(
+            context.conf_update_props("pipewire-rs.missing", &mut props),
+            0
+        );
+        assert_eq!(props.get("pipewire-rs.test"), Some("1"));
+    }
+
+    #[test]
+    fn standalone_loop() {
+        let loop_ = crate::loop_::Loop::new(None).unwrap();
+        let _context = Context::new(&loop_).unwrap();
+
+        let fired = Rc::new(std::cell::Cell::new(false));
+        let timer = loop_
+            .add_timer({
+                let fired = fired.clone();
+                move |_| fired.set(true)
+            })
+            .unwrap();
+        timer
+            .update_timer(Some(std::time::Duration::from_millis(1)), None)
+            .into_result()
+            .unwrap();
+
+        while !fired.get() {
+            loop_.iterate(std::time::Duration::from_millis(100));
+        }
+    }
+
+    #[test]
+    fn connect_to_missing_socket() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+
+        let res = context.connect_to(Remote::Name("pipewire-rs-test-does-not-exist"), None);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn several_cores() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let cores = [
+            context.connect_self(None).unwrap(),
+            context.connect_self(None).unwrap(),
+        ];
+
+        // the done events of each core only reach its own listener
+        let done = Rc::new(std::cell::RefCell::new(Vec::new()));
+        let _listeners: Vec<_> = cores
+            .iter()
+            .enumerate()
+            .map(|(i, core)| {
+                let done = done.clone();
+                core.add_listener_local()
+                    .done(move |_, seq| done.borrow_mut().push((i, seq)))
+                    .register()
+            })
+            .collect();
+        let seqs = [cores[0].sync(0).unwrap(), cores[1].sync(0).unwrap()];
+        mainloop.run_until(|_| done.borrow().len() >= 2);
+
+        let mut done = done.borrow().clone();
+        done.sort_by_key(|(i, _)| *i);
+        assert_eq!(done, [(0, seqs[0]), (1, seqs[1])]);
+
+        // an fd connection lives next to the others
+        let (fd, _peer) = std::os::unix::net::UnixStream::pair().unwrap();
+        let core = context.connect_to(Remote::Fd(fd.into()), None).unwrap();
+        drop(core);
+        assert!(cores[0].sync(0).is_ok());
+    }
+}
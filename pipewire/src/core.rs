@@ -3,19 +3,28 @@
 
 use bitflags::bitflags;
 use libc::{c_char, c_void};
+use nix::errno::Errno;
 use std::{
+    borrow::Cow,
+    cell::Cell,
     ffi::{CStr, CString},
+    os::fd::{BorrowedFd, FromRawFd, OwnedFd},
     rc::Rc,
 };
 use std::{fmt, mem, ptr};
 use std::{ops::Deref, pin::Pin};
 
 use crate::{
-    proxy::{Proxy, ProxyT},
+    main_loop::MainLoop,
+    mem::MemFlags,
+    proxy::{impl_listener, PendingProxy, Proxy, ProxyT},
     registry::Registry,
+    stream::StreamBuffers,
+    thread_loop::LoopHandle,
     Error,
 };
 use spa::{
+    buffer::DataType,
     spa_interface_call_method,
     utils::result::{AsyncSeq, SpaResult},
 };
@@ -58,6 +67,21 @@ impl CoreRef {
         Ok(Registry::new(registry))
     }
 
+    /// Steal the file descriptor of the connection to the server.
+    ///
+    /// The connection is closed on the side of the core, which stops using the file descriptor,
+    /// so that it can be handed over, such as to another process.
+    ///
+    /// # Safety
+    /// The core and the proxies created from it must not be used anymore, other than to drop them
+    /// or to [`disconnect`](Core::disconnect) the core.
+    pub unsafe fn steal_fd(&self) -> Result<OwnedFd, Error> {
+        let fd = pw_sys::pw_core_steal_fd(self.as_raw_ptr());
+        let fd = SpaResult::from_c(fd).into_sync_result()?;
+
+        Ok(OwnedFd::from_raw_fd(fd))
+    }
+
     pub fn sync(&self, seq: i32) -> Result<AsyncSeq, Error> {
         let res = unsafe {
             spa_interface_call_method!(
@@ -73,6 +97,36 @@ impl CoreRef {
         Ok(res)
     }
 
+    /// Run `main_loop` until the remote has processed the methods called before, using [`sync`](Self::sync).
+    ///
+    /// Fails with `EDEADLK` when called from a callback of a loop, as the loop cannot be run recursively,
+    /// and with `ECANCELED` if `main_loop` is quit before the roundtrip completed.
+    pub(crate) fn roundtrip(&self, main_loop: &MainLoop) -> Result<(), Error> {
+        if crate::utils::in_loop() {
+            return Err(Errno::EDEADLK.into());
+        }
+
+        let done = Rc::new(Cell::new(false));
+        let pending = self.sync(0)?;
+        let _listener = self
+            .add_listener_local()
+            .done({
+                let done = done.clone();
+                move |id, seq| {
+                    if id == PW_ID_CORE && seq == pending {
+                        done.set(true);
+                    }
+                }
+            })
+            .register();
+
+        main_loop.run_until(|_| done.get());
+        if !done.get() {
+            return Err(Errno::ECANCELED.into());
+        }
+        Ok(())
+    }
+
     /// Create a new object on the PipeWire server from a factory.
     ///
     /// You will need specify what type you are expecting to be constructed by either using type inference or the
@@ -147,7 +201,9 @@ impl CoreRef {
                 create_object,
                 factory_name.as_ptr(),
                 type_str.as_ptr(),
-                type_.client_version(),
+                type_
+                    .client_version()
+                    .expect("proxy types have a known version"),
                 properties.as_ref().as_raw_ptr(),
                 0
             )
@@ -155,7 +211,23 @@ impl CoreRef {
 
         let ptr = ptr::NonNull::new(res.cast()).ok_or(Error::CreationFailed)?;
 
-        Proxy::new(ptr).downcast().map_err(|(_, e)| e)
+        Proxy::new(ptr)
+            .downcast()
+            .map_err(|_| Error::WrongProxyType)
+    }
+
+    /// Create an object like [`create_object`](Self::create_object), tracking whether it gets bound.
+    ///
+    /// Errors creating the object, such as invalid properties for the factory, are reported
+    /// asynchronously by the server. Use [`PendingProxy::wait_bound`] to find out the outcome,
+    /// or the id of the new global.
+    pub fn create_object_pending<P: ProxyT>(
+        &self,
+        factory_name: &str,
+        properties: &impl AsRef<spa::utils::dict::DictRef>,
+    ) -> Result<PendingProxy<P>, Error> {
+        self.create_object(factory_name, properties)
+            .map(PendingProxy::new)
     }
 
     /// Destroy the object on the remote server represented by the provided proxy.
@@ -182,7 +254,9 @@ pub struct Core {
 }
 
 impl Core {
-    pub(crate) fn from_ptr(
+    /// # Safety
+    /// `ptr` must be a core connected with [`CORE_USER_DATA_SIZE`].
+    pub(crate) unsafe fn from_ptr(
         ptr: ptr::NonNull<pw_sys::pw_core>,
         _context: crate::context::Context,
     ) -> Self {
@@ -191,6 +265,27 @@ impl Core {
             inner: Rc::new(inner),
         }
     }
+
+    /// Disconnect from the server and destroy the core.
+    ///
+    /// The proxies created from the core are disconnected as well: their methods fail with `EPIPE`
+    /// and their listeners are not called anymore, see [`Proxy::is_connected`].
+    /// They and the listeners of the core can still be dropped afterwards.
+    ///
+    /// This fails with `EBUSY` if other clones of the core are alive, including the ones held by
+    /// [`Stream`](crate::stream::Stream)s. The core is then only disconnected once all of them are dropped.
+    pub fn disconnect(self) -> Result<(), Error> {
+        let inner = Rc::try_unwrap(self.inner).map_err(|_| Errno::EBUSY)?;
+        let ptr = inner.ptr;
+        // the context stays alive until the core is disconnected
+        let context = inner._context.clone();
+        drop(inner);
+        let res = unsafe { pw_sys::pw_core_disconnect(ptr.as_ptr()) };
+        drop(context);
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
+    }
 }
 
 impl Deref for Core {
@@ -210,25 +305,103 @@ impl AsRef<CoreRef> for Core {
 #[derive(Debug)]
 struct CoreInner {
     ptr: ptr::NonNull<pw_sys::pw_core>,
+    /// Pointed to by the user data of the core.
+    _stream_buffers: Box<StreamBuffers>,
     _context: crate::context::Context,
 }
 
 impl CoreInner {
-    fn from_ptr(ptr: ptr::NonNull<pw_sys::pw_core>, _context: crate::context::Context) -> Self {
-        Self { ptr, _context }
+    /// # Safety
+    /// `ptr` must be a core connected with [`CORE_USER_DATA_SIZE`].
+    unsafe fn from_ptr(
+        ptr: ptr::NonNull<pw_sys::pw_core>,
+        _context: crate::context::Context,
+    ) -> Self {
+        let stream_buffers = Box::<StreamBuffers>::default();
+        user_data(ptr.as_ptr()).write(&*stream_buffers);
+        Self {
+            ptr,
+            _stream_buffers: stream_buffers,
+            _context,
+        }
+    }
+}
+
+impl Drop for CoreInner {
+    fn drop(&mut self) {
+        // the core is only destroyed along with its context, or when disconnected
+        unsafe { user_data(self.ptr.as_ptr()).write(ptr::null()) };
+    }
+}
+
+/// The size of the user data of the cores connected by a [`Context`](crate::context::Context).
+pub(crate) const CORE_USER_DATA_SIZE: usize = mem::size_of::<*const StreamBuffers>();
+
+unsafe fn user_data(core: *mut pw_sys::pw_core) -> *mut *const StreamBuffers {
+    pw_sys::pw_core_get_user_data(core).cast()
+}
+
+/// The buffers of the streams of `core`, or `None` once its [`Core`] was dropped.
+///
+/// # Safety
+/// `core` must be a valid core connected by a [`Context`](crate::context::Context),
+/// and the returned reference must not outlive its [`Core`].
+pub(crate) unsafe fn stream_buffers<'a>(core: *mut pw_sys::pw_core) -> Option<&'a StreamBuffers> {
+    user_data(core).read().as_ref()
+}
+
+/// An error reported by the server, see [`ListenerLocalBuilder::core_error`].
+///
+/// Errors about proxies are reported with the id of the proxy, and errors about the connection
+/// itself with [`PW_ID_CORE`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("{message} (id {id}, {errno})")]
+pub struct CoreError {
+    /// Id of the object the error is about.
+    pub id: u32,
+    /// Sequence number of the method call which failed.
+    pub seq: i32,
+    pub errno: Errno,
+    pub message: String,
+}
+
+impl CoreError {
+    /// Build an error from the arguments of the `error` event, where `res` is a negative errno.
+    pub fn new(id: u32, seq: i32, res: i32, message: &str) -> Self {
+        Self {
+            id,
+            seq,
+            errno: Errno::from_i32(-res),
+            message: message.to_owned(),
+        }
+    }
+
+    /// Whether the error reports that the connection to the server was lost,
+    /// see [`ListenerLocalBuilder::disconnected`].
+    pub fn is_disconnected(&self) -> bool {
+        self.id == PW_ID_CORE && self.errno == Errno::EPIPE
     }
 }
 
 #[derive(Default)]
 struct ListenerLocalCallbacks {
     #[allow(clippy::type_complexity)]
-    info: Option<Box<dyn Fn(&Info)>>,
-    done: Option<Box<dyn Fn(u32, AsyncSeq)>>,
+    info: Option<Box<dyn FnMut(&CoreInfoRef)>>,
+    done: Option<Box<dyn FnMut(u32, AsyncSeq)>>,
+    #[allow(clippy::type_complexity)]
+    error: Option<Box<dyn FnMut(u32, i32, i32, &str)>>,
     #[allow(clippy::type_complexity)]
-    error: Option<Box<dyn Fn(u32, i32, i32, &str)>>, // TODO: return a proper Error enum?
-                                                     // TODO: ping, remove_id, bound_id, add_mem, remove_mem
+    add_mem: Option<Box<dyn FnMut(u32, DataType, BorrowedFd, MemFlags)>>,
+    remove_mem: Option<Box<dyn FnMut(u32)>>,
+    disconnected: Option<Box<dyn FnMut()>>,
+    // TODO: ping, remove_id, bound_id
 }
 
+/// Builder of a listener for the events of a [`CoreRef`], see [`CoreRef::add_listener_local`].
+///
+/// **Events emitted while one of the callbacks is running are lost.** The callbacks are never
+/// called re-entrantly: an event received when a callback iterates the loop, e.g. to wait for a
+/// reply, or emitted synchronously by a method it calls, is dropped without notice.
 pub struct ListenerLocalBuilder<'a> {
     core: &'a CoreRef,
     cbs: ListenerLocalCallbacks,
@@ -240,7 +413,8 @@ pub struct Listener {
     events: Pin<Box<pw_sys::pw_core_events>>,
     listener: Pin<Box<spa_sys::spa_hook>>,
     #[allow(dead_code)]
-    data: Box<ListenerLocalCallbacks>,
+    data: Box<crate::utils::Guarded<ListenerLocalCallbacks>>,
+    loop_: LoopHandle,
 }
 
 impl Listener {
@@ -249,8 +423,11 @@ impl Listener {
     }
 }
 
+impl_listener!(Listener);
+
 impl Drop for Listener {
     fn drop(&mut self) {
+        let _lock = self.loop_.lock();
         spa::utils::hook::remove(*self.listener);
     }
 }
@@ -259,44 +436,116 @@ impl<'a> ListenerLocalBuilder<'a> {
     #[must_use]
     pub fn info<F>(mut self, info: F) -> Self
     where
-        F: Fn(&Info) + 'static,
+        F: FnMut(&CoreInfoRef) + 'static,
     {
         self.cbs.info = Some(Box::new(info));
         self
     }
 
+    /// Set the callback of the `done` event, called with the id of the object and the sequence
+    /// number passed to [`sync`](CoreRef::sync).
+    ///
+    /// The sequence number compares equal to the one returned by `sync`.
     #[must_use]
     pub fn done<F>(mut self, done: F) -> Self
     where
-        F: Fn(u32, AsyncSeq) + 'static,
+        F: FnMut(u32, AsyncSeq) + 'static,
     {
         self.cbs.done = Some(Box::new(done));
         self
     }
 
+    /// Set the callback of the `error` event, called with the id of the object, the sequence
+    /// number of the failed call, a negative errno and an error message.
     #[must_use]
     pub fn error<F>(mut self, error: F) -> Self
     where
-        F: Fn(u32, i32, i32, &str) + 'static,
+        F: FnMut(u32, i32, i32, &str) + 'static,
     {
         self.cbs.error = Some(Box::new(error));
         self
     }
 
+    /// Set the callback of the `error` event, with the error as a [`CoreError`].
+    ///
+    /// This replaces the callback set with [`error`](Self::error).
+    #[must_use]
+    pub fn core_error<F>(self, mut error: F) -> Self
+    where
+        F: FnMut(CoreError) + 'static,
+    {
+        self.error(move |id, seq, res, message| error(CoreError::new(id, seq, res, message)))
+    }
+
+    /// Called when the server shares a memory block with the client, before buffers refer to it
+    /// by `id` in their [`DataType::MemId`] data.
+    ///
+    /// The fd is only valid during the callback, it has to be duplicated to be kept,
+    /// see [`MemBlocks`](crate::mem::MemBlocks).
+    #[must_use]
+    pub fn add_mem<F>(mut self, add_mem: F) -> Self
+    where
+        F: FnMut(u32, DataType, BorrowedFd, MemFlags) + 'static,
+    {
+        self.cbs.add_mem = Some(Box::new(add_mem));
+        self
+    }
+
+    /// Called when the memory block `id` is no longer used.
+    #[must_use]
+    pub fn remove_mem<F>(mut self, remove_mem: F) -> Self
+    where
+        F: FnMut(u32) + 'static,
+    {
+        self.cbs.remove_mem = Some(Box::new(remove_mem));
+        self
+    }
+
+    /// Called when the connection to the server is lost, for example when the daemon exits.
+    ///
+    /// The server can not be reached through the core anymore: the core should be disconnected
+    /// with [`Core::disconnect`] once its proxies, listeners and other clones are dropped, which
+    /// can happen in any order, and a new core connected with
+    /// [`Context::connect`](crate::context::Context::connect) on the same context.
+    /// The core must not be disconnected from this callback, as it is still emitting the event.
+    ///
+    /// This is called before the callback of the `error` event, which reports the loss with
+    /// an `EPIPE` error about the core, see [`CoreError::is_disconnected`].
+    #[must_use]
+    pub fn disconnected<F>(mut self, disconnected: F) -> Self
+    where
+        F: FnMut() + 'static,
+    {
+        self.cbs.disconnected = Some(Box::new(disconnected));
+        self
+    }
+
     #[must_use]
     pub fn register(self) -> Listener {
         unsafe extern "C" fn core_events_info(
             data: *mut c_void,
             info: *const pw_sys::pw_core_info,
         ) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            let info = Info::new(ptr::NonNull::new(info as *mut _).expect("info is NULL"));
-            callbacks.info.as_ref().unwrap()(&info);
+            crate::utils::catch_callback_panic("core info", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                let info = info.cast::<CoreInfoRef>().as_ref().expect("info is NULL");
+                callbacks.info.as_mut().unwrap()(info);
+            })
         }
 
         unsafe extern "C" fn core_events_done(data: *mut c_void, id: u32, seq: i32) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            callbacks.done.as_ref().unwrap()(id, AsyncSeq::from_raw(seq));
+            crate::utils::catch_callback_panic("core done", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                callbacks.done.as_mut().unwrap()(id, AsyncSeq::from_raw(seq));
+            })
         }
 
         unsafe extern "C" fn core_events_error(
@@ -306,9 +555,61 @@ impl<'a> ListenerLocalBuilder<'a> {
             res: i32,
             message: *const c_char,
         ) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            let message = CStr::from_ptr(message).to_str().unwrap();
-            callbacks.error.as_ref().unwrap()(id, seq, res, message);
+            crate::utils::catch_callback_panic("core error", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                let message = if message.is_null() {
+                    Cow::Borrowed("")
+                } else {
+                    CStr::from_ptr(message).to_string_lossy()
+                };
+                if id == PW_ID_CORE && res == -libc::EPIPE {
+                    if let Some(disconnected) = callbacks.disconnected.as_mut() {
+                        disconnected();
+                    }
+                }
+                if let Some(error) = callbacks.error.as_mut() {
+                    error(id, seq, res, &message);
+                }
+            })
+        }
+
+        unsafe extern "C" fn core_events_add_mem(
+            data: *mut c_void,
+            id: u32,
+            type_: u32,
+            fd: i32,
+            flags: u32,
+        ) {
+            crate::utils::catch_callback_panic("core add_mem", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                // the fd is owned by the memory pool of the core until remove_mem
+                let fd = BorrowedFd::borrow_raw(fd);
+                callbacks.add_mem.as_mut().unwrap()(
+                    id,
+                    DataType::from_raw(type_),
+                    fd,
+                    MemFlags::from_bits_retain(flags),
+                );
+            })
+        }
+
+        unsafe extern "C" fn core_events_remove_mem(data: *mut c_void, id: u32) {
+            crate::utils::catch_callback_panic("core remove_mem", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                callbacks.remove_mem.as_mut().unwrap()(id);
+            })
         }
 
         let e = unsafe {
@@ -321,16 +622,22 @@ impl<'a> ListenerLocalBuilder<'a> {
             if self.cbs.done.is_some() {
                 e.done = Some(core_events_done);
             }
-            if self.cbs.error.is_some() {
+            if self.cbs.error.is_some() || self.cbs.disconnected.is_some() {
                 e.error = Some(core_events_error);
             }
+            if self.cbs.add_mem.is_some() {
+                e.add_mem = Some(core_events_add_mem);
+            }
+            if self.cbs.remove_mem.is_some() {
+                e.remove_mem = Some(core_events_remove_mem);
+            }
 
             e
         };
 
         let (listener, data) = unsafe {
             let ptr = self.core.as_raw_ptr();
-            let data = Box::into_raw(Box::new(self.cbs));
+            let data = Box::into_raw(Box::new(crate::utils::Guarded::new(self.cbs)));
             let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
             // Have to cast from pw-sys namespaced type to the equivalent spa-sys type
             // as bindgen does not allow us to generate bindings dependings of another
@@ -345,6 +652,7 @@ impl<'a> ListenerLocalBuilder<'a> {
                 e.as_ref().get_ref(),
                 data as *mut _
             );
+            spa::utils::hook::reset_on_remove(&mut *listener_ptr);
 
             (listener, Box::from_raw(data))
         };
@@ -353,66 +661,71 @@ impl<'a> ListenerLocalBuilder<'a> {
             events: e,
             listener,
             data,
+            loop_: LoopHandle::of_core(self.core.as_raw_ptr()),
         }
     }
 }
 
-pub struct Info {
-    ptr: ptr::NonNull<pw_sys::pw_core_info>,
-}
+#[repr(transparent)]
+pub struct CoreInfoRef(pw_sys::pw_core_info);
+
+impl CoreInfoRef {
+    pub fn as_raw(&self) -> &pw_sys::pw_core_info {
+        &self.0
+    }
 
-impl Info {
-    fn new(info: ptr::NonNull<pw_sys::pw_core_info>) -> Self {
-        Self { ptr: info }
+    pub fn as_raw_ptr(&self) -> *mut pw_sys::pw_core_info {
+        std::ptr::addr_of!(self.0).cast_mut()
     }
 
     pub fn id(&self) -> u32 {
-        unsafe { self.ptr.as_ref().id }
+        self.0.id
     }
 
+    /// A random cookie identifying the instance of the server.
     pub fn cookie(&self) -> u32 {
-        unsafe { self.ptr.as_ref().cookie }
+        self.0.cookie
     }
 
     pub fn user_name(&self) -> &str {
-        unsafe {
-            CStr::from_ptr(self.ptr.as_ref().user_name)
-                .to_str()
-                .unwrap()
-        }
+        unsafe { CStr::from_ptr(self.0.user_name).to_str().unwrap() }
     }
 
     pub fn host_name(&self) -> &str {
-        unsafe {
-            CStr::from_ptr(self.ptr.as_ref().host_name)
-                .to_str()
-                .unwrap()
-        }
+        unsafe { CStr::from_ptr(self.0.host_name).to_str().unwrap() }
     }
 
     pub fn version(&self) -> &str {
-        unsafe { CStr::from_ptr(self.ptr.as_ref().version).to_str().unwrap() }
+        unsafe { CStr::from_ptr(self.0.version).to_str().unwrap() }
+    }
+
+    /// The version of the server, parsed into a [`Version`](crate::version::Version).
+    pub fn parsed_version(&self) -> Option<crate::version::Version> {
+        self.version().parse().ok()
     }
 
     pub fn name(&self) -> &str {
-        unsafe { CStr::from_ptr(self.ptr.as_ref().name).to_str().unwrap() }
+        unsafe { CStr::from_ptr(self.0.name).to_str().unwrap() }
     }
 
-    pub fn change_mask(&self) -> ChangeMask {
-        let mask = unsafe { self.ptr.as_ref().change_mask };
-        ChangeMask::from_bits_retain(mask)
+    /// What changed since the previous info event, [`CoreChangeMask::PROPS`] after the
+    /// properties of the server were updated.
+    ///
+    /// The first info event of a listener has all bits set.
+    pub fn change_mask(&self) -> CoreChangeMask {
+        CoreChangeMask::from_bits_retain(self.0.change_mask)
     }
 
+    /// The properties of the server, such as `default.clock.rate`.
     pub fn props(&self) -> Option<&spa::utils::dict::DictRef> {
-        let props_ptr: *mut spa::utils::dict::DictRef = unsafe { self.ptr.as_ref().props.cast() };
-
+        let props_ptr: *mut spa::utils::dict::DictRef = self.0.props.cast();
         ptr::NonNull::new(props_ptr).map(|ptr| unsafe { ptr.as_ref() })
     }
 }
 
-impl fmt::Debug for Info {
+impl fmt::Debug for CoreInfoRef {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("CoreInfo")
+        f.debug_struct("CoreInfoRef")
             .field("id", &self.id())
             .field("cookie", &self.cookie())
             .field("user-name", &self.user_name())
@@ -425,9 +738,290 @@ impl fmt::Debug for Info {
     }
 }
 
+/// Serializes the info with the keys used by `pw-dump`.
+///
+/// The fields that are not flagged in the change mask are not valid and are serialized as `null`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for CoreInfoRef {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mask = self.change_mask();
+        let mut info = serializer.serialize_struct("CoreInfo", 8)?;
+        info.serialize_field("id", &self.id())?;
+        info.serialize_field("cookie", &self.cookie())?;
+        info.serialize_field("user-name", self.user_name())?;
+        info.serialize_field("host-name", self.host_name())?;
+        info.serialize_field("version", self.version())?;
+        info.serialize_field("name", self.name())?;
+        info.serialize_field("change-mask", &crate::utils::FlagNames(mask))?;
+        info.serialize_field(
+            "props",
+            &self
+                .props()
+                .filter(|_| mask.contains(CoreChangeMask::PROPS)),
+        )?;
+        info.end()
+    }
+}
+
+pub struct CoreInfo {
+    ptr: ptr::NonNull<pw_sys::pw_core_info>,
+}
+
+impl CoreInfo {
+    pub fn new(ptr: ptr::NonNull<pw_sys::pw_core_info>) -> Self {
+        Self { ptr }
+    }
+
+    pub fn from_raw(raw: *mut pw_sys::pw_core_info) -> Self {
+        Self {
+            ptr: ptr::NonNull::new(raw).expect("Provided pointer is null"),
+        }
+    }
+
+    pub fn into_raw(self) -> *mut pw_sys::pw_core_info {
+        std::mem::ManuallyDrop::new(self).ptr.as_ptr()
+    }
+}
+
+impl Drop for CoreInfo {
+    fn drop(&mut self) {
+        unsafe { pw_sys::pw_core_info_free(self.ptr.as_ptr()) }
+    }
+}
+
+impl std::ops::Deref for CoreInfo {
+    type Target = CoreInfoRef;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.ptr.cast::<CoreInfoRef>().as_ref() }
+    }
+}
+
+impl AsRef<CoreInfoRef> for CoreInfo {
+    fn as_ref(&self) -> &CoreInfoRef {
+        self.deref()
+    }
+}
+
 bitflags! {
     #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-    pub struct ChangeMask: u64 {
+    pub struct CoreChangeMask: u64 {
         const PROPS = pw_sys::PW_CORE_CHANGE_MASK_PROPS as u64;
     }
 }
+
+impl fmt::Debug for CoreInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CoreInfo")
+            .field("id", &self.id())
+            .field("cookie", &self.cookie())
+            .field("user-name", &self.user_name())
+            .field("host-name", &self.host_name())
+            .field("version", &self.version())
+            .field("name", &self.name())
+            .field("change-mask", &self.change_mask())
+            .field("props", &self.props())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::{
+        context::Context, main_loop::MainLoop, node::Node, permissions::PermissionFlags,
+        proxy::Listener as _, registry::GlobalObject, types::ObjectType,
+    };
+
+    #[test]
+    fn sync_done() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+
+        let done = Rc::new(Cell::new(None));
+        let _listener = core
+            .add_listener_local()
+            .done({
+                let done = done.clone();
+                move |id, seq| done.set(Some((id, seq)))
+            })
+            .register();
+
+        let pending = core.sync(42).unwrap();
+        mainloop.run_until(|_| done.get().is_some());
+        assert_eq!(done.get(), Some((PW_ID_CORE, pending)));
+    }
+
+    #[test]
+    fn info_props() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+
+        let infos = Rc::new(std::cell::RefCell::new(Vec::new()));
+        let _listener = core
+            .add_listener_local()
+            .info({
+                let infos = infos.clone();
+                move |info| {
+                    assert!(!format!("{info:?}").is_empty());
+                    let rate = info
+                        .props()
+                        .and_then(|props| props.get("default.clock.rate"))
+                        .map(str::to_owned);
+                    infos
+                        .borrow_mut()
+                        .push((info.id(), info.change_mask(), rate));
+                }
+            })
+            .register();
+
+        mainloop.run_until(|_| !infos.borrow().is_empty());
+        let (id, mask, rate) = infos.borrow()[0].clone();
+        assert_eq!(id, PW_ID_CORE);
+        assert!(mask.contains(CoreChangeMask::PROPS));
+        let rate: u32 = rate.expect("no default.clock.rate").parse().unwrap();
+        assert!(rate > 0);
+    }
+
+    #[test]
+    fn block_listener() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+
+        let seqs = Rc::new(std::cell::RefCell::new(Vec::new()));
+        let mut listener = core
+            .add_listener_local()
+            .done({
+                let seqs = seqs.clone();
+                move |_, seq| seqs.borrow_mut().push(seq)
+            })
+            .register();
+        // tells when each sync is done, blocked or not
+        let last = Rc::new(Cell::new(None));
+        let _witness = core
+            .add_listener_local()
+            .done({
+                let last = last.clone();
+                move |_, seq| last.set(Some(seq))
+            })
+            .register();
+
+        listener.block();
+        assert!(listener.is_blocked());
+        let blocked = core.sync(1).unwrap();
+        mainloop.run_until(|_| last.get() == Some(blocked));
+        assert!(seqs.borrow().is_empty());
+
+        listener.unblock();
+        assert!(!listener.is_blocked());
+        let unblocked = core.sync(2).unwrap();
+        mainloop.run_until(|_| last.get() == Some(unblocked));
+        assert_eq!(*seqs.borrow(), [unblocked]);
+
+        listener.unregister();
+        let unregistered = core.sync(3).unwrap();
+        mainloop.run_until(|_| last.get() == Some(unregistered));
+        assert_eq!(*seqs.borrow(), [unblocked]);
+    }
+
+    #[test]
+    fn core_error() {
+        let err = CoreError::new(5, 3, -libc::ENOENT, "no global 42");
+        assert_eq!(err.id, 5);
+        assert_eq!(err.seq, 3);
+        assert_eq!(err.errno, Errno::ENOENT);
+        assert!(err.to_string().starts_with("no global 42 (id 5, ENOENT"));
+        assert!(!err.is_disconnected());
+        assert!(CoreError::new(PW_ID_CORE, 0, -libc::EPIPE, "connection error").is_disconnected());
+
+        // errors about proxies are also reported by the core
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let registry = core.get_registry().unwrap();
+
+        let error = Rc::new(std::cell::RefCell::new(None));
+        let _listener = core
+            .add_listener_local()
+            .core_error({
+                let error = error.clone();
+                move |err| *error.borrow_mut() = Some(err)
+            })
+            .register();
+
+        let global = GlobalObject {
+            id: u32::MAX - 1,
+            permissions: PermissionFlags::all(),
+            type_: ObjectType::Node,
+            version: 0,
+            props: None::<&spa::utils::dict::DictRef>,
+        };
+        let node: Node = registry.bind(&global).unwrap();
+        mainloop.run_until(|_| error.borrow().is_some());
+
+        let error = error.borrow_mut().take().unwrap();
+        assert_eq!(error.id, node.upcast_ref().id());
+        assert_eq!(error.errno, Errno::ENOENT);
+    }
+
+    #[test]
+    fn disconnect_before_proxies() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let registry = core.get_registry().unwrap();
+        let global = GlobalObject {
+            id: u32::MAX - 1,
+            permissions: PermissionFlags::all(),
+            type_: ObjectType::Node,
+            version: 0,
+            props: None::<&spa::utils::dict::DictRef>,
+        };
+        let node: Node = registry.bind(&global).unwrap();
+        let id = node.upcast_ref().id();
+
+        let removed = Rc::new(Cell::new(false));
+        let registry_listener = registry.add_listener_local().global(|_| {}).register();
+        let node_listener = node.add_listener_local().info(|_| {}).register();
+        let proxy_listener = node
+            .upcast_ref()
+            .add_listener_local()
+            .removed({
+                let removed = removed.clone();
+                move || removed.set(true)
+            })
+            .register();
+        let core_listener = core.add_listener_local().done(|_, _| {}).register();
+
+        let clone = core.clone();
+        assert_eq!(clone.disconnect().unwrap_err().errno(), Some(Errno::EBUSY));
+        core.disconnect().unwrap();
+
+        assert!(removed.get());
+        assert!(!node.upcast_ref().is_connected());
+        assert_eq!(node.upcast_ref().id(), id);
+        assert_eq!(node.upcast_ref().get_type().0, ObjectType::Node);
+        let err = node.enum_params(0, None, 0, u32::MAX, None).unwrap_err();
+        assert_eq!(err.errno(), Some(Errno::EPIPE));
+        let err = registry.bind::<Node, _>(&global).unwrap_err();
+        assert_eq!(err.errno(), Some(Errno::EPIPE));
+        // listening to a disconnected proxy is a no-op
+        let late_listener = node.add_listener_local().info(|_| {}).register();
+
+        // everything can still be dropped after the core
+        drop(node_listener);
+        drop(late_listener);
+        drop(proxy_listener);
+        drop(node);
+        drop(registry_listener);
+        drop(registry);
+        drop(core_listener);
+    }
+}
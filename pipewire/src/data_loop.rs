@@ -0,0 +1,249 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! A loop running in its own thread, for realtime processing.
+//!
+//! A [`DataLoop`] is what a [`Context`](crate::context::Context) uses internally to process
+//! the graph: a loop without a lock, started in a new thread that is made realtime with
+//! the thread utils of the loop, see [`DataLoop::set_thread_utils`].
+//!
+//! Prefer a [`ThreadLoop`](crate::thread_loop::ThreadLoop) to run the control objects of PipeWire,
+//! such as a core, proxies and streams, outside of the main thread: its lock allows to safely
+//! use them from other threads. Prefer a `DataLoop` for sources that must run with low latency,
+//! such as timers or io sources driving processing, which should never wait on a lock held by
+//! another thread.
+//!
+//! As the loop has no lock, sources must be added before [`start`](DataLoop::start)
+//! or from the loop thread, using [`invoke`](DataLoop::invoke).
+//! Their callbacks are called from the loop thread, so they must be [`Send`]: this is not checked
+//! by [`LoopRef`], which is why [`DataLoop::new`] is unsafe.
+
+use std::{
+    ffi::c_void,
+    ptr,
+    rc::{Rc, Weak},
+};
+
+use crate::{
+    error::Error,
+    loop_::{IsLoopRc, LoopRef},
+};
+use spa::utils::result::SpaResult;
+
+#[derive(Debug, Clone)]
+pub struct DataLoop {
+    inner: Rc<DataLoopInner>,
+}
+
+impl DataLoop {
+    /// Create a new, stopped, `DataLoop` with the optional properties, such as
+    /// `loop.name` or `thread.affinity`.
+    ///
+    /// # Safety
+    /// The callbacks of the sources added through [`loop_`](Self::loop_) are called from the
+    /// thread of the loop once it is started, while [`LoopRef`] does not require them to be [`Send`].
+    /// The caller must ensure that every source added to the loop, and everything its callback uses,
+    /// can be sent to and used from the thread of the loop, and that sources are only added and removed
+    /// while the loop is stopped or from the thread of the loop.
+    pub unsafe fn new(properties: Option<&spa::utils::dict::DictRef>) -> Result<Self, Error> {
+        super::init();
+
+        unsafe {
+            let props = properties.map_or(ptr::null(), |props| props.as_raw_ptr());
+            let l = pw_sys::pw_data_loop_new(props);
+            let ptr = ptr::NonNull::new(l).ok_or(Error::CreationFailed)?;
+
+            Ok(Self {
+                inner: Rc::new(DataLoopInner::from_raw(ptr)),
+            })
+        }
+    }
+
+    pub fn downgrade(&self) -> WeakDataLoop {
+        let weak = Rc::downgrade(&self.inner);
+        WeakDataLoop { weak }
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut pw_sys::pw_data_loop {
+        self.inner.ptr.as_ptr()
+    }
+
+    pub fn loop_(&self) -> &LoopRef {
+        unsafe {
+            let data_loop = pw_sys::pw_data_loop_get_loop(self.as_raw_ptr());
+            &*(data_loop.cast::<LoopRef>())
+        }
+    }
+
+    /// Start the thread of the loop.
+    pub fn start(&self) -> Result<(), Error> {
+        let res = unsafe { pw_sys::pw_data_loop_start(self.as_raw_ptr()) };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
+    }
+
+    /// Stop the loop and join its thread.
+    ///
+    /// This must not be called from the loop thread.
+    pub fn stop(&self) -> Result<(), Error> {
+        let res = unsafe { pw_sys::pw_data_loop_stop(self.as_raw_ptr()) };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
+    }
+
+    /// Check if inside the thread of the loop.
+    pub fn in_thread(&self) -> bool {
+        unsafe { pw_sys::pw_data_loop_in_thread(self.as_raw_ptr()) }
+    }
+
+    /// Call `f` from the thread of the loop.
+    ///
+    /// If called from the loop thread, or while the loop is not started, `f` is called right away.
+    /// Otherwise it is queued and called on the next iteration of the loop.
+    pub fn invoke<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        unsafe extern "C" fn call_closure<F>(
+            _loop: *mut spa_sys::spa_loop,
+            _async: bool,
+            _seq: u32,
+            _data: *const c_void,
+            _size: usize,
+            user_data: *mut c_void,
+        ) -> i32
+        where
+            F: FnOnce(),
+        {
+            crate::utils::catch_callback_panic("data loop invoke", 0, || {
+                let f = Box::from_raw(user_data.cast::<F>());
+                f();
+                0
+            })
+        }
+
+        let f = Box::into_raw(Box::new(f));
+        let res = unsafe {
+            pw_sys::pw_data_loop_invoke(
+                self.as_raw_ptr(),
+                Some(call_closure::<F>),
+                0,
+                ptr::null(),
+                0,
+                false,
+                f.cast(),
+            )
+        };
+
+        if let Err(err) = SpaResult::from_c(res).into_result() {
+            // `f` was neither called nor queued
+            drop(unsafe { Box::from_raw(f) });
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    /// Create the thread of the loop with `utils`, and make it realtime with them.
+    ///
+    /// Use the [`ThreadUtils`](crate::thread::ThreadUtils) of a context to go through
+    /// `libpipewire-module-rt`, as the context does for its own data loop.
+    ///
+    /// # Safety
+    /// `utils` must stay valid as long as the loop is started.
+    #[cfg(feature = "v0_3_32")]
+    pub unsafe fn set_thread_utils(&self, utils: &crate::thread::ThreadUtils) {
+        pw_sys::pw_data_loop_set_thread_utils(self.as_raw_ptr(), utils.as_raw_ptr());
+    }
+}
+
+// Safety: The pw_loop is guaranteed to remain valid while any clone of the `DataLoop` is held,
+//         because we use an internal Rc to keep the pw_data_loop containing the pw_loop alive.
+unsafe impl IsLoopRc for DataLoop {}
+
+impl std::convert::AsRef<LoopRef> for DataLoop {
+    fn as_ref(&self) -> &LoopRef {
+        self.loop_()
+    }
+}
+
+pub struct WeakDataLoop {
+    weak: Weak<DataLoopInner>,
+}
+
+impl WeakDataLoop {
+    pub fn upgrade(&self) -> Option<DataLoop> {
+        self.weak.upgrade().map(|inner| DataLoop { inner })
+    }
+}
+
+#[derive(Debug)]
+struct DataLoopInner {
+    ptr: ptr::NonNull<pw_sys::pw_data_loop>,
+}
+
+impl DataLoopInner {
+    pub unsafe fn from_raw(ptr: ptr::NonNull<pw_sys::pw_data_loop>) -> Self {
+        Self { ptr }
+    }
+}
+
+impl Drop for DataLoopInner {
+    fn drop(&mut self) {
+        unsafe { pw_sys::pw_data_loop_destroy(self.ptr.as_ptr()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn invoke() {
+        // only Send closures are added to the loop
+        let data_loop = unsafe { DataLoop::new(None) }.unwrap();
+        let (sender, receiver) = mpsc::channel();
+
+        // called right away while the loop is not started
+        let sender_clone = sender.clone();
+        data_loop
+            .invoke(move || sender_clone.send("stopped").unwrap())
+            .unwrap();
+        assert_eq!(receiver.try_recv(), Ok("stopped"));
+
+        data_loop.start().unwrap();
+
+        data_loop
+            .invoke(move || sender.send("started").unwrap())
+            .unwrap();
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok("started"));
+        assert!(!data_loop.in_thread());
+
+        data_loop.stop().unwrap();
+    }
+
+    #[test]
+    fn timer() {
+        // the timer only uses a sender, and is added before the loop is started
+        let data_loop = unsafe { DataLoop::new(None) }.unwrap();
+        let (sender, receiver) = mpsc::channel();
+
+        let timer = data_loop
+            .loop_()
+            .add_timer(move |_| {
+                let _ = sender.send(());
+            })
+            .unwrap();
+        timer
+            .update_timer(Some(Duration::from_millis(1)), None)
+            .into_sync_result()
+            .unwrap();
+
+        data_loop.start().unwrap();
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        data_loop.stop().unwrap();
+    }
+}
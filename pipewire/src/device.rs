@@ -8,12 +8,20 @@ use std::{fmt, mem};
 use std::{pin::Pin, ptr};
 
 use crate::{
-    proxy::{Listener, Proxy, ProxyT},
+    proxy::{impl_listener, impl_transparent_proxy, Proxy, ProxyT},
+    thread_loop::LoopHandle,
     types::ObjectType,
+    Error,
+};
+use spa::{
+    param::{profile::Profile, route::Route, ParamType},
+    pod::Pod,
+    spa_interface_call_method,
+    utils::result::SpaResult,
 };
-use spa::{pod::Pod, spa_interface_call_method};
 
 #[derive(Debug)]
+#[repr(transparent)]
 pub struct Device {
     proxy: Proxy,
 }
@@ -31,17 +39,19 @@ impl Device {
     /// Subscribe to parameter changes
     ///
     /// Automatically emit `param` events for the given ids when they are changed
-    // FIXME: Return result?
-    pub fn subscribe_params(&self, ids: &[spa::param::ParamType]) {
-        unsafe {
+    pub fn subscribe_params(&self, ids: &[spa::param::ParamType]) -> Result<(), Error> {
+        let res = unsafe {
             spa_interface_call_method!(
-                self.proxy.as_ptr(),
+                self.proxy.checked_ptr()?,
                 pw_sys::pw_device_methods,
                 subscribe_params,
                 ids.as_ptr() as *mut _,
                 ids.len().try_into().unwrap()
-            );
-        }
+            )
+        };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
     }
 
     /// Enumerate device parameters
@@ -55,13 +65,18 @@ impl Device {
     /// `start`: the start index or 0 for the first param \
     /// `num`: the maximum number of params to retrieve ([`u32::MAX`] may be used to retrieve all params)
     // FIXME: Add filter parameter
-    // FIXME: Return result?
-    pub fn enum_params(&self, seq: i32, id: Option<spa::param::ParamType>, start: u32, num: u32) {
+    pub fn enum_params(
+        &self,
+        seq: i32,
+        id: Option<spa::param::ParamType>,
+        start: u32,
+        num: u32,
+    ) -> Result<(), Error> {
         let id = id.map(|id| id.as_raw()).unwrap_or(crate::constants::ID_ANY);
 
-        unsafe {
+        let res = unsafe {
             spa_interface_call_method!(
-                self.proxy.as_ptr(),
+                self.proxy.checked_ptr()?,
                 pw_sys::pw_device_methods,
                 enum_params,
                 seq,
@@ -69,21 +84,32 @@ impl Device {
                 start,
                 num,
                 std::ptr::null()
-            );
-        }
+            )
+        };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
     }
 
-    pub fn set_param(&self, id: spa::param::ParamType, flags: u32, param: &Pod) {
-        unsafe {
+    pub fn set_param(
+        &self,
+        id: spa::param::ParamType,
+        flags: u32,
+        param: &Pod,
+    ) -> Result<(), Error> {
+        let res = unsafe {
             spa_interface_call_method!(
-                self.proxy.as_ptr(),
+                self.proxy.checked_ptr()?,
                 pw_sys::pw_device_methods,
                 set_param,
                 id.as_raw(),
                 flags,
                 param.as_raw_ptr()
-            );
-        }
+            )
+        };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
     }
 }
 
@@ -108,14 +134,66 @@ impl ProxyT for Device {
     }
 }
 
+impl_transparent_proxy!(Device);
+
 #[derive(Default)]
 struct ListenerLocalCallbacks {
     #[allow(clippy::type_complexity)]
-    info: Option<Box<dyn Fn(&DeviceInfoRef)>>,
+    info: Option<Box<dyn FnMut(&DeviceInfoRef)>>,
+    #[allow(clippy::type_complexity)]
+    param: Option<Box<dyn FnMut(i32, spa::param::ParamType, u32, u32, Option<&Pod>)>>,
+    route: Option<Box<dyn FnMut(Route)>>,
+    enum_route: Option<Box<dyn FnMut(Route)>>,
+    profile: Option<Box<dyn FnMut(Profile)>>,
+    enum_profile: Option<Box<dyn FnMut(Profile)>>,
     #[allow(clippy::type_complexity)]
-    param: Option<Box<dyn Fn(i32, spa::param::ParamType, u32, u32, Option<&Pod>)>>,
+    param_error: Option<Box<dyn FnMut(ParamType, spa::param::ParseError)>>,
 }
 
+impl ListenerLocalCallbacks {
+    fn has_param(&self) -> bool {
+        self.param.is_some()
+            || self.route.is_some()
+            || self.enum_route.is_some()
+            || self.profile.is_some()
+            || self.enum_profile.is_some()
+    }
+
+    fn param(&mut self, seq: i32, id: ParamType, index: u32, next: u32, param: Option<&Pod>) {
+        if let Some(cb) = &mut self.param {
+            cb(seq, id, index, next, param);
+        }
+
+        let Some(param) = param else {
+            return;
+        };
+        let res = match id {
+            ParamType::Route => self.route.as_mut().map(|cb| Route::from_pod(param).map(cb)),
+            ParamType::EnumRoute => self
+                .enum_route
+                .as_mut()
+                .map(|cb| Route::from_pod(param).map(cb)),
+            ParamType::Profile => self
+                .profile
+                .as_mut()
+                .map(|cb| Profile::from_pod(param).map(cb)),
+            ParamType::EnumProfile => self
+                .enum_profile
+                .as_mut()
+                .map(|cb| Profile::from_pod(param).map(cb)),
+            _ => None,
+        };
+        if let (Some(Err(err)), Some(cb)) = (res, &mut self.param_error) {
+            cb(id, err);
+        }
+    }
+}
+
+/// Builder of a listener for the events of a [`Device`], see [`Device::add_listener_local`].
+///
+/// **Events emitted while one of the callbacks is running are lost.** The callbacks are never
+/// called re-entrantly: an event received when a callback iterates the loop, e.g. to wait for a
+/// reply, or emitted synchronously by a method it calls, is dropped without notice.
 pub struct DeviceListenerLocalBuilder<'a> {
     device: &'a Device,
     cbs: ListenerLocalCallbacks,
@@ -138,13 +216,22 @@ impl DeviceInfoRef {
     }
 
     pub fn change_mask(&self) -> DeviceChangeMask {
-        DeviceChangeMask::from_bits(self.0.change_mask).expect("invalid change_mask")
+        DeviceChangeMask::from_bits_retain(self.0.change_mask)
     }
+
     pub fn props(&self) -> Option<&spa::utils::dict::DictRef> {
         let props_ptr: *mut spa::utils::dict::DictRef = self.0.props.cast();
         ptr::NonNull::new(props_ptr).map(|ptr| unsafe { ptr.as_ref() })
     }
 
+    /// The `object.serial` of the device, from its properties.
+    ///
+    /// Unlike the id, the serial is never reused by another object.
+    /// This is `None` if the properties are not in the info or if the server is too old to set it.
+    pub fn serial(&self) -> Option<u64> {
+        crate::utils::object_serial(self.props())
+    }
+
     /// Get the param infos for the device.
     pub fn params(&self) -> &[spa::param::ParamInfo] {
         let params = self.0.params;
@@ -169,6 +256,34 @@ impl fmt::Debug for DeviceInfoRef {
     }
 }
 
+/// Serializes the info with the keys used by `pw-dump`.
+///
+/// The fields that are not flagged in the change mask are not valid and are serialized as `null`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for DeviceInfoRef {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mask = self.change_mask();
+        let mut info = serializer.serialize_struct("DeviceInfo", 4)?;
+        info.serialize_field("id", &self.id())?;
+        info.serialize_field("change-mask", &crate::utils::FlagNames(mask))?;
+        info.serialize_field(
+            "props",
+            &self
+                .props()
+                .filter(|_| mask.contains(DeviceChangeMask::PROPS)),
+        )?;
+        info.serialize_field(
+            "params",
+            &mask
+                .contains(DeviceChangeMask::PARAMS)
+                .then(|| self.params()),
+        )?;
+        info.end()
+    }
+}
+
 pub struct DeviceInfo {
     ptr: ptr::NonNull<pw_sys::pw_device_info>,
 }
@@ -234,13 +349,15 @@ pub struct DeviceListener {
     events: Pin<Box<pw_sys::pw_device_events>>,
     listener: Pin<Box<spa_sys::spa_hook>>,
     #[allow(dead_code)]
-    data: Box<ListenerLocalCallbacks>,
+    data: Box<crate::utils::Guarded<ListenerLocalCallbacks>>,
+    loop_: LoopHandle,
 }
 
-impl Listener for DeviceListener {}
+impl_listener!(DeviceListener);
 
 impl Drop for DeviceListener {
     fn drop(&mut self) {
+        let _lock = self.loop_.lock();
         spa::utils::hook::remove(*self.listener);
     }
 }
@@ -249,32 +366,103 @@ impl<'a> DeviceListenerLocalBuilder<'a> {
     #[must_use]
     pub fn info<F>(mut self, info: F) -> Self
     where
-        F: Fn(&DeviceInfoRef) + 'static,
+        F: FnMut(&DeviceInfoRef) + 'static,
     {
         self.cbs.info = Some(Box::new(info));
         self
     }
 
+    /// Set the callback of the `param` event.
+    ///
+    /// The pod is only valid during the call, copy it with [`ToOwned::to_owned`] into an
+    /// [`OwnedPod`](spa::pod::OwnedPod) to keep it.
     #[must_use]
     pub fn param<F>(mut self, param: F) -> Self
     where
-        F: Fn(i32, spa::param::ParamType, u32, u32, Option<&Pod>) + 'static,
+        F: FnMut(i32, spa::param::ParamType, u32, u32, Option<&Pod>) + 'static,
     {
         self.cbs.param = Some(Box::new(param));
         self
     }
 
+    /// Call `route` with each decoded [`ParamType::Route`] param, in addition to [`param`](Self::param).
+    ///
+    /// Params failing to decode are reported to [`param_error`](Self::param_error).
+    #[must_use]
+    pub fn route<F>(mut self, route: F) -> Self
+    where
+        F: FnMut(Route) + 'static,
+    {
+        self.cbs.route = Some(Box::new(route));
+        self
+    }
+
+    /// Call `enum_route` with each decoded [`ParamType::EnumRoute`] param, in addition to [`param`](Self::param).
+    ///
+    /// Params failing to decode are reported to [`param_error`](Self::param_error).
+    #[must_use]
+    pub fn enum_route<F>(mut self, enum_route: F) -> Self
+    where
+        F: FnMut(Route) + 'static,
+    {
+        self.cbs.enum_route = Some(Box::new(enum_route));
+        self
+    }
+
+    /// Call `profile` with each decoded [`ParamType::Profile`] param, in addition to [`param`](Self::param).
+    ///
+    /// Params failing to decode are reported to [`param_error`](Self::param_error).
+    #[must_use]
+    pub fn profile<F>(mut self, profile: F) -> Self
+    where
+        F: FnMut(Profile) + 'static,
+    {
+        self.cbs.profile = Some(Box::new(profile));
+        self
+    }
+
+    /// Call `enum_profile` with each decoded [`ParamType::EnumProfile`] param, in addition to [`param`](Self::param).
+    ///
+    /// Params failing to decode are reported to [`param_error`](Self::param_error).
+    #[must_use]
+    pub fn enum_profile<F>(mut self, enum_profile: F) -> Self
+    where
+        F: FnMut(Profile) + 'static,
+    {
+        self.cbs.enum_profile = Some(Box::new(enum_profile));
+        self
+    }
+
+    /// Call `param_error` when a param could not be decoded for [`route`](Self::route),
+    /// [`enum_route`](Self::enum_route), [`profile`](Self::profile) or [`enum_profile`](Self::enum_profile).
+    ///
+    /// Such params are ignored if this is not set.
+    #[must_use]
+    pub fn param_error<F>(mut self, param_error: F) -> Self
+    where
+        F: FnMut(ParamType, spa::param::ParseError) + 'static,
+    {
+        self.cbs.param_error = Some(Box::new(param_error));
+        self
+    }
+
     #[must_use]
     pub fn register(self) -> DeviceListener {
         unsafe extern "C" fn device_events_info(
             data: *mut c_void,
             info: *const pw_sys::pw_device_info,
         ) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            let info =
-                ptr::NonNull::new(info as *mut pw_sys::pw_device_info).expect("info is NULL");
-            let info = info.cast::<DeviceInfoRef>().as_ref();
-            callbacks.info.as_ref().unwrap()(info);
+            crate::utils::catch_callback_panic("device info", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                let info =
+                    ptr::NonNull::new(info as *mut pw_sys::pw_device_info).expect("info is NULL");
+                let info = info.cast::<DeviceInfoRef>().as_ref();
+                callbacks.info.as_mut().unwrap()(info);
+            })
         }
 
         unsafe extern "C" fn device_events_param(
@@ -285,16 +473,22 @@ impl<'a> DeviceListenerLocalBuilder<'a> {
             next: u32,
             param: *const spa_sys::spa_pod,
         ) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-
-            let id = spa::param::ParamType::from_raw(id);
-            let param = if !param.is_null() {
-                unsafe { Some(Pod::from_raw(param)) }
-            } else {
-                None
-            };
-
-            callbacks.param.as_ref().unwrap()(seq, id, index, next, param);
+            crate::utils::catch_callback_panic("device param", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+
+                let id = spa::param::ParamType::from_raw(id);
+                let param = if !param.is_null() {
+                    unsafe { Some(Pod::from_raw(param)) }
+                } else {
+                    None
+                };
+
+                callbacks.param(seq, id, index, next, param);
+            })
         }
 
         let e = unsafe {
@@ -304,7 +498,7 @@ impl<'a> DeviceListenerLocalBuilder<'a> {
             if self.cbs.info.is_some() {
                 e.info = Some(device_events_info);
             }
-            if self.cbs.param.is_some() {
+            if self.cbs.has_param() {
                 e.param = Some(device_events_param);
             }
 
@@ -314,18 +508,21 @@ impl<'a> DeviceListenerLocalBuilder<'a> {
         let (listener, data) = unsafe {
             let device = &self.device.proxy.as_ptr();
 
-            let data = Box::into_raw(Box::new(self.cbs));
+            let data = Box::into_raw(Box::new(crate::utils::Guarded::new(self.cbs)));
             let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
             let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
 
-            spa_interface_call_method!(
-                device,
-                pw_sys::pw_device_methods,
-                add_listener,
-                listener_ptr.cast(),
-                e.as_ref().get_ref(),
-                data as *mut _
-            );
+            if self.device.proxy.is_connected() {
+                spa_interface_call_method!(
+                    device,
+                    pw_sys::pw_device_methods,
+                    add_listener,
+                    listener_ptr.cast(),
+                    e.as_ref().get_ref(),
+                    data as *mut _
+                );
+                spa::utils::hook::reset_on_remove(&mut *listener_ptr);
+            }
 
             (listener, Box::from_raw(data))
         };
@@ -334,6 +531,126 @@ impl<'a> DeviceListenerLocalBuilder<'a> {
             events: e,
             listener,
             data,
+            loop_: self.device.proxy.loop_handle(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spa::{
+        pod::{ObjectBuilder, Value},
+        utils::SpaTypes,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn decoded_params() {
+        let routes = Rc::new(RefCell::new(Vec::new()));
+        let profiles = Rc::new(RefCell::new(Vec::new()));
+        let errors = Rc::new(RefCell::new(Vec::new()));
+        let raw = Rc::new(RefCell::new(Vec::new()));
+
+        let mut cbs = ListenerLocalCallbacks::default();
+        assert!(!cbs.has_param());
+        let routes_clone = routes.clone();
+        cbs.route = Some(Box::new(move |route| routes_clone.borrow_mut().push(route)));
+        let profiles_clone = profiles.clone();
+        cbs.enum_profile = Some(Box::new(move |profile| {
+            profiles_clone.borrow_mut().push(profile)
+        }));
+        let errors_clone = errors.clone();
+        cbs.param_error = Some(Box::new(move |id, err| {
+            errors_clone.borrow_mut().push((id, err))
+        }));
+        let raw_clone = raw.clone();
+        cbs.param = Some(Box::new(move |_, id, _, _, param| {
+            raw_clone.borrow_mut().push((id, param.is_some()))
+        }));
+        assert!(cbs.has_param());
+
+        let route = ObjectBuilder::new(SpaTypes::ObjectParamRoute, ParamType::Route)
+            .prop(spa_sys::SPA_PARAM_ROUTE_index, 1)
+            .prop(
+                spa_sys::SPA_PARAM_ROUTE_direction,
+                spa::utils::Id(spa_sys::spa_direction::SPA_DIRECTION_OUTPUT.0),
+            )
+            .prop(spa_sys::SPA_PARAM_ROUTE_name, "analog-output-speaker")
+            .build();
+        let profile = ObjectBuilder::new(SpaTypes::ObjectParamProfile, ParamType::EnumProfile)
+            .prop(spa_sys::SPA_PARAM_PROFILE_index, 0)
+            .prop(spa_sys::SPA_PARAM_PROFILE_name, "off")
+            .build();
+        let invalid = ObjectBuilder::new(SpaTypes::ObjectParamProfile, ParamType::EnumProfile)
+            .prop(spa_sys::SPA_PARAM_PROFILE_name, Value::Int(0))
+            .build();
+
+        cbs.param(0, ParamType::Route, 0, 1, Some(&route));
+        cbs.param(0, ParamType::EnumProfile, 0, 1, Some(&profile));
+        cbs.param(0, ParamType::EnumProfile, 1, 2, Some(&invalid));
+        // no decoder for these
+        cbs.param(0, ParamType::Profile, 0, 1, Some(&profile));
+        cbs.param(0, ParamType::EnumRoute, 0, 1, Some(&invalid));
+        cbs.param(0, ParamType::Route, 0, 1, None);
+
+        assert_eq!(routes.borrow().len(), 1);
+        assert_eq!(routes.borrow()[0].name, "analog-output-speaker");
+        assert_eq!(profiles.borrow().len(), 1);
+        assert_eq!(profiles.borrow()[0].name, "off");
+        assert_eq!(
+            *errors.borrow(),
+            vec![(
+                ParamType::EnumProfile,
+                spa::param::ParseError::WrongType {
+                    object_type: SpaTypes::ObjectParamProfile,
+                    key: spa_sys::SPA_PARAM_PROFILE_name,
+                    expected: SpaTypes::String,
+                    found: SpaTypes::Int,
+                }
+            )]
+        );
+        assert_eq!(raw.borrow().len(), 6);
+        assert_eq!(raw.borrow()[5], (ParamType::Route, false));
+    }
+
+    #[test]
+    fn info() {
+        let param = |id: ParamType, flags: spa::param::ParamInfoFlags| {
+            let mut raw: spa_sys::spa_param_info = unsafe { std::mem::zeroed() };
+            raw.id = id.as_raw();
+            raw.flags = flags.bits();
+            raw
+        };
+        let mut params = [
+            param(ParamType::Route, spa::param::ParamInfoFlags::READWRITE),
+            param(ParamType::EnumProfile, spa::param::ParamInfoFlags::READ),
+        ];
+        let mut raw: pw_sys::pw_device_info = unsafe { std::mem::zeroed() };
+        raw.id = 42;
+        raw.params = params.as_mut_ptr();
+        raw.n_params = params.len() as u32;
+        // unknown bits are kept instead of panicking
+        raw.change_mask = pw_sys::PW_DEVICE_CHANGE_MASK_PARAMS as u64 | 1 << 8;
+
+        let info = unsafe { &*(std::ptr::addr_of!(raw) as *const DeviceInfoRef) };
+        assert_eq!(info.id(), 42);
+        assert!(info.props().is_none());
+        assert_eq!(info.serial(), None);
+        assert!(info.change_mask().contains(DeviceChangeMask::PARAMS));
+        assert!(!info.change_mask().contains(DeviceChangeMask::PROPS));
+        let params: Vec<_> = info
+            .params()
+            .iter()
+            .map(|param| (param.id(), param.flags()))
+            .collect();
+        assert_eq!(
+            params,
+            [
+                (ParamType::Route, spa::param::ParamInfoFlags::READWRITE),
+                (ParamType::EnumProfile, spa::param::ParamInfoFlags::READ),
+            ]
+        );
+    }
+}
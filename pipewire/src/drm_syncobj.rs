@@ -0,0 +1,213 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Explicit sync of DMA-BUF buffers with DRM syncobjs, enabled by the `drm-syncobj` feature.
+//!
+//! Buffers using explicit sync carry two syncobjs, see [`BufferRef::sync_objs`](crate::buffer::BufferRef::sync_objs),
+//! and their timeline points in a [`MetaSyncTimeline`](spa::buffer::MetaSyncTimeline).
+//! A consumer handles each buffer in this order:
+//!
+//! 1. wait for the acquire point on the acquire syncobj, the producer is done writing the buffer,
+//! 2. read the buffer,
+//! 3. signal the release point on the release syncobj, the producer can reuse the buffer,
+//! 4. queue the buffer back to the stream.
+//!
+//! ```no_run
+//! use pipewire::{buffer::BufferRef, drm_syncobj::DrmDevice};
+//! use std::{os::fd::BorrowedFd, time::Duration};
+//!
+//! fn consume(device: &DrmDevice, buffer: &mut BufferRef) -> Result<(), pipewire::Error> {
+//!     let sync = buffer.sync_objs().zip(buffer.meta_sync_timeline().copied());
+//!     let sync = match sync {
+//!         Some(((acquire, release), timeline)) => {
+//!             // Safety: the fds of the datas stay open while the buffer exists
+//!             let acquire = device.import(unsafe { BorrowedFd::borrow_raw(acquire) })?;
+//!             let release = device.import(unsafe { BorrowedFd::borrow_raw(release) })?;
+//!             acquire.wait(timeline.acquire_point(), Duration::from_millis(100))?;
+//!             Some((release, timeline.release_point()))
+//!         }
+//!         None => None,
+//!     };
+//!
+//!     // read the DMA-BUF of the first data here
+//!
+//!     if let Some((release, point)) = sync {
+//!         release.signal(point)?;
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::{
+    fs::OpenOptions,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd},
+    path::Path,
+    time::Duration,
+};
+
+use crate::Error;
+
+mod ioctl {
+    //! The syncobj ioctls of `drm.h`.
+
+    #[repr(C)]
+    pub struct SyncobjHandle {
+        pub handle: u32,
+        pub flags: u32,
+        pub fd: i32,
+        pub pad: u32,
+    }
+
+    #[repr(C)]
+    pub struct SyncobjDestroy {
+        pub handle: u32,
+        pub pad: u32,
+    }
+
+    #[repr(C)]
+    pub struct SyncobjTimelineWait {
+        pub handles: u64,
+        pub points: u64,
+        pub timeout_nsec: i64,
+        pub count_handles: u32,
+        pub flags: u32,
+        pub first_signaled: u32,
+        pub pad: u32,
+    }
+
+    #[repr(C)]
+    pub struct SyncobjTimelineArray {
+        pub handles: u64,
+        pub points: u64,
+        pub count_handles: u32,
+        pub flags: u32,
+    }
+
+    /// Wait for the point to be submitted instead of failing when it has no fence yet.
+    pub const WAIT_FLAGS_WAIT_FOR_SUBMIT: u32 = 1 << 1;
+
+    nix::ioctl_readwrite!(destroy, b'd', 0xc0, SyncobjDestroy);
+    nix::ioctl_readwrite!(fd_to_handle, b'd', 0xc2, SyncobjHandle);
+    nix::ioctl_readwrite!(timeline_wait, b'd', 0xca, SyncobjTimelineWait);
+    nix::ioctl_readwrite!(timeline_signal, b'd', 0xcd, SyncobjTimelineArray);
+}
+
+/// A DRM device, usually a render node such as `/dev/dri/renderD128`, used to wait for
+/// and signal the points of syncobj timelines.
+#[derive(Debug)]
+pub struct DrmDevice {
+    fd: OwnedFd,
+}
+
+impl DrmDevice {
+    /// Open the DRM device at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Self { fd: file.into() })
+    }
+
+    /// Use an already open DRM device.
+    pub fn from_fd(fd: OwnedFd) -> Self {
+        Self { fd }
+    }
+
+    /// Import a syncobj fd, such as one of [`BufferRef::sync_objs`](crate::buffer::BufferRef::sync_objs).
+    ///
+    /// The fd is not consumed, the returned [`SyncObj`] refers to the same syncobj.
+    pub fn import(&self, syncobj: BorrowedFd) -> Result<SyncObj<'_>, Error> {
+        let mut args = ioctl::SyncobjHandle {
+            handle: 0,
+            flags: 0,
+            fd: syncobj.as_raw_fd(),
+            pad: 0,
+        };
+        unsafe { ioctl::fd_to_handle(self.fd.as_raw_fd(), &mut args) }?;
+        Ok(SyncObj {
+            device: self,
+            handle: args.handle,
+        })
+    }
+}
+
+impl AsFd for DrmDevice {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+/// A syncobj imported in a [`DrmDevice`], released when dropped.
+#[derive(Debug)]
+pub struct SyncObj<'d> {
+    device: &'d DrmDevice,
+    handle: u32,
+}
+
+impl SyncObj<'_> {
+    /// The handle of the syncobj in its device.
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Wait until `point` of the timeline is signaled, for at most `timeout`.
+    ///
+    /// Fails with `ETIME` if the point was not signaled in time.
+    pub fn wait(&self, point: u64, timeout: Duration) -> Result<(), Error> {
+        let mut now: libc::timespec = unsafe { std::mem::zeroed() };
+        // the timeout of the ioctl is an absolute time of the monotonic clock
+        if unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now) } < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        // the fields are 32 bits wide on some targets
+        #[allow(clippy::unnecessary_cast)]
+        let now = now.tv_sec as i64 * 1_000_000_000 + now.tv_nsec as i64;
+        let timeout = i64::try_from(timeout.as_nanos()).unwrap_or(i64::MAX);
+
+        let mut args = ioctl::SyncobjTimelineWait {
+            handles: std::ptr::addr_of!(self.handle) as u64,
+            points: std::ptr::addr_of!(point) as u64,
+            timeout_nsec: now.saturating_add(timeout),
+            count_handles: 1,
+            flags: ioctl::WAIT_FLAGS_WAIT_FOR_SUBMIT,
+            first_signaled: 0,
+            pad: 0,
+        };
+        unsafe { ioctl::timeline_wait(self.device.fd.as_raw_fd(), &mut args) }?;
+        Ok(())
+    }
+
+    /// Signal `point` of the timeline.
+    pub fn signal(&self, point: u64) -> Result<(), Error> {
+        let mut args = ioctl::SyncobjTimelineArray {
+            handles: std::ptr::addr_of!(self.handle) as u64,
+            points: std::ptr::addr_of!(point) as u64,
+            count_handles: 1,
+            flags: 0,
+        };
+        unsafe { ioctl::timeline_signal(self.device.fd.as_raw_fd(), &mut args) }?;
+        Ok(())
+    }
+}
+
+impl Drop for SyncObj<'_> {
+    fn drop(&mut self) {
+        let mut args = ioctl::SyncobjDestroy {
+            handle: self.handle,
+            pad: 0,
+        };
+        let _ = unsafe { ioctl::destroy(self.device.fd.as_raw_fd(), &mut args) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn not_a_drm_device() {
+        // syncobj ioctls fail on other files
+        let device = DrmDevice::open("/dev/null").unwrap();
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let err = device.import(file.as_fd()).unwrap_err();
+        assert!(err.errno().is_some());
+    }
+}
@@ -0,0 +1,372 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! The endpoint stream interface of the session manager extension.
+//!
+//! An endpoint stream is a stream of an [`Endpoint`](crate::endpoint::Endpoint), such as the
+//! multimedia or the voice stream of a headset, which can be linked to the streams of other
+//! endpoints.
+
+use bitflags::bitflags;
+use libc::c_void;
+use std::{fmt, mem};
+use std::{pin::Pin, ptr};
+
+use crate::{
+    proxy::{impl_listener, impl_transparent_proxy, Proxy, ProxyT},
+    thread_loop::LoopHandle,
+    types::ObjectType,
+    utils::cstr_to_str,
+    Error,
+};
+use spa::{pod::Pod, spa_interface_call_method, utils::result::SpaResult};
+
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct EndpointStream {
+    proxy: Proxy,
+}
+
+impl EndpointStream {
+    #[must_use]
+    pub fn add_listener_local(&self) -> EndpointStreamListenerLocalBuilder {
+        EndpointStreamListenerLocalBuilder {
+            stream: self,
+            cbs: ListenerLocalCallbacks::default(),
+        }
+    }
+
+    /// Subscribe to parameter changes
+    ///
+    /// Automatically emit `param` events for the given ids when they are changed
+    pub fn subscribe_params(&self, ids: &[spa::param::ParamType]) -> Result<(), Error> {
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.checked_ptr()?,
+                pw_sys::pw_endpoint_stream_methods,
+                subscribe_params,
+                ids.as_ptr() as *mut _,
+                ids.len().try_into().unwrap()
+            )
+        };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
+    }
+
+    /// Enumerate endpoint stream parameters
+    ///
+    /// Start enumeration of endpoint stream parameters. For each param, a
+    /// param event will be emitted.
+    ///
+    /// # Parameters
+    /// `seq`: a sequence number to place in the reply \
+    /// `id`: the parameter id to enum, or [`None`] to allow any id \
+    /// `start`: the start index or 0 for the first param \
+    /// `num`: the maximum number of params to retrieve ([`u32::MAX`] may be used to retrieve all params) \
+    /// `filter`: a param to filter the results with, or [`None`] to retrieve all params
+    pub fn enum_params(
+        &self,
+        seq: i32,
+        id: Option<spa::param::ParamType>,
+        start: u32,
+        num: u32,
+        filter: Option<&Pod>,
+    ) -> Result<(), Error> {
+        let id = id.map(|id| id.as_raw()).unwrap_or(crate::constants::ID_ANY);
+        let filter = filter.map_or(ptr::null(), |filter| filter.as_raw_ptr().cast_const());
+
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.checked_ptr()?,
+                pw_sys::pw_endpoint_stream_methods,
+                enum_params,
+                seq,
+                id,
+                start,
+                num,
+                filter
+            )
+        };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
+    }
+
+    /// Set a parameter on the endpoint stream.
+    pub fn set_param(
+        &self,
+        id: spa::param::ParamType,
+        flags: u32,
+        param: &Pod,
+    ) -> Result<(), Error> {
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.checked_ptr()?,
+                pw_sys::pw_endpoint_stream_methods,
+                set_param,
+                id.as_raw(),
+                flags,
+                param.as_raw_ptr()
+            )
+        };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
+    }
+}
+
+impl ProxyT for EndpointStream {
+    fn type_() -> ObjectType {
+        ObjectType::EndpointStream
+    }
+
+    fn upcast(self) -> Proxy {
+        self.proxy
+    }
+
+    fn upcast_ref(&self) -> &Proxy {
+        &self.proxy
+    }
+
+    unsafe fn from_proxy_unchecked(proxy: Proxy) -> Self
+    where
+        Self: Sized,
+    {
+        Self { proxy }
+    }
+}
+
+impl_transparent_proxy!(EndpointStream);
+
+#[derive(Default)]
+struct ListenerLocalCallbacks {
+    #[allow(clippy::type_complexity)]
+    info: Option<Box<dyn FnMut(&EndpointStreamInfoRef)>>,
+    #[allow(clippy::type_complexity)]
+    param: Option<Box<dyn FnMut(i32, spa::param::ParamType, u32, u32, Option<&Pod>)>>,
+}
+
+/// Builder of a listener for the events of an [`EndpointStream`],
+/// see [`EndpointStream::add_listener_local`].
+///
+/// **Events emitted while one of the callbacks is running are lost.** The callbacks are never
+/// called re-entrantly: an event received when a callback iterates the loop, e.g. to wait for a
+/// reply, or emitted synchronously by a method it calls, is dropped without notice.
+pub struct EndpointStreamListenerLocalBuilder<'a> {
+    stream: &'a EndpointStream,
+    cbs: ListenerLocalCallbacks,
+}
+
+/// The info of an endpoint stream, as emitted by its `info` event.
+#[repr(transparent)]
+pub struct EndpointStreamInfoRef(pw_sys::pw_endpoint_stream_info);
+
+impl EndpointStreamInfoRef {
+    pub fn as_raw(&self) -> &pw_sys::pw_endpoint_stream_info {
+        &self.0
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut pw_sys::pw_endpoint_stream_info {
+        std::ptr::addr_of!(self.0).cast_mut()
+    }
+
+    pub fn id(&self) -> u32 {
+        self.0.id
+    }
+
+    /// The id of the endpoint of the stream.
+    pub fn endpoint_id(&self) -> u32 {
+        self.0.endpoint_id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        unsafe { cstr_to_str(self.0.name) }
+    }
+
+    pub fn change_mask(&self) -> EndpointStreamChangeMask {
+        EndpointStreamChangeMask::from_bits_retain(self.0.change_mask)
+    }
+
+    /// The params describing how the stream can be linked.
+    pub fn link_params(&self) -> Option<&Pod> {
+        let link_params = self.0.link_params;
+        if link_params.is_null() {
+            None
+        } else {
+            unsafe { Some(Pod::from_raw(link_params)) }
+        }
+    }
+
+    pub fn props(&self) -> Option<&spa::utils::dict::DictRef> {
+        let props_ptr: *mut spa::utils::dict::DictRef = self.0.props.cast();
+        ptr::NonNull::new(props_ptr).map(|ptr| unsafe { ptr.as_ref() })
+    }
+
+    /// Get the param infos for the endpoint stream.
+    pub fn params(&self) -> &[spa::param::ParamInfo] {
+        let params = self.0.params;
+        if params.is_null() {
+            &[]
+        } else {
+            unsafe {
+                std::slice::from_raw_parts(params as *const _, self.0.n_params.try_into().unwrap())
+            }
+        }
+    }
+}
+
+impl fmt::Debug for EndpointStreamInfoRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EndpointStreamInfoRef")
+            .field("id", &self.id())
+            .field("endpoint-id", &self.endpoint_id())
+            .field("name", &self.name())
+            .field("change-mask", &self.change_mask())
+            .field("link-params", &self.link_params())
+            .field("props", &self.props())
+            .field("params", &self.params())
+            .finish()
+    }
+}
+
+bitflags! {
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct EndpointStreamChangeMask: u64 {
+        const LINK_PARAMS = pw_sys::PW_ENDPOINT_STREAM_CHANGE_MASK_LINK_PARAMS as u64;
+        const PROPS = pw_sys::PW_ENDPOINT_STREAM_CHANGE_MASK_PROPS as u64;
+        const PARAMS = pw_sys::PW_ENDPOINT_STREAM_CHANGE_MASK_PARAMS as u64;
+    }
+}
+
+pub struct EndpointStreamListener {
+    // Need to stay allocated while the listener is registered
+    #[allow(dead_code)]
+    events: Pin<Box<pw_sys::pw_endpoint_stream_events>>,
+    listener: Pin<Box<spa_sys::spa_hook>>,
+    #[allow(dead_code)]
+    data: Box<crate::utils::Guarded<ListenerLocalCallbacks>>,
+    loop_: LoopHandle,
+}
+
+impl_listener!(EndpointStreamListener);
+
+impl Drop for EndpointStreamListener {
+    fn drop(&mut self) {
+        let _lock = self.loop_.lock();
+        spa::utils::hook::remove(*self.listener);
+    }
+}
+
+impl<'a> EndpointStreamListenerLocalBuilder<'a> {
+    #[must_use]
+    pub fn info<F>(mut self, info: F) -> Self
+    where
+        F: FnMut(&EndpointStreamInfoRef) + 'static,
+    {
+        self.cbs.info = Some(Box::new(info));
+        self
+    }
+
+    /// Set the callback of the `param` event.
+    ///
+    /// The pod is only valid during the call, copy it with [`ToOwned::to_owned`] into an
+    /// [`OwnedPod`](spa::pod::OwnedPod) to keep it.
+    #[must_use]
+    pub fn param<F>(mut self, param: F) -> Self
+    where
+        F: FnMut(i32, spa::param::ParamType, u32, u32, Option<&Pod>) + 'static,
+    {
+        self.cbs.param = Some(Box::new(param));
+        self
+    }
+
+    #[must_use]
+    pub fn register(self) -> EndpointStreamListener {
+        unsafe extern "C" fn endpoint_stream_events_info(
+            data: *mut c_void,
+            info: *const pw_sys::pw_endpoint_stream_info,
+        ) {
+            crate::utils::catch_callback_panic("endpoint stream info", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                let info = ptr::NonNull::new(info as *mut pw_sys::pw_endpoint_stream_info)
+                    .expect("info is NULL");
+                let info = info.cast::<EndpointStreamInfoRef>().as_ref();
+                callbacks.info.as_mut().unwrap()(info);
+            })
+        }
+
+        unsafe extern "C" fn endpoint_stream_events_param(
+            data: *mut c_void,
+            seq: i32,
+            id: u32,
+            index: u32,
+            next: u32,
+            param: *const spa_sys::spa_pod,
+        ) {
+            crate::utils::catch_callback_panic("endpoint stream param", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+
+                let id = spa::param::ParamType::from_raw(id);
+                let param = if !param.is_null() {
+                    unsafe { Some(Pod::from_raw(param)) }
+                } else {
+                    None
+                };
+
+                callbacks.param.as_mut().unwrap()(seq, id, index, next, param);
+            })
+        }
+
+        let e = unsafe {
+            let mut e: Pin<Box<pw_sys::pw_endpoint_stream_events>> = Box::pin(mem::zeroed());
+            e.version = pw_sys::PW_VERSION_ENDPOINT_STREAM_EVENTS;
+
+            if self.cbs.info.is_some() {
+                e.info = Some(endpoint_stream_events_info);
+            }
+            if self.cbs.param.is_some() {
+                e.param = Some(endpoint_stream_events_param);
+            }
+
+            e
+        };
+
+        let (listener, data) = unsafe {
+            let stream = &self.stream.proxy.as_ptr();
+
+            let data = Box::into_raw(Box::new(crate::utils::Guarded::new(self.cbs)));
+            let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
+            let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
+
+            if self.stream.proxy.is_connected() {
+                spa_interface_call_method!(
+                    stream,
+                    pw_sys::pw_endpoint_stream_methods,
+                    add_listener,
+                    listener_ptr.cast(),
+                    e.as_ref().get_ref(),
+                    data as *mut _
+                );
+                spa::utils::hook::reset_on_remove(&mut *listener_ptr);
+            }
+
+            (listener, Box::from_raw(data))
+        };
+
+        EndpointStreamListener {
+            events: e,
+            listener,
+            data,
+            loop_: self.stream.proxy.loop_handle(),
+        }
+    }
+}
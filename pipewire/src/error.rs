@@ -1,15 +1,82 @@
 // Copyright The pipewire-rs Contributors.
 // SPDX-License-Identifier: MIT
 
+use nix::errno::Errno;
 use thiserror::Error;
+
+/// Errors returned by the methods of this crate.
+///
+/// Failures reported by PipeWire as a negative errno, such as the result of the methods of proxies,
+/// are [`Error::SpaError`], whose [`Display`](std::fmt::Display) is the `strerror` text of the errno.
+/// Use [`Error::errno`] to match on the errno of any error.
 #[derive(Error, Debug)]
 pub enum Error {
+    /// An object could not be created, without an errno to tell why.
     #[error("Creation failed")]
     CreationFailed,
     #[error("No memory")]
     NoMemory,
+    /// A proxy is not of the expected type.
     #[error("Wrong proxy type")]
     WrongProxyType,
+    /// A negative errno returned by PipeWire.
     #[error(transparent)]
     SpaError(#[from] spa::utils::result::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl Error {
+    /// Build an error from the current `errno`.
+    ///
+    /// This is meant to be used after a PipeWire function signaled a failure by returning `NULL`.
+    /// If `errno` is not set, [`Error::CreationFailed`] is returned instead.
+    pub(crate) fn last_os_error() -> Self {
+        let errno = nix::errno::Errno::last() as i32;
+
+        match spa::utils::result::SpaResult::from_c(-errno).into_result() {
+            Err(e) => Error::SpaError(e),
+            Ok(_) => Error::CreationFailed,
+        }
+    }
+
+    /// The errno of the error, if it has one.
+    ///
+    /// [`Error::NoMemory`] is mapped to `ENOMEM`.
+    pub fn errno(&self) -> Option<Errno> {
+        match self {
+            Error::CreationFailed | Error::WrongProxyType => None,
+            Error::NoMemory => Some(Errno::ENOMEM),
+            Error::SpaError(e) => Some(e.errno()),
+            Error::Io(e) => e.raw_os_error().map(Errno::from_i32),
+        }
+    }
+}
+
+impl From<Errno> for Error {
+    fn from(errno: Errno) -> Self {
+        Error::SpaError(errno.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn errno() {
+        let err = Error::from(Errno::ENOENT);
+        assert_eq!(err.errno(), Some(Errno::ENOENT));
+        assert_eq!(err.to_string(), "ENOENT: No such file or directory");
+
+        let err = Error::from(std::io::Error::from_raw_os_error(libc::EACCES));
+        assert_eq!(err.errno(), Some(Errno::EACCES));
+
+        let err = Error::from(std::io::Error::other("oops"));
+        assert_eq!(err.errno(), None);
+
+        assert_eq!(Error::NoMemory.errno(), Some(Errno::ENOMEM));
+        assert_eq!(Error::WrongProxyType.errno(), None);
+    }
 }
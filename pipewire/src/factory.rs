@@ -5,16 +5,19 @@ use bitflags::bitflags;
 use libc::c_void;
 use std::ops::Deref;
 use std::pin::Pin;
-use std::{ffi::CStr, ptr};
+use std::ptr;
 use std::{fmt, mem};
 
 use crate::{
-    proxy::{Listener, Proxy, ProxyT},
+    proxy::{impl_listener, impl_transparent_proxy, Proxy, ProxyT},
+    thread_loop::LoopHandle,
     types::ObjectType,
+    utils::cstr_to_str,
 };
 use spa::spa_interface_call_method;
 
 #[derive(Debug)]
+#[repr(transparent)]
 pub struct Factory {
     proxy: Proxy,
 }
@@ -40,6 +43,8 @@ impl ProxyT for Factory {
     }
 }
 
+impl_transparent_proxy!(Factory);
+
 impl Factory {
     // TODO: add non-local version when we'll bind pw_thread_loop_start()
     #[must_use]
@@ -54,9 +59,14 @@ impl Factory {
 #[derive(Default)]
 struct ListenerLocalCallbacks {
     #[allow(clippy::type_complexity)]
-    info: Option<Box<dyn Fn(&FactoryInfoRef)>>,
+    info: Option<Box<dyn FnMut(&FactoryInfoRef)>>,
 }
 
+/// Builder of a listener for the events of a [`Factory`], see [`Factory::add_listener_local`].
+///
+/// **Events emitted while one of the callbacks is running are lost.** The callbacks are never
+/// called re-entrantly: an event received when a callback iterates the loop, e.g. to wait for a
+/// reply, or emitted synchronously by a method it calls, is dropped without notice.
 pub struct FactoryListenerLocalBuilder<'a> {
     factory: &'a Factory,
     cbs: ListenerLocalCallbacks,
@@ -78,8 +88,14 @@ impl FactoryInfoRef {
         self.0.id
     }
 
+    pub fn name(&self) -> Option<&str> {
+        unsafe { cstr_to_str(self.0.name) }
+    }
+
+    /// The type of the objects created by the factory, [`ObjectType::Other`] with an empty name
+    /// if the server did not provide a valid one.
     pub fn type_(&self) -> ObjectType {
-        ObjectType::from_str(unsafe { CStr::from_ptr(self.0.type_).to_str().unwrap() })
+        ObjectType::from_interface_name(unsafe { cstr_to_str(self.0.type_) }.unwrap_or_default())
     }
 
     pub fn version(&self) -> u32 {
@@ -87,7 +103,7 @@ impl FactoryInfoRef {
     }
 
     pub fn change_mask(&self) -> FactoryChangeMask {
-        FactoryChangeMask::from_bits(self.0.change_mask).expect("invalid change_mask")
+        FactoryChangeMask::from_bits_retain(self.0.change_mask)
     }
 
     pub fn props(&self) -> Option<&spa::utils::dict::DictRef> {
@@ -100,9 +116,10 @@ impl fmt::Debug for FactoryInfoRef {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("FactoryInfoRef")
             .field("id", &self.id())
+            .field("name", &self.name())
             .field("type", &self.type_())
             .field("version", &self.version())
-            .field("change_mask", &self.change_mask())
+            .field("change-mask", &self.change_mask())
             .field("props", &self.props())
             .finish()
     }
@@ -159,9 +176,10 @@ impl fmt::Debug for FactoryInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("FactoryInfo")
             .field("id", &self.id())
+            .field("name", &self.name())
             .field("type", &self.type_())
             .field("version", &self.version())
-            .field("change_mask", &self.change_mask())
+            .field("change-mask", &self.change_mask())
             .field("props", &self.props())
             .finish()
     }
@@ -173,13 +191,15 @@ pub struct FactoryListener {
     events: Pin<Box<pw_sys::pw_factory_events>>,
     listener: Pin<Box<spa_sys::spa_hook>>,
     #[allow(dead_code)]
-    data: Box<ListenerLocalCallbacks>,
+    data: Box<crate::utils::Guarded<ListenerLocalCallbacks>>,
+    loop_: LoopHandle,
 }
 
-impl Listener for FactoryListener {}
+impl_listener!(FactoryListener);
 
 impl Drop for FactoryListener {
     fn drop(&mut self) {
+        let _lock = self.loop_.lock();
         spa::utils::hook::remove(*self.listener);
     }
 }
@@ -188,7 +208,7 @@ impl<'a> FactoryListenerLocalBuilder<'a> {
     #[must_use]
     pub fn info<F>(mut self, info: F) -> Self
     where
-        F: Fn(&FactoryInfoRef) + 'static,
+        F: FnMut(&FactoryInfoRef) + 'static,
     {
         self.cbs.info = Some(Box::new(info));
         self
@@ -200,11 +220,17 @@ impl<'a> FactoryListenerLocalBuilder<'a> {
             data: *mut c_void,
             info: *const pw_sys::pw_factory_info,
         ) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            let info =
-                ptr::NonNull::new(info as *mut pw_sys::pw_factory_info).expect("info is NULL");
-            let info = info.cast::<FactoryInfoRef>().as_ref();
-            callbacks.info.as_ref().unwrap()(info);
+            crate::utils::catch_callback_panic("factory info", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                let info =
+                    ptr::NonNull::new(info as *mut pw_sys::pw_factory_info).expect("info is NULL");
+                let info = info.cast::<FactoryInfoRef>().as_ref();
+                callbacks.info.as_mut().unwrap()(info);
+            })
         }
 
         let e = unsafe {
@@ -221,18 +247,21 @@ impl<'a> FactoryListenerLocalBuilder<'a> {
         let (listener, data) = unsafe {
             let factory = &self.factory.proxy.as_ptr();
 
-            let data = Box::into_raw(Box::new(self.cbs));
+            let data = Box::into_raw(Box::new(crate::utils::Guarded::new(self.cbs)));
             let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
             let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
 
-            spa_interface_call_method!(
-                factory,
-                pw_sys::pw_factory_methods,
-                add_listener,
-                listener_ptr.cast(),
-                e.as_ref().get_ref(),
-                data as *mut _
-            );
+            if self.factory.proxy.is_connected() {
+                spa_interface_call_method!(
+                    factory,
+                    pw_sys::pw_factory_methods,
+                    add_listener,
+                    listener_ptr.cast(),
+                    e.as_ref().get_ref(),
+                    data as *mut _
+                );
+                spa::utils::hook::reset_on_remove(&mut *listener_ptr);
+            }
 
             (listener, Box::from_raw(data))
         };
@@ -241,6 +270,7 @@ impl<'a> FactoryListenerLocalBuilder<'a> {
             events: e,
             listener,
             data,
+            loop_: self.factory.proxy.loop_handle(),
         }
     }
 }
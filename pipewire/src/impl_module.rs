@@ -0,0 +1,83 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Modules loaded into a local [`Context`].
+
+use std::{fmt, mem::ManuallyDrop, ptr};
+
+use crate::{context::Context, module::ModuleInfoRef, properties::PropertiesRef};
+
+/// A module loaded into a [`Context`] with [`Context::load_module`].
+///
+/// The module is unloaded when this struct is dropped.
+/// Use [`keep()`](`Self::keep`) to keep it loaded for the lifetime of the context instead.
+pub struct ImplModule {
+    ptr: ptr::NonNull<pw_sys::pw_impl_module>,
+    // The context owns the module, so it must outlive it.
+    _context: Context,
+}
+
+impl ImplModule {
+    pub(crate) fn from_ptr(ptr: ptr::NonNull<pw_sys::pw_impl_module>, context: Context) -> Self {
+        Self {
+            ptr,
+            _context: context,
+        }
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut pw_sys::pw_impl_module {
+        self.ptr.as_ptr()
+    }
+
+    /// Get the info of the module.
+    pub fn info(&self) -> &ModuleInfoRef {
+        unsafe {
+            let info = pw_sys::pw_impl_module_get_info(self.as_raw_ptr());
+            let info = ptr::NonNull::new(info.cast_mut()).expect("module info is NULL");
+            info.cast().as_ref()
+        }
+    }
+
+    /// Get the properties of the module.
+    pub fn properties(&self) -> &PropertiesRef {
+        unsafe {
+            let props = pw_sys::pw_impl_module_get_properties(self.as_raw_ptr());
+            let props = ptr::NonNull::new(props.cast_mut()).expect("module properties is NULL");
+            props.cast().as_ref()
+        }
+    }
+
+    /// Keep the module loaded until the context it was loaded into is destroyed.
+    pub fn keep(self) {
+        let _ = self.into_raw();
+    }
+
+    /// Consume the `ImplModule`, returning the raw `pw_impl_module`.
+    ///
+    /// The module is not unloaded anymore when this is called, it will be destroyed
+    /// together with its context unless the caller destroys it manually before.
+    pub fn into_raw(self) -> *mut pw_sys::pw_impl_module {
+        let mut this = ManuallyDrop::new(self);
+
+        // Only the raw module must be leaked, the reference to the context still needs to be released.
+        unsafe {
+            ptr::drop_in_place(ptr::addr_of_mut!(this._context));
+        }
+
+        this.ptr.as_ptr()
+    }
+}
+
+impl Drop for ImplModule {
+    fn drop(&mut self) {
+        unsafe { pw_sys::pw_impl_module_destroy(self.as_raw_ptr()) }
+    }
+}
+
+impl fmt::Debug for ImplModule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImplModule")
+            .field("info", self.info())
+            .finish()
+    }
+}
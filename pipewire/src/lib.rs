@@ -64,6 +64,9 @@
 //! Note that registering any callback requires the closure to have the `'static` lifetime, so if you need to capture
 //! any variables, use `move ||` closures, and use `std::rc::Rc`s to access shared variables
 //! and some `std::cell` variant if you need to mutate them.
+//! Callbacks are [`FnMut`], so state owned by a single callback can be mutated directly.
+//! As a callback is borrowed mutably for the duration of the call, the events of its listener or
+//! source dispatched while it runs, e.g. by running the loop from inside it, are skipped.
 //!
 //! Also note that we called `mainloop.run()` at the end.
 //! This will enter the loop, and won't return until we call `mainloop.quit()` from some event.
@@ -83,7 +86,7 @@
 //! fn main() -> Result<(), Box<dyn std::error::Error>> {
 //!     let mainloop = MainLoop::new(None)?;
 //!
-//!     let timer = mainloop.loop_().add_timer(|_| println!("Hello"));
+//!     let timer = mainloop.loop_().add_timer(|_| println!("Hello"))?;
 //!     // Call the first time in half a second, and then in a one second interval.
 //!     timer.update_timer(Some(Duration::from_millis(500)), Some(Duration::from_secs(1))).into_result()?;
 //!
@@ -107,6 +110,40 @@
 //! we use a [`pipewire::channel`](`crate::channel`) instead.
 //!
 //! See the [`pipewire::channel`](`crate::channel`) module for details.
+//!
+//! Objects built for a [`ThreadLoop`](`thread_loop::ThreadLoop`) with its lock held can also be moved
+//! to another thread as a bundle by wrapping them in a [`ThreadBound`](`thread_loop::ThreadBound`).
+//!
+//! ## Library versions
+//! By default, the crate only uses the API of libpipewire 0.3, so it builds against any of its releases.
+//! APIs added in later releases are enabled by the `v0_3_xx` cargo features, each of which requires
+//! at least that version of libpipewire when building and enables all the features of older versions:
+//!
+//! | Feature   | Minimum libpipewire | Enables                                                               |
+//! |-----------|---------------------|-----------------------------------------------------------------------|
+//! | `v0_3_32` | 0.3.32              | more [`keys`], such as `NODE_LINK_GROUP`, realtime [`thread`]s        |
+//! | `v0_3_33` | 0.3.33              | the `NODE_RATE` and `NODE_WANT_DRIVER` keys                           |
+//! | `v0_3_34` | 0.3.34              | `Stream::trigger_process` and `Stream::is_driving`                    |
+//! | `v0_3_39` | 0.3.39              | the `command` stream event                                            |
+//! | `v0_3_40` | 0.3.40              | the `trigger_done` stream event                                       |
+//! | `v0_3_41` | 0.3.41              | the `TRIGGER` stream flag and the `OBJECT_SERIAL` key                 |
+//! | `v0_3_43` | 0.3.43              | the `AUDIO_ALLOWED_RATES` key                                         |
+//! | `v0_3_44` | 0.3.44              | the `TARGET_OBJECT` key                                               |
+//! | `v0_3_45` | 0.3.45              | the `NODE_FORCE_QUANTUM` and `NODE_FORCE_RATE` keys, config sections  |
+//! | `v0_3_49` | 0.3.49              | `Buffer::requested`                                                   |
+//! | `v0_3_53` | 0.3.53              | the `DEVICE_SYSFS_PATH` key                                           |
+//! | `v0_3_57` | 0.3.57              | the config override keys                                              |
+//! | `v0_3_64` | 0.3.64              | the `NODE_CHANNELNAMES` key                                           |
+//! | `v0_3_65` | 0.3.65              | the newer SPA types of `libspa`                                       |
+//! | `v0_3_77` | 0.3.77              | the `bound_props` proxy event, the `L` permission, security contexts  |
+//! | `v0_3_79` | 0.3.79              | the `Tag` param of `libspa`                                           |
+//!
+//! The same features exist on `pipewire-sys`, which checks the installed version with `system-deps`.
+//!
+//! The `drm-syncobj` feature enables the [`drm_syncobj`](`crate::drm_syncobj`) module, to wait for and
+//! signal the syncobjs of DMA-BUF buffers using explicit sync.
+//! The `futures` feature implements the `Stream` trait of `futures-core` for
+//! [`AsyncReceiver`](`crate::channel::AsyncReceiver`).
 
 pub mod buffer;
 pub mod channel;
@@ -114,23 +151,41 @@ pub mod client;
 pub mod constants;
 pub mod context;
 pub mod core;
+pub mod data_loop;
 pub mod device;
+#[cfg(feature = "drm-syncobj")]
+pub mod drm_syncobj;
+pub mod endpoint;
+pub mod endpoint_stream;
 pub mod factory;
+pub mod impl_module;
 pub mod keys;
 pub mod link;
+pub mod local_node;
+#[cfg(feature = "log")]
+pub mod log;
 pub mod loop_;
 pub mod main_loop;
+pub mod mem;
 pub mod metadata;
 pub mod module;
 pub mod node;
 pub mod permissions;
 pub mod port;
+pub mod profiler;
 pub mod properties;
 pub mod proxy;
+pub mod reconnect;
 pub mod registry;
+#[cfg(feature = "v0_3_77")]
+pub mod security_context;
 pub mod stream;
+#[cfg(feature = "v0_3_32")]
+pub mod thread;
 pub mod thread_loop;
 pub mod types;
+pub mod version;
+pub mod video_frame;
 
 mod error;
 pub use error::*;
@@ -140,25 +195,109 @@ mod utils;
 pub use pw_sys as sys;
 pub use spa;
 
-use std::ptr;
+use std::{ptr, sync::Mutex};
+
+/// How PipeWire is currently initialized by this crate.
+struct InitState {
+    /// Number of live [`InitGuard`]s, plus one if PipeWire was initialized by [`init`].
+    refs: usize,
+    /// Whether the last [`InitGuard`] has been dropped, deinitializing PipeWire.
+    deinitialized: bool,
+}
+
+static INIT_STATE: Mutex<InitState> = Mutex::new(InitState {
+    refs: 0,
+    deinitialized: false,
+});
+
+fn lock_init_state() -> std::sync::MutexGuard<'static, InitState> {
+    INIT_STATE.lock().unwrap_or_else(|err| err.into_inner())
+}
 
 /// Initialize PipeWire
 ///
 /// Initialize the PipeWire system and set up debugging
 /// through the environment variable `PIPEWIRE_DEBUG`.
+///
+/// PipeWire stays initialized for the rest of the process, unless it was already initialized
+/// by an [`InitGuard`], in which case this does nothing.
 pub fn init() {
-    use once_cell::sync::OnceCell;
-    static INITIALIZED: OnceCell<()> = OnceCell::new();
-    INITIALIZED.get_or_init(|| unsafe { pw_sys::pw_init(ptr::null_mut(), ptr::null_mut()) });
+    let mut state = lock_init_state();
+    debug_assert!(
+        !state.deinitialized,
+        "PipeWire was used after the last InitGuard was dropped"
+    );
+    if state.refs == 0 {
+        unsafe { pw_sys::pw_init(ptr::null_mut(), ptr::null_mut()) };
+        state.refs = 1;
+    }
+}
+
+/// Initialize PipeWire until the returned guard is dropped.
+///
+/// Guards can be nested: PipeWire is initialized by the first one and deinitialized when the last one
+/// is dropped, which releases the global resources of libpipewire, as needed when checking for leaks
+/// or when a plugin using PipeWire is unloaded.
+/// Using any PipeWire API after the last guard was dropped is undefined behavior,
+/// and panics in debug builds when creating loops.
+///
+/// If PipeWire was initialized by [`init`] first, it stays initialized after the guards are dropped.
+///
+/// ```
+/// let guard = pipewire::init_guarded();
+/// let mainloop = pipewire::main_loop::MainLoop::new(None).unwrap();
+/// // all PipeWire objects must be dropped before the last guard
+/// drop(mainloop);
+/// drop(guard);
+/// ```
+#[must_use = "PipeWire is deinitialized when the guard is dropped"]
+pub fn init_guarded() -> InitGuard {
+    let mut state = lock_init_state();
+    debug_assert!(
+        !state.deinitialized,
+        "PipeWire was used after the last InitGuard was dropped"
+    );
+    if state.refs == 0 {
+        unsafe { pw_sys::pw_init(ptr::null_mut(), ptr::null_mut()) };
+    }
+    state.refs += 1;
+
+    InitGuard { _private: () }
+}
+
+/// Keeps PipeWire initialized, see [`init_guarded`].
+#[derive(Debug)]
+pub struct InitGuard {
+    _private: (),
+}
+
+impl Drop for InitGuard {
+    fn drop(&mut self) {
+        let mut state = lock_init_state();
+        // already deinitialized by deinit()
+        if state.refs == 0 {
+            return;
+        }
+        state.refs -= 1;
+        if state.refs == 0 {
+            unsafe { pw_sys::pw_deinit() };
+            state.deinitialized = true;
+        }
+    }
 }
 
 /// Deinitialize PipeWire
 ///
+/// Prefer [`init_guarded`], which deinitializes PipeWire once it is not used anymore.
+/// A later call to [`init`] or [`init_guarded`] initializes PipeWire again.
+///
 /// # Safety
 /// This must only be called once during the lifetime of the process, once no PipeWire threads
 /// are running anymore and all PipeWire resources are released.
 pub unsafe fn deinit() {
-    pw_sys::pw_deinit()
+    let mut state = lock_init_state();
+    pw_sys::pw_deinit();
+    state.refs = 0;
 }
 
 #[cfg(test)]
@@ -1,20 +1,30 @@
 use std::{
+    cell::RefCell,
     ffi::{c_void, CStr},
     fmt, mem,
     ops::Deref,
     pin::Pin,
     ptr,
+    rc::Rc,
 };
 
 use bitflags::bitflags;
+use nix::errno::Errno;
 use spa::spa_interface_call_method;
 
 use crate::{
-    proxy::{Listener, Proxy, ProxyT},
+    core::CoreRef,
+    keys,
+    main_loop::MainLoop,
+    properties::Properties,
+    proxy::{impl_listener, impl_transparent_proxy, Proxy, ProxyError, ProxyListener, ProxyT},
+    thread_loop::LoopHandle,
     types::ObjectType,
+    Error,
 };
 
 #[derive(Debug)]
+#[repr(transparent)]
 pub struct Link {
     proxy: Proxy,
 }
@@ -40,6 +50,8 @@ impl ProxyT for Link {
     }
 }
 
+impl_transparent_proxy!(Link);
+
 impl Link {
     #[must_use]
     pub fn add_listener_local(&self) -> LinkListenerLocalBuilder {
@@ -56,13 +68,15 @@ pub struct LinkListener {
     events: Pin<Box<pw_sys::pw_link_events>>,
     listener: Pin<Box<spa_sys::spa_hook>>,
     #[allow(dead_code)]
-    data: Box<ListenerLocalCallbacks>,
+    data: Box<crate::utils::Guarded<ListenerLocalCallbacks>>,
+    loop_: LoopHandle,
 }
 
-impl Listener for LinkListener {}
+impl_listener!(LinkListener);
 
 impl Drop for LinkListener {
     fn drop(&mut self) {
+        let _lock = self.loop_.lock();
         spa::utils::hook::remove(*self.listener);
     }
 }
@@ -70,9 +84,14 @@ impl Drop for LinkListener {
 #[derive(Default)]
 struct ListenerLocalCallbacks {
     #[allow(clippy::type_complexity)]
-    info: Option<Box<dyn Fn(&LinkInfoRef)>>,
+    info: Option<Box<dyn FnMut(&LinkInfoRef)>>,
 }
 
+/// Builder of a listener for the events of a [`Link`], see [`Link::add_listener_local`].
+///
+/// **Events emitted while one of the callbacks is running are lost.** The callbacks are never
+/// called re-entrantly: an event received when a callback iterates the loop, e.g. to wait for a
+/// reply, or emitted synchronously by a method it calls, is dropped without notice.
 pub struct LinkListenerLocalBuilder<'link> {
     link: &'link Link,
     cbs: ListenerLocalCallbacks,
@@ -82,7 +101,7 @@ impl<'a> LinkListenerLocalBuilder<'a> {
     #[must_use]
     pub fn info<F>(mut self, info: F) -> Self
     where
-        F: Fn(&LinkInfoRef) + 'static,
+        F: FnMut(&LinkInfoRef) + 'static,
     {
         self.cbs.info = Some(Box::new(info));
         self
@@ -94,10 +113,17 @@ impl<'a> LinkListenerLocalBuilder<'a> {
             data: *mut c_void,
             info: *const pw_sys::pw_link_info,
         ) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            let info = ptr::NonNull::new(info as *mut pw_sys::pw_link_info).expect("info is NULL");
-            let info = info.cast::<LinkInfoRef>().as_ref();
-            callbacks.info.as_ref().unwrap()(info);
+            crate::utils::catch_callback_panic("link info", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                let info =
+                    ptr::NonNull::new(info as *mut pw_sys::pw_link_info).expect("info is NULL");
+                let info = info.cast::<LinkInfoRef>().as_ref();
+                callbacks.info.as_mut().unwrap()(info);
+            })
         }
 
         let e = unsafe {
@@ -114,18 +140,21 @@ impl<'a> LinkListenerLocalBuilder<'a> {
         let (listener, data) = unsafe {
             let link = &self.link.proxy.as_ptr();
 
-            let data = Box::into_raw(Box::new(self.cbs));
+            let data = Box::into_raw(Box::new(crate::utils::Guarded::new(self.cbs)));
             let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
             let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
 
-            spa_interface_call_method!(
-                link,
-                pw_sys::pw_link_methods,
-                add_listener,
-                listener_ptr.cast(),
-                e.as_ref().get_ref(),
-                data as *mut _
-            );
+            if self.link.proxy.is_connected() {
+                spa_interface_call_method!(
+                    link,
+                    pw_sys::pw_link_methods,
+                    add_listener,
+                    listener_ptr.cast(),
+                    e.as_ref().get_ref(),
+                    data as *mut _
+                );
+                spa::utils::hook::reset_on_remove(&mut *listener_ptr);
+            }
 
             (listener, Box::from_raw(data))
         };
@@ -134,6 +163,7 @@ impl<'a> LinkListenerLocalBuilder<'a> {
             events: e,
             listener,
             data,
+            loop_: self.link.proxy.loop_handle(),
         }
     }
 }
@@ -173,17 +203,17 @@ impl LinkInfoRef {
     pub fn state(&self) -> LinkState {
         let raw_state = self.0.state;
         match raw_state {
-            pw_sys::pw_link_state_PW_LINK_STATE_ERROR => {
+            pw_sys::pw_link_state::PW_LINK_STATE_ERROR => {
                 let error = unsafe { CStr::from_ptr(self.0.error).to_str().unwrap() };
                 LinkState::Error(error)
             }
-            pw_sys::pw_link_state_PW_LINK_STATE_UNLINKED => LinkState::Unlinked,
-            pw_sys::pw_link_state_PW_LINK_STATE_INIT => LinkState::Init,
-            pw_sys::pw_link_state_PW_LINK_STATE_NEGOTIATING => LinkState::Negotiating,
-            pw_sys::pw_link_state_PW_LINK_STATE_ALLOCATING => LinkState::Allocating,
-            pw_sys::pw_link_state_PW_LINK_STATE_PAUSED => LinkState::Paused,
-            pw_sys::pw_link_state_PW_LINK_STATE_ACTIVE => LinkState::Active,
-            _ => panic!("Invalid link state: {}", raw_state),
+            pw_sys::pw_link_state::PW_LINK_STATE_UNLINKED => LinkState::Unlinked,
+            pw_sys::pw_link_state::PW_LINK_STATE_INIT => LinkState::Init,
+            pw_sys::pw_link_state::PW_LINK_STATE_NEGOTIATING => LinkState::Negotiating,
+            pw_sys::pw_link_state::PW_LINK_STATE_ALLOCATING => LinkState::Allocating,
+            pw_sys::pw_link_state::PW_LINK_STATE_PAUSED => LinkState::Paused,
+            pw_sys::pw_link_state::PW_LINK_STATE_ACTIVE => LinkState::Active,
+            _ => panic!("Invalid link state: {}", raw_state.0),
         }
     }
 
@@ -222,6 +252,51 @@ impl fmt::Debug for LinkInfoRef {
     }
 }
 
+/// Serializes the info with the keys used by `pw-dump`.
+///
+/// The fields that are not flagged in the change mask are not valid and are serialized as `null`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for LinkInfoRef {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mask = self.change_mask();
+        let has_state = mask.contains(LinkChangeMask::STATE);
+        let (state, error) = match self.state() {
+            LinkState::Error(error) => ("error", Some(error)),
+            LinkState::Unlinked => ("unlinked", None),
+            LinkState::Init => ("init", None),
+            LinkState::Negotiating => ("negotiating", None),
+            LinkState::Allocating => ("allocating", None),
+            LinkState::Paused => ("paused", None),
+            LinkState::Active => ("active", None),
+        };
+
+        let mut info = serializer.serialize_struct("LinkInfo", 10)?;
+        info.serialize_field("id", &self.id())?;
+        info.serialize_field("output-node-id", &self.output_node_id())?;
+        info.serialize_field("output-port-id", &self.output_port_id())?;
+        info.serialize_field("input-node-id", &self.input_node_id())?;
+        info.serialize_field("input-port-id", &self.input_port_id())?;
+        info.serialize_field("change-mask", &crate::utils::FlagNames(mask))?;
+        info.serialize_field("state", &has_state.then_some(state))?;
+        info.serialize_field("error", &error.filter(|_| has_state))?;
+        info.serialize_field(
+            "format",
+            &self
+                .format()
+                .filter(|_| mask.contains(LinkChangeMask::FORMAT)),
+        )?;
+        info.serialize_field(
+            "props",
+            &self
+                .props()
+                .filter(|_| mask.contains(LinkChangeMask::PROPS)),
+        )?;
+        info.end()
+    }
+}
+
 pub struct LinkInfo {
     ptr: ptr::NonNull<pw_sys::pw_link_info>,
 }
@@ -297,3 +372,210 @@ pub enum LinkState<'a> {
     Paused,
     Active,
 }
+
+/// Create a link between an output port and an input port, with the `link-factory` of the server.
+///
+/// ```no_run
+/// # fn example(core: &pipewire::core::Core, main_loop: &pipewire::main_loop::MainLoop)
+/// # -> Result<(), Box<dyn std::error::Error>> {
+/// use pipewire::link::LinkBuilder;
+///
+/// // the ids of the globals of the nodes and ports, as announced by the registry
+/// let link = LinkBuilder::new(40, 41, 50, 51).linger(true).create(core)?;
+/// let id = link.wait(main_loop)?;
+/// println!("created link {id}");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LinkBuilder {
+    props: Properties,
+}
+
+impl LinkBuilder {
+    /// The name of the factory creating links.
+    pub const FACTORY_NAME: &'static str = "link-factory";
+
+    /// Link the port `output_port` of the node `output_node` to the port `input_port` of
+    /// the node `input_node`, using the ids of their globals.
+    pub fn new(output_node: u32, output_port: u32, input_node: u32, input_port: u32) -> Self {
+        let props = Properties::new()
+            .with(*keys::LINK_OUTPUT_NODE, output_node.to_string())
+            .with(*keys::LINK_OUTPUT_PORT, output_port.to_string())
+            .with(*keys::LINK_INPUT_NODE, input_node.to_string())
+            .with(*keys::LINK_INPUT_PORT, input_port.to_string());
+
+        Self { props }
+    }
+
+    /// Whether the link stays on the server once its proxy is dropped,
+    /// by setting the `object.linger` property.
+    #[must_use]
+    pub fn linger(mut self, linger: bool) -> Self {
+        self.props.insert(*keys::OBJECT_LINGER, linger.to_string());
+        self
+    }
+
+    /// Add the extra properties `props`, such as `link.passive`.
+    #[must_use]
+    pub fn properties(mut self, props: &spa::utils::dict::DictRef) -> Self {
+        self.props.merge(props);
+        self
+    }
+
+    /// Ask the server to create the link.
+    ///
+    /// The link is created asynchronously, use [`CreatedLink::wait`] to know if it succeeded.
+    pub fn create(self, core: &CoreRef) -> Result<CreatedLink, Error> {
+        let link: Link = core.create_object(Self::FACTORY_NAME, &self.props)?;
+        let status = Rc::new(RefCell::new(LinkStatus::default()));
+
+        let listener = link
+            .add_listener_local()
+            .info({
+                let status = status.clone();
+                move |info| {
+                    let mut status = status.borrow_mut();
+                    status.id = Some(info.id());
+                    if let LinkState::Error(error) = info.state() {
+                        status.error = Some(LinkError::State(error.to_owned()));
+                    }
+                }
+            })
+            .register();
+        let proxy_listener = link
+            .upcast_ref()
+            .add_listener_local()
+            .proxy_error({
+                let status = status.clone();
+                move |error| status.borrow_mut().error = Some(LinkError::Proxy(error))
+            })
+            .register();
+
+        Ok(CreatedLink {
+            listener,
+            proxy_listener,
+            link,
+            status,
+        })
+    }
+}
+
+/// An error of a link created with a [`LinkBuilder`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    /// The server failed to create the link, such as when a port does not exist.
+    #[error(transparent)]
+    Proxy(#[from] ProxyError),
+    /// The link is in the error state, such as when the ports have no common format.
+    #[error("link error: {0}")]
+    State(String),
+    /// The loop was quit before the link was created.
+    #[error("canceled before the link was created")]
+    Canceled,
+}
+
+impl LinkError {
+    /// The errno of the error, if it has one.
+    pub fn errno(&self) -> Option<Errno> {
+        match self {
+            LinkError::Proxy(error) => Some(error.errno),
+            LinkError::State(_) => None,
+            LinkError::Canceled => Some(Errno::ECANCELED),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct LinkStatus {
+    id: Option<u32>,
+    error: Option<LinkError>,
+}
+
+/// A link created with a [`LinkBuilder`], tracking whether the server created it.
+pub struct CreatedLink {
+    // Dropped before the link they listen to
+    #[allow(dead_code)]
+    listener: LinkListener,
+    #[allow(dead_code)]
+    proxy_listener: ProxyListener,
+    link: Link,
+    status: Rc<RefCell<LinkStatus>>,
+}
+
+impl CreatedLink {
+    pub fn link(&self) -> &Link {
+        &self.link
+    }
+
+    /// Stop tracking the link, returning its proxy.
+    pub fn into_link(self) -> Link {
+        self.link
+    }
+
+    /// The id of the global of the link, once the server announced it.
+    pub fn id(&self) -> Option<u32> {
+        self.status.borrow().id
+    }
+
+    /// The last error reported for the link, if any.
+    ///
+    /// Errors are reported asynchronously, even after the link was created,
+    /// such as when the format negotiation of the ports failed.
+    pub fn error(&self) -> Option<LinkError> {
+        self.status.borrow().error.clone()
+    }
+
+    /// Run `main_loop` until the server announced the link, returning the id of its global,
+    /// or until an error is reported.
+    ///
+    /// It must not be called from a callback of `main_loop`.
+    pub fn wait(&self, main_loop: &MainLoop) -> Result<u32, LinkError> {
+        main_loop.run_until(|_| {
+            let status = self.status.borrow();
+            status.id.is_some() || status.error.is_some()
+        });
+
+        let status = self.status.borrow();
+        match (&status.error, status.id) {
+            (Some(error), _) => Err(error.clone()),
+            (None, Some(id)) => Ok(id),
+            (None, None) => Err(LinkError::Canceled),
+        }
+    }
+}
+
+impl fmt::Debug for CreatedLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CreatedLink")
+            .field("link", &self.link)
+            .field("id", &self.id())
+            .field("error", &self.error())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder() {
+        crate::init();
+
+        let extra = crate::properties::properties! {
+            "link.passive" => "true",
+        };
+        let builder = LinkBuilder::new(1, 2, 3, 4)
+            .linger(true)
+            .properties(extra.dict());
+
+        let props = &builder.props;
+        assert_eq!(props.get("link.output.node"), Some("1"));
+        assert_eq!(props.get("link.output.port"), Some("2"));
+        assert_eq!(props.get("link.input.node"), Some("3"));
+        assert_eq!(props.get("link.input.port"), Some("4"));
+        assert_eq!(props.get("object.linger"), Some("true"));
+        assert_eq!(props.get("link.passive"), Some("true"));
+    }
+}
@@ -0,0 +1,1093 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Nodes implemented in Rust and exported to the server, such as virtual devices.
+//!
+//! Unlike a [`Stream`](crate::stream::Stream), a [`LocalNode`] has the ports it declares and
+//! nothing else: there is no format conversion, and its `process` callback works on the
+//! buffers of the ports directly.
+//!
+//! This first version is restricted to audio DSP ports, the mono `F32P` ports of filters and
+//! of the DSP side of audio adapters. Ports negotiate this format and their buffers with the
+//! server, and can be linked to any other DSP port.
+//!
+//! ```no_run
+//! use pipewire::{local_node::LocalNode, properties::properties};
+//!
+//! # fn example(core: &pipewire::core::Core) -> Result<(), pipewire::Error> {
+//! let node = LocalNode::builder(properties! {
+//!     *pipewire::keys::NODE_NAME => "gain",
+//!     *pipewire::keys::MEDIA_CLASS => "Audio/Filter",
+//! })
+//! .input("in", None)
+//! .output("out", None)
+//! .process(|ctx| {
+//!     let input = ctx.input(0);
+//!     if let Some(output) = ctx.output(0) {
+//!         output.fill(0.0);
+//!         if let Some(input) = input {
+//!             for (out, sample) in output.iter_mut().zip(input) {
+//!                 *out = sample * 0.5;
+//!             }
+//!         }
+//!     }
+//! })
+//! .export(core)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    cell::{RefCell, UnsafeCell},
+    fmt, mem,
+    os::raw::{c_int, c_void},
+    pin::Pin,
+    ptr,
+    sync::Mutex,
+};
+
+use spa::{
+    param::{audio::AudioInfoDsp, BuffersParam, ParamInfoFlags, ParamType},
+    pod::{ObjectBuilder, OwnedPod, Pod},
+    utils::{Direction, Id, SpaTypes},
+};
+
+use crate::{core::CoreRef, properties::Properties, proxy::Proxy, Error};
+
+/// The largest number of buffers a port asks for.
+const MAX_BUFFERS: i32 = 64;
+/// The default size of buffers, enough for the samples of the largest quantum.
+const DEFAULT_BUFFER_SIZE: i32 = 8192 * mem::size_of::<f32>() as i32;
+
+/// Builder of a [`LocalNode`], see [`LocalNode::builder`].
+pub struct LocalNodeBuilder {
+    properties: Properties,
+    ports: Vec<(Direction, Properties)>,
+    process: Option<Box<dyn FnMut(&mut ProcessContext) + Send>>,
+}
+
+impl LocalNodeBuilder {
+    /// Add an input port named `name`, with additional `properties`.
+    ///
+    /// Input ports are numbered from 0 in the order they are added, in
+    /// [`ProcessContext::input`] and in the port ids seen by the server.
+    #[must_use]
+    pub fn input(self, name: &str, properties: Option<Properties>) -> Self {
+        self.port(Direction::Input, name, properties)
+    }
+
+    /// Add an output port named `name`, with additional `properties`.
+    ///
+    /// Output ports are numbered from 0 in the order they are added, in
+    /// [`ProcessContext::output`] and in the port ids seen by the server.
+    #[must_use]
+    pub fn output(self, name: &str, properties: Option<Properties>) -> Self {
+        self.port(Direction::Output, name, properties)
+    }
+
+    fn port(mut self, direction: Direction, name: &str, properties: Option<Properties>) -> Self {
+        let mut properties = properties.unwrap_or_default();
+        properties.insert(*crate::keys::PORT_NAME, name);
+        properties.insert(*crate::keys::FORMAT_DSP, "32 bit float mono audio");
+        self.ports.push((direction, properties));
+        self
+    }
+
+    /// Set the callback called in each cycle of the graph, with the buffers of the ports.
+    ///
+    /// It is called from the data loop of the context, which runs in its own thread.
+    #[must_use]
+    pub fn process<F>(mut self, process: F) -> Self
+    where
+        F: FnMut(&mut ProcessContext) + Send + 'static,
+    {
+        self.process = Some(Box::new(process));
+        self
+    }
+
+    /// Export the node to the server through `core`.
+    ///
+    /// The node is removed from the server when the returned [`LocalNode`] is dropped.
+    pub fn export(self, core: &CoreRef) -> Result<LocalNode, Error> {
+        let node = NodeImpl::new(self.ports, self.process);
+
+        let proxy = unsafe {
+            let object: *const spa_sys::spa_node = &node.node;
+            // SPA_TYPE_INTERFACE_Node
+            pw_sys::pw_core_export(
+                core.as_raw_ptr(),
+                c"Spa:Pointer:Interface:Node".as_ptr(),
+                self.properties.dict().as_raw_ptr(),
+                object as *mut c_void,
+                0,
+            )
+        };
+        let proxy = ptr::NonNull::new(proxy).ok_or_else(Error::last_os_error)?;
+
+        Ok(LocalNode {
+            proxy: Proxy::new(proxy),
+            _node: node,
+        })
+    }
+}
+
+impl fmt::Debug for LocalNodeBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalNodeBuilder")
+            .field("properties", &self.properties)
+            .field("ports", &self.ports)
+            .finish()
+    }
+}
+
+/// A node implemented in Rust, exported to the server with [`LocalNodeBuilder::export`].
+pub struct LocalNode {
+    // dropped first, so that the server stops using the node before it is freed
+    proxy: Proxy,
+    _node: Pin<Box<NodeImpl>>,
+}
+
+impl LocalNode {
+    /// Start building a node with `properties`, such as its
+    /// [`NODE_NAME`](crate::keys::NODE_NAME) and [`MEDIA_CLASS`](crate::keys::MEDIA_CLASS).
+    pub fn builder(properties: Properties) -> LocalNodeBuilder {
+        LocalNodeBuilder {
+            properties,
+            ports: Vec::new(),
+            process: None,
+        }
+    }
+
+    /// The proxy of the exported node, whose [`bound`](crate::proxy::ProxyListenerLocalBuilder::bound)
+    /// event gives the id of the node in the registry.
+    pub fn proxy(&self) -> &Proxy {
+        &self.proxy
+    }
+}
+
+impl fmt::Debug for LocalNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalNode")
+            .field("proxy", &self.proxy)
+            .finish()
+    }
+}
+
+/// The buffers of the ports during a cycle, given to the `process` callback.
+pub struct ProcessContext<'a> {
+    n_samples: usize,
+    inputs: &'a [Option<(*const f32, usize)>],
+    outputs: &'a mut [Option<(*mut f32, usize, u32)>],
+}
+
+impl<'a> ProcessContext<'a> {
+    /// The number of samples of the cycle, the quantum of the graph.
+    pub fn n_samples(&self) -> usize {
+        self.n_samples
+    }
+
+    /// The samples received by the input port `port`, or `None` if it has no data in this cycle.
+    ///
+    /// The data is also skipped if the peer sent a chunk which does not start on a sample.
+    pub fn input(&self, port: usize) -> Option<&'a [f32]> {
+        let (data, len) = (*self.inputs.get(port)?)?;
+        // Safety: the buffer is not reused by the peer before the next cycle.
+        Some(unsafe { std::slice::from_raw_parts(data, len) })
+    }
+
+    /// The samples to send from the output port `port`, or `None` if it has no buffers.
+    ///
+    /// The slice holds [`n_samples`](Self::n_samples) samples, unless the buffers are smaller,
+    /// and still contains the samples of an earlier cycle: it must be filled entirely.
+    pub fn output(&mut self, port: usize) -> Option<&mut [f32]> {
+        let (data, len, _) = (*self.outputs.get(port)?)?;
+        // Safety: the buffer belongs to the port until it is queued at the end of the cycle.
+        Some(unsafe { std::slice::from_raw_parts_mut(data, len) })
+    }
+}
+
+/// A port, as declared with the builder.
+struct Port {
+    direction: Direction,
+    id: u32,
+    properties: Properties,
+    /// Only used from the main loop.
+    state: RefCell<PortState>,
+}
+
+struct PortState {
+    configured: bool,
+    params: [spa_sys::spa_param_info; 5],
+}
+
+impl PortState {
+    const FORMAT: usize = 3;
+    const BUFFERS: usize = 4;
+
+    fn new() -> Self {
+        fn param_info(id: ParamType, flags: ParamInfoFlags) -> spa_sys::spa_param_info {
+            let mut info: spa_sys::spa_param_info = unsafe { mem::zeroed() };
+            info.id = id.as_raw();
+            info.flags = flags.bits();
+            info
+        }
+
+        Self {
+            configured: false,
+            params: [
+                param_info(ParamType::EnumFormat, ParamInfoFlags::READ),
+                param_info(ParamType::Meta, ParamInfoFlags::READ),
+                param_info(ParamType::IO, ParamInfoFlags::READ),
+                param_info(ParamType::Format, ParamInfoFlags::WRITE),
+                param_info(ParamType::Buffers, ParamInfoFlags::empty()),
+            ],
+        }
+    }
+
+    /// Make the format and buffers readable once the format is set, and tell they changed.
+    fn set_configured(&mut self, configured: bool) {
+        self.configured = configured;
+        let read = if configured {
+            ParamInfoFlags::READ
+        } else {
+            ParamInfoFlags::empty()
+        };
+        for (index, flags) in [
+            (Self::FORMAT, ParamInfoFlags::WRITE | read),
+            (Self::BUFFERS, read),
+        ] {
+            let param = &mut self.params[index];
+            let serial = !ParamInfoFlags::from_bits_retain(param.flags) & ParamInfoFlags::SERIAL;
+            param.flags = (flags | serial).bits();
+            param.user += 1;
+        }
+    }
+}
+
+impl Port {
+    /// The param `id` of the port, ports have at most one param of each id.
+    fn param(&self, id: ParamType) -> Option<OwnedPod> {
+        let configured = self.state.borrow().configured;
+        match id {
+            ParamType::EnumFormat => Some(AudioInfoDsp::default().to_pod(id)),
+            ParamType::Format if configured => Some(AudioInfoDsp::default().to_pod(id)),
+            ParamType::Buffers if configured => {
+                let buffers = BuffersParam::new()
+                    .buffers_range(1, 1, MAX_BUFFERS)
+                    .blocks(1)
+                    .size_range(DEFAULT_BUFFER_SIZE, mem::size_of::<f32>() as i32, i32::MAX)
+                    .stride(mem::size_of::<f32>() as i32);
+                Some(buffers.to_pod())
+            }
+            ParamType::Meta => Some(
+                ObjectBuilder::new(SpaTypes::ObjectParamMeta, id)
+                    .prop(spa_sys::SPA_PARAM_META_type, Id(spa_sys::SPA_META_Header))
+                    .prop(
+                        spa_sys::SPA_PARAM_META_size,
+                        mem::size_of::<spa_sys::spa_meta_header>() as i32,
+                    )
+                    .build(),
+            ),
+            ParamType::IO => Some(
+                ObjectBuilder::new(SpaTypes::ObjectParamIO, id)
+                    .prop(spa_sys::SPA_PARAM_IO_id, Id(spa_sys::SPA_IO_Buffers))
+                    .prop(
+                        spa_sys::SPA_PARAM_IO_size,
+                        mem::size_of::<spa_sys::spa_io_buffers>() as i32,
+                    )
+                    .build(),
+            ),
+            _ => None,
+        }
+    }
+
+    fn info(&self, state: &PortState) -> spa_sys::spa_port_info {
+        let mut info: spa_sys::spa_port_info = unsafe { mem::zeroed() };
+        info.change_mask = (spa_sys::SPA_PORT_CHANGE_MASK_FLAGS
+            | spa_sys::SPA_PORT_CHANGE_MASK_PROPS
+            | spa_sys::SPA_PORT_CHANGE_MASK_PARAMS)
+            .into();
+        info.props = self.properties.dict().as_raw_ptr();
+        info.params = state.params.as_ptr().cast_mut();
+        info.n_params = state.params.len() as u32;
+        info
+    }
+}
+
+/// What the data loop needs to run a cycle, shared with the main loop.
+struct ProcessState {
+    callback: Option<Box<dyn FnMut(&mut ProcessContext) + Send>>,
+    position: *mut spa_sys::spa_io_position,
+    /// The io area and buffers of each port, in the order of [`NodeImpl::ports`].
+    ports: Vec<PortBuffers>,
+    inputs: Vec<Option<(*const f32, usize)>>,
+    outputs: Vec<Option<(*mut f32, usize, u32)>>,
+}
+
+struct PortBuffers {
+    io: *mut spa_sys::spa_io_buffers,
+    buffers: Vec<*mut spa_sys::spa_buffer>,
+    /// The next buffer to send, for output ports.
+    next: usize,
+}
+
+impl PortBuffers {
+    /// The samples of the first data of buffer `id`, with the range of its chunk for inputs.
+    ///
+    /// Returns `None` if the offset of the chunk, set by the peer, is not aligned for `f32`.
+    unsafe fn samples(&self, id: u32, chunk: bool) -> Option<(*mut f32, usize)> {
+        let buffer = *self.buffers.get(id as usize)?;
+        if (*buffer).n_datas == 0 {
+            return None;
+        }
+        let data = &*(*buffer).datas;
+        if data.data.is_null() {
+            return None;
+        }
+
+        let (offset, size) = if chunk {
+            let chunk = &*data.chunk;
+            let offset = chunk.offset.min(data.maxsize);
+            (offset, chunk.size.min(data.maxsize - offset))
+        } else {
+            (0, data.maxsize)
+        };
+        let samples = data.data.cast::<u8>().add(offset as usize).cast::<f32>();
+        if samples as usize % mem::align_of::<f32>() != 0 {
+            return None;
+        }
+        Some((samples, size as usize / mem::size_of::<f32>()))
+    }
+}
+
+/// The `spa_node` implementation, at a stable address for as long as the node is exported.
+struct NodeImpl {
+    node: spa_sys::spa_node,
+    methods: spa_sys::spa_node_methods,
+    /// The listeners of the node, only used from the main loop.
+    hooks: UnsafeCell<spa_sys::spa_hook_list>,
+    n_inputs: u32,
+    n_outputs: u32,
+    ports: Vec<Port>,
+    process: Mutex<ProcessState>,
+}
+
+impl NodeImpl {
+    fn new(
+        ports: Vec<(Direction, Properties)>,
+        callback: Option<Box<dyn FnMut(&mut ProcessContext) + Send>>,
+    ) -> Pin<Box<Self>> {
+        let mut n_inputs = 0;
+        let mut n_outputs = 0;
+        let mut ports: Vec<Port> = ports
+            .into_iter()
+            .map(|(direction, properties)| {
+                let counter = if direction == Direction::Input {
+                    &mut n_inputs
+                } else {
+                    &mut n_outputs
+                };
+                let id = *counter;
+                *counter += 1;
+                Port {
+                    direction,
+                    id,
+                    properties,
+                    state: RefCell::new(PortState::new()),
+                }
+            })
+            .collect();
+        // inputs first, matching the slices of ProcessContext
+        ports.sort_by_key(|port| port.direction != Direction::Input);
+
+        let process = ProcessState {
+            callback,
+            position: ptr::null_mut(),
+            ports: ports
+                .iter()
+                .map(|_| PortBuffers {
+                    io: ptr::null_mut(),
+                    buffers: Vec::new(),
+                    next: 0,
+                })
+                .collect(),
+            inputs: vec![None; n_inputs as usize],
+            outputs: vec![None; n_outputs as usize],
+        };
+
+        let mut methods: spa_sys::spa_node_methods = unsafe { mem::zeroed() };
+        methods.version = spa_sys::SPA_VERSION_NODE_METHODS;
+        methods.add_listener = Some(node_add_listener);
+        methods.set_callbacks = Some(node_set_callbacks);
+        methods.sync = Some(node_sync);
+        methods.enum_params = Some(node_enum_params);
+        methods.set_param = Some(node_set_param);
+        methods.set_io = Some(node_set_io);
+        methods.send_command = Some(node_send_command);
+        methods.add_port = Some(node_add_port);
+        methods.remove_port = Some(node_remove_port);
+        methods.port_enum_params = Some(node_port_enum_params);
+        methods.port_set_param = Some(node_port_set_param);
+        methods.port_use_buffers = Some(node_port_use_buffers);
+        methods.port_set_io = Some(node_port_set_io);
+        methods.port_reuse_buffer = Some(node_port_reuse_buffer);
+        methods.process = Some(node_process);
+
+        let mut node = Box::pin(NodeImpl {
+            node: unsafe { mem::zeroed() },
+            methods,
+            hooks: UnsafeCell::new(unsafe { mem::zeroed() }),
+            n_inputs,
+            n_outputs,
+            ports,
+            process: Mutex::new(process),
+        });
+
+        // the struct does not move anymore, point the interface and the list head to it
+        unsafe {
+            let this = node.as_mut().get_unchecked_mut();
+            let data: *mut NodeImpl = this;
+            let head = ptr::addr_of_mut!((*this.hooks.get()).list);
+            (*head).next = head;
+            (*head).prev = head;
+            this.node.iface.type_ = c"Spa:Pointer:Interface:Node".as_ptr();
+            this.node.iface.version = spa_sys::SPA_VERSION_NODE;
+            this.node.iface.cb.funcs = ptr::addr_of!(this.methods).cast();
+            this.node.iface.cb.data = data.cast();
+        }
+
+        node
+    }
+
+    fn port(&self, direction: spa_sys::spa_direction, port_id: u32) -> Option<(usize, &Port)> {
+        self.ports.iter().enumerate().find(|(_, port)| {
+            port.direction == Direction::from_raw(direction) && port.id == port_id
+        })
+    }
+
+    fn info(&self) -> spa_sys::spa_node_info {
+        let mut info: spa_sys::spa_node_info = unsafe { mem::zeroed() };
+        info.max_input_ports = self.n_inputs;
+        info.max_output_ports = self.n_outputs;
+        info.change_mask = spa_sys::SPA_NODE_CHANGE_MASK_FLAGS.into();
+        info.flags = spa_sys::SPA_NODE_FLAG_RT.into();
+        info
+    }
+
+    /// Call `f` with the events and data of each listener, as `spa_hook_list_call()`.
+    unsafe fn emit(&self, mut f: impl FnMut(&spa_sys::spa_node_events, *mut c_void)) {
+        let head = ptr::addr_of_mut!((*self.hooks.get()).list);
+        let mut link = (*head).next;
+        while link != head {
+            // listeners can remove themselves when called
+            let next = (*link).next;
+            emit_to(link.cast(), &mut f);
+            link = next;
+        }
+    }
+
+    fn emit_port_info(&self, port: &Port) {
+        let state = port.state.borrow();
+        let info = port.info(&state);
+        unsafe {
+            self.emit(|events, data| {
+                if let Some(port_info) = events.port_info {
+                    port_info(data, port.direction.as_raw(), port.id, &info);
+                }
+            })
+        }
+    }
+
+    fn process(&self) -> c_int {
+        // the main loop only holds the lock briefly while the node is reconfigured,
+        // skip the cycle rather than waiting for it
+        let Ok(mut state) = self.process.try_lock() else {
+            return spa_sys::SPA_STATUS_OK as c_int;
+        };
+        let state = &mut *state;
+        let n_samples = match unsafe { state.position.as_ref() } {
+            Some(position) => position.clock.duration as usize,
+            None => 0,
+        };
+
+        let mut status = spa_sys::SPA_STATUS_OK;
+        let (inputs, outputs) = state.ports.split_at_mut(self.n_inputs as usize);
+        for (port, slot) in inputs.iter_mut().zip(state.inputs.iter_mut()) {
+            *slot = None;
+            let Some(io) = (unsafe { port.io.as_mut() }) else {
+                continue;
+            };
+            status |= spa_sys::SPA_STATUS_NEED_DATA;
+            if io.status != spa_sys::SPA_STATUS_HAVE_DATA as i32 {
+                continue;
+            }
+            *slot = unsafe { port.samples(io.buffer_id, true) }
+                .map(|(samples, len)| (samples.cast_const(), len));
+            io.status = spa_sys::SPA_STATUS_NEED_DATA as i32;
+        }
+        for (port, slot) in outputs.iter_mut().zip(state.outputs.iter_mut()) {
+            *slot = None;
+            if port.io.is_null() || port.buffers.is_empty() {
+                continue;
+            }
+            // buffers are sent in turn, the peer is done with a buffer by the next cycle
+            let id = port.next as u32;
+            port.next = (port.next + 1) % port.buffers.len();
+            *slot = unsafe { port.samples(id, false) }
+                .map(|(samples, len)| (samples, len.min(n_samples), id));
+        }
+
+        if let Some(callback) = state.callback.as_mut() {
+            callback(&mut ProcessContext {
+                n_samples,
+                inputs: &state.inputs,
+                outputs: &mut state.outputs,
+            });
+        }
+
+        for (port, slot) in outputs.iter().zip(state.outputs.iter()) {
+            let Some((_, len, id)) = *slot else {
+                continue;
+            };
+            unsafe {
+                let buffer = port.buffers[id as usize];
+                let chunk = &mut *(*(*buffer).datas).chunk;
+                chunk.offset = 0;
+                chunk.size = (len * mem::size_of::<f32>()) as u32;
+                chunk.stride = mem::size_of::<f32>() as i32;
+                chunk.flags = 0;
+                (*port.io).buffer_id = id;
+                (*port.io).status = spa_sys::SPA_STATUS_HAVE_DATA as i32;
+            }
+            status |= spa_sys::SPA_STATUS_HAVE_DATA;
+        }
+
+        status as c_int
+    }
+}
+
+/// Call `f` with the events and data of `hook`, unless it is blocked.
+unsafe fn emit_to(
+    hook: *const spa_sys::spa_hook,
+    f: &mut impl FnMut(&spa_sys::spa_node_events, *mut c_void),
+) {
+    let events: *const spa_sys::spa_node_events = (*hook).cb.funcs.cast();
+    if let Some(events) = events.as_ref() {
+        f(events, (*hook).cb.data);
+    }
+}
+
+unsafe fn node_ref<'a>(object: *mut c_void) -> &'a NodeImpl {
+    (object as *const NodeImpl).as_ref().unwrap()
+}
+
+unsafe extern "C" fn node_add_listener(
+    object: *mut c_void,
+    listener: *mut spa_sys::spa_hook,
+    events: *const spa_sys::spa_node_events,
+    data: *mut c_void,
+) -> c_int {
+    crate::utils::catch_callback_panic("local node add_listener", -libc::EIO, || {
+        let node = node_ref(object);
+
+        // as spa_hook_list_append()
+        ptr::write_bytes(listener, 0, 1);
+        (*listener).cb.funcs = events.cast();
+        (*listener).cb.data = data;
+        let head = ptr::addr_of_mut!((*node.hooks.get()).list);
+        let link = ptr::addr_of_mut!((*listener).link);
+        (*link).prev = (*head).prev;
+        (*link).next = head;
+        (*(*head).prev).next = link;
+        (*head).prev = link;
+
+        // only the new listener is told about the node and its ports
+        let info = node.info();
+        emit_to(listener, &mut |events, data| {
+            if let Some(node_info) = events.info {
+                node_info(data, &info);
+            }
+        });
+        for port in &node.ports {
+            let state = port.state.borrow();
+            let info = port.info(&state);
+            emit_to(listener, &mut |events, data| {
+                if let Some(port_info) = events.port_info {
+                    port_info(data, port.direction.as_raw(), port.id, &info);
+                }
+            });
+        }
+        0
+    })
+}
+
+unsafe extern "C" fn node_set_callbacks(
+    _object: *mut c_void,
+    _callbacks: *const spa_sys::spa_node_callbacks,
+    _data: *mut c_void,
+) -> c_int {
+    // the node never drives the graph, so it has nothing to signal
+    0
+}
+
+unsafe extern "C" fn node_sync(object: *mut c_void, seq: c_int) -> c_int {
+    crate::utils::catch_callback_panic("local node sync", -libc::EIO, || {
+        node_ref(object).emit(|events, data| {
+            if let Some(result) = events.result {
+                result(data, seq, 0, 0, ptr::null());
+            }
+        });
+        0
+    })
+}
+
+unsafe extern "C" fn node_enum_params(
+    _object: *mut c_void,
+    _seq: c_int,
+    _id: u32,
+    _start: u32,
+    _max: u32,
+    _filter: *const spa_sys::spa_pod,
+) -> c_int {
+    // the node itself has no params
+    0
+}
+
+unsafe extern "C" fn node_set_param(
+    _object: *mut c_void,
+    _id: u32,
+    _flags: u32,
+    _param: *const spa_sys::spa_pod,
+) -> c_int {
+    -libc::ENOENT
+}
+
+unsafe extern "C" fn node_set_io(
+    object: *mut c_void,
+    id: u32,
+    data: *mut c_void,
+    size: usize,
+) -> c_int {
+    crate::utils::catch_callback_panic("local node set_io", -libc::EIO, || {
+        let node = node_ref(object);
+        if id == spa_sys::SPA_IO_Position {
+            let position = if size >= mem::size_of::<spa_sys::spa_io_position>() {
+                data.cast()
+            } else {
+                ptr::null_mut()
+            };
+            node.process.lock().unwrap().position = position;
+        }
+        0
+    })
+}
+
+unsafe extern "C" fn node_send_command(
+    _object: *mut c_void,
+    _command: *const spa_sys::spa_command,
+) -> c_int {
+    // the node is scheduled by the server, starting and pausing does not change anything
+    0
+}
+
+unsafe extern "C" fn node_add_port(
+    _object: *mut c_void,
+    _direction: spa_sys::spa_direction,
+    _port_id: u32,
+    _props: *const spa_sys::spa_dict,
+) -> c_int {
+    -libc::ENOTSUP
+}
+
+unsafe extern "C" fn node_remove_port(
+    _object: *mut c_void,
+    _direction: spa_sys::spa_direction,
+    _port_id: u32,
+) -> c_int {
+    -libc::ENOTSUP
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn node_port_enum_params(
+    object: *mut c_void,
+    seq: c_int,
+    direction: spa_sys::spa_direction,
+    port_id: u32,
+    id: u32,
+    start: u32,
+    num: u32,
+    _filter: *const spa_sys::spa_pod,
+) -> c_int {
+    crate::utils::catch_callback_panic("local node port_enum_params", -libc::EIO, || {
+        let node = node_ref(object);
+        let Some((_, port)) = node.port(direction, port_id) else {
+            return -libc::EINVAL;
+        };
+
+        // each param has a single value, filtering is done by the server
+        if start > 0 || num == 0 {
+            return 0;
+        }
+        let Some(param) = port.param(ParamType::from_raw(id)) else {
+            return 0;
+        };
+        let pod: &Pod = &param;
+        let mut result: spa_sys::spa_result_node_params = mem::zeroed();
+        result.id = id;
+        result.index = 0;
+        result.next = 1;
+        result.param = pod.as_raw_ptr();
+        node.emit(|events, data| {
+            if let Some(emit) = events.result {
+                emit(
+                    data,
+                    seq,
+                    0,
+                    spa_sys::SPA_RESULT_TYPE_NODE_PARAMS,
+                    ptr::addr_of!(result).cast(),
+                );
+            }
+        });
+        0
+    })
+}
+
+unsafe extern "C" fn node_port_set_param(
+    object: *mut c_void,
+    direction: spa_sys::spa_direction,
+    port_id: u32,
+    id: u32,
+    _flags: u32,
+    param: *const spa_sys::spa_pod,
+) -> c_int {
+    crate::utils::catch_callback_panic("local node port_set_param", -libc::EIO, || {
+        let node = node_ref(object);
+        let Some((index, port)) = node.port(direction, port_id) else {
+            return -libc::EINVAL;
+        };
+        if id != spa_sys::SPA_PARAM_Format {
+            // other params, such as the latency, are not used by the ports
+            return 0;
+        }
+
+        let configured = match param.as_ref() {
+            Some(param) => {
+                let format = Pod::from_raw(param);
+                match AudioInfoDsp::from_pod(format) {
+                    Ok(info) if info == AudioInfoDsp::default() => true,
+                    _ => return -libc::EINVAL,
+                }
+            }
+            None => {
+                // the buffers are cleared with the format
+                let mut state = node.process.lock().unwrap();
+                state.ports[index].buffers.clear();
+                false
+            }
+        };
+        port.state.borrow_mut().set_configured(configured);
+        node.emit_port_info(port);
+        0
+    })
+}
+
+unsafe extern "C" fn node_port_use_buffers(
+    object: *mut c_void,
+    direction: spa_sys::spa_direction,
+    port_id: u32,
+    _flags: u32,
+    buffers: *mut *mut spa_sys::spa_buffer,
+    n_buffers: u32,
+) -> c_int {
+    crate::utils::catch_callback_panic("local node port_use_buffers", -libc::EIO, || {
+        let node = node_ref(object);
+        let Some((index, port)) = node.port(direction, port_id) else {
+            return -libc::EINVAL;
+        };
+        if n_buffers > 0 && !port.state.borrow().configured {
+            return -libc::EIO;
+        }
+
+        let buffers = if n_buffers == 0 {
+            Vec::new()
+        } else {
+            std::slice::from_raw_parts(buffers, n_buffers as usize).to_vec()
+        };
+        // the samples are accessed in memory, which the server maps for local nodes
+        for &buffer in &buffers {
+            if buffer.is_null() || (*buffer).n_datas == 0 || (*buffer).datas.is_null() {
+                return -libc::EINVAL;
+            }
+            let data = &*(*buffer).datas;
+            if data.data.is_null()
+                || data.chunk.is_null()
+                || data.data as usize % mem::align_of::<f32>() != 0
+            {
+                return -libc::EINVAL;
+            }
+        }
+
+        let mut state = node.process.lock().unwrap();
+        let port = &mut state.ports[index];
+        port.buffers = buffers;
+        port.next = 0;
+        0
+    })
+}
+
+unsafe extern "C" fn node_port_set_io(
+    object: *mut c_void,
+    direction: spa_sys::spa_direction,
+    port_id: u32,
+    id: u32,
+    data: *mut c_void,
+    size: usize,
+) -> c_int {
+    crate::utils::catch_callback_panic("local node port_set_io", -libc::EIO, || {
+        let node = node_ref(object);
+        let Some((index, _)) = node.port(direction, port_id) else {
+            return -libc::EINVAL;
+        };
+        if id != spa_sys::SPA_IO_Buffers {
+            return -libc::ENOENT;
+        }
+
+        let io = if size >= mem::size_of::<spa_sys::spa_io_buffers>() {
+            data.cast()
+        } else {
+            ptr::null_mut()
+        };
+        node.process.lock().unwrap().ports[index].io = io;
+        0
+    })
+}
+
+unsafe extern "C" fn node_port_reuse_buffer(
+    _object: *mut c_void,
+    _port_id: u32,
+    _buffer_id: u32,
+) -> c_int {
+    // output buffers are sent in turn, see NodeImpl::process
+    0
+}
+
+unsafe extern "C" fn node_process(object: *mut c_void) -> c_int {
+    crate::utils::catch_callback_panic("local node process", -libc::EIO, || {
+        node_ref(object).process()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::properties::properties;
+
+    #[test]
+    fn port_params() {
+        let node = NodeImpl::new(
+            vec![
+                (Direction::Output, Properties::new()),
+                (Direction::Input, Properties::new()),
+            ],
+            None,
+        );
+        // inputs come first, each direction is numbered from 0
+        assert_eq!(node.ports[0].direction, Direction::Input);
+        assert_eq!(node.ports[0].id, 0);
+        assert_eq!(node.ports[1].direction, Direction::Output);
+        assert_eq!(node.ports[1].id, 0);
+
+        let port = &node.ports[1];
+        let format = port.param(ParamType::EnumFormat).unwrap();
+        assert_eq!(
+            AudioInfoDsp::from_pod(&format).unwrap(),
+            AudioInfoDsp::default()
+        );
+        assert!(port.param(ParamType::Format).is_none());
+        assert!(port.param(ParamType::Buffers).is_none());
+
+        // buffers are readable once the format is set, the serial bit tells they changed
+        let flags = |port: &Port| {
+            let state = port.state.borrow();
+            ParamInfoFlags::from_bits_retain(state.params[PortState::BUFFERS].flags)
+        };
+        assert_eq!(flags(port), ParamInfoFlags::empty());
+        port.state.borrow_mut().set_configured(true);
+        assert_eq!(flags(port), ParamInfoFlags::READ | ParamInfoFlags::SERIAL);
+        assert!(port.param(ParamType::Format).is_some());
+        assert!(BuffersParam::from_pod(&port.param(ParamType::Buffers).unwrap()).is_ok());
+
+        port.state.borrow_mut().set_configured(false);
+        assert_eq!(flags(port), ParamInfoFlags::empty());
+        assert!(port.param(ParamType::Buffers).is_none());
+    }
+
+    #[test]
+    fn use_buffers() {
+        let node = NodeImpl::new(vec![(Direction::Input, Properties::new())], None);
+        node.ports[0].state.borrow_mut().set_configured(true);
+        let object = &*node as *const NodeImpl as *mut c_void;
+
+        let mut samples = [0f32; 4];
+        let mut chunk: spa_sys::spa_chunk = unsafe { mem::zeroed() };
+        let mut data: spa_sys::spa_data = unsafe { mem::zeroed() };
+        data.data = samples.as_mut_ptr().cast();
+        data.maxsize = mem::size_of_val(&samples) as u32;
+        data.chunk = &mut chunk;
+        let mut buffer: spa_sys::spa_buffer = unsafe { mem::zeroed() };
+        buffer.n_datas = 1;
+        let mut buffers = [&mut buffer as *mut spa_sys::spa_buffer];
+        let use_buffers = |buffers: &mut [*mut spa_sys::spa_buffer]| unsafe {
+            node_port_use_buffers(
+                object,
+                Direction::Input.as_raw(),
+                0,
+                0,
+                buffers.as_mut_ptr(),
+                buffers.len() as u32,
+            )
+        };
+
+        // the datas are checked before they are used
+        assert_eq!(use_buffers(&mut buffers), -libc::EINVAL);
+        unsafe { (*buffers[0]).datas = &mut data };
+        assert_eq!(use_buffers(&mut buffers), 0);
+
+        // the chunk set by the peer must start on a sample
+        let state = node.process.lock().unwrap();
+        let set_chunk = |offset, size| unsafe {
+            let chunk = (*(*buffers[0]).datas).chunk;
+            (*chunk).offset = offset;
+            (*chunk).size = size;
+        };
+        set_chunk(4, 8);
+        let (ptr, len) = unsafe { state.ports[0].samples(0, true) }.unwrap();
+        assert_eq!(ptr as usize, data.data as usize + 4);
+        assert_eq!(len, 2);
+        set_chunk(2, 8);
+        assert!(unsafe { state.ports[0].samples(0, true) }.is_none());
+    }
+
+    #[test]
+    fn builder_ports() {
+        let builder = LocalNode::builder(properties! {
+            *crate::keys::NODE_NAME => "pipewire-rs-test",
+        })
+        .input("in", None)
+        .output("out", Some(properties! { "port.extra" => "yes" }));
+        assert_eq!(builder.ports.len(), 2);
+        let (direction, props) = &builder.ports[1];
+        assert_eq!(*direction, Direction::Output);
+        assert_eq!(props.get(*crate::keys::PORT_NAME), Some("out"));
+        assert_eq!(
+            props.get(*crate::keys::FORMAT_DSP),
+            Some("32 bit float mono audio")
+        );
+        assert_eq!(props.get("port.extra"), Some("yes"));
+    }
+
+    #[cfg(feature = "integration-tests")]
+    #[test]
+    fn link_local_nodes() {
+        use std::{cell::RefCell, rc::Rc, time::Duration};
+
+        use crate::{
+            context::Context, link::LinkBuilder, main_loop::MainLoop, types::ObjectType,
+            utils::spawn_daemon,
+        };
+
+        let name = format!("pipewire-rs-test-local-node-{}", std::process::id());
+        let mut daemon = spawn_daemon(&name);
+
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        // the daemon takes a moment to listen on its socket
+        let core = (0..100)
+            .find_map(|_| {
+                let core = context.connect(Some(properties! {
+                    *crate::keys::REMOTE_NAME => name.clone(),
+                }));
+                if core.is_err() {
+                    std::thread::sleep(Duration::from_millis(20));
+                }
+                core.ok()
+            })
+            .expect("failed to connect to the daemon");
+
+        // nodes without a driver are scheduled by the dummy driver of the daemon
+        let source = LocalNode::builder(properties! {
+            *crate::keys::NODE_NAME => "pipewire-rs-test-source",
+            "node.want-driver" => "true",
+        })
+        .output("out", None)
+        .process(|ctx| {
+            if let Some(output) = ctx.output(0) {
+                output.fill(0.5);
+            }
+        })
+        .export(&core)
+        .unwrap();
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let sink = LocalNode::builder(properties! {
+            *crate::keys::NODE_NAME => "pipewire-rs-test-sink",
+            "node.want-driver" => "true",
+        })
+        .input("in", None)
+        .process(move |ctx| {
+            if let Some(input) = ctx.input(0).filter(|input| !input.is_empty()) {
+                let _ = sender.send(input.to_vec());
+            }
+        })
+        .export(&core)
+        .unwrap();
+
+        let ids = Rc::new(RefCell::new([None; 2]));
+        let _listeners: Vec<_> = [&source, &sink]
+            .into_iter()
+            .enumerate()
+            .map(|(index, node)| {
+                let ids = ids.clone();
+                node.proxy()
+                    .add_listener_local()
+                    .bound(move |id| ids.borrow_mut()[index] = Some(id))
+                    .register()
+            })
+            .collect();
+        mainloop.run_until(|_| ids.borrow().iter().all(Option::is_some));
+        let [source_id, sink_id] = ids.borrow().map(Option::unwrap);
+
+        let registry = core.get_registry().unwrap();
+        let globals = registry.snapshot(&core, &mainloop).unwrap();
+        let port_of = |node_id: u32| {
+            globals
+                .iter()
+                .find(|global| {
+                    global.type_ == ObjectType::Port
+                        && global.props.as_ref().and_then(|props| props.get("node.id"))
+                            == Some(node_id.to_string().as_str())
+                })
+                .expect("port not in the registry")
+                .id
+        };
+        let link = LinkBuilder::new(source_id, port_of(source_id), sink_id, port_of(sink_id))
+            .create(&core)
+            .unwrap();
+        link.wait(&mainloop).unwrap();
+
+        // the sink receives what the source sends once the link negotiated the buffers
+        let samples = (0..500)
+            .find_map(|_| {
+                mainloop.loop_().iterate(Duration::from_millis(10));
+                receiver.try_recv().ok()
+            })
+            .expect("no samples received");
+        assert!(samples.iter().all(|sample| *sample == 0.5));
+
+        drop(link);
+        drop(sink);
+        drop(source);
+        daemon.kill().unwrap();
+        daemon.wait().unwrap();
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Forward the log messages of PipeWire to the [`log`](::log) facade.
+//!
+//! By default, libpipewire writes its messages to stderr or to the systemd journal, filtered with
+//! the `PIPEWIRE_DEBUG` environment variable. Once [`install`]ed, they are passed to the logger
+//! of the application instead, with their level, file, line, and topic such as `pw.context` as target.
+//! Use the `tracing-log` crate to receive them in a `tracing` subscriber.
+//!
+//! ```no_run
+//! pipewire::init();
+//! pipewire::log::install();
+//! // the messages of all the modules, and the warnings of the other topics
+//! pipewire::log::set_level("mod.*", log::LevelFilter::Debug);
+//! log::set_max_level(log::LevelFilter::Warn);
+//! ```
+//!
+//! Messages are logged from the threads of PipeWire, including its realtime threads.
+//! Messages which are not enabled are discarded without allocating or blocking,
+//! but the logger should also avoid blocking for long when logging the enabled ones.
+
+use std::{
+    ffi::{c_char, c_int, c_void, CStr},
+    mem, ptr,
+    sync::{PoisonError, RwLock},
+};
+
+use ::log::{Level, LevelFilter, Metadata, Record};
+use once_cell::sync::OnceCell;
+
+/// The target of the messages without a topic.
+const DEFAULT_TARGET: &str = "pipewire";
+
+/// The `spa_log` of [`install`], allocated once for the rest of the process.
+struct Forwarder {
+    log: spa_sys::spa_log,
+    sink: spa_sys::libspa_rs_log_sink,
+}
+
+struct ForwarderPtr(ptr::NonNull<Forwarder>);
+
+// the forwarder is never freed, and its callbacks only use thread-safe state
+unsafe impl Send for ForwarderPtr {}
+unsafe impl Sync for ForwarderPtr {}
+
+static FORWARDER: OnceCell<ForwarderPtr> = OnceCell::new();
+
+/// The levels set with [`set_level`], by topic pattern.
+static TOPIC_LEVELS: RwLock<Vec<(String, LevelFilter)>> = RwLock::new(Vec::new());
+
+impl ForwarderPtr {
+    fn new() -> Self {
+        let forwarder = Box::into_raw(Box::new(Forwarder {
+            log: unsafe { mem::zeroed() },
+            sink: spa_sys::libspa_rs_log_sink {
+                enabled: Some(enabled),
+                message: Some(message),
+                data: ptr::null_mut(),
+            },
+        }));
+
+        // the struct does not move anymore, point the interface to it
+        unsafe {
+            (*forwarder).log.iface.type_ = c"Spa:Pointer:Interface:Log".as_ptr();
+            (*forwarder).log.iface.version = spa_sys::SPA_VERSION_LOG;
+            (*forwarder).log.iface.cb.funcs =
+                ptr::addr_of!(spa_sys::libspa_rs_log_sink_methods).cast();
+            (*forwarder).log.iface.cb.data = ptr::addr_of_mut!((*forwarder).sink).cast();
+            Self(ptr::NonNull::new_unchecked(forwarder))
+        }
+    }
+}
+
+/// Forward the messages of PipeWire to the logger of the [`log`](::log) facade.
+///
+/// This must be called after [`init`](crate::init), which sets up the default log of PipeWire,
+/// and before creating any [`Context`](crate::context::Context), as contexts pass the current log
+/// to the plugins they load.
+///
+/// PipeWire only formats the messages up to the most verbose of [`log::max_level`](::log::max_level)
+/// and of the levels set with [`set_level`]. The level is updated by [`set_level`]:
+/// call it again after raising the max level of `log`.
+pub fn install() {
+    let forwarder = FORWARDER.get_or_init(ForwarderPtr::new);
+    unsafe { pw_sys::pw_log_set(ptr::addr_of_mut!((*forwarder.0.as_ptr()).log)) };
+    update_level();
+}
+
+/// Forward the messages up to `level` for the topics matching `pattern`, such as `pw.context`,
+/// or `mod.*` for the topics of all the modules.
+///
+/// `*` matches any characters, and the last pattern matching a topic sets its level.
+/// The messages of the other topics are filtered with [`log::max_level`](::log::max_level).
+/// The level of PipeWire is updated if the forwarder is [`install`]ed.
+pub fn set_level(pattern: &str, level: LevelFilter) {
+    let mut levels = TOPIC_LEVELS.write().unwrap_or_else(PoisonError::into_inner);
+    levels.retain(|(other, _)| other != pattern);
+    levels.push((pattern.to_owned(), level));
+    drop(levels);
+
+    if FORWARDER.get().is_some() {
+        update_level();
+    }
+}
+
+fn update_level() {
+    let levels = TOPIC_LEVELS.read().unwrap_or_else(PoisonError::into_inner);
+    let level = levels
+        .iter()
+        .map(|(_, level)| *level)
+        .fold(::log::max_level(), Ord::max);
+    drop(levels);
+
+    unsafe { pw_sys::pw_log_set_level(to_spa_level(level)) };
+}
+
+/// The level of the messages of `topic`, see [`set_level`].
+fn topic_level(topic: &str) -> LevelFilter {
+    // don't wait for set_level from a realtime thread
+    let Ok(levels) = TOPIC_LEVELS.try_read() else {
+        return ::log::max_level();
+    };
+    levels
+        .iter()
+        .rev()
+        .find(|(pattern, _)| matches(pattern, topic))
+        .map_or_else(::log::max_level, |(_, level)| *level)
+}
+
+/// Whether `topic` matches `pattern`, where `*` matches any number of characters.
+fn matches(pattern: &str, topic: &str) -> bool {
+    let (pattern, topic) = (pattern.as_bytes(), topic.as_bytes());
+    let (mut p, mut t) = (0, 0);
+    // the position of the last `*` of the pattern, and the end of the part of the topic it matches
+    let mut star = None;
+
+    while t < topic.len() {
+        if pattern.get(p) == Some(&b'*') {
+            star = Some((p, t));
+            p += 1;
+        } else if pattern.get(p) == Some(&topic[t]) {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // let the last `*` match one more character
+            star = Some((star_p, star_t + 1));
+            p = star_p + 1;
+            t = star_t + 1;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+fn from_spa_level(level: spa_sys::spa_log_level) -> Option<Level> {
+    match level {
+        spa_sys::SPA_LOG_LEVEL_ERROR => Some(Level::Error),
+        spa_sys::SPA_LOG_LEVEL_WARN => Some(Level::Warn),
+        spa_sys::SPA_LOG_LEVEL_INFO => Some(Level::Info),
+        spa_sys::SPA_LOG_LEVEL_DEBUG => Some(Level::Debug),
+        spa_sys::SPA_LOG_LEVEL_TRACE => Some(Level::Trace),
+        _ => None,
+    }
+}
+
+fn to_spa_level(level: LevelFilter) -> spa_sys::spa_log_level {
+    match level {
+        LevelFilter::Off => spa_sys::SPA_LOG_LEVEL_NONE,
+        LevelFilter::Error => spa_sys::SPA_LOG_LEVEL_ERROR,
+        LevelFilter::Warn => spa_sys::SPA_LOG_LEVEL_WARN,
+        LevelFilter::Info => spa_sys::SPA_LOG_LEVEL_INFO,
+        LevelFilter::Debug => spa_sys::SPA_LOG_LEVEL_DEBUG,
+        LevelFilter::Trace => spa_sys::SPA_LOG_LEVEL_TRACE,
+    }
+}
+
+unsafe fn c_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        None
+    } else {
+        CStr::from_ptr(s).to_str().ok()
+    }
+}
+
+unsafe extern "C" fn enabled(
+    _data: *mut c_void,
+    level: spa_sys::spa_log_level,
+    topic: *const c_char,
+) -> bool {
+    let Some(level) = from_spa_level(level) else {
+        return false;
+    };
+    let target = c_str(topic).unwrap_or(DEFAULT_TARGET);
+
+    crate::utils::catch_callback_panic("log", false, || {
+        level <= topic_level(target)
+            && ::log::logger().enabled(&Metadata::builder().level(level).target(target).build())
+    })
+}
+
+unsafe extern "C" fn message(
+    _data: *mut c_void,
+    level: spa_sys::spa_log_level,
+    topic: *const c_char,
+    file: *const c_char,
+    line: c_int,
+    _func: *const c_char,
+    message: *const c_char,
+) {
+    let Some(level) = from_spa_level(level) else {
+        return;
+    };
+    let message = CStr::from_ptr(message).to_string_lossy();
+    let (target, file) = (c_str(topic).unwrap_or(DEFAULT_TARGET), c_str(file));
+
+    crate::utils::catch_callback_panic("log", (), || {
+        ::log::logger().log(
+            &Record::builder()
+                .args(format_args!("{message}"))
+                .level(level)
+                .target(target)
+                .file(file)
+                .line(u32::try_from(line).ok())
+                .build(),
+        )
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{context::Context, main_loop::MainLoop, properties::properties};
+    use std::sync::Mutex;
+
+    #[test]
+    fn topic_patterns() {
+        assert!(matches("pw.context", "pw.context"));
+        assert!(!matches("pw.context", "pw.core"));
+        assert!(!matches("pw.context", "pw.context.x"));
+        assert!(matches("*", "pw.context"));
+        assert!(matches("*", ""));
+        assert!(matches("mod.*", "mod.protocol-native"));
+        assert!(!matches("mod.*", "pw.module"));
+        assert!(matches("*.context", "pw.context"));
+        assert!(matches("spa.*.convert*", "spa.audio.convert"));
+        assert!(matches("spa.*convert", "spa.videoconvert.convert"));
+        assert!(!matches("spa.*convert", "spa.audioconvert.x"));
+        assert!(!matches("", "pw"));
+    }
+
+    /// Keeps the messages logged through the `log` facade.
+    struct Capture(Mutex<Vec<(Level, String, String)>>);
+
+    impl ::log::Log for Capture {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            self.0.lock().unwrap().push((
+                record.level(),
+                record.target().to_owned(),
+                record.args().to_string(),
+            ));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static CAPTURE: Capture = Capture(Mutex::new(Vec::new()));
+
+    #[test]
+    fn forward() {
+        crate::init();
+        ::log::set_logger(&CAPTURE).unwrap();
+        ::log::set_max_level(LevelFilter::Warn);
+        install();
+
+        // a context with a missing configuration file fails with a warning
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::with_properties(
+            &mainloop,
+            properties! {
+                "config.name" => "pipewire-rs-does-not-exist.conf",
+            },
+        );
+        assert!(context.is_err());
+
+        let messages = CAPTURE.0.lock().unwrap();
+        assert!(
+            messages
+                .iter()
+                .any(|(level, _, message)| *level <= Level::Warn
+                    && message.contains("pipewire-rs-does-not-exist.conf")),
+            "{messages:?}"
+        );
+        assert!(
+            messages.iter().all(|(level, _, _)| *level <= Level::Warn),
+            "{messages:?}"
+        );
+    }
+}
@@ -2,19 +2,28 @@
 // SPDX-License-Identifier: MIT
 
 use std::{
+    cell::{Cell, RefCell},
     convert::TryInto,
     ops::Deref,
     os::unix::prelude::*,
+    pin::Pin,
     ptr::{self, NonNull},
     rc::{Rc, Weak},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use libc::{c_int, c_void};
 pub use nix::sys::signal::Signal;
-use spa::{spa_interface_call_method, support::system::IoFlags, utils::result::SpaResult};
+use spa::{
+    spa_interface_call_method,
+    support::system::{ClockId, IoFlags, SystemRef},
+    utils::result::SpaResult,
+};
 
-use crate::{utils::assert_main_thread, Error};
+use crate::{
+    utils::{run_loop, Guarded},
+    Error,
+};
 
 /// A transparent wrapper around a raw [`pw_loop`](`pw_sys::pw_loop`).
 /// It is usually only seen in a reference (`&LoopRef`).
@@ -22,6 +31,9 @@ use crate::{utils::assert_main_thread, Error};
 /// An owned version, [`Loop`], is available,
 /// which lets you create and own a [`pw_loop`](`pw_sys::pw_loop`),
 /// but other objects, such as [`MainLoop`](`crate::main_loop::MainLoop`), also contain them.
+///
+/// Adding a source to the loop fails with the errno reported by PipeWire,
+/// such as `EMFILE` when the process ran out of file descriptors.
 #[repr(transparent)]
 pub struct LoopRef(pw_sys::pw_loop);
 
@@ -34,6 +46,14 @@ impl LoopRef {
         std::ptr::addr_of!(self.0).cast_mut()
     }
 
+    /// The system used by this loop to access clocks and file descriptors.
+    ///
+    /// The timers of the loop use its [`ClockId::Monotonic`] clock, which may differ from the clock
+    /// of [`Instant`] if a custom system is used.
+    pub fn system(&self) -> &SystemRef {
+        unsafe { SystemRef::from_raw(self.as_raw().system) }
+    }
+
     /// Get the file descriptor backing this loop.
     pub fn fd(&self) -> BorrowedFd<'_> {
         unsafe {
@@ -97,7 +117,7 @@ impl LoopRef {
     ///
     /// # Panics
     /// This function will panic if the provided timeout as milliseconds does not fit inside a
-    /// `c_int` integer.
+    /// `c_int` integer, or resume the panic of a callback called during the iteration.
     pub fn iterate(&self, timeout: std::time::Duration) -> i32 {
         unsafe {
             self.enter();
@@ -110,16 +130,27 @@ impl LoopRef {
 
     /// A variant of [`iterate()`](`Self::iterate()`) that does not call [`Self::enter()`]  and [`Self::leave()`] on the loop.
     ///
+    /// If a callback panics during the iteration, the panic is resumed once the iteration is done.
+    ///
     /// # Safety
     /// Before calling this, [`Self::enter()`] must be called, and [`Self::leave()`] must be called afterwards.
     pub unsafe fn iterate_unguarded(&self, timeout: std::time::Duration) -> i32 {
-        let mut iface = self.as_raw().control.as_ref().unwrap().iface;
-
         let timeout: c_int = timeout
             .as_millis()
             .try_into()
             .expect("Provided timeout does not fit in a c_int");
 
+        run_loop(None, || self.iterate_unguarded_ms(timeout))
+    }
+
+    /// A variant of [`iterate_unguarded()`](`Self::iterate_unguarded()`) taking the timeout in milliseconds,
+    /// -1 blocking until there is activity.
+    ///
+    /// # Safety
+    /// Before calling this, [`Self::enter()`] must be called, and [`Self::leave()`] must be called afterwards.
+    pub(crate) unsafe fn iterate_unguarded_ms(&self, timeout: c_int) -> i32 {
+        let mut iface = self.as_raw().control.as_ref().unwrap().iface;
+
         spa_interface_call_method!(
             &mut iface as *mut spa_sys::spa_interface,
             spa_sys::spa_loop_control_methods,
@@ -134,23 +165,38 @@ impl LoopRef {
     /// The specified `event_mask` determines whether to trigger when either input, output, or any of the two is available.
     ///
     /// The returned IoSource needs to take ownership of the IO object, but will provide a reference to the callback when called.
-    #[must_use]
-    pub fn add_io<I, F>(&self, io: I, event_mask: IoFlags, callback: F) -> IoSource<I>
+    pub fn add_io<I, F>(
+        &self,
+        io: I,
+        event_mask: IoFlags,
+        callback: F,
+    ) -> Result<IoSource<I>, Error>
     where
         I: AsRawFd,
-        F: Fn(&mut I) + 'static,
+        F: FnMut(&mut I) + 'static,
         Self: Sized,
     {
         unsafe extern "C" fn call_closure<I>(data: *mut c_void, _fd: RawFd, _mask: u32)
         where
             I: AsRawFd,
         {
-            let (io, callback) = (data as *mut IoSourceData<I>).as_mut().unwrap();
-            callback(io);
+            let data = (data as *const SourceData<IoSourceData<I>>)
+                .as_ref()
+                .unwrap();
+            let Some(mut callback) = data.callback.enter() else {
+                return;
+            };
+            crate::utils::catch_source_panic("io source", &data.name, (), || {
+                let (io, callback) = &mut *callback;
+                callback(io);
+            })
         }
 
         let fd = io.as_raw_fd();
-        let data = Box::into_raw(Box::new((io, Box::new(callback) as Box<dyn Fn(&mut I)>)));
+        let data = Box::into_raw(Box::new(SourceData::new((
+            io,
+            Box::new(callback) as Box<dyn FnMut(&mut I)>,
+        ))));
 
         let (source, data) = unsafe {
             let mut iface = self.as_raw().utils.as_ref().unwrap().iface;
@@ -170,33 +216,35 @@ impl LoopRef {
             (source, Box::from_raw(data))
         };
 
-        let ptr = ptr::NonNull::new(source).expect("source is NULL");
+        let ptr = ptr::NonNull::new(source).ok_or_else(Error::last_os_error)?;
 
-        IoSource {
+        Ok(IoSource {
             ptr,
             loop_: self,
-            _data: data,
-        }
+            data,
+        })
     }
 
     /// Register a callback to be called whenever the loop is idle.
     ///
     /// This can be enabled and disabled as needed with the `enabled` parameter,
     /// and also with the `enable` method on the returned source.
-    #[must_use]
-    pub fn add_idle<F>(&self, enabled: bool, callback: F) -> IdleSource
+    pub fn add_idle<F>(&self, enabled: bool, callback: F) -> Result<IdleSource, Error>
     where
-        F: Fn() + 'static,
+        F: FnMut() + 'static,
     {
         unsafe extern "C" fn call_closure<F>(data: *mut c_void)
         where
-            F: Fn(),
+            F: FnMut(),
         {
-            let callback = (data as *mut F).as_ref().unwrap();
-            callback();
+            let data = (data as *const SourceData<F>).as_ref().unwrap();
+            let Some(mut callback) = data.callback.enter() else {
+                return;
+            };
+            crate::utils::catch_source_panic("idle source", &data.name, (), || (*callback)())
         }
 
-        let data = Box::into_raw(Box::new(callback));
+        let data = Box::into_raw(Box::new(SourceData::new(callback)));
 
         let (source, data) = unsafe {
             let mut iface = self.as_raw().utils.as_ref().unwrap().iface;
@@ -213,35 +261,40 @@ impl LoopRef {
             (source, Box::from_raw(data))
         };
 
-        let ptr = ptr::NonNull::new(source).expect("source is NULL");
+        let ptr = ptr::NonNull::new(source).ok_or_else(Error::last_os_error)?;
 
-        IdleSource {
+        Ok(IdleSource {
             ptr,
             loop_: self,
-            _data: data,
-        }
+            data,
+        })
     }
 
     /// Register a signal with a callback that is called when the signal is sent.
     ///
     /// For example, this can be used to quit the loop when the process receives the `SIGTERM` signal.
-    #[must_use]
-    pub fn add_signal_local<F>(&self, signal: Signal, callback: F) -> SignalSource
+    ///
+    /// Like other sources, this must be called from the thread the loop belongs to, which
+    /// [`MainLoop`](crate::main_loop::MainLoop) and [`Loop`] ensure by not being `Send`,
+    /// or with the lock of a [`ThreadLoop`](crate::thread_loop::ThreadLoop) held.
+    /// The signal is blocked on the calling thread, so it is delivered to the loop instead.
+    pub fn add_signal_local<F>(&self, signal: Signal, callback: F) -> Result<SignalSource, Error>
     where
-        F: Fn() + 'static,
+        F: FnMut() + 'static,
         Self: Sized,
     {
-        assert_main_thread();
-
         unsafe extern "C" fn call_closure<F>(data: *mut c_void, _signal: c_int)
         where
-            F: Fn(),
+            F: FnMut(),
         {
-            let callback = (data as *mut F).as_ref().unwrap();
-            callback();
+            let data = (data as *const SourceData<F>).as_ref().unwrap();
+            let Some(mut callback) = data.callback.enter() else {
+                return;
+            };
+            crate::utils::catch_source_panic("signal source", &data.name, (), || (*callback)())
         }
 
-        let data = Box::into_raw(Box::new(callback));
+        let data = Box::into_raw(Box::new(SourceData::new(callback)));
 
         let (source, data) = unsafe {
             let mut iface = self.as_raw().utils.as_ref().unwrap().iface;
@@ -258,33 +311,35 @@ impl LoopRef {
             (source, Box::from_raw(data))
         };
 
-        let ptr = ptr::NonNull::new(source).expect("source is NULL");
+        let ptr = ptr::NonNull::new(source).ok_or_else(Error::last_os_error)?;
 
-        SignalSource {
+        Ok(SignalSource {
             ptr,
             loop_: self,
-            _data: data,
-        }
+            data,
+        })
     }
 
     /// Register a new event with a callback that is called when the event happens.
     ///
     /// The returned [`EventSource`] can be used to trigger the event.
-    #[must_use]
-    pub fn add_event<F>(&self, callback: F) -> EventSource
+    pub fn add_event<F>(&self, callback: F) -> Result<EventSource, Error>
     where
-        F: Fn() + 'static,
+        F: FnMut() + 'static,
         Self: Sized,
     {
         unsafe extern "C" fn call_closure<F>(data: *mut c_void, _count: u64)
         where
-            F: Fn(),
+            F: FnMut(),
         {
-            let callback = (data as *mut F).as_ref().unwrap();
-            callback();
+            let data = (data as *const SourceData<F>).as_ref().unwrap();
+            let Some(mut callback) = data.callback.enter() else {
+                return;
+            };
+            crate::utils::catch_source_panic("event source", &data.name, (), || (*callback)())
         }
 
-        let data = Box::into_raw(Box::new(callback));
+        let data = Box::into_raw(Box::new(SourceData::new(callback)));
 
         let (source, data) = unsafe {
             let mut iface = self.as_raw().utils.as_ref().unwrap().iface;
@@ -299,13 +354,13 @@ impl LoopRef {
             (source, Box::from_raw(data))
         };
 
-        let ptr = ptr::NonNull::new(source).expect("source is NULL");
+        let ptr = ptr::NonNull::new(source).ok_or_else(Error::last_os_error)?;
 
-        EventSource {
+        Ok(EventSource {
             ptr,
             loop_: self,
-            _data: data,
-        }
+            data,
+        })
     }
 
     /// Register a timer with the loop with a callback that is called after the timer expired.
@@ -313,21 +368,25 @@ impl LoopRef {
     /// The timer will start out inactive, and the returned [`TimerSource`] can be used to arm the timer, or disarm it again.
     ///
     /// The callback will be provided with the number of timer expirations since the callback was last called.
-    #[must_use]
-    pub fn add_timer<F>(&self, callback: F) -> TimerSource
+    pub fn add_timer<F>(&self, callback: F) -> Result<TimerSource, Error>
     where
-        F: Fn(u64) + 'static,
+        F: FnMut(u64) + 'static,
         Self: Sized,
     {
         unsafe extern "C" fn call_closure<F>(data: *mut c_void, expirations: u64)
         where
-            F: Fn(u64),
+            F: FnMut(u64),
         {
-            let callback = (data as *mut F).as_ref().unwrap();
-            callback(expirations);
+            let data = (data as *const SourceData<F>).as_ref().unwrap();
+            let Some(mut callback) = data.callback.enter() else {
+                return;
+            };
+            crate::utils::catch_source_panic("timer source", &data.name, (), || {
+                (*callback)(expirations)
+            })
         }
 
-        let data = Box::into_raw(Box::new(callback));
+        let data = Box::into_raw(Box::new(SourceData::new(callback)));
 
         let (source, data) = unsafe {
             let mut iface = self.as_raw().utils.as_ref().unwrap().iface;
@@ -342,12 +401,54 @@ impl LoopRef {
             (source, Box::from_raw(data))
         };
 
-        let ptr = ptr::NonNull::new(source).expect("source is NULL");
+        let ptr = ptr::NonNull::new(source).ok_or_else(Error::last_os_error)?;
 
-        TimerSource {
+        Ok(TimerSource {
             ptr,
             loop_: self,
-            _data: data,
+            data,
+        })
+    }
+
+    /// Set an observer called after each Rust callback dispatched by the loop, with the name of the
+    /// callback and the wall time it took, to find the callbacks stalling the loop.
+    ///
+    /// The callbacks are the ones of the sources of this crate, named with their `set_name` method
+    /// or after their kind such as `"timer source"`, and the ones of listeners, named after their
+    /// event such as `"registry global"`.
+    /// Callbacks are only timed while an observer is set.
+    ///
+    /// The observer is called from the thread dispatching the loop, and is removed when the
+    /// returned [`DispatchObserver`] is dropped.
+    /// Like sources, it must be set and dropped with the lock of a
+    /// [`ThreadLoop`](crate::thread_loop::ThreadLoop) held.
+    #[must_use]
+    pub fn set_dispatch_observer<F>(&self, observer: F) -> DispatchObserver<'_>
+    where
+        F: Fn(&str, Duration) + 'static,
+    {
+        let data = Rc::new(ObserverData {
+            callback: Box::new(observer),
+        });
+        let mut hook: Pin<Box<spa_sys::spa_hook>> = Box::pin(unsafe { std::mem::zeroed() });
+
+        unsafe {
+            let mut iface = self.as_raw().control.as_ref().unwrap().iface;
+
+            spa_interface_call_method!(
+                &mut iface as *mut spa_sys::spa_interface,
+                spa_sys::spa_loop_control_methods,
+                add_hook,
+                hook.as_mut().get_unchecked_mut(),
+                &OBSERVER_HOOKS,
+                Rc::as_ptr(&data).cast_mut().cast()
+            );
+        }
+
+        DispatchObserver {
+            _loop: self,
+            hook,
+            data,
         }
     }
 
@@ -379,6 +480,14 @@ impl LoopRef {
 /// of the trait implementor is still alive. \
 pub unsafe trait IsLoopRc: Clone + AsRef<LoopRef> + 'static {}
 
+/// A standalone, reference counted [`pw_loop`](`pw_sys::pw_loop`).
+///
+/// Unlike [`MainLoop`](`crate::main_loop::MainLoop`), this loop has no run/quit machinery and
+/// is meant to be driven by an external poller: wait for [`LoopRef::fd()`] to become readable,
+/// then dispatch the pending events with [`LoopRef::iterate()`] and a zero timeout.
+///
+/// The loop implements [`IsLoopRc`], so it can be passed to [`Context::new`](`crate::context::Context::new`)
+/// and used to create sources. It is destroyed once the last clone is dropped.
 #[derive(Clone, Debug)]
 pub struct Loop {
     inner: Rc<LoopInner>,
@@ -464,12 +573,118 @@ impl Drop for LoopInner {
     }
 }
 
+/// The data of a source: its callback and the name reported to the dispatch observer.
+struct SourceData<C: ?Sized> {
+    name: SourceName,
+    callback: Guarded<C>,
+}
+
+impl<C> SourceData<C> {
+    fn new(callback: C) -> Self {
+        Self {
+            name: SourceName::default(),
+            callback: Guarded::new(callback),
+        }
+    }
+}
+
+/// The name of a source, set with the `set_name` method of the sources.
+#[derive(Default)]
+pub(crate) struct SourceName(RefCell<Option<Rc<str>>>);
+
+impl SourceName {
+    fn set(&self, name: &str) {
+        *self.0.borrow_mut() = Some(name.into());
+    }
+
+    pub(crate) fn get(&self) -> Option<Rc<str>> {
+        self.0.borrow().clone()
+    }
+}
+
+thread_local! {
+    /// The observer of the loop dispatching on this thread, set by the hooks of [`DispatchObserver`].
+    static CURRENT_OBSERVER: Cell<Option<NonNull<ObserverData>>> = const { Cell::new(None) };
+}
+
+struct ObserverData {
+    callback: Box<dyn Fn(&str, Duration)>,
+}
+
+/// The observer of the loop dispatching on this thread, if any.
+pub(crate) fn current_observer() -> Option<DispatchReporter> {
+    let data = CURRENT_OBSERVER.with(Cell::get)?;
+    // Safety: the pointer is cleared before the data of its observer is dropped,
+    //         and the strong count keeps it alive while the callback is timed.
+    let data = unsafe {
+        Rc::increment_strong_count(data.as_ptr());
+        Rc::from_raw(data.as_ptr())
+    };
+    Some(DispatchReporter(data))
+}
+
+/// Reports a callback to the observer returned by [`current_observer`].
+pub(crate) struct DispatchReporter(Rc<ObserverData>);
+
+impl DispatchReporter {
+    pub(crate) fn report(&self, name: &str, elapsed: Duration) {
+        (self.0.callback)(name, elapsed)
+    }
+}
+
+static OBSERVER_HOOKS: spa_sys::spa_loop_control_hooks = spa_sys::spa_loop_control_hooks {
+    version: spa_sys::SPA_VERSION_LOOP_CONTROL_HOOKS,
+    before: Some(observer_before),
+    after: Some(observer_after),
+};
+
+/// Called before the loop waits for events.
+unsafe extern "C" fn observer_before(data: *mut c_void) {
+    CURRENT_OBSERVER.with(|current| {
+        if current.get() == NonNull::new(data.cast()) {
+            current.set(None);
+        }
+    });
+}
+
+/// Called after the loop waited for events, before it dispatches them.
+unsafe extern "C" fn observer_after(data: *mut c_void) {
+    CURRENT_OBSERVER.with(|current| current.set(NonNull::new(data.cast())));
+}
+
+/// An observer of the callbacks dispatched by a loop, see [`LoopRef::set_dispatch_observer`].
+///
+/// The observer is removed when this is dropped.
+pub struct DispatchObserver<'l> {
+    _loop: &'l LoopRef,
+    hook: Pin<Box<spa_sys::spa_hook>>,
+    data: Rc<ObserverData>,
+}
+
+impl<'l> Drop for DispatchObserver<'l> {
+    fn drop(&mut self) {
+        spa::utils::hook::remove(*self.hook);
+        let data = NonNull::from(&*self.data);
+        CURRENT_OBSERVER.with(|current| {
+            if current.get() == Some(data) {
+                current.set(None);
+            }
+        });
+    }
+}
+
+impl<'l> std::fmt::Debug for DispatchObserver<'l> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DispatchObserver").finish_non_exhaustive()
+    }
+}
+
 pub trait IsSource {
     /// Return a valid pointer to a raw `spa_source`.
     fn as_ptr(&self) -> *mut spa_sys::spa_source;
 }
 
-type IoSourceData<I> = (I, Box<dyn Fn(&mut I) + 'static>);
+type IoSourceData<I> = (I, Box<dyn FnMut(&mut I) + 'static>);
 
 /// A source that can be used to react to IO events.
 ///
@@ -481,7 +696,17 @@ where
     ptr: ptr::NonNull<spa_sys::spa_source>,
     loop_: &'l LoopRef,
     // Store data wrapper to prevent leak
-    _data: Box<IoSourceData<I>>,
+    data: Box<SourceData<IoSourceData<I>>>,
+}
+
+impl<'l, I> IoSource<'l, I>
+where
+    I: AsRawFd,
+{
+    /// Set the name reported for the callbacks of this source, see [`LoopRef::set_dispatch_observer`].
+    pub fn set_name(&self, name: &str) {
+        self.data.name.set(name);
+    }
 }
 
 impl<'l, I> IsSource for IoSource<'l, I>
@@ -509,10 +734,15 @@ pub struct IdleSource<'l> {
     ptr: ptr::NonNull<spa_sys::spa_source>,
     loop_: &'l LoopRef,
     // Store data wrapper to prevent leak
-    _data: Box<dyn Fn() + 'static>,
+    data: Box<SourceData<dyn FnMut() + 'static>>,
 }
 
 impl<'l> IdleSource<'l> {
+    /// Set the name reported for the callbacks of this source, see [`LoopRef::set_dispatch_observer`].
+    pub fn set_name(&self, name: &str) {
+        self.data.name.set(name);
+    }
+
     /// Set the source as enabled or disabled, allowing or preventing the callback from being called.
     pub fn enable(&self, enable: bool) {
         unsafe {
@@ -548,7 +778,14 @@ pub struct SignalSource<'l> {
     ptr: ptr::NonNull<spa_sys::spa_source>,
     loop_: &'l LoopRef,
     // Store data wrapper to prevent leak
-    _data: Box<dyn Fn() + 'static>,
+    data: Box<SourceData<dyn FnMut() + 'static>>,
+}
+
+impl<'l> SignalSource<'l> {
+    /// Set the name reported for the callbacks of this source, see [`LoopRef::set_dispatch_observer`].
+    pub fn set_name(&self, name: &str) {
+        self.data.name.set(name);
+    }
 }
 
 impl<'l> IsSource for SignalSource<'l> {
@@ -573,7 +810,7 @@ pub struct EventSource<'l> {
     ptr: ptr::NonNull<spa_sys::spa_source>,
     loop_: &'l LoopRef,
     // Store data wrapper to prevent leak
-    _data: Box<dyn Fn() + 'static>,
+    data: Box<SourceData<dyn FnMut() + 'static>>,
 }
 
 impl<'l> IsSource for EventSource<'l> {
@@ -583,6 +820,11 @@ impl<'l> IsSource for EventSource<'l> {
 }
 
 impl<'l> EventSource<'l> {
+    /// Set the name reported for the callbacks of this source, see [`LoopRef::set_dispatch_observer`].
+    pub fn set_name(&self, name: &str) {
+        self.data.name.set(name);
+    }
+
     /// Signal the loop associated with this source that the event has occurred,
     /// to make the loop call the callback at the next possible occasion.
     pub fn signal(&self) -> SpaResult {
@@ -617,10 +859,15 @@ pub struct TimerSource<'l> {
     ptr: ptr::NonNull<spa_sys::spa_source>,
     loop_: &'l LoopRef,
     // Store data wrapper to prevent leak
-    _data: Box<dyn Fn(u64) + 'static>,
+    data: Box<SourceData<dyn FnMut(u64) + 'static>>,
 }
 
 impl<'l> TimerSource<'l> {
+    /// Set the name reported for the callbacks of this source, see [`LoopRef::set_dispatch_observer`].
+    pub fn set_name(&self, name: &str) {
+        self.data.name.set(name);
+    }
+
     /// Arm or disarm the timer.
     ///
     /// The timer will be called the next time after the provided `value` duration.
@@ -632,21 +879,38 @@ impl<'l> TimerSource<'l> {
     /// # Panics
     /// The provided durations seconds must fit in an i64. Otherwise, this function will panic.
     pub fn update_timer(&self, value: Option<Duration>, interval: Option<Duration>) -> SpaResult {
-        fn duration_to_timespec(duration: Duration) -> spa_sys::timespec {
-            spa_sys::timespec {
-                tv_sec: duration.as_secs().try_into().expect("Duration too long"),
-                // `Into` is only implemented on some platforms for these types,
-                // so use a fallible conversion.
-                // As there are a limited amount of nanoseconds in a second, this shouldn't fail
-                #[allow(clippy::unnecessary_fallible_conversions)]
-                tv_nsec: duration
-                    .subsec_nanos()
-                    .try_into()
-                    .expect("Nanoseconds should fit into timespec"),
-            }
+        match value {
+            Some(delay) if !delay.is_zero() => self.set(TimerSpec::After { delay, interval }),
+            _ => self.set(TimerSpec::Disarmed),
         }
+    }
+
+    /// Arm the timer to be called at `instant`, then repeatedly at the specified `interval`.
+    ///
+    /// This is a shorthand for [`set`](Self::set) with [`TimerSpec::At`].
+    pub fn at(&self, instant: Instant, interval: Option<Duration>) -> SpaResult {
+        self.set(TimerSpec::At { instant, interval })
+    }
 
-        let value = duration_to_timespec(value.unwrap_or_default());
+    /// Arm or disarm the timer as described by `spec`.
+    ///
+    /// # Panics
+    /// The provided durations seconds must fit in an i64. Otherwise, this function will panic.
+    pub fn set(&self, spec: TimerSpec) -> SpaResult {
+        let (value, interval, absolute) = match spec {
+            TimerSpec::Disarmed => (Duration::ZERO, None, false),
+            // a zero value disarms the timer, expire as soon as possible instead
+            TimerSpec::After { delay, interval } => {
+                (delay.max(Duration::from_nanos(1)), interval, false)
+            }
+            TimerSpec::At { instant, interval } => {
+                match monotonic_time(self.loop_.system(), instant) {
+                    Ok(value) => (value, interval, true),
+                    Err(err) => return SpaResult::from_c(-(err.errno() as i32)),
+                }
+            }
+        };
+        let value = duration_to_timespec(value);
         let interval = duration_to_timespec(interval.unwrap_or_default());
 
         let res = unsafe {
@@ -659,7 +923,7 @@ impl<'l> TimerSource<'l> {
                 self.as_ptr(),
                 &value as *const _ as *mut _,
                 &interval as *const _ as *mut _,
-                false
+                absolute
             )
         };
 
@@ -667,6 +931,54 @@ impl<'l> TimerSource<'l> {
     }
 }
 
+/// When a [`TimerSource`] expires, used with [`TimerSource::set`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerSpec {
+    /// The timer does not expire.
+    Disarmed,
+    /// The timer expires after `delay`, then every `interval` if any.
+    ///
+    /// A zero `delay` expires as soon as possible.
+    After {
+        delay: Duration,
+        interval: Option<Duration>,
+    },
+    /// The timer expires at `instant`, then every `interval` if any.
+    ///
+    /// An `instant` in the past expires as soon as possible.
+    At {
+        instant: Instant,
+        interval: Option<Duration>,
+    },
+}
+
+fn duration_to_timespec(duration: Duration) -> spa_sys::timespec {
+    spa_sys::timespec {
+        tv_sec: duration.as_secs().try_into().expect("Duration too long"),
+        // `Into` is only implemented on some platforms for these types,
+        // so use a fallible conversion.
+        // As there are a limited amount of nanoseconds in a second, this shouldn't fail
+        #[allow(clippy::unnecessary_fallible_conversions)]
+        tv_nsec: duration
+            .subsec_nanos()
+            .try_into()
+            .expect("Nanoseconds should fit into timespec"),
+    }
+}
+
+/// Convert `instant` to a time of the monotonic clock of `system`, the clock of the timers of a
+/// loop.
+///
+/// `Instant` does not expose its clock, so the conversion goes through the current time of both.
+/// Instants in the past are clamped to the current time.
+fn monotonic_time(
+    system: &SystemRef,
+    instant: Instant,
+) -> Result<Duration, spa::utils::result::Error> {
+    let now = system.clock_gettime(ClockId::Monotonic)?;
+    Ok(now + instant.saturating_duration_since(Instant::now()))
+}
+
 impl<'l> IsSource for TimerSource<'l> {
     fn as_ptr(&self) -> *mut spa_sys::spa_source {
         self.ptr.as_ptr()
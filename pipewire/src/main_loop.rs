@@ -3,10 +3,15 @@
 
 use std::ptr::{self, NonNull};
 use std::rc::{Rc, Weak};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, PoisonError,
+};
 
 use crate::{
     error::Error,
     loop_::{IsLoopRc, LoopRef},
+    utils::run_loop,
 };
 
 #[derive(Debug, Clone)]
@@ -16,6 +21,9 @@ pub struct MainLoop {
 
 impl MainLoop {
     /// Initialize Pipewire and create a new `MainLoop`
+    ///
+    /// The properties configure the loop, such as `loop.name` which names it in the logs of PipeWire.
+    /// They are only read during the call.
     pub fn new(properties: Option<&spa::utils::dict::DictRef>) -> Result<Self, Error> {
         super::init();
 
@@ -59,15 +67,50 @@ impl MainLoop {
         }
     }
 
+    /// Run the loop until it is quit.
+    ///
+    /// If a callback panics while the loop is running, the loop is quit and the panic
+    /// is resumed once it returns.
     pub fn run(&self) {
-        unsafe {
-            pw_sys::pw_main_loop_run(self.as_raw_ptr());
-        }
+        run_loop(Some(self.quit_handle()), || {
+            unsafe {
+                pw_sys::pw_main_loop_run(self.as_raw_ptr());
+            }
+            self.inner.quit.requested.store(false, Ordering::SeqCst);
+        })
+    }
+
+    /// Run the loop until `done` returns `true`, or until the loop is quit.
+    ///
+    /// `done` is called before each iteration of the loop, so it is checked again
+    /// each time events have been dispatched. Panics of callbacks are handled as in [`run`](Self::run).
+    pub fn run_until<F>(&self, mut done: F)
+    where
+        F: FnMut(&Self) -> bool,
+    {
+        let loop_ = self.loop_();
+        run_loop(Some(self.quit_handle()), || {
+            unsafe {
+                loop_.enter();
+                while !self.inner.quit.requested.load(Ordering::SeqCst) && !done(self) {
+                    if loop_.iterate_unguarded_ms(-1) < 0 {
+                        break;
+                    }
+                }
+                loop_.leave();
+            }
+            self.inner.quit.requested.store(false, Ordering::SeqCst);
+        })
     }
 
     pub fn quit(&self) {
-        unsafe {
-            pw_sys::pw_main_loop_quit(self.as_raw_ptr());
+        self.inner.quit.quit();
+    }
+
+    /// Get a handle to quit the loop from any thread.
+    pub fn quit_handle(&self) -> MainLoopQuitHandle {
+        MainLoopQuitHandle {
+            state: self.inner.quit.clone(),
         }
     }
 }
@@ -92,19 +135,444 @@ impl WeakMainLoop {
     }
 }
 
+/// A cloneable handle to quit a [`MainLoop`] from any thread, see [`MainLoop::quit_handle`].
+///
+/// Quitting a loop which has been destroyed does nothing.
+#[derive(Debug, Clone)]
+pub struct MainLoopQuitHandle {
+    state: Arc<QuitState>,
+}
+
+impl MainLoopQuitHandle {
+    /// Make the running loop return from [`MainLoop::run`] or [`MainLoop::run_until`],
+    /// waking it up if needed.
+    pub fn quit(&self) {
+        self.state.quit();
+    }
+}
+
+#[derive(Debug)]
+struct LoopPtr(NonNull<pw_sys::pw_main_loop>);
+
+// Safety: the pointer is only used to call `pw_main_loop_quit()`, which can be called from any thread.
+unsafe impl Send for LoopPtr {}
+
+#[derive(Debug)]
+struct QuitState {
+    /// The loop, `None` once it has been destroyed.
+    ptr: Mutex<Option<LoopPtr>>,
+    /// Whether the loop has been quit, for `run_until()` which does not use `pw_main_loop_run()`.
+    requested: AtomicBool,
+}
+
+impl QuitState {
+    fn quit(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+
+        // Keep the lock while quitting so the loop cannot be destroyed meanwhile.
+        let ptr = self.ptr.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(ptr) = &*ptr {
+            unsafe {
+                pw_sys::pw_main_loop_quit(ptr.0.as_ptr());
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 struct MainLoopInner {
     ptr: ptr::NonNull<pw_sys::pw_main_loop>,
+    quit: Arc<QuitState>,
 }
 
 impl MainLoopInner {
     pub unsafe fn from_raw(ptr: NonNull<pw_sys::pw_main_loop>) -> Self {
-        Self { ptr }
+        Self {
+            ptr,
+            quit: Arc::new(QuitState {
+                ptr: Mutex::new(Some(LoopPtr(ptr))),
+                requested: AtomicBool::new(false),
+            }),
+        }
     }
 }
 
 impl Drop for MainLoopInner {
     fn drop(&mut self) {
+        let mut quit_ptr = self.quit.ptr.lock().unwrap_or_else(PoisonError::into_inner);
+        *quit_ptr = None;
         unsafe { pw_sys::pw_main_loop_destroy(self.ptr.as_ptr()) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc, thread, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn quit_from_thread() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let handle = mainloop.quit_handle();
+
+        let thread = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            handle.quit();
+        });
+
+        mainloop.run();
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn signal_outside_main_thread() {
+        use nix::sys::signal::{raise, Signal};
+
+        // sources can be added from any thread owning the loop, not only the main thread
+        thread::Builder::new()
+            .name("loop".to_owned())
+            .spawn(|| {
+                let mainloop = MainLoop::new(None).unwrap();
+                let _signal = mainloop
+                    .loop_()
+                    .add_signal_local(Signal::SIGUSR1, {
+                        let mainloop = mainloop.clone();
+                        move || mainloop.quit()
+                    })
+                    .unwrap();
+
+                raise(Signal::SIGUSR1).unwrap();
+                mainloop.run();
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn run_until() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let count = Rc::new(Cell::new(0));
+
+        let timer = mainloop
+            .loop_()
+            .add_timer({
+                let count = count.clone();
+                move |_| count.set(count.get() + 1)
+            })
+            .unwrap();
+        timer
+            .update_timer(
+                Some(Duration::from_millis(1)),
+                Some(Duration::from_millis(1)),
+            )
+            .into_sync_result()
+            .unwrap();
+
+        mainloop.run_until(|_| count.get() >= 3);
+        assert!(count.get() >= 3);
+
+        // quitting also stops the loop
+        let handle = mainloop.quit_handle();
+        let thread = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            handle.quit();
+        });
+        mainloop.run_until(|_| false);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn absolute_timers() {
+        use std::{cell::RefCell, time::Instant};
+
+        let mainloop = MainLoop::new(None).unwrap();
+        let fired = Rc::new(RefCell::new(Vec::new()));
+        let add_timer = |name: &'static str| {
+            let fired = fired.clone();
+            mainloop
+                .loop_()
+                .add_timer(move |_| fired.borrow_mut().push((name, Instant::now())))
+                .unwrap()
+        };
+        let late = add_timer("late");
+        let early = add_timer("early");
+
+        // scheduled out of order, the timers still fire in the order of their instants
+        let start = Instant::now();
+        late.at(start + Duration::from_millis(60), None)
+            .into_sync_result()
+            .unwrap();
+        early
+            .at(start + Duration::from_millis(20), None)
+            .into_sync_result()
+            .unwrap();
+
+        mainloop.run_until(|_| fired.borrow().len() >= 2);
+        let fired = fired.borrow();
+        assert_eq!(
+            fired.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+            ["early", "late"]
+        );
+        assert!(fired[0].1 >= start + Duration::from_millis(20));
+        assert!(fired[1].1 >= start + Duration::from_millis(60));
+    }
+
+    #[test]
+    fn timer_in_the_past() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let count = Rc::new(Cell::new(0));
+
+        let timer = mainloop
+            .loop_()
+            .add_timer({
+                let count = count.clone();
+                move |_| count.set(count.get() + 1)
+            })
+            .unwrap();
+
+        let past = std::time::Instant::now()
+            .checked_sub(Duration::from_secs(1))
+            .unwrap_or_else(std::time::Instant::now);
+        timer.at(past, None).into_sync_result().unwrap();
+        mainloop.run_until(|_| count.get() >= 1);
+
+        // a zero delay also expires right away instead of disarming the timer
+        timer
+            .set(crate::loop_::TimerSpec::After {
+                delay: Duration::ZERO,
+                interval: None,
+            })
+            .into_sync_result()
+            .unwrap();
+        mainloop.run_until(|_| count.get() >= 2);
+    }
+
+    #[test]
+    fn system_clock() {
+        use spa::support::system::ClockId;
+
+        let mainloop = MainLoop::new(None).unwrap();
+        let system = mainloop.loop_().system();
+
+        let start = std::time::Instant::now();
+        let first = system.clock_gettime(ClockId::Monotonic).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        let second = system.clock_gettime(ClockId::Monotonic).unwrap();
+        let elapsed = start.elapsed();
+
+        assert!(second >= first + Duration::from_millis(20));
+        // `Instant` uses the same clock, read around the system clock
+        assert!(second - first <= elapsed);
+
+        let realtime = system.clock_gettime(ClockId::Realtime).unwrap();
+        let since_epoch = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap();
+        assert!(since_epoch.max(realtime) - since_epoch.min(realtime) < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn dispatch_observer() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let reports = Rc::new(std::cell::RefCell::new(Vec::new()));
+
+        let observer = mainloop.loop_().set_dispatch_observer({
+            let reports = reports.clone();
+            move |name, elapsed| reports.borrow_mut().push((name.to_owned(), elapsed))
+        });
+
+        let fired = Rc::new(Cell::new(0));
+        let slow = mainloop
+            .loop_()
+            .add_timer({
+                let fired = fired.clone();
+                move |_| {
+                    thread::sleep(Duration::from_millis(30));
+                    fired.set(fired.get() + 1);
+                }
+            })
+            .unwrap();
+        slow.set_name("slow-timer");
+        slow.update_timer(Some(Duration::from_millis(1)), None)
+            .into_sync_result()
+            .unwrap();
+        let fast = mainloop
+            .loop_()
+            .add_timer({
+                let fired = fired.clone();
+                move |_| fired.set(fired.get() + 1)
+            })
+            .unwrap();
+        fast.update_timer(Some(Duration::from_millis(1)), None)
+            .into_sync_result()
+            .unwrap();
+
+        mainloop.run_until(|_| fired.get() == 2);
+
+        let (_, elapsed) = reports
+            .borrow()
+            .iter()
+            .find(|(name, _)| name == "slow-timer")
+            .cloned()
+            .expect("slow callback not reported");
+        assert!(elapsed >= Duration::from_millis(30));
+        assert!(reports
+            .borrow()
+            .iter()
+            .any(|(name, _)| name == "timer source"));
+
+        // nothing is reported once the observer is dropped
+        drop(observer);
+        let count = reports.borrow().len();
+        fast.update_timer(Some(Duration::from_millis(1)), None)
+            .into_sync_result()
+            .unwrap();
+        mainloop.run_until(|_| fired.get() == 3);
+        assert_eq!(reports.borrow().len(), count);
+    }
+
+    #[test]
+    fn mutable_callback() {
+        let mainloop = MainLoop::new(None).unwrap();
+
+        // the callback owns and mutates its state without any cell
+        let mut count = 0;
+        let timer = mainloop
+            .loop_()
+            .add_timer({
+                let mainloop = mainloop.clone();
+                move |_| {
+                    count += 1;
+                    if count == 3 {
+                        mainloop.quit();
+                    }
+                }
+            })
+            .unwrap();
+        timer
+            .update_timer(
+                Some(Duration::from_millis(1)),
+                Some(Duration::from_millis(1)),
+            )
+            .into_sync_result()
+            .unwrap();
+
+        mainloop.run();
+    }
+
+    #[test]
+    fn nested_callback() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let count = Rc::new(Cell::new(0));
+        let other = Rc::new(Cell::new(0));
+
+        // the first call runs the loop until the other timer fired, its own nested calls are skipped
+        let timer = mainloop
+            .loop_()
+            .add_timer({
+                let (mainloop, count, other) = (mainloop.clone(), count.clone(), other.clone());
+                move |_| {
+                    count.set(count.get() + 1);
+                    if count.get() == 1 {
+                        mainloop.run_until(|_| other.get() >= 3);
+                        assert_eq!(count.get(), 1);
+                    }
+                }
+            })
+            .unwrap();
+        let other_timer = mainloop
+            .loop_()
+            .add_timer({
+                let other = other.clone();
+                move |_| other.set(other.get() + 1)
+            })
+            .unwrap();
+        for timer in [&timer, &other_timer] {
+            timer
+                .update_timer(
+                    Some(Duration::from_millis(1)),
+                    Some(Duration::from_millis(1)),
+                )
+                .into_sync_result()
+                .unwrap();
+        }
+
+        mainloop.run_until(|_| count.get() >= 3);
+        assert!(other.get() >= 3);
+    }
+
+    #[test]
+    fn callback_panic() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let count = Rc::new(Cell::new(0));
+
+        let timer = mainloop
+            .loop_()
+            .add_timer({
+                let count = count.clone();
+                move |_| {
+                    count.set(count.get() + 1);
+                    if count.get() == 1 {
+                        panic!("panic in timer");
+                    }
+                }
+            })
+            .unwrap();
+        timer
+            .update_timer(
+                Some(Duration::from_millis(1)),
+                Some(Duration::from_millis(1)),
+            )
+            .into_sync_result()
+            .unwrap();
+
+        let payload =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| mainloop.run())).unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"panic in timer"));
+        assert_eq!(count.get(), 1);
+
+        // the loop keeps working after the panic
+        mainloop.run_until(|_| count.get() >= 3);
+        assert!(count.get() >= 3);
+    }
+
+    #[test]
+    fn info_callback_panic() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = crate::context::Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+
+        let _listener = core
+            .add_listener_local()
+            .info(|_| panic!("panic in info"))
+            .register();
+
+        let payload =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| mainloop.run())).unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"panic in info"));
+
+        // the loop and the core are still usable
+        let done = Rc::new(Cell::new(false));
+        let _done_listener = core
+            .add_listener_local()
+            .done({
+                let done = done.clone();
+                move |_, _| done.set(true)
+            })
+            .register();
+        core.sync(0).unwrap();
+        mainloop.run_until(|_| done.get());
+        assert!(done.get());
+    }
+
+    #[test]
+    fn quit_destroyed_loop() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let handle = mainloop.quit_handle();
+        drop(mainloop);
+
+        handle.quit();
+    }
+}
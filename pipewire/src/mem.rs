@@ -0,0 +1,403 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Memory shared with the server.
+//!
+//! The server shares memory with the client using the `add_mem` and `remove_mem` events of the core,
+//! and buffers then refer to it by id, with data of type [`DataType::MemId`].
+//! [`MemBlocks`] keeps track of these blocks so that such data can be resolved to a mappable fd.
+//!
+//! ```no_run
+//! use pipewire::{context::Context, main_loop::MainLoop, mem::MemBlocks};
+//!
+//! pipewire::init();
+//! let mainloop = MainLoop::new(None).unwrap();
+//! let context = Context::new(&mainloop).unwrap();
+//! let core = context.connect(None).unwrap();
+//!
+//! let (blocks, _listener) = MemBlocks::track(&core);
+//! // later, for the data of a buffer:
+//! // let block = blocks.borrow().resolve(data);
+//! ```
+//!
+//! Conversely, streams connected with [`StreamFlags::ALLOC_BUFFERS`](crate::stream::StreamFlags::ALLOC_BUFFERS)
+//! provide the memory of their buffers, usually allocated with [`alloc_memfd`] and attached
+//! to the buffers with [`attach_memfd`].
+
+use bitflags::bitflags;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+    ptr::NonNull,
+    rc::Rc,
+};
+
+use crate::core::{CoreRef, Listener};
+use spa::{
+    buffer::{Data, DataFlags, DataType},
+    param::DataTypes,
+};
+
+bitflags! {
+    /// Flags of a memory block.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct MemFlags: u32 {
+        /// Memory is readable
+        const READABLE = pw_sys::pw_memblock_flags::PW_MEMBLOCK_FLAG_READABLE.0;
+        /// Memory is writable
+        const WRITABLE = pw_sys::pw_memblock_flags::PW_MEMBLOCK_FLAG_WRITABLE.0;
+        /// Memory is sealed and cannot be resized
+        const SEAL = pw_sys::pw_memblock_flags::PW_MEMBLOCK_FLAG_SEAL.0;
+        /// Memory is mapped by the owner
+        const MAP = pw_sys::pw_memblock_flags::PW_MEMBLOCK_FLAG_MAP.0;
+        /// The fd is not closed by the owner
+        const DONT_CLOSE = pw_sys::pw_memblock_flags::PW_MEMBLOCK_FLAG_DONT_CLOSE.0;
+        /// The peer is not notified of the memory
+        const DONT_NOTIFY = pw_sys::pw_memblock_flags::PW_MEMBLOCK_FLAG_DONT_NOTIFY.0;
+        const READWRITE = Self::READABLE.bits() | Self::WRITABLE.bits();
+    }
+}
+
+/// A memory block shared by the server, with its own duplicate of the fd.
+#[derive(Debug)]
+pub struct MemBlock {
+    id: u32,
+    type_: DataType,
+    flags: MemFlags,
+    fd: OwnedFd,
+}
+
+impl MemBlock {
+    /// The id buffers use to refer to the block.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The type of the memory, such as [`DataType::MemFd`] or [`DataType::DmaBuf`].
+    pub fn type_(&self) -> DataType {
+        self.type_
+    }
+
+    pub fn flags(&self) -> MemFlags {
+        self.flags
+    }
+
+    /// The fd of the memory, to be mapped with `mmap`.
+    pub fn fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+/// The memory blocks shared by the server, by id.
+#[derive(Debug, Default)]
+pub struct MemBlocks {
+    blocks: HashMap<u32, MemBlock>,
+}
+
+impl MemBlocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track the blocks shared on `core`, for as long as the returned listener is alive.
+    ///
+    /// Blocks whose fd cannot be duplicated are skipped.
+    pub fn track(core: &CoreRef) -> (Rc<RefCell<Self>>, Listener) {
+        let blocks = Rc::new(RefCell::new(Self::new()));
+        let blocks_add = blocks.clone();
+        let blocks_remove = blocks.clone();
+
+        let listener = core
+            .add_listener_local()
+            .add_mem(move |id, type_, fd, flags| {
+                // a failure is noticed when resolving data referring to the block
+                let _ = blocks_add.borrow_mut().insert(id, type_, fd, flags);
+            })
+            .remove_mem(move |id| {
+                blocks_remove.borrow_mut().remove(id);
+            })
+            .register();
+
+        (blocks, listener)
+    }
+
+    /// Add a block, duplicating `fd` as it is only valid during the `add_mem` event.
+    ///
+    /// A previous block with the same id is replaced.
+    pub fn insert(
+        &mut self,
+        id: u32,
+        type_: DataType,
+        fd: BorrowedFd,
+        flags: MemFlags,
+    ) -> io::Result<()> {
+        let fd = fd.try_clone_to_owned()?;
+        self.blocks.insert(
+            id,
+            MemBlock {
+                id,
+                type_,
+                flags,
+                fd,
+            },
+        );
+        Ok(())
+    }
+
+    /// Remove a block, closing its fd when it is dropped.
+    pub fn remove(&mut self, id: u32) -> Option<MemBlock> {
+        self.blocks.remove(&id)
+    }
+
+    pub fn get(&self, id: u32) -> Option<&MemBlock> {
+        self.blocks.get(&id)
+    }
+
+    /// The block `data` refers to, if it is of type [`DataType::MemId`].
+    pub fn resolve(&self, data: &Data) -> Option<&MemBlock> {
+        if data.type_() != DataType::MemId {
+            return None;
+        }
+        // the id of the block is stored in the fd field
+        u32::try_from(data.as_raw().fd)
+            .ok()
+            .and_then(|id| self.get(id))
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
+
+/// Memory mapped with `mmap`, unmapped when dropped.
+#[derive(Debug)]
+pub struct MappedMem {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+impl MappedMem {
+    /// Map `len` bytes of `fd` for reading and writing, starting at `offset`.
+    pub fn map(fd: BorrowedFd, offset: usize, len: usize) -> io::Result<Self> {
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd.as_raw_fd(),
+                offset as libc::off_t,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            ptr: NonNull::new(ptr.cast()).expect("mmap returned NULL"),
+            len,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for MappedMem {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr.as_ptr().cast(), self.len) };
+    }
+}
+
+/// Allocate `size` bytes of shared memory with `memfd_create`, and map it.
+///
+/// The memory is sealed against resizing, so that it can safely be mapped by the peers.
+pub fn alloc_memfd(size: usize) -> io::Result<(OwnedFd, MappedMem)> {
+    let fd = unsafe {
+        libc::memfd_create(
+            c"pipewire-rs-memfd".as_ptr(),
+            libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    if unsafe { libc::ftruncate(fd.as_raw_fd(), size as libc::off_t) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let seals = libc::F_SEAL_GROW | libc::F_SEAL_SHRINK | libc::F_SEAL_SEAL;
+    if unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_ADD_SEALS, seals) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mem = MappedMem::map(fd.as_fd(), 0, size)?;
+    Ok((fd, mem))
+}
+
+/// Error returned by [`attach_memfd`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AttachError {
+    /// The stream does not accept [`DataType::MemFd`] data.
+    #[error("MemFd is not an allowed data type, allowed types are {0:?}")]
+    TypeNotAllowed(DataTypes),
+    /// The memory is smaller than the buffer size negotiated with the `Buffers` param.
+    #[error(
+        "memory of {size} bytes is smaller than the negotiated buffer size of {maxsize} bytes"
+    )]
+    TooSmall { size: usize, maxsize: u32 },
+}
+
+/// Use `mem`, mapped from `fd`, as the memory of `data` of a buffer, in the `add_buffer`
+/// callback of a stream connected with [`StreamFlags::ALLOC_BUFFERS`](crate::stream::StreamFlags::ALLOC_BUFFERS).
+///
+/// `fd` and `mem` have to be kept until the buffer is removed, in the `remove_buffer` callback.
+///
+/// ```no_run
+/// use pipewire::{mem, stream::Stream};
+/// use std::os::fd::AsFd;
+///
+/// # fn f(stream: &Stream) {
+/// let _listener = stream
+///     .add_local_listener_with_user_data(Vec::new())
+///     .add_buffer(|_, memory, buffer| {
+///         let data = &mut buffer.datas_mut()[0];
+///         let (fd, mut mapped) = mem::alloc_memfd(data.maxsize() as usize).unwrap();
+///         mem::attach_memfd(data, fd.as_fd(), &mut mapped).unwrap();
+///         memory.push((buffer.as_raw_ptr(), fd, mapped));
+///     })
+///     .remove_buffer(|_, memory, buffer| {
+///         memory.retain(|(ptr, _, _)| *ptr != buffer.as_raw_ptr());
+///     })
+///     .register();
+/// # }
+/// ```
+pub fn attach_memfd(
+    data: &mut Data,
+    fd: BorrowedFd,
+    mem: &mut MappedMem,
+) -> Result<(), AttachError> {
+    let allowed = data.allowed_types();
+    if !allowed.allows(DataType::MemFd) {
+        return Err(AttachError::TypeNotAllowed(allowed));
+    }
+    if mem.len() < data.maxsize() as usize {
+        return Err(AttachError::TooSmall {
+            size: mem.len(),
+            maxsize: data.maxsize(),
+        });
+    }
+
+    data.set_type(DataType::MemFd);
+    data.set_flags(DataFlags::READWRITE);
+    data.set_fd(fd.as_raw_fd().into());
+    data.set_mapoffset(0);
+    data.set_maxsize(u32::try_from(mem.len()).unwrap_or(u32::MAX));
+    unsafe { data.set_data(mem.as_ptr().cast()) };
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::fd::AsRawFd;
+
+    fn data(type_: DataType, fd: i64) -> Data {
+        let mut raw: spa_sys::spa_data = unsafe { std::mem::zeroed() };
+        raw.type_ = type_.as_raw();
+        raw.fd = fd;
+        // SAFETY: Data is a transparent wrapper of spa_data
+        unsafe { std::mem::transmute(raw) }
+    }
+
+    #[test]
+    fn insert_resolve_remove() {
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let mut blocks = MemBlocks::new();
+        assert!(blocks.is_empty());
+
+        blocks
+            .insert(3, DataType::MemFd, file.as_fd(), MemFlags::READWRITE)
+            .unwrap();
+        // the block keeps its own fd
+        drop(file);
+        assert_eq!(blocks.len(), 1);
+
+        let block = blocks.resolve(&data(DataType::MemId, 3)).unwrap();
+        assert_eq!(block.id(), 3);
+        assert_eq!(block.type_(), DataType::MemFd);
+        assert_eq!(block.flags(), MemFlags::READWRITE);
+        assert!(block.fd().as_raw_fd() >= 0);
+
+        assert!(blocks.resolve(&data(DataType::MemId, 4)).is_none());
+        assert!(blocks.resolve(&data(DataType::MemId, -1)).is_none());
+        assert!(blocks.resolve(&data(DataType::MemFd, 3)).is_none());
+
+        assert_eq!(blocks.remove(3).map(|block| block.id()), Some(3));
+        assert!(blocks.resolve(&data(DataType::MemId, 3)).is_none());
+        assert!(blocks.is_empty());
+    }
+
+    #[test]
+    fn alloc_and_attach() {
+        let (fd, mut mem) = alloc_memfd(4096).unwrap();
+        assert_eq!(mem.len(), 4096);
+        mem.as_mut_slice()[..4].copy_from_slice(b"test");
+
+        // the memory is shared through the fd
+        let other = MappedMem::map(fd.as_fd(), 0, 4096).unwrap();
+        assert_eq!(&other.as_slice()[..4], b"test");
+
+        let mut data = data(DataType::from_raw(DataTypes::MEM_PTR.bits()), -1);
+        data.set_maxsize(4096);
+        unsafe { data.set_data(std::ptr::null_mut()) };
+        assert_eq!(
+            attach_memfd(&mut data, fd.as_fd(), &mut mem),
+            Err(AttachError::TypeNotAllowed(DataTypes::MEM_PTR))
+        );
+
+        data.set_type(DataType::from_raw(
+            (DataTypes::MEM_FD | DataTypes::DMA_BUF).bits(),
+        ));
+        data.set_maxsize(8192);
+        assert_eq!(
+            attach_memfd(&mut data, fd.as_fd(), &mut mem),
+            Err(AttachError::TooSmall {
+                size: 4096,
+                maxsize: 8192
+            })
+        );
+
+        data.set_maxsize(1024);
+        attach_memfd(&mut data, fd.as_fd(), &mut mem).unwrap();
+        assert_eq!(data.type_(), DataType::MemFd);
+        assert_eq!(data.fd(), i64::from(fd.as_raw_fd()));
+        assert_eq!(data.maxsize(), 4096);
+        assert_eq!(data.data().map(|d| &d[..4]), Some(&b"test"[..]));
+    }
+}
@@ -1,22 +1,34 @@
 // Copyright The pipewire-rs Contributors.
 // SPDX-License-Identifier: MIT
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi::CString;
 use std::os::raw::c_char;
+use std::rc::Rc;
 use std::{
     ffi::{c_void, CStr},
-    mem,
+    fmt, mem,
     pin::Pin,
     ptr,
 };
 
 use crate::{
-    proxy::{Listener, Proxy, ProxyT},
+    core::CoreRef,
+    proxy::{impl_listener, impl_transparent_proxy, Proxy, ProxyT},
+    registry::{GlobalObject, Registry},
+    thread_loop::LoopHandle,
     types::ObjectType,
+    Error,
+};
+use spa::{
+    json::JsonValue,
+    spa_interface_call_method,
+    utils::{dict::DictRef, result::SpaResult},
 };
-use spa::spa_interface_call_method;
 
 #[derive(Debug)]
+#[repr(transparent)]
 pub struct Metadata {
     proxy: Proxy,
 }
@@ -42,6 +54,8 @@ impl ProxyT for Metadata {
     }
 }
 
+impl_transparent_proxy!(Metadata);
+
 impl Metadata {
     pub fn add_listener_local(&self) -> MetadataListenerLocalBuilder {
         MetadataListenerLocalBuilder {
@@ -50,7 +64,13 @@ impl Metadata {
         }
     }
 
-    pub fn set_property(&self, subject: u32, key: &str, type_: Option<&str>, value: Option<&str>) {
+    pub fn set_property(
+        &self,
+        subject: u32,
+        key: &str,
+        type_: Option<&str>,
+        value: Option<&str>,
+    ) -> Result<(), Error> {
         // Keep CStrings allocated here in order for pointers to remain valid.
         let key = CString::new(key).expect("Invalid byte in metadata key");
         let type_ = type_.map(|t| CString::new(t).expect("Invalid byte in metadata type"));
@@ -66,28 +86,34 @@ impl Metadata {
         key: &CStr,
         type_: Option<&CStr>,
         value: Option<&CStr>,
-    ) {
-        unsafe {
+    ) -> Result<(), Error> {
+        let res = unsafe {
             spa::spa_interface_call_method!(
-                self.proxy.as_ptr(),
+                self.proxy.checked_ptr()?,
                 pw_sys::pw_metadata_methods,
                 set_property,
                 subject,
                 key.as_ptr() as *const _,
                 type_.map_or_else(ptr::null, CStr::as_ptr) as *const _,
                 value.map_or_else(ptr::null, CStr::as_ptr) as *const _
-            );
-        }
+            )
+        };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
     }
 
-    pub fn clear(&self) {
-        unsafe {
+    pub fn clear(&self) -> Result<(), Error> {
+        let res = unsafe {
             spa::spa_interface_call_method!(
-                self.proxy.as_ptr(),
+                self.proxy.checked_ptr()?,
                 pw_sys::pw_metadata_methods,
                 clear,
-            );
-        }
+            )
+        };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
     }
 }
 
@@ -97,13 +123,15 @@ pub struct MetadataListener {
     events: Pin<Box<pw_sys::pw_metadata_events>>,
     listener: Pin<Box<spa_sys::spa_hook>>,
     #[allow(dead_code)]
-    data: Box<ListenerLocalCallbacks>,
+    data: Box<crate::utils::Guarded<ListenerLocalCallbacks>>,
+    loop_: LoopHandle,
 }
 
-impl Listener for MetadataListener {}
+impl_listener!(MetadataListener);
 
 impl Drop for MetadataListener {
     fn drop(&mut self) {
+        let _lock = self.loop_.lock();
         spa::utils::hook::remove(*self.listener);
     }
 }
@@ -111,9 +139,14 @@ impl Drop for MetadataListener {
 #[derive(Default)]
 struct ListenerLocalCallbacks {
     #[allow(clippy::type_complexity)]
-    property: Option<Box<dyn Fn(u32, Option<&str>, Option<&str>, Option<&str>) -> i32>>,
+    property: Option<Box<dyn FnMut(u32, Option<&str>, Option<&str>, Option<&str>) -> i32>>,
 }
 
+/// Builder of a listener for the events of a [`Metadata`], see [`Metadata::add_listener_local`].
+///
+/// **Events emitted while one of the callbacks is running are lost.** The callbacks are never
+/// called re-entrantly: an event received when a callback iterates the loop, e.g. to wait for a
+/// reply, or emitted synchronously by a method it calls, is dropped without notice.
 #[must_use]
 pub struct MetadataListenerLocalBuilder<'meta> {
     metadata: &'meta Metadata,
@@ -129,7 +162,7 @@ impl<'meta> MetadataListenerLocalBuilder<'meta> {
     /// `None` for `key` means removal of all properties.
     pub fn property<F>(mut self, property: F) -> Self
     where
-        F: Fn(u32, Option<&str>, Option<&str>, Option<&str>) -> i32 + 'static,
+        F: FnMut(u32, Option<&str>, Option<&str>, Option<&str>) -> i32 + 'static,
     {
         self.cbs.property = Some(Box::new(property));
         self
@@ -144,28 +177,34 @@ impl<'meta> MetadataListenerLocalBuilder<'meta> {
             type_: *const c_char,
             value: *const c_char,
         ) -> i32 {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            let key = if !key.is_null() {
-                Some(CStr::from_ptr(key).to_string_lossy())
-            } else {
-                None
-            };
-            let type_ = if !type_.is_null() {
-                Some(CStr::from_ptr(type_).to_string_lossy())
-            } else {
-                None
-            };
-            let value = if !value.is_null() {
-                Some(CStr::from_ptr(value).to_string_lossy())
-            } else {
-                None
-            };
-            callbacks.property.as_ref().unwrap()(
-                subject,
-                key.as_deref(),
-                type_.as_deref(),
-                value.as_deref(),
-            )
+            crate::utils::catch_callback_panic("metadata property", 0, || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return 0;
+                };
+                let key = if !key.is_null() {
+                    Some(CStr::from_ptr(key).to_string_lossy())
+                } else {
+                    None
+                };
+                let type_ = if !type_.is_null() {
+                    Some(CStr::from_ptr(type_).to_string_lossy())
+                } else {
+                    None
+                };
+                let value = if !value.is_null() {
+                    Some(CStr::from_ptr(value).to_string_lossy())
+                } else {
+                    None
+                };
+                callbacks.property.as_mut().unwrap()(
+                    subject,
+                    key.as_deref(),
+                    type_.as_deref(),
+                    value.as_deref(),
+                )
+            })
         }
 
         let e = unsafe {
@@ -182,18 +221,21 @@ impl<'meta> MetadataListenerLocalBuilder<'meta> {
         let (listener, data) = unsafe {
             let metadata = &self.metadata.proxy.as_ptr();
 
-            let data = Box::into_raw(Box::new(self.cbs));
+            let data = Box::into_raw(Box::new(crate::utils::Guarded::new(self.cbs)));
             let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
             let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
 
-            spa_interface_call_method!(
-                metadata,
-                pw_sys::pw_metadata_methods,
-                add_listener,
-                listener_ptr.cast(),
-                e.as_ref().get_ref(),
-                data as *mut _
-            );
+            if self.metadata.proxy.is_connected() {
+                spa_interface_call_method!(
+                    metadata,
+                    pw_sys::pw_metadata_methods,
+                    add_listener,
+                    listener_ptr.cast(),
+                    e.as_ref().get_ref(),
+                    data as *mut _
+                );
+                spa::utils::hook::reset_on_remove(&mut *listener_ptr);
+            }
 
             (listener, Box::from_raw(data))
         };
@@ -202,6 +244,390 @@ impl<'meta> MetadataListenerLocalBuilder<'meta> {
             events: e,
             listener,
             data,
+            loop_: self.metadata.proxy.loop_handle(),
+        }
+    }
+}
+
+/// The `metadata.name` of the metadata object holding the default nodes.
+const DEFAULT_METADATA_NAME: &str = "default";
+
+/// A default node tracked by a [`DefaultsTracker`], named after its key in the `default` metadata.
+///
+/// The session manager sets the default nodes it currently uses, while the configured ones are the
+/// nodes chosen by the user, which may be unavailable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DefaultKind {
+    /// `default.audio.sink`
+    AudioSink,
+    /// `default.audio.source`
+    AudioSource,
+    /// `default.video.source`
+    VideoSource,
+    /// `default.configured.audio.sink`
+    ConfiguredAudioSink,
+    /// `default.configured.audio.source`
+    ConfiguredAudioSource,
+    /// `default.configured.video.source`
+    ConfiguredVideoSource,
+}
+
+impl DefaultKind {
+    pub const ALL: [DefaultKind; 6] = [
+        DefaultKind::AudioSink,
+        DefaultKind::AudioSource,
+        DefaultKind::VideoSource,
+        DefaultKind::ConfiguredAudioSink,
+        DefaultKind::ConfiguredAudioSource,
+        DefaultKind::ConfiguredVideoSource,
+    ];
+
+    /// The key of the default in the `default` metadata.
+    pub fn key(&self) -> &'static str {
+        match self {
+            DefaultKind::AudioSink => "default.audio.sink",
+            DefaultKind::AudioSource => "default.audio.source",
+            DefaultKind::VideoSource => "default.video.source",
+            DefaultKind::ConfiguredAudioSink => "default.configured.audio.sink",
+            DefaultKind::ConfiguredAudioSource => "default.configured.audio.source",
+            DefaultKind::ConfiguredVideoSource => "default.configured.video.source",
         }
     }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.key() == key)
+    }
+}
+
+/// A default node, as set in the `default` metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefaultTarget {
+    /// The `node.name` of the node.
+    pub name: String,
+    /// The serial of the node with this name, or `None` if there is no such node.
+    pub node_serial: Option<u64>,
+}
+
+/// Parse the node name from a value of the `default` metadata, such as
+/// `{ "name": "alsa_output.pci-0000_00_1f.3.analog-stereo" }`.
+fn parse_default_name(value: &str) -> Option<String> {
+    let value = JsonValue::parse(value).ok()?;
+    value.get("name")?.as_str().map(str::to_owned)
+}
+
+/// Tracks the default nodes set in the `default` metadata.
+///
+/// The metadata object is bound once it appears in the registry, and bound again if it is
+/// removed and announced again, such as when the session manager restarts.
+/// The names of the defaults are resolved to the nodes of the registry, and the callback is called
+/// with the kind of default and its new target, or `None` when it is unset, whenever one changes.
+///
+/// ```no_run
+/// use pipewire::{context::Context, main_loop::MainLoop, metadata::{DefaultKind, DefaultsTracker}};
+///
+/// let mainloop = MainLoop::new(None)?;
+/// let context = Context::new(&mainloop)?;
+/// let core = context.connect(None)?;
+///
+/// let _tracker = DefaultsTracker::new(&core, |kind, target| {
+///     if kind == DefaultKind::AudioSink {
+///         println!("default sink: {:?}", target.map(|target| &target.name));
+///     }
+/// })?;
+///
+/// mainloop.run();
+/// # Ok::<(), pipewire::Error>(())
+/// ```
+pub struct DefaultsTracker {
+    // removed before the registry is destroyed with the tracker state
+    _listener: crate::registry::Listener,
+    inner: Rc<TrackerInner>,
+}
+
+struct TrackerInner {
+    registry: Registry,
+    metadata: RefCell<Option<BoundMetadata>>,
+    names: RefCell<HashMap<DefaultKind, String>>,
+    nodes: RefCell<HashMap<u32, (String, Option<u64>)>>,
+    targets: RefCell<HashMap<DefaultKind, DefaultTarget>>,
+    #[allow(clippy::type_complexity)]
+    callback: RefCell<Box<dyn FnMut(DefaultKind, Option<&DefaultTarget>)>>,
+}
+
+struct BoundMetadata {
+    id: u32,
+    _listener: MetadataListener,
+    _metadata: Metadata,
+}
+
+impl DefaultsTracker {
+    /// Start tracking the defaults with a registry of `core`, calling `callback` when they change.
+    pub fn new<F>(core: &CoreRef, callback: F) -> Result<Self, Error>
+    where
+        F: FnMut(DefaultKind, Option<&DefaultTarget>) + 'static,
+    {
+        let inner = Rc::new(TrackerInner {
+            registry: core.get_registry()?,
+            metadata: RefCell::new(None),
+            names: RefCell::new(HashMap::new()),
+            nodes: RefCell::new(HashMap::new()),
+            targets: RefCell::new(HashMap::new()),
+            callback: RefCell::new(Box::new(callback)),
+        });
+
+        let listener = inner
+            .registry
+            .add_listener_local()
+            .global({
+                let inner = Rc::downgrade(&inner);
+                move |global| {
+                    if let Some(inner) = inner.upgrade() {
+                        inner.global_added(global);
+                    }
+                }
+            })
+            .global_remove({
+                let inner = Rc::downgrade(&inner);
+                move |id| {
+                    if let Some(inner) = inner.upgrade() {
+                        inner.global_removed(id);
+                    }
+                }
+            })
+            .register();
+
+        Ok(Self {
+            _listener: listener,
+            inner,
+        })
+    }
+
+    /// The current target of the default `kind`, if it is set.
+    pub fn get(&self, kind: DefaultKind) -> Option<DefaultTarget> {
+        self.inner.targets.borrow().get(&kind).cloned()
+    }
+}
+
+impl fmt::Debug for DefaultsTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DefaultsTracker")
+            .field("targets", &self.inner.targets.borrow())
+            .finish_non_exhaustive()
+    }
+}
+
+impl TrackerInner {
+    fn global_added(self: &Rc<Self>, global: &GlobalObject<&DictRef>) {
+        let Some(props) = global.props else {
+            return;
+        };
+
+        match global.type_ {
+            ObjectType::Metadata => {
+                if props.get("metadata.name") == Some(DEFAULT_METADATA_NAME)
+                    && self.metadata.borrow().is_none()
+                {
+                    // failing to bind is like the metadata not being there
+                    let _ = self.bind_metadata(global);
+                }
+            }
+            ObjectType::Node => {
+                if let Some(name) = props.get(*crate::keys::NODE_NAME) {
+                    self.nodes
+                        .borrow_mut()
+                        .insert(global.id, (name.to_owned(), global.serial()));
+                    self.update();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn global_removed(&self, id: u32) {
+        let is_metadata = matches!(&*self.metadata.borrow(), Some(metadata) if metadata.id == id);
+        if is_metadata {
+            let metadata = self.metadata.borrow_mut().take();
+            drop(metadata);
+            self.names.borrow_mut().clear();
+            self.update();
+        } else if self.nodes.borrow_mut().remove(&id).is_some() {
+            self.update();
+        }
+    }
+
+    fn bind_metadata(self: &Rc<Self>, global: &GlobalObject<&DictRef>) -> Result<(), Error> {
+        let metadata: Metadata = self.registry.bind(global)?;
+        let listener = metadata
+            .add_listener_local()
+            .property({
+                let inner = Rc::downgrade(self);
+                move |subject, key, _, value| {
+                    if let Some(inner) = inner.upgrade() {
+                        if subject == pw_sys::PW_ID_CORE {
+                            inner.property_changed(key, value);
+                        }
+                    }
+                    0
+                }
+            })
+            .register();
+
+        *self.metadata.borrow_mut() = Some(BoundMetadata {
+            id: global.id,
+            _listener: listener,
+            _metadata: metadata,
+        });
+        Ok(())
+    }
+
+    fn property_changed(&self, key: Option<&str>, value: Option<&str>) {
+        match key {
+            // all the properties were removed
+            None => self.names.borrow_mut().clear(),
+            Some(key) => {
+                let Some(kind) = DefaultKind::from_key(key) else {
+                    return;
+                };
+                match value.and_then(parse_default_name) {
+                    Some(name) => self.names.borrow_mut().insert(kind, name),
+                    None => self.names.borrow_mut().remove(&kind),
+                };
+            }
+        }
+        self.update();
+    }
+
+    /// Resolve the names of the defaults to nodes, and report the targets which changed.
+    fn update(&self) {
+        let mut changed = Vec::new();
+        {
+            let names = self.names.borrow();
+            let nodes = self.nodes.borrow();
+            let mut targets = self.targets.borrow_mut();
+
+            for kind in DefaultKind::ALL {
+                let target = names.get(&kind).map(|name| DefaultTarget {
+                    name: name.clone(),
+                    node_serial: nodes
+                        .values()
+                        .find(|(node_name, _)| node_name == name)
+                        .and_then(|&(_, serial)| serial),
+                });
+                if targets.get(&kind) == target.as_ref() {
+                    continue;
+                }
+                match &target {
+                    Some(target) => targets.insert(kind, target.clone()),
+                    None => targets.remove(&kind),
+                };
+                changed.push((kind, target));
+            }
+        }
+
+        let mut callback = self.callback.borrow_mut();
+        for (kind, target) in changed {
+            callback(kind, target.as_ref());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{context::Context, main_loop::MainLoop, properties::properties};
+
+    #[test]
+    fn default_keys() {
+        for kind in DefaultKind::ALL {
+            assert_eq!(DefaultKind::from_key(kind.key()), Some(kind));
+        }
+        assert_eq!(DefaultKind::from_key("default.clock.rate"), None);
+    }
+
+    #[test]
+    fn default_name() {
+        assert_eq!(
+            parse_default_name(r#"{ "name": "alsa_output.pci-0000_00_1f.3.analog-stereo" }"#),
+            Some("alsa_output.pci-0000_00_1f.3.analog-stereo".to_owned())
+        );
+        // the relaxed syntax of SPA is accepted too
+        assert_eq!(
+            parse_default_name("{ name = my-sink }"),
+            Some("my-sink".to_owned())
+        );
+        assert_eq!(parse_default_name(r#"{ "name": 1 }"#), None);
+        assert_eq!(parse_default_name("my-sink"), None);
+    }
+
+    #[test]
+    fn defaults_tracker() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let _metadata_module = context
+            .load_module("libpipewire-module-metadata", None, None)
+            .unwrap();
+        let _adapter = context
+            .load_module("libpipewire-module-adapter", None, None)
+            .unwrap();
+        let core = context.connect_self(None).unwrap();
+
+        let updates = Rc::new(RefCell::new(Vec::new()));
+        let tracker = DefaultsTracker::new(&core, {
+            let updates = updates.clone();
+            move |kind, target| updates.borrow_mut().push((kind, target.cloned()))
+        })
+        .unwrap();
+
+        let _sink: crate::node::Node = core
+            .create_object(
+                "adapter",
+                &properties! {
+                    "factory.name" => "support.null-audio-sink",
+                    "node.name" => "pipewire-rs.test.default-sink",
+                    "media.class" => "Audio/Sink",
+                    "object.linger" => "false",
+                },
+            )
+            .unwrap();
+        let metadata: Metadata = core
+            .create_object(
+                "metadata",
+                &properties! {
+                    "metadata.name" => DEFAULT_METADATA_NAME,
+                },
+            )
+            .unwrap();
+        metadata
+            .set_property(
+                pw_sys::PW_ID_CORE,
+                DefaultKind::AudioSink.key(),
+                Some("Spa:String:JSON"),
+                Some(r#"{ "name": "pipewire-rs.test.default-sink" }"#),
+            )
+            .unwrap();
+
+        mainloop.run_until(|_| {
+            matches!(
+                tracker.get(DefaultKind::AudioSink),
+                Some(DefaultTarget {
+                    node_serial: Some(_),
+                    ..
+                })
+            )
+        });
+        let target = tracker.get(DefaultKind::AudioSink).unwrap();
+        assert_eq!(target.name, "pipewire-rs.test.default-sink");
+        assert_eq!(
+            updates.borrow().last(),
+            Some(&(DefaultKind::AudioSink, Some(target)))
+        );
+
+        // the default is unset when the metadata goes away
+        drop(metadata);
+        mainloop.run_until(|_| tracker.get(DefaultKind::AudioSink).is_none());
+        assert_eq!(
+            updates.borrow().last(),
+            Some(&(DefaultKind::AudioSink, None))
+        );
+    }
 }
@@ -5,16 +5,19 @@ use bitflags::bitflags;
 use libc::c_void;
 use std::ops::Deref;
 use std::pin::Pin;
-use std::{ffi::CStr, ptr};
+use std::ptr;
 use std::{fmt, mem};
 
 use crate::{
-    proxy::{Listener, Proxy, ProxyT},
+    proxy::{impl_listener, impl_transparent_proxy, Proxy, ProxyT},
+    thread_loop::LoopHandle,
     types::ObjectType,
+    utils::cstr_to_str,
 };
 use spa::spa_interface_call_method;
 
 #[derive(Debug)]
+#[repr(transparent)]
 pub struct Module {
     proxy: Proxy,
 }
@@ -40,6 +43,8 @@ impl ProxyT for Module {
     }
 }
 
+impl_transparent_proxy!(Module);
+
 impl Module {
     // TODO: add non-local version when we'll bind pw_thread_loop_start()
     #[must_use]
@@ -54,9 +59,14 @@ impl Module {
 #[derive(Default)]
 struct ListenerLocalCallbacks {
     #[allow(clippy::type_complexity)]
-    info: Option<Box<dyn Fn(&ModuleInfoRef)>>,
+    info: Option<Box<dyn FnMut(&ModuleInfoRef)>>,
 }
 
+/// Builder of a listener for the events of a [`Module`], see [`Module::add_listener_local`].
+///
+/// **Events emitted while one of the callbacks is running are lost.** The callbacks are never
+/// called re-entrantly: an event received when a callback iterates the loop, e.g. to wait for a
+/// reply, or emitted synchronously by a method it calls, is dropped without notice.
 pub struct ModuleListenerLocalBuilder<'a> {
     module: &'a Module,
     cbs: ListenerLocalCallbacks,
@@ -78,25 +88,20 @@ impl ModuleInfoRef {
         self.0.id
     }
 
-    pub fn name(&self) -> &str {
-        unsafe { CStr::from_ptr(self.0.name).to_str().unwrap() }
+    pub fn name(&self) -> Option<&str> {
+        unsafe { cstr_to_str(self.0.name) }
     }
 
-    pub fn filename(&self) -> &str {
-        unsafe { CStr::from_ptr(self.0.name).to_str().unwrap() }
+    pub fn filename(&self) -> Option<&str> {
+        unsafe { cstr_to_str(self.0.filename) }
     }
 
     pub fn args(&self) -> Option<&str> {
-        let args = self.0.args;
-        if args.is_null() {
-            None
-        } else {
-            Some(unsafe { CStr::from_ptr(args).to_str().unwrap() })
-        }
+        unsafe { cstr_to_str(self.0.args) }
     }
 
     pub fn change_mask(&self) -> ModuleChangeMask {
-        ModuleChangeMask::from_bits(self.0.change_mask).expect("invalid change_mask")
+        ModuleChangeMask::from_bits_retain(self.0.change_mask)
     }
 
     pub fn props(&self) -> Option<&spa::utils::dict::DictRef> {
@@ -109,9 +114,10 @@ impl fmt::Debug for ModuleInfoRef {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ModuleInfoRef")
             .field("id", &self.id())
+            .field("name", &self.name())
             .field("filename", &self.filename())
             .field("args", &self.args())
-            .field("change_mask", &self.change_mask())
+            .field("change-mask", &self.change_mask())
             .field("props", &self.props())
             .finish()
     }
@@ -168,9 +174,10 @@ impl fmt::Debug for ModuleInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ModuleInfo")
             .field("id", &self.id())
+            .field("name", &self.name())
             .field("filename", &self.filename())
             .field("args", &self.args())
-            .field("change_mask", &self.change_mask())
+            .field("change-mask", &self.change_mask())
             .field("props", &self.props())
             .finish()
     }
@@ -182,13 +189,15 @@ pub struct ModuleListener {
     events: Pin<Box<pw_sys::pw_module_events>>,
     listener: Pin<Box<spa_sys::spa_hook>>,
     #[allow(dead_code)]
-    data: Box<ListenerLocalCallbacks>,
+    data: Box<crate::utils::Guarded<ListenerLocalCallbacks>>,
+    loop_: LoopHandle,
 }
 
-impl Listener for ModuleListener {}
+impl_listener!(ModuleListener);
 
 impl Drop for ModuleListener {
     fn drop(&mut self) {
+        let _lock = self.loop_.lock();
         spa::utils::hook::remove(*self.listener);
     }
 }
@@ -197,7 +206,7 @@ impl<'a> ModuleListenerLocalBuilder<'a> {
     #[must_use]
     pub fn info<F>(mut self, info: F) -> Self
     where
-        F: Fn(&ModuleInfoRef) + 'static,
+        F: FnMut(&ModuleInfoRef) + 'static,
     {
         self.cbs.info = Some(Box::new(info));
         self
@@ -209,11 +218,17 @@ impl<'a> ModuleListenerLocalBuilder<'a> {
             data: *mut c_void,
             info: *const pw_sys::pw_module_info,
         ) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            let info =
-                ptr::NonNull::new(info as *mut pw_sys::pw_module_info).expect("info is NULL");
-            let info = info.cast::<ModuleInfoRef>().as_ref();
-            callbacks.info.as_ref().unwrap()(info);
+            crate::utils::catch_callback_panic("module info", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                let info =
+                    ptr::NonNull::new(info as *mut pw_sys::pw_module_info).expect("info is NULL");
+                let info = info.cast::<ModuleInfoRef>().as_ref();
+                callbacks.info.as_mut().unwrap()(info);
+            })
         }
 
         let e = unsafe {
@@ -230,18 +245,21 @@ impl<'a> ModuleListenerLocalBuilder<'a> {
         let (listener, data) = unsafe {
             let module = &self.module.proxy.as_ptr();
 
-            let data = Box::into_raw(Box::new(self.cbs));
+            let data = Box::into_raw(Box::new(crate::utils::Guarded::new(self.cbs)));
             let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
             let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
 
-            spa_interface_call_method!(
-                module,
-                pw_sys::pw_module_methods,
-                add_listener,
-                listener_ptr.cast(),
-                e.as_ref().get_ref(),
-                data as *mut _
-            );
+            if self.module.proxy.is_connected() {
+                spa_interface_call_method!(
+                    module,
+                    pw_sys::pw_module_methods,
+                    add_listener,
+                    listener_ptr.cast(),
+                    e.as_ref().get_ref(),
+                    data as *mut _
+                );
+                spa::utils::hook::reset_on_remove(&mut *listener_ptr);
+            }
 
             (listener, Box::from_raw(data))
         };
@@ -250,6 +268,7 @@ impl<'a> ModuleListenerLocalBuilder<'a> {
             events: e,
             listener,
             data,
+            loop_: self.module.proxy.loop_handle(),
         }
     }
 }
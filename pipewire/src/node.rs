@@ -3,18 +3,32 @@
 
 use bitflags::bitflags;
 use libc::c_void;
+use std::cell::{Cell, RefCell};
 use std::ops::Deref;
 use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicI32, Ordering};
 use std::{ffi::CStr, ptr};
 use std::{fmt, mem};
 
 use crate::{
-    proxy::{Listener, Proxy, ProxyT},
+    core::CoreRef,
+    main_loop::MainLoop,
+    proxy::{impl_listener, impl_transparent_proxy, Proxy, ProxyT},
+    thread_loop::LoopHandle,
     types::ObjectType,
+    Error,
+};
+use nix::errno::Errno;
+use spa::{
+    param::{props::Props, ParamInfoFlags, ParamType},
+    pod::{OwnedPod, Pod},
+    spa_interface_call_method,
+    utils::result::{AsyncSeq, SpaResult},
 };
-use spa::{pod::Pod, spa_interface_call_method};
 
 #[derive(Debug)]
+#[repr(transparent)]
 pub struct Node {
     proxy: Proxy,
 }
@@ -32,17 +46,19 @@ impl Node {
     /// Subscribe to parameter changes
     ///
     /// Automatically emit `param` events for the given ids when they are changed
-    // FIXME: Return result?
-    pub fn subscribe_params(&self, ids: &[spa::param::ParamType]) {
-        unsafe {
+    pub fn subscribe_params(&self, ids: &[spa::param::ParamType]) -> Result<(), Error> {
+        let res = unsafe {
             spa_interface_call_method!(
-                self.proxy.as_ptr(),
+                self.proxy.checked_ptr()?,
                 pw_sys::pw_node_methods,
                 subscribe_params,
                 ids.as_ptr() as *mut _,
                 ids.len().try_into().unwrap()
-            );
-        }
+            )
+        };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
     }
 
     /// Enumerate node parameters
@@ -54,37 +70,89 @@ impl Node {
     /// `seq`: a sequence number to place in the reply \
     /// `id`: the parameter id to enum, or [`None`] to allow any id \
     /// `start`: the start index or 0 for the first param \
-    /// `num`: the maximum number of params to retrieve ([`u32::MAX`] may be used to retrieve all params)
-    // FIXME: Add filter parameter
-    // FIXME: Return result?
-    pub fn enum_params(&self, seq: i32, id: Option<spa::param::ParamType>, start: u32, num: u32) {
+    /// `num`: the maximum number of params to retrieve ([`u32::MAX`] may be used to retrieve all params) \
+    /// `filter`: a param to filter the results with, or [`None`] to retrieve all params
+    pub fn enum_params(
+        &self,
+        seq: i32,
+        id: Option<spa::param::ParamType>,
+        start: u32,
+        num: u32,
+        filter: Option<&Pod>,
+    ) -> Result<AsyncSeq, Error> {
         let id = id.map(|id| id.as_raw()).unwrap_or(crate::constants::ID_ANY);
+        let filter = filter.map_or(ptr::null(), |filter| filter.as_raw_ptr().cast_const());
 
-        unsafe {
+        let res = unsafe {
             spa_interface_call_method!(
-                self.proxy.as_ptr(),
+                self.proxy.checked_ptr()?,
                 pw_sys::pw_node_methods,
                 enum_params,
                 seq,
                 id,
                 start,
                 num,
-                std::ptr::null()
-            );
-        }
+                filter
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
     }
 
-    pub fn set_param(&self, id: spa::param::ParamType, flags: u32, param: &Pod) {
-        unsafe {
+    /// Retrieve all the params of the node with the given `id`.
+    ///
+    /// This enumerates the params and runs `main_loop` until `core` has processed the request,
+    /// so it fails with `EDEADLK` when called from a callback of a loop, and with `ECANCELED`
+    /// if `main_loop` is quit before the params were received.
+    pub fn enum_params_sync(
+        &self,
+        core: &CoreRef,
+        main_loop: &MainLoop,
+        id: spa::param::ParamType,
+    ) -> Result<Vec<OwnedPod>, Error> {
+        static NEXT_SEQ: AtomicI32 = AtomicI32::new(1);
+        let seq = NEXT_SEQ.fetch_add(1, Ordering::Relaxed);
+
+        let params = Rc::new(RefCell::new(Vec::new()));
+        let _listener = self
+            .add_listener_local()
+            .param({
+                let params = params.clone();
+                move |param_seq, _, _, _, param| match param {
+                    Some(param) if param_seq == seq => params.borrow_mut().push(param.to_owned()),
+                    _ => {}
+                }
+            })
+            .register();
+
+        self.enum_params(seq, Some(id), 0, u32::MAX, None)?;
+
+        core.roundtrip(main_loop)?;
+
+        let params = params.take();
+        Ok(params)
+    }
+
+    pub fn set_param(
+        &self,
+        id: spa::param::ParamType,
+        flags: u32,
+        param: &Pod,
+    ) -> Result<(), Error> {
+        let res = unsafe {
             spa_interface_call_method!(
-                self.proxy.as_ptr(),
+                self.proxy.checked_ptr()?,
                 pw_sys::pw_node_methods,
                 set_param,
                 id.as_raw(),
                 flags,
                 param.as_raw_ptr()
-            );
-        }
+            )
+        };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
     }
 }
 
@@ -109,14 +177,21 @@ impl ProxyT for Node {
     }
 }
 
+impl_transparent_proxy!(Node);
+
 #[derive(Default)]
 struct ListenerLocalCallbacks {
     #[allow(clippy::type_complexity)]
-    info: Option<Box<dyn Fn(&NodeInfoRef)>>,
+    info: Option<Box<dyn FnMut(&NodeInfoRef)>>,
     #[allow(clippy::type_complexity)]
-    param: Option<Box<dyn Fn(i32, spa::param::ParamType, u32, u32, Option<&Pod>)>>,
+    param: Option<Box<dyn FnMut(i32, spa::param::ParamType, u32, u32, Option<&Pod>)>>,
 }
 
+/// Builder of a listener for the events of a [`Node`], see [`Node::add_listener_local`].
+///
+/// **Events emitted while one of the callbacks is running are lost.** The callbacks are never
+/// called re-entrantly: an event received when a callback iterates the loop, e.g. to wait for a
+/// reply, or emitted synchronously by a method it calls, is dropped without notice.
 pub struct NodeListenerLocalBuilder<'a> {
     node: &'a Node,
     cbs: ListenerLocalCallbacks,
@@ -138,6 +213,7 @@ impl NodeInfoRef {
         self.0.id
     }
 
+    /// The maximum number of input ports of the node.
     pub fn max_input_ports(&self) -> u32 {
         self.0.max_input_ports
     }
@@ -150,29 +226,40 @@ impl NodeInfoRef {
         NodeChangeMask::from_bits_retain(self.0.change_mask)
     }
 
+    /// The current number of input ports, valid when the change mask contains
+    /// [`NodeChangeMask::INPUT_PORTS`].
     pub fn n_input_ports(&self) -> u32 {
         self.0.n_input_ports
     }
 
+    /// The current number of output ports, valid when the change mask contains
+    /// [`NodeChangeMask::OUTPUT_PORTS`].
     pub fn n_output_ports(&self) -> u32 {
         self.0.n_output_ports
     }
 
+    /// The state of the node, valid when the change mask contains [`NodeChangeMask::STATE`].
+    ///
+    /// The message of [`NodeState::Error`] is empty if the server did not provide a valid one.
     pub fn state(&self) -> NodeState {
         let raw_state = self.0.state;
         match raw_state {
-            pw_sys::pw_node_state_PW_NODE_STATE_ERROR => {
-                let error = unsafe {
-                    let error = self.0.error;
-                    CStr::from_ptr(error).to_str().unwrap()
+            pw_sys::pw_node_state::PW_NODE_STATE_ERROR => {
+                let error = self.0.error;
+                let error = if error.is_null() {
+                    ""
+                } else {
+                    unsafe { CStr::from_ptr(error) }
+                        .to_str()
+                        .unwrap_or_default()
                 };
                 NodeState::Error(error)
             }
-            pw_sys::pw_node_state_PW_NODE_STATE_CREATING => NodeState::Creating,
-            pw_sys::pw_node_state_PW_NODE_STATE_SUSPENDED => NodeState::Suspended,
-            pw_sys::pw_node_state_PW_NODE_STATE_IDLE => NodeState::Idle,
-            pw_sys::pw_node_state_PW_NODE_STATE_RUNNING => NodeState::Running,
-            _ => panic!("Invalid node state: {}", raw_state),
+            pw_sys::pw_node_state::PW_NODE_STATE_CREATING => NodeState::Creating,
+            pw_sys::pw_node_state::PW_NODE_STATE_SUSPENDED => NodeState::Suspended,
+            pw_sys::pw_node_state::PW_NODE_STATE_IDLE => NodeState::Idle,
+            pw_sys::pw_node_state::PW_NODE_STATE_RUNNING => NodeState::Running,
+            _ => panic!("Invalid node state: {}", raw_state.0),
         }
     }
 
@@ -181,6 +268,14 @@ impl NodeInfoRef {
         ptr::NonNull::new(props_ptr).map(|ptr| unsafe { ptr.as_ref() })
     }
 
+    /// The `object.serial` of the node, from its properties.
+    ///
+    /// Unlike the id, the serial is never reused by another object.
+    /// This is `None` if the properties are not in the info or if the server is too old to set it.
+    pub fn serial(&self) -> Option<u64> {
+        crate::utils::object_serial(self.props())
+    }
+
     /// Get the param infos for the node.
     pub fn params(&self) -> &[spa::param::ParamInfo] {
         unsafe {
@@ -214,6 +309,57 @@ impl fmt::Debug for NodeInfoRef {
     }
 }
 
+/// Serializes the info with the keys used by `pw-dump`.
+///
+/// The fields that are not flagged in the change mask are not valid and are serialized as `null`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for NodeInfoRef {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mask = self.change_mask();
+        let has_state = mask.contains(NodeChangeMask::STATE);
+        let (state, error) = match self.state() {
+            NodeState::Error(error) => ("error", Some(error)),
+            NodeState::Creating => ("creating", None),
+            NodeState::Suspended => ("suspended", None),
+            NodeState::Idle => ("idle", None),
+            NodeState::Running => ("running", None),
+        };
+
+        let mut info = serializer.serialize_struct("NodeInfo", 10)?;
+        info.serialize_field("id", &self.id())?;
+        info.serialize_field("max-input-ports", &self.max_input_ports())?;
+        info.serialize_field("max-output-ports", &self.max_output_ports())?;
+        info.serialize_field("change-mask", &crate::utils::FlagNames(mask))?;
+        info.serialize_field(
+            "n-input-ports",
+            &mask
+                .contains(NodeChangeMask::INPUT_PORTS)
+                .then(|| self.n_input_ports()),
+        )?;
+        info.serialize_field(
+            "n-output-ports",
+            &mask
+                .contains(NodeChangeMask::OUTPUT_PORTS)
+                .then(|| self.n_output_ports()),
+        )?;
+        info.serialize_field("state", &has_state.then_some(state))?;
+        info.serialize_field("error", &error.filter(|_| has_state))?;
+        info.serialize_field(
+            "props",
+            &self
+                .props()
+                .filter(|_| mask.contains(NodeChangeMask::PROPS)),
+        )?;
+        info.serialize_field(
+            "params",
+            &mask.contains(NodeChangeMask::PARAMS).then(|| self.params()),
+        )?;
+        info.end()
+    }
+}
+
 pub struct NodeInfo {
     ptr: ptr::NonNull<pw_sys::pw_node_info>,
 }
@@ -269,8 +415,10 @@ bitflags! {
     }
 }
 
-#[derive(Debug)]
+/// The state of a node, see [`NodeInfoRef::state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NodeState<'a> {
+    /// The node is in error, with the error message borrowed from the info.
     Error(&'a str),
     Creating,
     Suspended,
@@ -300,13 +448,15 @@ pub struct NodeListener {
     events: Pin<Box<pw_sys::pw_node_events>>,
     listener: Pin<Box<spa_sys::spa_hook>>,
     #[allow(dead_code)]
-    data: Box<ListenerLocalCallbacks>,
+    data: Box<crate::utils::Guarded<ListenerLocalCallbacks>>,
+    loop_: LoopHandle,
 }
 
-impl Listener for NodeListener {}
+impl_listener!(NodeListener);
 
 impl Drop for NodeListener {
     fn drop(&mut self) {
+        let _lock = self.loop_.lock();
         spa::utils::hook::remove(*self.listener);
     }
 }
@@ -315,16 +465,20 @@ impl<'a> NodeListenerLocalBuilder<'a> {
     #[must_use]
     pub fn info<F>(mut self, info: F) -> Self
     where
-        F: Fn(&NodeInfoRef) + 'static,
+        F: FnMut(&NodeInfoRef) + 'static,
     {
         self.cbs.info = Some(Box::new(info));
         self
     }
 
+    /// Set the callback of the `param` event.
+    ///
+    /// The pod is only valid during the call, copy it with [`ToOwned::to_owned`] into an
+    /// [`OwnedPod`] to keep it.
     #[must_use]
     pub fn param<F>(mut self, param: F) -> Self
     where
-        F: Fn(i32, spa::param::ParamType, u32, u32, Option<&Pod>) + 'static,
+        F: FnMut(i32, spa::param::ParamType, u32, u32, Option<&Pod>) + 'static,
     {
         self.cbs.param = Some(Box::new(param));
         self
@@ -336,10 +490,17 @@ impl<'a> NodeListenerLocalBuilder<'a> {
             data: *mut c_void,
             info: *const pw_sys::pw_node_info,
         ) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            let info = ptr::NonNull::new(info as *mut pw_sys::pw_node_info).expect("info is NULL");
-            let info = info.cast::<NodeInfoRef>().as_ref();
-            callbacks.info.as_ref().unwrap()(info);
+            crate::utils::catch_callback_panic("node info", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                let info =
+                    ptr::NonNull::new(info as *mut pw_sys::pw_node_info).expect("info is NULL");
+                let info = info.cast::<NodeInfoRef>().as_ref();
+                callbacks.info.as_mut().unwrap()(info);
+            })
         }
 
         unsafe extern "C" fn node_events_param(
@@ -350,16 +511,22 @@ impl<'a> NodeListenerLocalBuilder<'a> {
             next: u32,
             param: *const spa_sys::spa_pod,
         ) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
+            crate::utils::catch_callback_panic("node param", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
 
-            let id = spa::param::ParamType::from_raw(id);
-            let param = if !param.is_null() {
-                unsafe { Some(Pod::from_raw(param)) }
-            } else {
-                None
-            };
+                let id = spa::param::ParamType::from_raw(id);
+                let param = if !param.is_null() {
+                    unsafe { Some(Pod::from_raw(param)) }
+                } else {
+                    None
+                };
 
-            callbacks.param.as_ref().unwrap()(seq, id, index, next, param);
+                callbacks.param.as_mut().unwrap()(seq, id, index, next, param);
+            })
         }
 
         let e = unsafe {
@@ -379,18 +546,21 @@ impl<'a> NodeListenerLocalBuilder<'a> {
         let (listener, data) = unsafe {
             let node = &self.node.proxy.as_ptr();
 
-            let data = Box::into_raw(Box::new(self.cbs));
+            let data = Box::into_raw(Box::new(crate::utils::Guarded::new(self.cbs)));
             let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
             let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
 
-            spa_interface_call_method!(
-                node,
-                pw_sys::pw_node_methods,
-                add_listener,
-                listener_ptr.cast(),
-                e.as_ref().get_ref(),
-                data as *mut _
-            );
+            if self.node.proxy.is_connected() {
+                spa_interface_call_method!(
+                    node,
+                    pw_sys::pw_node_methods,
+                    add_listener,
+                    listener_ptr.cast(),
+                    e.as_ref().get_ref(),
+                    data as *mut _
+                );
+                spa::utils::hook::reset_on_remove(&mut *listener_ptr);
+            }
 
             (listener, Box::from_raw(data))
         };
@@ -399,6 +569,414 @@ impl<'a> NodeListenerLocalBuilder<'a> {
             events: e,
             listener,
             data,
+            loop_: self.node.proxy.loop_handle(),
         }
     }
 }
+
+/// The volume and mute of a node, kept up to date from its [`Props`] param.
+///
+/// This subscribes to the `Props` param of the node, and decodes the latest one.
+/// [`set_volume`](Self::set_volume) and [`set_mute`](Self::set_mute) send a `Props` param
+/// to the node and update the state right away, the state is then replaced by the param
+/// the node sends back once it applied it.
+///
+/// Nodes expose their volume in different ways: the adapters of audio streams and devices
+/// have `channelVolumes`, others only have `softVolumes` or a global `volume`. The state is read
+/// from, and written to, the first of these the node has. Nodes without a `Props` param, or
+/// without volume or mute in it, are reported as not supported.
+///
+/// The controls are a handle which can be cloned, the node is listened to until the last
+/// clone is dropped.
+///
+/// ```no_run
+/// use pipewire::{context::Context, main_loop::MainLoop, node::{Node, NodeControls}};
+///
+/// # fn controls(node: Node) -> Result<(), pipewire::Error> {
+/// let controls = NodeControls::new(node)?;
+/// controls.changed(|controls| {
+///     println!("volume: {:?}, mute: {:?}", controls.volume(), controls.mute());
+/// });
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct NodeControls {
+    inner: Rc<ControlsInner>,
+}
+
+struct ControlsInner {
+    // removed before the node is dropped
+    listener: RefCell<Option<NodeListener>>,
+    node: Node,
+    supported: Cell<Option<bool>>,
+    props: RefCell<Option<Props>>,
+    #[allow(clippy::type_complexity)]
+    changed: RefCell<Option<Rc<dyn Fn(&NodeControls)>>>,
+}
+
+impl NodeControls {
+    /// Start following the `Props` param of `node`.
+    pub fn new(node: Node) -> Result<Self, Error> {
+        let inner = Rc::new(ControlsInner {
+            listener: RefCell::new(None),
+            node,
+            supported: Cell::new(None),
+            props: RefCell::new(None),
+            changed: RefCell::new(None),
+        });
+
+        let listener = inner
+            .node
+            .add_listener_local()
+            .info({
+                let inner = Rc::downgrade(&inner);
+                move |info| {
+                    if let Some(inner) = inner.upgrade() {
+                        NodeControls { inner }.info(info);
+                    }
+                }
+            })
+            .param({
+                let inner = Rc::downgrade(&inner);
+                move |_, id, _, _, param| {
+                    if let (ParamType::Props, Some(param)) = (id, param) {
+                        if let Some(inner) = inner.upgrade() {
+                            NodeControls { inner }.props_param(param);
+                        }
+                    }
+                }
+            })
+            .register();
+        *inner.listener.borrow_mut() = Some(listener);
+        inner.node.subscribe_params(&[ParamType::Props])?;
+
+        Ok(Self { inner })
+    }
+
+    pub fn node(&self) -> &Node {
+        &self.inner.node
+    }
+
+    /// Set the callback called when the state of the controls changed,
+    /// replacing the previous one.
+    pub fn changed<F>(&self, changed: F)
+    where
+        F: Fn(&NodeControls) + 'static,
+    {
+        *self.inner.changed.borrow_mut() = Some(Rc::new(changed));
+    }
+
+    /// Whether the node has volume or mute controls, or `None` until it is known.
+    pub fn supported(&self) -> Option<bool> {
+        self.inner.supported.get()
+    }
+
+    /// The latest `Props` param of the node, if it has one.
+    pub fn props(&self) -> Option<Props> {
+        self.inner.props.borrow().clone()
+    }
+
+    /// The volume of the node: the loudest of its channel volumes, like mixers show it, or its
+    /// global volume if it has no channel volumes.
+    pub fn volume(&self) -> Option<f32> {
+        let props = self.inner.props.borrow();
+        let props = props.as_ref()?;
+        match channel_volumes(props) {
+            Some(volumes) => volumes.iter().copied().reduce(f32::max),
+            None => props.volume,
+        }
+    }
+
+    pub fn mute(&self) -> Option<bool> {
+        let props = self.inner.props.borrow();
+        let props = props.as_ref()?;
+        props.mute.or(props.soft_mute)
+    }
+
+    /// The volume of each channel, in the order of the channel map of the node.
+    pub fn channel_volumes(&self) -> Option<Vec<f32>> {
+        let props = self.inner.props.borrow();
+        props
+            .as_ref()
+            .and_then(channel_volumes)
+            .map(<[f32]>::to_vec)
+    }
+
+    /// Set the volume of the node, keeping the balance between its channels.
+    ///
+    /// Fails with `ENOTSUP` if the node has no volume, and with `EAGAIN` if its `Props` param
+    /// was not received yet.
+    pub fn set_volume(&self, volume: f32) -> Result<(), Error> {
+        self.update(|props, update| {
+            if let Some(volumes) = props.channel_volumes.as_mut().filter(|v| !v.is_empty()) {
+                scale_volumes(volumes, volume);
+                update.channel_volumes = Some(volumes.clone());
+            } else if let Some(volumes) = props.soft_volumes.as_mut().filter(|v| !v.is_empty()) {
+                scale_volumes(volumes, volume);
+                update.soft_volumes = Some(volumes.clone());
+            } else if props.volume.is_some() {
+                props.volume = Some(volume);
+                update.volume = Some(volume);
+            } else {
+                return Err(Errno::ENOTSUP.into());
+            }
+            Ok(())
+        })
+    }
+
+    /// Mute or unmute the node.
+    ///
+    /// Fails with `ENOTSUP` if the node has no mute, and with `EAGAIN` if its `Props` param
+    /// was not received yet.
+    pub fn set_mute(&self, mute: bool) -> Result<(), Error> {
+        self.update(|props, update| {
+            if props.mute.is_some() {
+                props.mute = Some(mute);
+                update.mute = Some(mute);
+            } else if props.soft_mute.is_some() {
+                props.soft_mute = Some(mute);
+                update.soft_mute = Some(mute);
+            } else {
+                return Err(Errno::ENOTSUP.into());
+            }
+            Ok(())
+        })
+    }
+
+    /// Apply `f` to a copy of the current props, and send the props it set in `update`.
+    fn update(
+        &self,
+        f: impl FnOnce(&mut Props, &mut Props) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut props = match (self.supported(), self.props()) {
+            (_, Some(props)) => props,
+            (Some(false), None) => return Err(Errno::ENOTSUP.into()),
+            (_, None) => return Err(Errno::EAGAIN.into()),
+        };
+        let mut update = Props::new();
+        f(&mut props, &mut update)?;
+
+        self.inner
+            .node
+            .set_param(ParamType::Props, 0, &update.to_pod())?;
+        *self.inner.props.borrow_mut() = Some(props);
+        self.notify();
+        Ok(())
+    }
+
+    fn info(&self, info: &NodeInfoRef) {
+        if !info.change_mask().contains(NodeChangeMask::PARAMS) || self.props().is_some() {
+            return;
+        }
+
+        let has_props = info.params().iter().any(|param| {
+            param.id() == ParamType::Props && param.flags().contains(ParamInfoFlags::READ)
+        });
+        if !has_props && self.supported() != Some(false) {
+            self.inner.supported.set(Some(false));
+            self.notify();
+        }
+    }
+
+    fn props_param(&self, param: &Pod) {
+        let Ok(props) = Props::from_pod(param) else {
+            return;
+        };
+        let supported = props.mute.is_some()
+            || props.soft_mute.is_some()
+            || props.volume.is_some()
+            || channel_volumes(&props).is_some();
+
+        self.inner.supported.set(Some(supported));
+        *self.inner.props.borrow_mut() = Some(props);
+        self.notify();
+    }
+
+    fn notify(&self) {
+        // cloned, so that the callback can replace itself
+        let changed = self.inner.changed.borrow().clone();
+        if let Some(changed) = changed {
+            changed(self);
+        }
+    }
+}
+
+impl fmt::Debug for NodeControls {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NodeControls")
+            .field("node", &self.inner.node)
+            .field("supported", &self.supported())
+            .field("volume", &self.volume())
+            .field("mute", &self.mute())
+            .field("channel-volumes", &self.channel_volumes())
+            .finish()
+    }
+}
+
+impl Drop for ControlsInner {
+    fn drop(&mut self) {
+        self.listener.get_mut().take();
+    }
+}
+
+/// The volumes of the channels of `props`: its `channelVolumes`, or its `softVolumes`.
+fn channel_volumes(props: &Props) -> Option<&[f32]> {
+    [&props.channel_volumes, &props.soft_volumes]
+        .into_iter()
+        .flatten()
+        .map(Vec::as_slice)
+        .find(|volumes| !volumes.is_empty())
+}
+
+/// Scale `volumes` so that the loudest is `volume`, keeping their balance.
+fn scale_volumes(volumes: &mut [f32], volume: f32) {
+    let max = volumes.iter().copied().fold(0.0, f32::max);
+    for v in volumes.iter_mut() {
+        *v = if max > 0.0 { *v * volume / max } else { volume };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{context::Context, properties::properties};
+    use spa::param::ParamType;
+
+    #[test]
+    fn enum_params_sync() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let _adapter = context
+            .load_module("libpipewire-module-adapter", None, None)
+            .unwrap();
+        let core = context.connect_self(None).unwrap();
+
+        let node: Node = core
+            .create_object(
+                "adapter",
+                &properties! {
+                    "factory.name" => "support.null-audio-sink",
+                    "node.name" => "pipewire-rs.test.sink",
+                    "media.class" => "Audio/Sink",
+                    "object.linger" => "false",
+                },
+            )
+            .unwrap();
+
+        let formats = node
+            .enum_params_sync(&core, &mainloop, ParamType::EnumFormat)
+            .unwrap();
+        assert!(!formats.is_empty());
+        assert!(formats.iter().all(|format| format.is_object()));
+
+        let props = node
+            .enum_params_sync(&core, &mainloop, ParamType::Props)
+            .unwrap();
+        assert!(!props.is_empty());
+    }
+
+    #[test]
+    fn controls() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let _adapter = context
+            .load_module("libpipewire-module-adapter", None, None)
+            .unwrap();
+        let core = context.connect_self(None).unwrap();
+
+        let node: Node = core
+            .create_object(
+                "adapter",
+                &properties! {
+                    "factory.name" => "support.null-audio-sink",
+                    "node.name" => "pipewire-rs.test.controls",
+                    "media.class" => "Audio/Sink",
+                    "audio.position" => "FL,FR",
+                    "object.linger" => "false",
+                },
+            )
+            .unwrap();
+        let controls = NodeControls::new(node).unwrap();
+        assert_eq!(controls.supported(), None);
+        assert_eq!(
+            controls.set_mute(true).unwrap_err().errno(),
+            Some(Errno::EAGAIN)
+        );
+
+        let changes = Rc::new(Cell::new(0));
+        controls.changed({
+            let changes = changes.clone();
+            move |_| changes.set(changes.get() + 1)
+        });
+        mainloop.run_until(|_| controls.props().is_some());
+        assert_eq!(controls.supported(), Some(true));
+        assert_eq!(controls.mute(), Some(false));
+        assert_eq!(controls.volume(), Some(1.0));
+
+        // the state is updated right away, then by the param of the node
+        let count = changes.get();
+        controls.set_mute(true).unwrap();
+        controls.set_volume(0.5).unwrap();
+        assert_eq!(changes.get(), count + 2);
+        assert_eq!(controls.mute(), Some(true));
+        assert_eq!(controls.volume(), Some(0.5));
+
+        mainloop.run_until(|_| changes.get() > count + 2 && controls.mute() == Some(true));
+        mainloop.run_until(|_| controls.volume() == Some(0.5));
+        // the channels of the sink may not be configured yet, leaving only the global volume
+        let volumes = controls.channel_volumes().unwrap_or_default();
+        assert!(volumes.iter().all(|volume| (volume - 0.5).abs() < 1e-6));
+    }
+
+    #[test]
+    fn scale_channel_volumes() {
+        let mut volumes = [0.5, 0.25];
+        scale_volumes(&mut volumes, 0.8);
+        assert_eq!(volumes, [0.8, 0.4]);
+
+        let mut volumes = [0.0, 0.0];
+        scale_volumes(&mut volumes, 0.3);
+        assert_eq!(volumes, [0.3, 0.3]);
+
+        let mut props = Props {
+            volume: Some(1.0),
+            soft_volumes: Some(vec![0.5, 0.5]),
+            channel_volumes: Some(vec![]),
+            ..Default::default()
+        };
+        assert_eq!(channel_volumes(&props), Some(&[0.5, 0.5][..]));
+        props.channel_volumes = Some(vec![0.1, 0.2]);
+        assert_eq!(channel_volumes(&props), Some(&[0.1, 0.2][..]));
+        props.channel_volumes = None;
+        props.soft_volumes = None;
+        assert_eq!(channel_volumes(&props), None);
+    }
+
+    #[test]
+    fn state() {
+        let mut raw: pw_sys::pw_node_info = unsafe { std::mem::zeroed() };
+        raw.state = pw_sys::pw_node_state::PW_NODE_STATE_ERROR;
+        raw.n_input_ports = 2;
+        raw.max_input_ports = 8;
+        raw.change_mask =
+            (pw_sys::PW_NODE_CHANGE_MASK_STATE | pw_sys::PW_NODE_CHANGE_MASK_INPUT_PORTS) as u64;
+
+        let info = unsafe { &*(std::ptr::addr_of!(raw) as *const NodeInfoRef) };
+        assert_eq!(info.state(), NodeState::Error(""));
+        assert_eq!((info.n_input_ports(), info.max_input_ports()), (2, 8));
+        assert_eq!(
+            info.change_mask(),
+            NodeChangeMask::STATE | NodeChangeMask::INPUT_PORTS
+        );
+
+        let error = c"no format";
+        raw.error = error.as_ptr();
+        let info = unsafe { &*(std::ptr::addr_of!(raw) as *const NodeInfoRef) };
+        assert_eq!(info.state(), NodeState::Error("no format"));
+
+        raw.state = pw_sys::pw_node_state::PW_NODE_STATE_RUNNING;
+        let info = unsafe { &*(std::ptr::addr_of!(raw) as *const NodeInfoRef) };
+        assert_eq!(info.state(), NodeState::Running);
+    }
+}
@@ -1,18 +1,88 @@
 // Copyright The pipewire-rs Contributors.
 // SPDX-License-Identifier: MIT
 
+//! Permissions of clients on objects.
+//!
+//! Permissions are usually written in the `"rwxm"` form, with a `-` for each missing permission:
+//!
+//! ```
+//! use pipewire::permissions::PermissionFlags;
+//!
+//! let flags: PermissionFlags = "r-x-".parse().unwrap();
+//! assert_eq!(flags, PermissionFlags::R | PermissionFlags::X);
+//! assert!(PermissionFlags::ALL.to_string().starts_with("rwxm"));
+//! ```
+
 use bitflags::bitflags;
-use std::fmt;
+use std::{fmt, str::FromStr};
+
+use crate::constants::ID_ANY;
 
 bitflags! {
     #[derive(Debug, PartialEq, Eq, Clone, Copy)]
     pub struct PermissionFlags: u32 {
+        /// Object can be seen and events can be received
         const R = pw_sys::PW_PERM_R;
+        /// Methods can be called that modify the object
         const W = pw_sys::PW_PERM_W;
+        /// Methods can be called on the object
         const X = pw_sys::PW_PERM_X;
+        /// Metadata can be set on the object
         const M = pw_sys::PW_PERM_M;
+        /// A link can be made between a node that doesn't have permission to see the other node
         #[cfg(feature = "v0_3_77")]
         const L = pw_sys::PW_PERM_L;
+        /// The read, write, execute and metadata permissions
+        const ALL = Self::R.bits() | Self::W.bits() | Self::X.bits() | Self::M.bits();
+    }
+}
+
+impl PermissionFlags {
+    /// The flags in the order of the `"rwxm"` form, with their character.
+    fn chars() -> impl Iterator<Item = (Self, char)> {
+        [
+            (Self::R, 'r'),
+            (Self::W, 'w'),
+            (Self::X, 'x'),
+            (Self::M, 'm'),
+            #[cfg(feature = "v0_3_77")]
+            (Self::L, 'l'),
+        ]
+        .into_iter()
+    }
+}
+
+/// Print the flags in the `"rwxm"` form, with a `-` for each missing permission.
+impl fmt::Display for PermissionFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (flag, c) in Self::chars() {
+            let c = if self.contains(flag) { c } else { '-' };
+            fmt::Write::write_char(f, c)?;
+        }
+        Ok(())
+    }
+}
+
+/// Error returned when parsing [`PermissionFlags`] fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("invalid permission character {0:?}")]
+pub struct ParsePermissionError(pub char);
+
+/// Parse flags in the `"rwxm"` form.
+///
+/// The characters may appear in any order, and `-` is ignored.
+impl FromStr for PermissionFlags {
+    type Err = ParsePermissionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.chars()
+            .filter(|c| *c != '-')
+            .try_fold(Self::empty(), |flags, c| {
+                Self::chars()
+                    .find(|(_, flag_char)| *flag_char == c)
+                    .map(|(flag, _)| flags | flag)
+                    .ok_or(ParsePermissionError(c))
+            })
     }
 }
 
@@ -28,6 +98,20 @@ impl Permission {
         })
     }
 
+    /// The permissions on all objects without a permission of their own.
+    pub fn any(flags: PermissionFlags) -> Self {
+        Self::new(ID_ANY, flags)
+    }
+
+    /// Obtain a [`Permission`] from a raw `pw_permission`.
+    pub fn from_raw(raw: pw_sys::pw_permission) -> Self {
+        Self(raw)
+    }
+
+    pub fn as_raw(&self) -> &pw_sys::pw_permission {
+        &self.0
+    }
+
     pub fn id(&self) -> u32 {
         self.0.id
     }
@@ -45,6 +129,18 @@ impl Permission {
     }
 }
 
+impl From<pw_sys::pw_permission> for Permission {
+    fn from(value: pw_sys::pw_permission) -> Self {
+        Self::from_raw(value)
+    }
+}
+
+impl From<Permission> for pw_sys::pw_permission {
+    fn from(value: Permission) -> Self {
+        value.0
+    }
+}
+
 impl fmt::Debug for Permission {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Permission")
@@ -53,3 +149,74 @@ impl fmt::Debug for Permission {
             .finish()
     }
 }
+
+/// Print the permission as `id:rwxm`, or `any:rwxm` for [`Permission::any`].
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.id() == ID_ANY {
+            write!(f, "any:{}", self.permission_flags())
+        } else {
+            write!(f, "{}:{}", self.id(), self.permission_flags())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The dashes printed for the permissions which are not in the `"rwxm"` form of older versions.
+    const EXTRA: &str = if cfg!(feature = "v0_3_77") { "-" } else { "" };
+
+    #[test]
+    fn display() {
+        assert_eq!(PermissionFlags::ALL.to_string(), format!("rwxm{EXTRA}"));
+        assert_eq!(
+            (PermissionFlags::R | PermissionFlags::X).to_string(),
+            format!("r-x-{EXTRA}")
+        );
+        assert_eq!(PermissionFlags::empty().to_string(), format!("----{EXTRA}"));
+
+        assert_eq!(
+            Permission::new(42, PermissionFlags::R).to_string(),
+            format!("42:r---{EXTRA}")
+        );
+        assert_eq!(
+            Permission::any(PermissionFlags::ALL).to_string(),
+            format!("any:rwxm{EXTRA}")
+        );
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!("rwxm".parse(), Ok(PermissionFlags::ALL));
+        assert_eq!("r-x-".parse(), Ok(PermissionFlags::R | PermissionFlags::X));
+        assert_eq!("xr".parse(), Ok(PermissionFlags::R | PermissionFlags::X));
+        assert_eq!("----".parse(), Ok(PermissionFlags::empty()));
+        assert_eq!("".parse(), Ok(PermissionFlags::empty()));
+        assert_eq!(
+            "rwz".parse::<PermissionFlags>(),
+            Err(ParsePermissionError('z'))
+        );
+
+        for flags in [
+            PermissionFlags::ALL,
+            PermissionFlags::W | PermissionFlags::M,
+            PermissionFlags::all(),
+        ] {
+            assert_eq!(flags.to_string().parse(), Ok(flags));
+        }
+    }
+
+    #[test]
+    fn raw() {
+        let permission = Permission::any(PermissionFlags::R);
+        let raw: pw_sys::pw_permission = permission.into();
+        assert_eq!(raw.id, ID_ANY);
+        assert_eq!(raw.permissions, pw_sys::PW_PERM_R);
+
+        let permission = Permission::from(raw);
+        assert_eq!(permission.id(), ID_ANY);
+        assert_eq!(permission.permission_flags(), PermissionFlags::R);
+    }
+}
@@ -3,18 +3,31 @@
 
 use bitflags::bitflags;
 use libc::c_void;
+use nix::errno::Errno;
+use std::cell::{Cell, RefCell};
 use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicI32, Ordering};
 use std::{fmt, mem};
 use std::{pin::Pin, ptr};
 
 use crate::{
-    proxy::{Listener, Proxy, ProxyT},
+    core::CoreRef,
+    main_loop::MainLoop,
+    proxy::{impl_listener, impl_transparent_proxy, Proxy, ProxyT},
     spa::utils::Direction,
+    thread_loop::LoopHandle,
     types::ObjectType,
+    Error,
+};
+use spa::{
+    pod::{OwnedPod, Pod},
+    spa_interface_call_method,
+    utils::result::{AsyncSeq, SpaResult},
 };
-use spa::{pod::Pod, spa_interface_call_method};
 
 #[derive(Debug)]
+#[repr(transparent)]
 pub struct Port {
     proxy: Proxy,
 }
@@ -32,47 +45,134 @@ impl Port {
     /// Subscribe to parameter changes
     ///
     /// Automatically emit `param` events for the given ids when they are changed
-    // FIXME: Return result?
-    pub fn subscribe_params(&self, ids: &[spa::param::ParamType]) {
-        unsafe {
+    pub fn subscribe_params(&self, ids: &[spa::param::ParamType]) -> Result<(), Error> {
+        let res = unsafe {
             spa_interface_call_method!(
-                self.proxy.as_ptr(),
+                self.proxy.checked_ptr()?,
                 pw_sys::pw_port_methods,
                 subscribe_params,
                 ids.as_ptr() as *mut _,
                 ids.len().try_into().unwrap()
-            );
-        }
+            )
+        };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
     }
 
-    /// Enumerate node parameters
+    /// Enumerate port parameters
     ///
-    /// Start enumeration of node parameters. For each param, a
+    /// Start enumeration of port parameters. For each param, a
     /// param event will be emitted.
     ///
     /// # Parameters
     /// `seq`: a sequence number to place in the reply \
     /// `id`: the parameter id to enum, or [`None`] to allow any id \
     /// `start`: the start index or 0 for the first param \
-    /// `num`: the maximum number of params to retrieve ([`u32::MAX`] may be used to retrieve all params)
-    // FIXME: Add filter parameter
-    // FIXME: Return result?
-    pub fn enum_params(&self, seq: i32, id: Option<spa::param::ParamType>, start: u32, num: u32) {
+    /// `num`: the maximum number of params to retrieve ([`u32::MAX`] may be used to retrieve all params) \
+    /// `filter`: a param to filter the results with, or [`None`] to retrieve all params
+    pub fn enum_params(
+        &self,
+        seq: i32,
+        id: Option<spa::param::ParamType>,
+        start: u32,
+        num: u32,
+        filter: Option<&Pod>,
+    ) -> Result<AsyncSeq, Error> {
         let id = id.map(|id| id.as_raw()).unwrap_or(crate::constants::ID_ANY);
+        let filter = filter.map_or(ptr::null(), |filter| filter.as_raw_ptr().cast_const());
 
-        unsafe {
+        let res = unsafe {
             spa_interface_call_method!(
-                self.proxy.as_ptr(),
-                pw_sys::pw_node_methods,
+                self.proxy.checked_ptr()?,
+                pw_sys::pw_port_methods,
                 enum_params,
                 seq,
                 id,
                 start,
                 num,
-                std::ptr::null()
-            );
+                filter
+            )
+        };
+
+        let res = SpaResult::from_c(res).into_async_result()?;
+        Ok(res)
+    }
+
+    /// Retrieve all the params of the port with the given `id`.
+    ///
+    /// This enumerates the params and runs `main_loop` until `core` has processed the request,
+    /// so it must not be called from a callback of `main_loop`.
+    pub fn enum_params_sync(
+        &self,
+        core: &CoreRef,
+        main_loop: &MainLoop,
+        id: spa::param::ParamType,
+    ) -> Result<Vec<OwnedPod>, Error> {
+        static NEXT_SEQ: AtomicI32 = AtomicI32::new(1);
+        let seq = NEXT_SEQ.fetch_add(1, Ordering::Relaxed);
+
+        let params = Rc::new(RefCell::new(Vec::new()));
+        let _listener = self
+            .add_listener_local()
+            .param({
+                let params = params.clone();
+                move |param_seq, _, _, _, param| match param {
+                    Some(param) if param_seq == seq => params.borrow_mut().push(param.to_owned()),
+                    _ => {}
+                }
+            })
+            .register();
+
+        self.enum_params(seq, Some(id), 0, u32::MAX, None)?;
+
+        let done = Rc::new(Cell::new(false));
+        let pending = core.sync(0)?;
+        let _core_listener = core
+            .add_listener_local()
+            .done({
+                let done = done.clone();
+                move |id, seq| {
+                    if id == pw_sys::PW_ID_CORE && seq == pending {
+                        done.set(true);
+                    }
+                }
+            })
+            .register();
+
+        main_loop.run_until(|_| done.get());
+        if !done.get() {
+            // the loop was quit before the params were received
+            return Err(Errno::ECANCELED.into());
         }
+
+        let params = params.take();
+        Ok(params)
     }
+
+    /// Retrieve the formats the port can be configured with, its `EnumFormat` params.
+    ///
+    /// A port without any format returns an empty list.
+    /// See [`enum_params_sync`](Self::enum_params_sync) for how the loop is run.
+    pub fn formats(&self, core: &CoreRef, main_loop: &MainLoop) -> Result<Vec<OwnedPod>, Error> {
+        self.enum_params_sync(core, main_loop, spa::param::ParamType::EnumFormat)
+    }
+}
+
+/// The formats accepted by both `a` and `b`, as lists of `EnumFormat` params.
+///
+/// Each format of `a` is intersected with each format of `b` with [`Pod::filter`],
+/// and the pairs without a value in common for a property are skipped.
+pub fn intersect_formats(a: &[OwnedPod], b: &[OwnedPod]) -> Vec<OwnedPod> {
+    a.iter()
+        .flat_map(|a| b.iter().filter_map(move |b| a.filter(b).ok()))
+        .collect()
+}
+
+/// Whether a port with the formats `a` can be linked to a port with the formats `b`,
+/// which is the case if they have at least one format in common.
+pub fn formats_compatible(a: &[OwnedPod], b: &[OwnedPod]) -> bool {
+    a.iter().any(|a| b.iter().any(|b| a.filter(b).is_ok()))
 }
 
 impl ProxyT for Port {
@@ -96,14 +196,21 @@ impl ProxyT for Port {
     }
 }
 
+impl_transparent_proxy!(Port);
+
 #[derive(Default)]
 struct ListenerLocalCallbacks {
     #[allow(clippy::type_complexity)]
-    info: Option<Box<dyn Fn(&PortInfoRef)>>,
+    info: Option<Box<dyn FnMut(&PortInfoRef)>>,
     #[allow(clippy::type_complexity)]
-    param: Option<Box<dyn Fn(i32, spa::param::ParamType, u32, u32, Option<&Pod>)>>,
+    param: Option<Box<dyn FnMut(i32, spa::param::ParamType, u32, u32, Option<&Pod>)>>,
 }
 
+/// Builder of a listener for the events of a [`Port`], see [`Port::add_listener_local`].
+///
+/// **Events emitted while one of the callbacks is running are lost.** The callbacks are never
+/// called re-entrantly: an event received when a callback iterates the loop, e.g. to wait for a
+/// reply, or emitted synchronously by a method it calls, is dropped without notice.
 pub struct PortListenerLocalBuilder<'a> {
     port: &'a Port,
     cbs: ListenerLocalCallbacks,
@@ -138,6 +245,14 @@ impl PortInfoRef {
         ptr::NonNull::new(props_ptr).map(|ptr| unsafe { ptr.as_ref() })
     }
 
+    /// The `object.serial` of the port, from its properties.
+    ///
+    /// Unlike the id, the serial is never reused by another object.
+    /// This is `None` if the properties are not in the info or if the server is too old to set it.
+    pub fn serial(&self) -> Option<u64> {
+        crate::utils::object_serial(self.props())
+    }
+
     /// Get the param infos for the port.
     pub fn params(&self) -> &[spa::param::ParamInfo] {
         let params = self.0.params;
@@ -163,6 +278,39 @@ impl fmt::Debug for PortInfoRef {
     }
 }
 
+/// Serializes the info with the keys used by `pw-dump`.
+///
+/// The fields that are not flagged in the change mask are not valid and are serialized as `null`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for PortInfoRef {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mask = self.change_mask();
+        let direction = match self.direction() {
+            Direction::Input => "input",
+            Direction::Output => "output",
+            _ => "invalid",
+        };
+
+        let mut info = serializer.serialize_struct("PortInfo", 5)?;
+        info.serialize_field("id", &self.id())?;
+        info.serialize_field("direction", direction)?;
+        info.serialize_field("change-mask", &crate::utils::FlagNames(mask))?;
+        info.serialize_field(
+            "props",
+            &self
+                .props()
+                .filter(|_| mask.contains(PortChangeMask::PROPS)),
+        )?;
+        info.serialize_field(
+            "params",
+            &mask.contains(PortChangeMask::PARAMS).then(|| self.params()),
+        )?;
+        info.end()
+    }
+}
+
 pub struct PortInfo {
     ptr: ptr::NonNull<pw_sys::pw_port_info>,
 }
@@ -229,13 +377,15 @@ pub struct PortListener {
     events: Pin<Box<pw_sys::pw_port_events>>,
     listener: Pin<Box<spa_sys::spa_hook>>,
     #[allow(dead_code)]
-    data: Box<ListenerLocalCallbacks>,
+    data: Box<crate::utils::Guarded<ListenerLocalCallbacks>>,
+    loop_: LoopHandle,
 }
 
-impl Listener for PortListener {}
+impl_listener!(PortListener);
 
 impl Drop for PortListener {
     fn drop(&mut self) {
+        let _lock = self.loop_.lock();
         spa::utils::hook::remove(*self.listener);
     }
 }
@@ -244,16 +394,20 @@ impl<'a> PortListenerLocalBuilder<'a> {
     #[must_use]
     pub fn info<F>(mut self, info: F) -> Self
     where
-        F: Fn(&PortInfoRef) + 'static,
+        F: FnMut(&PortInfoRef) + 'static,
     {
         self.cbs.info = Some(Box::new(info));
         self
     }
 
+    /// Set the callback of the `param` event.
+    ///
+    /// The pod is only valid during the call, copy it with [`ToOwned::to_owned`] into an
+    /// [`OwnedPod`](spa::pod::OwnedPod) to keep it.
     #[must_use]
     pub fn param<F>(mut self, param: F) -> Self
     where
-        F: Fn(i32, spa::param::ParamType, u32, u32, Option<&Pod>) + 'static,
+        F: FnMut(i32, spa::param::ParamType, u32, u32, Option<&Pod>) + 'static,
     {
         self.cbs.param = Some(Box::new(param));
         self
@@ -265,10 +419,17 @@ impl<'a> PortListenerLocalBuilder<'a> {
             data: *mut c_void,
             info: *const pw_sys::pw_port_info,
         ) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            let info = ptr::NonNull::new(info as *mut pw_sys::pw_port_info).expect("info is NULL");
-            let info = info.cast::<PortInfoRef>().as_ref();
-            callbacks.info.as_ref().unwrap()(info);
+            crate::utils::catch_callback_panic("port info", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                let info =
+                    ptr::NonNull::new(info as *mut pw_sys::pw_port_info).expect("info is NULL");
+                let info = info.cast::<PortInfoRef>().as_ref();
+                callbacks.info.as_mut().unwrap()(info);
+            })
         }
 
         unsafe extern "C" fn port_events_param(
@@ -279,16 +440,22 @@ impl<'a> PortListenerLocalBuilder<'a> {
             next: u32,
             param: *const spa_sys::spa_pod,
         ) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-
-            let id = spa::param::ParamType::from_raw(id);
-            let param = if !param.is_null() {
-                unsafe { Some(Pod::from_raw(param)) }
-            } else {
-                None
-            };
-
-            callbacks.param.as_ref().unwrap()(seq, id, index, next, param);
+            crate::utils::catch_callback_panic("port param", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+
+                let id = spa::param::ParamType::from_raw(id);
+                let param = if !param.is_null() {
+                    unsafe { Some(Pod::from_raw(param)) }
+                } else {
+                    None
+                };
+
+                callbacks.param.as_mut().unwrap()(seq, id, index, next, param);
+            })
         }
 
         let e = unsafe {
@@ -308,18 +475,21 @@ impl<'a> PortListenerLocalBuilder<'a> {
         let (listener, data) = unsafe {
             let port = &self.port.proxy.as_ptr();
 
-            let data = Box::into_raw(Box::new(self.cbs));
+            let data = Box::into_raw(Box::new(crate::utils::Guarded::new(self.cbs)));
             let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
             let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
 
-            spa_interface_call_method!(
-                port,
-                pw_sys::pw_port_methods,
-                add_listener,
-                listener_ptr.cast(),
-                e.as_ref().get_ref(),
-                data as *mut _
-            );
+            if self.port.proxy.is_connected() {
+                spa_interface_call_method!(
+                    port,
+                    pw_sys::pw_port_methods,
+                    add_listener,
+                    listener_ptr.cast(),
+                    e.as_ref().get_ref(),
+                    data as *mut _
+                );
+                spa::utils::hook::reset_on_remove(&mut *listener_ptr);
+            }
 
             (listener, Box::from_raw(data))
         };
@@ -328,6 +498,64 @@ impl<'a> PortListenerLocalBuilder<'a> {
             events: e,
             listener,
             data,
+            loop_: self.port.proxy.loop_handle(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spa::{
+        param::{
+            audio::AudioFormat,
+            format::{FormatProperties, MediaSubtype, MediaType},
+            ParamType,
+        },
+        pod::{deserialize::PodDeserializer, ObjectBuilder, Value},
+        utils::SpaTypes,
+    };
+
+    fn audio_format(formats: &[AudioFormat]) -> OwnedPod {
+        ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+            .prop(FormatProperties::MediaType, MediaType::Audio)
+            .prop(FormatProperties::MediaSubtype, MediaSubtype::Raw)
+            .prop_choice_enum(
+                FormatProperties::AudioFormat,
+                formats[0],
+                formats.iter().copied(),
+            )
+            .build()
+    }
+
+    fn value(pod: &Pod) -> Value {
+        PodDeserializer::deserialize_any_from(pod.as_bytes())
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn format_intersection() {
+        let source = [
+            audio_format(&[AudioFormat::F32P]),
+            audio_format(&[AudioFormat::S16LE, AudioFormat::S32LE]),
+        ];
+        let sink = [audio_format(&[AudioFormat::S32LE, AudioFormat::F32LE])];
+
+        assert!(formats_compatible(&source, &sink));
+        let common = intersect_formats(&source, &sink);
+        assert_eq!(common.len(), 1);
+        assert_eq!(
+            value(&common[0].fixate().unwrap()),
+            value(&audio_format(&[AudioFormat::S32LE]).fixate().unwrap())
+        );
+
+        let planar = [audio_format(&[AudioFormat::F32P])];
+        assert!(!formats_compatible(&planar, &sink));
+        assert!(intersect_formats(&planar, &sink).is_empty());
+
+        // a port without formats is compatible with nothing
+        assert!(!formats_compatible(&[], &sink));
+        assert!(intersect_formats(&source, &[]).is_empty());
+    }
+}
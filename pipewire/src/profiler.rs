@@ -0,0 +1,168 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! The profiler interface, exported by `libpipewire-module-profiler`.
+//!
+//! While a listener is registered, the profiler emits a `profile` event with the timings of
+//! each processing cycle of the graph, which can be decoded with
+//! [`ProfilerData::from_pod`](spa::param::profiler::ProfilerData::from_pod).
+
+use libc::c_void;
+use std::mem;
+use std::pin::Pin;
+
+use crate::{
+    proxy::{impl_listener, impl_transparent_proxy, Proxy, ProxyT},
+    thread_loop::LoopHandle,
+    types::ObjectType,
+};
+use spa::{pod::Pod, spa_interface_call_method};
+
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct Profiler {
+    proxy: Proxy,
+}
+
+impl ProxyT for Profiler {
+    fn type_() -> ObjectType {
+        ObjectType::Profiler
+    }
+
+    fn upcast(self) -> Proxy {
+        self.proxy
+    }
+
+    fn upcast_ref(&self) -> &Proxy {
+        &self.proxy
+    }
+
+    unsafe fn from_proxy_unchecked(proxy: Proxy) -> Self
+    where
+        Self: Sized,
+    {
+        Self { proxy }
+    }
+}
+
+impl_transparent_proxy!(Profiler);
+
+impl Profiler {
+    #[must_use]
+    pub fn add_listener_local(&self) -> ProfilerListenerLocalBuilder {
+        ProfilerListenerLocalBuilder {
+            profiler: self,
+            cbs: ListenerLocalCallbacks::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ListenerLocalCallbacks {
+    profile: Option<Box<dyn FnMut(&Pod)>>,
+}
+
+/// Builder of a listener for the events of a [`Profiler`], see [`Profiler::add_listener_local`].
+///
+/// **Events emitted while one of the callbacks is running are lost.** The callbacks are never
+/// called re-entrantly: an event received when a callback iterates the loop, e.g. to wait for a
+/// reply, or emitted synchronously by a method it calls, is dropped without notice.
+pub struct ProfilerListenerLocalBuilder<'a> {
+    profiler: &'a Profiler,
+    cbs: ListenerLocalCallbacks,
+}
+
+pub struct ProfilerListener {
+    // Need to stay allocated while the listener is registered
+    #[allow(dead_code)]
+    events: Pin<Box<pw_sys::pw_profiler_events>>,
+    listener: Pin<Box<spa_sys::spa_hook>>,
+    #[allow(dead_code)]
+    data: Box<crate::utils::Guarded<ListenerLocalCallbacks>>,
+    loop_: LoopHandle,
+}
+
+impl_listener!(ProfilerListener);
+
+impl Drop for ProfilerListener {
+    fn drop(&mut self) {
+        let _lock = self.loop_.lock();
+        spa::utils::hook::remove(*self.listener);
+    }
+}
+
+impl<'a> ProfilerListenerLocalBuilder<'a> {
+    /// Set the callback of the `profile` event.
+    ///
+    /// The pod is only valid during the call, copy it with [`ToOwned::to_owned`] into an
+    /// [`OwnedPod`](spa::pod::OwnedPod) to keep it.
+    #[must_use]
+    pub fn profile<F>(mut self, profile: F) -> Self
+    where
+        F: FnMut(&Pod) + 'static,
+    {
+        self.cbs.profile = Some(Box::new(profile));
+        self
+    }
+
+    #[must_use]
+    pub fn register(self) -> ProfilerListener {
+        unsafe extern "C" fn profiler_events_profile(
+            data: *mut c_void,
+            pod: *const spa_sys::spa_pod,
+        ) {
+            crate::utils::catch_callback_panic("profiler profile", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                if pod.is_null() {
+                    return;
+                }
+                let pod = Pod::from_raw(pod);
+                callbacks.profile.as_mut().unwrap()(pod);
+            })
+        }
+
+        let e = unsafe {
+            let mut e: Pin<Box<pw_sys::pw_profiler_events>> = Box::pin(mem::zeroed());
+            e.version = pw_sys::PW_VERSION_PROFILER_EVENTS;
+
+            if self.cbs.profile.is_some() {
+                e.profile = Some(profiler_events_profile);
+            }
+
+            e
+        };
+
+        let (listener, data) = unsafe {
+            let profiler = &self.profiler.proxy.as_ptr();
+
+            let data = Box::into_raw(Box::new(crate::utils::Guarded::new(self.cbs)));
+            let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
+            let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
+
+            if self.profiler.proxy.is_connected() {
+                spa_interface_call_method!(
+                    profiler,
+                    pw_sys::pw_profiler_methods,
+                    add_listener,
+                    listener_ptr.cast(),
+                    e.as_ref().get_ref(),
+                    data as *mut _
+                );
+                spa::utils::hook::reset_on_remove(&mut *listener_ptr);
+            }
+
+            (listener, Box::from_raw(data))
+        };
+
+        ProfilerListener {
+            events: e,
+            listener,
+            data,
+            loop_: self.profiler.proxy.loop_handle(),
+        }
+    }
+}
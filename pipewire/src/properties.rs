@@ -1,9 +1,12 @@
+use crate::keys::typed::{Key, KeyType};
 use std::{
+    collections::HashMap,
     ffi::{CStr, CString},
     fmt,
     mem::ManuallyDrop,
-    ops::Deref,
+    ops::{Deref, DerefMut},
     ptr,
+    str::FromStr,
 };
 
 /// A collection of key/value pairs.
@@ -97,7 +100,7 @@ impl Properties {
     }
 
     // TODO: `fn from_string` that calls `pw_sys::pw_properties_new_string`
-    // TODO: bindings for pw_properties_update_keys, pw_properties_update, pw_properties_add, pw_properties_add_keys
+    // TODO: bindings for pw_properties_update_keys, pw_properties_add_keys
 
     /// Create a new `Properties` from a given dictionary.
     ///
@@ -109,6 +112,25 @@ impl Properties {
             Self::from_ptr(ptr::NonNull::new(copy).expect("pw_properties_new_dict() returned NULL"))
         }
     }
+
+    /// Insert `key` with `value`, returning the properties to chain calls.
+    ///
+    /// ```
+    /// use pipewire::properties::properties;
+    ///
+    /// let defaults = properties! { "media.type" => "Audio" };
+    /// let props = defaults.clone().with("node.name", "my-node");
+    /// assert_eq!(props.get("node.name"), Some("my-node"));
+    /// ```
+    #[must_use]
+    pub fn with<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<Vec<u8>>,
+        V: Into<Vec<u8>>,
+    {
+        self.insert(key, value);
+        self
+    }
 }
 
 impl AsRef<PropertiesRef> for Properties {
@@ -167,6 +189,42 @@ where
     }
 }
 
+impl From<&spa::utils::dict::DictRef> for Properties {
+    /// Create a new `Properties` by copying all entries of `dict`.
+    fn from(dict: &spa::utils::dict::DictRef) -> Self {
+        Self::from_dict(dict)
+    }
+}
+
+impl<K, V, S> From<HashMap<K, V, S>> for Properties
+where
+    K: Into<Vec<u8>>,
+    V: Into<Vec<u8>>,
+{
+    fn from(map: HashMap<K, V, S>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl<K, V> Extend<(K, V)> for Properties
+where
+    K: Into<Vec<u8>>,
+    V: Into<Vec<u8>>,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        self.deref_mut().extend(iter);
+    }
+}
+
+impl<'a> IntoIterator for &'a Properties {
+    type Item = (&'a str, &'a str);
+    type IntoIter = spa::utils::dict::Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 impl Drop for Properties {
     fn drop(&mut self) {
         unsafe { pw_sys::pw_properties_free(self.ptr.as_ptr()) }
@@ -232,6 +290,64 @@ impl PropertiesRef {
         res.and_then(|res| res.to_str().ok())
     }
 
+    /// Get the value associated with `key` and parse it into `T`.
+    ///
+    /// Returns `None` if the key is not set, and `Some(Err(_))` if the value could not be parsed.
+    pub fn get_parsed<T: FromStr>(&self, key: &str) -> Option<Result<T, T::Err>> {
+        self.get(key).map(str::parse)
+    }
+
+    /// Get the value associated with `key` as a boolean.
+    ///
+    /// The value is parsed with `pw_properties_parse_bool()`, so `"true"` and `"1"` are `true`,
+    /// anything else is `false`.
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        let key = CString::new(key).expect("key contains null byte");
+        unsafe {
+            let value = pw_sys::pw_properties_get(self.as_raw_ptr().cast_const(), key.as_ptr());
+            (!value.is_null()).then(|| pw_sys::pw_properties_parse_bool(value))
+        }
+    }
+
+    /// Get the value associated with a typed `key`.
+    ///
+    /// Returns `None` if the key is not set or its value could not be parsed.
+    pub fn get_typed<T: KeyType>(&self, key: &Key<T>) -> Option<T::Value<'_>> {
+        self.get(key.name()).and_then(T::parse)
+    }
+
+    /// Set the value of a typed `key`, formatting it the way PipeWire expects.
+    pub fn set<T: KeyType>(&mut self, key: &Key<T>, value: T::Value<'_>) {
+        self.insert(key.name(), T::format(&value));
+    }
+
+    /// An iterator over all key-value pairs that are valid utf-8.
+    ///
+    /// The iterator reads the underlying dictionary directly,
+    /// so it always reflects the entries currently stored.
+    pub fn iter(&self) -> spa::utils::dict::Iter<'_> {
+        self.dict().iter()
+    }
+
+    /// Returns the number of key-value-pairs stored.
+    pub fn len(&self) -> usize {
+        self.dict().len()
+    }
+
+    /// Returns `true` if no key-value-pairs are stored.
+    pub fn is_empty(&self) -> bool {
+        self.dict().is_empty()
+    }
+
+    /// Copy all entries into a [`HashMap`].
+    ///
+    /// Entries whose key or value is not valid utf-8 are skipped.
+    pub fn to_hashmap(&self) -> HashMap<String, String> {
+        self.iter()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect()
+    }
+
     pub fn insert<K, V>(&mut self, key: K, value: V)
     where
         K: Into<Vec<u8>>,
@@ -242,6 +358,15 @@ impl PropertiesRef {
         unsafe { pw_sys::pw_properties_set(self.as_raw_ptr(), k.as_ptr(), v.as_ptr()) };
     }
 
+    /// Like [`insert`](Self::insert), but without allocating the C strings of the key and value.
+    ///
+    /// This is meant for hot paths, like per-buffer callbacks, with keys and values kept as
+    /// `CStr`, for example the `PW_KEY_*` constants of `pipewire-sys` converted once with
+    /// [`CStr::from_bytes_with_nul`].
+    pub fn insert_cstr(&mut self, key: &CStr, value: &CStr) {
+        unsafe { pw_sys::pw_properties_set(self.as_raw_ptr(), key.as_ptr(), value.as_ptr()) };
+    }
+
     pub fn remove<T>(&mut self, key: T)
     where
         T: Into<Vec<u8>>,
@@ -253,6 +378,22 @@ impl PropertiesRef {
     pub fn clear(&mut self) {
         unsafe { pw_sys::pw_properties_clear(self.as_raw_ptr()) }
     }
+
+    /// Copy all entries of `other`, overwriting the values of existing keys.
+    ///
+    /// Returns the number of keys whose value changed.
+    pub fn merge(&mut self, other: &spa::utils::dict::DictRef) -> usize {
+        let changed = unsafe { pw_sys::pw_properties_update(self.as_raw_ptr(), other.as_raw()) };
+        changed.try_into().unwrap_or(0)
+    }
+
+    /// Copy the entries of `other` whose keys are not set yet, keeping existing values.
+    ///
+    /// Returns the number of keys added.
+    pub fn merge_missing(&mut self, other: &spa::utils::dict::DictRef) -> usize {
+        let added = unsafe { pw_sys::pw_properties_add(self.as_raw_ptr(), other.as_raw()) };
+        added.try_into().unwrap_or(0)
+    }
 }
 
 impl AsRef<spa::utils::dict::DictRef> for PropertiesRef {
@@ -261,6 +402,15 @@ impl AsRef<spa::utils::dict::DictRef> for PropertiesRef {
     }
 }
 
+impl<'a> IntoIterator for &'a PropertiesRef {
+    type Item = (&'a str, &'a str);
+    type IntoIter = spa::utils::dict::Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 impl fmt::Debug for PropertiesRef {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // FIXME: Debug-print dict key and values directly
@@ -280,6 +430,66 @@ where
     }
 }
 
+/// Serializes the properties as a map of strings.
+///
+/// Keys and values that are not valid utf-8 are converted lossily.
+#[cfg(feature = "serde")]
+impl serde::Serialize for PropertiesRef {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let dict = self.dict();
+        let mut map = serializer.serialize_map(Some(dict.len()))?;
+        for (k, v) in dict.iter_cstr() {
+            map.serialize_entry(&k.to_string_lossy(), &v.to_string_lossy())?;
+        }
+        map.end()
+    }
+}
+
+/// Serializes the properties as a map of strings.
+///
+/// Keys and values that are not valid utf-8 are converted lossily.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Properties {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(self.deref(), serializer)
+    }
+}
+
+/// Deserializes the properties from a map of strings.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Properties {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PropertiesVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for PropertiesVisitor {
+            type Value = Properties;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of strings")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut access: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut props = Properties::new();
+                while let Some((k, v)) = access.next_entry::<String, String>()? {
+                    if k.contains('\0') || v.contains('\0') {
+                        return Err(serde::de::Error::custom("null byte in properties entry"));
+                    }
+                    props.insert(k, v);
+                }
+
+                Ok(props)
+            }
+        }
+
+        deserializer.deserialize_map(PropertiesVisitor)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -317,6 +527,18 @@ mod tests {
         assert_eq!(Some("V1"), props.dict().get("K1"));
     }
 
+    #[test]
+    fn insert_cstr() {
+        let mut props = Properties::new();
+        let key = CStr::from_bytes_with_nul(pw_sys::PW_KEY_NODE_NAME).unwrap();
+
+        props.insert_cstr(key, CStr::from_bytes_with_nul(b"V0\0").unwrap());
+        assert_eq!(Some("V0"), props.get("node.name"));
+        props.insert_cstr(key, CStr::from_bytes_with_nul(b"V1\0").unwrap());
+        assert_eq!(Some("V1"), props.get_cstr(key));
+        assert_eq!(props.len(), 1);
+    }
+
     #[test]
     fn clone() {
         let props1 = properties! {
@@ -350,6 +572,134 @@ mod tests {
         assert_eq!(props.dict().get("K1"), Some("V1"));
     }
 
+    #[test]
+    fn iter() {
+        let mut props = properties! {
+            "K0" => "V0",
+            "K1" => "V1"
+        };
+        assert_eq!(props.len(), 2);
+        assert!(!props.is_empty());
+
+        let entries: Vec<_> = props.iter().collect();
+        assert_eq!(entries, vec![("K0", "V0"), ("K1", "V1")]);
+
+        // The iterator is backed by the dict, so it reflects changes.
+        props.remove("K0");
+        props.insert("K2", "V2");
+        let entries: Vec<_> = (&props).into_iter().collect();
+        assert_eq!(entries, vec![("K1", "V1"), ("K2", "V2")]);
+
+        props.clear();
+        assert!(props.is_empty());
+        assert_eq!(props.iter().next(), None);
+    }
+
+    #[test]
+    fn extend() {
+        let mut props = Properties::new();
+        props.extend(vec![("K0", "V0"), ("K1", "V1")]);
+        assert_eq!(props.get("K0"), Some("V0"));
+        assert_eq!(props.get("K1"), Some("V1"));
+
+        let copy: Properties = props.iter().collect();
+        assert_eq!(copy.len(), 2);
+        assert_eq!(copy.get("K1"), Some("V1"));
+    }
+
+    #[test]
+    fn get_parsed() {
+        let props = properties! {
+            "int" => "42",
+            "float" => "1.5",
+            "badger" => "badger",
+            "true" => "true",
+            "one" => "1",
+            "false" => "false"
+        };
+
+        assert_eq!(props.get_parsed::<u32>("int"), Some(Ok(42)));
+        assert_eq!(props.get_parsed::<f64>("float"), Some(Ok(1.5)));
+        assert!(matches!(props.get_parsed::<u32>("badger"), Some(Err(_))));
+        assert_eq!(props.get_parsed::<u32>("missing"), None);
+
+        assert_eq!(props.get_bool("true"), Some(true));
+        assert_eq!(props.get_bool("one"), Some(true));
+        assert_eq!(props.get_bool("false"), Some(false));
+        assert_eq!(props.get_bool("badger"), Some(false));
+        assert_eq!(props.get_bool("missing"), None);
+    }
+
+    #[test]
+    fn typed_keys() {
+        use crate::keys::typed;
+        use spa::utils::Fraction;
+
+        let mut props = Properties::new();
+        props.set(&typed::NODE_NAME, "node");
+        props.set(&typed::AUDIO_CHANNELS, 2);
+        props.set(&typed::NODE_DONT_RECONNECT, true);
+        props.set(
+            &typed::NODE_LATENCY,
+            Fraction {
+                num: 256,
+                denom: 48000,
+            },
+        );
+
+        assert_eq!(props.get("node.name"), Some("node"));
+        assert_eq!(props.get("audio.channels"), Some("2"));
+        assert_eq!(props.get("node.dont-reconnect"), Some("true"));
+        assert_eq!(props.get("node.latency"), Some("256/48000"));
+
+        assert_eq!(props.get_typed(&typed::NODE_NAME), Some("node"));
+        assert_eq!(props.get_typed(&typed::AUDIO_CHANNELS), Some(2));
+        assert_eq!(props.get_typed(&typed::NODE_DONT_RECONNECT), Some(true));
+        let latency = props.get_typed(&typed::NODE_LATENCY).unwrap();
+        assert_eq!((latency.num, latency.denom), (256, 48000));
+
+        props.insert("audio.channels", "badger");
+        props.insert("node.latency", "1024");
+        assert_eq!(props.get_typed(&typed::AUDIO_CHANNELS), None);
+        assert!(props.get_typed(&typed::NODE_LATENCY).is_none());
+        assert_eq!(props.get_typed(&typed::MEDIA_CLASS), None);
+    }
+
+    #[test]
+    fn conversions() {
+        use spa::static_dict;
+
+        let dict = static_dict! { "K0" => "V0" };
+        let props = Properties::from(&*dict);
+        assert_eq!(props.get("K0"), Some("V0"));
+
+        let mut map = HashMap::new();
+        map.insert("K0", "V0");
+        map.insert("K1", "V1");
+        let props = Properties::from(map);
+        assert_eq!(props.len(), 2);
+
+        let map = props.to_hashmap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("K0").map(String::as_str), Some("V0"));
+        assert_eq!(map.get("K1").map(String::as_str), Some("V1"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let props = properties! {
+            "K0" => "V0"
+        };
+
+        let json = serde_json::to_string(&props).unwrap();
+        assert_eq!(json, r#"{"K0":"V0"}"#);
+
+        let props: Properties = serde_json::from_str(r#"{"K0":"V0","K1":"V1"}"#).unwrap();
+        assert_eq!(props.len(), 2);
+        assert_eq!(props.get("K1"), Some("V1"));
+    }
+
     #[test]
     fn properties_ref() {
         let props = properties! {
@@ -366,4 +716,46 @@ mod tests {
         assert_eq!(props_copy.dict().len(), 1);
         assert_eq!(props_copy.dict().get("K0"), Some("V0"));
     }
+
+    #[test]
+    fn merge() {
+        let defaults = properties! {
+            "K0" => "default",
+            "K1" => "default"
+        };
+        let overrides = properties! {
+            "K1" => "override",
+            "K2" => "override"
+        };
+
+        let mut props = defaults.clone();
+        assert_eq!(props.merge(overrides.dict()), 2);
+        assert_eq!(props.get("K0"), Some("default"));
+        assert_eq!(props.get("K1"), Some("override"));
+        assert_eq!(props.get("K2"), Some("override"));
+        // nothing changes when merging the same entries again
+        assert_eq!(props.merge(overrides.dict()), 0);
+
+        let mut props = defaults.clone();
+        assert_eq!(props.merge_missing(overrides.dict()), 1);
+        assert_eq!(props.get("K1"), Some("default"));
+        assert_eq!(props.get("K2"), Some("override"));
+
+        let empty = Properties::new();
+        let mut props = defaults.clone();
+        assert_eq!(props.merge(empty.dict()), 0);
+        assert_eq!(props.merge_missing(empty.dict()), 0);
+        assert_eq!(props.len(), 2);
+
+        let mut props = Properties::new();
+        assert_eq!(props.merge_missing(defaults.dict()), 2);
+        assert_eq!(props.get("K0"), Some("default"));
+    }
+
+    #[test]
+    fn with() {
+        let props = Properties::new().with("K0", "V0").with("K0", "V1");
+        assert_eq!(props.len(), 1);
+        assert_eq!(props.get("K0"), Some("V1"));
+    }
 }
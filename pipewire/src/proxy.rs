@@ -2,27 +2,129 @@
 // SPDX-License-Identifier: MIT
 
 use libc::{c_char, c_void};
+use nix::errno::Errno;
+use std::cell::{Cell, RefCell};
 use std::fmt;
 use std::mem;
 use std::pin::Pin;
+use std::rc::Rc;
+use std::time::Duration;
 use std::{ffi::CStr, ptr};
 
-use crate::{types::ObjectType, Error};
+use crate::{
+    loop_::TimerSpec, main_loop::MainLoop, thread_loop::LoopHandle, types::ObjectType, Error,
+};
 
 pub struct Proxy {
     ptr: ptr::NonNull<pw_sys::pw_proxy>,
+    // Tracks the destruction of the proxy by libpipewire, such as when disconnecting its core.
+    // Need to stay allocated while the listener is registered
+    #[allow(dead_code)]
+    destroy_events: Pin<Box<pw_sys::pw_proxy_events>>,
+    destroy_listener: Pin<Box<spa_sys::spa_hook>>,
+    destroyed: Box<DestroyedState>,
+    loop_: LoopHandle,
+}
+
+/// The id and type of a proxy, saved when libpipewire destroys it.
+struct DestroyedState {
+    ptr: ptr::NonNull<pw_sys::pw_proxy>,
+    destroyed: RefCell<Option<(u32, ObjectType, u32)>>,
 }
 
 // Wrapper around a proxy pointer
 impl Proxy {
     pub(crate) fn new(ptr: ptr::NonNull<pw_sys::pw_proxy>) -> Self {
-        Proxy { ptr }
+        unsafe extern "C" fn proxy_destroy(data: *mut c_void) {
+            crate::utils::catch_callback_panic("proxy destroy", (), || {
+                let state = (data as *const DestroyedState).as_ref().unwrap();
+                let (type_, version) = proxy_type(state.ptr.as_ptr());
+                let id = pw_sys::pw_proxy_get_id(state.ptr.as_ptr());
+                *state.destroyed.borrow_mut() = Some((id, type_, version));
+            })
+        }
+
+        let destroyed = Box::new(DestroyedState {
+            ptr,
+            destroyed: RefCell::new(None),
+        });
+
+        unsafe {
+            let mut events: Pin<Box<pw_sys::pw_proxy_events>> = Box::pin(mem::zeroed());
+            events.version = pw_sys::PW_VERSION_PROXY_EVENTS;
+            events.destroy = Some(proxy_destroy);
+
+            let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
+            let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
+            let funcs: *const pw_sys::pw_proxy_events = events.as_ref().get_ref();
+            let data: *const DestroyedState = destroyed.as_ref();
+
+            pw_sys::pw_proxy_add_listener(
+                ptr.as_ptr(),
+                listener_ptr.cast(),
+                funcs.cast(),
+                data as *mut _,
+            );
+            spa::utils::hook::reset_on_remove(&mut *listener_ptr);
+
+            Proxy {
+                ptr,
+                destroy_events: events,
+                destroy_listener: listener,
+                destroyed,
+                loop_: LoopHandle::of_proxy(ptr.as_ptr()),
+            }
+        }
     }
 
     pub(crate) fn as_ptr(&self) -> *mut pw_sys::pw_proxy {
         self.ptr.as_ptr()
     }
 
+    /// The loop of the proxy, to lock while dropping it or its listeners.
+    pub(crate) fn loop_handle(&self) -> LoopHandle {
+        self.loop_.clone()
+    }
+
+    /// The pointer of the proxy, or `EPIPE` if it is not connected anymore.
+    pub(crate) fn checked_ptr(&self) -> Result<*mut pw_sys::pw_proxy, Error> {
+        if self.is_connected() {
+            Ok(self.as_ptr())
+        } else {
+            Err(Errno::EPIPE.into())
+        }
+    }
+
+    /// Whether libpipewire destroyed the proxy, in which case it must not be used anymore.
+    fn is_destroyed(&self) -> bool {
+        self.destroyed.destroyed.borrow().is_some()
+    }
+
+    /// Whether the proxy is still connected to the server.
+    ///
+    /// A proxy is disconnected along with its core, such as by [`Core::disconnect`](crate::core::Core::disconnect).
+    /// The methods of a disconnected proxy then fail with `EPIPE`, and its listeners are not called anymore.
+    /// It is still safe to drop the proxy and its listeners.
+    pub fn is_connected(&self) -> bool {
+        !self.is_destroyed() && !unsafe { pw_sys::pw_proxy_get_core(self.as_ptr()) }.is_null()
+    }
+
+    /// Listen to the events of the proxy.
+    ///
+    /// The events of any high level proxy are available through [`ProxyT::upcast_ref`], for example
+    /// to be notified with [`ProxyListenerLocalBuilder::proxy_error`] when binding a node failed:
+    ///
+    /// ```no_run
+    /// # fn example(node: &pipewire::node::Node) {
+    /// use pipewire::proxy::ProxyT;
+    ///
+    /// let _listener = node
+    ///     .upcast_ref()
+    ///     .add_listener_local()
+    ///     .proxy_error(|err| eprintln!("failed to bind node: {err}"))
+    ///     .register();
+    /// # }
+    /// ```
     pub fn add_listener_local(&self) -> ProxyListenerLocalBuilder {
         ProxyListenerLocalBuilder {
             proxy: self,
@@ -31,39 +133,78 @@ impl Proxy {
     }
 
     pub fn id(&self) -> u32 {
+        if let Some((id, _, _)) = &*self.destroyed.destroyed.borrow() {
+            return *id;
+        }
         unsafe { pw_sys::pw_proxy_get_id(self.as_ptr()) }
     }
 
     /// Get the type of the proxy as well as it's version.
     pub fn get_type(&self) -> (ObjectType, u32) {
-        unsafe {
-            let mut version = 0;
-            let proxy_type = pw_sys::pw_proxy_get_type(self.as_ptr(), &mut version);
-            let proxy_type = CStr::from_ptr(proxy_type);
-
-            (
-                ObjectType::from_str(proxy_type.to_str().expect("invalid proxy type")),
-                version,
-            )
+        if let Some((_, type_, version)) = &*self.destroyed.destroyed.borrow() {
+            return (type_.clone(), *version);
         }
+        unsafe { proxy_type(self.as_ptr()) }
+    }
+
+    /// The version of the interface of the proxy, as negotiated when it was created,
+    /// such as with [`Registry::bind`](crate::registry::Registry::bind).
+    pub fn version(&self) -> u32 {
+        self.get_type().1
+    }
+
+    /// Whether the proxy represents an object of the type of `P`.
+    fn is<P: ProxyT>(&self) -> bool {
+        self.get_type().0 == P::type_()
     }
 
     /// Attempt to downcast the proxy to the provided type.
     ///
-    /// The downcast will fail if the type that the proxy represents does not match the provided type. \
-    /// In that case, the function returns `(self, Error::WrongProxyType)` so that the proxy is not lost.
-    pub(crate) fn downcast<P: ProxyT>(self) -> Result<P, (Self, Error)> {
-        // Make sure the proxy we got has the type that is requested
-        if P::type_() == self.get_type().0 {
+    /// The downcast will fail if the type that the proxy represents does not match the provided type.
+    /// In that case, the proxy is returned unconsumed so that it is not lost.
+    ///
+    /// Proxies with an older version than the one used by this crate can be downcast,
+    /// see [`Registry::bind_version`](crate::registry::Registry::bind_version).
+    pub fn downcast<P: ProxyT>(self) -> Result<P, Self> {
+        if self.is::<P>() {
             unsafe { Ok(P::from_proxy_unchecked(self)) }
         } else {
-            Err((self, Error::WrongProxyType))
+            Err(self)
+        }
+    }
+
+    /// Attempt to downcast a reference to the proxy to the provided type.
+    ///
+    /// Returns `None` in the same cases as [`downcast`](Self::downcast).
+    /// Only the high level proxies of this crate can be downcast by reference.
+    pub fn downcast_ref<P: ProxyT + sealed::TransparentProxy>(&self) -> Option<&P> {
+        if self.is::<P>() {
+            unsafe { Some(P::from_proxy_ref_unchecked(self)) }
+        } else {
+            None
         }
     }
 }
 
+/// Get the type of `proxy` as well as it's version.
+unsafe fn proxy_type(proxy: *mut pw_sys::pw_proxy) -> (ObjectType, u32) {
+    let mut version = 0;
+    let proxy_type = pw_sys::pw_proxy_get_type(proxy, &mut version);
+    let proxy_type = CStr::from_ptr(proxy_type);
+
+    (
+        ObjectType::from_interface_name(proxy_type.to_str().expect("invalid proxy type")),
+        version,
+    )
+}
+
 impl Drop for Proxy {
     fn drop(&mut self) {
+        let _lock = self.loop_.lock();
+        if self.is_destroyed() {
+            return;
+        }
+        spa::utils::hook::remove(*self.destroy_listener);
         unsafe {
             pw_sys::pw_proxy_destroy(self.as_ptr());
         }
@@ -82,6 +223,166 @@ impl fmt::Debug for Proxy {
     }
 }
 
+/// An error reported by the server for a proxy, see [`ProxyListenerLocalBuilder::proxy_error`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("{message} ({errno})")]
+pub struct ProxyError {
+    /// Sequence number of the method call which failed.
+    pub seq: i32,
+    pub errno: Errno,
+    pub message: String,
+}
+
+impl ProxyError {
+    /// Build an error from the arguments of the `error` event, where `res` is a negative errno.
+    pub fn new(seq: i32, res: i32, message: &str) -> Self {
+        Self {
+            seq,
+            errno: Errno::from_i32(-res),
+            message: message.to_owned(),
+        }
+    }
+}
+
+/// Why [`PendingProxy::wait_bound`] failed.
+#[derive(thiserror::Error, Debug)]
+pub enum BindError {
+    /// The server reported an error for the proxy, such as invalid properties for a factory.
+    #[error(transparent)]
+    Failed(#[from] ProxyError),
+    /// The proxy was not bound before the timeout.
+    #[error("timed out waiting for the proxy to be bound")]
+    TimedOut,
+    /// The proxy was removed without being bound, such as when the connection was lost.
+    #[error("proxy removed before being bound")]
+    Removed,
+    /// The loop was quit while waiting.
+    #[error("loop quit while waiting for the proxy to be bound")]
+    Canceled,
+    /// The timeout could not be set up on the loop.
+    #[error(transparent)]
+    Loop(#[from] Error),
+}
+
+#[derive(Debug, Clone)]
+enum BindState {
+    Pending,
+    Bound(u32),
+    Failed(ProxyError),
+    Removed,
+}
+
+/// A proxy whose object may not be bound yet, as returned by
+/// [`Core::create_object_pending`](crate::core::CoreRef::create_object_pending).
+///
+/// The `bound` and `error` events of the proxy are tracked from the creation of the handle, so
+/// that the outcome of creating an object can be told apart from the ones of other objects
+/// created at the same time, whose errors are also reported to the core.
+pub struct PendingProxy<P: ProxyT> {
+    // removed before the proxy is dropped
+    _listener: ProxyListener,
+    proxy: P,
+    state: Rc<RefCell<BindState>>,
+}
+
+impl<P: ProxyT> PendingProxy<P> {
+    /// Start tracking whether `proxy` gets bound.
+    ///
+    /// This must be called before the loop of the proxy is iterated after creating it,
+    /// not to miss its events.
+    pub fn new(proxy: P) -> Self {
+        let state = Rc::new(RefCell::new(BindState::Pending));
+
+        let settle = |state: &Rc<RefCell<BindState>>, new_state: BindState| {
+            let mut state = state.borrow_mut();
+            if matches!(*state, BindState::Pending) {
+                *state = new_state;
+            }
+        };
+        let listener = proxy
+            .upcast_ref()
+            .add_listener_local()
+            .bound({
+                let state = state.clone();
+                move |id| settle(&state, BindState::Bound(id))
+            })
+            .proxy_error({
+                let state = state.clone();
+                move |err| settle(&state, BindState::Failed(err))
+            })
+            .removed({
+                let state = state.clone();
+                move || settle(&state, BindState::Removed)
+            })
+            .register();
+
+        Self {
+            _listener: listener,
+            proxy,
+            state,
+        }
+    }
+
+    pub fn proxy(&self) -> &P {
+        &self.proxy
+    }
+
+    pub fn into_proxy(self) -> P {
+        self.proxy
+    }
+
+    /// The id of the global the proxy is bound to, `None` until the `bound` event is received,
+    /// or if binding the proxy failed.
+    pub fn bound_id(&self) -> Option<u32> {
+        match *self.state.borrow() {
+            BindState::Bound(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// Run `main_loop` until the proxy is bound, returning the id of its global, or until it fails.
+    ///
+    /// This returns right away if the outcome is already known. It runs the loop, so it must not be
+    /// called from a callback of `main_loop`.
+    pub fn wait_bound(&self, main_loop: &MainLoop, timeout: Duration) -> Result<u32, BindError> {
+        let state = || self.state.borrow().clone();
+        let timed_out = Rc::new(Cell::new(false));
+
+        if matches!(state(), BindState::Pending) {
+            let timer = main_loop.loop_().add_timer({
+                let timed_out = timed_out.clone();
+                move |_| timed_out.set(true)
+            })?;
+            timer
+                .set(TimerSpec::After {
+                    delay: timeout,
+                    interval: None,
+                })
+                .into_sync_result()
+                .map_err(Error::SpaError)?;
+
+            main_loop.run_until(|_| timed_out.get() || !matches!(state(), BindState::Pending));
+        }
+
+        match state() {
+            BindState::Bound(id) => Ok(id),
+            BindState::Failed(err) => Err(err.into()),
+            BindState::Removed => Err(BindError::Removed),
+            BindState::Pending if timed_out.get() => Err(BindError::TimedOut),
+            BindState::Pending => Err(BindError::Canceled),
+        }
+    }
+}
+
+impl<P: ProxyT + fmt::Debug> fmt::Debug for PendingProxy<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingProxy")
+            .field("proxy", &self.proxy)
+            .field("state", &*self.state.borrow())
+            .finish()
+    }
+}
+
 // Trait implemented by high level proxy wrappers
 pub trait ProxyT {
     // Add Sized restriction on those methods so it can be used as a
@@ -106,8 +407,83 @@ pub trait ProxyT {
         Self: Sized;
 }
 
-// Trait implemented by listener on high level proxy wrappers.
-pub trait Listener {}
+pub(crate) mod sealed {
+    use super::Proxy;
+
+    /// Implemented by the high level proxies of the crate which can be downcast by reference,
+    /// see [`Proxy::downcast_ref`].
+    pub trait TransparentProxy {
+        /// Downcast a reference to the provided proxy to `Self` without checking that the type matches.
+        ///
+        /// # Safety
+        /// It must be manually ensured that the provided proxy is actually a proxy representing the created type,
+        /// and `Self` must be a `#[repr(transparent)]` wrapper of [`Proxy`]. \
+        /// Otherwise, undefined behaviour may occur.
+        unsafe fn from_proxy_ref_unchecked(proxy: &Proxy) -> &Self;
+    }
+}
+
+/// Implement [`sealed::TransparentProxy`] for a `#[repr(transparent)]` wrapper of [`Proxy`].
+macro_rules! impl_transparent_proxy {
+    ($proxy:ty) => {
+        impl $crate::proxy::sealed::TransparentProxy for $proxy {
+            unsafe fn from_proxy_ref_unchecked(proxy: &$crate::proxy::Proxy) -> &Self {
+                &*(proxy as *const $crate::proxy::Proxy).cast::<Self>()
+            }
+        }
+    };
+}
+pub(crate) use impl_transparent_proxy;
+
+/// Trait implemented by listener on high level proxy wrappers.
+///
+/// Listeners must be dropped, unregistered, blocked or unblocked on the thread of the loop of their proxy,
+/// or while holding the lock of its [`ThreadLoop`](crate::thread_loop::ThreadLoop).
+pub trait Listener {
+    /// Stop the listener from receiving any events.
+    ///
+    /// Removes the listener registration and cleans up allocated resources, as dropping it does.
+    fn unregister(self)
+    where
+        Self: Sized,
+    {
+        // Consuming the listener will call drop()
+    }
+
+    /// Drop the events received by the listener, until [`unblock`](Self::unblock) is called.
+    ///
+    /// The listener stays registered, so this can be used to ignore the events caused by
+    /// a change made by the application itself, such as a param event following `set_param()`.
+    fn block(&mut self);
+
+    /// Deliver events to the listener again after [`block`](Self::block).
+    fn unblock(&mut self);
+
+    /// Whether the listener is blocked by [`block`](Self::block).
+    fn is_blocked(&self) -> bool;
+}
+
+/// Implement [`Listener`] for a listener with `events` and `listener` fields.
+macro_rules! impl_listener {
+    ($listener:ty) => {
+        impl $crate::proxy::Listener for $listener {
+            fn block(&mut self) {
+                spa::utils::hook::block(&mut self.listener);
+            }
+
+            fn unblock(&mut self) {
+                let funcs: *const _ = &*self.events;
+                // Safety: the listener was added with its events, which live as long as it does.
+                unsafe { spa::utils::hook::unblock(&mut self.listener, funcs.cast()) };
+            }
+
+            fn is_blocked(&self) -> bool {
+                spa::utils::hook::is_blocked(&self.listener)
+            }
+        }
+    };
+}
+pub(crate) use impl_listener;
 
 pub struct ProxyListener {
     // Need to stay allocated while the listener is registered
@@ -115,26 +491,36 @@ pub struct ProxyListener {
     events: Pin<Box<pw_sys::pw_proxy_events>>,
     listener: Pin<Box<spa_sys::spa_hook>>,
     #[allow(dead_code)]
-    data: Box<ListenerLocalCallbacks>,
+    data: Box<crate::utils::Guarded<ListenerLocalCallbacks>>,
+    loop_: LoopHandle,
 }
 
-impl Listener for ProxyListener {}
+impl_listener!(ProxyListener);
 
 impl Drop for ProxyListener {
     fn drop(&mut self) {
+        let _lock = self.loop_.lock();
         spa::utils::hook::remove(*self.listener);
     }
 }
 #[derive(Default)]
 struct ListenerLocalCallbacks {
-    destroy: Option<Box<dyn Fn()>>,
-    bound: Option<Box<dyn Fn(u32)>>,
-    removed: Option<Box<dyn Fn()>>,
-    done: Option<Box<dyn Fn(i32)>>,
+    destroy: Option<Box<dyn FnMut()>>,
+    bound: Option<Box<dyn FnMut(u32)>>,
+    #[cfg(feature = "v0_3_77")]
+    #[allow(clippy::type_complexity)]
+    bound_props: Option<Box<dyn FnMut(u32, &spa::utils::dict::DictRef)>>,
+    removed: Option<Box<dyn FnMut()>>,
+    done: Option<Box<dyn FnMut(i32)>>,
     #[allow(clippy::type_complexity)]
-    error: Option<Box<dyn Fn(i32, i32, &str)>>, // TODO: return a proper Error enum?
+    error: Option<Box<dyn FnMut(i32, i32, &str)>>,
 }
 
+/// Builder of a listener for the events of a [`Proxy`], see [`Proxy::add_listener_local`].
+///
+/// **Events emitted while one of the callbacks is running are lost.** The callbacks are never
+/// called re-entrantly: an event received when a callback iterates the loop, e.g. to wait for a
+/// reply, or emitted synchronously by a method it calls, is dropped without notice.
 pub struct ProxyListenerLocalBuilder<'a> {
     proxy: &'a Proxy,
     cbs: ListenerLocalCallbacks,
@@ -144,7 +530,7 @@ impl<'a> ProxyListenerLocalBuilder<'a> {
     #[must_use]
     pub fn destroy<F>(mut self, destroy: F) -> Self
     where
-        F: Fn() + 'static,
+        F: FnMut() + 'static,
     {
         self.cbs.destroy = Some(Box::new(destroy));
         self
@@ -153,16 +539,33 @@ impl<'a> ProxyListenerLocalBuilder<'a> {
     #[must_use]
     pub fn bound<F>(mut self, bound: F) -> Self
     where
-        F: Fn(u32) + 'static,
+        F: FnMut(u32) + 'static,
     {
         self.cbs.bound = Some(Box::new(bound));
         self
     }
 
+    /// Set the callback called when the proxy is bound to a global, with the id of the global
+    /// and the properties it was bound with.
+    ///
+    /// This saves a registry roundtrip to get the properties of an object created with
+    /// [`Core::create_object`](crate::core::Core::create_object).
+    /// If the PipeWire library is older than 0.3.77, the callback is called from the `bound` event
+    /// with empty properties instead.
+    #[cfg(feature = "v0_3_77")]
+    #[must_use]
+    pub fn bound_props<F>(mut self, bound_props: F) -> Self
+    where
+        F: FnMut(u32, &spa::utils::dict::DictRef) + 'static,
+    {
+        self.cbs.bound_props = Some(Box::new(bound_props));
+        self
+    }
+
     #[must_use]
     pub fn removed<F>(mut self, removed: F) -> Self
     where
-        F: Fn() + 'static,
+        F: FnMut() + 'static,
     {
         self.cbs.removed = Some(Box::new(removed));
         self
@@ -171,41 +574,110 @@ impl<'a> ProxyListenerLocalBuilder<'a> {
     #[must_use]
     pub fn done<F>(mut self, done: F) -> Self
     where
-        F: Fn(i32) + 'static,
+        F: FnMut(i32) + 'static,
     {
         self.cbs.done = Some(Box::new(done));
         self
     }
 
+    /// Set the callback of the `error` event, called with the sequence number of the failed call,
+    /// a negative errno and an error message.
     #[must_use]
     pub fn error<F>(mut self, error: F) -> Self
     where
-        F: Fn(i32, i32, &str) + 'static,
+        F: FnMut(i32, i32, &str) + 'static,
     {
         self.cbs.error = Some(Box::new(error));
         self
     }
 
+    /// Set the callback of the `error` event, with the error as a [`ProxyError`].
+    ///
+    /// This replaces the callback set with [`error`](Self::error).
+    #[must_use]
+    pub fn proxy_error<F>(self, mut error: F) -> Self
+    where
+        F: FnMut(ProxyError) + 'static,
+    {
+        self.error(move |seq, res, message| error(ProxyError::new(seq, res, message)))
+    }
+
     #[must_use]
     pub fn register(self) -> ProxyListener {
         unsafe extern "C" fn proxy_destroy(data: *mut c_void) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            callbacks.destroy.as_ref().unwrap()();
+            crate::utils::catch_callback_panic("proxy destroy", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                callbacks.destroy.as_mut().unwrap()();
+            })
         }
 
         unsafe extern "C" fn proxy_bound(data: *mut c_void, global_id: u32) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            callbacks.bound.as_ref().unwrap()(global_id);
+            crate::utils::catch_callback_panic("proxy bound", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                if let Some(bound) = &mut callbacks.bound {
+                    bound(global_id);
+                }
+
+                // Older libraries do not emit bound_props, so emulate it without properties.
+                #[cfg(feature = "v0_3_77")]
+                if let Some(bound_props) = &mut callbacks.bound_props {
+                    if !pw_sys::pw_check_library_version(0, 3, 77) {
+                        bound_props(global_id, &spa::utils::dict::Dict::new());
+                    }
+                }
+            })
+        }
+
+        #[cfg(feature = "v0_3_77")]
+        unsafe extern "C" fn proxy_bound_props(
+            data: *mut c_void,
+            global_id: u32,
+            props: *const spa_sys::spa_dict,
+        ) {
+            crate::utils::catch_callback_panic("proxy bound props", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                match (props as *const spa::utils::dict::DictRef).as_ref() {
+                    Some(props) => callbacks.bound_props.as_mut().unwrap()(global_id, props),
+                    None => callbacks.bound_props.as_mut().unwrap()(
+                        global_id,
+                        &spa::utils::dict::Dict::new(),
+                    ),
+                }
+            })
         }
 
         unsafe extern "C" fn proxy_removed(data: *mut c_void) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            callbacks.removed.as_ref().unwrap()();
+            crate::utils::catch_callback_panic("proxy removed", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                callbacks.removed.as_mut().unwrap()();
+            })
         }
 
         unsafe extern "C" fn proxy_done(data: *mut c_void, seq: i32) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            callbacks.done.as_ref().unwrap()(seq);
+            crate::utils::catch_callback_panic("proxy done", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                callbacks.done.as_mut().unwrap()(seq);
+            })
         }
 
         unsafe extern "C" fn proxy_error(
@@ -214,9 +686,19 @@ impl<'a> ProxyListenerLocalBuilder<'a> {
             res: i32,
             message: *const c_char,
         ) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            let message = CStr::from_ptr(message).to_str().unwrap();
-            callbacks.error.as_ref().unwrap()(seq, res, message);
+            crate::utils::catch_callback_panic("proxy error", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                let message = if message.is_null() {
+                    Default::default()
+                } else {
+                    CStr::from_ptr(message).to_string_lossy()
+                };
+                callbacks.error.as_mut().unwrap()(seq, res, &message);
+            })
         }
 
         let e = unsafe {
@@ -231,6 +713,14 @@ impl<'a> ProxyListenerLocalBuilder<'a> {
                 e.bound = Some(proxy_bound);
             }
 
+            // The library only reads the events its version knows about, so registering
+            // bound_props is safe with older libraries, which use the bound fallback.
+            #[cfg(feature = "v0_3_77")]
+            if self.cbs.bound_props.is_some() {
+                e.bound = Some(proxy_bound);
+                e.bound_props = Some(proxy_bound_props);
+            }
+
             if self.cbs.removed.is_some() {
                 e.removed = Some(proxy_removed);
             }
@@ -249,17 +739,20 @@ impl<'a> ProxyListenerLocalBuilder<'a> {
         let (listener, data) = unsafe {
             let proxy = &self.proxy.as_ptr();
 
-            let data = Box::into_raw(Box::new(self.cbs));
+            let data = Box::into_raw(Box::new(crate::utils::Guarded::new(self.cbs)));
             let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
             let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
             let funcs: *const pw_sys::pw_proxy_events = e.as_ref().get_ref();
 
-            pw_sys::pw_proxy_add_listener(
-                proxy.cast(),
-                listener_ptr.cast(),
-                funcs.cast(),
-                data as *mut _,
-            );
+            if self.proxy.is_connected() {
+                pw_sys::pw_proxy_add_listener(
+                    proxy.cast(),
+                    listener_ptr.cast(),
+                    funcs.cast(),
+                    data as *mut _,
+                );
+                spa::utils::hook::reset_on_remove(&mut *listener_ptr);
+            }
 
             (listener, Box::from_raw(data))
         };
@@ -268,6 +761,218 @@ impl<'a> ProxyListenerLocalBuilder<'a> {
             events: e,
             listener,
             data,
+            loop_: self.proxy.loop_handle(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, time::Duration};
+
+    use super::*;
+    use crate::{
+        context::Context,
+        main_loop::MainLoop,
+        node::Node,
+        permissions::PermissionFlags,
+        port::Port,
+        properties::{properties, Properties},
+        registry::GlobalObject,
+    };
+
+    fn global(id: u32, type_: ObjectType) -> GlobalObject<&'static spa::utils::dict::DictRef> {
+        GlobalObject {
+            id,
+            permissions: PermissionFlags::all(),
+            version: type_.client_version().unwrap(),
+            type_,
+            props: None,
+        }
+    }
+
+    #[test]
+    fn downcast() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let registry = core.get_registry().unwrap();
+
+        let node: Node = registry
+            .bind(&global(u32::MAX - 1, ObjectType::Node))
+            .unwrap();
+        let proxy = node.upcast();
+        assert!(proxy.downcast_ref::<Node>().is_some());
+        assert!(proxy.downcast_ref::<Port>().is_none());
+
+        // wrong type, the proxy is handed back
+        let proxy = proxy.downcast::<Port>().unwrap_err();
+        let id = proxy.id();
+        let node = proxy.downcast::<Node>().unwrap();
+        assert_eq!(node.upcast_ref().id(), id);
+
+        // older versions are negotiated
+        let node: Node = registry
+            .bind_version(&global(u32::MAX - 1, ObjectType::Node), 1)
+            .unwrap();
+        assert_eq!(node.upcast_ref().get_type(), (ObjectType::Node, 1));
+        assert_eq!(node.upcast_ref().version(), 1);
+        assert!(node.upcast().downcast::<Node>().is_ok());
+    }
+
+    #[test]
+    fn bind_version() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let registry = core.get_registry().unwrap();
+
+        let mut node_global = global(u32::MAX - 1, ObjectType::Node);
+        let node: Node = registry.bind(&node_global).unwrap();
+        assert_eq!(node.upcast_ref().version(), pw_sys::PW_VERSION_NODE);
+
+        // the version announced by the server is used if it is older
+        node_global.version = 2;
+        let node: Node = registry.bind(&node_global).unwrap();
+        assert_eq!(node.upcast_ref().version(), 2);
+
+        // and newer versions are lowered to the one of the crate
+        let node: Node = registry.bind_version(&node_global, 1000).unwrap();
+        assert_eq!(node.upcast_ref().version(), pw_sys::PW_VERSION_NODE);
+    }
+
+    #[test]
+    fn proxy_error() {
+        let err = ProxyError::new(3, -libc::ENOENT, "no global 42");
+        assert_eq!(err.seq, 3);
+        assert_eq!(err.errno, Errno::ENOENT);
+        assert!(err.to_string().starts_with("no global 42 (ENOENT"));
+    }
+
+    #[test]
+    fn bind_unknown_global() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let registry = core.get_registry().unwrap();
+
+        let node: Node = registry
+            .bind(&global(u32::MAX - 1, ObjectType::Node))
+            .unwrap();
+
+        let error = Rc::new(RefCell::new(None));
+        let _listener = node
+            .upcast_ref()
+            .add_listener_local()
+            .proxy_error({
+                let error = error.clone();
+                move |err| *error.borrow_mut() = Some(err)
+            })
+            .register();
+
+        for _ in 0..100 {
+            if error.borrow().is_some() {
+                break;
+            }
+            mainloop.loop_().iterate(Duration::from_millis(10));
         }
+
+        let error = error.borrow_mut().take().expect("error event not received");
+        assert_eq!(error.errno, Errno::ENOENT);
+    }
+
+    #[test]
+    #[cfg(feature = "v0_3_77")]
+    fn bound_props() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let _adapter = context
+            .load_module("libpipewire-module-adapter", None, None)
+            .unwrap();
+        let core = context.connect_self(None).unwrap();
+
+        let node: Node = core
+            .create_object(
+                "adapter",
+                &properties! {
+                    "factory.name" => "support.null-audio-sink",
+                    "node.name" => "pipewire-rs.test.sink",
+                    "media.class" => "Audio/Sink",
+                    "object.linger" => "false",
+                },
+            )
+            .unwrap();
+
+        let bound = Rc::new(RefCell::new(None));
+        let _listener = node
+            .upcast_ref()
+            .add_listener_local()
+            .bound_props({
+                let bound = bound.clone();
+                move |id, props| {
+                    *bound.borrow_mut() = Some((id, props.get("node.name").map(str::to_owned)))
+                }
+            })
+            .register();
+
+        for _ in 0..100 {
+            if bound.borrow().is_some() {
+                break;
+            }
+            mainloop.loop_().iterate(Duration::from_millis(10));
+        }
+
+        let (id, name) = bound
+            .borrow_mut()
+            .take()
+            .expect("bound_props event not received");
+        assert_ne!(id, crate::constants::ID_ANY);
+        assert_eq!(name.as_deref(), Some("pipewire-rs.test.sink"));
+    }
+
+    fn sink_props(factory_name: &str) -> Properties {
+        crate::properties::properties! {
+            "factory.name" => factory_name,
+            "node.name" => "pipewire-rs.test.pending",
+            "media.class" => "Audio/Sink",
+            "object.linger" => "false",
+        }
+    }
+
+    #[test]
+    fn pending_bound() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+
+        let pending = core
+            .create_object_pending::<Node>("adapter", &sink_props("support.null-audio-sink"))
+            .unwrap();
+        assert_eq!(pending.bound_id(), None);
+
+        let id = pending
+            .wait_bound(&mainloop, Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(pending.bound_id(), Some(id));
+
+        // the outcome is kept, without running the loop again
+        assert_eq!(pending.wait_bound(&mainloop, Duration::ZERO).unwrap(), id);
+    }
+
+    #[test]
+    fn pending_error() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+
+        let pending = core
+            .create_object_pending::<Node>("adapter", &sink_props("support.does-not-exist"))
+            .unwrap();
+
+        let err = pending
+            .wait_bound(&mainloop, Duration::from_secs(5))
+            .unwrap_err();
+        assert!(matches!(err, BindError::Failed(_)), "{err:?}");
+        assert_eq!(pending.bound_id(), None);
     }
 }
@@ -0,0 +1,287 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Staying connected to the PipeWire daemon when it restarts.
+//!
+//! When the daemon exits, the core reports it with its
+//! [`disconnected`](crate::core::ListenerLocalBuilder::disconnected) callback, and all the
+//! proxies and listeners created from it stop working.
+//! A [`ReconnectingCore`] then drops them, disconnects the core, and connects a new one on the
+//! same context once the daemon is back, running a setup closure again to recreate them.
+//!
+//! ```no_run
+//! use pipewire::{context::Context, main_loop::MainLoop, reconnect::ReconnectingCore};
+//! use std::time::Duration;
+//!
+//! let mainloop = MainLoop::new(None)?;
+//! let context = Context::new(&mainloop)?;
+//!
+//! let _core = ReconnectingCore::new(
+//!     &context,
+//!     mainloop.loop_(),
+//!     None,
+//!     Duration::from_secs(1),
+//!     |core| {
+//!         // everything created from the core lives in the state returned by the closure
+//!         let registry = core.get_registry()?;
+//!         let listener = registry
+//!             .add_listener_local()
+//!             .global(|global| println!("new global {}", global.id))
+//!             .register();
+//!         Ok((registry, listener))
+//!     },
+//! )?;
+//!
+//! mainloop.run();
+//! # Ok::<(), pipewire::Error>(())
+//! ```
+
+use std::{
+    cell::{Cell, Ref, RefCell},
+    fmt,
+    rc::Rc,
+    time::Duration,
+};
+
+use crate::{
+    context::Context,
+    core::{self, Core},
+    loop_::{LoopRef, TimerSource},
+    properties::Properties,
+    Error,
+};
+
+/// A connection to the PipeWire daemon which is established again whenever it is lost.
+///
+/// Each time a core is connected, the setup closure is called with it to create the state of
+/// the connection, such as a registry and its listeners. The state is dropped when the
+/// connection is lost, before the core is disconnected.
+///
+/// Connecting is retried every `retry_interval` while the daemon is unreachable,
+/// and a lost connection is noticed at the next retry.
+pub struct ReconnectingCore<'l, S> {
+    inner: Rc<Inner<S>>,
+    _timer: TimerSource<'l>,
+}
+
+struct Inner<S> {
+    context: Context,
+    properties: Option<Properties>,
+    #[allow(clippy::type_complexity)]
+    setup: RefCell<Box<dyn FnMut(&Core) -> Result<S, Error>>>,
+    connection: RefCell<Option<Connection<S>>>,
+    lost: Cell<bool>,
+}
+
+/// A connected core, with the state created from it.
+struct Connection<S> {
+    state: S,
+    listener: core::Listener,
+    core: Core,
+}
+
+impl<S> Connection<S> {
+    fn close(self) {
+        let Connection {
+            state,
+            listener,
+            core,
+        } = self;
+        drop(state);
+        drop(listener);
+        // fails if clones of the core outlive the state, it is then disconnected with the context
+        let _ = core.disconnect();
+    }
+}
+
+impl<S: 'static> Inner<S> {
+    /// Drop the lost connection, if any, and try to connect again.
+    fn reconnect(self: &Rc<Self>) {
+        let lost = self.connection.borrow_mut().take();
+        if let Some(connection) = lost {
+            connection.close();
+        }
+        self.lost.set(false);
+
+        if let Ok(connection) = self.connect() {
+            *self.connection.borrow_mut() = Some(connection);
+        }
+    }
+
+    fn connect(self: &Rc<Self>) -> Result<Connection<S>, Error> {
+        let core = self.context.connect(self.properties.clone())?;
+        let listener = core
+            .add_listener_local()
+            .disconnected({
+                let inner = Rc::downgrade(self);
+                move || {
+                    if let Some(inner) = inner.upgrade() {
+                        inner.lost.set(true);
+                    }
+                }
+            })
+            .register();
+
+        let state = (self.setup.borrow_mut())(&core);
+        match state {
+            Ok(state) => Ok(Connection {
+                state,
+                listener,
+                core,
+            }),
+            Err(err) => {
+                drop(listener);
+                let _ = core.disconnect();
+                Err(err)
+            }
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connection.borrow().is_some() && !self.lost.get()
+    }
+}
+
+impl<'l, S: 'static> ReconnectingCore<'l, S> {
+    /// Connect to the daemon with [`Context::connect`] and `properties`, then call `setup`.
+    ///
+    /// If connecting or `setup` fails, this is retried every `retry_interval` on `loop_`,
+    /// which must be the loop of the context.
+    pub fn new<F>(
+        context: &Context,
+        loop_: &'l LoopRef,
+        properties: Option<Properties>,
+        retry_interval: Duration,
+        setup: F,
+    ) -> Result<Self, Error>
+    where
+        F: FnMut(&Core) -> Result<S, Error> + 'static,
+    {
+        let inner = Rc::new(Inner {
+            context: context.clone(),
+            properties,
+            setup: RefCell::new(Box::new(setup)),
+            connection: RefCell::new(None),
+            lost: Cell::new(false),
+        });
+
+        let timer = loop_.add_timer({
+            let inner = Rc::downgrade(&inner);
+            move |_| {
+                let Some(inner) = inner.upgrade() else {
+                    return;
+                };
+                if !inner.is_connected() {
+                    inner.reconnect();
+                }
+            }
+        })?;
+        timer
+            .update_timer(Some(retry_interval), Some(retry_interval))
+            .into_sync_result()?;
+
+        inner.reconnect();
+
+        Ok(Self {
+            inner,
+            _timer: timer,
+        })
+    }
+
+    /// Whether the daemon is currently connected.
+    pub fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    /// The currently connected core, if any.
+    pub fn core(&self) -> Option<Core> {
+        self.inner
+            .connection
+            .borrow()
+            .as_ref()
+            .map(|connection| connection.core.clone())
+    }
+
+    /// The state returned by the setup closure for the current connection, if any.
+    pub fn state(&self) -> Option<Ref<'_, S>> {
+        Ref::filter_map(self.inner.connection.borrow(), |connection| {
+            connection.as_ref().map(|connection| &connection.state)
+        })
+        .ok()
+    }
+}
+
+impl<S> Drop for ReconnectingCore<'_, S> {
+    fn drop(&mut self) {
+        let connection = self.inner.connection.borrow_mut().take();
+        if let Some(connection) = connection {
+            connection.close();
+        }
+    }
+}
+
+impl<S> fmt::Debug for ReconnectingCore<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReconnectingCore")
+            .field("connected", &self.inner.connection.borrow().is_some())
+            .field("lost", &self.inner.lost.get())
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "integration-tests"))]
+mod tests {
+    use super::*;
+    use crate::{main_loop::MainLoop, properties::properties, utils::spawn_daemon};
+
+    #[test]
+    fn daemon_restart() {
+        let name = format!("pipewire-rs-test-{}", std::process::id());
+        let mut daemon = spawn_daemon(&name);
+
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let connections = Rc::new(Cell::new(0));
+        let disconnects = Rc::new(Cell::new(0));
+
+        let reconnecting = ReconnectingCore::new(
+            &context,
+            mainloop.loop_(),
+            Some(properties! {
+                *crate::keys::REMOTE_NAME => name.clone(),
+            }),
+            Duration::from_millis(50),
+            {
+                let connections = connections.clone();
+                let disconnects = disconnects.clone();
+                move |core| {
+                    connections.set(connections.get() + 1);
+                    let disconnects = disconnects.clone();
+                    let listener = core
+                        .add_listener_local()
+                        .disconnected(move || disconnects.set(disconnects.get() + 1))
+                        .register();
+                    Ok(listener)
+                }
+            },
+        )
+        .unwrap();
+
+        mainloop.run_until(|_| connections.get() == 1);
+        assert!(reconnecting.is_connected());
+
+        daemon.kill().unwrap();
+        daemon.wait().unwrap();
+        mainloop.run_until(|_| disconnects.get() == 1);
+        assert!(!reconnecting.is_connected());
+
+        let mut daemon = spawn_daemon(&name);
+        mainloop.run_until(|_| connections.get() == 2);
+        assert!(reconnecting.is_connected());
+        assert!(reconnecting.core().is_some());
+
+        drop(reconnecting);
+        daemon.kill().unwrap();
+        daemon.wait().unwrap();
+    }
+}
@@ -4,32 +4,40 @@
 use libc::{c_char, c_void};
 
 use std::{
+    cell::RefCell,
     ffi::{CStr, CString},
     mem,
     pin::Pin,
     ptr,
+    rc::Rc,
 };
 
 use crate::{
+    core::CoreRef,
+    keys,
+    main_loop::MainLoop,
     permissions::PermissionFlags,
     properties::Properties,
-    proxy::{Proxy, ProxyT},
+    proxy::{impl_listener, Proxy, ProxyT},
+    thread_loop::LoopHandle,
     types::ObjectType,
     Error,
 };
 
 #[derive(Debug)]
 pub struct Registry {
-    ptr: ptr::NonNull<pw_sys::pw_registry>,
+    proxy: Proxy,
 }
 
 impl Registry {
     pub(crate) fn new(ptr: ptr::NonNull<pw_sys::pw_registry>) -> Self {
-        Registry { ptr }
+        Registry {
+            proxy: Proxy::new(ptr.cast()),
+        }
     }
 
     fn as_ptr(&self) -> *mut pw_sys::pw_registry {
-        self.ptr.as_ptr()
+        self.proxy.as_ptr().cast()
     }
 
     // TODO: add non-local version when we'll bind pw_thread_loop_start()
@@ -41,16 +49,35 @@ impl Registry {
         }
     }
 
+    /// Bind to the global `object`, creating a proxy of type `T` for it.
+    ///
+    /// The proxy uses the highest version of the interface supported by both the server,
+    /// as announced in the [`version`](GlobalObject::version) of the global, and this crate.
+    /// The negotiated version is available with [`Proxy::version`].
     pub fn bind<T: ProxyT, P: AsRef<spa::utils::dict::DictRef>>(
         &self,
         object: &GlobalObject<P>,
     ) -> Result<T, Error> {
+        self.bind_version(object, object.version)
+    }
+
+    /// Bind to the global `object` with the given interface `version`.
+    ///
+    /// Versions higher than the one supported by this crate are lowered to it.
+    /// Methods and events added in later versions of the interface are not available
+    /// on the resulting proxy: the server ignores the former, and never sends the latter.
+    pub fn bind_version<T: ProxyT, P: AsRef<spa::utils::dict::DictRef>>(
+        &self,
+        object: &GlobalObject<P>,
+        version: u32,
+    ) -> Result<T, Error> {
+        let registry: *mut pw_sys::pw_registry = self.proxy.checked_ptr()?.cast();
         let proxy = unsafe {
             let type_ = CString::new(object.type_.to_str()).unwrap();
-            let version = object.type_.client_version();
+            let version = version.min(object.type_.client_version().unwrap_or(version));
 
             let proxy = spa::spa_interface_call_method!(
-                self.as_ptr(),
+                registry,
                 pw_sys::pw_registry_methods,
                 bind,
                 object.id,
@@ -64,11 +91,16 @@ impl Registry {
 
         let proxy = ptr::NonNull::new(proxy.cast()).ok_or(Error::NoMemory)?;
 
-        Proxy::new(proxy).downcast().map_err(|(_, e)| e)
+        Proxy::new(proxy)
+            .downcast()
+            .map_err(|_| Error::WrongProxyType)
     }
 
     /// Attempt to destroy the global object with the specified id on the remote.
     pub fn destroy_global(&self, global_id: u32) -> spa::utils::result::SpaResult {
+        if !self.proxy.is_connected() {
+            return spa::utils::result::SpaResult::from_c(-libc::EPIPE);
+        }
         let result = unsafe {
             spa::spa_interface_call_method!(
                 self.as_ptr(),
@@ -80,18 +112,45 @@ impl Registry {
 
         spa::utils::result::SpaResult::from_c(result)
     }
-}
 
-impl Drop for Registry {
-    fn drop(&mut self) {
-        unsafe {
-            pw_sys::pw_proxy_destroy(self.as_ptr().cast());
-        }
+    /// Collect the globals currently announced by the registry.
+    ///
+    /// This runs `main_loop` until `core` has processed a roundtrip, so every global existing on the
+    /// remote when the registry was created is returned, in the order they were announced, as well as the
+    /// globals added in the meantime. Globals removed before the roundtrip completed are not returned.
+    ///
+    /// The remote announces the existing globals only once, so this must be called before the loop
+    /// is run after getting the registry from [`CoreRef::get_registry`].
+    ///
+    /// As it runs `main_loop`, this fails with `EDEADLK` when called from a callback of a loop,
+    /// and with `ECANCELED` if `main_loop` is quit before the roundtrip completed.
+    pub fn snapshot(
+        &self,
+        core: &CoreRef,
+        main_loop: &MainLoop,
+    ) -> Result<Vec<GlobalObject<Properties>>, Error> {
+        let globals = Rc::new(RefCell::new(Vec::<GlobalObject<Properties>>::new()));
+        let _listener = self
+            .add_listener_local()
+            .global({
+                let globals = globals.clone();
+                move |global| globals.borrow_mut().push(global.to_owned())
+            })
+            .global_remove({
+                let globals = globals.clone();
+                move |id| globals.borrow_mut().retain(|global| global.id != id)
+            })
+            .register();
+
+        core.roundtrip(main_loop)?;
+
+        let globals = globals.take();
+        Ok(globals)
     }
 }
 
-type GlobalCallback = dyn Fn(&GlobalObject<&spa::utils::dict::DictRef>);
-type GlobalRemoveCallback = dyn Fn(u32);
+type GlobalCallback = dyn FnMut(&GlobalObject<&spa::utils::dict::DictRef>);
+type GlobalRemoveCallback = dyn FnMut(u32);
 
 #[derive(Default)]
 struct ListenerLocalCallbacks {
@@ -99,6 +158,11 @@ struct ListenerLocalCallbacks {
     global_remove: Option<Box<GlobalRemoveCallback>>,
 }
 
+/// Builder of a listener for the events of a [`Registry`], see [`Registry::add_listener_local`].
+///
+/// **Events emitted while one of the callbacks is running are lost.** The callbacks are never
+/// called re-entrantly: an event received when a callback iterates the loop, e.g. to wait for a
+/// reply, or emitted synchronously by a method it calls, is dropped without notice.
 pub struct ListenerLocalBuilder<'a> {
     registry: &'a Registry,
     cbs: ListenerLocalCallbacks,
@@ -110,11 +174,15 @@ pub struct Listener {
     events: Pin<Box<pw_sys::pw_registry_events>>,
     listener: Pin<Box<spa_sys::spa_hook>>,
     #[allow(dead_code)]
-    data: Box<ListenerLocalCallbacks>,
+    data: Box<crate::utils::Guarded<ListenerLocalCallbacks>>,
+    loop_: LoopHandle,
 }
 
+impl_listener!(Listener);
+
 impl Drop for Listener {
     fn drop(&mut self) {
+        let _lock = self.loop_.lock();
         spa::utils::hook::remove(*self.listener);
     }
 }
@@ -123,16 +191,28 @@ impl<'a> ListenerLocalBuilder<'a> {
     #[must_use]
     pub fn global<F>(mut self, global: F) -> Self
     where
-        F: Fn(&GlobalObject<&spa::utils::dict::DictRef>) + 'static,
+        F: FnMut(&GlobalObject<&spa::utils::dict::DictRef>) + 'static,
     {
         self.cbs.global = Some(Box::new(global));
         self
     }
 
+    /// Set the callback of the `global` event, with the global copied by
+    /// [`GlobalObject::to_owned`] so it can be kept, such as in a cache of the globals.
+    ///
+    /// This replaces the callback set with [`global`](Self::global).
+    #[must_use]
+    pub fn global_owned<F>(self, mut global: F) -> Self
+    where
+        F: FnMut(GlobalObject<Properties>) + 'static,
+    {
+        self.global(move |obj| global(obj.to_owned()))
+    }
+
     #[must_use]
     pub fn global_remove<F>(mut self, global_remove: F) -> Self
     where
-        F: Fn(u32) + 'static,
+        F: FnMut(u32) + 'static,
     {
         self.cbs.global_remove = Some(Box::new(global_remove));
         self
@@ -148,15 +228,27 @@ impl<'a> ListenerLocalBuilder<'a> {
             version: u32,
             props: *const spa_sys::spa_dict,
         ) {
-            let type_ = CStr::from_ptr(type_).to_str().unwrap();
-            let obj = GlobalObject::new(id, permissions, type_, version, props);
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            callbacks.global.as_ref().unwrap()(&obj);
+            crate::utils::catch_callback_panic("registry global", (), || {
+                let type_ = CStr::from_ptr(type_).to_string_lossy();
+                let obj = GlobalObject::new(id, permissions, &type_, version, props);
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                callbacks.global.as_mut().unwrap()(&obj);
+            })
         }
 
         unsafe extern "C" fn registry_events_global_remove(data: *mut c_void, id: u32) {
-            let callbacks = (data as *mut ListenerLocalCallbacks).as_ref().unwrap();
-            callbacks.global_remove.as_ref().unwrap()(id);
+            crate::utils::catch_callback_panic("registry global remove", (), || {
+                let Some(mut callbacks) =
+                    crate::utils::Guarded::<ListenerLocalCallbacks>::enter_raw(data)
+                else {
+                    return;
+                };
+                callbacks.global_remove.as_mut().unwrap()(id);
+            })
         }
 
         let e = unsafe {
@@ -175,18 +267,21 @@ impl<'a> ListenerLocalBuilder<'a> {
 
         let (listener, data) = unsafe {
             let ptr = self.registry.as_ptr();
-            let data = Box::into_raw(Box::new(self.cbs));
+            let data = Box::into_raw(Box::new(crate::utils::Guarded::new(self.cbs)));
             let mut listener: Pin<Box<spa_sys::spa_hook>> = Box::pin(mem::zeroed());
             let listener_ptr: *mut spa_sys::spa_hook = listener.as_mut().get_unchecked_mut();
 
-            spa::spa_interface_call_method!(
-                ptr,
-                pw_sys::pw_registry_methods,
-                add_listener,
-                listener_ptr.cast(),
-                e.as_ref().get_ref(),
-                data as *mut _
-            );
+            if self.registry.proxy.is_connected() {
+                spa::spa_interface_call_method!(
+                    ptr,
+                    pw_sys::pw_registry_methods,
+                    add_listener,
+                    listener_ptr.cast(),
+                    e.as_ref().get_ref(),
+                    data as *mut _
+                );
+                spa::utils::hook::reset_on_remove(&mut *listener_ptr);
+            }
 
             (listener, Box::from_raw(data))
         };
@@ -195,6 +290,7 @@ impl<'a> ListenerLocalBuilder<'a> {
             events: e,
             listener,
             data,
+            loop_: self.registry.proxy.loop_handle(),
         }
     }
 }
@@ -216,7 +312,7 @@ impl GlobalObject<&spa::utils::dict::DictRef> {
         version: u32,
         props: *const spa_sys::spa_dict,
     ) -> Self {
-        let type_ = ObjectType::from_str(type_);
+        let type_ = ObjectType::from_interface_name(type_);
         let permissions = PermissionFlags::from_bits_retain(permissions);
         let props = ptr::NonNull::new(props.cast_mut())
             .map(|ptr| ptr.cast::<spa::utils::dict::DictRef>().as_ref());
@@ -232,6 +328,20 @@ impl GlobalObject<&spa::utils::dict::DictRef> {
 }
 
 impl<P: AsRef<spa::utils::dict::DictRef>> GlobalObject<P> {
+    /// The `object.serial` of the global, from its properties.
+    ///
+    /// Ids are reused by the server once their object is destroyed, while serials are not:
+    /// the serial tells apart objects announced with the same id over time.
+    /// This is `None` if the global has no properties, or if the server is too old to set it.
+    pub fn serial(&self) -> Option<u64> {
+        crate::utils::object_serial(self.props.as_ref().map(AsRef::as_ref))
+    }
+
+    /// Copy the global, including its properties, so it can outlive the `global` event.
+    ///
+    /// The properties are copied byte for byte: values which are not valid UTF-8 are kept,
+    /// but are skipped by [`PropertiesRef::get`](crate::properties::PropertiesRef::get) and the other `&str` accessors of the dictionary.
+    /// They can still be read with [`DictRef::iter_cstr`](spa::utils::dict::DictRef::iter_cstr).
     pub fn to_owned(&self) -> GlobalObject<Properties> {
         GlobalObject {
             id: self.id,
@@ -246,27 +356,439 @@ impl<P: AsRef<spa::utils::dict::DictRef>> GlobalObject<P> {
     }
 }
 
+/// A node in a list of globals, such as a [`Registry::snapshot`], see [`ports_of_node`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKey {
+    /// The node with this global id, such as [`StreamRef::node_id`](crate::stream::StreamRef::node_id).
+    Id(u32),
+    /// The node with this `object.serial`.
+    Serial(u64),
+}
+
+/// The port globals of `node` among `globals`, in the order of `globals`.
+///
+/// Ports are matched on their `node.id` property, which is the global id of their node, never its serial.
+/// A node given by [`NodeKey::Serial`] is first looked up among `globals`, and no ports are returned
+/// if it is not found there.
+pub fn ports_of_node<P: AsRef<spa::utils::dict::DictRef>>(
+    globals: &[GlobalObject<P>],
+    node: NodeKey,
+) -> Vec<&GlobalObject<P>> {
+    let node_id = match node {
+        NodeKey::Id(id) => id,
+        NodeKey::Serial(serial) => {
+            let Some(node) = globals
+                .iter()
+                .find(|global| global.type_ == ObjectType::Node && global.serial() == Some(serial))
+            else {
+                return Vec::new();
+            };
+            node.id
+        }
+    };
+
+    globals
+        .iter()
+        .filter(|global| {
+            global.type_ == ObjectType::Port
+                && global
+                    .props
+                    .as_ref()
+                    .and_then(|props| props.as_ref().parse::<u32>(*keys::NODE_ID))
+                    .and_then(Result::ok)
+                    == Some(node_id)
+        })
+        .collect()
+}
+
+/// The port of `node` among `globals` in `direction`, with the channel or name `name`.
+///
+/// `name` is compared to the `audio.channel` of the port, such as `FL`, and then to its `port.name`,
+/// such as `monitor_FL`, for ports without a channel or when no channel matches.
+/// The direction is the `port.direction` of the port, `in` or `out`.
+pub fn find_port<'a, P: AsRef<spa::utils::dict::DictRef>>(
+    globals: &'a [GlobalObject<P>],
+    node: NodeKey,
+    direction: spa::utils::Direction,
+    name: &str,
+) -> Option<&'a GlobalObject<P>> {
+    let direction = match direction {
+        spa::utils::Direction::Input => "in",
+        spa::utils::Direction::Output => "out",
+        _ => return None,
+    };
+    let ports: Vec<_> = ports_of_node(globals, node)
+        .into_iter()
+        .filter(|port| port_prop(port, *keys::PORT_DIRECTION) == Some(direction))
+        .collect();
+
+    // a channel takes precedence over a port name, even for a port found later
+    [*keys::AUDIO_CHANNEL, *keys::PORT_NAME]
+        .into_iter()
+        .find_map(|key| {
+            ports
+                .iter()
+                .copied()
+                .find(|port| port_prop(port, key) == Some(name))
+        })
+}
+
+fn port_prop<'a, P: AsRef<spa::utils::dict::DictRef>>(
+    port: &'a GlobalObject<P>,
+    key: &str,
+) -> Option<&'a str> {
+    port.props.as_ref()?.as_ref().get(key)
+}
+
+/// Serializes the global with the keys used by `pw-dump`, such as
+/// `{"id":0,"type":"PipeWire:Interface:Core","version":4,"permissions":["r","w","x","m"],"props":{..}}`.
+#[cfg(feature = "serde")]
+impl<P: AsRef<spa::utils::dict::DictRef>> serde::Serialize for GlobalObject<P> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut global = serializer.serialize_struct("GlobalObject", 5)?;
+        global.serialize_field("id", &self.id)?;
+        global.serialize_field("type", self.type_.to_str())?;
+        global.serialize_field("version", &self.version)?;
+        global.serialize_field("permissions", &crate::utils::FlagNames(self.permissions))?;
+        global.serialize_field("props", &self.props.as_ref().map(AsRef::as_ref))?;
+        global.end()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::cell::Cell;
+
     #[test]
     fn set_object_type() {
         assert_eq!(
-            ObjectType::from_str("PipeWire:Interface:Client"),
+            ObjectType::from_interface_name("PipeWire:Interface:Client"),
             ObjectType::Client
         );
         assert_eq!(ObjectType::Client.to_str(), "PipeWire:Interface:Client");
-        assert_eq!(ObjectType::Client.client_version(), 3);
+        assert_eq!(ObjectType::Client.client_version(), Some(3));
 
         let o = ObjectType::Other("PipeWire:Interface:Badger".to_string());
-        assert_eq!(ObjectType::from_str("PipeWire:Interface:Badger"), o);
+        assert_eq!(
+            ObjectType::from_interface_name("PipeWire:Interface:Badger"),
+            o
+        );
         assert_eq!(o.to_str(), "PipeWire:Interface:Badger");
     }
 
     #[test]
-    #[should_panic(expected = "Invalid object type")]
-    fn client_version_panic() {
-        let o = ObjectType::Other("PipeWire:Interface:Badger".to_string());
-        assert_eq!(o.client_version(), 0);
+    fn snapshot() {
+        let mainloop = crate::main_loop::MainLoop::new(None).unwrap();
+        let context = crate::context::Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let registry = core.get_registry().unwrap();
+
+        let globals = registry.snapshot(&core, &mainloop).unwrap();
+        // the core of the context and the client of the connection are always announced
+        let core_global = globals
+            .iter()
+            .find(|global| global.type_ == ObjectType::Core)
+            .expect("core global not announced");
+        assert_eq!(core_global.id, crate::core::PW_ID_CORE);
+        assert!(globals
+            .iter()
+            .any(|global| global.type_ == ObjectType::Client));
+
+        // every global is only reported once
+        let mut ids: Vec<_> = globals.iter().map(|global| global.id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), globals.len());
+    }
+
+    #[test]
+    fn snapshot_in_loop() {
+        let mainloop = crate::main_loop::MainLoop::new(None).unwrap();
+        let context = crate::context::Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let registry = core.get_registry().unwrap();
+
+        // the loop cannot be run again from one of its callbacks
+        let res = Rc::new(RefCell::new(None));
+        let _idle = mainloop
+            .loop_()
+            .add_idle(true, {
+                let mainloop = mainloop.clone();
+                let core = core.clone();
+                let res = res.clone();
+                move || {
+                    *res.borrow_mut() = Some(registry.snapshot(&core, &mainloop).map(|_| ()));
+                    mainloop.quit();
+                }
+            })
+            .unwrap();
+        mainloop.run();
+
+        let err = res.take().unwrap().unwrap_err();
+        assert_eq!(err.errno(), Some(nix::errno::Errno::EDEADLK));
+    }
+
+    #[test]
+    fn to_owned() {
+        let mut dict = spa::utils::dict::Dict::new();
+        dict.insert("node.name", "test");
+        dict.insert("node.description", b"caf\xe9".to_vec());
+        let global = GlobalObject {
+            id: 42,
+            permissions: PermissionFlags::R | PermissionFlags::X,
+            type_: ObjectType::Node,
+            version: 3,
+            props: Some(&*dict),
+        };
+
+        let owned = global.to_owned();
+        drop(dict);
+        assert_eq!(owned.id, 42);
+        assert_eq!(owned.permissions, PermissionFlags::R | PermissionFlags::X);
+        assert_eq!(owned.type_, ObjectType::Node);
+        assert_eq!(owned.version, 3);
+        let props = owned.props.unwrap();
+        assert_eq!(props.get("node.name"), Some("test"));
+        // non UTF-8 values are copied but not returned as strings
+        assert_eq!(props.get("node.description"), None);
+        assert!(props
+            .dict()
+            .iter_cstr()
+            .any(|(k, v)| k.to_bytes() == b"node.description" && v.to_bytes() == b"caf\xe9"));
+    }
+
+    #[test]
+    fn serial() {
+        let mut dict = spa::utils::dict::Dict::new();
+        let mut global = GlobalObject {
+            id: 42,
+            permissions: PermissionFlags::all(),
+            type_: ObjectType::Node,
+            version: 3,
+            props: None,
+        };
+        assert_eq!(global.serial(), None);
+
+        dict.insert("node.name", "test");
+        global.props = Some(&*dict);
+        assert_eq!(global.serial(), None);
+
+        let mut dict = spa::utils::dict::Dict::new();
+        dict.insert("object.serial", "not a serial");
+        global.props = Some(&*dict);
+        assert_eq!(global.serial(), None);
+
+        let mut dict = spa::utils::dict::Dict::new();
+        dict.insert("object.serial", "1234");
+        global.props = Some(&*dict);
+        assert_eq!(global.serial(), Some(1234));
+        assert_eq!(global.to_owned().serial(), Some(1234));
+    }
+
+    /// Globals announced with the same id over time have different serials.
+    #[test]
+    #[cfg(feature = "v0_3_41")]
+    fn serial_id_reuse() {
+        let mainloop = crate::main_loop::MainLoop::new(None).unwrap();
+        let context = crate::context::Context::new(&mainloop).unwrap();
+        let _adapter = context
+            .load_module("libpipewire-module-adapter", None, None)
+            .unwrap();
+        let core = context.connect_self(None).unwrap();
+        let registry = core.get_registry().unwrap();
+
+        let sinks = Rc::new(RefCell::new(Vec::new()));
+        let removed = Rc::new(RefCell::new(Vec::new()));
+        let _listener = registry
+            .add_listener_local()
+            .global_owned({
+                let sinks = sinks.clone();
+                move |global| {
+                    let name = global
+                        .props
+                        .as_ref()
+                        .and_then(|props| props.get("node.name"));
+                    if name == Some("pipewire-rs.test.serial") {
+                        sinks.borrow_mut().push(global);
+                    }
+                }
+            })
+            .global_remove({
+                let removed = removed.clone();
+                move |id| removed.borrow_mut().push(id)
+            })
+            .register();
+
+        let create_sink = || {
+            core.create_object::<crate::node::Node>(
+                "adapter",
+                &crate::properties::properties! {
+                    "factory.name" => "support.null-audio-sink",
+                    "node.name" => "pipewire-rs.test.serial",
+                    "media.class" => "Audio/Sink",
+                    "object.linger" => "false",
+                },
+            )
+            .unwrap()
+        };
+
+        let _sink = create_sink();
+        mainloop.run_until(|_| !sinks.borrow().is_empty());
+        let first_id = sinks.borrow()[0].id;
+        registry.destroy_global(first_id).into_result().unwrap();
+        mainloop.run_until(|_| removed.borrow().contains(&first_id));
+
+        let _sink = create_sink();
+        mainloop.run_until(|_| sinks.borrow().len() == 2);
+
+        let sinks = sinks.borrow();
+        let (first, second) = (sinks[0].serial(), sinks[1].serial());
+        assert!(first.is_some());
+        assert!(second.is_some());
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn global_owned() {
+        let mainloop = crate::main_loop::MainLoop::new(None).unwrap();
+        let context = crate::context::Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let registry = core.get_registry().unwrap();
+
+        let globals = Rc::new(RefCell::new(Vec::new()));
+        let _listener = registry
+            .add_listener_local()
+            .global_owned({
+                let globals = globals.clone();
+                move |global| globals.borrow_mut().push(global)
+            })
+            .register();
+
+        let done = Rc::new(Cell::new(false));
+        let pending = core.sync(0).unwrap();
+        let _core_listener = core
+            .add_listener_local()
+            .done({
+                let done = done.clone();
+                move |_, seq| {
+                    if seq == pending {
+                        done.set(true);
+                    }
+                }
+            })
+            .register();
+        mainloop.run_until(|_| done.get());
+
+        let globals = globals.borrow();
+        let core_global = globals
+            .iter()
+            .find(|global| global.id == crate::core::PW_ID_CORE)
+            .expect("core global not announced");
+        assert_eq!(core_global.type_, ObjectType::Core);
+        assert!(core_global.props.is_some());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let mainloop = crate::main_loop::MainLoop::new(None).unwrap();
+        let context = crate::context::Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let registry = core.get_registry().unwrap();
+
+        let globals = registry.snapshot(&core, &mainloop).unwrap();
+        let json = serde_json::to_value(&globals).unwrap();
+        let core_global = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|global| global["id"] == crate::core::PW_ID_CORE)
+            .expect("core global not serialized");
+        assert_eq!(core_global["type"], "PipeWire:Interface:Core");
+        assert!(core_global["permissions"]
+            .as_array()
+            .unwrap()
+            .contains(&"r".into()));
+        assert!(core_global["props"].is_object());
+    }
+
+    fn global(id: u32, type_: ObjectType, props: Properties) -> GlobalObject<Properties> {
+        GlobalObject {
+            id,
+            permissions: PermissionFlags::all(),
+            type_,
+            version: 3,
+            props: Some(props),
+        }
+    }
+
+    fn port(
+        id: u32,
+        node: u32,
+        direction: &str,
+        name: &str,
+        channel: &str,
+    ) -> GlobalObject<Properties> {
+        let mut props = crate::properties::properties! {
+            "node.id" => node.to_string(),
+            "port.direction" => direction,
+            "port.name" => name,
+        };
+        if !channel.is_empty() {
+            props.insert("audio.channel", channel);
+        }
+        global(id, ObjectType::Port, props)
+    }
+
+    #[test]
+    fn ports() {
+        use spa::utils::Direction;
+
+        let globals = [
+            global(
+                40,
+                ObjectType::Node,
+                crate::properties::properties! { "object.serial" => "1040" },
+            ),
+            port(41, 40, "in", "playback_FL", "FL"),
+            port(42, 40, "out", "monitor_FL", "FL"),
+            // a port of another node
+            port(43, 50, "out", "output_FL", "FL"),
+            port(44, 40, "out", "FR", ""),
+            port(45, 40, "out", "monitor_FR", "FR"),
+            global(
+                46,
+                ObjectType::Link,
+                crate::properties::properties! { "node.id" => "40" },
+            ),
+        ];
+        let ids = |ports: Vec<&GlobalObject<Properties>>| -> Vec<u32> {
+            ports.iter().map(|port| port.id).collect()
+        };
+
+        assert_eq!(
+            ids(ports_of_node(&globals, NodeKey::Id(40))),
+            [41, 42, 44, 45]
+        );
+        assert_eq!(
+            ids(ports_of_node(&globals, NodeKey::Serial(1040))),
+            [41, 42, 44, 45]
+        );
+        assert!(ports_of_node(&globals, NodeKey::Serial(40)).is_empty());
+        assert!(ports_of_node(&globals, NodeKey::Id(1040)).is_empty());
+
+        let find = |direction, name| {
+            find_port(&globals, NodeKey::Id(40), direction, name).map(|port| port.id)
+        };
+        assert_eq!(find(Direction::Input, "FL"), Some(41));
+        assert_eq!(find(Direction::Output, "FL"), Some(42));
+        assert_eq!(find(Direction::Output, "monitor_FL"), Some(42));
+        // the channel of port 45 is used before the name of port 44
+        assert_eq!(find(Direction::Output, "FR"), Some(45));
+        assert_eq!(find(Direction::Input, "FR"), None);
+        assert_eq!(find(Direction::Output, "RL"), None);
     }
 }
@@ -0,0 +1,80 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! The security context interface, exported by `libpipewire-module-protocol-native`.
+//!
+//! A security context lets a trusted client, such as a sandbox manager, create a new listening
+//! socket on the server. Clients connecting through that socket get the properties of the
+//! context applied, which the session manager uses to restrict their permissions.
+
+use std::os::fd::{AsRawFd, BorrowedFd};
+
+use crate::{
+    proxy::{impl_transparent_proxy, Proxy, ProxyT},
+    types::ObjectType,
+    Error,
+};
+use spa::{spa_interface_call_method, utils::result::SpaResult};
+
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct SecurityContext {
+    proxy: Proxy,
+}
+
+impl ProxyT for SecurityContext {
+    fn type_() -> ObjectType {
+        ObjectType::SecurityContext
+    }
+
+    fn upcast(self) -> Proxy {
+        self.proxy
+    }
+
+    fn upcast_ref(&self) -> &Proxy {
+        &self.proxy
+    }
+
+    unsafe fn from_proxy_unchecked(proxy: Proxy) -> Self
+    where
+        Self: Sized,
+    {
+        Self { proxy }
+    }
+}
+
+impl_transparent_proxy!(SecurityContext);
+
+impl SecurityContext {
+    /// Create a new security context.
+    ///
+    /// The server accepts new clients on `listen_fd`, a bound and listening unix socket, and
+    /// applies `properties` to them, such as `pipewire.sec.engine` and `pipewire.access`.
+    /// The context is destroyed when the peer of `close_fd` is closed, which is how the caller
+    /// revokes it.
+    ///
+    /// Both fds are sent along with the message when the connection is flushed, so they must
+    /// stay open until then, for example until a [`CoreRef::sync`](crate::core::CoreRef::sync)
+    /// roundtrip completed. The server gets its own copies, the caller keeps ownership of
+    /// `listen_fd` and `close_fd`.
+    pub fn create(
+        &self,
+        listen_fd: BorrowedFd,
+        close_fd: BorrowedFd,
+        properties: &spa::utils::dict::DictRef,
+    ) -> Result<(), Error> {
+        let res = unsafe {
+            spa_interface_call_method!(
+                self.proxy.checked_ptr()?,
+                pw_sys::pw_security_context_methods,
+                create,
+                listen_fd.as_raw_fd(),
+                close_fd.as_raw_fd(),
+                properties.as_raw_ptr()
+            )
+        };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
+    }
+}
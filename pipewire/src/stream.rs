@@ -3,24 +3,39 @@
 
 //! Pipewire Stream
 
-use crate::buffer::Buffer;
+use crate::buffer::{Buffer, BufferRef};
 use crate::{
     core::Core,
     error::Error,
     properties::{Properties, PropertiesRef},
+    registry::{ports_of_node, GlobalObject, NodeKey},
+    thread_loop::LoopHandle,
 };
 use bitflags::bitflags;
+use spa::support::io::{IoClock, IoPosition, IoType};
 use spa::utils::result::SpaResult;
 use std::{
+    borrow::Cow,
+    cell,
+    collections::{HashMap, VecDeque},
     ffi::{self, CStr, CString},
     fmt::Debug,
     mem, os,
     pin::Pin,
     ptr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
 };
 
-#[derive(Debug, PartialEq)]
+/// The state of a [`Stream`].
+///
+/// States compare equal including the error message, use [`is_error`](Self::is_error)
+/// to check for any error.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum StreamState {
+    /// The stream is in error, with the error message.
     Error(String),
     Unconnected,
     Connecting,
@@ -31,10 +46,10 @@ pub enum StreamState {
 impl StreamState {
     pub(crate) fn from_raw(state: pw_sys::pw_stream_state, error: *const os::raw::c_char) -> Self {
         match state {
-            pw_sys::pw_stream_state_PW_STREAM_STATE_UNCONNECTED => StreamState::Unconnected,
-            pw_sys::pw_stream_state_PW_STREAM_STATE_CONNECTING => StreamState::Connecting,
-            pw_sys::pw_stream_state_PW_STREAM_STATE_PAUSED => StreamState::Paused,
-            pw_sys::pw_stream_state_PW_STREAM_STATE_STREAMING => StreamState::Streaming,
+            pw_sys::pw_stream_state::PW_STREAM_STATE_UNCONNECTED => StreamState::Unconnected,
+            pw_sys::pw_stream_state::PW_STREAM_STATE_CONNECTING => StreamState::Connecting,
+            pw_sys::pw_stream_state::PW_STREAM_STATE_PAUSED => StreamState::Paused,
+            pw_sys::pw_stream_state::PW_STREAM_STATE_STREAMING => StreamState::Streaming,
             _ => {
                 let error = if error.is_null() {
                     "".to_string()
@@ -46,6 +61,19 @@ impl StreamState {
             }
         }
     }
+
+    /// Whether the stream is in error, whatever the error message.
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::Error(_))
+    }
+
+    /// The error message if the stream is in error.
+    pub fn error(&self) -> Option<&str> {
+        match self {
+            Self::Error(error) => Some(error),
+            _ => None,
+        }
+    }
 }
 
 /// A wrapper around the pipewire stream interface. Streams are a higher
@@ -55,7 +83,9 @@ impl StreamState {
 pub struct Stream {
     ptr: ptr::NonNull<pw_sys::pw_stream>,
     // objects that need to stay alive while the Stream is
-    _core: Core,
+    core: Core,
+    loop_: LoopHandle,
+    generation: GenerationListener,
 }
 
 impl Stream {
@@ -78,10 +108,17 @@ impl Stream {
 
         Ok(Stream {
             ptr: stream,
-            _core: core.clone(),
+            core: core.clone(),
+            loop_: LoopHandle::of_core(core.as_raw_ptr()),
+            generation: unsafe { GenerationListener::register(stream) },
         })
     }
 
+    /// The core the stream was created on.
+    pub fn core(&self) -> &Core {
+        &self.core
+    }
+
     pub fn into_raw(self) -> *mut pw_sys::pw_stream {
         let mut this = std::mem::ManuallyDrop::new(self);
 
@@ -89,7 +126,12 @@ impl Stream {
         //        isn't destroyed. However, the core should still be dropped.
         //        Is there a cleaner and safer way to drop the core than like this?
         unsafe {
-            ptr::drop_in_place(ptr::addr_of_mut!(this._core));
+            let lock = this.loop_.lock();
+            this.generation.remove();
+            drop(lock);
+            ptr::drop_in_place(ptr::addr_of_mut!(this.core));
+            ptr::drop_in_place(ptr::addr_of_mut!(this.generation));
+            ptr::drop_in_place(ptr::addr_of_mut!(this.loop_));
         }
 
         this.ptr.as_ptr()
@@ -117,7 +159,132 @@ impl std::fmt::Debug for Stream {
 
 impl std::ops::Drop for Stream {
     fn drop(&mut self) {
-        unsafe { pw_sys::pw_stream_destroy(self.as_raw_ptr()) }
+        let _lock = self.loop_.lock();
+        unsafe {
+            self.generation.remove();
+            pw_sys::pw_stream_destroy(self.as_raw_ptr())
+        }
+    }
+}
+
+/// The buffers of the streams of a [`Core`], with the buffer generation of their stream.
+///
+/// Owned by the core and reached through the user data of its `pw_core`, so that a [`Buffer`]
+/// finds the generation of its stream from the buffer it was dequeued with.
+#[derive(Default, Debug)]
+pub(crate) struct StreamBuffers(RwLock<HashMap<usize, ptr::NonNull<AtomicU64>>>);
+
+impl StreamBuffers {
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<usize, ptr::NonNull<AtomicU64>>> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<usize, ptr::NonNull<AtomicU64>>> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The generation of the stream of `buffer`, or `None` if the stream does not track it.
+    fn generation(&self, buffer: *mut pw_sys::pw_buffer) -> Option<ptr::NonNull<AtomicU64>> {
+        self.read().get(&(buffer as usize)).copied()
+    }
+}
+
+/// An internal listener of a [`Stream`], bumping its buffer generation when it reclaims its buffers.
+///
+/// A [`Buffer`] is only queued back or accessed while the generation it was dequeued in is current.
+struct GenerationListener {
+    hook: Box<spa_sys::spa_hook>,
+    // Need to stay allocated while the listener is registered
+    _events: Box<pw_sys::pw_stream_events>,
+    state: Box<GenerationState>,
+}
+
+struct GenerationState {
+    generation: AtomicU64,
+    /// The buffers of the core of the stream, which the stream keeps alive.
+    buffers: ptr::NonNull<StreamBuffers>,
+}
+
+impl GenerationListener {
+    /// # Safety
+    /// `stream` must be valid, and [`remove`](Self::remove) must be called before it is destroyed
+    /// or its core is dropped.
+    unsafe fn register(stream: ptr::NonNull<pw_sys::pw_stream>) -> Self {
+        unsafe extern "C" fn on_state_changed(
+            data: *mut os::raw::c_void,
+            _old: pw_sys::pw_stream_state,
+            new: pw_sys::pw_stream_state,
+            _error: *const os::raw::c_char,
+        ) {
+            // the buffers are cleared when the stream is disconnected
+            if new == pw_sys::pw_stream_state::PW_STREAM_STATE_UNCONNECTED {
+                let state = &*(data as *const GenerationState);
+                state.generation.fetch_add(1, Ordering::AcqRel);
+            }
+        }
+
+        unsafe extern "C" fn on_add_buffer(
+            data: *mut os::raw::c_void,
+            buffer: *mut pw_sys::pw_buffer,
+        ) {
+            let state = &*(data as *const GenerationState);
+            state
+                .buffers
+                .as_ref()
+                .write()
+                .insert(buffer as usize, ptr::NonNull::from(&state.generation));
+        }
+
+        unsafe extern "C" fn on_remove_buffer(
+            data: *mut os::raw::c_void,
+            buffer: *mut pw_sys::pw_buffer,
+        ) {
+            let state = &*(data as *const GenerationState);
+            // the buffers are only removed all at once, when they are renegotiated or cleared
+            state.generation.fetch_add(1, Ordering::AcqRel);
+            state.buffers.as_ref().write().remove(&(buffer as usize));
+        }
+
+        let mut events: Box<pw_sys::pw_stream_events> = Box::new(mem::zeroed());
+        events.version = pw_sys::PW_VERSION_STREAM_EVENTS;
+        events.state_changed = Some(on_state_changed);
+        events.add_buffer = Some(on_add_buffer);
+        events.remove_buffer = Some(on_remove_buffer);
+
+        let core = pw_sys::pw_stream_get_core(stream.as_ptr());
+        let state = Box::new(GenerationState {
+            generation: AtomicU64::new(0),
+            buffers: ptr::NonNull::from(
+                crate::core::stream_buffers(core).expect("streams are created with a Core"),
+            ),
+        });
+        let mut hook: Box<spa_sys::spa_hook> = Box::new(mem::zeroed());
+        pw_sys::pw_stream_add_listener(
+            stream.as_ptr(),
+            &mut *hook,
+            &*events,
+            &*state as *const GenerationState as *mut _,
+        );
+
+        Self {
+            hook,
+            _events: events,
+            state,
+        }
+    }
+
+    /// Stop tracking the buffers of the stream, before it is destroyed or leaked.
+    ///
+    /// # Safety
+    /// The lock of the loop of the stream must be held.
+    unsafe fn remove(&mut self) {
+        spa::utils::hook::remove(*self.hook);
+        let generation = ptr::NonNull::from(&self.state.generation);
+        self.state
+            .buffers
+            .as_ref()
+            .write()
+            .retain(|_, buffer_generation| *buffer_generation != generation);
     }
 }
 
@@ -134,6 +301,17 @@ impl StreamRef {
     }
 
     /// Add a local listener builder
+    ///
+    /// Each callback receives `&mut user_data`, and the callbacks never run nested in each other
+    /// nor at the same time. Events emitted from a callback, for example the state change of
+    /// [`set_error`](Self::set_error), are delivered after it returns.
+    ///
+    /// With [`StreamFlags::RT_PROCESS`], a `process` event emitted while another callback runs on
+    /// the thread of the loop is delivered on that thread once the callback returns, and the
+    /// other events wait for a `process` callback running on the realtime thread to return.
+    ///
+    /// A buffer removed from a callback, for example by [`disconnect`](Self::disconnect),
+    /// is freed before the callback returns: its `remove_buffer` callback is not called.
     #[must_use = "Fluent builder API"]
     pub fn add_local_listener_with_user_data<D>(
         &self,
@@ -171,7 +349,7 @@ impl StreamRef {
                 self.as_raw_ptr(),
                 direction.as_raw(),
                 id.unwrap_or(crate::constants::ID_ANY),
-                flags.bits(),
+                pw_sys::pw_stream_flags(flags.bits()),
                 // We cast from *mut [&spa::pod::Pod] to *mut [*const spa_sys::spa_pod] here,
                 // which is valid because spa::pod::Pod is a transparent wrapper around spa_sys::spa_pod
                 params.as_mut_ptr().cast(),
@@ -183,16 +361,91 @@ impl StreamRef {
         Ok(())
     }
 
-    /// Update Parameters
+    /// Connect the stream to `target` in the given `direction`.
+    ///
+    /// [`Target::Serial`] and [`Target::Name`] set the `target.object` property of the stream,
+    /// which the session manager uses to link the stream once it is connected.
+    /// Unlike global ids, serials are not reused when objects are destroyed.
+    pub fn connect_target(
+        &self,
+        direction: spa::utils::Direction,
+        target: Target,
+        flags: StreamFlags,
+        params: &mut [&spa::pod::Pod],
+    ) -> Result<(), Error> {
+        let id = match target {
+            Target::Any => None,
+            Target::GlobalId(id) => Some(id),
+            #[cfg(feature = "v0_3_44")]
+            Target::Serial(serial) => {
+                self.set_target_object(serial.to_string())?;
+                None
+            }
+            #[cfg(feature = "v0_3_44")]
+            Target::Name(name) => {
+                self.set_target_object(name)?;
+                None
+            }
+        };
+
+        self.connect(direction, id, flags, params)
+    }
+
+    /// Build the flags and target of [`connect_target`](Self::connect_target) step by step,
+    /// checking that they can be used together before connecting.
+    ///
+    /// ```no_run
+    /// use pipewire::{
+    ///     spa::{pod::Pod, utils::Direction},
+    ///     stream::{ConnectError, Stream, Target},
+    /// };
+    ///
+    /// fn connect(stream: &Stream, format: &Pod) -> Result<(), ConnectError> {
+    ///     stream
+    ///         .connect_builder(Direction::Input)
+    ///         .autoconnect(true)
+    ///         .map_buffers(true)
+    ///         .target(Target::GlobalId(42))
+    ///         .params(&[format])
+    ///         .connect()
+    /// }
+    /// ```
+    #[must_use = "Fluent builder API"]
+    pub fn connect_builder(&self, direction: spa::utils::Direction) -> StreamConnectBuilder<'_> {
+        StreamConnectBuilder {
+            stream: self,
+            direction,
+            target: Target::Any,
+            flags: StreamFlags::empty(),
+            params: Vec::new(),
+        }
+    }
+
+    #[cfg(feature = "v0_3_44")]
+    fn set_target_object(&self, target: String) -> Result<(), Error> {
+        if target.contains('\0') {
+            return Err(nix::errno::Errno::EINVAL.into());
+        }
+        let mut props = Properties::new();
+        props.insert(*crate::keys::TARGET_OBJECT, target);
+        self.update_properties(&props)?;
+        Ok(())
+    }
+
+    /// Update the parameters of the stream, such as its `Buffers` and `Meta` params.
+    ///
+    /// This is usually called from the `param_changed` callback to answer the negotiation
+    /// of a new format, which is supported.
     ///
-    /// Call from the `param_changed` callback to negotiate a new set of
-    /// parameters for the stream.
+    /// The pods are copied by PipeWire before this returns, so they only need to live for
+    /// the duration of the call and can be built in a temporary buffer.
     // FIXME: high-level API for params
-    pub fn update_params(&self, params: &mut [&spa::pod::Pod]) -> Result<(), Error> {
+    pub fn update_params(&self, params: &[&spa::pod::Pod]) -> Result<(), Error> {
         let r = unsafe {
             pw_sys::pw_stream_update_params(
                 self.as_raw_ptr(),
-                params.as_mut_ptr().cast(),
+                // the array is not modified, the C API lacks the const qualifier
+                params.as_ptr().cast_mut().cast(),
                 params.len() as u32,
             )
         };
@@ -201,6 +454,19 @@ impl StreamRef {
         Ok(())
     }
 
+    /// Set a parameter of the stream, such as its `Props`.
+    ///
+    /// As with [`Self::update_params`], the pod is copied and only needs to live for the
+    /// duration of the call.
+    pub fn set_param(&self, id: spa::param::ParamType, param: &spa::pod::Pod) -> Result<(), Error> {
+        let r = unsafe {
+            pw_sys::pw_stream_set_param(self.as_raw_ptr(), id.as_raw(), param.as_raw_ptr())
+        };
+
+        SpaResult::from_c(r).into_sync_result()?;
+        Ok(())
+    }
+
     /// Activate or deactivate the stream
     pub fn set_active(&self, active: bool) -> Result<(), Error> {
         let r = unsafe { pw_sys::pw_stream_set_active(self.as_raw_ptr(), active) };
@@ -223,10 +489,23 @@ impl StreamRef {
         pw_sys::pw_stream_dequeue_buffer(self.as_raw_ptr())
     }
 
+    /// Take a buffer from the stream, queued back when the returned [`Buffer`] is dropped.
+    ///
+    /// Returns `None` if no buffer is available.
     pub fn dequeue_buffer(&self) -> Option<Buffer> {
         unsafe { Buffer::from_raw(self.dequeue_raw_buffer(), self) }
     }
 
+    /// The buffer generation of the stream, if `buffer` is one of its buffers.
+    pub(crate) fn buffer_generation(&self, buffer: *mut pw_sys::pw_buffer) -> Option<&AtomicU64> {
+        let core = unsafe { pw_sys::pw_stream_get_core(self.as_raw_ptr()) };
+        let buffers = unsafe { crate::core::stream_buffers(core) }?;
+        // Safety: the generation is removed from the buffers before the stream is dropped.
+        buffers
+            .generation(buffer)
+            .map(|generation| unsafe { generation.as_ref() })
+    }
+
     /// Return a Buffer to the Stream
     ///
     /// Give back a buffer once processing is complete. Use this to queue up a
@@ -251,23 +530,24 @@ impl StreamRef {
 
     /// Set the stream in error state
     ///
-    /// # Panics
-    /// Will panic if `error` contains a 0 byte.
+    /// `res` is the negative errno of the error, and `error` a message describing it.
+    /// The stream changes to the [`StreamState::Error`] state with the message, which is
+    /// also reported to the server.
     ///
-    pub fn set_error(&mut self, res: i32, error: &str) {
-        let error = CString::new(error).expect("failed to convert error to CString");
-        let error_cstr = error.as_c_str();
-        StreamRef::set_error_cstr(self, res, error_cstr)
+    /// If `error` contains a 0 byte, the message is truncated there.
+    pub fn set_error(&self, res: i32, error: &str) {
+        let error = error.split('\0').next().unwrap_or_default();
+        let error = CString::new(error).expect("message contains no 0 byte after truncation");
+        StreamRef::set_error_cstr(self, res, &error)
     }
 
     /// Set the stream in error state with CStr
     ///
-    /// # Panics
-    /// Will panic if `error` contains a 0 byte.
-    ///
-    pub fn set_error_cstr(&mut self, res: i32, error: &CStr) {
+    /// See [`set_error`](Self::set_error).
+    pub fn set_error_cstr(&self, res: i32, error: &CStr) {
         unsafe {
-            pw_sys::pw_stream_set_error(self.as_raw_ptr(), res, error.as_ptr());
+            // the message is passed as an argument so it is not interpreted as a format string
+            pw_sys::pw_stream_set_error(self.as_raw_ptr(), res, c"%s".as_ptr(), error.as_ptr());
         }
     }
 
@@ -293,6 +573,24 @@ impl StreamRef {
         Ok(())
     }
 
+    /// Update the properties of the stream with the entries of `properties`.
+    ///
+    /// The properties are also updated on the node of the stream, so this can be used to change
+    /// the metadata of a connected stream, such as its `media.name`.
+    /// Entries with a `NULL` value are removed.
+    /// Returns the number of properties that were changed.
+    pub fn update_properties(
+        &self,
+        properties: &impl AsRef<spa::utils::dict::DictRef>,
+    ) -> Result<u32, Error> {
+        let r = unsafe {
+            pw_sys::pw_stream_update_properties(self.as_raw_ptr(), properties.as_ref().as_raw_ptr())
+        };
+
+        let changed = SpaResult::from_c(r).into_sync_result()?;
+        Ok(changed as u32)
+    }
+
     // getters
 
     /// Get the name of the stream.
@@ -323,11 +621,36 @@ impl StreamRef {
         }
     }
 
-    /// Get the node ID of the stream.
+    /// Whether the stream is connected and processing data.
+    pub fn is_streaming(&self) -> bool {
+        self.state() == StreamState::Streaming
+    }
+
+    /// Get the id of the global of the node of the stream.
+    ///
+    /// The id is only known once the stream is connected and reached the
+    /// [`Paused`](StreamState::Paused) or [`Streaming`](StreamState::Streaming) state.
+    /// Before that, this is `SPA_ID_INVALID`, the same value as [`ID_ANY`](crate::constants::ID_ANY).
     pub fn node_id(&self) -> u32 {
         unsafe { pw_sys::pw_stream_get_node_id(self.as_raw_ptr()) }
     }
 
+    /// The port globals of the node of the stream among `globals`, such as a
+    /// [`Registry::snapshot`](crate::registry::Registry::snapshot) taken after the stream is connected.
+    ///
+    /// This is empty as long as the [`node_id`](Self::node_id) is unknown.
+    /// Use [`find_port`](crate::registry::find_port) with a [`NodeKey::Id`] to look up a single port.
+    pub fn ports<'a, P: AsRef<spa::utils::dict::DictRef>>(
+        &self,
+        globals: &'a [GlobalObject<P>],
+    ) -> Vec<&'a GlobalObject<P>> {
+        match self.node_id() {
+            crate::constants::ID_ANY => Vec::new(),
+            id => ports_of_node(globals, NodeKey::Id(id)),
+        }
+    }
+
+    /// Whether the stream is driving the graph it is part of.
     #[cfg(feature = "v0_3_34")]
     pub fn is_driving(&self) -> bool {
         unsafe { pw_sys::pw_stream_is_driving(self.as_raw_ptr()) }
@@ -341,13 +664,71 @@ impl StreamRef {
         Ok(())
     }
 
-    // TODO: pw_stream_get_core()
     // TODO: pw_stream_get_time()
 }
 
 type ParamChangedCB<D> = dyn FnMut(&StreamRef, &mut D, u32, Option<&spa::pod::Pod>);
 type ProcessCB<D> = dyn FnMut(&StreamRef, &mut D);
 
+/// The clock of the driver during one cycle of the graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ClockCycle {
+    id: u32,
+    rate: u32,
+    quantum: u64,
+    position: u64,
+}
+
+impl ClockCycle {
+    fn from_clock(clock: &IoClock) -> Self {
+        Self {
+            id: clock.id(),
+            rate: clock.rate().denom,
+            quantum: clock.duration(),
+            position: clock.position(),
+        }
+    }
+}
+
+/// Follows the clock of the position io area from one `process` call to the next,
+/// for the `quantum_changed` and `xrun` callbacks.
+#[derive(Default)]
+struct ClockTracker {
+    position: Option<ptr::NonNull<spa_sys::spa_io_position>>,
+    last: Option<ClockCycle>,
+    xruns: u64,
+}
+
+impl ClockTracker {
+    fn set_area(&mut self, area: *mut os::raw::c_void, size: u32) {
+        self.position = unsafe { IoPosition::from_area(area, size) }
+            .and_then(|p| ptr::NonNull::new(p.as_raw_ptr()));
+    }
+
+    fn clock(&self) -> Option<ClockCycle> {
+        let position = self.position?;
+        // Safety: the area was checked by set_area(), and stays valid until the next io_changed event
+        let position = unsafe { &*position.as_ptr().cast::<IoPosition>() };
+        Some(ClockCycle::from_clock(&position.clock().snapshot()))
+    }
+
+    /// Record the clock of a new cycle, returning whether its rate or quantum changed
+    /// and whether cycles were missed since the previous one.
+    fn update(&mut self, cycle: ClockCycle) -> (bool, bool) {
+        let Some(last) = self.last.replace(cycle) else {
+            return (true, false);
+        };
+        let changed = (last.rate, last.quantum) != (cycle.rate, cycle.quantum);
+        // the position of a driver advances by the quantum of each cycle,
+        // it jumps further when cycles were skipped
+        let xrun = last.id == cycle.id && cycle.position > last.position + last.quantum;
+        if xrun {
+            self.xruns += 1;
+        }
+        (changed, xrun)
+    }
+}
+
 #[allow(clippy::type_complexity)]
 pub struct ListenerLocalCallbacks<D> {
     pub state_changed: Option<Box<dyn FnMut(&StreamRef, &mut D, StreamState, StreamState)>>,
@@ -355,16 +736,19 @@ pub struct ListenerLocalCallbacks<D> {
         Option<Box<dyn FnMut(&StreamRef, &mut D, u32, *const pw_sys::pw_stream_control)>>,
     pub io_changed: Option<Box<dyn FnMut(&StreamRef, &mut D, u32, *mut os::raw::c_void, u32)>>,
     pub param_changed: Option<Box<ParamChangedCB<D>>>,
-    pub add_buffer: Option<Box<dyn FnMut(&StreamRef, &mut D, *mut pw_sys::pw_buffer)>>,
-    pub remove_buffer: Option<Box<dyn FnMut(&StreamRef, &mut D, *mut pw_sys::pw_buffer)>>,
+    pub add_buffer: Option<Box<dyn FnMut(&StreamRef, &mut D, &mut BufferRef)>>,
+    pub remove_buffer: Option<Box<dyn FnMut(&StreamRef, &mut D, &mut BufferRef)>>,
     pub process: Option<Box<ProcessCB<D>>>,
     pub drained: Option<Box<dyn FnMut(&StreamRef, &mut D)>>,
     #[cfg(feature = "v0_3_39")]
     pub command: Option<Box<dyn FnMut(&StreamRef, &mut D, *const spa_sys::spa_command)>>,
     #[cfg(feature = "v0_3_40")]
     pub trigger_done: Option<Box<dyn FnMut(&StreamRef, &mut D)>>,
+    pub quantum_changed: Option<Box<dyn FnMut(&StreamRef, &mut D, u32, u64)>>,
+    pub xrun: Option<Box<dyn FnMut(&StreamRef, &mut D, u64)>>,
     pub user_data: D,
     stream: Option<ptr::NonNull<pw_sys::pw_stream>>,
+    clock: ClockTracker,
 }
 
 unsafe fn unwrap_stream_ptr<'a>(stream: Option<ptr::NonNull<pw_sys::pw_stream>>) -> &'a StreamRef {
@@ -389,17 +773,111 @@ impl<D> ListenerLocalCallbacks<D> {
             command: Default::default(),
             #[cfg(feature = "v0_3_40")]
             trigger_done: Default::default(),
+            quantum_changed: Default::default(),
+            xrun: Default::default(),
             user_data,
+            clock: Default::default(),
         }
     }
 
-    pub(crate) fn into_raw(
-        self,
-    ) -> (
-        Pin<Box<pw_sys::pw_stream_events>>,
-        Box<ListenerLocalCallbacks<D>>,
-    ) {
-        let callbacks = Box::new(self);
+    fn tracks_clock(&self) -> bool {
+        self.quantum_changed.is_some() || self.xrun.is_some()
+    }
+
+    /// Call the `quantum_changed` and `xrun` callbacks for the clock of the current cycle.
+    fn notify_clock(&mut self, stream: &StreamRef) {
+        let Some(cycle) = self.clock.clock() else {
+            return;
+        };
+        let (changed, xrun) = self.clock.update(cycle);
+        if changed {
+            if let Some(cb) = &mut self.quantum_changed {
+                cb(stream, &mut self.user_data, cycle.rate, cycle.quantum);
+            }
+        }
+        if xrun {
+            if let Some(cb) = &mut self.xrun {
+                cb(stream, &mut self.user_data, self.clock.xruns);
+            }
+        }
+    }
+
+    /// Call the `process` callback, and the clock callbacks.
+    fn process(&mut self) {
+        let stream = unsafe { unwrap_stream_ptr(self.stream) };
+        if self.tracks_clock() {
+            self.notify_clock(stream);
+        }
+        if let Some(cb) = &mut self.process {
+            cb(stream, &mut self.user_data);
+        }
+    }
+
+    /// Call the callback of `event`.
+    fn dispatch(&mut self, event: StreamEvent<'_>) {
+        let stream = unsafe { unwrap_stream_ptr(self.stream) };
+        match event {
+            StreamEvent::StateChanged(old, new) => {
+                if let Some(cb) = &mut self.state_changed {
+                    cb(stream, &mut self.user_data, old, new);
+                }
+            }
+            StreamEvent::ControlInfo(id, control) => {
+                if let Some(cb) = &mut self.control_info {
+                    cb(stream, &mut self.user_data, id, control);
+                }
+            }
+            StreamEvent::IoChanged(id, area, size) => {
+                if id == IoType::Position.as_raw() {
+                    self.clock.set_area(area, size);
+                }
+                if let Some(cb) = &mut self.io_changed {
+                    cb(stream, &mut self.user_data, id, area, size);
+                }
+            }
+            StreamEvent::ParamChanged(id, param) => {
+                if let Some(cb) = &mut self.param_changed {
+                    cb(stream, &mut self.user_data, id, param.as_deref());
+                }
+            }
+            StreamEvent::AddBuffer(buffer) => {
+                if let Some(cb) = &mut self.add_buffer {
+                    let buffer = ptr::NonNull::new(buffer).expect("buffer is NULL");
+                    cb(stream, &mut self.user_data, unsafe {
+                        BufferRef::from_raw_mut(buffer)
+                    });
+                }
+            }
+            StreamEvent::RemoveBuffer(buffer) => {
+                if let Some(cb) = &mut self.remove_buffer {
+                    let buffer = ptr::NonNull::new(buffer).expect("buffer is NULL");
+                    cb(stream, &mut self.user_data, unsafe {
+                        BufferRef::from_raw_mut(buffer)
+                    });
+                }
+            }
+            StreamEvent::Drained => {
+                if let Some(cb) = &mut self.drained {
+                    cb(stream, &mut self.user_data);
+                }
+            }
+            #[cfg(feature = "v0_3_39")]
+            StreamEvent::Command(command) => {
+                if let Some(cb) = &mut self.command {
+                    cb(stream, &mut self.user_data, command.as_raw_ptr().cast());
+                }
+            }
+            #[cfg(feature = "v0_3_40")]
+            StreamEvent::TriggerDone => {
+                if let Some(cb) = &mut self.trigger_done {
+                    cb(stream, &mut self.user_data);
+                }
+            }
+        }
+    }
+
+    fn into_raw(self) -> (Pin<Box<pw_sys::pw_stream_events>>, Box<ListenerState<D>>) {
+        let callbacks = self;
 
         unsafe extern "C" fn on_state_changed<D>(
             data: *mut os::raw::c_void,
@@ -407,14 +885,11 @@ impl<D> ListenerLocalCallbacks<D> {
             new: pw_sys::pw_stream_state,
             error: *const os::raw::c_char,
         ) {
-            if let Some(state) = (data as *mut ListenerLocalCallbacks<D>).as_mut() {
-                if let Some(cb) = &mut state.state_changed {
-                    let stream = unwrap_stream_ptr(state.stream);
-                    let old = StreamState::from_raw(old, error);
-                    let new = StreamState::from_raw(new, error);
-                    cb(stream, &mut state.user_data, old, new)
-                };
-            }
+            crate::utils::catch_callback_panic("stream state changed", (), || {
+                let old = StreamState::from_raw(old, error);
+                let new = StreamState::from_raw(new, error);
+                ListenerState::<D>::emit(data, StreamEvent::StateChanged(old, new));
+            })
         }
 
         unsafe extern "C" fn on_control_info<D>(
@@ -422,12 +897,9 @@ impl<D> ListenerLocalCallbacks<D> {
             id: u32,
             control: *const pw_sys::pw_stream_control,
         ) {
-            if let Some(state) = (data as *mut ListenerLocalCallbacks<D>).as_mut() {
-                if let Some(cb) = &mut state.control_info {
-                    let stream = unwrap_stream_ptr(state.stream);
-                    cb(stream, &mut state.user_data, id, control);
-                }
-            }
+            crate::utils::catch_callback_panic("stream control info", (), || {
+                ListenerState::<D>::emit(data, StreamEvent::ControlInfo(id, control));
+            })
         }
 
         unsafe extern "C" fn on_io_changed<D>(
@@ -436,12 +908,9 @@ impl<D> ListenerLocalCallbacks<D> {
             area: *mut os::raw::c_void,
             size: u32,
         ) {
-            if let Some(state) = (data as *mut ListenerLocalCallbacks<D>).as_mut() {
-                if let Some(cb) = &mut state.io_changed {
-                    let stream = unwrap_stream_ptr(state.stream);
-                    cb(stream, &mut state.user_data, id, area, size);
-                }
-            }
+            crate::utils::catch_callback_panic("stream io changed", (), || {
+                ListenerState::<D>::emit(data, StreamEvent::IoChanged(id, area, size));
+            })
         }
 
         unsafe extern "C" fn on_param_changed<D>(
@@ -449,60 +918,44 @@ impl<D> ListenerLocalCallbacks<D> {
             id: u32,
             param: *const spa_sys::spa_pod,
         ) {
-            if let Some(state) = (data as *mut ListenerLocalCallbacks<D>).as_mut() {
-                if let Some(cb) = &mut state.param_changed {
-                    let stream = unwrap_stream_ptr(state.stream);
-                    let param = if !param.is_null() {
-                        Some(spa::pod::Pod::from_raw(param))
-                    } else {
-                        None
-                    };
-
-                    cb(stream, &mut state.user_data, id, param);
-                }
-            }
+            crate::utils::catch_callback_panic("stream param changed", (), || {
+                let param = if !param.is_null() {
+                    Some(Cow::Borrowed(spa::pod::Pod::from_raw(param)))
+                } else {
+                    None
+                };
+                ListenerState::<D>::emit(data, StreamEvent::ParamChanged(id, param));
+            })
         }
 
         unsafe extern "C" fn on_add_buffer<D>(
             data: *mut ::std::os::raw::c_void,
             buffer: *mut pw_sys::pw_buffer,
         ) {
-            if let Some(state) = (data as *mut ListenerLocalCallbacks<D>).as_mut() {
-                if let Some(cb) = &mut state.add_buffer {
-                    let stream = unwrap_stream_ptr(state.stream);
-                    cb(stream, &mut state.user_data, buffer);
-                }
-            }
+            crate::utils::catch_callback_panic("stream add buffer", (), || {
+                ListenerState::<D>::emit(data, StreamEvent::AddBuffer(buffer));
+            })
         }
 
         unsafe extern "C" fn on_remove_buffer<D>(
             data: *mut ::std::os::raw::c_void,
             buffer: *mut pw_sys::pw_buffer,
         ) {
-            if let Some(state) = (data as *mut ListenerLocalCallbacks<D>).as_mut() {
-                if let Some(cb) = &mut state.remove_buffer {
-                    let stream = unwrap_stream_ptr(state.stream);
-                    cb(stream, &mut state.user_data, buffer);
-                }
-            }
+            crate::utils::catch_callback_panic("stream remove buffer", (), || {
+                ListenerState::<D>::emit(data, StreamEvent::RemoveBuffer(buffer));
+            })
         }
 
         unsafe extern "C" fn on_process<D>(data: *mut ::std::os::raw::c_void) {
-            if let Some(state) = (data as *mut ListenerLocalCallbacks<D>).as_mut() {
-                if let Some(cb) = &mut state.process {
-                    let stream = unwrap_stream_ptr(state.stream);
-                    cb(stream, &mut state.user_data);
-                }
-            }
+            crate::utils::catch_callback_panic("stream process", (), || {
+                ListenerState::<D>::process(data);
+            })
         }
 
         unsafe extern "C" fn on_drained<D>(data: *mut ::std::os::raw::c_void) {
-            if let Some(state) = (data as *mut ListenerLocalCallbacks<D>).as_mut() {
-                if let Some(cb) = &mut state.drained {
-                    let stream = unwrap_stream_ptr(state.stream);
-                    cb(stream, &mut state.user_data);
-                }
-            }
+            crate::utils::catch_callback_panic("stream drained", (), || {
+                ListenerState::<D>::emit(data, StreamEvent::Drained);
+            })
         }
 
         #[cfg(feature = "v0_3_39")]
@@ -510,22 +963,17 @@ impl<D> ListenerLocalCallbacks<D> {
             data: *mut ::std::os::raw::c_void,
             command: *const spa_sys::spa_command,
         ) {
-            if let Some(state) = (data as *mut ListenerLocalCallbacks<D>).as_mut() {
-                if let Some(cb) = &mut state.command {
-                    let stream = unwrap_stream_ptr(state.stream);
-                    cb(stream, &mut state.user_data, command);
-                }
-            }
+            crate::utils::catch_callback_panic("stream command", (), || {
+                let command = spa::pod::Pod::from_raw(command.cast());
+                ListenerState::<D>::emit(data, StreamEvent::Command(Cow::Borrowed(command)));
+            })
         }
 
         #[cfg(feature = "v0_3_40")]
         unsafe extern "C" fn on_trigger_done<D>(data: *mut ::std::os::raw::c_void) {
-            if let Some(state) = (data as *mut ListenerLocalCallbacks<D>).as_mut() {
-                if let Some(cb) = &mut state.trigger_done {
-                    let stream = unwrap_stream_ptr(state.stream);
-                    cb(stream, &mut state.user_data);
-                }
-            }
+            crate::utils::catch_callback_panic("stream trigger done", (), || {
+                ListenerState::<D>::emit(data, StreamEvent::TriggerDone);
+            })
         }
 
         let events = unsafe {
@@ -538,7 +986,7 @@ impl<D> ListenerLocalCallbacks<D> {
             if callbacks.control_info.is_some() {
                 events.control_info = Some(on_control_info::<D>);
             }
-            if callbacks.io_changed.is_some() {
+            if callbacks.io_changed.is_some() || callbacks.tracks_clock() {
                 events.io_changed = Some(on_io_changed::<D>);
             }
             if callbacks.param_changed.is_some() {
@@ -550,7 +998,7 @@ impl<D> ListenerLocalCallbacks<D> {
             if callbacks.remove_buffer.is_some() {
                 events.remove_buffer = Some(on_remove_buffer::<D>);
             }
-            if callbacks.process.is_some() {
+            if callbacks.process.is_some() || callbacks.tracks_clock() {
                 events.process = Some(on_process::<D>);
             }
             if callbacks.drained.is_some() {
@@ -568,7 +1016,215 @@ impl<D> ListenerLocalCallbacks<D> {
             events
         };
 
-        (events, callbacks)
+        (events, Box::new(ListenerState::new(callbacks)))
+    }
+}
+
+/// An event of a stream which can be delivered after the callback running when it was emitted.
+enum StreamEvent<'a> {
+    StateChanged(StreamState, StreamState),
+    ControlInfo(u32, *const pw_sys::pw_stream_control),
+    IoChanged(u32, *mut os::raw::c_void, u32),
+    ParamChanged(u32, Option<Cow<'a, spa::pod::Pod>>),
+    /// Queued until the buffer is removed, and never delivered after that.
+    AddBuffer(*mut pw_sys::pw_buffer),
+    /// Never queued, as the buffer is freed once the event returns.
+    RemoveBuffer(*mut pw_sys::pw_buffer),
+    Drained,
+    #[cfg(feature = "v0_3_39")]
+    Command(Cow<'a, spa::pod::Pod>),
+    #[cfg(feature = "v0_3_40")]
+    TriggerDone,
+}
+
+impl StreamEvent<'_> {
+    /// Copy the pods of the event, to queue it.
+    fn into_owned(self) -> StreamEvent<'static> {
+        match self {
+            Self::StateChanged(old, new) => StreamEvent::StateChanged(old, new),
+            Self::ControlInfo(id, control) => StreamEvent::ControlInfo(id, control),
+            Self::IoChanged(id, area, size) => StreamEvent::IoChanged(id, area, size),
+            Self::ParamChanged(id, param) => {
+                StreamEvent::ParamChanged(id, param.map(|param| Cow::Owned(param.into_owned())))
+            }
+            Self::AddBuffer(buffer) => StreamEvent::AddBuffer(buffer),
+            Self::RemoveBuffer(buffer) => StreamEvent::RemoveBuffer(buffer),
+            Self::Drained => StreamEvent::Drained,
+            #[cfg(feature = "v0_3_39")]
+            Self::Command(command) => StreamEvent::Command(Cow::Owned(command.into_owned())),
+            #[cfg(feature = "v0_3_40")]
+            Self::TriggerDone => StreamEvent::TriggerDone,
+        }
+    }
+}
+
+/// The callbacks of a registered listener, as seen by its trampolines.
+///
+/// Only one callback of the listener runs at a time, the one of the thread owning the callbacks:
+/// - Events emitted from a callback on its own thread, such as [`StreamRef::set_error`] emitting
+///   `state_changed`, are queued and delivered once the running callback returns.
+/// - `process`, which runs on the realtime thread with [`StreamFlags::RT_PROCESS`] where waiting
+///   and allocating are not allowed, is deferred with a flag when another callback runs,
+///   and called by the thread of that callback once it returns.
+/// - Other events emitted while `process` runs on the realtime thread wait for it to return.
+///
+/// A `remove_buffer` event emitted from a callback is not delivered, as its buffer is freed
+/// before the callback returns. If the `add_buffer` event of the buffer is still queued,
+/// it is not delivered either.
+struct ListenerState<D> {
+    callbacks: cell::UnsafeCell<ListenerLocalCallbacks<D>>,
+    /// The thread owning the callbacks, as returned by [`current_thread`], or 0.
+    owner: AtomicUsize,
+    process_pending: AtomicBool,
+    /// Whether `pending` is not empty, checked without locking it from the realtime thread.
+    queued: AtomicBool,
+    pending: Mutex<VecDeque<StreamEvent<'static>>>,
+}
+
+/// An identifier of the current thread, which is never 0.
+fn current_thread() -> usize {
+    thread_local! {
+        static TOKEN: u8 = const { 0 };
+    }
+    TOKEN.with(|token| token as *const u8 as usize)
+}
+
+impl<D> ListenerState<D> {
+    fn new(callbacks: ListenerLocalCallbacks<D>) -> Self {
+        Self {
+            callbacks: cell::UnsafeCell::new(callbacks),
+            owner: AtomicUsize::new(0),
+            process_pending: AtomicBool::new(false),
+            queued: AtomicBool::new(false),
+            pending: Default::default(),
+        }
+    }
+
+    fn lock_pending(&self) -> MutexGuard<'_, VecDeque<StreamEvent<'static>>> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Own the callbacks from the current thread until the guard is dropped.
+    ///
+    /// Fails with `true` if a callback of the listener is running on the current thread,
+    /// and with `false` if it is running on another thread.
+    fn try_enter(&self) -> Result<OwnerGuard<'_>, bool> {
+        let thread = current_thread();
+        match self
+            .owner
+            .compare_exchange(0, thread, Ordering::SeqCst, Ordering::SeqCst)
+        {
+            Ok(_) => Ok(OwnerGuard(&self.owner)),
+            Err(owner) => Err(owner == thread),
+        }
+    }
+
+    /// Deliver `event` now, once the callback running on this thread returns, or once `process`
+    /// returns on the realtime thread.
+    ///
+    /// # Safety
+    /// `data` must be null or point to the `ListenerState<D>` registered with the event.
+    unsafe fn emit(data: *mut os::raw::c_void, event: StreamEvent<'_>) {
+        let Some(state) = (data as *const Self).as_ref() else {
+            return;
+        };
+        loop {
+            match state.try_enter() {
+                Ok(_owner) => {
+                    // Safety: this thread owns the callbacks until `_owner` is dropped.
+                    (*state.callbacks.get()).dispatch(event);
+                    break;
+                }
+                Err(true) => {
+                    state.queue(event);
+                    break;
+                }
+                // only `process` runs on another thread, and it does not wait for this one
+                Err(false) => std::thread::yield_now(),
+            }
+        }
+        // also when the running callback returned before the event was queued
+        state.deliver_pending();
+    }
+
+    /// Queue `event`, emitted from a callback running on this thread.
+    fn queue(&self, event: StreamEvent<'_>) {
+        let mut pending = self.lock_pending();
+        if let StreamEvent::RemoveBuffer(buffer) = event {
+            // the buffer is freed before the running callback returns
+            pending.retain(
+                |event| !matches!(event, StreamEvent::AddBuffer(added) if *added == buffer),
+            );
+        } else {
+            pending.push_back(event.into_owned());
+        }
+        self.queued.store(!pending.is_empty(), Ordering::SeqCst);
+    }
+
+    fn pop_pending(&self) -> Option<StreamEvent<'static>> {
+        if !self.queued.load(Ordering::SeqCst) {
+            return None;
+        }
+        let mut pending = self.lock_pending();
+        let event = pending.pop_front();
+        self.queued.store(!pending.is_empty(), Ordering::SeqCst);
+        event
+    }
+
+    /// Call the `process` callback now, or once the running callback of the listener returns.
+    ///
+    /// # Safety
+    /// `data` must be null or point to the `ListenerState<D>` registered with the event.
+    unsafe fn process(data: *mut os::raw::c_void) {
+        let Some(state) = (data as *const Self).as_ref() else {
+            return;
+        };
+        match state.try_enter() {
+            // Safety: this thread owns the callbacks until the guard is dropped.
+            Ok(_owner) => (*state.callbacks.get()).process(),
+            Err(_) => {
+                state.process_pending.store(true, Ordering::SeqCst);
+                // in case the running callback returned before the flag was set
+                state.deliver_pending();
+            }
+        }
+    }
+
+    /// Deliver the queued events and the deferred `process`, unless a callback of the listener
+    /// is running, in which case they are delivered once it returns.
+    ///
+    /// Does not lock nor allocate when nothing is queued, as on the realtime thread.
+    ///
+    /// # Safety
+    /// `self` must be the `ListenerState<D>` registered with the event being emitted.
+    unsafe fn deliver_pending(&self) {
+        while self.queued.load(Ordering::SeqCst) || self.process_pending.load(Ordering::SeqCst) {
+            let Ok(_owner) = self.try_enter() else {
+                return;
+            };
+
+            // Safety: this thread owns the callbacks until `_owner` is dropped.
+            let callbacks = &mut *self.callbacks.get();
+            loop {
+                // not locked while dispatching, as the callback may emit other events
+                if let Some(event) = self.pop_pending() {
+                    callbacks.dispatch(event);
+                } else if self.process_pending.swap(false, Ordering::SeqCst) {
+                    callbacks.process();
+                } else {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Releases the callbacks of a [`ListenerState`], even if the callback panics.
+struct OwnerGuard<'a>(&'a AtomicUsize);
+
+impl Drop for OwnerGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(0, Ordering::SeqCst);
     }
 }
 
@@ -607,6 +1263,9 @@ impl<'a, D> ListenerLocalBuilder<'a, D> {
     }
 
     /// Set the callback for the `param_changed` event.
+    ///
+    /// The pod is only valid during the call, copy it with [`ToOwned::to_owned`] into an
+    /// [`OwnedPod`](spa::pod::OwnedPod) to keep it, e.g. to compare it with the next one.
     pub fn param_changed<F>(mut self, callback: F) -> Self
     where
         F: FnMut(&StreamRef, &mut D, u32, Option<&spa::pod::Pod>) + 'static,
@@ -616,24 +1275,36 @@ impl<'a, D> ListenerLocalBuilder<'a, D> {
     }
 
     /// Set the callback for the `add_buffer` event.
+    ///
+    /// With [`StreamFlags::ALLOC_BUFFERS`], this is where the memory of the datas of the buffer
+    /// is provided, see [`attach_memfd`](crate::mem::attach_memfd).
     pub fn add_buffer<F>(mut self, callback: F) -> Self
     where
-        F: FnMut(&StreamRef, &mut D, *mut pw_sys::pw_buffer) + 'static,
+        F: FnMut(&StreamRef, &mut D, &mut BufferRef) + 'static,
     {
         self.callbacks.add_buffer = Some(Box::new(callback));
         self
     }
 
     /// Set the callback for the `remove_buffer` event.
+    ///
+    /// With [`StreamFlags::ALLOC_BUFFERS`], the memory provided in `add_buffer` can be freed here.
+    ///
+    /// The callback is not called for buffers removed from another callback of the listener,
+    /// for example when disconnecting the stream from `state_changed`, as they are freed before
+    /// that callback returns. Their memory has to be freed when disconnecting then.
     pub fn remove_buffer<F>(mut self, callback: F) -> Self
     where
-        F: FnMut(&StreamRef, &mut D, *mut pw_sys::pw_buffer) + 'static,
+        F: FnMut(&StreamRef, &mut D, &mut BufferRef) + 'static,
     {
         self.callbacks.remove_buffer = Some(Box::new(callback));
         self
     }
 
     /// Set the callback for the `process` event.
+    ///
+    /// With [`StreamFlags::RT_PROCESS`], the callback is called on the thread of the loop instead
+    /// of the realtime thread when the event is emitted while another callback runs.
     pub fn process<F>(mut self, callback: F) -> Self
     where
         F: FnMut(&StreamRef, &mut D) + 'static,
@@ -651,6 +1322,32 @@ impl<'a, D> ListenerLocalBuilder<'a, D> {
         self
     }
 
+    /// Set a callback called with the rate and the quantum of the graph when they change,
+    /// such as when `clock.force-quantum` is set in the settings metadata.
+    ///
+    /// They are read from the [`IoPosition`] of the stream before each `process` event,
+    /// so the callback is called from the same thread, first with the initial values.
+    pub fn quantum_changed<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&StreamRef, &mut D, u32, u64) + 'static,
+    {
+        self.callbacks.quantum_changed = Some(Box::new(callback));
+        self
+    }
+
+    /// Set a callback called with the number of xruns seen so far, when cycles of the graph
+    /// were skipped since the previous `process` event.
+    ///
+    /// Skipped cycles are detected when the position of the clock of the driver jumps by more
+    /// than the quantum. The callback is called before the `process` event, from the same thread.
+    pub fn xrun<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&StreamRef, &mut D, u64) + 'static,
+    {
+        self.callbacks.xrun = Some(Box::new(callback));
+        self
+    }
+
     //// Register the Callbacks
     ///
     /// Stop building the listener and register it on the stream. Returns a
@@ -673,6 +1370,9 @@ impl<'a, D> ListenerLocalBuilder<'a, D> {
             listener,
             _events: events,
             _data: data,
+            loop_: LoopHandle::of_core(unsafe {
+                pw_sys::pw_stream_get_core(self.stream.as_raw_ptr())
+            }),
         })
     }
 }
@@ -681,7 +1381,8 @@ pub struct StreamListener<D> {
     listener: Box<spa_sys::spa_hook>,
     // Need to stay allocated while the listener is registered
     _events: Pin<Box<pw_sys::pw_stream_events>>,
-    _data: Box<ListenerLocalCallbacks<D>>,
+    _data: Box<ListenerState<D>>,
+    loop_: LoopHandle,
 }
 
 impl<D> StreamListener<D> {
@@ -691,28 +1392,1014 @@ impl<D> StreamListener<D> {
     pub fn unregister(self) {
         // do nothing, drop will clean up.
     }
+
+    /// Drop the events received by the listener, until [`unblock`](Self::unblock) is called.
+    ///
+    /// The listener stays registered, see [`Listener::block`](crate::proxy::Listener::block).
+    pub fn block(&mut self) {
+        spa::utils::hook::block(&mut self.listener);
+    }
+
+    /// Deliver events to the listener again after [`block`](Self::block).
+    pub fn unblock(&mut self) {
+        let funcs: *const _ = &*self._events;
+        // Safety: the listener was added with its events, which live as long as it does.
+        unsafe { spa::utils::hook::unblock(&mut self.listener, funcs.cast()) };
+    }
+
+    /// Whether the listener is blocked by [`block`](Self::block).
+    pub fn is_blocked(&self) -> bool {
+        spa::utils::hook::is_blocked(&self.listener)
+    }
 }
 
 impl<D> std::ops::Drop for StreamListener<D> {
     fn drop(&mut self) {
+        let _lock = self.loop_.lock();
         spa::utils::hook::remove(*self.listener);
     }
 }
 
 bitflags! {
-    /// Extra flags that can be used in [`Stream::connect()`]
+    /// Extra flags that can be used in [`Stream::connect()`], or set one by one with
+    /// [`StreamRef::connect_builder`]
     #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-    pub struct StreamFlags: pw_sys::pw_stream_flags {
-        const AUTOCONNECT = pw_sys::pw_stream_flags_PW_STREAM_FLAG_AUTOCONNECT;
-        const INACTIVE = pw_sys::pw_stream_flags_PW_STREAM_FLAG_INACTIVE;
-        const MAP_BUFFERS = pw_sys::pw_stream_flags_PW_STREAM_FLAG_MAP_BUFFERS;
-        const DRIVER = pw_sys::pw_stream_flags_PW_STREAM_FLAG_DRIVER;
-        const RT_PROCESS = pw_sys::pw_stream_flags_PW_STREAM_FLAG_RT_PROCESS;
-        const NO_CONVERT = pw_sys::pw_stream_flags_PW_STREAM_FLAG_NO_CONVERT;
-        const EXCLUSIVE = pw_sys::pw_stream_flags_PW_STREAM_FLAG_EXCLUSIVE;
-        const DONT_RECONNECT = pw_sys::pw_stream_flags_PW_STREAM_FLAG_DONT_RECONNECT;
-        const ALLOC_BUFFERS = pw_sys::pw_stream_flags_PW_STREAM_FLAG_ALLOC_BUFFERS;
+    pub struct StreamFlags: u32 {
+        const AUTOCONNECT = pw_sys::pw_stream_flags::PW_STREAM_FLAG_AUTOCONNECT.0;
+        const INACTIVE = pw_sys::pw_stream_flags::PW_STREAM_FLAG_INACTIVE.0;
+        const MAP_BUFFERS = pw_sys::pw_stream_flags::PW_STREAM_FLAG_MAP_BUFFERS.0;
+        const DRIVER = pw_sys::pw_stream_flags::PW_STREAM_FLAG_DRIVER.0;
+        const RT_PROCESS = pw_sys::pw_stream_flags::PW_STREAM_FLAG_RT_PROCESS.0;
+        const NO_CONVERT = pw_sys::pw_stream_flags::PW_STREAM_FLAG_NO_CONVERT.0;
+        const EXCLUSIVE = pw_sys::pw_stream_flags::PW_STREAM_FLAG_EXCLUSIVE.0;
+        const DONT_RECONNECT = pw_sys::pw_stream_flags::PW_STREAM_FLAG_DONT_RECONNECT.0;
+        /// The application allocates the memory of the buffers in the `add_buffer` callback,
+        /// see [`attach_memfd`](crate::mem::attach_memfd)
+        const ALLOC_BUFFERS = pw_sys::pw_stream_flags::PW_STREAM_FLAG_ALLOC_BUFFERS.0;
         #[cfg(feature = "v0_3_41")]
-        const TRIGGER = pw_sys::pw_stream_flags_PW_STREAM_FLAG_TRIGGER;
+        const TRIGGER = pw_sys::pw_stream_flags::PW_STREAM_FLAG_TRIGGER.0;
+        /// Buffers are not dequeued and queued from the realtime `process` callback,
+        /// available since PipeWire 0.3.73
+        #[cfg(feature = "v0_3_77")]
+        const ASYNC = pw_sys::pw_stream_flags::PW_STREAM_FLAG_ASYNC.0;
+    }
+}
+
+/// The node a stream connects to, see [`StreamRef::connect_target`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// Let the session manager pick a suitable node.
+    Any,
+    /// The object with this global id.
+    ///
+    /// Global ids are reused once their object is destroyed, prefer [`Target::Serial`].
+    GlobalId(u32),
+    /// The object with this `object.serial`.
+    #[cfg(feature = "v0_3_44")]
+    Serial(u64),
+    /// The object with this `node.name`, or `object.path`.
+    #[cfg(feature = "v0_3_44")]
+    Name(String),
+}
+
+/// Builder connecting a stream, see [`StreamRef::connect_builder`].
+///
+/// [`connect`](Self::connect) rejects these combinations, which the C API accepts and then
+/// silently ignores or fails on later:
+/// - [`alloc_buffers`](Self::alloc_buffers) with [`map_buffers`](Self::map_buffers):
+///   the application allocates the memory itself, there is nothing for PipeWire to map.
+/// - A [`target`](Self::target) other than [`Target::Any`] without [`autoconnect`](Self::autoconnect):
+///   the session manager only links streams asking for it.
+/// - [`dont_reconnect`](Self::dont_reconnect) without [`autoconnect`](Self::autoconnect).
+///
+/// A stream connected with [`inactive`](Self::inactive) does not process buffers until
+/// [`StreamRef::set_active`] is called.
+#[must_use = "Fluent builder API"]
+pub struct StreamConnectBuilder<'a> {
+    stream: &'a StreamRef,
+    direction: spa::utils::Direction,
+    target: Target,
+    flags: StreamFlags,
+    params: Vec<&'a spa::pod::Pod>,
+}
+
+impl<'a> StreamConnectBuilder<'a> {
+    /// Ask the session manager to link the stream, see [`StreamFlags::AUTOCONNECT`].
+    pub fn autoconnect(self, enabled: bool) -> Self {
+        self.flag(StreamFlags::AUTOCONNECT, enabled)
+    }
+
+    /// Start the stream inactive, see [`StreamFlags::INACTIVE`].
+    pub fn inactive(self, enabled: bool) -> Self {
+        self.flag(StreamFlags::INACTIVE, enabled)
+    }
+
+    /// Map the memory of the buffers, see [`StreamFlags::MAP_BUFFERS`].
+    pub fn map_buffers(self, enabled: bool) -> Self {
+        self.flag(StreamFlags::MAP_BUFFERS, enabled)
+    }
+
+    /// Let the application allocate the buffers, see [`StreamFlags::ALLOC_BUFFERS`].
+    pub fn alloc_buffers(self, enabled: bool) -> Self {
+        self.flag(StreamFlags::ALLOC_BUFFERS, enabled)
+    }
+
+    /// Call the `process` callback from the realtime thread, see [`StreamFlags::RT_PROCESS`].
+    pub fn rt_process(self, enabled: bool) -> Self {
+        self.flag(StreamFlags::RT_PROCESS, enabled)
+    }
+
+    /// Do not link the stream again when its target goes away,
+    /// see [`StreamFlags::DONT_RECONNECT`].
+    pub fn dont_reconnect(self, enabled: bool) -> Self {
+        self.flag(StreamFlags::DONT_RECONNECT, enabled)
+    }
+
+    /// Set or clear any other `flags`.
+    pub fn flag(mut self, flags: StreamFlags, enabled: bool) -> Self {
+        self.flags.set(flags, enabled);
+        self
+    }
+
+    /// The node to connect to, [`Target::Any`] by default.
+    pub fn target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// The params offered when connecting, such as the supported formats.
+    pub fn params(mut self, params: &[&'a spa::pod::Pod]) -> Self {
+        self.params = params.to_vec();
+        self
+    }
+
+    /// The flags the stream will be connected with, if they can be used together
+    /// with the target.
+    pub fn check(&self) -> Result<StreamFlags, ConnectError> {
+        let autoconnect = self.flags.contains(StreamFlags::AUTOCONNECT);
+
+        if self
+            .flags
+            .contains(StreamFlags::ALLOC_BUFFERS | StreamFlags::MAP_BUFFERS)
+        {
+            return Err(ConnectError::MapAllocatedBuffers);
+        }
+        if !autoconnect && self.target != Target::Any {
+            return Err(ConnectError::TargetWithoutAutoconnect);
+        }
+        if !autoconnect && self.flags.contains(StreamFlags::DONT_RECONNECT) {
+            return Err(ConnectError::DontReconnectWithoutAutoconnect);
+        }
+        #[cfg(feature = "v0_3_44")]
+        if matches!(&self.target, Target::Name(name) if name.contains('\0')) {
+            return Err(ConnectError::InvalidTargetName);
+        }
+
+        Ok(self.flags)
+    }
+
+    /// Connect the stream, as [`StreamRef::connect_target`] with the built flags.
+    pub fn connect(mut self) -> Result<(), ConnectError> {
+        let flags = self.check()?;
+        self.stream
+            .connect_target(self.direction, self.target, flags, &mut self.params)?;
+        Ok(())
+    }
+}
+
+/// Error returned by [`StreamConnectBuilder::connect`].
+#[derive(thiserror::Error, Debug)]
+pub enum ConnectError {
+    /// [`StreamFlags::MAP_BUFFERS`] was used with [`StreamFlags::ALLOC_BUFFERS`].
+    #[error("buffers allocated by the application cannot be mapped by PipeWire")]
+    MapAllocatedBuffers,
+    /// A target was given without [`StreamFlags::AUTOCONNECT`].
+    #[error("the target is ignored unless the stream is autoconnected")]
+    TargetWithoutAutoconnect,
+    /// [`StreamFlags::DONT_RECONNECT`] was used without [`StreamFlags::AUTOCONNECT`].
+    #[error("a stream which is not autoconnected is never reconnected")]
+    DontReconnectWithoutAutoconnect,
+    /// The name of a [`Target::Name`] contains a null byte.
+    #[error("the target name contains a null byte")]
+    InvalidTargetName,
+    /// PipeWire failed to connect the stream.
+    #[error(transparent)]
+    Failed(#[from] Error),
+}
+
+/// Convert the rejected combinations to `EINVAL`, as returned by [`StreamRef::connect_target`]
+/// for an invalid target name.
+impl From<ConnectError> for Error {
+    fn from(err: ConnectError) -> Self {
+        match err {
+            ConnectError::Failed(err) => err,
+            _ => nix::errno::Errno::EINVAL.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{context::Context, main_loop::MainLoop, properties::properties};
+
+    #[test]
+    fn state_from_raw() {
+        let error = std::ffi::CString::new("no target node").unwrap();
+        assert_eq!(
+            StreamState::from_raw(
+                pw_sys::pw_stream_state::PW_STREAM_STATE_STREAMING,
+                ptr::null()
+            ),
+            StreamState::Streaming
+        );
+
+        let state = StreamState::from_raw(
+            pw_sys::pw_stream_state::PW_STREAM_STATE_ERROR,
+            error.as_ptr(),
+        );
+        assert!(state.is_error());
+        assert_eq!(state.error(), Some("no target node"));
+
+        let state =
+            StreamState::from_raw(pw_sys::pw_stream_state::PW_STREAM_STATE_ERROR, ptr::null());
+        assert!(state.is_error());
+        assert_eq!(state.error(), Some(""));
+        assert_eq!(StreamState::Paused.error(), None);
+    }
+
+    #[test]
+    fn state() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let stream = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+
+        assert_eq!(stream.state(), StreamState::Unconnected);
+        assert!(!stream.is_streaming());
+    }
+
+    #[test]
+    fn set_error() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let stream = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+
+        let last_state = std::rc::Rc::new(std::cell::RefCell::new(None));
+        let _listener = stream
+            .add_local_listener::<()>()
+            .state_changed({
+                let last_state = last_state.clone();
+                move |_, _, _, new| *last_state.borrow_mut() = Some(new)
+            })
+            .register()
+            .unwrap();
+
+        // a message which would be a format string, with a 0 byte
+        stream.set_error(-libc::EIO, "device %s unplugged\0ignored");
+
+        let expected = StreamState::Error("device %s unplugged".to_string());
+        assert_eq!(stream.state(), expected);
+        assert_eq!(last_state.borrow_mut().take(), Some(expected));
+    }
+
+    #[test]
+    fn nested_state_changed() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let stream = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+
+        let states = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let _listener = stream
+            .add_local_listener_with_user_data(false)
+            .state_changed({
+                let states = states.clone();
+                move |stream, in_callback, _, new| {
+                    assert!(!*in_callback);
+                    *in_callback = true;
+                    states.borrow_mut().push(new);
+                    if states.borrow().len() == 1 {
+                        // emits a state change before this callback returns
+                        stream.set_error(-libc::EIO, "second");
+                    }
+                    *in_callback = false;
+                }
+            })
+            .register()
+            .unwrap();
+
+        stream.set_error(-libc::EIO, "first");
+
+        assert_eq!(
+            *states.borrow(),
+            [
+                StreamState::Error("first".to_string()),
+                StreamState::Error("second".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_events() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let stream = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+
+        use os::raw::c_void;
+        type IoChanged = unsafe extern "C" fn(*mut c_void, u32, *mut c_void, u32);
+        let io_changed = std::rc::Rc::new(cell::Cell::new(None::<(IoChanged, *mut c_void)>));
+        let listener = stream
+            .add_local_listener_with_user_data(Vec::new())
+            .state_changed({
+                let io_changed = io_changed.clone();
+                move |_, events, _, _| {
+                    events.push("state begin");
+                    // the io area changes before this callback returns
+                    let (io_changed, data) = io_changed.get().unwrap();
+                    unsafe { io_changed(data, 0, ptr::null_mut(), 0) };
+                    events.push("state end");
+                }
+            })
+            .io_changed(|_, events, _, _, _| events.push("io"))
+            .register()
+            .unwrap();
+        let data: *const ListenerState<Vec<&str>> = &*listener._data;
+        io_changed.set(Some((
+            listener._events.io_changed.unwrap(),
+            data.cast_mut().cast(),
+        )));
+
+        stream.set_error(-libc::EIO, "error");
+
+        let events = unsafe { &(*listener._data.callbacks.get()).user_data };
+        assert_eq!(*events, ["state begin", "state end", "io"]);
+    }
+
+    #[test]
+    fn nested_process() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let stream = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+
+        use os::raw::c_void;
+        type Process = unsafe extern "C" fn(*mut c_void);
+        type IoChanged = unsafe extern "C" fn(*mut c_void, u32, *mut c_void, u32);
+        let emit = std::rc::Rc::new(cell::Cell::new(None::<(Process, IoChanged, *mut c_void)>));
+        let events = std::rc::Rc::new(cell::RefCell::new(Vec::new()));
+        let listener = stream
+            .add_local_listener()
+            .state_changed({
+                let (emit, events) = (emit.clone(), events.clone());
+                move |_, _: &mut (), _, _| {
+                    events.borrow_mut().push("state begin");
+                    let (process, io_changed, data) = emit.get().unwrap();
+                    unsafe {
+                        // both delivered once this callback returns, never dropped
+                        io_changed(data, 0, ptr::null_mut(), 0);
+                        process(data);
+                    }
+                    events.borrow_mut().push("state end");
+                }
+            })
+            .io_changed({
+                let events = events.clone();
+                move |_, _, _, _, _| events.borrow_mut().push("io")
+            })
+            .process({
+                let events = events.clone();
+                move |_, _| events.borrow_mut().push("process")
+            })
+            .register()
+            .unwrap();
+        let data: *const ListenerState<()> = &*listener._data;
+        emit.set(Some((
+            listener._events.process.unwrap(),
+            listener._events.io_changed.unwrap(),
+            data.cast_mut().cast(),
+        )));
+
+        stream.set_error(-libc::EIO, "error");
+
+        assert_eq!(
+            *events.borrow(),
+            ["state begin", "state end", "io", "process"]
+        );
+    }
+
+    #[test]
+    fn nested_buffers() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let stream = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+
+        use os::raw::c_void;
+        type BufferEvent = unsafe extern "C" fn(*mut c_void, *mut pw_sys::pw_buffer);
+        let emit = std::rc::Rc::new(cell::Cell::new(
+            None::<(BufferEvent, BufferEvent, *mut c_void)>,
+        ));
+        let mut first: pw_sys::pw_buffer = unsafe { mem::zeroed() };
+        first.size = 1;
+        let mut second: pw_sys::pw_buffer = unsafe { mem::zeroed() };
+        second.size = 2;
+        let buffers = (ptr::addr_of_mut!(first), ptr::addr_of_mut!(second));
+        let listener = stream
+            .add_local_listener_with_user_data(Vec::new())
+            .state_changed({
+                let emit = emit.clone();
+                move |stream, events: &mut Vec<String>, _, _| {
+                    if !events.is_empty() {
+                        return;
+                    }
+                    events.push("state begin".to_string());
+                    let _ = stream.disconnect();
+                    // as disconnecting does with the buffers of a connected stream
+                    let (add_buffer, remove_buffer, data) = emit.get().unwrap();
+                    unsafe {
+                        add_buffer(data, buffers.0);
+                        remove_buffer(data, buffers.0);
+                        add_buffer(data, buffers.1);
+                    }
+                    events.push("state end".to_string());
+                }
+            })
+            .add_buffer(|_, events, buffer| events.push(format!("add {}", buffer.size())))
+            .remove_buffer(|_, events, buffer| events.push(format!("remove {}", buffer.size())))
+            .register()
+            .unwrap();
+        let data: *const ListenerState<Vec<String>> = &*listener._data;
+        emit.set(Some((
+            listener._events.add_buffer.unwrap(),
+            listener._events.remove_buffer.unwrap(),
+            data.cast_mut().cast(),
+        )));
+
+        stream.set_error(-libc::EIO, "error");
+
+        // the first buffer was removed before the callback returned, so it is never seen
+        let events = unsafe { &(*listener._data.callbacks.get()).user_data };
+        assert_eq!(*events, ["state begin", "state end", "add 2"]);
+    }
+
+    #[test]
+    fn update_properties() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let stream = Stream::new(
+            &core,
+            "pipewire-rs-test",
+            properties! {
+                "media.name" => "first",
+            },
+        )
+        .unwrap();
+
+        let changed = stream
+            .update_properties(&properties! {
+                "media.name" => "second",
+                "media.icon-name" => "audio-x-generic",
+            })
+            .unwrap();
+        assert_eq!(changed, 2);
+        assert_eq!(stream.properties().get("media.name"), Some("second"));
+        assert_eq!(
+            stream.properties().get("media.icon-name"),
+            Some("audio-x-generic")
+        );
+
+        // setting the same values again does not change anything
+        let props = stream.properties().to_owned();
+        assert_eq!(stream.update_properties(&props).unwrap(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "v0_3_44")]
+    fn connect_target() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+
+        let stream = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+        stream
+            .connect_target(
+                spa::utils::Direction::Output,
+                Target::Name("pipewire-rs-test-sink".to_string()),
+                StreamFlags::empty(),
+                &mut [],
+            )
+            .unwrap();
+        assert_eq!(
+            stream.properties().get("target.object"),
+            Some("pipewire-rs-test-sink")
+        );
+
+        let stream = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+        stream
+            .connect_target(
+                spa::utils::Direction::Input,
+                Target::Serial(1234),
+                StreamFlags::empty(),
+                &mut [],
+            )
+            .unwrap();
+        assert_eq!(stream.properties().get("target.object"), Some("1234"));
+
+        let stream = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+        let err = stream
+            .connect_target(
+                spa::utils::Direction::Input,
+                Target::Name("bad\0name".to_string()),
+                StreamFlags::empty(),
+                &mut [],
+            )
+            .unwrap_err();
+        assert_eq!(err.errno(), Some(nix::errno::Errno::EINVAL));
+    }
+
+    #[test]
+    fn connect_builder_invalid() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let stream = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+        let builder = || stream.connect_builder(spa::utils::Direction::Input);
+
+        assert!(matches!(
+            builder()
+                .autoconnect(true)
+                .alloc_buffers(true)
+                .map_buffers(true)
+                .connect(),
+            Err(ConnectError::MapAllocatedBuffers)
+        ));
+        assert!(matches!(
+            builder().target(Target::GlobalId(42)).connect(),
+            Err(ConnectError::TargetWithoutAutoconnect)
+        ));
+        assert!(matches!(
+            builder().dont_reconnect(true).connect(),
+            Err(ConnectError::DontReconnectWithoutAutoconnect)
+        ));
+        #[cfg(feature = "v0_3_44")]
+        assert!(matches!(
+            builder()
+                .autoconnect(true)
+                .target(Target::Name("bad\0name".to_string()))
+                .connect(),
+            Err(ConnectError::InvalidTargetName)
+        ));
+
+        // nothing was done to the stream
+        assert_eq!(stream.state(), StreamState::Unconnected);
+        assert_eq!(stream.properties().get("target.object"), None);
+
+        let err = Error::from(builder().dont_reconnect(true).connect().unwrap_err());
+        assert_eq!(err.errno(), Some(nix::errno::Errno::EINVAL));
+    }
+
+    #[test]
+    #[cfg(feature = "v0_3_44")]
+    fn connect_builder() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let flags = StreamFlags::AUTOCONNECT | StreamFlags::INACTIVE | StreamFlags::MAP_BUFFERS;
+
+        let built = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+        let b = built
+            .connect_builder(spa::utils::Direction::Input)
+            .autoconnect(true)
+            .inactive(true)
+            .map_buffers(true)
+            .rt_process(true)
+            .rt_process(false)
+            .target(Target::Name("pipewire-rs-test-source".to_string()));
+        assert_eq!(b.check().unwrap(), flags);
+        b.connect().unwrap();
+
+        let raw = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+        raw.connect_target(
+            spa::utils::Direction::Input,
+            Target::Name("pipewire-rs-test-source".to_string()),
+            flags,
+            &mut [],
+        )
+        .unwrap();
+
+        assert_eq!(built.state(), raw.state());
+        assert_ne!(built.state(), StreamState::Unconnected);
+        assert_eq!(
+            built.properties().get("target.object"),
+            raw.properties().get("target.object")
+        );
+
+        // other flags are passed through as given
+        let stream = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+        let b = stream
+            .connect_builder(spa::utils::Direction::Output)
+            .flag(StreamFlags::DRIVER | StreamFlags::ALLOC_BUFFERS, true);
+        assert_eq!(
+            b.check().unwrap(),
+            StreamFlags::DRIVER | StreamFlags::ALLOC_BUFFERS
+        );
+        b.connect().unwrap();
+    }
+
+    #[test]
+    fn clock_tracker() {
+        let cycle = |position, quantum| ClockCycle {
+            id: 1,
+            rate: 48000,
+            quantum,
+            position,
+        };
+        let mut tracker = ClockTracker::default();
+
+        // the first cycle reports the initial quantum
+        assert_eq!(tracker.update(cycle(0, 1024)), (true, false));
+        assert_eq!(tracker.update(cycle(1024, 1024)), (false, false));
+        assert_eq!(tracker.update(cycle(2048, 256)), (true, false));
+        assert_eq!(tracker.update(cycle(2304, 256)), (false, false));
+        // two cycles were skipped
+        assert_eq!(tracker.update(cycle(3072, 256)), (false, true));
+        assert_eq!(tracker.xruns, 1);
+        assert_eq!(
+            tracker.update(ClockCycle {
+                rate: 44100,
+                ..cycle(3328, 256)
+            }),
+            (true, false)
+        );
+        // the position of another driver is unrelated
+        assert_eq!(
+            tracker.update(ClockCycle {
+                id: 2,
+                rate: 44100,
+                ..cycle(100_000, 256)
+            }),
+            (false, false)
+        );
+        assert_eq!(tracker.xruns, 1);
+    }
+
+    #[test]
+    #[cfg(feature = "v0_3_45")]
+    fn quantum_changed() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let stream = Stream::new(
+            &core,
+            "pipewire-rs-test",
+            properties! {
+                "node.always-process" => "true",
+                "node.force-quantum" => "256",
+            },
+        )
+        .unwrap();
+
+        let quanta = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let _listener = stream
+            .add_local_listener::<()>()
+            .quantum_changed({
+                let quanta = quanta.clone();
+                move |_, _, rate, quantum| quanta.borrow_mut().push((rate, quantum))
+            })
+            .register()
+            .unwrap();
+
+        stream
+            .connect(
+                spa::utils::Direction::Output,
+                None,
+                StreamFlags::DRIVER,
+                &mut [],
+            )
+            .unwrap();
+        let wait_for_quantum = |quantum| {
+            for _ in 0..100 {
+                if quanta.borrow().last().map(|(_, q)| *q) == Some(quantum) {
+                    return;
+                }
+                // the stream drives the graph, a cycle starts when it is triggered
+                let _ = stream.trigger_process();
+                mainloop
+                    .loop_()
+                    .iterate(std::time::Duration::from_millis(10));
+            }
+            panic!(
+                "quantum never changed to {quantum}, got {:?}",
+                quanta.borrow()
+            );
+        };
+
+        wait_for_quantum(256);
+        stream
+            .update_properties(&properties! {
+                "node.force-quantum" => "512",
+            })
+            .unwrap();
+        wait_for_quantum(512);
+
+        let quanta = quanta.borrow();
+        assert!(quanta.iter().all(|(rate, _)| *rate > 0));
+        assert_eq!(quanta.iter().filter(|(_, q)| *q == 512).count(), 1);
+    }
+
+    #[test]
+    fn queue_disconnected() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let stream = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+        assert!(stream.dequeue_buffer().is_none());
+
+        // a buffer still dequeued when its stream was disconnected is not queued back
+        let mut raw: pw_sys::pw_buffer = unsafe { mem::zeroed() };
+        let buffer = unsafe { Buffer::from_raw(&mut raw, &stream) }.unwrap();
+        let err = buffer.queue().unwrap_err();
+        assert_eq!(err.errno(), Some(nix::errno::Errno::EPIPE));
+
+        let buffer = unsafe { Buffer::from_raw(&mut raw, &stream) }.unwrap();
+        drop(buffer);
+
+        let buffer = unsafe { Buffer::from_raw(&mut raw, &stream) }.unwrap();
+        assert_eq!(buffer.into_raw(), &mut raw as *mut _);
+
+        let buffer = unsafe { Buffer::from_raw(&mut raw, &stream) }.unwrap();
+        buffer.discard();
+    }
+
+    #[test]
+    fn queue_reconnected() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let stream = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+
+        // a buffer dequeued before the stream was reconnected is not queued back nor accessed
+        let mut raw: pw_sys::pw_buffer = unsafe { mem::zeroed() };
+        // as when the stream adds its buffers
+        unsafe { crate::core::stream_buffers(core.as_raw_ptr()) }
+            .unwrap()
+            .write()
+            .insert(
+                ptr::addr_of_mut!(raw) as usize,
+                ptr::NonNull::from(&stream.generation.state.generation),
+            );
+        let mut buffer = unsafe { Buffer::from_raw(&mut raw, &stream) }.unwrap();
+        assert!(!buffer.is_reclaimed());
+        assert!(buffer.get().is_some());
+        let connect = || {
+            stream
+                .connect(
+                    spa::utils::Direction::Output,
+                    None,
+                    StreamFlags::empty(),
+                    &mut [],
+                )
+                .unwrap()
+        };
+        connect();
+        stream.disconnect().unwrap();
+        connect();
+        assert!(buffer.is_reclaimed());
+
+        assert!(buffer.get_mut().is_none());
+        assert!(buffer.datas_mut().is_empty());
+        let err = buffer.queue().unwrap_err();
+        assert_eq!(err.errno(), Some(nix::errno::Errno::EPIPE));
+    }
+
+    #[test]
+    fn node_id() {
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let stream = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+        assert_eq!(stream.core().as_raw_ptr(), core.as_raw_ptr());
+        assert_eq!(stream.node_id(), crate::constants::ID_ANY);
+
+        stream
+            .connect(
+                spa::utils::Direction::Output,
+                None,
+                StreamFlags::empty(),
+                &mut [],
+            )
+            .unwrap();
+        for _ in 0..100 {
+            if stream.node_id() != crate::constants::ID_ANY {
+                break;
+            }
+            mainloop
+                .loop_()
+                .iterate(std::time::Duration::from_millis(10));
+        }
+        let node_id = stream.node_id();
+        assert_ne!(node_id, crate::constants::ID_ANY);
+
+        let registry = core.get_registry().unwrap();
+        let globals = registry.snapshot(&core, &mainloop).unwrap();
+        let node = globals
+            .iter()
+            .find(|global| global.id == node_id)
+            .expect("stream node not in the registry");
+        assert_eq!(node.type_, crate::types::ObjectType::Node);
+    }
+
+    #[test]
+    fn ports() {
+        use crate::registry::{find_port, ports_of_node, GlobalObject, NodeKey};
+        use spa::param::{
+            audio::AudioFormat,
+            format::{FormatProperties, MediaSubtype, MediaType},
+            ParamType,
+        };
+        use spa::pod::{ObjectBuilder, ValueArray};
+        use spa::utils::{Direction, Id, SpaTypes};
+
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+
+        let sink = core
+            .create_object_pending::<crate::node::Node>(
+                "adapter",
+                &properties! {
+                    "factory.name" => "support.null-audio-sink",
+                    "node.name" => "pipewire-rs.test.ports",
+                    "media.class" => "Audio/Sink",
+                    "audio.position" => "FL,FR",
+                    "object.linger" => "false",
+                },
+            )
+            .unwrap();
+        let sink_id = sink
+            .wait_bound(&mainloop, std::time::Duration::from_secs(5))
+            .unwrap();
+
+        let format = ObjectBuilder::new(SpaTypes::ObjectParamFormat, ParamType::EnumFormat)
+            .prop(FormatProperties::MediaType, MediaType::Audio)
+            .prop(FormatProperties::MediaSubtype, MediaSubtype::Raw)
+            .prop(FormatProperties::AudioFormat, AudioFormat::F32LE)
+            .prop(FormatProperties::AudioRate, 48000)
+            .prop(FormatProperties::AudioChannels, 2)
+            .prop(
+                FormatProperties::AudioPosition,
+                ValueArray::Id(vec![
+                    Id(spa::sys::SPA_AUDIO_CHANNEL_FL),
+                    Id(spa::sys::SPA_AUDIO_CHANNEL_FR),
+                ]),
+            )
+            .build();
+        let stream = Stream::new(
+            &core,
+            "pipewire-rs-test",
+            properties! {
+                "media.type" => "Audio",
+                "media.category" => "Playback",
+            },
+        )
+        .unwrap();
+        assert!(stream.ports::<Properties>(&[]).is_empty());
+        stream
+            .connect(
+                Direction::Output,
+                None,
+                StreamFlags::AUTOCONNECT,
+                &mut [format.as_pod()],
+            )
+            .unwrap();
+
+        // the ports are added once the node of the stream is configured
+        let mut globals = Vec::new();
+        for _ in 0..100 {
+            mainloop
+                .loop_()
+                .iterate(std::time::Duration::from_millis(10));
+            globals = core
+                .get_registry()
+                .unwrap()
+                .snapshot(&core, &mainloop)
+                .unwrap();
+            if stream.ports(&globals).len() == 2 {
+                break;
+            }
+        }
+
+        let prop = |port: &GlobalObject<Properties>, key| {
+            port.props.as_ref().unwrap().get(key).map(str::to_owned)
+        };
+        let ports = stream.ports(&globals);
+        assert_eq!(ports.len(), 2);
+        assert!(ports
+            .iter()
+            .all(|port| prop(port, "port.direction").as_deref() == Some("out")));
+        let node = NodeKey::Id(stream.node_id());
+        let fl = find_port(&globals, node, Direction::Output, "FL").unwrap();
+        assert_eq!(prop(fl, "audio.channel").as_deref(), Some("FL"));
+        assert!(find_port(&globals, node, Direction::Input, "FL").is_none());
+
+        // the sink has input ports, and a monitor output port for each channel
+        let serial = globals
+            .iter()
+            .find(|global| global.id == sink_id)
+            .and_then(GlobalObject::serial)
+            .unwrap();
+        let sink_ports = ports_of_node(&globals, NodeKey::Serial(serial));
+        assert_eq!(sink_ports.len(), 4);
+        let monitor = find_port(&globals, NodeKey::Id(sink_id), Direction::Output, "FR").unwrap();
+        assert_eq!(prop(monitor, "port.name").as_deref(), Some("monitor_FR"));
+    }
+
+    #[test]
+    fn update_params_from_param_changed() {
+        use spa::pod::{serialize::PodSerializer, Object, Pod, Property, Value};
+
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let stream = Stream::new(&core, "pipewire-rs-test", Properties::new()).unwrap();
+
+        let results = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let _listener = stream
+            .add_local_listener::<()>()
+            .param_changed({
+                let results = results.clone();
+                move |stream, _, id, param| {
+                    if id != spa::param::ParamType::Props.as_raw() || param.is_none() {
+                        return;
+                    }
+                    // answer with a Buffers param built on the stack, gone after the call
+                    let mut buf = [0u8; 256];
+                    let buffers = Value::Object(Object {
+                        type_: spa::utils::SpaTypes::ObjectParamBuffers.as_raw(),
+                        id: spa::param::ParamType::Buffers.as_raw(),
+                        properties: vec![
+                            Property::new(spa::sys::SPA_PARAM_BUFFERS_buffers, Value::Int(4)),
+                            Property::new(spa::sys::SPA_PARAM_BUFFERS_size, Value::Int(4096)),
+                        ],
+                    });
+                    let (cursor, _) =
+                        PodSerializer::serialize(std::io::Cursor::new(&mut buf[..]), &buffers)
+                            .unwrap();
+                    let len = cursor.position() as usize;
+                    let buffers = Pod::from_bytes(&buf[..len]).unwrap();
+                    results.borrow_mut().push(stream.update_params(&[buffers]));
+                }
+            })
+            .register()
+            .unwrap();
+
+        stream
+            .connect(
+                spa::utils::Direction::Output,
+                None,
+                StreamFlags::empty(),
+                &mut [],
+            )
+            .unwrap();
+        for _ in 0..100 {
+            if stream.node_id() != crate::constants::ID_ANY {
+                break;
+            }
+            mainloop
+                .loop_()
+                .iterate(std::time::Duration::from_millis(10));
+        }
+
+        // setting the props of the node from the outside emits param_changed on the stream
+        let registry = core.get_registry().unwrap();
+        let globals = registry.snapshot(&core, &mainloop).unwrap();
+        let global = globals
+            .iter()
+            .find(|global| global.id == stream.node_id())
+            .expect("stream node not in the registry");
+        let node: crate::node::Node = registry.bind(global).unwrap();
+        let props = spa::pod::ObjectBuilder::new(
+            spa::utils::SpaTypes::ObjectParamProps,
+            spa::param::ParamType::Props,
+        )
+        .build();
+        node.set_param(spa::param::ParamType::Props, 0, props.as_pod())
+            .unwrap();
+
+        for _ in 0..100 {
+            if !results.borrow().is_empty() {
+                break;
+            }
+            mainloop
+                .loop_()
+                .iterate(std::time::Duration::from_millis(10));
+        }
+        let results = results.borrow();
+        assert!(!results.is_empty());
+        assert!(results.iter().all(Result::is_ok));
     }
 }
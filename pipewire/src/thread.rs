@@ -0,0 +1,147 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Realtime scheduling of threads.
+//!
+//! libpipewire makes the thread of the data loop of a [`Context`](crate::context::Context)
+//! realtime when `libpipewire-module-rt` is loaded, which the default client configuration does.
+//! The priority is the `rt.prio` argument of the module, and realtime is acquired through
+//! the scheduler directly or through RTKit/the realtime portal when the process is not allowed to.
+//! Streams created with the [`RT_PROCESS`](crate::stream::StreamFlags::RT_PROCESS) flag call
+//! their `process` callback from that thread.
+//!
+//! Other threads, such as the one of a [`ThreadLoop`](crate::thread_loop::ThreadLoop),
+//! are not realtime. Code running on them can opt in with [`ThreadUtils::acquire_rt`],
+//! using the [`ThreadUtils`] of the context to go through the RT module the same way.
+//!
+//! ```no_run
+//! use pipewire::{context::Context, main_loop::MainLoop, thread};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mainloop = MainLoop::new(None)?;
+//! let context = Context::new(&mainloop)?;
+//!
+//! let utils = context.thread_utils();
+//! let range = utils.rt_priority_range(None)?;
+//! utils.acquire_rt(*range.end())?;
+//! println!("realtime: {}", thread::is_realtime());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{marker::PhantomData, ops::RangeInclusive, ptr};
+
+use crate::Error;
+use spa::utils::{dict::DictRef, result::SpaResult};
+
+/// Call `method` of `utils`, or fail with `ENOTSUP` if it is not implemented.
+macro_rules! thread_utils_call {
+    ($utils:expr, $method:ident, $( $arg:expr ),*) => {{
+        let iface: *mut spa_sys::spa_interface = $utils.ptr.as_ptr().cast();
+        let funcs: *const spa_sys::spa_thread_utils_methods = (*iface).cb.funcs.cast();
+        match (*funcs).$method {
+            Some(f) => f((*iface).cb.data, $($arg),*),
+            None => -libc::ENOTSUP,
+        }
+    }};
+}
+
+/// An implementation of the thread utilities of SPA, used to create threads and manage
+/// their scheduling.
+#[derive(Debug, Clone, Copy)]
+pub struct ThreadUtils<'a> {
+    ptr: ptr::NonNull<spa_sys::spa_thread_utils>,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl ThreadUtils<'static> {
+    /// The global thread utilities of libpipewire, which change the scheduler of threads
+    /// directly, without going through the RT module.
+    pub fn global() -> Self {
+        let ptr = unsafe { pw_sys::pw_thread_utils_get() };
+        Self {
+            ptr: ptr::NonNull::new(ptr).expect("pw_thread_utils_get() returned NULL"),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a> ThreadUtils<'a> {
+    /// Wrap a raw `spa_thread_utils`.
+    ///
+    /// # Safety
+    /// `ptr` must point to a valid `spa_thread_utils` living for `'a`.
+    pub unsafe fn from_raw(ptr: ptr::NonNull<spa_sys::spa_thread_utils>) -> Self {
+        Self {
+            ptr,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn as_raw_ptr(&self) -> *mut spa_sys::spa_thread_utils {
+        self.ptr.as_ptr()
+    }
+
+    /// The range of realtime priorities that can be acquired.
+    ///
+    /// `props` can hold the `thread.affinity` and other properties used to create threads.
+    pub fn rt_priority_range(&self, props: Option<&DictRef>) -> Result<RangeInclusive<i32>, Error> {
+        let props = props.map_or(ptr::null(), |props| props.as_raw_ptr().cast_const());
+        let (mut min, mut max) = (0, 0);
+        let res = unsafe { thread_utils_call!(self, get_rt_range, props, &mut min, &mut max) };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(min..=max)
+    }
+
+    /// Acquire realtime scheduling with `priority` for the calling thread.
+    ///
+    /// Use [`is_realtime`] to check the resulting scheduling policy.
+    pub fn acquire_rt(&self, priority: i32) -> Result<(), Error> {
+        let res = unsafe { thread_utils_call!(self, acquire_rt, current_thread(), priority) };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
+    }
+
+    /// Drop the realtime scheduling of the calling thread.
+    pub fn drop_rt(&self) -> Result<(), Error> {
+        let res = unsafe { thread_utils_call!(self, drop_rt, current_thread()) };
+
+        SpaResult::from_c(res).into_result()?;
+        Ok(())
+    }
+}
+
+/// The calling thread, as a `spa_thread` is a `pthread_t` in libpipewire.
+fn current_thread() -> *mut spa_sys::spa_thread {
+    unsafe { libc::pthread_self() as *mut spa_sys::spa_thread }
+}
+
+/// Whether the calling thread runs with a realtime scheduling policy, `SCHED_FIFO` or `SCHED_RR`.
+///
+/// This can be called from a `process` callback to check that realtime was actually acquired.
+pub fn is_realtime() -> bool {
+    let policy = unsafe { libc::sched_getscheduler(0) } & !libc::SCHED_RESET_ON_FORK;
+    policy == libc::SCHED_FIFO || policy == libc::SCHED_RR
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn global() {
+        crate::init();
+
+        let utils = ThreadUtils::global();
+        if let Ok(range) = utils.rt_priority_range(None) {
+            assert!(range.start() <= range.end());
+        }
+        // acquiring may be denied in the test environment, but dropping is always allowed
+        let _ = utils.acquire_rt(1);
+        utils.drop_rt().unwrap();
+        assert!(!is_realtime());
+    }
+}
@@ -3,9 +3,13 @@
 
 use std::{
     ffi::{CStr, CString},
-    mem::MaybeUninit,
+    mem::{ManuallyDrop, MaybeUninit},
     ptr,
     rc::{Rc, Weak},
+    sync::{
+        atomic::{AtomicPtr, Ordering},
+        Arc,
+    },
 };
 
 use crate::{
@@ -16,6 +20,9 @@ use crate::{
 /// A wrapper around the pipewire threaded loop interface. ThreadLoops are a higher level
 /// of abstraction around the loop interface. A ThreadLoop can be used to spawn a new thread
 /// that runs the wrapped loop.
+///
+/// Proxies, streams and their listeners lock the loop while they are dropped, so they can be
+/// dropped without holding the lock, as long as they are dropped before the loop.
 #[derive(Debug, Clone)]
 pub struct ThreadLoop {
     inner: Rc<ThreadLoopInner>,
@@ -68,6 +75,12 @@ impl ThreadLoop {
         self.inner.ptr.as_ptr()
     }
 
+    /// The pointer to the thread loop shared with the objects of its contexts,
+    /// cleared once the thread loop is destroyed.
+    pub(crate) fn shared_ptr(&self) -> &Arc<AtomicPtr<pw_sys::pw_thread_loop>> {
+        &self.inner.shared
+    }
+
     pub fn loop_(&self) -> &LoopRef {
         unsafe {
             let thread_loop = pw_sys::pw_thread_loop_get_loop(self.as_raw_ptr());
@@ -167,10 +180,8 @@ impl ThreadLoop {
     }
 
     /// Check if inside the thread
-    pub fn in_thread(&self) {
-        unsafe {
-            pw_sys::pw_thread_loop_in_thread(self.as_raw_ptr());
-        }
+    pub fn in_thread(&self) -> bool {
+        unsafe { pw_sys::pw_thread_loop_in_thread(self.as_raw_ptr()) }
     }
 }
 
@@ -222,19 +233,258 @@ impl<'a> Drop for ThreadLoopLockGuard<'a> {
     }
 }
 
+/// A value tied to the objects of a [`ThreadLoop`], which can be moved to other threads.
+///
+/// Proxies, listeners and loop sources are not [`Send`], even when their callbacks are:
+/// dropping them removes their hooks from objects of the loop, which races with the loop thread
+/// dispatching events unless the loop is locked, and their callbacks commonly share `Rc`s with each other.
+///
+/// This wrapper allows building such objects on one thread with the loop locked, and moving them
+/// as a bundle to another thread or into the loop thread itself.
+/// The value is only accessible with the loop locked or from the loop thread,
+/// and it is dropped with the loop locked.
+pub struct ThreadBound<T> {
+    value: ManuallyDrop<T>,
+    thread_loop: ptr::NonNull<pw_sys::pw_thread_loop>,
+}
+
+impl<T> ThreadBound<T> {
+    /// Wrap a value built from the objects of `thread_loop`.
+    ///
+    /// # Safety
+    /// - Any non-[`Send`] state of `value`, such as `Rc`s captured by callbacks, must only be shared with
+    ///   the objects of the loop, or with other values that are only accessed with the loop locked.
+    /// - `thread_loop` must outlive the returned wrapper.
+    pub unsafe fn new(thread_loop: &ThreadLoop, value: T) -> Self {
+        Self {
+            value: ManuallyDrop::new(value),
+            thread_loop: thread_loop.inner.ptr,
+        }
+    }
+
+    /// Access the value with the loop locked.
+    pub fn with_lock<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> R {
+        let _lock = RawLockGuard::new(self.thread_loop);
+        f(&mut self.value)
+    }
+
+    /// Unwrap the value, if called from the thread of the loop.
+    ///
+    /// Returns the wrapper back when called from any other thread.
+    pub fn into_inner_on_loop_thread(self) -> Result<T, Self> {
+        if !unsafe { pw_sys::pw_thread_loop_in_thread(self.thread_loop.as_ptr()) } {
+            return Err(self);
+        }
+
+        let mut this = ManuallyDrop::new(self);
+        Ok(unsafe { ManuallyDrop::take(&mut this.value) })
+    }
+}
+
+// Safety: The value is only accessed with the loop locked or from the loop thread, and
+//         the caller of `ThreadBound::new` guarantees its non-Send state does not escape the loop.
+unsafe impl<T> Send for ThreadBound<T> {}
+
+impl<T> Drop for ThreadBound<T> {
+    fn drop(&mut self) {
+        // The lock is recursive, so this is fine on the loop thread too.
+        let _lock = RawLockGuard::new(self.thread_loop);
+        unsafe { ManuallyDrop::drop(&mut self.value) }
+    }
+}
+
+pub(crate) struct RawLockGuard(ptr::NonNull<pw_sys::pw_thread_loop>);
+
+impl RawLockGuard {
+    fn new(thread_loop: ptr::NonNull<pw_sys::pw_thread_loop>) -> Self {
+        unsafe { pw_sys::pw_thread_loop_lock(thread_loop.as_ptr()) };
+        Self(thread_loop)
+    }
+}
+
+impl Drop for RawLockGuard {
+    fn drop(&mut self) {
+        unsafe { pw_sys::pw_thread_loop_unlock(self.0.as_ptr()) }
+    }
+}
+
 #[derive(Debug)]
 struct ThreadLoopInner {
     ptr: ptr::NonNull<pw_sys::pw_thread_loop>,
+    /// Shared with the [`LoopHandle`]s of the objects of the loop, which may outlive it.
+    shared: Arc<AtomicPtr<pw_sys::pw_thread_loop>>,
 }
 
 impl ThreadLoopInner {
     pub unsafe fn from_raw(ptr: ptr::NonNull<pw_sys::pw_thread_loop>) -> Self {
-        Self { ptr }
+        Self {
+            ptr,
+            shared: Arc::new(AtomicPtr::new(ptr.as_ptr())),
+        }
     }
 }
 
 impl Drop for ThreadLoopInner {
     fn drop(&mut self) {
+        self.shared.store(ptr::null_mut(), Ordering::Release);
         unsafe { pw_sys::pw_thread_loop_destroy(self.ptr.as_ptr()) }
     }
 }
+
+/// The loop of an object, captured when the object is created so that it can be dropped
+/// with the loop locked when a [`ThreadLoop`] runs it.
+///
+/// Dropping a proxy, a stream or a listener removes hooks from objects of the loop, which races
+/// with the loop thread dispatching their events unless the loop is locked.
+/// Objects dropped after their thread loop are dropped without locking it.
+#[derive(Debug, Clone)]
+pub(crate) struct LoopHandle(Option<Arc<AtomicPtr<pw_sys::pw_thread_loop>>>);
+
+impl LoopHandle {
+    /// The thread loop of the context of `core`, which may be null.
+    pub(crate) fn of_core(core: *mut pw_sys::pw_core) -> Self {
+        if core.is_null() {
+            return Self(None);
+        }
+        unsafe {
+            let context = pw_sys::pw_core_get_context(core);
+            Self(crate::context::thread_loop_of(context))
+        }
+    }
+
+    /// The thread loop of the context of the core of `proxy`.
+    pub(crate) fn of_proxy(proxy: *mut pw_sys::pw_proxy) -> Self {
+        Self::of_core(unsafe { pw_sys::pw_proxy_get_core(proxy) })
+    }
+
+    /// Lock the thread loop running the loop until the guard is dropped, if there is one.
+    ///
+    /// The lock is recursive, so this is also fine on the loop thread or with the loop locked.
+    pub(crate) fn lock(&self) -> Option<RawLockGuard> {
+        let thread_loop = self.0.as_ref()?.load(Ordering::Acquire);
+        ptr::NonNull::new(thread_loop).map(RawLockGuard::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, sync::mpsc, thread, time::Duration};
+
+    use super::*;
+    use crate::{
+        context::Context,
+        main_loop::MainLoop,
+        properties::Properties,
+        spa::utils::Direction,
+        stream::{Stream, StreamFlags},
+    };
+
+    #[test]
+    fn loop_handle() {
+        let thread_loop = unsafe { ThreadLoop::new(Some("loop-handle"), None) }.unwrap();
+        let context = Context::new(&thread_loop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        assert!(LoopHandle::of_core(core.as_raw_ptr()).lock().is_some());
+
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        assert!(LoopHandle::of_core(core.as_raw_ptr()).lock().is_none());
+        assert!(LoopHandle::of_core(ptr::null_mut()).lock().is_none());
+    }
+
+    #[test]
+    fn loop_handle_outlives_loop() {
+        let thread_loop = unsafe { ThreadLoop::new(Some("loop-handle-outlives"), None) }.unwrap();
+        let context = Context::new(&thread_loop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        let handle = LoopHandle::of_core(core.as_raw_ptr());
+        drop(core);
+        drop(context);
+        drop(thread_loop);
+
+        // the objects of the loop can still be dropped, without locking it
+        assert!(handle.lock().is_none());
+    }
+
+    #[test]
+    fn drop_while_running() {
+        let thread_loop = unsafe { ThreadLoop::new(Some("drop-while-running"), None) }.unwrap();
+        let context = Context::new(&thread_loop).unwrap();
+        let core = context.connect_self(None).unwrap();
+        thread_loop.start();
+
+        for _ in 0..100 {
+            let lock = thread_loop.lock();
+            let stream = Stream::new(&core, "drop-while-running", Properties::new()).unwrap();
+            let stream_listener = stream
+                .add_local_listener::<()>()
+                .state_changed(|_, _, _, _| {})
+                .process(|stream, _| {
+                    stream.dequeue_buffer();
+                })
+                .register()
+                .unwrap();
+            stream
+                .connect(Direction::Output, None, StreamFlags::empty(), &mut [])
+                .unwrap();
+            let registry = core.get_registry().unwrap();
+            let registry_listener = registry.add_listener_local().global(|_| {}).register();
+            lock.unlock();
+
+            // dropped without the lock, while the loop thread dispatches their events
+            drop(registry_listener);
+            drop(registry);
+            drop(stream_listener);
+            drop(stream);
+        }
+
+        thread_loop.stop();
+    }
+
+    #[test]
+    fn thread_bound() {
+        let thread_loop = unsafe { ThreadLoop::new(Some("thread-bound"), None) }.unwrap();
+        thread_loop.start();
+
+        let lock = thread_loop.lock();
+        let bound = unsafe { ThreadBound::new(&thread_loop, Rc::new(Cell::new(0))) };
+        lock.unlock();
+
+        // the bundle can be moved to another thread and accessed with the loop locked
+        let bound = thread::spawn(move || {
+            let mut bound = bound.into_inner_on_loop_thread().err().unwrap();
+            bound.with_lock(|value| value.set(1));
+            bound
+        })
+        .join()
+        .unwrap();
+
+        // and unwrapped on the loop thread
+        let (bound_tx, bound_rx) = mpsc::channel();
+        let (value_tx, value_rx) = mpsc::channel();
+        bound_tx.send(bound).unwrap();
+        let lock = thread_loop.lock();
+        let idle = thread_loop
+            .loop_()
+            .add_idle(true, move || {
+                if let Ok(bound) = bound_rx.try_recv() {
+                    let value = bound
+                        .into_inner_on_loop_thread()
+                        .ok()
+                        .map(|value| value.get());
+                    value_tx.send(value).unwrap();
+                }
+            })
+            .unwrap();
+        lock.unlock();
+
+        let value = value_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(value, Some(1));
+
+        let lock = thread_loop.lock();
+        drop(idle);
+        lock.unlock();
+        thread_loop.stop();
+    }
+}
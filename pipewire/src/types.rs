@@ -2,40 +2,64 @@ use std::fmt;
 
 // Macro generating the ObjectType enum
 macro_rules! object_type {
-    ($( ($x:ident, $version:ident) ),*) => {
+    ($( $(#[$attr:meta])* ($x:ident, $version:ident) ),*) => {
+        /// The type of a PipeWire object, identified by its interface name such as
+        /// `PipeWire:Interface:Node`.
+        ///
+        /// Interfaces unknown to this crate, such as the ones of vendor extensions,
+        /// are kept by name as [`ObjectType::Other`].
         #[derive(Debug, Eq, PartialEq, Clone)]
         pub enum ObjectType {
-            $($x,)*
+            $($(#[$attr])* $x,)*
             Other(String),
         }
 
         impl ObjectType {
-            pub(crate) fn from_str(s: &str) -> ObjectType {
+            /// Parse an interface name, such as the type of a global announced by the registry.
+            pub fn from_interface_name(s: &str) -> ObjectType {
                 match s {
                     $(
+                    $(#[$attr])*
                     concat!("PipeWire:Interface:", stringify!($x)) => ObjectType::$x,
                     )*
                     s => ObjectType::Other(s.to_string()),
                 }
             }
 
+            /// The interface name of the type, such as `PipeWire:Interface:Node`.
             pub fn to_str(&self) -> &str {
                 match self {
                     $(
+                        $(#[$attr])*
                         ObjectType::$x => concat!("PipeWire:Interface:", stringify!($x)),
                     )*
                     ObjectType::Other(s) => s,
                 }
             }
 
-            pub(crate) fn client_version(&self) -> u32 {
+            /// The version of the interface supported by this crate, the `PW_VERSION_*` constant
+            /// of the type.
+            ///
+            /// Returns `None` for [`ObjectType::Other`] interfaces.
+            pub fn client_version(&self) -> Option<u32> {
                 match self {
                     $(
-                        ObjectType::$x => pw_sys::$version,
+                        $(#[$attr])*
+                        ObjectType::$x => Some(pw_sys::$version),
                     )*
-                    ObjectType::Other(_) => panic!("Invalid object type"),
+                    ObjectType::Other(_) => None,
                 }
             }
+
+            #[cfg(test)]
+            fn known() -> Vec<ObjectType> {
+                let mut known = Vec::new();
+                $(
+                    $(#[$attr])*
+                    known.push(ObjectType::$x);
+                )*
+                known
+            }
         }
 
         impl fmt::Display for ObjectType {
@@ -65,5 +89,36 @@ object_type![
     (Port, PW_VERSION_PORT),
     (Profiler, PW_VERSION_PROFILER),
     (Registry, PW_VERSION_REGISTRY),
+    #[cfg(feature = "v0_3_77")]
+    (SecurityContext, PW_VERSION_SECURITY_CONTEXT),
     (Session, PW_VERSION_SESSION)
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interface_names() {
+        for type_ in ObjectType::known() {
+            let name = type_.to_string();
+            assert!(name.starts_with("PipeWire:Interface:"));
+            assert_eq!(name, type_.to_str());
+            assert_eq!(ObjectType::from_interface_name(&name), type_);
+            assert!(type_.client_version().is_some());
+        }
+        assert_eq!(ObjectType::Node.to_string(), "PipeWire:Interface:Node");
+        assert_eq!(
+            ObjectType::Node.client_version(),
+            Some(pw_sys::PW_VERSION_NODE)
+        );
+
+        let vendor = ObjectType::from_interface_name("Vendor:Interface:Widget");
+        assert_eq!(
+            vendor,
+            ObjectType::Other("Vendor:Interface:Widget".to_string())
+        );
+        assert_eq!(vendor.to_string(), "Vendor:Interface:Widget");
+        assert_eq!(vendor.client_version(), None);
+    }
+}
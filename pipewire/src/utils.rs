@@ -1,8 +1,220 @@
 // Copyright The pipewire-rs Contributors.
 // SPDX-License-Identifier: MIT
 
-use std::thread;
+use std::{
+    any::Any,
+    cell::{Cell, RefCell, UnsafeCell},
+    ops::{Deref, DerefMut},
+    panic::{self, AssertUnwindSafe},
+    time::Instant,
+};
 
-pub fn assert_main_thread() {
-    assert_eq!(thread::current().name(), Some("main"));
+use crate::{loop_::SourceName, main_loop::MainLoopQuitHandle};
+
+thread_local! {
+    /// Number of loops currently run from Rust on this thread, which resume the panics of callbacks.
+    static LOOP_DEPTH: Cell<usize> = const { Cell::new(0) };
+    /// The first panic caught in a callback, resumed once the loop returns to Rust.
+    static PENDING_PANIC: RefCell<Option<Box<dyn Any + Send>>> = const { RefCell::new(None) };
+    /// The main loops run from Rust on this thread, quit when a callback panics.
+    static RUNNING_LOOPS: RefCell<Vec<MainLoopQuitHandle>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Call the Rust callback `f` from a C trampoline, without unwinding through C if it panics.
+///
+/// If a loop is run from Rust on this thread, the panic is stored and `fallback` is returned to C.
+/// The running main loop is then quit, and the panic is resumed once the loop returns to Rust,
+/// see [`run_loop`].
+/// Otherwise there is no Rust caller to resume the panic in, so the process is aborted.
+///
+/// The callback is reported as `kind` to the dispatch observer of the loop, if any,
+/// see [`LoopRef::set_dispatch_observer`](crate::loop_::LoopRef::set_dispatch_observer).
+pub(crate) fn catch_callback_panic<R>(kind: &str, fallback: R, f: impl FnOnce() -> R) -> R {
+    catch_observed_panic(kind, None, fallback, f)
+}
+
+/// Like [`catch_callback_panic`], for the callback of a source reported with its `name` if it has one.
+pub(crate) fn catch_source_panic<R>(
+    kind: &str,
+    name: &SourceName,
+    fallback: R,
+    f: impl FnOnce() -> R,
+) -> R {
+    catch_observed_panic(kind, Some(name), fallback, f)
+}
+
+fn catch_observed_panic<R>(
+    kind: &str,
+    name: Option<&SourceName>,
+    fallback: R,
+    f: impl FnOnce() -> R,
+) -> R {
+    let Some(observer) = crate::loop_::current_observer() else {
+        return catch_panic(kind, fallback, f);
+    };
+
+    // the source, and its name, may be dropped by the callback
+    let name = name.and_then(SourceName::get);
+    let start = Instant::now();
+    let res = catch_panic(kind, fallback, f);
+    observer.report(name.as_deref().unwrap_or(kind), start.elapsed());
+    res
+}
+
+fn catch_panic<R>(kind: &str, fallback: R, f: impl FnOnce() -> R) -> R {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(res) => res,
+        Err(payload) => {
+            if LOOP_DEPTH.with(Cell::get) == 0 {
+                eprintln!("pipewire: panic in a {kind} callback outside of a loop run from Rust, aborting");
+                std::process::abort();
+            }
+
+            PENDING_PANIC.with(|pending| {
+                pending.borrow_mut().get_or_insert(payload);
+            });
+            if let Some(main_loop) = RUNNING_LOOPS.with(|loops| loops.borrow().last().cloned()) {
+                main_loop.quit();
+            }
+
+            fallback
+        }
+    }
+}
+
+/// Whether a loop is being run from Rust on this thread, i.e. whether this is called from one of its callbacks.
+pub(crate) fn in_loop() -> bool {
+    LOOP_DEPTH.with(Cell::get) > 0
+}
+
+/// Run a loop with `f`, resuming the panics caught by [`catch_callback_panic`] once it returns.
+///
+/// `main_loop` is quit when a callback panics, so that `f` returns.
+pub(crate) fn run_loop<R>(main_loop: Option<MainLoopQuitHandle>, f: impl FnOnce() -> R) -> R {
+    let has_main_loop = main_loop.is_some();
+    if let Some(main_loop) = main_loop {
+        RUNNING_LOOPS.with(|loops| loops.borrow_mut().push(main_loop));
+    }
+    LOOP_DEPTH.with(|depth| depth.set(depth.get() + 1));
+
+    let res = f();
+
+    LOOP_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    if has_main_loop {
+        RUNNING_LOOPS.with(|loops| loops.borrow_mut().pop());
+    }
+
+    if let Some(payload) = PENDING_PANIC.with(|pending| pending.borrow_mut().take()) {
+        panic::resume_unwind(payload);
+    }
+    res
+}
+
+/// The callbacks of a listener or a source, called from C trampolines.
+///
+/// The callbacks are [`FnMut`], so they must not be called while they are already running,
+/// e.g. when a callback runs the loop dispatching it, or emits another event of its listener.
+/// [`Guarded::enter`] skips such nested calls instead of creating a second mutable reference.
+///
+/// The skipped events are not queued, as their arguments are only valid during the call,
+/// the builders of the listeners document that they are lost.
+pub(crate) struct Guarded<T: ?Sized> {
+    busy: Cell<bool>,
+    inner: UnsafeCell<T>,
+}
+
+impl<T> Guarded<T> {
+    pub(crate) fn new(inner: T) -> Self {
+        Self {
+            busy: Cell::new(false),
+            inner: UnsafeCell::new(inner),
+        }
+    }
+
+    /// Like [`Self::enter`], for the `data` pointer passed to a trampoline.
+    ///
+    /// # Safety
+    /// `data` must point to a `Guarded<T>` that outlives `'a`.
+    pub(crate) unsafe fn enter_raw<'a>(data: *mut libc::c_void) -> Option<GuardedMut<'a, T>> {
+        (data as *const Self).as_ref().unwrap().enter()
+    }
+}
+
+impl<T: ?Sized> Guarded<T> {
+    /// Borrow the callbacks mutably, or `None` if they are already running.
+    pub(crate) fn enter(&self) -> Option<GuardedMut<'_, T>> {
+        if self.busy.replace(true) {
+            return None;
+        }
+        Some(GuardedMut(self))
+    }
+}
+
+/// The callbacks of a [`Guarded`] being called, released when dropped.
+pub(crate) struct GuardedMut<'a, T: ?Sized>(&'a Guarded<T>);
+
+impl<T: ?Sized> Deref for GuardedMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.0.inner.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for GuardedMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.0.inner.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for GuardedMut<'_, T> {
+    fn drop(&mut self) {
+        self.0.busy.set(false);
+    }
+}
+
+/// Convert a nullable C string into an optional `&str`.
+///
+/// Strings which are not valid UTF-8 are `None` as well, as they may come from the server.
+///
+/// # Safety
+/// `ptr` must either be null or point to a valid, nul-terminated string that outlives `'a`.
+pub(crate) unsafe fn cstr_to_str<'a>(ptr: *const libc::c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        None
+    } else {
+        std::ffi::CStr::from_ptr(ptr).to_str().ok()
+    }
+}
+
+/// Parse the `object.serial` property of an object.
+///
+/// The key is only defined by the headers since PipeWire 0.3.41, older servers simply do not set it.
+/// Missing or malformed serials are `None`.
+pub(crate) fn object_serial(props: Option<&spa::utils::dict::DictRef>) -> Option<u64> {
+    props?.parse::<u64>("object.serial")?.ok()
+}
+
+/// Serializes the names of the flags set in `F`, as `pw-dump` does, such as `["input-ports", "props"]`.
+#[cfg(feature = "serde")]
+pub(crate) struct FlagNames<F>(pub(crate) F);
+
+#[cfg(feature = "serde")]
+impl<F: bitflags::Flags> serde::Serialize for FlagNames<F> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            self.0
+                .iter_names()
+                .map(|(name, _)| name.to_lowercase().replace('_', "-")),
+        )
+    }
+}
+
+/// Spawn a PipeWire daemon, found in the `PATH`, listening on the socket `name`.
+#[cfg(all(test, feature = "integration-tests"))]
+pub(crate) fn spawn_daemon(name: &str) -> std::process::Child {
+    std::process::Command::new("pipewire")
+        .env("PIPEWIRE_CORE", name)
+        .spawn()
+        .expect("failed to spawn the pipewire daemon")
 }
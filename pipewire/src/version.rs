@@ -0,0 +1,134 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Versions of libpipewire.
+//!
+//! The version of the library the program runs with may be newer than the one it was built against,
+//! and the version of the server, see [`CoreInfoRef::parsed_version`](crate::core::CoreInfoRef::parsed_version),
+//! may differ from both.
+//!
+//! ```no_run
+//! use pipewire::version::{self, Version};
+//!
+//! pipewire::init();
+//! if version::check_library_version(0, 3, 44) {
+//!     // it is safe to use the API of 0.3.44
+//! }
+//! assert!(version::parsed_library_version().unwrap() >= Version::new(0, 3, 0));
+//! ```
+
+use std::{ffi::CStr, fmt, str::FromStr};
+
+/// A `major.minor.micro` version.
+///
+/// Versions are ordered by their components. They are parsed from strings such as `"0.3.77"`,
+/// ignoring a suffix after the micro version as in `"0.3.77-1"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub micro: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, micro: u32) -> Self {
+        Self {
+            major,
+            minor,
+            micro,
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.micro)
+    }
+}
+
+/// Error returned when parsing a [`Version`] fails.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid version {0:?}")]
+pub struct ParseVersionError(pub String);
+
+impl FromStr for Version {
+    type Err = ParseVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseVersionError(s.to_owned());
+
+        let mut parts = s.trim().splitn(3, '.');
+        let major = parts.next().and_then(|p| p.parse().ok()).ok_or_else(err)?;
+        let minor = parts.next().and_then(|p| p.parse().ok()).ok_or_else(err)?;
+        // only keep the leading digits of the micro version, dropping suffixes such as "-1"
+        let micro = parts.next().ok_or_else(err)?;
+        let micro = &micro[..micro
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(micro.len())];
+        let micro = micro.parse().map_err(|_| err())?;
+
+        Ok(Self::new(major, minor, micro))
+    }
+}
+
+/// The version of libpipewire the program is running with.
+pub fn library_version() -> &'static str {
+    unsafe {
+        CStr::from_ptr(pw_sys::pw_get_library_version())
+            .to_str()
+            .unwrap()
+    }
+}
+
+/// The version of libpipewire the program is running with, parsed into a [`Version`].
+///
+/// Returns `None` if the library returns a version in an unexpected form.
+pub fn parsed_library_version() -> Option<Version> {
+    library_version().parse().ok()
+}
+
+/// Whether the version of libpipewire the program is running with is at least `major.minor.micro`.
+pub fn check_library_version(major: u32, minor: u32, micro: u32) -> bool {
+    unsafe { pw_sys::pw_check_library_version(major as _, minor as _, micro as _) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("0.3.77".parse(), Ok(Version::new(0, 3, 77)));
+        assert_eq!("0.3.77-1".parse(), Ok(Version::new(0, 3, 77)));
+        assert_eq!("1.0.0~rc1".parse(), Ok(Version::new(1, 0, 0)));
+        assert_eq!(" 1.2.3 ".parse(), Ok(Version::new(1, 2, 3)));
+
+        for invalid in ["", "0.3", "0.3.", "a.b.c", "0.3.x", "-1.3.0"] {
+            assert_eq!(
+                invalid.parse::<Version>(),
+                Err(ParseVersionError(invalid.to_owned()))
+            );
+        }
+    }
+
+    #[test]
+    fn ord() {
+        assert!(Version::new(0, 3, 77) > Version::new(0, 3, 44));
+        assert!(Version::new(1, 0, 0) > Version::new(0, 3, 77));
+        assert_eq!(Version::new(0, 3, 44).to_string(), "0.3.44");
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn running_library() {
+        crate::init();
+        let version = parsed_library_version().expect("invalid library version");
+        assert!(check_library_version(
+            version.major,
+            version.minor,
+            version.micro
+        ));
+        assert!(check_library_version(0, 3, 0));
+        assert!(!check_library_version(version.major + 1, 0, 0));
+    }
+}
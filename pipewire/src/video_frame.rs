@@ -0,0 +1,574 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+//! Access to the pixels of the buffers of video streams.
+//!
+//! A [`VideoFrame`] combines the format negotiated in the `param_changed` event of a stream
+//! with a buffer dequeued in its `process` event, and locates the planes of the frame
+//! in the data of the buffer:
+//!
+//! ```no_run
+//! use pipewire::{stream::StreamRef, video_frame::VideoFrame};
+//! use pipewire::spa::param::video::VideoInfoRaw;
+//!
+//! fn process(stream: &StreamRef, format: &VideoInfoRaw) {
+//!     let Some(mut buffer) = stream.dequeue_buffer() else {
+//!         return;
+//!     };
+//!     let mut frame = match VideoFrame::new(format, &mut buffer) {
+//!         Ok(frame) => frame,
+//!         Err(err) => return eprintln!("cannot access the frame: {err}"),
+//!     };
+//!     let plane = frame.plane(0).unwrap();
+//!     let first_row = &plane.data[..plane.stride as usize];
+//!     println!("first pixel: {:?}", &first_row[..4]);
+//! }
+//! ```
+
+use spa::param::video::{VideoFormat, VideoInfoRaw};
+
+use crate::buffer::BufferRef;
+
+/// An error of a [`VideoFrame`].
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoFrameError {
+    /// The plane layout of the format is not known.
+    #[error("unsupported video format {0:?}")]
+    UnsupportedFormat(VideoFormat),
+    /// The buffer has no data for the plane.
+    #[error("no data for plane {0}")]
+    MissingPlane(usize),
+    /// The data of the plane is not mapped in memory, such as for DMA-BUF buffers
+    /// of streams connected without `MAP_BUFFERS`.
+    #[error("the data of plane {0} is not mapped")]
+    Unmapped(usize),
+    /// The plane does not fit in its data.
+    #[error("plane {0} is larger than its data")]
+    TooSmall(usize),
+}
+
+/// How a plane is subsampled and how many bytes its pixels use.
+#[derive(Debug, Clone, Copy)]
+struct PlaneFormat {
+    bytes_per_pixel: u32,
+    /// log2 of the horizontal subsampling
+    x_shift: u32,
+    /// log2 of the vertical subsampling
+    y_shift: u32,
+}
+
+const fn plane(bytes_per_pixel: u32, x_shift: u32, y_shift: u32) -> PlaneFormat {
+    PlaneFormat {
+        bytes_per_pixel,
+        x_shift,
+        y_shift,
+    }
+}
+
+fn plane_formats(format: VideoFormat) -> Option<&'static [PlaneFormat]> {
+    const PACKED_4: &[PlaneFormat] = &[plane(4, 0, 0)];
+    const PACKED_3: &[PlaneFormat] = &[plane(3, 0, 0)];
+    const PACKED_2: &[PlaneFormat] = &[plane(2, 0, 0)];
+    const GRAY: &[PlaneFormat] = &[plane(1, 0, 0)];
+    const SEMI_PLANAR_420: &[PlaneFormat] = &[plane(1, 0, 0), plane(2, 1, 1)];
+    const PLANAR_420: &[PlaneFormat] = &[plane(1, 0, 0), plane(1, 1, 1), plane(1, 1, 1)];
+
+    let planes = match format {
+        VideoFormat::RGBx
+        | VideoFormat::BGRx
+        | VideoFormat::xRGB
+        | VideoFormat::xBGR
+        | VideoFormat::RGBA
+        | VideoFormat::BGRA
+        | VideoFormat::ARGB
+        | VideoFormat::ABGR
+        | VideoFormat::AYUV => PACKED_4,
+        VideoFormat::RGB | VideoFormat::BGR => PACKED_3,
+        VideoFormat::YUY2 | VideoFormat::UYVY | VideoFormat::YVYU => PACKED_2,
+        VideoFormat::GRAY8 => GRAY,
+        VideoFormat::NV12 | VideoFormat::NV21 => SEMI_PLANAR_420,
+        VideoFormat::I420 | VideoFormat::YV12 => PLANAR_420,
+        _ => return None,
+    };
+    Some(planes)
+}
+
+/// Where a plane is in the data of a buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PlaneLayout {
+    /// The index of the data containing the plane.
+    data: usize,
+    offset: u32,
+    stride: u32,
+    size: u32,
+}
+
+impl PlaneFormat {
+    fn height(&self, height: u32) -> u32 {
+        subsample(height, self.y_shift)
+    }
+
+    /// The stride of a plane without padding between rows, aligned to 4 bytes,
+    /// or `None` if it overflows or is zero, such as for an empty frame.
+    fn default_stride(&self, width: u32) -> Option<u32> {
+        let stride = subsample(width, self.x_shift)
+            .checked_mul(self.bytes_per_pixel)?
+            .checked_add(3)?;
+        Some(stride & !3).filter(|&stride| stride > 0)
+    }
+
+    /// The stride of this plane when the first plane of the frame uses `stride`,
+    /// or `None` if it overflows or is zero, such as for a subsampled plane of a tiny stride.
+    fn stride_from(&self, first: &PlaneFormat, stride: u32) -> Option<u32> {
+        let stride =
+            (stride.checked_mul(self.bytes_per_pixel)? / first.bytes_per_pixel) >> self.x_shift;
+        Some(stride).filter(|&stride| stride > 0)
+    }
+
+    /// The size of this plane with rows of `stride` bytes, or `None` if it overflows.
+    fn size(&self, stride: u32, height: u32) -> Option<u32> {
+        stride.checked_mul(self.height(height))
+    }
+}
+
+fn subsample(size: u32, shift: u32) -> u32 {
+    size.div_ceil(1 << shift)
+}
+
+/// The layout of planes stored one after the other in a single data,
+/// starting at `offset` and with the first plane using `stride`.
+///
+/// Fails with [`VideoFrameError::TooSmall`] for the first plane whose end overflows.
+fn packed_layouts(
+    planes: &[PlaneFormat],
+    height: u32,
+    offset: u32,
+    stride: u32,
+) -> Result<Vec<PlaneLayout>, VideoFrameError> {
+    let mut offset = Some(offset);
+    planes
+        .iter()
+        .enumerate()
+        .map(|(index, plane)| {
+            let layout = offset
+                .and_then(|offset| {
+                    let stride = plane.stride_from(&planes[0], stride)?;
+                    Some(PlaneLayout {
+                        data: 0,
+                        offset,
+                        stride,
+                        size: plane.size(stride, height)?,
+                    })
+                })
+                .ok_or(VideoFrameError::TooSmall(index))?;
+            // an end overflowing only fails if there is a next plane
+            offset = layout.offset.checked_add(layout.size);
+            Ok(layout)
+        })
+        .collect()
+}
+
+/// A video frame in a buffer dequeued from a stream, providing access to its planes.
+///
+/// The planes are either all stored in the first data of the buffer, one after the other,
+/// or each in its own data when the buffer has as many datas as the format has planes.
+///
+/// The common RGB formats, packed YUV formats, `GRAY8`, `NV12`, `NV21`, `I420` and `YV12`
+/// are supported.
+pub struct VideoFrame<'b> {
+    info: VideoInfoRaw,
+    buffer: &'b mut BufferRef,
+    layouts: Vec<PlaneLayout>,
+}
+
+impl<'b> VideoFrame<'b> {
+    /// Access the frame received in `buffer`, for streams capturing video.
+    ///
+    /// The offsets and strides of the planes are read from the chunks of the data,
+    /// falling back to rows without padding when the producer does not set a stride.
+    pub fn new(info: &VideoInfoRaw, buffer: &'b mut BufferRef) -> Result<Self, VideoFrameError> {
+        let planes = plane_formats(info.format())
+            .ok_or_else(|| VideoFrameError::UnsupportedFormat(info.format()))?;
+        let size = info.size();
+        let datas = buffer.datas_mut();
+
+        let chunk_layout = |index: usize, plane: &PlaneFormat| -> Result<_, VideoFrameError> {
+            let data = datas
+                .get(index)
+                .ok_or(VideoFrameError::MissingPlane(index))?;
+            let chunk = data.chunk();
+            let offset = chunk.offset().checked_rem(data.maxsize()).unwrap_or(0);
+            let stride = match u32::try_from(chunk.stride()) {
+                Ok(stride) if stride > 0 => stride,
+                _ => plane
+                    .default_stride(size.width)
+                    .ok_or(VideoFrameError::TooSmall(index))?,
+            };
+            Ok((offset, stride))
+        };
+
+        let layouts = if planes.len() > 1 && datas.len() >= planes.len() {
+            planes
+                .iter()
+                .enumerate()
+                .map(|(index, plane)| {
+                    let (offset, stride) = chunk_layout(index, plane)?;
+                    Ok(PlaneLayout {
+                        data: index,
+                        offset,
+                        stride,
+                        size: plane
+                            .size(stride, size.height)
+                            .ok_or(VideoFrameError::TooSmall(index))?,
+                    })
+                })
+                .collect::<Result<_, VideoFrameError>>()?
+        } else {
+            let (offset, stride) = chunk_layout(0, &planes[0])?;
+            packed_layouts(planes, size.height, offset, stride)?
+        };
+
+        Self::with_layouts(*info, buffer, layouts)
+    }
+
+    /// Access the frame to be sent in `buffer`, for streams producing video.
+    ///
+    /// The planes are laid out one after the other in the first data of the buffer,
+    /// or in their own data if the buffer has one for each plane, with rows without padding.
+    /// The chunks of the data are updated accordingly, so the buffer can be queued
+    /// once the planes are filled.
+    pub fn new_output(
+        info: &VideoInfoRaw,
+        buffer: &'b mut BufferRef,
+    ) -> Result<Self, VideoFrameError> {
+        let planes = plane_formats(info.format())
+            .ok_or_else(|| VideoFrameError::UnsupportedFormat(info.format()))?;
+        let size = info.size();
+        let datas = buffer.datas_mut();
+
+        let layouts = if planes.len() > 1 && datas.len() >= planes.len() {
+            planes
+                .iter()
+                .enumerate()
+                .map(|(index, plane)| {
+                    let layout = plane.default_stride(size.width).and_then(|stride| {
+                        Some(PlaneLayout {
+                            data: index,
+                            offset: 0,
+                            stride,
+                            size: plane.size(stride, size.height)?,
+                        })
+                    });
+                    layout.ok_or(VideoFrameError::TooSmall(index))
+                })
+                .collect::<Result<_, VideoFrameError>>()?
+        } else {
+            let stride = planes[0]
+                .default_stride(size.width)
+                .ok_or(VideoFrameError::TooSmall(0))?;
+            packed_layouts(planes, size.height, 0, stride)?
+        };
+
+        let mut frame = Self::with_layouts(*info, buffer, layouts)?;
+        let datas = frame.buffer.datas_mut();
+        for (index, layout) in frame.layouts.iter().enumerate() {
+            // Only the first plane of each data sets its chunk.
+            if index > 0 && frame.layouts[index - 1].data == layout.data {
+                continue;
+            }
+            let end = frame
+                .layouts
+                .iter()
+                .filter(|other| other.data == layout.data)
+                .map(|other| other.offset + other.size)
+                .max()
+                .unwrap_or(0);
+
+            let mut chunk = datas[layout.data].chunk_mut();
+            chunk.set_offset(0);
+            chunk.set_stride(layout.stride as i32);
+            chunk.set_size(end);
+        }
+        Ok(frame)
+    }
+
+    /// Check that every plane fits in mapped memory.
+    fn with_layouts(
+        info: VideoInfoRaw,
+        buffer: &'b mut BufferRef,
+        layouts: Vec<PlaneLayout>,
+    ) -> Result<Self, VideoFrameError> {
+        let datas = buffer.datas_mut();
+        for (index, layout) in layouts.iter().enumerate() {
+            let data = datas
+                .get_mut(layout.data)
+                .ok_or(VideoFrameError::MissingPlane(index))?;
+            let len = data.data().ok_or(VideoFrameError::Unmapped(index))?.len();
+            match (layout.offset as usize).checked_add(layout.size as usize) {
+                Some(end) if end <= len => {}
+                _ => return Err(VideoFrameError::TooSmall(index)),
+            }
+        }
+
+        Ok(Self {
+            info,
+            buffer,
+            layouts,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.info.size().width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.info.size().height
+    }
+
+    pub fn format(&self) -> VideoFormat {
+        self.info.format()
+    }
+
+    /// The number of planes of the format, such as 1 for `BGRx` and 2 for `NV12`.
+    pub fn plane_count(&self) -> usize {
+        self.layouts.len()
+    }
+
+    /// Access the plane `index`, or return [`VideoFrameError::MissingPlane`] if the format
+    /// has fewer planes.
+    pub fn plane(&mut self, index: usize) -> Result<PlaneView<'_>, VideoFrameError> {
+        let layout = *self
+            .layouts
+            .get(index)
+            .ok_or(VideoFrameError::MissingPlane(index))?;
+        let data = self.buffer.datas_mut()[layout.data]
+            .data()
+            .ok_or(VideoFrameError::Unmapped(index))?;
+        let start = layout.offset as usize;
+        let data = data
+            .get_mut(start..)
+            .and_then(|data| data.get_mut(..layout.size as usize))
+            .ok_or(VideoFrameError::TooSmall(index))?;
+
+        Ok(PlaneView {
+            data,
+            stride: layout.stride,
+            offset: layout.offset,
+        })
+    }
+}
+
+impl std::fmt::Debug for VideoFrame<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VideoFrame")
+            .field("format", &self.format())
+            .field("width", &self.width())
+            .field("height", &self.height())
+            .field("planes", &self.layouts)
+            .finish()
+    }
+}
+
+/// A plane of a [`VideoFrame`].
+#[derive(Debug)]
+pub struct PlaneView<'a> {
+    /// The bytes of the plane, made of its rows of `stride` bytes.
+    pub data: &'a mut [u8],
+    /// The number of bytes between the start of two rows.
+    pub stride: u32,
+    /// The offset of the plane in the data of the buffer.
+    pub offset: u32,
+}
+
+impl PlaneView<'_> {
+    /// The rows of the plane, including their padding.
+    ///
+    /// # Panics
+    /// If `stride` is zero, which is never the case for the planes of a [`VideoFrame`].
+    pub fn rows(&mut self) -> std::slice::ChunksExactMut<'_, u8> {
+        self.data.chunks_exact_mut(self.stride as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spa::utils::Rectangle;
+
+    #[test]
+    fn layouts() {
+        let i420 = plane_formats(VideoFormat::I420).unwrap();
+        let layouts = packed_layouts(i420, 4, 0, i420[0].default_stride(6).unwrap()).unwrap();
+        assert_eq!(
+            layouts
+                .iter()
+                .map(|l| (l.offset, l.stride, l.size))
+                .collect::<Vec<_>>(),
+            [(0, 8, 32), (32, 4, 8), (40, 4, 8)]
+        );
+
+        let nv12 = plane_formats(VideoFormat::NV12).unwrap();
+        let layouts = packed_layouts(nv12, 3, 16, 64).unwrap();
+        assert_eq!(
+            layouts
+                .iter()
+                .map(|l| (l.offset, l.stride, l.size))
+                .collect::<Vec<_>>(),
+            [(16, 64, 192), (208, 64, 128)]
+        );
+
+        assert_eq!(
+            plane_formats(VideoFormat::RGB).unwrap()[0].default_stride(3),
+            Some(12)
+        );
+        assert!(plane_formats(VideoFormat::v210).is_none());
+
+        // rows cannot be empty
+        assert_eq!(i420[0].default_stride(0), None);
+        assert_eq!(
+            packed_layouts(i420, 2, 0, 1),
+            Err(VideoFrameError::TooSmall(1))
+        );
+
+        // the sizes overflow instead of wrapping around
+        assert_eq!(i420[0].default_stride(u32::MAX), None);
+        assert_eq!(
+            packed_layouts(nv12, u32::MAX, 0, 64),
+            Err(VideoFrameError::TooSmall(0))
+        );
+        assert_eq!(
+            packed_layouts(nv12, 2, u32::MAX - 64, 64),
+            Err(VideoFrameError::TooSmall(1))
+        );
+    }
+
+    /// A buffer with a single data using `memory`, and its chunk.
+    struct TestBuffer {
+        memory: Vec<u8>,
+        chunk: Box<spa_sys::spa_chunk>,
+        data: Box<spa_sys::spa_data>,
+        spa_buffer: Box<spa_sys::spa_buffer>,
+        buffer: Box<pw_sys::pw_buffer>,
+    }
+
+    impl TestBuffer {
+        fn new(memory: Vec<u8>) -> Self {
+            let mut test = TestBuffer {
+                memory,
+                chunk: Box::new(unsafe { std::mem::zeroed() }),
+                data: Box::new(unsafe { std::mem::zeroed() }),
+                spa_buffer: Box::new(unsafe { std::mem::zeroed() }),
+                buffer: Box::new(unsafe { std::mem::zeroed() }),
+            };
+            test.data.maxsize = test.memory.len() as u32;
+            test.data.data = test.memory.as_mut_ptr().cast();
+            test.data.chunk = &mut *test.chunk;
+            test.spa_buffer.n_datas = 1;
+            test.spa_buffer.datas = &mut *test.data;
+            test.buffer.buffer = &mut *test.spa_buffer;
+            test
+        }
+
+        fn buffer(&mut self) -> &mut BufferRef {
+            unsafe { BufferRef::from_raw_mut(std::ptr::NonNull::from(&mut *self.buffer)) }
+        }
+    }
+
+    fn info(format: VideoFormat, width: u32, height: u32) -> VideoInfoRaw {
+        let mut info = VideoInfoRaw::new();
+        info.set_format(format);
+        info.set_size(Rectangle { width, height });
+        info
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn frame() {
+        let mut memory = vec![0; 64];
+        memory[8 + 16] = 42;
+        let mut test = TestBuffer::new(memory);
+        test.chunk.offset = 8;
+        test.chunk.stride = 16;
+
+        let bgrx = info(VideoFormat::BGRx, 2, 3);
+        let mut frame = VideoFrame::new(&bgrx, test.buffer()).unwrap();
+        assert_eq!(frame.format(), VideoFormat::BGRx);
+        assert_eq!((frame.width(), frame.height()), (2, 3));
+        assert_eq!(frame.plane_count(), 1);
+
+        let mut plane = frame.plane(0).unwrap();
+        assert_eq!((plane.offset, plane.stride, plane.data.len()), (8, 16, 48));
+        assert_eq!(plane.rows().nth(1).unwrap()[0], 42);
+        assert_eq!(
+            frame.plane(1).unwrap_err(),
+            VideoFrameError::MissingPlane(1)
+        );
+
+        // The planes do not fit anymore
+        test.chunk.offset = 32;
+        assert_eq!(
+            VideoFrame::new(&bgrx, test.buffer()).unwrap_err(),
+            VideoFrameError::TooSmall(0)
+        );
+
+        test.data.data = std::ptr::null_mut();
+        assert_eq!(
+            VideoFrame::new(&bgrx, test.buffer()).unwrap_err(),
+            VideoFrameError::Unmapped(0)
+        );
+
+        assert_eq!(
+            VideoFrame::new(&info(VideoFormat::v210, 2, 3), test.buffer()).unwrap_err(),
+            VideoFrameError::UnsupportedFormat(VideoFormat::v210)
+        );
+
+        test.chunk.offset = 0;
+        test.chunk.stride = 0;
+        assert_eq!(
+            VideoFrame::new(&info(VideoFormat::BGRx, u32::MAX, 3), test.buffer()).unwrap_err(),
+            VideoFrameError::TooSmall(0)
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn output_frame() {
+        let mut test = TestBuffer::new(vec![0; 64]);
+        test.chunk.offset = 12;
+
+        let info = info(VideoFormat::NV12, 4, 4);
+        let mut frame = VideoFrame::new_output(&info, test.buffer()).unwrap();
+        assert_eq!(frame.plane_count(), 2);
+        frame.plane(1).unwrap().data.fill(1);
+
+        assert_eq!(
+            (test.chunk.offset, test.chunk.stride, test.chunk.size),
+            (0, 4, 24)
+        );
+        assert_eq!(test.memory[15], 0);
+        assert_eq!(test.memory[16], 1);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn zero_width() {
+        let mut test = TestBuffer::new(vec![0; 64]);
+
+        let empty = info(VideoFormat::BGRx, 0, 4);
+        assert_eq!(
+            VideoFrame::new(&empty, test.buffer()).unwrap_err(),
+            VideoFrameError::TooSmall(0)
+        );
+        assert_eq!(
+            VideoFrame::new_output(&empty, test.buffer()).unwrap_err(),
+            VideoFrameError::TooSmall(0)
+        );
+
+        // the chroma planes of a one byte stride are empty
+        test.chunk.stride = 1;
+        assert_eq!(
+            VideoFrame::new(&info(VideoFormat::I420, 1, 2), test.buffer()).unwrap_err(),
+            VideoFrameError::TooSmall(1)
+        );
+    }
+}
@@ -0,0 +1,35 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+// Initialization is global to the process, so this is a separate test binary with a single test.
+
+use pipewire::{context::Context, main_loop::MainLoop};
+
+fn use_pipewire() {
+    let mainloop = MainLoop::new(None).unwrap();
+    let context = Context::new(&mainloop).unwrap();
+    drop(context);
+    drop(mainloop);
+}
+
+#[test]
+fn init_deinit() {
+    pipewire::init();
+    {
+        // init() keeps its reference after the last guard is dropped
+        let guard = pipewire::init_guarded();
+        use_pipewire();
+        drop(guard);
+    }
+    use_pipewire();
+
+    unsafe { pipewire::deinit() };
+
+    // PipeWire can be initialized again after deinit()
+    pipewire::init();
+    use_pipewire();
+    let guard = pipewire::init_guarded();
+    use_pipewire();
+    drop(guard);
+    use_pipewire();
+}
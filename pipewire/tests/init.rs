@@ -0,0 +1,31 @@
+// Copyright The pipewire-rs Contributors.
+// SPDX-License-Identifier: MIT
+
+// Initialization is global to the process, so this is a separate test binary with a single test.
+
+use pipewire::{context::Context, main_loop::MainLoop};
+
+#[test]
+fn init_guarded() {
+    let outer = pipewire::init_guarded();
+    {
+        let inner = pipewire::init_guarded();
+        let mainloop = MainLoop::new(None).unwrap();
+        let context = Context::new(&mainloop).unwrap();
+        drop(context);
+        drop(mainloop);
+        drop(inner);
+    }
+
+    // the outer guard still keeps PipeWire initialized
+    let mainloop = MainLoop::new(None).unwrap();
+    let context = Context::new(&mainloop).unwrap();
+    drop(context);
+    drop(mainloop);
+    drop(outer);
+
+    if cfg!(debug_assertions) {
+        let res = std::panic::catch_unwind(|| MainLoop::new(None).map(drop));
+        assert!(res.is_err(), "using PipeWire after deinit did not panic");
+    }
+}